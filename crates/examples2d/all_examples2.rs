@@ -10,6 +10,7 @@ mod rbd_dynamic2;
 mod rbd_joint_ball2;
 mod rbd_joint_fixed2;
 mod rbd_joint_prismatic2;
+mod rbd_multibody_pendulum2;
 mod rbd_polyline2;
 mod rbd_primitives2;
 mod rbd_pyramid2;
@@ -64,6 +65,7 @@ demos! {
     "Joints (spherical)" => Rbd : rbd_joint_ball2,
     "Joints (prismatic)" => Rbd : rbd_joint_prismatic2,
     "Joints (fixed)" => Rbd : rbd_joint_fixed2,
+    "Multibody (Pendulum)" => Rbd : rbd_multibody_pendulum2,
     // MPM demos.
     "Cantilever beam" => Mpm : mpm_centilever_beam2,
     "Sand" => Mpm : mpm_sand2,
@@ -0,0 +1,94 @@
+use khal::backend::GpuTimestamps;
+use nexus_viewer2d::NexusViewer;
+use nexus2d::prelude::{NexusPipeline, NexusState, RbdCoupling};
+use rapier2d::prelude::*;
+
+pub async fn run(
+    viewer: &mut NexusViewer,
+    pipeline: &mut NexusPipeline,
+) -> anyhow::Result<NexusState> {
+    let mut state = NexusState::default();
+    let no_coupling = RbdCoupling::None;
+
+    /*
+     * The ground
+     */
+    let ground_size = 100.0;
+    let ground_height = 0.1;
+
+    let rigid_body = RigidBodyBuilder::fixed().translation(Vec2::new(0.0, -30.0));
+    let collider = ColliderBuilder::cuboid(ground_size, ground_height).build();
+    let shape = collider.shared_shape().clone();
+    let handle = state.insert_rigid_body(rigid_body.build(), collider, no_coupling);
+    viewer.insert_shape(handle, &shape, Pose::IDENTITY);
+
+    /*
+     * Planar pendulums modeled with rapier's MultibodyJointSet.
+     *
+     * - Each chain hangs from a fixed root.
+     * - Links are connected by revolute joints, so every link has a single
+     *   reduced coordinate.
+     * - The chains start horizontal and swing down onto the ground.
+     */
+    let rad = 0.4;
+    let link_len = 2.0;
+    let num_links = 10;
+    let num_chains = 5;
+
+    for k in 0..num_chains {
+        let y = k as f32 * 3.0;
+
+        // Fixed root.
+        let root_body = RigidBodyBuilder::fixed()
+            .translation(Vec2::new(0.0, y))
+            .build();
+        let root_collider = ColliderBuilder::cuboid(rad, rad).build();
+        let root_shape = root_collider.shared_shape().clone();
+        let mut parent_handle = state.insert_rigid_body(root_body, root_collider, no_coupling);
+        viewer.insert_shape(parent_handle, &root_shape, Pose::IDENTITY);
+
+        for i in 0..num_links {
+            let x = (i as f32 + 1.0) * link_len;
+            let rigid_body = RigidBodyBuilder::dynamic()
+                .translation(Vec2::new(x, y))
+                .build();
+            let collider = ColliderBuilder::cuboid(link_len * 0.4, rad * 0.5)
+                .collision_groups(InteractionGroups::new(
+                    Group::GROUP_2,
+                    Group::GROUP_1,
+                    Default::default(),
+                ))
+                .build();
+            let shape = collider.shared_shape().clone();
+            let handle = state.insert_rigid_body(rigid_body, collider, no_coupling);
+            viewer.insert_shape(handle, &shape, Pose::IDENTITY);
+
+            let parent_anchor = if i == 0 {
+                Vec2::ZERO
+            } else {
+                Vec2::new(link_len * 0.5, 0.0)
+            };
+            let joint = RevoluteJointBuilder::new()
+                .local_anchor1(parent_anchor)
+                .local_anchor2(Vec2::new(-link_len * 0.5, 0.0))
+                .build();
+            state.insert_multibody_joint(parent_handle, handle, joint);
+
+            parent_handle = handle;
+        }
+    }
+
+    let mut timestamps = GpuTimestamps::new(viewer.backend(), 2048);
+    state.finalize(viewer.backend()).await?;
+
+    while viewer.render_frame().await {
+        if viewer.simulating() {
+            pipeline
+                .simulate(viewer.backend(), &mut state, Some(&mut timestamps))
+                .await?;
+        }
+        viewer.sync(&mut state, Some(&mut timestamps)).await?;
+    }
+
+    Ok(state)
+}
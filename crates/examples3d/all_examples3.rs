@@ -8,10 +8,12 @@ mod rbd_boxes_and_balls3;
 mod rbd_compound3;
 mod rbd_dynamic3;
 mod rbd_joint_ball3;
+mod rbd_joint_breakable3;
 mod rbd_joint_fixed3;
 mod rbd_joint_prismatic3;
 mod rbd_joint_revolute3;
 mod rbd_joint_revolute_batch3;
+mod rbd_joint_rope3;
 mod rbd_joints3;
 mod rbd_keva3;
 mod rbd_many_pyramids3;
@@ -92,6 +94,8 @@ demos! {
     "Joints (Prismatic)" => Rbd : rbd_joint_prismatic3,
     "Joints (Revolute)" => Rbd : rbd_joint_revolute3,
     "Joints (Revolute - Batched)" => Rbd : rbd_joint_revolute_batch3,
+    "Joints (Rope & spring)" => Rbd : rbd_joint_rope3,
+    "Joints (Breakable)" => Rbd : rbd_joint_breakable3,
     "Multibody (Pendulum)" => Rbd : rbd_multibody_pendulum3,
     "Trimesh" => Rbd : rbd_trimesh3,
     #[cfg(not(target_arch = "wasm32"))]
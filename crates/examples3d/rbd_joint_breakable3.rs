@@ -0,0 +1,116 @@
+use khal::backend::GpuTimestamps;
+use nexus_viewer3d::NexusViewer;
+use nexus3d::prelude::{NexusPipeline, NexusState, RbdCoupling};
+use rapier3d::prelude::*;
+
+pub async fn run(
+    viewer: &mut NexusViewer,
+    pipeline: &mut NexusPipeline,
+) -> anyhow::Result<NexusState> {
+    /*
+     * World
+     */
+    let mut state = NexusState::default();
+    let no_coupling = RbdCoupling::None;
+
+    let num_planks = 20;
+    let plank_len = 1.0;
+    let num_bridges = 5;
+    let bridge_shift = 4.0;
+
+    /*
+     * The ground.
+     */
+    let ground_size = 50.0;
+    let ground_height = 0.1;
+    let rigid_body = RigidBodyBuilder::fixed().translation(Vec3::new(0.0, -ground_height, 0.0));
+    let collider = ColliderBuilder::cuboid(ground_size, ground_height, ground_size).build();
+    let shape = collider.shared_shape().clone();
+    let handle = state.insert_rigid_body(rigid_body.build(), collider, no_coupling);
+    viewer.insert_shape(handle, &shape, Pose::IDENTITY);
+
+    /*
+     * Bridges of planks welded by fixed joints, breaking under the load of
+     * boxes falling on them. Every bridge is sturdier than the previous one.
+     */
+    let mut bridge_joints = Vec::new();
+    for b in 0..num_bridges {
+        let z = b as f32 * bridge_shift;
+        let x0 = -(num_planks as f32) * plank_len * 0.5;
+        let mut joints = Vec::new();
+        let mut parent_handle = None;
+
+        for i in 0..=num_planks {
+            let x = x0 + i as f32 * plank_len;
+            let rigid_body = if i == 0 || i == num_planks {
+                RigidBodyBuilder::fixed()
+            } else {
+                RigidBodyBuilder::dynamic()
+            };
+            let collider =
+                ColliderBuilder::cuboid(plank_len * 0.45, 0.1, bridge_shift * 0.3).build();
+            let shape = collider.shared_shape().clone();
+            let handle = state.insert_rigid_body(
+                rigid_body.translation(Vec3::new(x, 5.0, z)).build(),
+                collider,
+                no_coupling,
+            );
+            viewer.insert_shape(handle, &shape, Pose::IDENTITY);
+
+            if let Some(parent_handle) = parent_handle {
+                let joint = FixedJointBuilder::new()
+                    .local_anchor1(Vec3::new(plank_len * 0.5, 0.0, 0.0))
+                    .local_anchor2(Vec3::new(-plank_len * 0.5, 0.0, 0.0));
+                joints.push(state.insert_impulse_joint(parent_handle, handle, joint));
+            }
+            parent_handle = Some(handle);
+        }
+
+        // The load.
+        for k in 0..4 {
+            let rigid_body = RigidBodyBuilder::dynamic()
+                .translation(Vec3::new(k as f32 - 1.5, 8.0 + k as f32 * 1.5, z))
+                .build();
+            let collider = ColliderBuilder::cuboid(0.4, 0.4, 0.4).density(10.0).build();
+            let shape = collider.shared_shape().clone();
+            let handle = state.insert_rigid_body(rigid_body, collider, no_coupling);
+            viewer.insert_shape(handle, &shape, Pose::IDENTITY);
+        }
+
+        bridge_joints.push(joints);
+    }
+
+    // Optional, useful so we can render even before starting the simulation.
+    let mut timestamps = GpuTimestamps::new(viewer.backend(), 2048);
+    viewer
+        .scene3d_mut()
+        .add_directional_light(glamx::Vec3::new(1.0, -2.0, 3.0));
+    state.finalize(viewer.backend()).await?;
+
+    for (b, joints) in bridge_joints.iter().enumerate() {
+        let max_force = 200.0 * (b + 1) as f32;
+        for handle in joints {
+            state.set_impulse_joint_break_threshold(
+                viewer.backend(),
+                0,
+                *handle,
+                max_force,
+                max_force,
+            )?;
+        }
+    }
+
+    while viewer.render_frame().await {
+        if viewer.simulating() {
+            pipeline
+                .simulate(viewer.backend(), &mut state, Some(&mut timestamps))
+                .await?;
+        }
+        // Draining the events keeps the broken joints disabled in the rapier world.
+        state.poll_events(viewer.backend())?;
+        let _ = state.drain_joint_break_events();
+        viewer.sync(&mut state, Some(&mut timestamps)).await?;
+    }
+
+    Ok(state)
+}
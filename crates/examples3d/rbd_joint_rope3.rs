@@ -0,0 +1,81 @@
+use khal::backend::GpuTimestamps;
+use nexus_viewer3d::NexusViewer;
+use nexus3d::prelude::{NexusPipeline, NexusState, RbdCoupling};
+use rapier3d::prelude::*;
+
+pub async fn run(
+    viewer: &mut NexusViewer,
+    pipeline: &mut NexusPipeline,
+) -> anyhow::Result<NexusState> {
+    /*
+     * World
+     */
+    let mut state = NexusState::default();
+    let no_coupling = RbdCoupling::None;
+
+    let rad = 0.4;
+    let num = 20;
+    let shift = 3.0;
+    let rope_len = 4.0;
+
+    for k in 0..num {
+        for i in 0..num {
+            let x = k as f32 * shift;
+            let z = i as f32 * shift;
+
+            /*
+             * The anchor.
+             */
+            let anchor = RigidBodyBuilder::fixed()
+                .translation(Vec3::new(x, 10.0, z))
+                .build();
+            let collider = ColliderBuilder::cuboid(rad * 0.5, rad * 0.5, rad * 0.5).build();
+            let shape = collider.shared_shape().clone();
+            let anchor_handle = state.insert_rigid_body(anchor, collider, no_coupling);
+            viewer.insert_shape(anchor_handle, &shape, Pose::IDENTITY);
+
+            /*
+             * Every other payload hangs from a rope, starting with some slack
+             * and off to the side so it swings. The others bounce on a spring.
+             */
+            let payload = RigidBodyBuilder::dynamic()
+                .translation(Vec3::new(x + rope_len * 0.5, 10.0 - rope_len * 0.5, z))
+                .build();
+            let collider = if (k + i) % 2 == 0 {
+                ColliderBuilder::cuboid(rad, rad, rad).build()
+            } else {
+                ColliderBuilder::ball(rad).build()
+            };
+            let shape = collider.shared_shape().clone();
+            let payload_handle = state.insert_rigid_body(payload, collider, no_coupling);
+            viewer.insert_shape(payload_handle, &shape, Pose::IDENTITY);
+
+            if (k + i) % 2 == 0 {
+                // Attach the rope to a corner of the box so it tumbles.
+                let joint = RopeJointBuilder::new(rope_len).local_anchor2(Vec3::new(rad, rad, 0.0));
+                state.insert_impulse_joint(anchor_handle, payload_handle, joint);
+            } else {
+                let joint = SpringJointBuilder::new(rope_len * 0.5, 50.0, 1.0);
+                state.insert_impulse_joint(anchor_handle, payload_handle, joint);
+            }
+        }
+    }
+
+    // Optional, useful so we can render even before starting the simulation.
+    let mut timestamps = GpuTimestamps::new(viewer.backend(), 2048);
+    viewer
+        .scene3d_mut()
+        .add_directional_light(glamx::Vec3::new(1.0, -2.0, 3.0));
+    state.finalize(viewer.backend()).await?;
+
+    while viewer.render_frame().await {
+        if viewer.simulating() {
+            pipeline
+                .simulate(viewer.backend(), &mut state, Some(&mut timestamps))
+                .await?;
+        }
+        viewer.sync(&mut state, Some(&mut timestamps)).await?;
+    }
+
+    Ok(state)
+}
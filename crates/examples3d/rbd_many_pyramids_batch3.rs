@@ -87,6 +87,8 @@ pub async fn run(
             rad,
         );
 
+        // Environments don't need to share the same topology: the height of
+        // the second pyramid varies from one environment to the next.
         create_pyramid(
             &mut state,
             viewer,
@@ -96,7 +98,7 @@ pub async fn run(
                 bottomy,
                 (pyramid_index as f32 - pyramid_count as f32 / 2.0) * spacing,
             ),
-            60,
+            30 + pyramid_index % 31,
             rad,
         );
     }
@@ -251,6 +251,32 @@ impl NexusState {
             .map(MultibodyJointHandle)
     }
 
+    /// Removes a body of environment `env` with its colliders and joints.
+    /// Bodies added with `add_rigid_bodies` are removed from the live GPU scene
+    /// in place; anything else is rebuilt on the next `finalize`. Returns
+    /// whether the body existed.
+    fn remove_rigid_body(
+        &mut self,
+        viewer: PyRef<NexusViewer>,
+        env: usize,
+        handle: RigidBodyHandle,
+    ) -> PyResult<bool> {
+        self.0
+            .remove_rigid_body(viewer.backend(), env, handle.0)
+            .map(|body| body.is_some())
+            .map_err(gpu_err)
+    }
+
+    /// Removes an impulse joint of environment `env`. Returns whether it existed.
+    fn remove_impulse_joint(&mut self, env: usize, handle: ImpulseJointHandle) -> bool {
+        self.0.remove_impulse_joint(env, handle.0).is_some()
+    }
+
+    /// Removes a multibody joint of environment `env`.
+    fn remove_multibody_joint(&mut self, env: usize, handle: MultibodyJointHandle) {
+        self.0.remove_multibody_joint(env, handle.0);
+    }
+
     // --- batched environments ---------------------------------------------
 
     /// Allocates a new batched simulation environment, returning its index.
@@ -365,6 +391,155 @@ impl NexusState {
             .map_err(gpu_err)
     }
 
+    fn set_kinematic_next_pose(
+        &mut self,
+        viewer: PyRef<NexusViewer>,
+        env: usize,
+        handle: RigidBodyHandle,
+        pose: Pose,
+    ) -> PyResult<()> {
+        self.0
+            .set_kinematic_next_pose(viewer.backend(), env, handle.0, pose.0)
+            .map_err(gpu_err)
+    }
+
+    fn set_kinematic_velocity(
+        &mut self,
+        viewer: PyRef<NexusViewer>,
+        env: usize,
+        handle: RigidBodyHandle,
+        linvel: Vec3,
+        angvel: Vec3,
+    ) -> PyResult<()> {
+        self.0
+            .set_kinematic_velocity(viewer.backend(), env, handle.0, linvel.0, angvel.0)
+            .map_err(gpu_err)
+    }
+
+    fn set_body_pose(&mut self, env: usize, handle: RigidBodyHandle, pose: Pose) {
+        self.0.set_body_pose(env, handle.0, pose.0);
+    }
+
+    fn set_body_velocity(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        linvel: Vec3,
+        angvel: Vec3,
+    ) {
+        self.0.set_body_velocity(env, handle.0, linvel.0, angvel.0);
+    }
+
+    #[pyo3(signature = (env, handle, impulse, torque_impulse=Vec3::ZERO))]
+    fn apply_impulse(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        impulse: Vec3,
+        torque_impulse: Vec3,
+    ) {
+        self.0.apply_impulse(
+            env,
+            handle.0,
+            nexus3d::rbd::Impulse::new(impulse.0, torque_impulse.0),
+        );
+    }
+
+    fn add_force(&mut self, env: usize, handle: RigidBodyHandle, force: Vec3) {
+        self.0.add_force(env, handle.0, force.0);
+    }
+
+    fn add_torque(&mut self, env: usize, handle: RigidBodyHandle, torque: Vec3) {
+        self.0.add_torque(env, handle.0, torque.0);
+    }
+
+    fn reset_forces(&mut self, env: usize, handle: RigidBodyHandle) {
+        self.0.reset_forces(env, handle.0);
+    }
+
+    /// Non-blocking read of `(pose, linvel, angvel)` for each body of `handles`
+    /// in environment `env`. Lags the simulation by a frame or two; entries are
+    /// `None` until the first readback completes. Call once per frame.
+    fn read_body_states(
+        &mut self,
+        viewer: PyRef<NexusViewer>,
+        env: usize,
+        handles: Vec<RigidBodyHandle>,
+    ) -> PyResult<Vec<Option<(Pose, Vec3, Vec3)>>> {
+        let handles: Vec<_> = handles.into_iter().map(|h| h.0).collect();
+        let states = self
+            .0
+            .read_body_states(viewer.backend(), env, &handles)
+            .map_err(gpu_err)?;
+        Ok(states
+            .into_iter()
+            .map(|s| s.map(|s| (Pose(s.pose), Vec3(s.linvel), Vec3(s.angvel))))
+            .collect())
+    }
+
+    /// Polls the non-blocking readback of the collision and contact-force
+    /// events. Call once per frame, then drain them with
+    /// `drain_collision_events` / `drain_contact_force_events`. Lags the
+    /// simulation by a frame or two.
+    fn poll_events(&mut self, viewer: PyRef<NexusViewer>) -> PyResult<()> {
+        self.0.poll_events(viewer.backend()).map_err(gpu_err)
+    }
+
+    /// Collision events harvested by `poll_events`, one list per environment.
+    /// Each event is `(body1, body2, started)` with the parent bodies of the two
+    /// colliders; `started` is `False` when they stopped touching or
+    /// intersecting.
+    fn drain_collision_events(&mut self) -> Vec<Vec<(RigidBodyHandle, RigidBodyHandle, bool)>> {
+        let events = self.0.drain_collision_events();
+        events
+            .into_iter()
+            .enumerate()
+            .map(|(env, events)| {
+                let colliders = &self.0.rbd_world(env).colliders;
+                let parent = |h: rp::ColliderHandle| colliders.get(h)?.parent();
+                events
+                    .into_iter()
+                    .filter_map(|event| {
+                        let b1 = parent(event.collider1())?;
+                        let b2 = parent(event.collider2())?;
+                        Some((RigidBodyHandle(b1), RigidBodyHandle(b2), event.started()))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Contact-force events harvested by `poll_events`, one list per
+    /// environment. Each event is `(body1, body2, total_force, total_force_magnitude)`
+    /// with the parent bodies of the two colliders; `total_force` is the force
+    /// applied by the first collider on the second.
+    fn drain_contact_force_events(
+        &mut self,
+    ) -> Vec<Vec<(RigidBodyHandle, RigidBodyHandle, Vec3, f32)>> {
+        let events = self.0.drain_contact_force_events();
+        events
+            .into_iter()
+            .enumerate()
+            .map(|(env, events)| {
+                let colliders = &self.0.rbd_world(env).colliders;
+                let parent = |h: rp::ColliderHandle| colliders.get(h)?.parent();
+                events
+                    .into_iter()
+                    .filter_map(|event| {
+                        let b1 = parent(event.collider1)?;
+                        let b2 = parent(event.collider2)?;
+                        Some((
+                            RigidBodyHandle(b1),
+                            RigidBodyHandle(b2),
+                            Vec3(event.total_force),
+                            event.total_force_magnitude,
+                        ))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
     // --- mpm --------------------------------------------------------------
 
     fn set_mpm_params(
@@ -444,6 +619,12 @@ impl NexusState {
     fn finalize(&mut self, viewer: PyRef<NexusViewer>) -> PyResult<()> {
         pollster::block_on(self.0.finalize(viewer.backend())).map_err(gpu_err)
     }
+
+    /// Copies the simulated body poses/velocities, multibody joint coordinates
+    /// and joint impulses back into the rapier worlds. Blocks on the readback.
+    fn sync_to_rapier(&mut self, viewer: PyRef<NexusViewer>) -> PyResult<()> {
+        pollster::block_on(self.0.sync_to_rapier(viewer.backend())).map_err(gpu_err)
+    }
 }
 
 /// The GPU compute pipelines (`nexus3d::prelude::NexusPipeline`).
@@ -230,6 +230,24 @@ impl ColliderBuilder {
     fn solver_groups(&self, groups: InteractionGroups) -> Self {
         Self(self.0.clone().solver_groups(groups.0))
     }
+    /// Sensors detect intersections (see `NexusState.drain_collision_events`)
+    /// but never generate contacts.
+    fn sensor(&self, is_sensor: bool) -> Self {
+        Self(self.0.clone().sensor(is_sensor))
+    }
+    /// Enables the collision events and/or the contact-force events of this
+    /// collider (see `NexusState.poll_events`).
+    #[pyo3(signature = (collision_events=false, contact_force_events=false))]
+    fn active_events(&self, collision_events: bool, contact_force_events: bool) -> Self {
+        let mut events = rp::ActiveEvents::empty();
+        events.set(rp::ActiveEvents::COLLISION_EVENTS, collision_events);
+        events.set(rp::ActiveEvents::CONTACT_FORCE_EVENTS, contact_force_events);
+        Self(self.0.clone().active_events(events))
+    }
+    /// Total contact force above which contact-force events are emitted.
+    fn contact_force_event_threshold(&self, threshold: f32) -> Self {
+        Self(self.0.clone().contact_force_event_threshold(threshold))
+    }
 
     fn build(&self) -> Collider {
         Collider(self.0.clone().build())
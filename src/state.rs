@@ -2,15 +2,21 @@ use crate::mpm::pipeline::{MpmCapacities, MpmState};
 use crate::mpm::solver::{BoundaryCondition, Particle, SimulationParams};
 use crate::rapier::data::{Arena, Coarena, Index};
 use crate::rapier::prelude::{
-    Collider, ColliderHandle, GenericJoint, ImpulseJointHandle, MultibodyJointHandle, PhysicsWorld,
-    RigidBody, RigidBodyHandle,
+    Collider, ColliderHandle, CollisionEvent, CollisionEventFlags, ContactForceEvent, GenericJoint,
+    ImpulseJoint, ImpulseJointHandle, IslandManager, MultibodyJointHandle, PhysicsWorld, RigidBody,
+    RigidBodyHandle,
 };
 use crate::rbd::dynamics::{
     RbdSimParams,
     body::{BodyCoupling, RapierBodyCouplingEntry},
 };
-use crate::rbd::pipeline::{RbdCapacities, RbdResizePolicy, RbdState, RunStats};
-use khal::backend::{GpuBackend, GpuBackendError};
+use crate::rbd::pipeline::{
+    ParamSample, RbdCapacities, RbdParam, RbdResizePolicy, RbdState, RunStats,
+};
+use crate::rbd::shaders::broad_phase::{COLLISION_EVENT_FLAG_SENSOR, COLLISION_EVENT_STARTED};
+use crate::rbd::shaders::dynamics::JointBreakThreshold;
+use crate::rbd::shapes::num_shape_parts;
+use khal::backend::{Backend, GpuBackend, GpuBackendError};
 
 /// Handle referencing a rigid-body managed by a [`NexusState`].
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
@@ -88,6 +94,68 @@ impl Default for GpuRigidBodyRef {
     }
 }
 
+/// Simulated pose and velocity of a rigid-body, read back from the GPU by
+/// [`NexusState::read_body_states`].
+#[derive(Copy, Clone, Debug)]
+pub struct RbdBodyState {
+    /// Body-origin world pose (matches rapier's `RigidBody::position`).
+    pub pose: crate::rbd::math::Pose,
+    pub linvel: crate::rbd::math::Vector,
+    pub angvel: crate::rbd::math::AngVector,
+}
+
+/// Reaction force and torque of a joint, read back from the GPU by
+/// [`NexusState::read_impulse_joint_wrenches`] and
+/// [`NexusState::read_multibody_joint_wrenches`].
+///
+/// Both are expressed in the joint frame, and are the impulses of the last
+/// substep divided by the substep length.
+/// Multibody joint wrenches are zero along the locked axes of the joint, see
+/// [`NexusState::read_multibody_joint_wrenches`].
+#[derive(Copy, Clone, Debug)]
+pub struct RbdJointWrench {
+    pub force: crate::rbd::math::Vector,
+    pub torque: crate::rbd::math::AngVector,
+}
+
+/// An impulse joint broken because its reaction wrench exceeded the threshold
+/// set with [`NexusState::set_impulse_joint_break_threshold`]. Returned by
+/// [`NexusState::drain_joint_break_events`].
+#[derive(Copy, Clone, Debug)]
+pub struct RbdJointBreakEvent {
+    /// The broken joint.
+    pub joint: ImpulseJointHandle,
+    /// Norm of the reaction force when the joint broke.
+    pub force: f32,
+    /// Norm of the reaction torque when the joint broke.
+    pub torque: f32,
+}
+
+/// A physical parameter of one environment, randomized with
+/// [`NexusState::set_env_param`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RbdEnvParam {
+    /// The friction coefficient of a collider.
+    Friction(ColliderHandle),
+    /// The restitution coefficient of a collider.
+    Restitution(ColliderHandle),
+    /// The mass of a dynamic body, or multibody link. Its angular inertia is
+    /// scaled by the same ratio.
+    Mass(RigidBodyHandle),
+    /// The damping of a multibody joint along one of its free axes.
+    JointDamping {
+        joint: MultibodyJointHandle,
+        axis: crate::rapier::dynamics::JointAxis,
+    },
+    /// The armature of a multibody joint along one of its free axes.
+    JointArmature {
+        joint: MultibodyJointHandle,
+        axis: crate::rapier::dynamics::JointAxis,
+    },
+    /// One component of the rigid-body gravity (`0` for X, `1` for Y, `2` for Z).
+    Gravity(usize),
+}
+
 /// Entity counts for the current scene, surfaced in the viewer UI. Rigid-body
 /// counts are summed across all environments (batches).
 #[derive(Clone, Copy, Default, Debug)]
@@ -155,6 +223,14 @@ pub struct NexusState {
     /// Set whenever the rapier worlds change; consumed by [`Self::finalize`] to
     /// decide whether the GPU [`RbdState`] needs rebuilding.
     rbd_dirty: bool,
+    /// Per-environment breaking threshold of the impulse joints, re-uploaded
+    /// whenever the GPU [`RbdState`] is rebuilt.
+    rbd_joint_break_thresholds: Vec<Coarena<JointBreakThreshold>>,
+    /// Per-environment template, set for the environments added by
+    /// [`Self::clone_environment`] as long as neither they nor their template
+    /// are modified. Their collider data is then copied from the template's
+    /// batch on the GPU instead of being converted again.
+    rbd_env_templates: Vec<Option<usize>>,
     /// Number of rigid-body solver steps advanced per [`NexusPipeline::simulate`](crate::pipeline::NexusPipeline::simulate) call.
     pub rbd_steps_per_frame: u32,
     /// Per-environment GPU collider-slot reservation. When > 0, the GPU
@@ -184,6 +260,8 @@ impl NexusState {
             rbd_envs: vec![PhysicsWorld::default()],
             rbd_sim_params: vec![RbdSimParams::tgs_soft()],
             rbd_dirty: false,
+            rbd_joint_break_thresholds: vec![Coarena::new()],
+            rbd_env_templates: vec![None],
             rbd_steps_per_frame: 1,
             rbd_reserve_per_env: 0,
             rbd2gpu: vec![Coarena::new()],
@@ -288,11 +366,12 @@ impl NexusState {
             .unwrap_or(crate::rbd::math::Vector::ZERO)
     }
 
-    /// Sets the rigid-body gravity vector, e.g. `[0.0, 0.0, -9.81]` for a Z-up
-    /// scene. Every solver path reads the same uniform, so this applies to free
-    /// rigid-bodies and multibody links alike (in 2D the third component is
-    /// ignored). No-op until the rigid-body state is built, so call it after
-    /// [`Self::finalize`].
+    /// Sets the rigid-body gravity vector of every environment, e.g.
+    /// `[0.0, 0.0, -9.81]` for a Z-up scene. Every solver path reads the same
+    /// per-environment gravity, so this applies to free rigid-bodies and
+    /// multibody links alike (in 2D the third component is ignored). It
+    /// overrides the gravities randomized with [`Self::set_env_param`]. No-op
+    /// until the rigid-body state is built, so call it after [`Self::finalize`].
     #[cfg(feature = "rbd")]
     pub fn set_rbd_gravity(&mut self, backend: &GpuBackend, gravity: [f32; 3]) {
         if let Some(rbd) = self.rbd.as_mut() {
@@ -367,6 +446,8 @@ impl NexusState {
         self.rbd_envs.push(PhysicsWorld::default());
         self.rbd_sim_params.push(RbdSimParams::tgs_soft());
         self.rbd2gpu.push(Coarena::new());
+        self.rbd_joint_break_thresholds.push(Coarena::new());
+        self.rbd_env_templates.push(None);
         self.rbd_dirty = true;
         self.rbd_envs.len() - 1
     }
@@ -376,6 +457,76 @@ impl NexusState {
         self.rbd_envs.len()
     }
 
+    /// Adds `count` copies of environment `template` and returns their indices.
+    ///
+    /// Each copy is moved by `transform(env)`, given the index of the new
+    /// environment: it is applied to the body poses (and rotates their
+    /// velocities), so it can spread or randomly orient the copies. The copies
+    /// keep the handles of the template, as well as its solver parameters and
+    /// joint breaking thresholds, but none of its MPM couplings.
+    ///
+    /// The rapier sets are cloned as is, so the copies share the shapes of the
+    /// template. The next [`Self::finalize`] converts the template's colliders
+    /// once and copies them into the batches of the copies on the GPU, which
+    /// only read their body states from their own rapier sets. Modifying a copy
+    /// or its template before then converts them separately. Like
+    /// [`Self::add_environment`], this rebuilds the whole GPU state on the next
+    /// [`Self::finalize`], so clone every environment before the first one
+    /// rather than one at a time between steps.
+    pub fn clone_environment(
+        &mut self,
+        template: usize,
+        count: usize,
+        mut transform: impl FnMut(usize) -> crate::rbd::math::Pose,
+    ) -> std::ops::Range<usize> {
+        let first = self.rbd_envs.len();
+        // Copies of a copy are replicated from the original template.
+        let root = self.rbd_env_templates[template].unwrap_or(template);
+        for env in first..first + count {
+            let t = transform(env);
+            let source = &self.rbd_envs[template];
+            let mut world = PhysicsWorld::default();
+            world.bodies = source.bodies.clone();
+            world.colliders = source.colliders.clone();
+            world.impulse_joints = source.impulse_joints.clone();
+            world.multibody_joints = source.multibody_joints.clone();
+
+            for (_, body) in world.bodies.iter_mut() {
+                body.set_position(t * *body.position(), false);
+                body.set_linvel(t.rotation * body.linvel(), false);
+                #[cfg(feature = "dim3")]
+                body.set_angvel(t.rotation * body.angvel(), false);
+            }
+            // Parented colliders follow their body, parentless ones are placed
+            // in world space.
+            for (_, collider) in world.colliders.iter_mut() {
+                if collider.parent().is_none() {
+                    collider.set_position(t * *collider.position());
+                }
+            }
+
+            self.rbd_envs.push(world);
+            self.rbd_sim_params.push(self.rbd_sim_params[template]);
+            self.rbd2gpu.push(Coarena::new());
+            self.rbd_joint_break_thresholds
+                .push(self.rbd_joint_break_thresholds[template].clone());
+            self.rbd_env_templates.push(Some(root));
+        }
+        self.rbd_dirty = true;
+        first..self.rbd_envs.len()
+    }
+
+    /// Stops replicating environment `env` from its template, and into its
+    /// copies, once its rapier sets are modified.
+    fn detach_env_clones(&mut self, env: usize) {
+        self.rbd_env_templates[env] = None;
+        for template in &mut self.rbd_env_templates {
+            if *template == Some(env) {
+                *template = None;
+            }
+        }
+    }
+
     /// Overwrite environment `env`'s solver parameters (default `tgs_soft`).
     /// Marks the rbd state dirty so [`Self::finalize`] rebuilds with them.
     /// Mainly for tests that need to match an external engine's
@@ -398,6 +549,7 @@ impl NexusState {
     /// dirty so [`Self::finalize`] rebuilds the GPU buffers.
     pub fn rbd_world_mut(&mut self, env: usize) -> &mut PhysicsWorld {
         self.rbd_dirty = true;
+        self.detach_env_clones(env);
         &mut self.rbd_envs[env]
     }
 
@@ -432,6 +584,7 @@ impl NexusState {
         collider: Collider,
         coupling: RbdCoupling,
     ) -> RigidBodyHandle {
+        self.detach_env_clones(env);
         let (handle, _) = self.rbd_envs[env].insert(body, collider);
         self.rbd2gpu[env].insert(
             handle.0,
@@ -498,6 +651,7 @@ impl NexusState {
         let mut gpu_pairs: Vec<(RigidBody, Collider)> = Vec::new();
         let mut handles: Vec<RigidBodyHandle> = Vec::new();
         let mut couplings: Vec<RbdCoupling> = Vec::new();
+        self.detach_env_clones(0);
         for (body, collider, coupling) in bodies {
             gpu_pairs.push((body.clone(), collider.clone()));
             let (handle, _) = self.rbd_envs[0].insert(body, collider);
@@ -510,8 +664,8 @@ impl NexusState {
 
         let appended = match self.rbd.as_mut() {
             Some(rbd)
-                if (rbd.num_active_colliders() as usize) + gpu_pairs.len()
-                    <= rbd.num_colliders_per_batch() as usize =>
+                if (rbd.num_active_colliders(0) + gpu_pairs.len() as u32)
+                    <= rbd.batch_range(0).capacity =>
             {
                 let range = rbd.append_bodies(backend, &gpu_pairs)?;
                 // Single environment: the per-batch local slot is the gpu_id.
@@ -564,6 +718,7 @@ impl NexusState {
         body: RigidBody,
         coupling: RbdCoupling,
     ) -> RigidBodyHandle {
+        self.detach_env_clones(env);
         let handle = self.rbd_envs[env].insert_body(body);
         self.rbd2gpu[env].insert(
             handle.0,
@@ -588,6 +743,7 @@ impl NexusState {
         parent: Option<RigidBodyHandle>,
     ) -> ColliderHandle {
         self.rbd_dirty = true;
+        self.detach_env_clones(env);
         self.rbd_envs[env].insert_collider(collider, parent)
     }
 
@@ -610,6 +766,7 @@ impl NexusState {
         joint: impl Into<GenericJoint>,
     ) -> ImpulseJointHandle {
         self.rbd_dirty = true;
+        self.detach_env_clones(env);
         self.rbd_envs[env].insert_impulse_joint(body1, body2, joint)
     }
 
@@ -635,9 +792,199 @@ impl NexusState {
         joint: impl Into<GenericJoint>,
     ) -> Option<MultibodyJointHandle> {
         self.rbd_dirty = true;
+        self.detach_env_clones(env);
         self.rbd_envs[env].insert_multibody_joint(body1, body2, joint)
     }
 
+    /// Removes the rigid-body `handle` of environment `env`, along with its
+    /// colliders and joints, and returns it.
+    ///
+    /// The body is removed from the GPU state in place when that state was built
+    /// by appending bodies (see [`Self::reserve_rigid_bodies`]) and the body has a
+    /// single collider and no joint. With several environments, its GPU slot is
+    /// left inert until the next rebuild (see [`RbdState::remove_batch_bodies`]).
+    /// Otherwise the GPU state is rebuilt on the next [`Self::finalize`].
+    pub fn remove_rigid_body(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handle: RigidBodyHandle,
+    ) -> Result<Option<RigidBody>, GpuBackendError> {
+        if !self.rbd_envs[env].bodies.contains(handle) {
+            return Ok(None);
+        }
+        self.detach_env_clones(env);
+
+        let in_place_slot = self.in_place_removable_slot(env, handle);
+        if let Some(gpu_ref) = self.rbd2gpu[env].remove(handle.0, GpuRigidBodyRef::default()) {
+            if gpu_ref.coupling != RbdCoupling::None {
+                self.mpm_dirty = true;
+            }
+        }
+        match in_place_slot {
+            Some(slot) => self.remove_gpu_body_slot(backend, env, slot)?,
+            None => self.rbd_dirty = true,
+        }
+
+        let world = &mut self.rbd_envs[env];
+        // The rapier world is never stepped, so its island manager is always empty.
+        let mut islands = IslandManager::new();
+        Ok(world.bodies.remove(
+            handle,
+            &mut islands,
+            &mut world.colliders,
+            &mut world.impulse_joints,
+            &mut world.multibody_joints,
+            true,
+        ))
+    }
+
+    /// Removes the collider `handle` of environment `env` and returns it.
+    ///
+    /// Removing the only collider of a body removes that body from the GPU state
+    /// (collider-less free bodies aren't simulated), in place under the same
+    /// conditions as [`Self::remove_rigid_body`]. Any other collider removal
+    /// changes the shapes or mass of a body, which rebuilds the GPU state on the
+    /// next [`Self::finalize`].
+    pub fn remove_collider(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handle: ColliderHandle,
+    ) -> Result<Option<Collider>, GpuBackendError> {
+        let Some(parent) = self.rbd_envs[env]
+            .colliders
+            .get(handle)
+            .map(|co| co.parent())
+        else {
+            return Ok(None);
+        };
+        self.detach_env_clones(env);
+
+        let in_place_slot = parent.and_then(|body| self.in_place_removable_slot(env, body));
+        if let Some(gpu_ref) = parent.and_then(|body| self.rbd2gpu[env].get_mut(body.0)) {
+            if gpu_ref.coupling != RbdCoupling::None {
+                self.mpm_dirty = true;
+            }
+            if in_place_slot.is_some() {
+                gpu_ref.gpu_id = u32::MAX;
+            }
+        }
+        match in_place_slot {
+            Some(slot) => self.remove_gpu_body_slot(backend, env, slot)?,
+            None => self.rbd_dirty = true,
+        }
+
+        let world = &mut self.rbd_envs[env];
+        // The rapier world is never stepped, so its island manager is always empty.
+        let mut islands = IslandManager::new();
+        Ok(world
+            .colliders
+            .remove(handle, &mut islands, &mut world.bodies, true))
+    }
+
+    /// Removes the impulse joint `handle` of environment `env` and returns it.
+    ///
+    /// The GPU state is rebuilt on the next [`Self::finalize`].
+    pub fn remove_impulse_joint(
+        &mut self,
+        env: usize,
+        handle: ImpulseJointHandle,
+    ) -> Option<ImpulseJoint> {
+        let joint = self.rbd_envs[env].impulse_joints.remove(handle, true);
+        if joint.is_some() {
+            self.rbd_dirty = true;
+            self.detach_env_clones(env);
+        }
+        joint
+    }
+
+    /// Removes the multibody joint `handle` of environment `env`.
+    ///
+    /// The GPU state is rebuilt on the next [`Self::finalize`].
+    pub fn remove_multibody_joint(&mut self, env: usize, handle: MultibodyJointHandle) {
+        let world = &mut self.rbd_envs[env];
+        if world.multibody_joints.get(handle).is_some() {
+            world.multibody_joints.remove(handle, true);
+            self.rbd_dirty = true;
+            self.detach_env_clones(env);
+        }
+    }
+
+    /// The GPU body slot of `handle` if it can be removed from the GPU state in
+    /// place (see [`Self::remove_gpu_body_slot`]).
+    ///
+    /// This requires the one-collider-per-slot layout of states built by
+    /// appending bodies, no joint touching the body, and a body with a single
+    /// collider that isn't a compound shape.
+    fn in_place_removable_slot(&self, env: usize, handle: RigidBodyHandle) -> Option<u32> {
+        if self.rbd.is_none() || self.rbd_dirty || self.rbd_reserve_per_env == 0 {
+            return None;
+        }
+
+        let world = &self.rbd_envs[env];
+        let body = world.bodies.get(handle)?;
+        let has_joints = world
+            .impulse_joints
+            .attached_joints(handle)
+            .next()
+            .is_some()
+            || world.multibody_joints.rigid_body_link(handle).is_some();
+        // Compound colliders take one GPU slot per part, and only one slot is
+        // removed in place.
+        let single_part = match body.colliders() {
+            [co] => world
+                .colliders
+                .get(*co)
+                .is_some_and(|co| num_shape_parts(co.shape()) == 1),
+            _ => false,
+        };
+        if !single_part || has_joints {
+            return None;
+        }
+        self.rbd_gpu_id(env, handle)
+    }
+
+    /// Removes the GPU body `slot` of environment `env`.
+    ///
+    /// With a single environment, the slot is swap-removed and `rbd2gpu` is
+    /// patched to follow the relocations the GPU performed. With several
+    /// environments, the other batches still use that local slot: it is only
+    /// neutralized in the batch of `env`, and no body moves.
+    fn remove_gpu_body_slot(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        slot: u32,
+    ) -> Result<(), GpuBackendError> {
+        let Some(rbd) = self.rbd.as_mut() else {
+            return Ok(());
+        };
+        if self.rbd_envs.len() != 1 {
+            let local = slot - rbd.batch_range(env as u32).offset;
+            return rbd.remove_batch_bodies(backend, env as u32, &[local]);
+        }
+
+        let remaps = rbd.remove_bodies(backend, &[slot])?;
+        // Each `(from, to)`: the body at local slot `from` was moved down to the
+        // freed slot `to`. With a single environment, local slots are gpu ids.
+        for (from, to) in remaps {
+            for (handle, _) in self.rbd_envs[0].bodies.iter() {
+                if let Some(gpu_ref) = self.rbd2gpu[0]
+                    .get_mut(handle.0)
+                    .filter(|r| r.gpu_id == from)
+                {
+                    gpu_ref.gpu_id = to;
+                    // The MPM coupling records the rigid-body slots.
+                    if gpu_ref.coupling != RbdCoupling::None {
+                        self.mpm_dirty = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Number of GPU batches (== number of environments) once finalized.
     pub fn rbd_num_batches(&self) -> u32 {
         self.rbd.as_ref().map(|r| r.num_batches()).unwrap_or(0)
@@ -645,7 +992,6 @@ impl NexusState {
 
     /// Sets a multibody joint motor's target velocity on the GPU state (used by
     /// the URDF demo for per-frame actuation). No-op until the rbd state exists.
-    #[cfg(feature = "dim3")]
     pub fn set_multibody_motor_velocity(
         &mut self,
         backend: &GpuBackend,
@@ -661,6 +1007,687 @@ impl NexusState {
         Ok(())
     }
 
+    /// The global GPU body slot of the body `handle` of environment `env`, if
+    /// it has one.
+    fn rbd_gpu_id(&self, env: usize, handle: RigidBodyHandle) -> Option<u32> {
+        self.rbd2gpu[env]
+            .get(handle.0)
+            .filter(|r| r.gpu_id != u32::MAX)
+            .map(|r| r.gpu_id)
+    }
+
+    /// Sets the pose the position-based kinematic body `handle` of environment
+    /// `env` reaches at the end of the next step (see
+    /// [`RbdState::set_kinematic_next_pose`]). No-op until the body
+    /// has a GPU slot.
+    pub fn set_kinematic_next_pose(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handle: RigidBodyHandle,
+        pose: crate::rbd::math::Pose,
+    ) -> Result<(), GpuBackendError> {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.set_kinematic_next_pose(backend, gpu_id, pose)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the velocity of the velocity-based kinematic body `handle` of
+    /// environment `env` (see [`RbdState::set_kinematic_velocity`]). No-op until
+    /// the body has a GPU slot.
+    pub fn set_kinematic_velocity(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handle: RigidBodyHandle,
+        linvel: crate::rbd::math::Vector,
+        angvel: crate::rbd::math::AngVector,
+    ) -> Result<(), GpuBackendError> {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.set_kinematic_velocity(backend, gpu_id, linvel, angvel)?;
+        }
+        Ok(())
+    }
+
+    // ── Rigid-body runtime writes ───────────────────────────────────────
+    //
+    // Queued on the CPU and scattered into the GPU buffers at the beginning of
+    // the next rigid-body step. All are no-ops until the body has a GPU slot.
+
+    /// Teleports the body `handle` of environment `env` (see [`RbdState::set_body_pose`]).
+    pub fn set_body_pose(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        pose: crate::rbd::math::Pose,
+    ) {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.set_body_pose(gpu_id, pose);
+        }
+    }
+
+    /// Sets the velocity of the body `handle` of environment `env` (see
+    /// [`RbdState::set_body_velocity`]).
+    pub fn set_body_velocity(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        linvel: crate::rbd::math::Vector,
+        angvel: crate::rbd::math::AngVector,
+    ) {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.set_body_velocity(gpu_id, linvel, angvel);
+        }
+    }
+
+    /// Applies an impulse at the center-of-mass of the body `handle` of
+    /// environment `env` (see [`RbdState::apply_impulse`]).
+    pub fn apply_impulse(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        impulse: crate::rbd::Impulse,
+    ) {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.apply_impulse(gpu_id, impulse);
+        }
+    }
+
+    /// Adds a persistent force at the center-of-mass of the body `handle` of
+    /// environment `env` (see [`RbdState::add_force`]).
+    pub fn add_force(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        force: crate::rbd::math::Vector,
+    ) {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.add_force(gpu_id, force);
+        }
+    }
+
+    /// Adds a persistent torque to the body `handle` of environment `env` (see
+    /// [`RbdState::add_torque`]).
+    pub fn add_torque(
+        &mut self,
+        env: usize,
+        handle: RigidBodyHandle,
+        torque: crate::rbd::math::AngVector,
+    ) {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.add_torque(gpu_id, torque);
+        }
+    }
+
+    /// Clears the persistent force and torque of the body `handle` of
+    /// environment `env` (see [`RbdState::reset_forces`]).
+    pub fn reset_forces(&mut self, env: usize, handle: RigidBodyHandle) {
+        if let (Some(gpu_id), Some(rbd)) = (self.rbd_gpu_id(env, handle), self.rbd.as_mut()) {
+            rbd.reset_forces(gpu_id);
+        }
+    }
+
+    // ── Per-environment snapshots ───────────────────────────────────────
+    //
+    // Captured and restored in place at the beginning of the next rigid-body
+    // step. Snapshots don't survive a rebuild of the GPU state by
+    // [`Self::finalize`].
+
+    /// Captures the rigid-body state of environment `env` as the snapshot
+    /// [`Self::reset_envs`] restores (see [`RbdState::snapshot_batch`]). No-op
+    /// until the rbd state exists.
+    pub fn snapshot_env(&mut self, backend: &GpuBackend, env: usize) {
+        if let Some(rbd) = self.rbd.as_mut() {
+            rbd.snapshot_batch(backend, env as u32);
+        }
+    }
+
+    /// Resets every environment `i` with `mask[i] == true` to its snapshot (see
+    /// [`RbdState::reset_batches`]). No-op until the rbd state exists.
+    ///
+    /// The body updates queued in the same frame are applied after the reset,
+    /// so they can be used to randomize the restored state.
+    pub fn reset_envs(&mut self, mask: &[bool]) {
+        if let Some(rbd) = self.rbd.as_mut() {
+            rbd.reset_batches(mask);
+        }
+    }
+
+    // ── Domain randomization ────────────────────────────────────────────
+    //
+    // Written in place at the beginning of the next rigid-body step, after the
+    // environment resets. The parameters survive the resets but not a rebuild of
+    // the GPU state by [`Self::finalize`].
+
+    /// Writes, or draws on the GPU from `sample`, a physical parameter of
+    /// environment `env` (see [`RbdState::set_batch_param`]). No-op until the
+    /// rbd state exists, or if the collider, body or joint has no GPU slot.
+    ///
+    /// # Panics
+    /// Panics if a randomized joint axis is locked.
+    pub fn set_env_param(&mut self, env: usize, param: RbdEnvParam, sample: ParamSample) {
+        let Some(rbd) = self.rbd.as_ref() else {
+            return;
+        };
+        let batch = env as u32;
+        let offset = rbd.batch_range(batch).offset;
+        let local_id = |handle| Some(self.rbd_gpu_id(env, handle)? - offset);
+        let link_id = |joint| {
+            let (multibody, link_id) = self.rbd_envs[env].multibody_joints.get(joint)?;
+            let body = multibody.link(link_id)?.rigid_body_handle();
+            rbd.multibodies().link_of_body(batch, local_id(body)?)
+        };
+
+        let params: Vec<RbdParam> = match param {
+            RbdEnvParam::Friction(handle) | RbdEnvParam::Restitution(handle) => {
+                // A compound collider owns one slot per part.
+                self.collider_slots(env)
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, slot)| *slot == Some(handle))
+                    .map(|(slot, _)| {
+                        let collider_id = slot as u32;
+                        if matches!(param, RbdEnvParam::Friction(_)) {
+                            RbdParam::Friction { collider_id }
+                        } else {
+                            RbdParam::Restitution { collider_id }
+                        }
+                    })
+                    .collect()
+            }
+            RbdEnvParam::Mass(handle) => local_id(handle)
+                .map(|body_id| RbdParam::Mass { body_id })
+                .into_iter()
+                .collect(),
+            RbdEnvParam::JointDamping { joint, axis } => link_id(joint)
+                .map(|link_id| RbdParam::DofDamping {
+                    link_id,
+                    axis: axis as usize,
+                })
+                .into_iter()
+                .collect(),
+            RbdEnvParam::JointArmature { joint, axis } => link_id(joint)
+                .map(|link_id| RbdParam::DofArmature {
+                    link_id,
+                    axis: axis as usize,
+                })
+                .into_iter()
+                .collect(),
+            RbdEnvParam::Gravity(axis) => vec![RbdParam::Gravity { axis }],
+        };
+
+        let rbd = self.rbd.as_mut().unwrap();
+        for param in params {
+            rbd.set_batch_param(batch, param, sample);
+        }
+    }
+
+    /// Seeds the sampler of [`Self::set_env_param`] (see
+    /// [`RbdState::set_param_seed`]). No-op until the rbd state exists.
+    pub fn set_env_param_seed(&mut self, seed: u32) {
+        if let Some(rbd) = self.rbd.as_mut() {
+            rbd.set_param_seed(seed);
+        }
+    }
+
+    // ── Rigid-body readback ─────────────────────────────────────────────
+    //
+    // Non-blocking: each call polls the readback in flight and returns the latest
+    // harvested snapshot, which lags the GPU by a frame or two (see
+    // [`RbdState::poll_body_states`]). Call once per frame after `simulate`.
+
+    /// Reads the pose and velocity of the given bodies of environment `env`.
+    ///
+    /// The result is in the same order as `handles`, with `None` for the bodies
+    /// without a GPU slot and for every body until the first snapshot arrives.
+    pub fn read_body_states(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handles: &[RigidBodyHandle],
+    ) -> Result<Vec<Option<RbdBodyState>>, GpuBackendError> {
+        if !self.poll_body_states(backend)? {
+            return Ok(vec![None; handles.len()]);
+        }
+        Ok(handles
+            .iter()
+            .map(|handle| self.body_state(env, *handle))
+            .collect())
+    }
+
+    /// Reads the pose and velocity of every body with a GPU slot, grouped by
+    /// environment.
+    ///
+    /// Returns one (possibly empty) list per environment, empty until the first
+    /// snapshot arrives.
+    pub fn read_all_body_states(
+        &mut self,
+        backend: &GpuBackend,
+    ) -> Result<Vec<Vec<(RigidBodyHandle, RbdBodyState)>>, GpuBackendError> {
+        let ready = self.poll_body_states(backend)?;
+        Ok((0..self.rbd_envs.len())
+            .map(|env| {
+                if !ready {
+                    return Vec::new();
+                }
+                self.rbd_envs[env]
+                    .bodies
+                    .iter()
+                    .filter_map(|(handle, _)| Some((handle, self.body_state(env, handle)?)))
+                    .collect()
+            })
+            .collect())
+    }
+
+    // ── Joint wrench readback ───────────────────────────────────────────
+    //
+    // Non-blocking, like the rigid-body readback (see
+    // [`RbdState::poll_joint_wrenches`]). Call once per frame after `simulate`.
+
+    /// Reads the reaction wrench of the given impulse joints of environment `env`.
+    ///
+    /// This is the wrench applied by the joint on its second body, with the torque
+    /// taken about the second anchor, expressed in the joint frame attached to the
+    /// first body. Joints attached to a multibody link are solved by the
+    /// multibody solver and reported the same way. The result is in the same
+    /// order as `handles`, with `None` for every joint until the first snapshot
+    /// arrives.
+    pub fn read_impulse_joint_wrenches(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handles: &[ImpulseJointHandle],
+    ) -> Result<Vec<Option<RbdJointWrench>>, GpuBackendError> {
+        if !self.poll_joint_wrenches(backend)? {
+            return Ok(vec![None; handles.len()]);
+        }
+        let Some(rbd) = self.rbd.as_ref() else {
+            return Ok(vec![None; handles.len()]);
+        };
+        let gpu_handles = rbd.joints().handles(env as u32);
+        let mb_handles = rbd.multibodies().impulse_joint_handles(env as u32);
+        Ok(handles
+            .iter()
+            .map(|handle| {
+                let wrench = match gpu_handles.iter().position(|h| h == handle) {
+                    Some(joint_id) => rbd.impulse_joint_wrench(env as u32, joint_id as u32)?,
+                    None => {
+                        let joint_id = mb_handles.iter().position(|h| h == handle)?;
+                        rbd.multibody_impulse_joint_wrench(env as u32, joint_id as u32)?
+                    }
+                };
+                Some(RbdJointWrench {
+                    force: wrench.linear,
+                    torque: wrench.angular,
+                })
+            })
+            .collect())
+    }
+
+    /// Reads the wrench of the given multibody joints of environment `env`.
+    ///
+    /// This is the wrench applied by the joint on its child link, expressed in the
+    /// joint frame. Reduced coordinates produce no constraint force along the
+    /// locked axes of a multibody joint, so only the efforts of its limits, motors
+    /// and DoF couplings along its free axes are reported; the locked components
+    /// are zero. The result is in the same order as `handles`, with `None` for
+    /// every joint until the first snapshot arrives.
+    pub fn read_multibody_joint_wrenches(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handles: &[MultibodyJointHandle],
+    ) -> Result<Vec<Option<RbdJointWrench>>, GpuBackendError> {
+        if !self.poll_joint_wrenches(backend)? {
+            return Ok(vec![None; handles.len()]);
+        }
+        let Some(rbd) = self.rbd.as_ref() else {
+            return Ok(vec![None; handles.len()]);
+        };
+        let offset = rbd.batch_range(env as u32).offset;
+        let joints = &self.rbd_envs[env].multibody_joints;
+        Ok(handles
+            .iter()
+            .map(|handle| {
+                let (multibody, link_id) = joints.get(*handle)?;
+                let body = multibody.link(link_id)?.rigid_body_handle();
+                let local_id = self.rbd_gpu_id(env, body)? - offset;
+                let link = rbd.multibodies().link_of_body(env as u32, local_id)?;
+                let wrench = rbd.multibody_link_wrench(env as u32, link)?;
+                Some(RbdJointWrench {
+                    force: wrench.linear,
+                    torque: wrench.angular,
+                })
+            })
+            .collect())
+    }
+
+    /// Copies the simulated state back into the rapier worlds returned by
+    /// [`Self::rbd_world`]: body poses, velocities and sleep states, multibody joint
+    /// coordinates and velocities, and the impulses accumulated by impulse
+    /// joints.
+    ///
+    /// This is a blocking readback, meant for mixing nexus stepping with rapier's
+    /// CPU tooling (queries, serialization, debug rendering) rather than for
+    /// every frame. It does nothing while the rapier worlds have pending changes
+    /// that the GPU state doesn't reflect yet (see [`Self::finalize`]).
+    pub async fn sync_to_rapier(&mut self, backend: &GpuBackend) -> Result<(), GpuBackendError> {
+        if self.rbd_dirty {
+            return Ok(());
+        }
+        let Some(rbd) = self.rbd.as_ref() else {
+            return Ok(());
+        };
+
+        let poses: Vec<crate::rbd::math::Pose> =
+            backend.slow_read_vec(rbd.body_poses().buffer()).await?;
+        let vels: Vec<crate::rbd::Velocity> = backend
+            .slow_read_vec(rbd.body_velocities().buffer())
+            .await?;
+        let activations: Vec<crate::rbd::BodyActivation> = backend
+            .slow_read_vec(rbd.body_activations().buffer())
+            .await?;
+        for (world, rbd2gpu) in self.rbd_envs.iter_mut().zip(self.rbd2gpu.iter()) {
+            for (handle, body) in world.bodies.iter_mut() {
+                let Some(gpu_id) = rbd2gpu
+                    .get(handle.0)
+                    .filter(|r| r.gpu_id != u32::MAX)
+                    .map(|r| r.gpu_id as usize)
+                else {
+                    continue;
+                };
+                body.set_position(poses[gpu_id], false);
+                body.set_linvel(vels[gpu_id].linear, false);
+                body.set_angvel(vels[gpu_id].angular, false);
+                if body.is_dynamic() {
+                    let activation = activations[gpu_id];
+                    body.activation_mut().time_since_can_sleep = activation.time_since_can_sleep;
+                    if activation.is_sleeping() {
+                        body.sleep();
+                    } else if body.is_sleeping() {
+                        body.wake_up(false);
+                    }
+                }
+            }
+        }
+
+        rbd.multibodies()
+            .writeback_to_rapier(
+                backend,
+                self.rbd_envs
+                    .iter_mut()
+                    .map(|world| &mut world.multibody_joints),
+            )
+            .await?;
+
+        let joints = rbd.joints();
+        let constraints = joints.read_constraints(backend).await?;
+        for (batch_id, world) in self.rbd_envs.iter_mut().enumerate() {
+            joints.writeback_impulses(&constraints, batch_id as u32, &mut world.impulse_joints);
+        }
+
+        Ok(())
+    }
+
+    fn poll_body_states(&mut self, backend: &GpuBackend) -> Result<bool, GpuBackendError> {
+        // A pending rebuild reassigns every slot: the snapshot would be stale.
+        if self.rbd_dirty {
+            return Ok(false);
+        }
+        match self.rbd.as_mut() {
+            Some(rbd) => rbd.poll_body_states(backend),
+            None => Ok(false),
+        }
+    }
+
+    /// Sets the reaction force and torque norms above which the impulse joint
+    /// `handle` of environment `env` breaks.
+    ///
+    /// The joint's reaction wrench (see [`Self::read_impulse_joint_wrenches`]) is
+    /// compared to the threshold at the end of every step. A broken joint is
+    /// disabled on the GPU without rebuilding the state, and reported by
+    /// [`Self::drain_joint_break_events`]. Joints are unbreakable by default;
+    /// pass `f32::MAX` to leave either norm unbounded. Joints attached to a
+    /// multibody link never break.
+    pub fn set_impulse_joint_break_threshold(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handle: ImpulseJointHandle,
+        max_force: f32,
+        max_torque: f32,
+    ) -> Result<(), GpuBackendError> {
+        let threshold = JointBreakThreshold {
+            max_force,
+            max_torque,
+        };
+        self.rbd_joint_break_thresholds[env].insert(handle.0, threshold);
+        // Otherwise, uploaded by the next `finalize`.
+        if !self.rbd_dirty {
+            self.upload_joint_break_threshold(backend, env, handle, threshold)?;
+        }
+        Ok(())
+    }
+
+    fn upload_joint_break_threshold(
+        &mut self,
+        backend: &GpuBackend,
+        env: usize,
+        handle: ImpulseJointHandle,
+        threshold: JointBreakThreshold,
+    ) -> Result<(), GpuBackendError> {
+        let Some(rbd) = self.rbd.as_mut() else {
+            return Ok(());
+        };
+        let Some(joint_id) = rbd
+            .joints()
+            .handles(env as u32)
+            .iter()
+            .position(|h| *h == handle)
+        else {
+            return Ok(());
+        };
+        rbd.joints_mut()
+            .set_break_threshold(backend, env as u32, joint_id as u32, threshold)
+    }
+
+    fn poll_joint_wrenches(&mut self, backend: &GpuBackend) -> Result<bool, GpuBackendError> {
+        // A pending rebuild reassigns every joint: the snapshot would be stale.
+        if self.rbd_dirty {
+            return Ok(false);
+        }
+        match self.rbd.as_mut() {
+            Some(rbd) => rbd.poll_joint_wrenches(backend),
+            None => Ok(false),
+        }
+    }
+
+    fn body_state(&self, env: usize, handle: RigidBodyHandle) -> Option<RbdBodyState> {
+        let (pose, vel) = self
+            .rbd
+            .as_ref()?
+            .body_state(self.rbd_gpu_id(env, handle)?)?;
+        Some(RbdBodyState {
+            pose,
+            linvel: vel.linear,
+            angvel: vel.angular,
+        })
+    }
+
+    /// Polls the non-blocking readback of the collision, contact-force and
+    /// joint-break events.
+    ///
+    /// Call once per frame after `simulate`, then retrieve the harvested events
+    /// with [`Self::drain_collision_events`], [`Self::drain_contact_force_events`]
+    /// and [`Self::drain_joint_break_events`].
+    /// Events lag the GPU by a frame or two (see [`RbdState::poll_events`]).
+    /// Events still on the GPU when its state gets rebuilt by [`Self::finalize`]
+    /// are lost, and none are polled while such a rebuild is pending.
+    pub fn poll_events(&mut self, backend: &GpuBackend) -> Result<(), GpuBackendError> {
+        if self.rbd_dirty {
+            return Ok(());
+        }
+        match self.rbd.as_mut() {
+            Some(rbd) => rbd.poll_events(backend),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes the collision events harvested by [`Self::poll_events`], one list
+    /// per environment.
+    ///
+    /// Sensor intersections are reported with [`CollisionEventFlags::SENSOR`].
+    /// Contacts are reported only if one of the colliders has
+    /// `ActiveEvents::COLLISION_EVENTS`, but sensors always report their
+    /// intersections.
+    ///
+    /// Compound colliders are simulated as one GPU collider per part, but report
+    /// a single event per collider pair under the compound's handle: `Started`
+    /// when their first part starts touching, `Stopped` once no part touches.
+    pub fn drain_collision_events(&mut self) -> Vec<Vec<CollisionEvent>> {
+        let mut result = vec![Vec::new(); self.rbd_envs.len()];
+        let Some(rbd) = self.rbd.as_mut() else {
+            return result;
+        };
+        let events: Vec<_> = (0..self.rbd_envs.len())
+            .map(|env| rbd.drain_collision_events(env as u32))
+            .collect();
+
+        for (env, events) in events.into_iter().enumerate() {
+            if events.is_empty() {
+                continue;
+            }
+            let slots = self.collider_slots(env);
+            let handle = |slot: u32| slots.get(slot as usize).copied().flatten();
+            for event in events {
+                let (Some(h1), Some(h2)) = (handle(event.colliders.x), handle(event.colliders.y))
+                else {
+                    continue;
+                };
+                let flags = if event.flags & COLLISION_EVENT_FLAG_SENSOR != 0 {
+                    CollisionEventFlags::SENSOR
+                } else {
+                    CollisionEventFlags::empty()
+                };
+                result[env].push(if event.kind == COLLISION_EVENT_STARTED {
+                    CollisionEvent::Started(h1, h2, flags)
+                } else {
+                    CollisionEvent::Stopped(h1, h2, flags)
+                });
+            }
+        }
+        result
+    }
+
+    /// Takes the joint-break events harvested by [`Self::poll_events`], one list
+    /// per environment.
+    ///
+    /// The broken joints are also disabled in the rapier worlds, so they stay
+    /// broken if the GPU state gets rebuilt. Joints broken but not drained yet
+    /// when such a rebuild happens are restored.
+    pub fn drain_joint_break_events(&mut self) -> Vec<Vec<RbdJointBreakEvent>> {
+        let mut result = vec![Vec::new(); self.rbd_envs.len()];
+        let Some(rbd) = self.rbd.as_mut() else {
+            return result;
+        };
+        for (env, world) in self.rbd_envs.iter_mut().enumerate() {
+            let events = rbd.drain_joint_break_events(env as u32);
+            let handles = rbd.joints().handles(env as u32);
+            for event in events {
+                let Some(handle) = handles.get(event.joint_id as usize).copied() else {
+                    continue;
+                };
+                if let Some(joint) = world.impulse_joints.get_mut(handle, false) {
+                    joint.data.set_enabled(false);
+                }
+                result[env].push(RbdJointBreakEvent {
+                    joint: handle,
+                    force: event.force,
+                    torque: event.torque,
+                });
+            }
+        }
+        result
+    }
+
+    /// Takes the contact-force events harvested by [`Self::poll_events`], one
+    /// list per environment.
+    ///
+    /// A pair is reported if one of its colliders has
+    /// `ActiveEvents::CONTACT_FORCE_EVENTS` and the total contact force exceeds
+    /// its `contact_force_event_threshold`. The forces are estimated from the
+    /// normal impulses of the last solver substep. The forces of all the touching
+    /// parts of a compound collider are summed into a single event.
+    pub fn drain_contact_force_events(&mut self) -> Vec<Vec<ContactForceEvent>> {
+        let mut result = vec![Vec::new(); self.rbd_envs.len()];
+        let Some(rbd) = self.rbd.as_mut() else {
+            return result;
+        };
+        let events: Vec<_> = (0..self.rbd_envs.len())
+            .map(|env| rbd.drain_contact_force_events(env as u32))
+            .collect();
+
+        for (env, events) in events.into_iter().enumerate() {
+            if events.is_empty() {
+                continue;
+            }
+            let slots = self.collider_slots(env);
+            let handle = |slot: u32| slots.get(slot as usize).copied().flatten();
+            for event in events {
+                let (Some(h1), Some(h2)) = (handle(event.colliders.x), handle(event.colliders.y))
+                else {
+                    continue;
+                };
+                result[env].push(ContactForceEvent {
+                    collider1: h1,
+                    collider2: h2,
+                    total_force: *event.total_force,
+                    total_force_magnitude: event.total_force_magnitude,
+                    max_force_direction: *event.max_force_direction,
+                    max_force_magnitude: event.max_force_magnitude,
+                });
+            }
+        }
+        result
+    }
+
+    /// The collider handle of each batch-local GPU collider slot of environment
+    /// `env`, mirroring the layout chosen by [`Self::finalize`].
+    fn collider_slots(&self, env: usize) -> Vec<Option<ColliderHandle>> {
+        let Some(rbd) = self.rbd.as_ref() else {
+            return Vec::new();
+        };
+        let range = rbd.batch_range(env as u32);
+        let mut slots = vec![None; range.capacity as usize];
+        let world = &self.rbd_envs[env];
+        if self.rbd_reserve_per_env > 0 {
+            // Appended bodies own exactly one collider, stored at the body slot.
+            for (handle, collider) in world.colliders.iter() {
+                let Some(gpu_id) = collider
+                    .parent()
+                    .and_then(|body| self.rbd_gpu_id(env, body))
+                else {
+                    continue;
+                };
+                if let Some(slot) = slots.get_mut((gpu_id - range.offset) as usize) {
+                    *slot = Some(handle);
+                }
+            }
+        } else {
+            // `from_rapier` gives colliders consecutive slots, in iteration order,
+            // and compound colliders one slot per part.
+            let parts = world
+                .colliders
+                .iter()
+                .flat_map(|(handle, co)| std::iter::repeat_n(handle, num_shape_parts(co.shape())));
+            for (slot, handle) in slots.iter_mut().zip(parts) {
+                *slot = Some(handle);
+            }
+        }
+        slots
+    }
+
     /// Appends a new chunk of MPM particles (`O(added)`) and returns its handle.
     pub fn add_particles(
         &mut self,
@@ -841,23 +1868,28 @@ impl NexusState {
                         )
                     })
                     .collect();
-                RbdState::from_rapier(backend, &environments, self.capacities.rbd)
+                RbdState::from_rapier_cloned(
+                    backend,
+                    &environments,
+                    &self.rbd_env_templates,
+                    self.capacities.rbd,
+                )
             };
 
             // Rebuild the per-environment handle to GPU-slot maps. A handle's
             // `gpu_id` is its *body* slot, not a collider slot, since a body may
             // own several colliders. Body slots are assigned in the order
             // `from_rapier` uses (the first time each parent body is seen while
-            // iterating colliders) and are laid out env-major with stride
-            // `num_colliders_per_batch`.
-            let stride = rbd_state.num_colliders_per_batch();
+            // iterating colliders) and are laid out env-major, each environment
+            // starting at the offset of its batch range.
             for (env_idx, world) in self.rbd_envs.iter().enumerate() {
+                let offset = rbd_state.batch_range(env_idx as u32).offset;
                 let mut body_slot: std::collections::HashMap<_, u32> =
                     std::collections::HashMap::new();
                 let mut next_slot = 0u32;
                 // Not a plain loop counter: parentless colliders consume a slot
-                // without a map entry, and (on dim3) the multibody-link loop
-                // below continues the same counter.
+                // without a map entry, and the multibody-link loop below
+                // continues the same counter.
                 #[allow(clippy::explicit_counter_loop)]
                 for (_, collider) in world.colliders.iter() {
                     let Some(body_handle) = collider.parent() else {
@@ -879,7 +1911,7 @@ impl NexusState {
                         body_handle.0,
                         GpuRigidBodyRef {
                             coupling,
-                            gpu_id: env_idx as u32 * stride + slot,
+                            gpu_id: offset + slot,
                         },
                     );
                 }
@@ -887,7 +1919,6 @@ impl NexusState {
                 // Mirror `from_rapier`: append a body slot for every multibody
                 // link that no collider mapped (collider-less links), in the same
                 // multibody-link order.
-                #[cfg(feature = "dim3")]
                 for mb in world.multibody_joints.multibodies() {
                     for link in mb.links() {
                         let body_handle = link.rigid_body_handle();
@@ -905,7 +1936,7 @@ impl NexusState {
                             body_handle.0,
                             GpuRigidBodyRef {
                                 coupling,
-                                gpu_id: env_idx as u32 * stride + slot,
+                                gpu_id: offset + slot,
                             },
                         );
                     }
@@ -913,6 +1944,20 @@ impl NexusState {
             }
             self.rbd = Some(rbd_state);
             self.rbd_dirty = false;
+
+            // Slots were reassigned: re-upload the breaking thresholds.
+            for env in 0..self.rbd_envs.len() {
+                let handles = self
+                    .rbd
+                    .as_ref()
+                    .map(|rbd| rbd.joints().handles(env as u32).to_vec());
+                for handle in handles.unwrap_or_default() {
+                    let threshold = self.rbd_joint_break_thresholds[env].get(handle.0).copied();
+                    if let Some(threshold) = threshold {
+                        self.upload_joint_break_threshold(backend, env, handle, threshold)?;
+                    }
+                }
+            }
         }
 
         // MPM/rapier coupling. Boundary colliders are inserted into environment 0
@@ -970,3 +2015,186 @@ impl NexusState {
         Ok(())
     }
 }
+
+#[cfg(all(test, feature = "cpu"))]
+mod tests {
+    use super::*;
+    use crate::pipeline::NexusPipeline;
+    use crate::rapier::prelude::{ColliderBuilder, RevoluteJointBuilder, RigidBodyBuilder};
+    use crate::rbd::math::{Pose, Vector};
+    use crate::rbd::shaders::dynamics::{BODY_TYPE_DYNAMIC, BODY_TYPE_FIXED};
+
+    /// Builds `num_envs` copies of a row of four dynamic balls, in a state
+    /// built by appending bodies so they can be removed in place.
+    async fn balls_state(
+        backend: &GpuBackend,
+        num_envs: usize,
+    ) -> (NexusState, Vec<RigidBodyHandle>) {
+        let mut state = NexusState::default();
+        state.reserve_rigid_bodies(8);
+        let handles = (0..4)
+            .map(|i| {
+                let body = RigidBodyBuilder::dynamic()
+                    .translation(Vector::X * 3.0 * i as f32)
+                    .build();
+                let collider = ColliderBuilder::ball(0.5).build();
+                state.insert_rigid_body(body, collider, RbdCoupling::None)
+            })
+            .collect();
+        if num_envs > 1 {
+            state.clone_environment(0, num_envs - 1, |_| Pose::IDENTITY);
+        }
+        state.finalize(backend).await.unwrap();
+        (state, handles)
+    }
+
+    /// Checks that every body of every environment with a collider maps to a
+    /// slot of its own batch holding its own pose, and returns the GPU body
+    /// types.
+    async fn check_rbd2gpu(state: &NexusState, backend: &GpuBackend) -> Vec<u32> {
+        let rbd = state.rbd.as_ref().unwrap();
+        let poses: Vec<Pose> = backend
+            .slow_read_vec(rbd.body_poses().buffer())
+            .await
+            .unwrap();
+        let body_types: Vec<u32> = backend
+            .slow_read_vec(rbd.body_types().buffer())
+            .await
+            .unwrap();
+        for (env, world) in state.rbd_envs.iter().enumerate() {
+            for (handle, body) in world.bodies.iter() {
+                let Some(gpu_id) = state.rbd_gpu_id(env, handle) else {
+                    assert!(body.colliders().is_empty());
+                    continue;
+                };
+                let range = rbd.batch_range(env as u32);
+                assert!((range.offset..range.offset + range.bodies_len).contains(&gpu_id));
+                assert_eq!(
+                    poses[gpu_id as usize].translation,
+                    body.position().translation
+                );
+                assert_eq!(body_types[gpu_id as usize], BODY_TYPE_DYNAMIC);
+            }
+        }
+        body_types
+    }
+
+    #[futures_test::test]
+    async fn test_remove_rigid_body_in_place_single_env() {
+        let backend = GpuBackend::Cpu;
+        let (mut state, handles) = balls_state(&backend, 1).await;
+
+        // The last body is swap-removed into the slot of the second one.
+        state.remove_rigid_body(&backend, 0, handles[1]).unwrap();
+        assert!(!state.rbd_dirty);
+        assert_eq!(state.rbd_gpu_id(0, handles[3]), Some(1));
+        assert_eq!(state.rbd.as_ref().unwrap().num_active_colliders(0), 3);
+        let body_types = check_rbd2gpu(&state, &backend).await;
+        assert_eq!(body_types[3], BODY_TYPE_FIXED);
+    }
+
+    #[futures_test::test]
+    async fn test_remove_rigid_body_in_place_multiple_envs() {
+        let backend = GpuBackend::Cpu;
+        let (mut state, handles) = balls_state(&backend, 3).await;
+        // Removals free slots in place, so the batch ranges don't change.
+        let rbd = state.rbd.as_ref().unwrap();
+        let offsets: Vec<_> = (0..3).map(|b| rbd.batch_range(b).offset).collect();
+        let slot = |batch: usize, local: u32| offsets[batch] + local;
+
+        // Each environment loses a different body, without moving the others.
+        state.remove_rigid_body(&backend, 1, handles[1]).unwrap();
+        state.remove_rigid_body(&backend, 2, handles[3]).unwrap();
+        assert!(!state.rbd_dirty);
+        assert_eq!(state.rbd_gpu_id(1, handles[3]), Some(slot(1, 3)));
+        assert_eq!(state.rbd_gpu_id(2, handles[1]), Some(slot(2, 1)));
+        let body_types = check_rbd2gpu(&state, &backend).await;
+
+        // The freed slots are inert in their own batch only.
+        assert_eq!(body_types[slot(1, 1) as usize], BODY_TYPE_FIXED);
+        assert_eq!(body_types[slot(2, 3) as usize], BODY_TYPE_FIXED);
+        assert_eq!(body_types[1], BODY_TYPE_DYNAMIC);
+        assert_eq!(body_types[3], BODY_TYPE_DYNAMIC);
+
+        // Removing the collider of a body also frees its slot in place.
+        let collider = state.rbd_envs[0].bodies[handles[2]].colliders()[0];
+        state.remove_collider(&backend, 0, collider).unwrap();
+        assert!(!state.rbd_dirty);
+        assert_eq!(state.rbd_gpu_id(0, handles[2]), None);
+        let body_types = check_rbd2gpu(&state, &backend).await;
+        assert_eq!(body_types[2], BODY_TYPE_FIXED);
+        assert_eq!(body_types[slot(1, 2) as usize], BODY_TYPE_DYNAMIC);
+    }
+
+    #[futures_test::test]
+    async fn test_sync_to_rapier() {
+        let backend = GpuBackend::Cpu;
+        let mut state = NexusState::default();
+        // A ball thrown sideways, and a ball hanging from a fixed body.
+        let thrown = state.insert_rigid_body(
+            RigidBodyBuilder::dynamic()
+                .translation(Vector::X * 10.0)
+                .linvel(Vector::X)
+                .build(),
+            ColliderBuilder::ball(0.1).build(),
+            RbdCoupling::None,
+        );
+        let anchor = state.insert_rigid_body(
+            RigidBodyBuilder::fixed()
+                .translation(Vector::Y * 10.0)
+                .build(),
+            ColliderBuilder::ball(0.1).build(),
+            RbdCoupling::None,
+        );
+        let hanging = state.insert_rigid_body(
+            RigidBodyBuilder::dynamic()
+                .translation(Vector::X * 2.0 + Vector::Y * 10.0)
+                .build(),
+            ColliderBuilder::ball(0.1).build(),
+            RbdCoupling::None,
+        );
+        #[cfg(feature = "dim2")]
+        let joint = RevoluteJointBuilder::new().local_anchor1(Vector::X * 2.0);
+        #[cfg(feature = "dim3")]
+        let joint = RevoluteJointBuilder::new(Vector::Z).local_anchor1(Vector::X * 2.0);
+        let joint = state.insert_impulse_joint(anchor, hanging, joint);
+        state.clone_environment(0, 1, |_| Pose::from_translation(Vector::X * 100.0));
+
+        let mut pipeline = NexusPipeline::default();
+        for _ in 0..3 {
+            pipeline.simulate(&backend, &mut state, None).await.unwrap();
+        }
+        state.sync_to_rapier(&backend).await.unwrap();
+
+        let rbd = state.rbd.as_ref().unwrap();
+        let poses: Vec<Pose> = backend
+            .slow_read_vec(rbd.body_poses().buffer())
+            .await
+            .unwrap();
+        for (env, world) in state.rbd_envs.iter().enumerate() {
+            let shift = env as f32 * 100.0;
+            for (handle, body) in world.bodies.iter() {
+                let gpu_id = state.rbd_gpu_id(env, handle).unwrap();
+                assert_eq!(
+                    body.position().translation,
+                    poses[gpu_id as usize].translation
+                );
+            }
+
+            // The thrown ball falls while keeping its horizontal velocity.
+            let body = &world.bodies[thrown];
+            assert!(body.position().translation.x > 10.0 + shift);
+            assert!(body.position().translation.y < 0.0);
+            assert!((body.linvel().x - 1.0).abs() < 1.0e-4);
+            assert!(body.linvel().y < 0.0);
+
+            // The fixed body doesn't move, and the joint holds the hanging ball
+            // in place.
+            let anchor_pos = Vector::X * shift + Vector::Y * 10.0;
+            assert_eq!(world.bodies[anchor].position().translation, anchor_pos);
+            let hanging_pos = world.bodies[hanging].position().translation;
+            assert!((hanging_pos - anchor_pos - Vector::X * 2.0).length() < 1.0e-2);
+            assert!(world.impulse_joints.get(joint).unwrap().impulses[1] != 0.0);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Collision events and contact-force events.
+
+use crate::queries::{GpuColliderMaterial, GpuIndexedContact};
+use crate::shaders::broad_phase::{
+    CollisionEvent, ContactForceEvent, GpuEmitCollisionEvents, GpuEmitContactForceEvents,
+    GpuResetTouchingPairs, GpuTouchingPairs,
+};
+use crate::shaders::dynamics::{RbdSimParams, TwoBodyConstraint};
+use crate::shaders::queries::{COLLIDER_FLAG_COLLISION_EVENTS, COLLIDER_FLAG_CONTACT_FORCE_EVENTS};
+use crate::shaders::utils::BatchIndices;
+use glamx::UVec2;
+use khal::backend::{Backend, GpuBackend, GpuBackendError, GpuPass};
+use khal::{BufferUsages, Shader};
+use vortx::tensor::Tensor;
+
+/// GPU shader emitting the collision events of touching colliders, and the
+/// contact-force events.
+///
+/// The collision events of sensors are emitted by [`GpuSensors`](super::GpuSensors).
+#[derive(Shader)]
+pub struct GpuCollisionEvents {
+    reset_touching_pairs: GpuResetTouchingPairs,
+    touching_pairs: GpuTouchingPairs,
+    emit_collision_events: GpuEmitCollisionEvents,
+    emit_contact_force_events: GpuEmitContactForceEvents,
+}
+
+/// GPU-resident state of the collision and contact-force events.
+///
+/// The touching pairs are double-buffered: each step's pairs are compared with
+/// the previous step's to emit the collision-started/stopped events. The events
+/// of each batch go to ring buffers of [`Self::events_per_batch`] entries,
+/// indexed by ever-growing per-batch counters.
+pub struct CollisionEventsState {
+    num_batches: u32,
+    pairs_per_batch: u32,
+    events_per_batch: u32,
+    /// Whether any collider has collision events. The touching-pair passes are
+    /// skipped otherwise.
+    contact_events_enabled: bool,
+    /// Whether any collider has contact-force events. The contact-force pass is
+    /// skipped otherwise.
+    contact_force_events_enabled: bool,
+    touching_pairs: Tensor<UVec2>,
+    touching_pairs_len: Tensor<u32>,
+    old_touching_pairs: Tensor<UVec2>,
+    old_touching_pairs_len: Tensor<u32>,
+    events: Tensor<CollisionEvent>,
+    events_len: Tensor<u32>,
+    force_events: Tensor<ContactForceEvent>,
+    force_events_len: Tensor<u32>,
+}
+
+impl CollisionEventsState {
+    /// Allocates the event buffers of `num_batches` batches, each tracking up to
+    /// `pairs_per_batch` touching pairs and holding up to `events_per_batch`
+    /// unread events of each kind.
+    ///
+    /// `collider_flags` is the union of the `COLLIDER_FLAG_*` of all the colliders.
+    pub fn new(
+        backend: &GpuBackend,
+        num_batches: u32,
+        pairs_per_batch: u32,
+        events_per_batch: u32,
+        collider_flags: u32,
+    ) -> Result<Self, GpuBackendError> {
+        let storage = BufferUsages::STORAGE;
+        let writable = BufferUsages::STORAGE | BufferUsages::COPY_DST;
+        let readable = BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+        // Zeroed: the first step compares against an empty set of touching pairs.
+        let zeros = vec![0u32; num_batches as usize];
+        let mut result = Self {
+            num_batches,
+            pairs_per_batch,
+            events_per_batch,
+            contact_events_enabled: false,
+            contact_force_events_enabled: false,
+            touching_pairs: Tensor::vector_uninit(backend, pairs_per_batch * num_batches, storage)?,
+            touching_pairs_len: Tensor::vector(backend, &zeros, writable)?,
+            old_touching_pairs: Tensor::vector_uninit(
+                backend,
+                pairs_per_batch * num_batches,
+                storage,
+            )?,
+            old_touching_pairs_len: Tensor::vector(backend, &zeros, writable)?,
+            events: Tensor::vector_uninit(backend, events_per_batch * num_batches, readable)?,
+            events_len: Tensor::vector(backend, &zeros, readable)?,
+            force_events: Tensor::vector_uninit(backend, events_per_batch * num_batches, readable)?,
+            force_events_len: Tensor::vector(backend, &zeros, readable)?,
+        };
+        result.enable(collider_flags);
+        Ok(result)
+    }
+
+    /// Maximum number of touching pairs tracked per batch.
+    pub fn pairs_per_batch(&self) -> u32 {
+        self.pairs_per_batch
+    }
+
+    /// Size of the per-batch ring buffers of events.
+    pub fn events_per_batch(&self) -> u32 {
+        self.events_per_batch
+    }
+
+    /// Whether any collider has collision events.
+    pub fn has_contact_events(&self) -> bool {
+        self.contact_events_enabled
+    }
+
+    /// Whether any collider has contact-force events.
+    pub fn has_contact_force_events(&self) -> bool {
+        self.contact_force_events_enabled
+    }
+
+    /// Enables the passes needed by a collider with the given `COLLIDER_FLAG_*`.
+    pub(crate) fn enable(&mut self, collider_flags: u32) {
+        self.contact_events_enabled |= collider_flags & COLLIDER_FLAG_COLLISION_EVENTS != 0;
+        self.contact_force_events_enabled |=
+            collider_flags & COLLIDER_FLAG_CONTACT_FORCE_EVENTS != 0;
+    }
+
+    /// The per-batch ring buffers of collision events.
+    pub(crate) fn collision_events(&self) -> &Tensor<CollisionEvent> {
+        &self.events
+    }
+
+    /// The per-batch number of collision events emitted since the last
+    /// [`Self::reset`].
+    pub(crate) fn collision_events_len(&self) -> &Tensor<u32> {
+        &self.events_len
+    }
+
+    /// The collision-event ring buffers and their counters, for the passes
+    /// appending to them.
+    pub(crate) fn collision_events_mut(
+        &mut self,
+    ) -> (&mut Tensor<CollisionEvent>, &mut Tensor<u32>) {
+        (&mut self.events, &mut self.events_len)
+    }
+
+    /// The per-batch ring buffers of contact-force events.
+    pub(crate) fn contact_force_events(&self) -> &Tensor<ContactForceEvent> {
+        &self.force_events
+    }
+
+    /// The per-batch number of contact-force events emitted since the last
+    /// [`Self::reset`].
+    pub(crate) fn contact_force_events_len(&self) -> &Tensor<u32> {
+        &self.force_events_len
+    }
+
+    /// Forgets the touching pairs and the events emitted so far.
+    ///
+    /// Needed whenever colliders move to a different slot: the recorded pairs
+    /// refer to the old slots.
+    pub(crate) fn reset(&mut self, backend: &GpuBackend) -> Result<(), GpuBackendError> {
+        let zeros = vec![0u32; self.num_batches as usize];
+        backend.write_buffer(self.old_touching_pairs_len.buffer_mut(), 0, &zeros)?;
+        backend.write_buffer(self.touching_pairs_len.buffer_mut(), 0, &zeros)?;
+        backend.write_buffer(self.events_len.buffer_mut(), 0, &zeros)?;
+        backend.write_buffer(self.force_events_len.buffer_mut(), 0, &zeros)?;
+        Ok(())
+    }
+}
+
+impl GpuCollisionEvents {
+    /// Collects the pairs of the narrow-phase `contacts` involving a collider with
+    /// collision events and appends the resulting collision events.
+    ///
+    /// No-op if no collider has collision events.
+    pub fn dispatch_contact_events(
+        &self,
+        pass: &mut GpuPass,
+        state: &mut CollisionEventsState,
+        contacts: &Tensor<GpuIndexedContact>,
+        contacts_len: &Tensor<u32>,
+        contacts_indirect: &Tensor<[u32; 3]>,
+        collider_materials: &Tensor<GpuColliderMaterial>,
+        batch_indices: &Tensor<BatchIndices>,
+    ) -> Result<(), GpuBackendError> {
+        if !state.contact_events_enabled {
+            return Ok(());
+        }
+
+        let num_batches = state.num_batches;
+        self.reset_touching_pairs
+            .call(pass, [num_batches, 1, 1], &mut state.touching_pairs_len)?;
+        self.touching_pairs.call(
+            pass,
+            contacts_indirect,
+            contacts,
+            contacts_len,
+            collider_materials,
+            &mut state.touching_pairs,
+            &mut state.touching_pairs_len,
+            batch_indices,
+        )?;
+        // Runs even when no pair is touching this step, so the pairs that stopped
+        // touching get their events.
+        self.emit_collision_events.call(
+            pass,
+            [state.pairs_per_batch, num_batches, 1],
+            &state.touching_pairs,
+            &state.touching_pairs_len,
+            &state.old_touching_pairs,
+            &state.old_touching_pairs_len,
+            collider_materials,
+            &mut state.events,
+            &mut state.events_len,
+            batch_indices,
+        )?;
+
+        // This step's touching pairs are the next step's old ones.
+        std::mem::swap(&mut state.touching_pairs, &mut state.old_touching_pairs);
+        std::mem::swap(
+            &mut state.touching_pairs_len,
+            &mut state.old_touching_pairs_len,
+        );
+
+        Ok(())
+    }
+
+    /// Appends the contact-force events of the solved contact `constraints`.
+    ///
+    /// No-op if no collider has contact-force events.
+    pub fn dispatch_contact_force_events(
+        &self,
+        pass: &mut GpuPass,
+        state: &mut CollisionEventsState,
+        contacts: &Tensor<GpuIndexedContact>,
+        contacts_len: &Tensor<u32>,
+        contacts_indirect: &Tensor<[u32; 3]>,
+        constraints: &Tensor<TwoBodyConstraint>,
+        collider_materials: &Tensor<GpuColliderMaterial>,
+        sim_params: &Tensor<RbdSimParams>,
+        batch_indices: &Tensor<BatchIndices>,
+    ) -> Result<(), GpuBackendError> {
+        if !state.contact_force_events_enabled {
+            return Ok(());
+        }
+
+        self.emit_contact_force_events.call(
+            pass,
+            contacts_indirect,
+            contacts,
+            contacts_len,
+            constraints,
+            collider_materials,
+            sim_params,
+            &mut state.force_events,
+            &mut state.force_events_len,
+            batch_indices,
+        )?;
+
+        Ok(())
+    }
+}
@@ -7,10 +7,12 @@ use crate::math::Pose;
 use crate::shaders::PaddedVector;
 use crate::shaders::bounding_volumes::Aabb;
 use crate::shaders::broad_phase::{
-    CollisionPair, GpuBfComputeAabbs, GpuBfFindPairs, GpuLbvhBuild, GpuLbvhComputeDomain,
-    GpuLbvhComputeMorton, GpuLbvhFindCollisionPairs, GpuLbvhInitDispatch, GpuLbvhRefitInternal,
-    GpuLbvhRefitLeaves, GpuLbvhResetCollisionPairs, LbvhNode,
+    CollisionPair, GpuBfComputeAabbs, GpuBfFindPairs, GpuBfSweepAabbs, GpuLbvhBuild,
+    GpuLbvhComputeDomain, GpuLbvhComputeMorton, GpuLbvhFindCollisionPairs, GpuLbvhInitDispatch,
+    GpuLbvhRefitInternal, GpuLbvhRefitLeaves, GpuLbvhResetCollisionPairs, GpuLbvhSweepLeaves,
+    LbvhNode,
 };
+use crate::shaders::dynamics::CcdSweep;
 use crate::shaders::shapes::Shape;
 use crate::utils::{RadixSort, RadixSortWorkspace};
 use khal::backend::{
@@ -29,6 +31,7 @@ pub struct GpuLbvh {
     compute_morton: GpuLbvhComputeMorton,
     build: GpuLbvhBuild,
     refit_leaves: GpuLbvhRefitLeaves,
+    sweep_leaves: GpuLbvhSweepLeaves,
     refit_internal: GpuLbvhRefitInternal,
     reset_collision_pairs: GpuLbvhResetCollisionPairs,
     find_collision_pairs: GpuLbvhFindCollisionPairs,
@@ -36,6 +39,7 @@ pub struct GpuLbvh {
     // Kernels for brute-force broad-phase for small scenes
     // (typically, small scenes but many batches).
     bf_compute_aabbs: GpuBfComputeAabbs,
+    bf_sweep_aabbs: GpuBfSweepAabbs,
     bf_find_pairs: GpuBfFindPairs,
 }
 
@@ -46,11 +50,11 @@ pub struct LbvhState {
     buffer_usages: BufferUsages,
     domain_aabb: Tensor<Aabb>,
     n_sort: Tensor<u32>,
-    /// Per-batch active key count currently uploaded to `n_sort`, as
-    /// `(active_per_batch, num_batches)`. `None` forces a re-upload (e.g. after
-    /// a resize re-seeds `n_sort` with the capacity). Avoids rewriting `n_sort`
-    /// every frame when the live collider count hasn't changed.
-    n_sort_active: Option<(u32, u32)>,
+    /// Per-batch active key counts currently uploaded to `n_sort`. `None`
+    /// forces a re-upload (e.g. after a resize re-seeds `n_sort` with the
+    /// capacity). Avoids rewriting `n_sort` every frame when the live collider
+    /// counts haven't changed.
+    n_sort_active: Option<Vec<u32>>,
     unsorted_morton_keys: Tensor<u32>,
     sorted_morton_keys: Tensor<u32>,
     unsorted_colliders: Tensor<u32>,
@@ -100,14 +104,17 @@ impl LbvhState {
         &self.sorted_colliders
     }
 
-    fn resize_buffers(&mut self, backend: &GpuBackend, colliders_len: u32, num_batches: u32) {
+    fn resize_buffers(&mut self, backend: &GpuBackend, batch_capacity: u32, num_batches: u32) {
         if (self.domain_aabb.len() as u32) < num_batches {
             self.domain_aabb =
                 Tensor::vector_uninit(backend, num_batches, self.buffer_usages).unwrap();
         }
 
-        // NOTE: colliders_len is the total colliders count, already taking all batches into account.
-        if (self.tree.len() as u32) < 2 * colliders_len {
+        // The radix sort derives its per-batch stride from the key buffer length,
+        // so the sort buffers are sized exactly (not just grown) to the capacity
+        // of every batch.
+        let colliders_len = batch_capacity * num_batches;
+        if self.unsorted_morton_keys.len() as u32 != colliders_len {
             self.unsorted_morton_keys =
                 Tensor::vector_uninit(backend, colliders_len, self.buffer_usages).unwrap();
             self.sorted_morton_keys =
@@ -115,10 +122,8 @@ impl LbvhState {
             // Use per-batch LOCAL indices so that after sorting, each batch's
             // sorted_colliders slice contains local indices usable with per-batch
             // Slice offsets in the shaders.
-            let colliders_per_batch = colliders_len / num_batches;
-            let unsorted_colliders: Vec<_> = (0..num_batches)
-                .flat_map(|_| 0..colliders_per_batch)
-                .collect();
+            let unsorted_colliders: Vec<_> =
+                (0..num_batches).flat_map(|_| 0..batch_capacity).collect();
             self.unsorted_colliders =
                 Tensor::vector(backend, &unsorted_colliders, self.buffer_usages).unwrap();
             self.sorted_colliders =
@@ -126,21 +131,20 @@ impl LbvhState {
             self.tree =
                 Tensor::vector_uninit(backend, 2 * colliders_len, self.buffer_usages).unwrap();
 
-            // FIXME: this doesn’t account for batches having mismatched numbers of colliders.
-            // n_sort is a per-batch vector: each element is the per-batch *active*
-            // key count, rewritten by `update_tree` (hence COPY_DST) when the live
-            // collider count changes, so dynamic body insertion/removal is reflected
+            // n_sort is a per-batch vector: each element is the *active* key count
+            // of its batch, rewritten by `update_tree` (hence COPY_DST) when the live
+            // collider counts change, so dynamic body insertion/removal is reflected
             // without a resize. Seeded here with the capacity; the next `update_tree`
-            // narrows it to the live count (the invalidated cache below forces it).
-            let n_sort_data = vec![colliders_per_batch; num_batches as usize];
+            // narrows it to the live counts (the invalidated cache below forces it).
+            let n_sort_data = vec![batch_capacity; num_batches as usize];
             self.n_sort = Tensor::vector(
                 backend,
                 &n_sort_data,
                 self.buffer_usages | BufferUsages::COPY_DST,
             )
             .unwrap();
-            // The new buffer holds the capacity, not the active count — force the
-            // next `update_tree` to upload the real per-batch live count.
+            // The new buffer holds the capacity, not the active counts — force the
+            // next `update_tree` to upload the real per-batch live counts.
             self.n_sort_active = None;
         }
     }
@@ -167,39 +171,42 @@ impl Lbvh {
     /// Rebuilds the LBVH tree from current collider poses and shapes.
     ///
     /// Should be called each frame before [`find_pairs`](Self::find_pairs) if colliders have moved.
+    ///
+    /// If `sweeps` is set, the leaf AABBs are enlarged by the per-collider motion
+    /// of the continuous collision detection.
     pub fn update_tree(
         &self,
         backend: &GpuBackend,
         encoder: &mut GpuEncoder,
         state: &mut LbvhState,
-        colliders_len: u32,
-        active_per_batch: u32,
-        num_batches: u32,
+        batch_capacity: u32,
+        active_per_batch: &[u32],
         poses: &Tensor<Pose>,
         vertex_buffers: &Tensor<PaddedVector>,
         shapes: &Tensor<Shape>,
+        sweeps: Option<&Tensor<CcdSweep>>,
         batch_indices: &Tensor<crate::shaders::utils::BatchIndices>,
         mut timestamps: Option<&mut GpuTimestamps>,
     ) -> Result<(), GpuBackendError> {
-        // `colliders_len` is the full per-batch *capacity* × batches (it sizes the
-        // buffers and is the per-batch stride). The sort and tree build, however,
-        // only need to touch the live colliders, so every per-collider dispatch
-        // below — and the radix sort's `n_sort` — uses `active_per_batch`. The
-        // padding slots `[active_per_batch, capacity)` are never sorted or built.
-        state.resize_buffers(backend, colliders_len, num_batches);
+        // The LBVH buffers hold `batch_capacity` slots per batch. The sort and tree
+        // build, however, only need to touch the live colliders, so every
+        // per-collider dispatch below is as wide as the largest batch, and the
+        // radix sort's `n_sort` holds the live count of every batch. The padding
+        // slots of a batch are never sorted or built.
+        let num_batches = active_per_batch.len() as u32;
+        state.resize_buffers(backend, batch_capacity, num_batches);
 
         // Tell the radix sort how many keys are actually live per batch (it sizes
         // its indirect dispatch from `max(n_sort)` and sorts only that many,
-        // leaving padding untouched). The live count only changes when bodies are
+        // leaving padding untouched). The live counts only change when bodies are
         // added/removed (or after a resize re-seeds the buffer), so skip the upload
-        // when it's unchanged rather than rewriting `n_sort` every frame.
-        if state.n_sort_active != Some((active_per_batch, num_batches)) {
-            let n_sort_data = vec![active_per_batch; num_batches as usize];
-            backend.write_buffer(state.n_sort.buffer_mut(), 0, &n_sort_data)?;
-            state.n_sort_active = Some((active_per_batch, num_batches));
+        // when they're unchanged rather than rewriting `n_sort` every frame.
+        if state.n_sort_active.as_deref() != Some(active_per_batch) {
+            backend.write_buffer(state.n_sort.buffer_mut(), 0, active_per_batch)?;
+            state.n_sort_active = Some(active_per_batch.to_vec());
         }
 
-        let colliders_per_batch = active_per_batch;
+        let colliders_per_batch = active_per_batch.iter().copied().max().unwrap_or(0);
 
         let mut pass = encoder.begin_pass("[RBD] lbvh-compute-domain", timestamps.as_deref_mut());
         self.shaders.compute_domain.call(
@@ -258,6 +265,15 @@ impl Lbvh {
             batch_indices,
             vertex_buffers,
         )?;
+        if let Some(sweeps) = sweeps {
+            self.shaders.sweep_leaves.call(
+                &mut pass,
+                [colliders_per_batch, num_batches, 1],
+                sweeps,
+                &mut state.tree,
+                batch_indices,
+            )?;
+        }
         drop(pass);
 
         let mut pass = encoder.begin_pass("[RBD] lbvh-refit-internal", timestamps);
@@ -280,7 +296,7 @@ impl Lbvh {
         &self,
         pass: &mut GpuPass,
         state: &mut LbvhState,
-        active_per_batch: u32,
+        max_active_per_batch: u32,
         num_batches: u32,
         batch_indices: &Tensor<crate::shaders::utils::BatchIndices>,
         collision_pairs: &mut Tensor<CollisionPair>,
@@ -289,8 +305,9 @@ impl Lbvh {
         collision_groups: &Tensor<crate::rapier::geometry::InteractionGroups>,
         pair_filter: &Tensor<[u32; 2]>,
     ) -> Result<(), GpuBackendError> {
-        // One thread per live collider (leaf); padding slots aren't in the tree.
-        let colliders_per_batch = active_per_batch;
+        // One thread per live collider (leaf) of the largest batch; padding slots
+        // aren't in the tree.
+        let colliders_per_batch = max_active_per_batch;
 
         self.shaders
             .reset_collision_pairs
@@ -318,18 +335,21 @@ impl Lbvh {
     /// [`Self::find_pairs`], used when each batch holds at most
     /// [`BRUTE_FORCE_MAX_COLLIDERS`] colliders. One AABB pass and one
     /// all-pairs pass emit the same pair set as the whole tree pipeline.
+    ///
+    /// If `sweeps` is set, the AABBs are enlarged by the per-collider motion of
+    /// the continuous collision detection.
     #[allow(clippy::too_many_arguments)]
     pub fn brute_force_pairs(
         &self,
         backend: &GpuBackend,
         pass: &mut GpuPass,
         state: &mut LbvhState,
-        colliders_len: u32,
-        active_per_batch: u32,
+        batch_capacity: u32,
         num_batches: u32,
         poses: &Tensor<Pose>,
         vertex_buffers: &Tensor<PaddedVector>,
         shapes: &Tensor<Shape>,
+        sweeps: Option<&Tensor<CcdSweep>>,
         batch_indices: &Tensor<crate::shaders::utils::BatchIndices>,
         collision_pairs: &mut Tensor<CollisionPair>,
         collision_pairs_len: &mut Tensor<u32>,
@@ -337,23 +357,34 @@ impl Lbvh {
         collision_groups: &Tensor<crate::rapier::geometry::InteractionGroups>,
         pair_filter: &Tensor<[u32; 2]>,
     ) -> Result<(), GpuBackendError> {
-        state.resize_bf_buffers(backend, colliders_len);
+        // The kernels decompose their thread index on the batch capacity, and skip
+        // the padding slots of every batch.
+        state.resize_bf_buffers(backend, batch_capacity * num_batches);
 
         self.shaders.bf_compute_aabbs.call(
             pass,
-            [active_per_batch * num_batches, 1, 1],
+            [batch_capacity * num_batches, 1, 1],
             poses,
             shapes,
             &mut state.aabbs,
             batch_indices,
             vertex_buffers,
         )?;
+        if let Some(sweeps) = sweeps {
+            self.shaders.bf_sweep_aabbs.call(
+                pass,
+                [batch_capacity * num_batches, 1, 1],
+                sweeps,
+                &mut state.aabbs,
+                batch_indices,
+            )?;
+        }
         self.shaders
             .reset_collision_pairs
             .call(pass, [num_batches, 1, 1], collision_pairs_len)?;
         self.shaders.bf_find_pairs.call(
             pass,
-            [active_per_batch * active_per_batch * num_batches, 1, 1],
+            [batch_capacity * batch_capacity * num_batches, 1, 1],
             &state.aabbs,
             collision_pairs,
             collision_pairs_len,
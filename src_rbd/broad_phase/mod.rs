@@ -1,7 +1,11 @@
 //! GPU-accelerated broad-phase collision detection (LBVH).
 
+mod collision_events;
 mod lbvh;
 mod narrow_phase;
+mod sensors;
 
+pub use collision_events::*;
 pub use lbvh::*;
 pub use narrow_phase::*;
+pub use sensors::*;
@@ -0,0 +1,180 @@
+//! Sensor colliders: intersection detection.
+
+use super::CollisionEventsState;
+use crate::math::Pose;
+use crate::shaders::PaddedVector;
+use crate::shaders::broad_phase::{
+    CollisionPair, GpuEmitCollisionEvents, GpuResetSensors, GpuSensorIntersections, GpuSensorPairs,
+};
+use crate::shaders::shapes::Shape;
+use glamx::UVec2;
+use khal::backend::{Backend, GpuBackend, GpuBackendError, GpuPass};
+use khal::{BufferUsages, Shader};
+use vortx::tensor::Tensor;
+
+/// GPU shader detecting the intersections of sensor colliders.
+#[derive(Shader)]
+pub struct GpuSensors {
+    reset_sensors: GpuResetSensors,
+    sensor_pairs: GpuSensorPairs,
+    sensor_intersections: GpuSensorIntersections,
+    emit_collision_events: GpuEmitCollisionEvents,
+}
+
+/// GPU-resident state of the sensor intersections.
+///
+/// The intersecting pairs are double-buffered: each step's pairs are compared
+/// with the previous step's to emit the collision-started/stopped events of
+/// [`CollisionEventsState`].
+pub struct SensorState {
+    num_batches: u32,
+    pairs_per_batch: u32,
+    /// Whether any collider is a sensor. The sensor passes are skipped otherwise.
+    enabled: bool,
+    sensor_pairs: Tensor<UVec2>,
+    sensor_pairs_len: Tensor<u32>,
+    intersections: Tensor<UVec2>,
+    intersections_len: Tensor<u32>,
+    old_intersections: Tensor<UVec2>,
+    old_intersections_len: Tensor<u32>,
+}
+
+impl SensorState {
+    /// Allocates the sensor buffers of `num_batches` batches, each holding up to
+    /// `pairs_per_batch` sensor pairs.
+    pub fn new(
+        backend: &GpuBackend,
+        num_batches: u32,
+        pairs_per_batch: u32,
+        enabled: bool,
+    ) -> Result<Self, GpuBackendError> {
+        let storage = BufferUsages::STORAGE;
+        let writable = BufferUsages::STORAGE | BufferUsages::COPY_DST;
+        // Zeroed: the first step compares against an empty set of intersections.
+        let zeros = vec![0u32; num_batches as usize];
+        Ok(Self {
+            num_batches,
+            pairs_per_batch,
+            enabled,
+            sensor_pairs: Tensor::vector_uninit(backend, pairs_per_batch * num_batches, storage)?,
+            sensor_pairs_len: Tensor::vector_uninit(backend, num_batches, storage)?,
+            intersections: Tensor::vector_uninit(backend, pairs_per_batch * num_batches, storage)?,
+            intersections_len: Tensor::vector(backend, &zeros, writable)?,
+            old_intersections: Tensor::vector_uninit(
+                backend,
+                pairs_per_batch * num_batches,
+                storage,
+            )?,
+            old_intersections_len: Tensor::vector(backend, &zeros, writable)?,
+        })
+    }
+
+    /// Maximum number of sensor pairs tracked per batch.
+    pub fn pairs_per_batch(&self) -> u32 {
+        self.pairs_per_batch
+    }
+
+    /// Whether the sensor passes run, i.e., whether any collider is a sensor.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables the sensor passes, after a sensor collider was added.
+    pub(crate) fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// Forgets the intersecting pairs.
+    ///
+    /// Needed whenever colliders move to a different slot: the recorded pairs
+    /// refer to the old slots.
+    pub(crate) fn reset(&mut self, backend: &GpuBackend) -> Result<(), GpuBackendError> {
+        let zeros = vec![0u32; self.num_batches as usize];
+        backend.write_buffer(self.old_intersections_len.buffer_mut(), 0, &zeros)?;
+        backend.write_buffer(self.intersections_len.buffer_mut(), 0, &zeros)?;
+        Ok(())
+    }
+}
+
+impl GpuSensors {
+    /// Detects the intersections of the sensor pairs among the broad-phase
+    /// `collision_pairs` and appends the resulting collision events to `events`.
+    ///
+    /// No-op if no collider is a sensor.
+    pub fn dispatch(
+        &self,
+        pass: &mut GpuPass,
+        state: &mut SensorState,
+        events: &mut CollisionEventsState,
+        poses: &Tensor<Pose>,
+        shapes: &Tensor<Shape>,
+        vertices: &Tensor<PaddedVector>,
+        indices: &Tensor<u32>,
+        collision_pairs: &Tensor<CollisionPair>,
+        collision_pairs_len: &Tensor<u32>,
+        collision_pairs_indirect: &Tensor<[u32; 3]>,
+        batch_indices: &Tensor<crate::shaders::utils::BatchIndices>,
+        collider_parent: &Tensor<u32>,
+        collider_materials: &Tensor<crate::shaders::queries::ColliderMaterial>,
+    ) -> Result<(), GpuBackendError> {
+        if !state.enabled {
+            return Ok(());
+        }
+
+        let num_batches = state.num_batches;
+        self.reset_sensors.call(
+            pass,
+            [num_batches, 1, 1],
+            &mut state.sensor_pairs_len,
+            &mut state.intersections_len,
+        )?;
+        self.sensor_pairs.call(
+            pass,
+            collision_pairs_indirect,
+            collision_pairs,
+            collision_pairs_len,
+            collider_parent,
+            collider_materials,
+            &mut state.sensor_pairs,
+            &mut state.sensor_pairs_len,
+            batch_indices,
+        )?;
+        // The last two passes run even when no pair was found this step, so the
+        // pairs that stopped intersecting get their events.
+        self.sensor_intersections.call(
+            pass,
+            [state.pairs_per_batch, num_batches, 1],
+            &state.sensor_pairs,
+            &state.sensor_pairs_len,
+            poses,
+            shapes,
+            vertices,
+            indices,
+            &mut state.intersections,
+            &mut state.intersections_len,
+            batch_indices,
+        )?;
+        let (events, events_len) = events.collision_events_mut();
+        self.emit_collision_events.call(
+            pass,
+            [state.pairs_per_batch, num_batches, 1],
+            &state.intersections,
+            &state.intersections_len,
+            &state.old_intersections,
+            &state.old_intersections_len,
+            collider_materials,
+            events,
+            events_len,
+            batch_indices,
+        )?;
+
+        // This step's intersections are the next step's old ones.
+        std::mem::swap(&mut state.intersections, &mut state.old_intersections);
+        std::mem::swap(
+            &mut state.intersections_len,
+            &mut state.old_intersections_len,
+        );
+
+        Ok(())
+    }
+}
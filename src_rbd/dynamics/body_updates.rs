@@ -0,0 +1,59 @@
+//! Sparse runtime writes to the rigid-body state.
+
+use crate::math::Pose;
+use crate::shaders::dynamics::{
+    BodyActivation, BodyParams, BodyUpdate, Force, GpuApplyBodyUpdates, LocalMassProperties,
+    Velocity,
+};
+use khal::Shader;
+use khal::backend::{GpuBackendError, GpuPass};
+use vortx::tensor::Tensor;
+
+/// GPU shader scattering the queued per-body updates (pose, velocity, impulse,
+/// force) into the body buffers, waking up the updated bodies.
+#[derive(Shader)]
+pub struct GpuBodyUpdates {
+    /// Compute pipeline for the scatter kernel.
+    apply_updates: GpuApplyBodyUpdates,
+}
+
+impl GpuBodyUpdates {
+    /// Dispatches the scatter kernel over the first `num_updates` entries of `updates`.
+    ///
+    /// Must run before the world mass properties and collider poses are refreshed.
+    pub fn dispatch(
+        &self,
+        pass: &mut GpuPass,
+        updates: &Tensor<BodyUpdate>,
+        body_poses: &mut Tensor<Pose>,
+        vels: &mut Tensor<Velocity>,
+        kinematic_targets: &mut Tensor<Pose>,
+        forces: &mut Tensor<Force>,
+        local_mprops: &Tensor<LocalMassProperties>,
+        body_params: &Tensor<BodyParams>,
+        activations: &mut Tensor<BodyActivation>,
+        body_types: &Tensor<u32>,
+        num_updates_uniform: &Tensor<u32>,
+        num_updates: u32,
+    ) -> Result<(), GpuBackendError> {
+        if num_updates == 0 {
+            return Ok(());
+        }
+
+        self.apply_updates.call(
+            pass,
+            [num_updates, 1, 1],
+            updates,
+            body_poses,
+            vels,
+            kinematic_targets,
+            forces,
+            local_mprops,
+            body_params,
+            activations,
+            body_types,
+            num_updates_uniform,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Continuous collision detection (CCD) of fast rigid-bodies.
+
+use crate::math::Pose;
+use crate::queries::GpuColliderMaterial;
+use crate::shaders::PaddedVector;
+use crate::shaders::broad_phase::CollisionPair;
+use crate::shaders::dynamics::{
+    BodyParams, CcdSweep, GpuCcdClampMotion, GpuCcdInit, GpuCcdPairs, GpuCcdResetPairs,
+    GpuCcdSweeps, GpuCcdToi, GpuSyncColliderPoses, RbdSimParams, Velocity, WorldMassProperties,
+};
+use crate::shaders::shapes::Shape;
+use crate::shaders::utils::BatchIndices;
+use glamx::UVec4;
+use khal::backend::{GpuBackend, GpuBackendError, GpuPass};
+use khal::{BufferUsages, Shader};
+use vortx::tensor::Tensor;
+
+/// GPU shaders preventing the bodies with CCD enabled from tunnelling through
+/// other colliders.
+///
+/// Before the broad phase, the AABBs of the colliders of CCD bodies are swept
+/// along their predicted motion so the broad phase finds the pairs they may
+/// meet during the step. Once the solver is done, the time of impact of these
+/// pairs is computed along the actual motion of the step, and the CCD bodies are
+/// moved back to their pose at their earliest impact. Their velocities are kept,
+/// so the next step's narrow phase and solver handle the contact.
+#[derive(Shader)]
+pub struct GpuCcd {
+    sweeps: GpuCcdSweeps,
+    reset_pairs: GpuCcdResetPairs,
+    init: GpuCcdInit,
+    pairs: GpuCcdPairs,
+    /// Computes the collider poses at the end of the step.
+    sync_collider_poses: GpuSyncColliderPoses,
+    toi: GpuCcdToi,
+    clamp_motion: GpuCcdClampMotion,
+}
+
+/// GPU-resident state of the continuous collision detection.
+///
+/// Buffers are allocated lazily, on the first step with a CCD body, and resized
+/// along with the collider and collision-pair buffers.
+pub struct CcdState {
+    /// Whether any body has CCD enabled. The CCD passes are skipped otherwise.
+    enabled: bool,
+    sweeps: Tensor<CcdSweep>,
+    start_poses: Tensor<Pose>,
+    collider_end_poses: Tensor<Pose>,
+    toi_keys: Tensor<u32>,
+    pairs: Tensor<UVec4>,
+    pairs_len: Tensor<u32>,
+}
+
+impl CcdState {
+    /// Creates an empty CCD state.
+    pub fn new(backend: &GpuBackend, enabled: bool) -> Result<Self, GpuBackendError> {
+        let storage = BufferUsages::STORAGE;
+        Ok(Self {
+            enabled,
+            sweeps: Tensor::vector_uninit(backend, 0, storage)?,
+            start_poses: Tensor::vector_uninit(backend, 0, storage)?,
+            collider_end_poses: Tensor::vector_uninit(backend, 0, storage)?,
+            toi_keys: Tensor::vector_uninit(backend, 0, storage)?,
+            pairs: Tensor::vector_uninit(backend, 0, storage)?,
+            pairs_len: Tensor::vector_uninit(backend, 0, storage)?,
+        })
+    }
+
+    /// Whether the CCD passes run, i.e., whether any body has CCD enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enables the CCD passes, after a body with CCD enabled was added.
+    pub(crate) fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// The per-collider sweeps enlarging the broad-phase AABBs, or `None` if
+    /// CCD is disabled.
+    pub(crate) fn sweeps(&self) -> Option<&Tensor<CcdSweep>> {
+        self.enabled.then_some(&self.sweeps)
+    }
+
+    /// Grows the per-collider and per-body buffers to `colliders_len` elements
+    /// (all batches included).
+    fn resize_collider_buffers(
+        &mut self,
+        backend: &GpuBackend,
+        colliders_len: u32,
+    ) -> Result<(), GpuBackendError> {
+        if (self.sweeps.len() as u32) < colliders_len {
+            let storage = BufferUsages::STORAGE;
+            self.sweeps = Tensor::vector_uninit(backend, colliders_len, storage)?;
+            self.start_poses = Tensor::vector_uninit(backend, colliders_len, storage)?;
+            self.collider_end_poses = Tensor::vector_uninit(backend, colliders_len, storage)?;
+            self.toi_keys = Tensor::vector_uninit(backend, colliders_len, storage)?;
+        }
+        Ok(())
+    }
+
+    /// Grows the CCD pair buffers to `pairs_len` pairs (all batches included).
+    fn resize_pair_buffers(
+        &mut self,
+        backend: &GpuBackend,
+        pairs_len: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        let storage = BufferUsages::STORAGE;
+        if (self.pairs.len() as u32) < pairs_len {
+            self.pairs = Tensor::vector_uninit(backend, pairs_len, storage)?;
+        }
+        if (self.pairs_len.len() as u32) < num_batches {
+            self.pairs_len = Tensor::vector_uninit(backend, num_batches, storage)?;
+        }
+        Ok(())
+    }
+}
+
+impl GpuCcd {
+    /// Computes the sweeps of the colliders of CCD bodies, from their current
+    /// velocities.
+    ///
+    /// Must run after the mass properties update and before the broad phase.
+    /// No-op if no body has CCD enabled.
+    pub fn dispatch_sweeps(
+        &self,
+        backend: &GpuBackend,
+        pass: &mut GpuPass,
+        state: &mut CcdState,
+        collider_parent: &Tensor<u32>,
+        vels: &Tensor<Velocity>,
+        mprops: &Tensor<WorldMassProperties>,
+        body_params: &Tensor<BodyParams>,
+        sim_params: &Tensor<RbdSimParams>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_colliders: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        if !state.enabled {
+            return Ok(());
+        }
+
+        state.resize_collider_buffers(backend, collider_parent.len() as u32)?;
+        self.sweeps.call(
+            pass,
+            [num_colliders, num_batches, 1],
+            collider_parent,
+            vels,
+            mprops,
+            body_params,
+            sim_params,
+            &mut state.sweeps,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Records the body poses at the start of the step and collects the
+    /// broad-phase `collision_pairs` involving a CCD body.
+    ///
+    /// Must run before the solver. No-op if no body has CCD enabled.
+    pub fn dispatch_pairs(
+        &self,
+        backend: &GpuBackend,
+        pass: &mut GpuPass,
+        state: &mut CcdState,
+        body_poses: &Tensor<Pose>,
+        collision_pairs: &Tensor<CollisionPair>,
+        collision_pairs_len: &Tensor<u32>,
+        collision_pairs_indirect: &Tensor<[u32; 3]>,
+        collider_parent: &Tensor<u32>,
+        collider_materials: &Tensor<GpuColliderMaterial>,
+        body_params: &Tensor<BodyParams>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_bodies: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        if !state.enabled {
+            return Ok(());
+        }
+
+        state.resize_collider_buffers(backend, body_poses.len() as u32)?;
+        state.resize_pair_buffers(backend, collision_pairs.len() as u32, num_batches)?;
+        self.reset_pairs
+            .call(pass, [num_batches, 1, 1], &mut state.pairs_len)?;
+        self.init.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            body_poses,
+            &mut state.start_poses,
+            &mut state.toi_keys,
+            batch_indices,
+        )?;
+        self.pairs.call(
+            pass,
+            collision_pairs_indirect,
+            collision_pairs,
+            collision_pairs_len,
+            collider_parent,
+            collider_materials,
+            body_params,
+            &mut state.pairs,
+            &mut state.pairs_len,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Computes the times of impact of the CCD pairs over the step and moves
+    /// the CCD bodies back to their earliest impact.
+    ///
+    /// Must run after the solver wrote back the final `body_poses`, while
+    /// `collider_world_poses` still holds the collider poses at the start of the
+    /// step. No-op if no body has CCD enabled.
+    pub fn dispatch_clamp_motion(
+        &self,
+        pass: &mut GpuPass,
+        state: &mut CcdState,
+        body_poses: &mut Tensor<Pose>,
+        collider_local_poses: &Tensor<Pose>,
+        collider_world_poses: &Tensor<Pose>,
+        collider_parent: &Tensor<u32>,
+        shapes: &Tensor<Shape>,
+        vertices: &Tensor<PaddedVector>,
+        indices: &Tensor<u32>,
+        collision_pairs_indirect: &Tensor<[u32; 3]>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_colliders: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        if !state.enabled {
+            return Ok(());
+        }
+
+        self.sync_collider_poses.call(
+            pass,
+            [num_colliders, num_batches, 1],
+            &*body_poses,
+            collider_local_poses,
+            &mut state.collider_end_poses,
+            collider_parent,
+            batch_indices,
+        )?;
+        // There are at most as many CCD pairs as collision pairs.
+        self.toi.call(
+            pass,
+            collision_pairs_indirect,
+            &state.pairs,
+            &state.pairs_len,
+            collider_world_poses,
+            &state.collider_end_poses,
+            shapes,
+            &mut state.toi_keys,
+            batch_indices,
+            vertices,
+            indices,
+        )?;
+        self.clamp_motion.call(
+            pass,
+            [num_colliders, num_batches, 1],
+            &state.start_poses,
+            &state.toi_keys,
+            body_poses,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+}
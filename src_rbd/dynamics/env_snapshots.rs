@@ -0,0 +1,93 @@
+//! Per-environment snapshots of the rigid-body state.
+
+use crate::math::Pose;
+use crate::shaders::dynamics::{
+    BodyActivation, BodySnapshot, Force, GpuCopyBodySnapshots, GpuCopyContactSnapshots,
+    TwoBodyConstraint, TwoBodyConstraintBuilder, Velocity,
+};
+use crate::shaders::utils::BatchIndices;
+use khal::Shader;
+use khal::backend::{GpuBackendError, GpuPass};
+use vortx::tensor::Tensor;
+
+/// GPU shader capturing the body states and the contact warmstart of the
+/// flagged batches into their snapshots, and restoring them from it.
+#[derive(Shader)]
+pub struct GpuEnvSnapshots {
+    /// Compute pipeline for the per-batch copy kernel.
+    copy_bodies: GpuCopyBodySnapshots,
+    /// Compute pipeline for the per-batch copy of the old contact constraints.
+    copy_contacts: GpuCopyContactSnapshots,
+}
+
+impl GpuEnvSnapshots {
+    /// Dispatches the copy kernel for every batch with nonzero `ops` (a
+    /// combination of the `ENV_SNAPSHOT_*` flags).
+    ///
+    /// Must run before anything else in the step, so the restored state is
+    /// the one simulated.
+    pub fn dispatch(
+        &self,
+        pass: &mut GpuPass,
+        ops: &Tensor<u32>,
+        snapshots: &mut Tensor<BodySnapshot>,
+        body_poses: &mut Tensor<Pose>,
+        vels: &mut Tensor<Velocity>,
+        kinematic_targets: &mut Tensor<Pose>,
+        forces: &mut Tensor<Force>,
+        activations: &mut Tensor<BodyActivation>,
+        old_body_constraint_counts: &mut Tensor<u32>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_bodies: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        self.copy_bodies.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            ops,
+            snapshots,
+            body_poses,
+            vels,
+            kinematic_targets,
+            forces,
+            activations,
+            old_body_constraint_counts,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Dispatches the copy of the contact warmstart (the constraints solved by
+    /// the last step and their per-body ids) for every batch with nonzero `ops`.
+    ///
+    /// The snapshot buffers are laid out like the old constraint buffers, with
+    /// `contacts_per_batch` contacts per batch.
+    pub fn dispatch_contacts(
+        &self,
+        pass: &mut GpuPass,
+        ops: &Tensor<u32>,
+        constraint_snapshots: &mut Tensor<TwoBodyConstraint>,
+        builder_snapshots: &mut Tensor<TwoBodyConstraintBuilder>,
+        body_constraint_id_snapshots: &mut Tensor<u32>,
+        old_constraints: &mut Tensor<TwoBodyConstraint>,
+        old_constraint_builders: &mut Tensor<TwoBodyConstraintBuilder>,
+        old_body_constraint_ids: &mut Tensor<u32>,
+        batch_indices: &Tensor<BatchIndices>,
+        contacts_per_batch: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        self.copy_contacts.call(
+            pass,
+            [contacts_per_batch, num_batches, 1],
+            ops,
+            constraint_snapshots,
+            builder_snapshots,
+            body_constraint_id_snapshots,
+            old_constraints,
+            old_constraint_builders,
+            old_body_constraint_ids,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Island-based sleeping of the rigid-bodies at rest.
+
+use super::GpuImpulseJointSet;
+use crate::queries::GpuIndexedContact;
+use crate::shaders::dynamics::{
+    BodyActivation, BodyParams, GpuIslandsApply, GpuIslandsAwake, GpuIslandsCompress,
+    GpuIslandsInit, GpuIslandsLinkContacts, GpuIslandsLinkJoints, RbdSimParams, Velocity,
+    num_island_link_rounds,
+};
+use crate::shaders::utils::BatchIndices;
+use khal::backend::{GpuBackend, GpuBackendError, GpuPass};
+use khal::{BufferUsages, Shader};
+use vortx::tensor::Tensor;
+
+/// GPU shaders detecting the islands of bodies at rest and putting them to sleep.
+///
+/// Runs at the end of each step. A dynamic body whose velocity stayed below its
+/// sleep thresholds for its `time_until_sleep` can fall asleep, but only once
+/// every body of its island (the dynamic bodies it is linked to, through contacts
+/// and impulse joints) can too. Sleeping bodies are seen as fixed by the solver
+/// and are woken up with their whole island by a contact with an awake body, or
+/// by any write queued on one of them.
+#[derive(Shader)]
+pub struct GpuIslands {
+    init: GpuIslandsInit,
+    link_contacts: GpuIslandsLinkContacts,
+    link_joints: GpuIslandsLinkJoints,
+    compress: GpuIslandsCompress,
+    awake: GpuIslandsAwake,
+    apply: GpuIslandsApply,
+}
+
+/// GPU-resident scratch buffers of the island detection.
+///
+/// Allocated lazily on the first step, and grown along with the body buffers.
+pub struct IslandState {
+    /// Per-body island label.
+    labels: Tensor<u32>,
+    /// Per-label flag set if the island has a body not ready to sleep.
+    island_awake: Tensor<u32>,
+}
+
+impl IslandState {
+    /// Creates an empty island state.
+    pub fn new(backend: &GpuBackend) -> Result<Self, GpuBackendError> {
+        let storage = BufferUsages::STORAGE;
+        Ok(Self {
+            labels: Tensor::vector_uninit(backend, 0, storage)?,
+            island_awake: Tensor::vector_uninit(backend, 0, storage)?,
+        })
+    }
+
+    /// Grows the per-body buffers to `bodies_len` elements (all batches included).
+    fn resize(&mut self, backend: &GpuBackend, bodies_len: u32) -> Result<(), GpuBackendError> {
+        if (self.labels.len() as u32) < bodies_len {
+            let storage = BufferUsages::STORAGE;
+            self.labels = Tensor::vector_uninit(backend, bodies_len, storage)?;
+            self.island_awake = Tensor::vector_uninit(backend, bodies_len, storage)?;
+        }
+        Ok(())
+    }
+}
+
+impl GpuIslands {
+    /// Updates the sleep timers, detects the islands, and puts to sleep or wakes
+    /// up the bodies accordingly.
+    ///
+    /// Must run after the solver wrote back the final velocities, while
+    /// `contacts` still holds this step's contacts.
+    pub fn dispatch(
+        &self,
+        backend: &GpuBackend,
+        pass: &mut GpuPass,
+        state: &mut IslandState,
+        activations: &mut Tensor<BodyActivation>,
+        vels: &mut Tensor<Velocity>,
+        body_types: &Tensor<u32>,
+        body_params: &Tensor<BodyParams>,
+        contacts: &Tensor<GpuIndexedContact>,
+        contacts_len: &Tensor<u32>,
+        contacts_indirect: &Tensor<[u32; 3]>,
+        joints: &GpuImpulseJointSet,
+        sim_params: &Tensor<RbdSimParams>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_bodies: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        state.resize(backend, activations.len() as u32)?;
+        self.init.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            &*vels,
+            body_types,
+            body_params,
+            &mut *activations,
+            &mut state.labels,
+            &mut state.island_awake,
+            sim_params,
+            batch_indices,
+        )?;
+
+        // The longest island of a batch has at most `num_bodies` bodies.
+        for _ in 0..num_island_link_rounds(num_bodies) {
+            self.link_contacts.call(
+                pass,
+                contacts_indirect,
+                contacts,
+                contacts_len,
+                body_types,
+                &*vels,
+                &mut *activations,
+                &mut state.labels,
+                batch_indices,
+            )?;
+            if !joints.is_empty() {
+                self.link_joints.call(
+                    pass,
+                    [joints.joints_per_batch(), num_batches, 1],
+                    joints.gpu_joints(),
+                    body_types,
+                    &mut state.labels,
+                    batch_indices,
+                )?;
+            }
+            self.compress.call(
+                pass,
+                [num_bodies, num_batches, 1],
+                &mut state.labels,
+                batch_indices,
+            )?;
+        }
+
+        self.awake.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            body_types,
+            body_params,
+            &*activations,
+            &state.labels,
+            &mut state.island_awake,
+            batch_indices,
+        )?;
+        self.apply.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            body_types,
+            &state.labels,
+            &state.island_awake,
+            activations,
+            vels,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+}
@@ -4,11 +4,14 @@
 //! to be connected with various joint types (revolute, prismatic, fixed, etc.).
 
 use crate::math::Pose;
+use crate::shaders::DIM;
 use crate::shaders::dynamics::{
-    GpuInitJointConstraints, GpuSolveJointConstraints, GpuUpdateJointConstraints, ImpulseJoint,
-    JointConstraint, JointConstraintBuilder, LocalMassProperties, RbdSimParams, Velocity,
-    WorldMassProperties,
+    Force, GpuBreakJoints, GpuComputeJointWrenches, GpuCopyJointSnapshots, GpuInitJointConstraints,
+    GpuSolveJointConstraints, GpuUpdateJointConstraints, ImpulseJoint, JointBreakEvent,
+    JointBreakThreshold, JointConstraint, JointConstraintBuilder, LIN_AXES_MASK,
+    LocalMassProperties, RbdSimParams, SPATIAL_DIM, Velocity, WorldMassProperties,
 };
+use crate::shaders::utils::BatchIndices;
 use bytemuck::Zeroable;
 use khal::Shader;
 use khal::backend::{GpuBackend, GpuBackendError, GpuPass};
@@ -16,11 +19,15 @@ use vortx::tensor::Tensor;
 
 use {
     crate::rapier::dynamics::{
-        GenericJoint as RapierGenericJoint, ImpulseJoint as RapierImpulseJoint, ImpulseJointSet,
-        JointLimits as RapierJointLimits, JointMotor as RapierJointMotor, RigidBodyHandle,
+        GenericJoint as RapierGenericJoint, ImpulseJoint as RapierImpulseJoint, ImpulseJointHandle,
+        ImpulseJointSet, JointAxesMask, JointLimits as RapierJointLimits,
+        JointMotor as RapierJointMotor, RigidBodyHandle,
     },
     crate::rapier::prelude::MotorModel,
-    crate::shaders::dynamics::{GenericJoint, JointLimits, JointMotor},
+    crate::shaders::dynamics::{
+        GenericJoint, JOINT_KIND_GENERIC, JOINT_KIND_ROPE, JOINT_KIND_SPRING, JointLimits,
+        JointMotor,
+    },
     khal::BufferUsages,
     std::collections::HashMap,
 };
@@ -64,6 +71,25 @@ fn convert_generic_joint(joint: RapierGenericJoint) -> GenericJoint {
     }
 }
 
+/// The GPU kind of a rapier joint.
+///
+/// Rapier's `RopeJoint` and `SpringJoint` are generic joints with no locked axis and all the
+/// linear axes coupled: a rope limits the first linear axis (the distance), a spring motorizes it.
+fn joint_kind(joint: &RapierGenericJoint) -> u32 {
+    if !joint.locked_axes.is_empty()
+        || joint.coupled_axes != JointAxesMask::LIN_AXES
+        || (joint.limit_axes | joint.motor_axes).intersects(JointAxesMask::ANG_AXES)
+    {
+        JOINT_KIND_GENERIC
+    } else if joint.limit_axes.contains(JointAxesMask::LIN_X) {
+        JOINT_KIND_ROPE
+    } else if joint.motor_axes.intersects(JointAxesMask::LIN_AXES) {
+        JOINT_KIND_SPRING
+    } else {
+        JOINT_KIND_GENERIC
+    }
+}
+
 fn convert_impulse_joint(
     joint: &RapierImpulseJoint,
     body_ids: &HashMap<RigidBodyHandle, u32>,
@@ -71,35 +97,106 @@ fn convert_impulse_joint(
     ImpulseJoint {
         body_a: body_ids[&joint.body1()],
         body_b: body_ids[&joint.body2()],
-        #[cfg(feature = "dim3")]
-        padding: [0, 0],
+        kind: joint_kind(&joint.data),
+        enabled: joint.data.is_enabled() as u32,
         data: convert_generic_joint(joint.data),
     }
 }
 
+/// What an element of a GPU [`JointConstraint`] constrains, i.e. where rapier
+/// stores its accumulated impulse (rapier's `WritebackId`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum JointConstraintAxis {
+    /// A locked axis, written to `ImpulseJoint::impulses[axis]`.
+    Dof(usize),
+    /// A limited axis, written to `GenericJoint::limits[axis].impulse`.
+    Limit(usize),
+    /// A motorized axis, written to `GenericJoint::motors[axis].impulse`.
+    Motor(usize),
+}
+
+/// The axis constrained by each element of the GPU constraint of `joint`, in
+/// element order.
+///
+/// Mirrors the order in which `JointConstraintBuilder::update_constraint`
+/// emits the elements: motors, then locks, then limits (angular axes first).
+/// Ropes and springs follow the same order: their coupled distance motor,
+/// then the rope's distance limit.
+pub fn joint_constraint_axes(joint: &GenericJoint) -> Vec<JointConstraintAxis> {
+    use JointConstraintAxis::{Dof, Limit, Motor};
+
+    let dim = DIM as usize;
+    let locked = joint.locked_axes;
+    let motors = joint.motor_axes & !locked;
+    let limits = joint.limit_axes & !locked;
+    let coupled = joint.coupled_axes;
+    let first_coupled_lin = (coupled & LIN_AXES_MASK).trailing_zeros() as usize;
+    let ang_axes = |mask: u32| (dim..SPATIAL_DIM).filter(move |i| mask & (1 << i) != 0);
+    let lin_axes = |mask: u32| (0..dim).filter(move |i| mask & (1 << i) != 0);
+
+    let mut axes: Vec<_> = ang_axes(motors & !coupled).map(Motor).collect();
+    axes.extend(lin_axes(motors & !coupled).map(Motor));
+    // NOTE: coupled angular motors aren't implemented on the GPU yet.
+    if motors & coupled & LIN_AXES_MASK != 0 {
+        axes.push(Motor(first_coupled_lin));
+    }
+    axes.extend(ang_axes(locked).map(Dof));
+    axes.extend(lin_axes(locked).map(Dof));
+    axes.extend(ang_axes(limits & !coupled).map(Limit));
+    axes.extend(lin_axes(limits & !coupled).map(Limit));
+    if coupled & LIN_AXES_MASK != 0 && limits & (1 << first_coupled_lin) != 0 {
+        axes.push(Limit(first_coupled_lin));
+    }
+    axes
+}
+
+/// The end of the group of color `c` in `color_groups`, the per-color prefix
+/// sums of an environment with `len` joints. Colors past the environment's own
+/// are empty.
+fn color_group_end(color_groups: &[u32], c: usize, len: u32) -> u32 {
+    color_groups.get(c).copied().unwrap_or(len)
+}
+
 /// A set of impulse joints simulated on the GPU.
 pub struct GpuImpulseJointSet {
-    /// Per-batch buffer stride (capacity) of the `joints` / `builders` /
-    /// `constraints` slabs.
+    /// Joint count of the largest batch: the dispatch width of the joint kernels
+    /// and the per-batch capacity of the `break_events` ring buffers.
     len: u32,
-    /// Number of *active* impulse joints per batch (the kernel loop bound).
-    /// Identical across batches by the equal-topology invariant.
-    num_active_joints: u32,
+    /// Start of each batch's joints in the `joints` / `builders` / `constraints`
+    /// / `wrenches` / `break_thresholds` buffers. Batches are packed one after
+    /// the other, without padding.
+    offsets: Vec<u32>,
+    /// Number of joints of each batch.
+    lens: Vec<u32>,
+    /// Number of colors (also the per-batch stride of `color_groups`).
     num_colors: u32,
-    /// Host copy of the per-color prefix sums in `color_groups` (identical
-    /// across batches), used to size each per-color solve dispatch exactly.
-    color_groups_cpu: Vec<u32>,
+    /// Per-color max group size across batches, used to size each per-color
+    /// solve dispatch.
+    max_color_group_lens: Vec<u32>,
+    /// Per-batch per-color prefix sums over the color-sorted joints.
     color_groups: Tensor<u32>,
     joints: Tensor<ImpulseJoint>,
     builders: Tensor<JointConstraintBuilder>,
     constraints: Tensor<JointConstraint>,
+    /// Per-joint reaction wrench, computed at the end of each step.
+    wrenches: Tensor<Force>,
+    /// Per-joint breaking threshold, unbreakable by default.
+    break_thresholds: Tensor<JointBreakThreshold>,
+    /// Per-batch ring buffers of joint-break events, `len` entries per batch.
+    break_events: Tensor<JointBreakEvent>,
+    /// Per-batch number of joint-break events emitted so far.
+    break_events_len: Tensor<u32>,
+    /// Host copy of `joints` as uploaded, patched by [`Self::set_enabled`].
+    joints_cpu: Vec<ImpulseJoint>,
+    /// Per-batch rapier handle of each GPU joint, in (color-sorted) buffer order.
+    handles: Vec<Vec<ImpulseJointHandle>>,
 }
 
 impl GpuImpulseJointSet {
     /// Converts per-environment Rapier joints to GPU joints.
     ///
-    /// Each environment can have different joints. The joints are padded to the max
-    /// joint count across all environments, and graph coloring is done independently
+    /// Each environment can have different joints. The joints of every environment
+    /// are packed one after the other, and graph coloring is done independently
     /// per environment.
     ///
     /// `environments` is a slice of `(impulse_joints, body_ids)`. The optional
@@ -177,21 +274,13 @@ impl GpuImpulseJointSet {
 
         // Per-environment sorted joints and color groups.
         let mut per_env_sorted_joints: Vec<Vec<ImpulseJoint>> = Vec::new();
+        let mut per_env_sorted_handles: Vec<Vec<ImpulseJointHandle>> = Vec::new();
         let mut per_env_color_groups: Vec<Vec<u32>> = Vec::new();
-        // Per-environment structural signature (connectivity + locked/limit/
-        // motor/coupled axis masks, in joint order) and coloring, used to
-        // assert that every environment shares the same impulse-joint topology,
-        // DOFs and coloring — the precondition for the single-batch color-group
-        // buffer below.
-        let mut per_env_structure: Vec<Vec<[u32; 6]>> = Vec::new();
-        let mut per_env_colors: Vec<Vec<u32>> = Vec::new();
-
         for (env_idx, (joints, body_ids)) in environments.iter().enumerate() {
-            let _len = filtered_lens[env_idx];
-
             // Convert joints, dropping any with at least one multibody side.
             let mut unsorted_gpu_joints = vec![];
-            for (_, joint) in joints.iter() {
+            let mut unsorted_handles = vec![];
+            for (handle, joint) in joints.iter() {
                 let a = body_ids.get(&joint.body1()).copied();
                 let b = body_ids.get(&joint.body2()).copied();
                 let skip_a = a.map(|id| is_mb_for(env_idx, id)).unwrap_or(false);
@@ -200,24 +289,9 @@ impl GpuImpulseJointSet {
                     continue;
                 }
                 unsorted_gpu_joints.push(convert_impulse_joint(joint, body_ids));
+                unsorted_handles.push(handle);
             }
 
-            per_env_structure.push(
-                unsorted_gpu_joints
-                    .iter()
-                    .map(|j| {
-                        [
-                            j.body_a,
-                            j.body_b,
-                            j.data.locked_axes,
-                            j.data.limit_axes,
-                            j.data.motor_axes,
-                            j.data.coupled_axes,
-                        ]
-                    })
-                    .collect(),
-            );
-
             // Build the body-id → graph-group lookup. Without a multibody group
             // table, every body is its own node. With one, bodies that share a
             // multibody collapse to a single node so two impulse-joint contacts
@@ -267,73 +341,85 @@ impl GpuImpulseJointSet {
             let mut target = color_groups.clone();
             target.insert(0, 0);
             let mut sorted_gpu_joints = unsorted_gpu_joints.clone();
+            let mut sorted_handles = unsorted_handles.clone();
 
-            for (joint, color) in unsorted_gpu_joints.iter().zip(colors.iter()) {
+            for ((joint, handle), color) in unsorted_gpu_joints
+                .iter()
+                .zip(unsorted_handles.iter())
+                .zip(colors.iter())
+            {
                 sorted_gpu_joints[target[*color as usize] as usize] = *joint;
+                sorted_handles[target[*color as usize] as usize] = *handle;
                 target[*color as usize] += 1;
             }
 
             global_num_colors = global_num_colors.max(env_num_colors);
 
             per_env_sorted_joints.push(sorted_gpu_joints);
+            per_env_sorted_handles.push(sorted_handles);
             per_env_color_groups.push(color_groups);
-            per_env_colors.push(colors);
         }
 
-        // Enforce identical impulse-joint structure across environments: same
-        // connectivity + DOFs (axis masks) and therefore the same graph
-        // coloring. This lets the color-group prefix sums be stored once
-        // (single batch) and read at offset 0 by every batch in the solver.
-        if let Some((first_struct, rest)) = per_env_structure.split_first() {
-            for (i, s) in rest.iter().enumerate() {
-                assert_eq!(
-                    s,
-                    first_struct,
-                    "batched rbd requires identical impulse-joint topology/DOFs in every \
-                     environment (env {} differs from env 0)",
-                    i + 1
-                );
-            }
-        }
-        if let Some((first_colors, rest)) = per_env_colors.split_first() {
-            for (i, c) in rest.iter().enumerate() {
-                assert_eq!(
-                    c,
-                    first_colors,
-                    "batched rbd requires identical impulse-joint coloring in every \
-                     environment (env {} differs from env 0)",
-                    i + 1
-                );
-            }
-        }
-
-        // Build flat joint buffer [num_batches * max_joints], padded with zeroed joints.
-        let dummy_joint = ImpulseJoint::zeroed();
-        let mut all_joints = Vec::with_capacity(num_batches as usize * max_joints as usize);
+        // Build the flat joint buffer, each batch's joints after the previous ones.
+        let mut offsets = Vec::with_capacity(num_batches as usize);
+        let mut all_joints = Vec::new();
         for sorted_joints in &per_env_sorted_joints {
+            offsets.push(all_joints.len() as u32);
             all_joints.extend_from_slice(sorted_joints);
-            // Pad to max_joints.
-            for _ in sorted_joints.len()..max_joints as usize {
-                all_joints.push(dummy_joint);
+        }
+        let num_joints = all_joints.len() as u32;
+
+        // Per-batch color_groups buffer [num_batches * global_num_colors]. The
+        // prefix sums of environments with fewer colors are extended with their
+        // joint count, i.e. with empty groups.
+        let mut all_color_groups =
+            Vec::with_capacity(num_batches as usize * global_num_colors as usize);
+        let mut max_color_group_lens = vec![0u32; global_num_colors as usize];
+        for (color_groups, len) in per_env_color_groups.iter().zip(&filtered_lens) {
+            for c in 0..global_num_colors as usize {
+                let start = if c > 0 {
+                    color_group_end(color_groups, c - 1, *len)
+                } else {
+                    0
+                };
+                let end = color_group_end(color_groups, c, *len);
+                max_color_group_lens[c] = max_color_group_lens[c].max(end - start);
+                all_color_groups.push(end);
             }
         }
 
-        // Single-batch color_groups buffer [global_num_colors]: every
-        // environment has identical coloring (asserted above), so the prefix
-        // sums are stored once and read at offset 0 by all batches.
-        let all_color_groups = per_env_color_groups.first().cloned().unwrap_or_default();
+        let all_thresholds = vec![JointBreakThreshold::default(); num_joints as usize];
+        let readable = usage | BufferUsages::COPY_SRC;
 
         Self {
             len: max_joints,
-            // All environments have the same joint count by the equal-topology
-            // invariant, so the per-batch active count is any env's count.
-            num_active_joints: filtered_lens.first().copied().unwrap_or(0),
+            offsets,
+            lens: filtered_lens,
             num_colors: global_num_colors,
-            color_groups_cpu: all_color_groups.clone(),
+            max_color_group_lens,
             color_groups: Tensor::vector(backend, &all_color_groups, usage).unwrap(),
-            joints: Tensor::vector(backend, &all_joints, usage).unwrap(),
-            builders: Tensor::matrix_uninit(backend, num_batches, max_joints, usage).unwrap(),
-            constraints: Tensor::matrix_uninit(backend, num_batches, max_joints, usage).unwrap(),
+            // COPY_DST so joints can be re-enabled by `Self::set_enabled`.
+            joints: Tensor::vector(backend, &all_joints, usage | BufferUsages::COPY_DST).unwrap(),
+            builders: Tensor::vector_uninit(backend, num_joints, usage).unwrap(),
+            // COPY_SRC so the accumulated impulses can be read back.
+            constraints: Tensor::vector_uninit(backend, num_joints, usage | BufferUsages::COPY_SRC)
+                .unwrap(),
+            // COPY_SRC so the reaction wrenches can be read back.
+            wrenches: Tensor::vector_uninit(backend, num_joints, usage | BufferUsages::COPY_SRC)
+                .unwrap(),
+            break_thresholds: Tensor::vector(
+                backend,
+                &all_thresholds,
+                usage | BufferUsages::COPY_DST,
+            )
+            .unwrap(),
+            // COPY_SRC so the joint-break events can be read back.
+            break_events: Tensor::matrix_uninit(backend, num_batches, max_joints, readable)
+                .unwrap(),
+            break_events_len: Tensor::vector(backend, &vec![0u32; num_batches as usize], readable)
+                .unwrap(),
+            joints_cpu: all_joints,
+            handles: per_env_sorted_handles,
         }
     }
 
@@ -347,21 +433,167 @@ impl GpuImpulseJointSet {
         self.len as usize
     }
 
-    /// Per-batch capacity (max joints across batches). Stride for
-    /// `joints`, `builders`, `constraints` buffers.
+    /// Joint count of the largest batch: the dispatch width of the joint kernels,
+    /// and the per-batch capacity of the [`Self::break_events`] ring buffers.
     pub fn joints_per_batch(&self) -> u32 {
         self.len
     }
 
-    /// Number of *active* impulse joints per batch (the kernel loop bound).
-    pub fn num_active_joints(&self) -> u32 {
-        self.num_active_joints
+    /// Number of joints of batch `batch_id`.
+    pub fn num_joints(&self, batch_id: u32) -> u32 {
+        self.lens.get(batch_id as usize).copied().unwrap_or(0)
+    }
+
+    /// Index, in the per-joint buffers, of the joint `joint_id` of batch
+    /// `batch_id`. The joints of each batch are packed after the previous batch's.
+    pub fn joint_index(&self, batch_id: u32, joint_id: u32) -> u32 {
+        self.offsets[batch_id as usize] + joint_id
+    }
+
+    /// Writes the impulse-joint capacities and per-batch ranges into `bi`.
+    pub(crate) fn fill_batch_indices(&self, bi: &mut BatchIndices) {
+        bi.impulse_joints_batch_capacity = self.len;
+        bi.impulse_joint_color_groups_batch_capacity = self.num_colors;
+        for (batch_id, (offset, len)) in self.offsets.iter().zip(&self.lens).enumerate() {
+            bi.set_impulse_joints_range(batch_id as u32, *offset, *len);
+        }
+    }
+
+    /// The GPU joints of every batch, packed per batch (see [`Self::joint_index`]).
+    pub(crate) fn gpu_joints(&self) -> &Tensor<ImpulseJoint> {
+        &self.joints
     }
 
     /// Number of color groups (also the per-batch stride of `color_groups`).
     pub fn num_colors(&self) -> u32 {
         self.num_colors
     }
+
+    /// The rapier handles of the joints of batch `batch_id`, in GPU buffer order.
+    pub fn handles(&self, batch_id: u32) -> &[ImpulseJointHandle] {
+        self.handles
+            .get(batch_id as usize)
+            .map(|h| h.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The reaction wrench of every joint of every batch, packed per batch (see
+    /// [`Self::joint_index`]), in the same order as [`Self::handles`].
+    ///
+    /// This is the wrench applied by the joint on its second body at the end of
+    /// the last step (impulses of the last substep divided by the substep
+    /// length), with the torque taken about the second anchor and both
+    /// expressed in the joint frame attached to the first body.
+    pub fn wrenches(&self) -> &Tensor<Force> {
+        &self.wrenches
+    }
+
+    /// Sets the reaction force and torque norms above which the joint `joint_id`
+    /// of batch `batch_id` breaks.
+    ///
+    /// A broken joint is disabled on the GPU, without any rebuild, and reported
+    /// by a [`JointBreakEvent`] in [`Self::break_events`]. The force and torque
+    /// are compared to the norms of the joint's [`Self::wrenches`] at the end of
+    /// each step. Joints are unbreakable by default.
+    pub fn set_break_threshold(
+        &mut self,
+        backend: &GpuBackend,
+        batch_id: u32,
+        joint_id: u32,
+        threshold: JointBreakThreshold,
+    ) -> Result<(), GpuBackendError> {
+        if joint_id as usize >= self.handles(batch_id).len() {
+            return Ok(());
+        }
+        let idx = self.joint_index(batch_id, joint_id);
+        backend.write_buffer(
+            self.break_thresholds.buffer_mut(),
+            idx as u64,
+            std::slice::from_ref(&threshold),
+        )
+    }
+
+    /// Enables or disables the joint `joint_id` of batch `batch_id`, e.g. to
+    /// repair a broken joint.
+    ///
+    /// The GPU joint is overwritten with its host copy, as converted from rapier
+    /// by the constructor.
+    pub fn set_enabled(
+        &mut self,
+        backend: &GpuBackend,
+        batch_id: u32,
+        joint_id: u32,
+        enabled: bool,
+    ) -> Result<(), GpuBackendError> {
+        if joint_id as usize >= self.handles(batch_id).len() {
+            return Ok(());
+        }
+        let idx = self.joint_index(batch_id, joint_id) as usize;
+        self.joints_cpu[idx].enabled = enabled as u32;
+        backend.write_buffer(
+            self.joints.buffer_mut(),
+            idx as u64,
+            std::slice::from_ref(&self.joints_cpu[idx]),
+        )
+    }
+
+    /// The per-batch ring buffers of joint-break events, `joints_per_batch`
+    /// entries per batch, indexed by the ever-growing [`Self::break_events_len`].
+    pub fn break_events(&self) -> &Tensor<JointBreakEvent> {
+        &self.break_events
+    }
+
+    /// The per-batch number of joint-break events emitted so far.
+    pub fn break_events_len(&self) -> &Tensor<u32> {
+        &self.break_events_len
+    }
+
+    /// Reads back the joint constraints of every batch, as left by the last
+    /// substep of the last step.
+    pub async fn read_constraints(
+        &self,
+        backend: &GpuBackend,
+    ) -> Result<Vec<JointConstraint>, GpuBackendError> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+        backend.slow_read_vec(self.constraints.buffer()).await
+    }
+
+    /// Writes the impulses accumulated by the constraints of batch `batch_id`
+    /// into the matching joints of `joints`, that batch's rapier joint set.
+    ///
+    /// `constraints` is the buffer returned by [`Self::read_constraints`]. Locked
+    /// axes go to `ImpulseJoint::impulses`, limits and motors to their own
+    /// `impulse` field, as rapier's own writeback does.
+    pub fn writeback_impulses(
+        &self,
+        constraints: &[JointConstraint],
+        batch_id: u32,
+        joints: &mut ImpulseJointSet,
+    ) {
+        let Some(&start) = self.offsets.get(batch_id as usize) else {
+            return;
+        };
+        let Some(constraints) = constraints.get(start as usize..) else {
+            return;
+        };
+
+        for (constraint, handle) in constraints.iter().zip(self.handles(batch_id)) {
+            let Some(joint) = joints.get_mut(*handle, false) else {
+                continue;
+            };
+            let axes = joint_constraint_axes(&convert_generic_joint(joint.data));
+            let elements = constraint.elements.iter().take(constraint.len as usize);
+            for (element, axis) in elements.zip(axes) {
+                match axis {
+                    JointConstraintAxis::Dof(i) => joint.impulses[i] = element.impulse,
+                    JointConstraintAxis::Limit(i) => joint.data.limits[i].impulse = element.impulse,
+                    JointConstraintAxis::Motor(i) => joint.data.motors[i].impulse = element.impulse,
+                }
+            }
+        }
+    }
 }
 
 /// GPU shader for joint constraint solving.
@@ -373,6 +605,12 @@ pub struct GpuJointSolver {
     update_joint_constraints: GpuUpdateJointConstraints,
     /// Solves joint constraints.
     solve_joint_constraints: GpuSolveJointConstraints,
+    /// Computes the joint reaction wrenches.
+    compute_joint_wrenches: GpuComputeJointWrenches,
+    /// Disables the joints whose reaction wrench exceeds their threshold.
+    break_joints: GpuBreakJoints,
+    /// Captures and restores the per-environment snapshots of the enabled flags.
+    copy_env_snapshots: GpuCopyJointSnapshots,
 }
 
 /// Arguments given to the joint solver.
@@ -441,6 +679,84 @@ impl GpuJointSolver {
         Ok(())
     }
 
+    /// Computes the reaction wrench of every joint from the impulses of the
+    /// last substep. Must run after the last solve, while `poses` still holds
+    /// the COM-centered solver body poses.
+    pub fn compute_wrenches(
+        &self,
+        pass: &mut GpuPass,
+        args: &mut JointSolverArgs<'_>,
+        poses: &Tensor<Pose>,
+    ) -> Result<(), GpuBackendError> {
+        if args.joints.is_empty() {
+            return Ok(());
+        }
+
+        self.compute_joint_wrenches.call(
+            pass,
+            [args.joints.len, args.num_batches, 1],
+            &args.joints.builders,
+            &args.joints.constraints,
+            poses,
+            args.sim_params,
+            &mut args.joints.wrenches,
+            args.batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Disables the joints whose reaction wrench exceeds their breaking
+    /// threshold, emitting a joint-break event for each. Must run after
+    /// [`Self::compute_wrenches`].
+    pub fn break_joints(
+        &self,
+        pass: &mut GpuPass,
+        args: &mut JointSolverArgs<'_>,
+    ) -> Result<(), GpuBackendError> {
+        if args.joints.is_empty() {
+            return Ok(());
+        }
+
+        self.break_joints.call(
+            pass,
+            [args.joints.len, args.num_batches, 1],
+            &mut args.joints.joints,
+            &args.joints.wrenches,
+            &args.joints.break_thresholds,
+            &mut args.joints.break_events,
+            &mut args.joints.break_events_len,
+            args.batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Captures whether the joints of the batches flagged in `ops` are enabled
+    /// into `enabled_snapshots`, laid out like the joints, and/or restores it
+    /// from there (see the `ENV_SNAPSHOT_*` flags).
+    pub fn copy_env_snapshots(
+        &self,
+        pass: &mut GpuPass,
+        joints: &mut GpuImpulseJointSet,
+        ops: &Tensor<u32>,
+        enabled_snapshots: &mut Tensor<u32>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        if joints.is_empty() {
+            return Ok(());
+        }
+
+        self.copy_env_snapshots.call(
+            pass,
+            [joints.len, num_batches, 1],
+            ops,
+            enabled_snapshots,
+            &mut joints.joints,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+
     /// Apply a single Projected-Gauss-Seidel step for solving joints.
     pub fn solve(
         &self,
@@ -457,16 +773,11 @@ impl GpuJointSolver {
         //  We can use `color_uniforms` since `color_uniforms[k] == k`.
         let use_bias_uniform = &args.color_uniforms[use_bias as usize];
 
-        // One dispatch per color, sized exactly to that color's group (the
-        // prefix sums are known on the host). The color index is bound as a
-        // tiny pre-built uniform instead of a GPU-incremented cursor.
+        // One dispatch per color, sized to that color's largest group across
+        // batches (known on the host). The color index is bound as a tiny
+        // pre-built uniform instead of a GPU-incremented cursor.
         for c in 0..args.joints.num_colors as usize {
-            let start = if c > 0 {
-                args.joints.color_groups_cpu[c - 1]
-            } else {
-                0
-            };
-            let group_len = args.joints.color_groups_cpu[c] - start;
+            let group_len = args.joints.max_color_group_lens[c];
             if group_len == 0 {
                 continue;
             }
@@ -485,3 +796,97 @@ impl GpuJointSolver {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rapier::dynamics::{GenericJointBuilder, JointAxis};
+    use crate::shaders::MAX_FLT;
+    use crate::shaders::dynamics::JointConstraintElement;
+    use crate::shaders::utils::Slice;
+    use glamx::Vec2;
+
+    /// Builds the GPU constraint of `joint` between two coincident bodies.
+    fn build_constraint(joint: RapierGenericJoint) -> JointConstraint {
+        let mut builder = JointConstraintBuilder::zeroed();
+        builder.body2 = 1;
+        builder.kind = joint_kind(&joint);
+        builder.enabled = 1;
+        builder.joint = convert_generic_joint(joint);
+
+        let poses = [Pose::IDENTITY; 2];
+        let mprops = [WorldMassProperties::default(); 2];
+        let mut constraint = JointConstraint::default();
+        builder.update_constraint(
+            &mut constraint,
+            &Slice(&poses, 0),
+            &Slice(&mprops, 0),
+            &RbdSimParams::default(),
+        );
+        constraint
+    }
+
+    /// Checks that `element` constrains `axis`: its jacobian must be along that
+    /// axis, and its impulse bounds must match the constraint kind.
+    fn assert_element_axis(element: &JointConstraintElement, axis: JointConstraintAxis) {
+        let bounds = element.impulse_bounds;
+        let i = match axis {
+            JointConstraintAxis::Dof(i) => {
+                assert_eq!(bounds, Vec2::new(-MAX_FLT, MAX_FLT), "{axis:?}");
+                i
+            }
+            JointConstraintAxis::Motor(i) => {
+                assert!(bounds.y > 0.0 && bounds.y < MAX_FLT, "{axis:?}");
+                assert_eq!(bounds.x, -bounds.y, "{axis:?}");
+                i
+            }
+            // The joint is within its limits: neither side is active.
+            JointConstraintAxis::Limit(i) => {
+                assert_eq!(bounds, Vec2::ZERO, "{axis:?}");
+                i
+            }
+        };
+
+        if i < DIM as usize {
+            assert!((element.lin_jac[i].abs() - 1.0).abs() < 1.0e-4, "{axis:?}");
+            assert!((element.lin_jac.length() - 1.0).abs() < 1.0e-4, "{axis:?}");
+        } else {
+            assert!(element.lin_jac.length() < 1.0e-4, "{axis:?}");
+            #[cfg(feature = "dim2")]
+            assert_ne!(element.ang_jac_a, 0.0, "{axis:?}");
+            #[cfg(feature = "dim3")]
+            assert!(
+                (element.ang_jac_a.normalize()[i - DIM as usize].abs() - 1.0).abs() < 1.0e-4,
+                "{axis:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_joint_constraint_axes_match_the_constraint_elements() {
+        // Each axis is either locked, motorized or limited, so that every
+        // element of the constraint is identified by its jacobian.
+        #[cfg(feature = "dim2")]
+        let joint = GenericJointBuilder::new(JointAxesMask::LIN_X)
+            .motor_velocity(JointAxis::LinY, 1.0, 0.5)
+            .motor_max_force(JointAxis::LinY, 10.0)
+            .limits(JointAxis::AngX, [-1.0, 1.0])
+            .build();
+        #[cfg(feature = "dim3")]
+        let joint = GenericJointBuilder::new(JointAxesMask::LIN_X | JointAxesMask::ANG_X)
+            .motor_velocity(JointAxis::LinY, 1.0, 0.5)
+            .motor_max_force(JointAxis::LinY, 10.0)
+            .motor_velocity(JointAxis::AngY, 1.0, 0.5)
+            .motor_max_force(JointAxis::AngY, 10.0)
+            .limits(JointAxis::LinZ, [-1.0, 1.0])
+            .limits(JointAxis::AngZ, [-1.0, 1.0])
+            .build();
+
+        let axes = joint_constraint_axes(&convert_generic_joint(joint));
+        let constraint = build_constraint(joint);
+        assert_eq!(constraint.len as usize, axes.len());
+        for (element, axis) in constraint.elements.iter().zip(axes) {
+            assert_element_axis(element, axis);
+        }
+    }
+}
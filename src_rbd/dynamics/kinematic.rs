@@ -0,0 +1,80 @@
+//! Kinematic rigid-bodies driven by target poses or velocities.
+
+use crate::math::Pose;
+use crate::shaders::dynamics::{
+    GpuKinematicSnapPoses, GpuKinematicVelocities, RbdSimParams, Velocity,
+};
+use crate::shaders::utils::BatchIndices;
+use khal::Shader;
+use khal::backend::{GpuBackendError, GpuPass};
+use vortx::tensor::Tensor;
+
+/// GPU shaders moving the kinematic rigid-bodies.
+///
+/// Position-based kinematic bodies are given, at the beginning of each step, the
+/// velocity reaching their target pose at the end of the step. This velocity is
+/// what the solver sees, so dynamic bodies in contact are pushed with the correct
+/// relative velocity. Once the solver is done, they are snapped on their target
+/// pose exactly. Velocity-based kinematic bodies simply keep the velocity set by
+/// the user.
+#[derive(Shader)]
+pub struct GpuKinematics {
+    /// Computes the velocity of position-based kinematic bodies.
+    velocities: GpuKinematicVelocities,
+    /// Snaps position-based kinematic bodies on their target pose.
+    snap_poses: GpuKinematicSnapPoses,
+}
+
+impl GpuKinematics {
+    /// Dispatches the kernel computing the velocities of position-based kinematic bodies.
+    ///
+    /// Must run before the solver copies `vels` into its working velocities.
+    pub fn dispatch_velocities(
+        &self,
+        pass: &mut GpuPass,
+        body_types: &Tensor<u32>,
+        kinematic_targets: &Tensor<Pose>,
+        body_poses: &Tensor<Pose>,
+        vels: &mut Tensor<Velocity>,
+        sim_params: &Tensor<RbdSimParams>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_bodies: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        self.velocities.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            body_types,
+            kinematic_targets,
+            body_poses,
+            vels,
+            sim_params,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Dispatches the kernel snapping position-based kinematic bodies on their target pose.
+    ///
+    /// Must run after the solver wrote back the final body poses.
+    pub fn dispatch_snap_poses(
+        &self,
+        pass: &mut GpuPass,
+        body_types: &Tensor<u32>,
+        kinematic_targets: &Tensor<Pose>,
+        body_poses: &mut Tensor<Pose>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_bodies: u32,
+        num_batches: u32,
+    ) -> Result<(), GpuBackendError> {
+        self.snap_poses.call(
+            pass,
+            [num_bodies, num_batches, 1],
+            body_types,
+            kinematic_targets,
+            body_poses,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+}
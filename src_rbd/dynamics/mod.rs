@@ -2,21 +2,34 @@
 
 pub use crate::shaders::dynamics::RbdSimParams;
 pub use body::{BodyCoupling, BodyCouplingEntry, BodyDesc, GpuBodySet};
+pub use body_updates::GpuBodyUpdates;
+pub use ccd::{CcdState, GpuCcd};
 pub use coloring::{ColorBucketsArgs, ColoringArgs, GpuColoring};
-pub use joint::{GpuImpulseJointSet, GpuJointSolver, JointSolverArgs, convert_joint_motor};
+pub use env_snapshots::GpuEnvSnapshots;
+pub use islands::{GpuIslands, IslandState};
+pub use joint::{
+    GpuImpulseJointSet, GpuJointSolver, JointConstraintAxis, JointSolverArgs, convert_joint_motor,
+    joint_constraint_axes,
+};
+pub use kinematic::GpuKinematics;
 pub use mprops_update::{GpuMpropsUpdate, GpuSyncColliderPosesShader};
-#[cfg(feature = "dim3")]
 pub use multibody::{GpuMultibodySet, GpuMultibodySolver, MultibodySolverArgs};
+pub use param_updates::GpuParamUpdates;
 pub use prep_render::{RbdInstanceDesc, WgRbdPrepRender};
 pub use solver::{GpuSolver, SolverArgs};
 pub use warmstart::{GpuWarmstart, WarmstartArgs};
 
 pub mod body;
+mod body_updates;
+mod ccd;
 mod coloring;
+mod env_snapshots;
+mod islands;
 mod joint;
+mod kinematic;
 mod mprops_update;
-#[cfg(feature = "dim3")]
 pub(crate) mod multibody;
+mod param_updates;
 mod prep_render;
 mod solver;
 pub mod warmstart;
@@ -1,8 +1,10 @@
 //! Rigid-bodies world-space mass properties calculation.
 
 use crate::math::Pose;
+use crate::shaders::dynamics::{
+    BodyActivation, BodyParams, LocalMassProperties, WorldMassProperties,
+};
 use crate::shaders::dynamics::{GpuSyncColliderPoses, GpuUpdateMprops};
-use crate::shaders::dynamics::{LocalMassProperties, WorldMassProperties};
 use crate::shaders::utils::BatchIndices;
 use khal::Shader;
 use khal::backend::{GpuBackendError, GpuPass};
@@ -23,6 +25,8 @@ impl GpuMpropsUpdate {
         mprops: &mut Tensor<WorldMassProperties>,
         local_mprops: &Tensor<LocalMassProperties>,
         body_poses: &Tensor<Pose>,
+        body_params: &Tensor<BodyParams>,
+        activations: &Tensor<BodyActivation>,
         batch_indices: &Tensor<BatchIndices>,
         num_bodies: u32,
         num_batches: u32,
@@ -33,6 +37,8 @@ impl GpuMpropsUpdate {
             mprops,
             local_mprops,
             body_poses,
+            body_params,
+            activations,
             batch_indices,
         )?;
         Ok(())
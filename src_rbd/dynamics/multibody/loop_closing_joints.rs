@@ -2,14 +2,16 @@
 
 use super::multibody_set::*;
 use crate::shaders::dynamics::{
-    MAX_AXIS_CONSTRAINTS, MbImpulseJointBuilder, MbImpulseJointConstraint, SIDE_KIND_BODY,
-    SIDE_KIND_FIXED, SIDE_KIND_MB,
+    Force, MAX_AXIS_CONSTRAINTS, MbImpulseJointBuilder, MbImpulseJointConstraint, SIDE_KIND_BODY,
+    SIDE_KIND_FIXED, SIDE_KIND_MB, SPATIAL_DIM,
 };
 use khal::BufferUsages;
 use khal::backend::GpuBackend;
 use vortx::tensor::Tensor;
 use {
-    crate::rapier::dynamics::{ImpulseJointSet, MultibodyJointSet, RigidBodyHandle, RigidBodySet},
+    crate::rapier::dynamics::{
+        ImpulseJointHandle, ImpulseJointSet, MultibodyJointSet, RigidBodyHandle, RigidBodySet,
+    },
     std::collections::HashMap,
 };
 
@@ -40,6 +42,9 @@ impl GpuMultibodySet {
         // Stage 1 — per-batch list of touched joints + their side metadata.
         let mut per_env_builders: Vec<Vec<MbImpulseJointBuilder>> =
             Vec::with_capacity(self.num_batches as usize);
+        // Per-env handles of the touched joints, indexed by `joint_id`.
+        let mut per_env_handles: Vec<Vec<ImpulseJointHandle>> =
+            Vec::with_capacity(self.num_batches as usize);
         // Per-env color-group prefix sums (one Vec<u32> per batch), built
         // alongside the builders below. `global_num_colors` /
         // `global_max_color_group_len` are the cross-batch maxima used to
@@ -65,10 +70,11 @@ impl GpuMultibodySet {
             }
 
             let mut builders = Vec::new();
+            let mut handles = Vec::new();
             let mut jac_offset = 0u32;
             let mut constraint_id = 0u32;
 
-            for (_handle, joint) in impulse_joints.iter() {
+            for (handle, joint) in impulse_joints.iter() {
                 let body1 = joint.body1();
                 let body2 = joint.body2();
                 let local1 = match body_ids.get(&body1) {
@@ -96,7 +102,9 @@ impl GpuMultibodySet {
                         let mb = mb_set.multibodies().nth(mb_idx as usize).unwrap();
                         (SIDE_KIND_MB, mb_idx, link_idx, mb.ndofs() as u32)
                     }
-                    (None, Some(rb)) if rb.is_dynamic() => (SIDE_KIND_BODY, local1, 0, 6),
+                    (None, Some(rb)) if rb.is_dynamic() => {
+                        (SIDE_KIND_BODY, local1, 0, SPATIAL_DIM as u32)
+                    }
                     _ => (SIDE_KIND_FIXED, u32::MAX, 0, 0),
                 };
 
@@ -105,7 +113,9 @@ impl GpuMultibodySet {
                         let mb = mb_set.multibodies().nth(mb_idx as usize).unwrap();
                         (SIDE_KIND_MB, mb_idx, link_idx, mb.ndofs() as u32)
                     }
-                    (None, Some(rb)) if rb.is_dynamic() => (SIDE_KIND_BODY, local2, 0, 6),
+                    (None, Some(rb)) if rb.is_dynamic() => {
+                        (SIDE_KIND_BODY, local2, 0, SPATIAL_DIM as u32)
+                    }
                     _ => (SIDE_KIND_FIXED, u32::MAX, 0, 0),
                 };
 
@@ -161,6 +171,7 @@ impl GpuMultibodySet {
                     _pad0: [0; 2],
                 };
                 builders.push(builder);
+                handles.push(handle);
                 constraint_id += MAX_AXIS_CONSTRAINTS;
                 jac_offset += cap_floats;
             }
@@ -244,6 +255,7 @@ impl GpuMultibodySet {
 
             per_env_color_groups.push(color_groups);
             per_env_builders.push(sorted_builders);
+            per_env_handles.push(handles);
         }
 
         // Stage 2 — flatten with per-batch padding to `max_joints`.
@@ -284,6 +296,14 @@ impl GpuMultibodySet {
             storage,
         )
         .unwrap();
+        // COPY_SRC so the joint wrenches can be read back.
+        self.mb_imp_joint_wrenches = Tensor::vector(
+            backend,
+            vec![Force::default(); (joints_cap * self.num_batches) as usize],
+            storage | BufferUsages::COPY_SRC,
+        )
+        .unwrap();
+        self.mb_imp_joint_handles = per_env_handles;
         self.mb_imp_joints_per_batch = joints_cap;
         self.mb_imp_joint_constraints_per_batch = cons_cap;
         self.mb_imp_joint_jacobians_per_batch = jac_cap;
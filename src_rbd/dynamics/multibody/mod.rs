@@ -6,8 +6,6 @@
 //!
 //! Contacts and user-defined joint constraints are intentionally not handled.
 
-#![cfg(feature = "dim3")]
-
 mod loop_closing_joints;
 mod multibody_from_rapier;
 mod multibody_set;
@@ -1,10 +1,12 @@
 //! Conversion of rapier multibodies into the packed GPU buffers of [`GpuMultibodySet`].
 
 use super::multibody_set::*;
+use crate::math::DIM;
 use crate::shaders::dynamics::{
-    ConstraintSoftness, MAX_AXIS_CONSTRAINTS, MAX_MB_CONTACT_CONSTRAINTS_PER_MB, MbDofCoupling,
-    MbImpulseJointBuilder, MbImpulseJointConstraint, MultibodyContactConstraint, MultibodyInfo,
-    MultibodyJointConstraint, MultibodyLinkStatic, MultibodyLinkWorkspace, RbdSimParams,
+    ANG_AXES_MASK, ConstraintSoftness, Force, LIN_AXES_MASK, MAX_AXIS_CONSTRAINTS,
+    MAX_MB_CONTACT_CONSTRAINTS_PER_MB, MbDofCoupling, MbImpulseJointBuilder,
+    MbImpulseJointConstraint, MultibodyContactConstraint, MultibodyInfo, MultibodyJointConstraint,
+    MultibodyLinkStatic, MultibodyLinkWorkspace, RbdSimParams, SPATIAL_DIM,
 };
 use crate::shaders::utils::linalg::MAX_MB_DOFS;
 use khal::BufferUsages;
@@ -13,12 +15,14 @@ use vortx::tensor::Tensor;
 use {
     crate::rapier::dynamics::{MultibodyJointSet, RigidBodyHandle, RigidBodySet},
     std::collections::HashMap,
+    std::ops::Range,
 };
 
 impl GpuMultibodySet {
     /// Convert a slice of per-batch `(MultibodyJointSet, body_ids_map)` pairs into
     /// packed GPU buffers. `body_ids` maps each rapier `RigidBodyHandle` to the
     /// corresponding collider/body index used elsewhere (poses, mprops buffers).
+    /// `body_slots[b]` is the range of batch `b`'s body slots in these buffers.
     ///
     /// Root links must be the first link in their multibody (rapier guarantees
     /// this via assembly ids being assigned in traversal order).
@@ -29,7 +33,7 @@ impl GpuMultibodySet {
             &HashMap<RigidBodyHandle, u32>,
             &RigidBodySet,
         )],
-        colliders_per_batch: u32,
+        body_slots: &[Range<u32>],
     ) -> Self {
         let num_batches = environments.len() as u32;
 
@@ -95,10 +99,10 @@ impl GpuMultibodySet {
                     );
                 }
 
-                // rapier always creates the root with a free 6-DOF joint and only
+                // rapier always creates the root with a free joint and only
                 // converts it to a fixed joint later during its own step. Since we
                 // don't run rapier's step here, detect a fixed root body and lock
-                // all 6 DOFs ourselves.
+                // all its DOFs ourselves.
                 let root_is_dynamic = mb
                     .link(0)
                     .and_then(|r| bodies.get(r.rigid_body_handle()))
@@ -136,7 +140,7 @@ impl GpuMultibodySet {
                         let motor_axes = j.motor_axes.bits() as u32 & !locked;
                         // 1 per active limit + 1 per active motor (axis-wise).
                         let mut n = 0u32;
-                        for ax in 0u32..6 {
+                        for ax in 0..SPATIAL_DIM as u32 {
                             if (limit_axes >> ax) & 1 != 0 {
                                 n += 1;
                             }
@@ -197,10 +201,10 @@ impl GpuMultibodySet {
                         None => u32::MAX,
                     };
 
-                    // Lock all 6 DOFs on the root if its body is fixed.
+                    // Lock all the DOFs of the root if its body is fixed.
                     let mut data = convert_generic_joint(link.joint().data);
                     let link_ndofs = if link_idx == 0 && !root_is_dynamic {
-                        data.locked_axes = 0x3f;
+                        data.locked_axes = LIN_AXES_MASK | ANG_AXES_MASK;
                         0u32
                     } else {
                         link.joint().ndofs() as u32
@@ -220,8 +224,16 @@ impl GpuMultibodySet {
                     // contribute to the CRBA mass matrix (rapier skips them too).
                     let mp = if link_idx == 0 && !root_is_dynamic {
                         let mut z = mp;
-                        z.inv_mass = glamx::Vec3::ZERO;
-                        z.inv_principal_inertia = glamx::Vec3::ZERO;
+                        #[cfg(feature = "dim2")]
+                        {
+                            z.inv_mass = glamx::Vec2::ZERO;
+                            z.inv_inertia = 0.0;
+                        }
+                        #[cfg(feature = "dim3")]
+                        {
+                            z.inv_mass = glamx::Vec3::ZERO;
+                            z.inv_principal_inertia = glamx::Vec3::ZERO;
+                        }
                         z
                     } else {
                         mp
@@ -257,7 +269,16 @@ impl GpuMultibodySet {
                         let pos = rb.position();
                         ws.coords[0] = pos.translation.x;
                         ws.coords[1] = pos.translation.y;
-                        ws.coords[2] = pos.translation.z;
+                        // The 2D rotation is a plain coordinate, the 3D one only
+                        // lives in `joint_rot`.
+                        #[cfg(feature = "dim2")]
+                        {
+                            ws.coords[2] = pos.rotation.angle();
+                        }
+                        #[cfg(feature = "dim3")]
+                        {
+                            ws.coords[2] = pos.translation.z;
+                        }
                         ws.joint_rot = pos.rotation;
                     }
 
@@ -279,9 +300,9 @@ impl GpuMultibodySet {
                     // zero acceleration).
                     let locked = link.joint().data.locked_axes.bits() as u32;
                     let is_kin = link.joint().kinematic;
-                    let mut free_axis_of_dof = [0usize; 6];
+                    let mut free_axis_of_dof = [0usize; SPATIAL_DIM];
                     let mut nfree = 0usize;
-                    for ax in 0..6usize {
+                    for ax in 0..SPATIAL_DIM {
                         if (locked >> ax) & 1 == 0 {
                             free_axis_of_dof[nfree] = ax;
                             nfree += 1;
@@ -320,10 +341,10 @@ impl GpuMultibodySet {
 
                 first_link += num_links;
                 first_dof += ndofs;
-                jac_off += num_links * 6 * ndofs;
+                jac_off += num_links * SPATIAL_DIM as u32 * ndofs;
                 mm_off += ndofs * ndofs;
-                cor_off += num_links * 3 * ndofs;
-                icdt_off += 6 * ndofs;
+                cor_off += num_links * DIM as u32 * ndofs;
+                icdt_off += SPATIAL_DIM as u32 * ndofs;
                 cons_off += max_constraints;
                 coupling_off += num_couplings;
             }
@@ -372,20 +393,21 @@ impl GpuMultibodySet {
             .saturating_mul(MAX_MB_CONTACT_CONSTRAINTS_PER_MB)
             .max(1);
         let contact_cons_col_cap = contact_cons_cap.saturating_mul(dofs_cap).max(1);
-        let body_to_link_cap = colliders_per_batch.max(1);
+        let num_body_slots = body_slots.iter().map(|r| r.end).max().unwrap_or(0);
 
-        // Build the per-body multibody/link lookup. Free / non-multibody bodies
-        // get the sentinel `[u32::MAX, u32::MAX]`. The kernel reads
+        // Build the per-body multibody/link lookup, laid out like the body
+        // buffers. Free / non-multibody bodies get the sentinel
+        // `[u32::MAX, u32::MAX]`. The kernel reads
         // `body_to_link[batch_offset + body_local_id]` and skips the
         // sentinel.
         let mut all_body_to_link: Vec<[u32; 2]> =
-            vec![[u32::MAX, u32::MAX]; (body_to_link_cap * num_batches) as usize];
-        for (batch_idx, (set, body_ids, _)) in environments.iter().enumerate() {
-            let base = batch_idx * body_to_link_cap as usize;
+            vec![[u32::MAX, u32::MAX]; num_body_slots.max(1) as usize];
+        for ((set, body_ids, _), slots) in environments.iter().zip(body_slots) {
+            let base = slots.start as usize;
             for (mb_idx, mb) in set.multibodies().enumerate() {
                 for (link_idx, link) in mb.links().enumerate() {
                     if let Some(&local) = body_ids.get(&link.rigid_body_handle())
-                        && local < body_to_link_cap
+                        && local < slots.len() as u32
                     {
                         all_body_to_link[base + local as usize] = [mb_idx as u32, link_idx as u32];
                     }
@@ -511,10 +533,11 @@ impl GpuMultibodySet {
                 .unwrap(),
             links_static_mirror: all_statics.clone(),
             info_mirror,
+            // COPY_SRC so the joint coordinates can be read back.
             links_workspace: Tensor::vector(
                 backend,
                 crate::shaders::dynamics::ws_soa_from_structs(&all_ws, links_cap, num_batches),
-                storage,
+                storage | BufferUsages::COPY_SRC,
             )
             .unwrap(),
             dof_values: Tensor::vector(backend, &all_dof_vals, storage).unwrap(),
@@ -532,7 +555,7 @@ impl GpuMultibodySet {
                 buf.extend_from_slice(&all_dof_spring_ref);
                 buf.extend_from_slice(&all_dof_kinematic);
                 debug_assert_eq!(buf.len(), 6 * n);
-                Tensor::vector(backend, &buf, storage).unwrap()
+                Tensor::vector(backend, &buf, storage | BufferUsages::COPY_SRC).unwrap()
             },
             gen_forces: Tensor::vector(
                 backend,
@@ -581,6 +604,13 @@ impl GpuMultibodySet {
                 storage,
             )
             .unwrap(),
+            // COPY_SRC so the joint wrenches can be read back.
+            link_wrenches: Tensor::vector(
+                backend,
+                vec![Force::default(); (links_cap * num_batches) as usize],
+                storage | BufferUsages::COPY_SRC,
+            )
+            .unwrap(),
             dof_couplings: Tensor::vector(backend, &all_couplings, storage).unwrap(),
             couplings_per_batch: couplings_cap,
             body_to_link: Tensor::vector(backend, &all_body_to_link, storage).unwrap(),
@@ -673,6 +703,14 @@ impl GpuMultibodySet {
                 storage,
             )
             .unwrap(),
+            // COPY_SRC so the joint wrenches can be read back.
+            mb_imp_joint_wrenches: Tensor::vector(
+                backend,
+                vec![Force::default(); num_batches as usize],
+                storage | BufferUsages::COPY_SRC,
+            )
+            .unwrap(),
+            mb_imp_joint_handles: vec![Vec::new(); num_batches as usize],
             mb_imp_joints_per_batch: 0,
             mb_imp_joint_constraints_per_batch: MAX_AXIS_CONSTRAINTS,
             mb_imp_joint_jacobians_per_batch: 1,
@@ -1,16 +1,19 @@
 //! The [`GpuMultibodySet`] buffers: struct definition, accessors and
 //! runtime-mutation entry points (motors, dt, softness).
 
+#[cfg(feature = "dim3")]
+use crate::math::DIM;
 use crate::math::Pose;
+use crate::math::Rotation;
+use crate::rapier::prelude::{ImpulseJointHandle, JointAxis, MultibodyJointSet};
 use crate::shaders::dynamics::{
-    ConstraintSoftness, LocalMassProperties, MbDofCoupling, MbImpulseJointBuilder,
+    ConstraintSoftness, Force, LocalMassProperties, MbDofCoupling, MbImpulseJointBuilder,
     MbImpulseJointConstraint, MultibodyContactConstraint, MultibodyInfo, MultibodyJointConstraint,
-    MultibodyLinkStatic, MultibodyLinkWorkspace, RbdSimParams,
+    MultibodyLinkStatic, MultibodyLinkWorkspace, RbdSimParams, SPATIAL_DIM,
 };
 use crate::shaders::utils::BatchIndices;
 use khal::BufferUsages;
 use khal::backend::{Backend, GpuBackend, GpuBackendError};
-use rapier3d::prelude::JointAxis;
 use vortx::tensor::Tensor;
 
 /// Workgroup width for the parallelised LU decompose / solve kernels. Must
@@ -72,7 +75,7 @@ pub struct GpuMultibodySet {
     pub(super) dof_state: Tensor<f32>,
     /// Generalized forces / after solve, generalized accelerations.
     pub(super) gen_forces: Tensor<f32>,
-    /// Per-link `6 × ndofs` column-major jacobians.
+    /// Per-link `SPATIAL_DIM × ndofs` column-major jacobians.
     pub(super) body_jacobians: Tensor<f32>,
     /// Per-multibody `ndofs × ndofs` mass matrices (also used as LU work buffer).
     pub(super) mass_matrices: Tensor<f32>,
@@ -86,6 +89,9 @@ pub struct GpuMultibodySet {
     pub(super) joint_constraints: Tensor<MultibodyJointConstraint>,
     /// Per-constraint columns of `M⁻¹` (length `ndofs` each, contiguous per multibody).
     pub(super) joint_constraint_columns: Tensor<f32>,
+    /// Per-link joint wrench (joint frame) computed at the end of each step,
+    /// laid out like [`Self::links_static`].
+    pub(super) link_wrenches: Tensor<Force>,
     /// Per-batch slab of DoF couplings (rapier's `MultibodyDofCoupling`),
     /// batch-major; each multibody's slice is
     /// `[first_coupling, first_coupling + num_couplings)`.
@@ -123,6 +129,12 @@ pub struct GpuMultibodySet {
     /// Per-batch flat jacobians buffer — stores `J / W·J` for both sides
     /// of every axis constraint of every joint.
     pub(super) mb_imp_joint_jacobians: Tensor<f32>,
+    /// Per-batch reaction wrench of every multibody-touching impulse joint,
+    /// indexed by the joint's insertion order (not its color-sorted slot).
+    pub(super) mb_imp_joint_wrenches: Tensor<Force>,
+    /// Per-batch rapier handles of the multibody-touching impulse joints, in
+    /// the order of [`Self::mb_imp_joint_wrenches`].
+    pub(super) mb_imp_joint_handles: Vec<Vec<ImpulseJointHandle>>,
 
     /// Capacities (per-batch strides) for the impulse-joint slabs above.
     /// Mirrored into `BatchIndices` via [`Self::fill_batch_indices`].
@@ -183,6 +195,11 @@ impl GpuMultibodySet {
         self.multibodies_per_batch
     }
 
+    /// Capacity (max contact constraints) of the contact constraint slab per batch.
+    pub fn contact_constraints_per_batch(&self) -> u32 {
+        self.contact_constraints_per_batch
+    }
+
     /// Thread-count grid for the per-multibody kernels, with `(multibody,
     /// batch)` flattened into X. The kernels decode
     /// `batch_id = x / multibodies_len`, `mb_idx = x % multibodies_len`.
@@ -310,7 +327,7 @@ impl GpuMultibodySet {
     ///
     /// `link_id` is the global link id within the batch (it matches the body
     /// index given to [`from_rapier`](Self::from_rapier)) and `axis` indexes the
-    /// 6-DoF spatial layout (`0..DIM` linear, `DIM..` angular). Motors are baked
+    /// `SPATIAL_DIM` spatial layout (`0..DIM` linear, `DIM..` angular). Motors are baked
     /// into the GPU state at finalization, so per-step actuation has to come
     /// through here.
     pub fn set_motor(
@@ -321,7 +338,7 @@ impl GpuMultibodySet {
         axis: usize,
         motor: JointMotor,
     ) -> Result<(), GpuBackendError> {
-        if axis >= 6 {
+        if axis >= SPATIAL_DIM {
             return Ok(());
         }
         let global_idx = (link_id * self.num_batches + batch) as usize;
@@ -347,7 +364,7 @@ impl GpuMultibodySet {
     /// as last uploaded. Use it to adjust one field of a live motor without
     /// rebuilding the rest.
     pub fn motor(&self, batch: u32, link_id: u32, axis: usize) -> Option<JointMotor> {
-        if axis >= 6 {
+        if axis >= SPATIAL_DIM {
             return None;
         }
         let global_idx = (link_id * self.num_batches + batch) as usize;
@@ -356,6 +373,19 @@ impl GpuMultibodySet {
             .map(|e| e.data.motors[axis])
     }
 
+    /// Whether the motor on `axis` of multibody link `link_id` is enabled.
+    ///
+    /// The joint-constraint slots are sized at finalization from the motors
+    /// enabled in rapier, so only those can be driven reliably.
+    pub fn is_motor_enabled(&self, batch: u32, link_id: u32, axis: usize) -> bool {
+        let global_idx = (link_id * self.num_batches + batch) as usize;
+        axis < SPATIAL_DIM
+            && self
+                .links_static_mirror
+                .get(global_idx)
+                .is_some_and(|e| (e.data.motor_axes & !e.data.locked_axes) & (1 << axis) != 0)
+    }
+
     /// Batched [`Self::set_motor`]: applies every `(link_id, axis, motor)` and
     /// uploads each touched link once, rather than once per axis.
     ///
@@ -370,7 +400,7 @@ impl GpuMultibodySet {
     ) -> Result<(), GpuBackendError> {
         let mut touched: Vec<usize> = Vec::with_capacity(updates.len());
         for &(link_id, axis, motor) in updates {
-            if axis >= 6 {
+            if axis >= SPATIAL_DIM {
                 continue;
             }
             let global_idx = (link_id * self.num_batches + batch) as usize;
@@ -402,7 +432,7 @@ impl GpuMultibodySet {
     /// updated link to the GPU. `link_id` is the global link id within the
     /// batch (matches the body / collider index that was given to
     /// [`from_rapier`](Self::from_rapier)). `axis` is the joint axis index
-    /// (0..=2 for linear, 3..=5 for angular).
+    /// (`0..DIM` for linear, `DIM..SPATIAL_DIM` for angular).
     ///
     /// The motor is also auto-enabled (its bit is set in `motor_axes`) so the
     /// solver actually drives the joint at the requested velocity.
@@ -437,8 +467,31 @@ impl GpuMultibodySet {
         self.mb_imp_joints_per_batch
     }
 
+    /// Reaction wrenches of the multibody-touching impulse joints, computed at
+    /// the end of each step. Joint `j` of batch `b` is at
+    /// `b * mb_impulse_joints_per_batch() + j`, with `j` indexing
+    /// [`Self::impulse_joint_handles`].
+    ///
+    /// Like [`GpuImpulseJointSet::wrenches`](crate::dynamics::GpuImpulseJointSet::wrenches),
+    /// this is the wrench applied by the joint on its second side, with the
+    /// torque taken about the second anchor, expressed in the joint frame
+    /// attached to the first side.
+    pub fn impulse_joint_wrenches(&self) -> &Tensor<Force> {
+        &self.mb_imp_joint_wrenches
+    }
+
+    /// The rapier handles of the multibody-touching impulse joints of batch
+    /// `batch_id`, in the order of [`Self::impulse_joint_wrenches`].
+    pub fn impulse_joint_handles(&self, batch_id: u32) -> &[ImpulseJointHandle] {
+        self.mb_imp_joint_handles
+            .get(batch_id as usize)
+            .map(|h| h.as_slice())
+            .unwrap_or_default()
+    }
+
     /// Populate the multibody-owned fields of `BatchIndices`. Leaves the
-    /// RBD-side fields (`colliders_batch_capacity`, `contacts_batch_capacity`,
+    /// RBD-side fields (`colliders_batch_capacity`, the per-batch collider and
+    /// impulse-joint ranges, `contacts_batch_capacity`,
     /// `collision_pairs_batch_capacity`, `impulse_joints_batch_capacity`,
     /// `color_groups_batch_capacity`) untouched — the caller fills those.
     pub(crate) fn fill_batch_indices(&self, dst: &mut BatchIndices) {
@@ -557,6 +610,89 @@ impl GpuMultibodySet {
         &self.contact_constraint_columns
     }
 
+    /// Per-link joint wrenches, computed at the end of each step. Interleaved
+    /// across batches like the other per-link buffers: link `k` of batch `b` is
+    /// at `k * num_batches + b`.
+    ///
+    /// See [`GpuMultibodySolver::compute_joint_wrenches`](super::GpuMultibodySolver::compute_joint_wrenches)
+    /// for what is, and isn't, included.
+    pub fn link_wrenches(&self) -> &Tensor<Force> {
+        &self.link_wrenches
+    }
+
+    /// Capacity (max links) per batch.
+    pub fn links_per_batch(&self) -> u32 {
+        self.links_per_batch
+    }
+
+    /// The per-batch link index (the `k` of [`Self::link_wrenches`]) of the
+    /// multibody link backed by the body `body_id` of batch `batch_id`, if any.
+    pub fn link_of_body(&self, batch_id: u32, body_id: u32) -> Option<u32> {
+        let first_mb = (batch_id * self.multibodies_per_batch) as usize;
+        let infos = self
+            .info_mirror
+            .get(first_mb..first_mb + self.num_active_multibodies as usize)?;
+        infos
+            .iter()
+            .flat_map(|info| info.first_link..info.first_link + info.num_links)
+            .find(|k| {
+                self.links_static_mirror[(k * self.num_batches + batch_id) as usize].rb_id
+                    == body_id
+            })
+    }
+
+    /// The per-batch DOF index (the index of its velocity in [`Self::dof_state`])
+    /// of the joint axis `axis` of link `link_id` in batch `batch_id`, or `None`
+    /// if that axis is locked.
+    ///
+    /// `axis` is a joint axis index (`0..DIM` for linear, `DIM..SPATIAL_DIM` for
+    /// angular), like for [`Self::set_motor`].
+    pub fn link_dof(&self, batch_id: u32, link_id: u32, axis: usize) -> Option<u32> {
+        if axis >= SPATIAL_DIM || link_id >= self.links_per_batch {
+            return None;
+        }
+        let stat = self
+            .links_static_mirror
+            .get((link_id * self.num_batches + batch_id) as usize)?;
+        let free = !stat.data.locked_axes;
+        if free & (1 << axis) == 0 {
+            return None;
+        }
+        // Free DOFs are assembled in axis order, linear ones first.
+        let rank = (free & ((1 << axis) - 1)).count_ones();
+        if rank >= stat.ndofs {
+            return None;
+        }
+        let info = self
+            .info_mirror
+            .get((batch_id * self.multibodies_per_batch + stat.multibody_id) as usize)?;
+        Some(info.first_dof + stat.assembly_id + rank)
+    }
+
+    /// The batch-interleaved link workspace, holding the joint coordinates and
+    /// the external link wrenches.
+    pub(crate) fn links_workspace(&self) -> &Tensor<glamx::Vec4> {
+        &self.links_workspace
+    }
+
+    /// The GPU buffers written by per-step actuation: the link statics (motors)
+    /// and the link workspace (external wrenches).
+    ///
+    /// Writes through these bypass the CPU mirror read by [`Self::motor`].
+    pub(crate) fn actuation_buffers_mut(
+        &mut self,
+    ) -> (&mut Tensor<MultibodyLinkStatic>, &mut Tensor<glamx::Vec4>) {
+        (&mut self.links_static, &mut self.links_workspace)
+    }
+
+    /// The GPU buffers holding the randomizable multibody parameters: the link
+    /// statics (mass properties) and the DOF state (damping and armature).
+    pub(crate) fn parameter_buffers_mut(
+        &mut self,
+    ) -> (&mut Tensor<MultibodyLinkStatic>, &mut Tensor<f32>) {
+        (&mut self.links_static, &mut self.dof_state)
+    }
+
     /// Per-link `SPATIAL_DIM × ndofs` column-major body jacobians, indexed from
     /// each multibody's [`MultibodyInfo::jacobian_offset`].
     pub fn body_jacobians(&self) -> &Tensor<f32> {
@@ -587,7 +723,7 @@ impl GpuMultibodySet {
         for k in 0..self.links_per_batch {
             let stat = &self.links_static_mirror[(batch_id * self.links_per_batch + k) as usize];
             let locked = stat.data.locked_axes;
-            for axis in 0..6u32 {
+            for axis in 0..SPATIAL_DIM as u32 {
                 if locked & (1 << axis) == 0 {
                     out.push(ws_coord(&ws, a, k, axis));
                 }
@@ -596,6 +732,104 @@ impl GpuMultibodySet {
         Ok(out)
     }
 
+    /// Writes the joint coordinates and generalized velocities simulated on the
+    /// GPU back into rapier, given one [`MultibodyJointSet`] per batch (in batch
+    /// order).
+    ///
+    /// The sets must be the ones this set was built from: multibodies are
+    /// matched by iteration order. Rapier only exposes joint coordinates through
+    /// displacements, so each joint is displaced by the difference between its
+    /// GPU and rapier configurations. The link poses themselves are the body
+    /// poses and aren't touched here.
+    pub async fn writeback_to_rapier<'a>(
+        &self,
+        backend: &GpuBackend,
+        environments: impl IntoIterator<Item = &'a mut MultibodyJointSet>,
+    ) -> Result<(), GpuBackendError> {
+        #[cfg(feature = "dim3")]
+        use crate::shaders::dynamics::{WS_JOINT_ROT, ws_rot};
+        use crate::shaders::dynamics::{WsAddr, ws_coord};
+
+        if self.num_active_multibodies == 0 {
+            return Ok(());
+        }
+
+        let ws: Vec<glamx::Vec4> = backend.slow_read_vec(self.links_workspace.buffer()).await?;
+        // Only the velocity section (the first one) is used.
+        let dof_state: Vec<f32> = backend.slow_read_vec(self.dof_state.buffer()).await?;
+        let nb = self.num_batches as usize;
+
+        for (batch_id, set) in environments.into_iter().enumerate().take(nb) {
+            let a = WsAddr::new(0, self.num_batches, batch_id as u32);
+            let roots: Vec<_> = set
+                .multibodies()
+                .filter_map(|mb| Some(mb.link(0)?.rigid_body_handle()))
+                .collect();
+
+            for (mb_idx, root) in roots.into_iter().enumerate() {
+                let info =
+                    &self.info_mirror[batch_id * self.multibodies_per_batch as usize + mb_idx];
+                let Some(mb_id) = set.rigid_body_link(root).map(|link| link.multibody) else {
+                    continue;
+                };
+                let Some(mb) = set.get_multibody_mut(mb_id) else {
+                    continue;
+                };
+                // The GPU drops the DoFs of a fixed root (see `from_rapier`).
+                let skipped_root_dofs = mb.ndofs() - info.ndofs as usize;
+
+                let mut disp = vec![0.0; mb.ndofs()];
+                let mut rapier_assembly = 0;
+                for (link_idx, link) in mb.links().enumerate() {
+                    let joint = link.joint();
+                    let first_dof = rapier_assembly;
+                    rapier_assembly += joint.ndofs();
+                    if link_idx == 0 && skipped_root_dofs != 0 {
+                        continue;
+                    }
+
+                    // Same DoF order as rapier's `apply_displacement`: free
+                    // linear axes, then free angular axes.
+                    let k = info.first_link + link_idx as u32;
+                    let locked = joint.data.locked_axes.bits() as u32;
+                    let coords = joint.coords();
+                    let free_axes =
+                        (0..SPATIAL_DIM as u32).filter(|axis| locked & (1 << axis) == 0);
+                    // In 2D, the single angular DoF is always a plain coordinate.
+                    #[cfg(feature = "dim2")]
+                    for (dof, axis) in (first_dof..).zip(free_axes) {
+                        disp[dof] = ws_coord(&ws, a, k, axis) - coords[axis as usize];
+                    }
+                    #[cfg(feature = "dim3")]
+                    {
+                        let num_free_ang =
+                            free_axes.clone().filter(|axis| *axis >= DIM as u32).count();
+                        // Multi-DoF rotations are stored as `joint_rot`, not as coordinates.
+                        let rot_delta = (ws_rot(&ws, a, k, WS_JOINT_ROT)
+                            * joint.joint_rot().inverse())
+                        .to_scaled_axis();
+                        for (dof, axis) in (first_dof..).zip(free_axes) {
+                            disp[dof] = if axis < DIM as u32 || num_free_ang == 1 {
+                                ws_coord(&ws, a, k, axis) - coords[axis as usize]
+                            } else {
+                                rot_delta[axis as usize - DIM]
+                            };
+                        }
+                    }
+                }
+                mb.apply_displacements(&disp);
+
+                let mut vels = mb.generalized_velocity_mut();
+                for i in 0..info.ndofs as usize {
+                    vels[skipped_root_dofs + i] =
+                        dof_state[(info.first_dof as usize + i) * nb + batch_id];
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Upload a new integration timestep.
     pub fn set_dt(&mut self, backend: &GpuBackend, dt: f32) {
         self.dt = Tensor::scalar(
@@ -610,14 +844,26 @@ impl GpuMultibodySet {
 pub(super) fn convert_link_mprops(
     m: &crate::rapier::prelude::MassProperties,
 ) -> LocalMassProperties {
-    LocalMassProperties {
-        inertia_ref_frame: m.principal_inertia_local_frame,
-        inv_principal_inertia: m.inv_principal_inertia,
-        padding0: 0,
-        inv_mass: glamx::Vec3::splat(m.inv_mass),
-        padding1: 0,
-        com: m.local_com,
-        padding2: 0,
+    #[cfg(feature = "dim2")]
+    {
+        LocalMassProperties {
+            inv_mass: glamx::Vec2::splat(m.inv_mass),
+            com: m.local_com,
+            padding2: 0,
+            inv_inertia: m.inv_principal_inertia,
+        }
+    }
+    #[cfg(feature = "dim3")]
+    {
+        LocalMassProperties {
+            inertia_ref_frame: m.principal_inertia_local_frame,
+            inv_principal_inertia: m.inv_principal_inertia,
+            padding0: 0,
+            inv_mass: glamx::Vec3::splat(m.inv_mass),
+            padding1: 0,
+            com: m.local_com,
+            padding2: 0,
+        }
     }
 }
 
@@ -651,7 +897,7 @@ pub(super) fn convert_generic_joint(j: crate::rapier::dynamics::GenericJoint) ->
 
 pub(super) fn make_workspace_init() -> MultibodyLinkWorkspace {
     let mut w: MultibodyLinkWorkspace = bytemuck::Zeroable::zeroed();
-    w.joint_rot = glamx::Quat::IDENTITY;
+    w.joint_rot = Rotation::IDENTITY;
     w.gravity_scale = 1.0;
     w.local_to_parent = Pose::default();
     w.local_to_world = Pose::default();
@@ -5,14 +5,16 @@ use crate::math::Pose;
 use crate::queries::GpuIndexedContact;
 use crate::shaders::dynamics::{
     GpuMbApplyContactRestitution, GpuMbBuildContactDelassus, GpuMbComputeDynamicsPre,
-    GpuMbComputeSolveBounds, GpuMbFinalizeContactConstraints, GpuMbFinalizeImpulseJointConstraints,
-    GpuMbGravityAndLu, GpuMbGravityAndLuT1, GpuMbGravityAndLuT8, GpuMbGravityAndLuT16,
-    GpuMbGravityAndLuT32, GpuMbInitContactConstraints, GpuMbInitJointConstraints, GpuMbIntegrate,
-    GpuMbIntegrateVelocities, GpuMbRemoveImpulseJointConstraintBias, GpuMbSeedContactRestitution,
+    GpuMbComputeImpulseJointWrenches, GpuMbComputeJointWrenches, GpuMbComputeSolveBounds,
+    GpuMbCopyContactSnapshots, GpuMbCopyEnvSnapshots, GpuMbFinalizeContactConstraints,
+    GpuMbFinalizeImpulseJointConstraints, GpuMbGravityAndLu, GpuMbGravityAndLuT1,
+    GpuMbGravityAndLuT8, GpuMbGravityAndLuT16, GpuMbGravityAndLuT32, GpuMbInitContactConstraints,
+    GpuMbInitJointConstraints, GpuMbIntegrate, GpuMbIntegrateVelocities,
+    GpuMbRemoveImpulseJointConstraintBias, GpuMbSeedContactRestitution,
     GpuMbSnapshotContactWarmstart, GpuMbSolveConstraints, GpuMbSolveContactsDelassus,
     GpuMbSolveImpulseJointConstraints, GpuMbSolveJoints, GpuMbStashContactsLen,
     GpuMbTransferContactWarmstart, GpuMbUpdateImpulseJointConstraints,
-    GpuMbWarmstartContactConstraints, Velocity, WorldMassProperties,
+    GpuMbWarmstartContactConstraints, MultibodyContactConstraint, Velocity, WorldMassProperties,
 };
 use crate::shaders::utils::BatchIndices;
 use khal::Shader;
@@ -73,6 +75,18 @@ pub struct GpuMultibodySolver {
     remove_impulse_joint_constraint_bias: GpuMbRemoveImpulseJointConstraintBias,
     integrate_velocities: GpuMbIntegrateVelocities,
     integrate: GpuMbIntegrate,
+    /// Turns the joint-constraint impulses of the last substep into per-link
+    /// joint wrenches.
+    compute_joint_wrenches: GpuMbComputeJointWrenches,
+    /// Turns the impulses of the multibody-touching impulse joints of the last
+    /// substep into their reaction wrenches.
+    compute_impulse_joint_wrenches: GpuMbComputeImpulseJointWrenches,
+    /// Captures and restores the per-environment snapshots of the reduced
+    /// coordinates.
+    copy_env_snapshots: GpuMbCopyEnvSnapshots,
+    /// Captures and restores the per-environment snapshots of the contact
+    /// constraints.
+    copy_contact_snapshots: GpuMbCopyContactSnapshots,
 }
 
 /// Arguments for one multibody dispatch. The poses buffer is shared with the rest
@@ -96,7 +110,7 @@ pub struct MultibodySolverArgs<'a> {
     /// Shared `BatchIndices` uniform — per-batch caps and packed-section
     /// offsets read by every multibody kernel. Owned by `RbdState`.
     pub batch_indices: &'a Tensor<BatchIndices>,
-    /// The one gravity uniform every rigid-body and multibody kernel reads.
+    /// The per-batch gravity every rigid-body and multibody kernel reads.
     /// Owned by `RbdState`.
     pub gravity: &'a Tensor<glamx::Vec4>,
     /// Per-color-index uniform tensors (`color_uniforms[c]` holds `c`),
@@ -182,6 +196,56 @@ impl GpuMultibodySolver {
         Ok(())
     }
 
+    /// Captures the multibody state of the batches flagged in `ops` into
+    /// `ws_snapshots`/`dof_snapshots` and/or restores it from them (see the
+    /// `ENV_SNAPSHOT_*` flags).
+    ///
+    /// `ws_snapshots` is laid out like the link workspace, and `dof_snapshots`
+    /// holds two DOF sections (coordinates, then velocities).
+    /// `contact_snapshots` is laid out like the contact constraint slab, and
+    /// `contact_count_snapshots` holds the constraint count of each multibody,
+    /// so that restored batches warmstart from their snapshot contacts.
+    pub fn copy_env_snapshots(
+        &self,
+        pass: &mut GpuPass,
+        mb: &mut GpuMultibodySet,
+        ops: &Tensor<u32>,
+        ws_snapshots: &mut Tensor<glamx::Vec4>,
+        dof_snapshots: &mut Tensor<f32>,
+        contact_snapshots: &mut Tensor<MultibodyContactConstraint>,
+        contact_count_snapshots: &mut Tensor<u32>,
+        batch_indices: &Tensor<BatchIndices>,
+    ) -> Result<(), GpuBackendError> {
+        if mb.is_empty() {
+            return Ok(());
+        }
+        let num_quads = mb.links_per_batch * crate::shaders::dynamics::WS_QUADS;
+        self.copy_env_snapshots.call(
+            pass,
+            [num_quads.max(mb.dofs_per_batch), mb.num_batches, 1],
+            ops,
+            ws_snapshots,
+            dof_snapshots,
+            &mut mb.links_workspace,
+            &mut mb.dof_values,
+            &mut mb.dof_state,
+            batch_indices,
+        )?;
+        let num_threads = mb
+            .contact_constraints_per_batch
+            .max(mb.num_active_multibodies);
+        self.copy_contact_snapshots.call(
+            pass,
+            [num_threads, mb.num_batches, 1],
+            ops,
+            contact_snapshots,
+            contact_count_snapshots,
+            &mut mb.contact_constraints,
+            &mut mb.multibody_info,
+            batch_indices,
+        )
+    }
+
     // Per-substep work is split into five phases so the pipeline can interleave
     // them with the rigid-body substep: `substep_integrate_velocities` (P1),
     // `substep_build_constraints` (P2), `substep_solve_with_bias` (P3),
@@ -672,6 +736,48 @@ impl GpuMultibodySolver {
         )
     }
 
+    /// End-of-step pass writing the joint wrench of every link into
+    /// [`GpuMultibodySet::link_wrenches`], and the reaction wrench of every
+    /// multibody-touching impulse joint into
+    /// [`GpuMultibodySet::impulse_joint_wrenches`].
+    ///
+    /// The link wrench is the one applied by the link's joint on the link, in
+    /// the joint frame, computed from the impulses of the last substep divided
+    /// by the substep length. Reduced coordinates don't generate any constraint
+    /// force along locked axes, so only the limit, motor and DoF-coupling
+    /// efforts along the free axes are reported. Impulse joints are solved as
+    /// explicit constraints, so their wrenches cover every axis.
+    pub fn compute_joint_wrenches(
+        &self,
+        pass: &mut GpuPass,
+        mb: &mut GpuMultibodySet,
+        args: &mut MultibodySolverArgs<'_>,
+    ) -> Result<(), GpuBackendError> {
+        if mb.mb_imp_joints_per_batch > 0 {
+            self.compute_impulse_joint_wrenches.call(
+                pass,
+                [mb.mb_imp_joints_per_batch, mb.num_batches, 1],
+                &mb.mb_imp_joint_builders,
+                &mb.mb_imp_joint_constraints,
+                &mut mb.mb_imp_joint_wrenches,
+                &mb.dt,
+                args.batch_indices,
+            )?;
+        }
+        if mb.is_empty() {
+            return Ok(());
+        }
+        self.compute_joint_wrenches.call(
+            pass,
+            mb.flat_mb_dispatch(),
+            &mb.multibody_info,
+            &mb.joint_constraints,
+            &mut mb.link_wrenches,
+            &mb.dt,
+            args.batch_indices,
+        )
+    }
+
     /// Recompute the dynamics (mass matrix, LU factors, generalized
     /// acceleration). After this call, `gen_forces` holds the generalized
     /// acceleration `a` for the *next* substep's velocity update.
@@ -0,0 +1,57 @@
+//! Per-environment writes to the physical parameters.
+
+use crate::queries::GpuColliderMaterial;
+use crate::shaders::dynamics::{
+    GpuApplyParamUpdates, LocalMassProperties, MultibodyLinkStatic, ParamUpdate, ParamUpdatesHeader,
+};
+use crate::shaders::utils::BatchIndices;
+use khal::Shader;
+use khal::backend::{GpuBackendError, GpuPass};
+use vortx::tensor::Tensor;
+
+/// GPU shader scattering the queued parameter writes (friction, restitution,
+/// mass, DOF damping and armature, gravity) into the parameter buffers,
+/// sampling the randomized ones.
+#[derive(Shader)]
+pub struct GpuParamUpdates {
+    /// Compute pipeline for the scatter kernel.
+    apply_updates: GpuApplyParamUpdates,
+}
+
+impl GpuParamUpdates {
+    /// Dispatches the scatter kernel over the first `num_updates` entries of `updates`.
+    ///
+    /// Must run before the world mass properties are refreshed and before the
+    /// multibody step setup.
+    pub fn dispatch(
+        &self,
+        pass: &mut GpuPass,
+        updates: &Tensor<ParamUpdate>,
+        collider_materials: &mut Tensor<GpuColliderMaterial>,
+        local_mprops: &mut Tensor<LocalMassProperties>,
+        links_static: &mut Tensor<MultibodyLinkStatic>,
+        dof_state: &mut Tensor<f32>,
+        gravity: &mut Tensor<glamx::Vec4>,
+        header: &Tensor<ParamUpdatesHeader>,
+        batch_indices: &Tensor<BatchIndices>,
+        num_updates: u32,
+    ) -> Result<(), GpuBackendError> {
+        if num_updates == 0 {
+            return Ok(());
+        }
+
+        self.apply_updates.call(
+            pass,
+            [num_updates, 1, 1],
+            updates,
+            collider_materials,
+            local_mprops,
+            links_static,
+            dof_state,
+            gravity,
+            header,
+            batch_indices,
+        )?;
+        Ok(())
+    }
+}
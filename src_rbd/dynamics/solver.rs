@@ -5,17 +5,16 @@
 //! `Soft-TGS` algorithm (as in Rapier).
 
 use crate::dynamics::joint::{GpuJointSolver, JointSolverArgs};
-#[cfg(feature = "dim3")]
 use crate::dynamics::multibody::{GpuMultibodySet, GpuMultibodySolver, MultibodySolverArgs};
 use crate::math::Pose;
 use crate::queries::GpuIndexedContact;
 use crate::shaders::dynamics::{
-    GpuApplySolverVelsInc, GpuInitSolverBodies, GpuInitSolverVelsInc, GpuIntegrateLinearized,
-    GpuSolverCleanup, GpuSolverCountConstraints, GpuSolverFinalize, GpuSolverInitConstraints,
-    GpuSolverRefreshRhsWoBias, GpuSolverSortConstraints, GpuSolverUpdateConstraints,
-    GpuStepGaussSeidel, GpuStepGaussSeidelFused, GpuWarmstart, GpuWarmstartFused,
-    GpuWarmstartWithoutColors, LocalMassProperties, RbdSimParams, TwoBodyConstraint,
-    TwoBodyConstraintBuilder, Velocity, WorldMassProperties,
+    BodyParams, Force, GpuApplySolverVelsInc, GpuInitSolverBodies, GpuInitSolverVelsInc,
+    GpuIntegrateLinearized, GpuSolverCleanup, GpuSolverCountConstraints, GpuSolverFinalize,
+    GpuSolverInitConstraints, GpuSolverRefreshRhsWoBias, GpuSolverSortConstraints,
+    GpuSolverUpdateConstraints, GpuStepGaussSeidel, GpuStepGaussSeidelFused, GpuWarmstart,
+    GpuWarmstartFused, GpuWarmstartWithoutColors, LocalMassProperties, RbdSimParams,
+    TwoBodyConstraint, TwoBodyConstraintBuilder, Velocity, WorldMassProperties,
 };
 use crate::utils::{GpuPrefixSum, PrefixSumWorkspace};
 use khal::Shader;
@@ -110,6 +109,12 @@ pub struct SolverArgs<'a> {
     pub mprops: &'a Tensor<WorldMassProperties>,
     /// Local-space mass properties.
     pub local_mprops: &'a Tensor<LocalMassProperties>,
+    /// Per-body type (see [`crate::shaders::dynamics::BODY_TYPE_DYNAMIC`] and siblings).
+    pub body_types: &'a Tensor<u32>,
+    /// Per-body gravity scale, damping, locked axes and dominance group.
+    pub body_params: &'a Tensor<BodyParams>,
+    /// Per-body persistent user force and torque.
+    pub forces: &'a Tensor<Force>,
     /// Number of constraints per body.
     ///
     /// All constraints of all the bodies part of the same multibody are counted in a single
@@ -148,7 +153,7 @@ pub struct SolverArgs<'a> {
     pub rb_contacts_inert: bool,
     /// Shared per-batch indices.
     pub batch_indices: &'a Tensor<crate::shaders::utils::BatchIndices>,
-    /// The one gravity uniform every rigid-body and multibody kernel reads.
+    /// The per-batch gravity every rigid-body and multibody kernel reads.
     pub gravity: &'a Tensor<glamx::Vec4>,
     /// GPU-written workgroup grid for the per-multibody contact-constraint
     /// dispatches (zero workgroups on contact-free steps).
@@ -164,7 +169,7 @@ impl GpuSolver {
         args: SolverArgs<'a>,
         prefix_sum_workspace: &'a mut PrefixSumWorkspace,
     ) -> Result<(), GpuBackendError> {
-        // Cleanup zeroes body_constraint_counts, solver_vels, vels, mprops.
+        // Cleanup zeroes body_constraint_counts and seeds solver_vels from vels.
         self.cleanup.call(
             pass,
             [args.num_colliders, args.num_batches, 1],
@@ -172,6 +177,7 @@ impl GpuSolver {
             args.solver_vels,
             args.vels,
             args.mprops,
+            args.body_types,
             args.batch_indices,
         )?;
 
@@ -254,10 +260,9 @@ impl GpuSolver {
         joint_solver: &GpuJointSolver,
         args: SolverArgs<'a>,
         mut joint_args: JointSolverArgs<'a>,
-        #[cfg(feature = "dim3")] multibody: Option<(&GpuMultibodySolver, &mut GpuMultibodySet)>,
+        multibody: Option<(&GpuMultibodySolver, &mut GpuMultibodySet)>,
     ) -> Result<(), GpuBackendError> {
         let num_substeps = args.num_solver_iterations;
-        #[cfg(feature = "dim3")]
         let (mb_solver, mut mb_state) = match multibody {
             Some((s, st)) => (Some(s), Some(st)),
             None => (None, None),
@@ -277,6 +282,8 @@ impl GpuSolver {
                     [args.num_colliders, args.num_batches, 1],
                     args.solver_vels_inc,
                     args.mprops,
+                    args.body_params,
+                    args.forces,
                     args.sim_params,
                     args.batch_indices,
                     args.gravity,
@@ -287,7 +294,6 @@ impl GpuSolver {
 
             // Bound for the per-substep contact scans: runs after the narrow
             // phase wrote `contacts_len`, before the first substep build.
-            #[cfg(feature = "dim3")]
             if let (Some(solver), Some(state)) = (mb_solver, mb_state.as_deref_mut()) {
                 let mut mb_args = MultibodySolverArgs {
                     poses: &mut *args.solver_body_poses,
@@ -311,7 +317,6 @@ impl GpuSolver {
         // phase.
         macro_rules! mb_phase {
             ($label:expr, $method:ident $(, $extra:expr)*) => {{
-                #[cfg(feature = "dim3")]
                 if let (Some(solver), Some(state)) = (mb_solver, mb_state.as_deref_mut()) {
                     let mut pass = encoder.begin_pass($label, timestamps.as_deref_mut());
                     let mut mb_args = MultibodySolverArgs {
@@ -333,9 +338,6 @@ impl GpuSolver {
 
         for substep_id in 0..num_substeps {
             let is_last_substep = substep_id == num_substeps - 1;
-            // Only consumed by the dim3-only multibody phases.
-            #[cfg(not(feature = "dim3"))]
-            let _ = is_last_substep;
 
             /*
              * Integrate velocities (apply `a · dt'` / gravity increment).
@@ -357,7 +359,6 @@ impl GpuSolver {
              * Build + warmstart constraints.
              */
             {
-                #[cfg(feature = "dim3")]
                 if let (Some(solver), Some(state)) = (mb_solver, mb_state.as_deref_mut()) {
                     let mut mb_args = MultibodySolverArgs {
                         poses: &mut *args.solver_body_poses,
@@ -499,6 +500,7 @@ impl GpuSolver {
                     [args.num_colliders, args.num_batches, 1],
                     args.solver_body_poses,
                     args.solver_vels,
+                    args.body_params,
                     args.sim_params,
                     args.batch_indices,
                 )?;
@@ -560,13 +562,17 @@ impl GpuSolver {
         }
 
         mb_phase!("[RBD] slv/mb-restitution", apply_restitution);
+        mb_phase!("[RBD] slv/mb-joint-wrenches", compute_joint_wrenches);
 
         /*
-         * Writeback body velocities and convert COM-centered solver poses
-         * back to body-origin poses.
+         * Compute the joint reaction wrenches (from the COM-centered solver
+         * poses), then writeback body velocities and convert COM-centered
+         * solver poses back to body-origin poses.
          */
         {
             let mut pass = encoder.begin_pass("[RBD] slv/finalize", timestamps);
+            joint_solver.compute_wrenches(&mut pass, &mut joint_args, args.solver_body_poses)?;
+            joint_solver.break_joints(&mut pass, &mut joint_args)?;
             self.finalize.call(
                 &mut pass,
                 [args.num_colliders, args.num_batches, 1],
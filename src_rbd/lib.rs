@@ -28,7 +28,9 @@ pub static SPIRV_DIR: Dir<'static> = include_dir!("$OUT_DIR/shaders-spirv");
 
 // Re-export commonly used types from shader crate
 pub use shaders::bounding_volumes::Aabb;
-pub use shaders::dynamics::{Force, Impulse, LocalMassProperties, Velocity, WorldMassProperties};
+pub use shaders::dynamics::{
+    BodyActivation, Force, Impulse, LocalMassProperties, Velocity, WorldMassProperties,
+};
 pub use shaders::shapes::Shape;
 pub use shaders::{Pad, PaddedVector};
 
@@ -50,6 +52,9 @@ pub mod shapes;
 /// Utilities (GPU radix sort, prefix sum, etc.).
 pub mod utils;
 
+#[cfg(test)]
+mod tests;
+
 #[cfg(feature = "dim3")]
 pub mod math {
     //! Dimension-specific type aliases (3D).
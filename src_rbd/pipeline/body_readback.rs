@@ -0,0 +1,97 @@
+//! Non-blocking GPU→CPU readback of the body poses and velocities.
+
+use super::RbdState;
+use crate::math::Pose;
+use crate::shaders::dynamics::Velocity as GpuVelocity;
+use khal::backend::{GpuBackend, GpuBackendError, GpuReadback};
+
+/// Non-blocking readback of [`RbdState`]'s body poses and velocities, polled by
+/// [`RbdState::poll_body_states`].
+///
+/// Holds the most recently harvested snapshot. Since bodies may be relocated by
+/// [`RbdState::remove_bodies`], every readback is tagged with the layout epoch it
+/// was requested at, and discarded if the layout changed in the meantime.
+pub(super) struct BodyStatesReadback {
+    poses: GpuReadback<Pose>,
+    vels: GpuReadback<GpuVelocity>,
+    poses_cpu: Vec<Pose>,
+    vels_cpu: Vec<GpuVelocity>,
+    /// Incremented whenever bodies move to a different slot.
+    epoch: u32,
+    requested_epoch: u32,
+    /// Whether each half of the readback in flight was harvested already.
+    poses_taken: bool,
+    vels_taken: bool,
+    /// Whether `poses_cpu`/`vels_cpu` hold a snapshot matching the current layout.
+    ready: bool,
+}
+
+impl BodyStatesReadback {
+    pub(super) fn new(backend: &GpuBackend, num_bodies: usize) -> Self {
+        Self {
+            poses: GpuReadback::new(backend, num_bodies).unwrap(),
+            vels: GpuReadback::new(backend, num_bodies).unwrap(),
+            poses_cpu: vec![Pose::default(); num_bodies],
+            vels_cpu: vec![GpuVelocity::default(); num_bodies],
+            epoch: 0,
+            requested_epoch: 0,
+            poses_taken: false,
+            vels_taken: false,
+            ready: false,
+        }
+    }
+
+    /// Discards the current snapshot and any readback in flight: the body slots
+    /// they refer to are stale.
+    pub(super) fn invalidate(&mut self) {
+        self.epoch = self.epoch.wrapping_add(1);
+        self.ready = false;
+    }
+}
+
+impl RbdState {
+    /// Polls the non-blocking readback of the body poses and velocities.
+    ///
+    /// Harvests the readback in flight if it completed, then requests a new one
+    /// if none is pending. Call this once per frame, after the step was submitted.
+    /// Returns `true` if a snapshot is available through [`Self::body_state`];
+    /// like the auto-resize readback, it lags the GPU by a frame or two.
+    pub fn poll_body_states(&mut self, backend: &GpuBackend) -> Result<bool, GpuBackendError> {
+        let readback = &mut self.body_states_readback;
+        // Both halves are requested together but may complete at different polls.
+        readback.poses_taken |= readback.poses.try_take(backend, &mut readback.poses_cpu);
+        readback.vels_taken |= readback.vels.try_take(backend, &mut readback.vels_cpu);
+        if readback.poses_taken && readback.vels_taken {
+            readback.poses_taken = false;
+            readback.vels_taken = false;
+            readback.ready = readback.requested_epoch == readback.epoch;
+        }
+
+        if readback.poses.is_idle() && readback.vels.is_idle() {
+            readback.requested_epoch = readback.epoch;
+            readback.poses.request(
+                backend,
+                &[(self.body_poses.buffer(), 0, self.body_poses.len())],
+            )?;
+            readback
+                .vels
+                .request(backend, &[(self.vels.buffer(), 0, self.vels.len())])?;
+        }
+
+        Ok(readback.ready)
+    }
+
+    /// The body-origin world pose and the velocity of the body at the global slot
+    /// `body_id`, from the latest snapshot harvested by [`Self::poll_body_states`].
+    ///
+    /// Returns `None` if no snapshot is available yet.
+    pub fn body_state(&self, body_id: u32) -> Option<(Pose, GpuVelocity)> {
+        let readback = &self.body_states_readback;
+        if !readback.ready {
+            return None;
+        }
+        let pose = *readback.poses_cpu.get(body_id as usize)?;
+        let vel = *readback.vels_cpu.get(body_id as usize)?;
+        Some((pose, vel))
+    }
+}
@@ -0,0 +1,232 @@
+//! Per-environment snapshots of the simulation state, restored in place.
+
+use super::RbdState;
+use crate::shaders::dynamics::{
+    BodySnapshot, ENV_SNAPSHOT_CAPTURE, ENV_SNAPSHOT_DROP_WARMSTART, ENV_SNAPSHOT_RESTORE,
+    MultibodyContactConstraint, TwoBodyConstraint, TwoBodyConstraintBuilder, WS_QUADS,
+};
+use khal::BufferUsages;
+use khal::backend::{GpuBackend, GpuBackendError};
+use vortx::tensor::Tensor;
+
+/// The per-batch snapshots of [`RbdState`], with the captures and restorations
+/// queued since the last step.
+pub(super) struct EnvSnapshots {
+    pub(super) bodies: Tensor<BodySnapshot>,
+    /// The contact warmstart, laid out like the old constraints, builders and
+    /// per-body constraint ids of [`RbdState`].
+    pub(super) contacts: Tensor<TwoBodyConstraint>,
+    pub(super) contact_builders: Tensor<TwoBodyConstraintBuilder>,
+    pub(super) body_constraint_ids: Tensor<u32>,
+    /// The per-batch contact capacity the contact warmstart was allocated for.
+    contacts_capacity: u32,
+    /// The enabled flag of each impulse joint, laid out like the joints.
+    pub(super) joints_enabled: Tensor<u32>,
+    /// Laid out like the multibody link workspace.
+    pub(super) mb_workspace: Tensor<glamx::Vec4>,
+    /// Generalized coordinates then velocities, interleaved across batches.
+    pub(super) mb_dofs: Tensor<f32>,
+    /// Laid out like the multibody contact constraint slab.
+    pub(super) mb_contacts: Tensor<MultibodyContactConstraint>,
+    /// The contact constraint count of each multibody, laid out like the
+    /// multibody infos.
+    pub(super) mb_contact_counts: Tensor<u32>,
+    /// Per-batch combination of the `ENV_SNAPSHOT_*` flags.
+    pub(super) ops: Tensor<u32>,
+    ops_cpu: Vec<u32>,
+    /// Whether any flag of `ops_cpu` is set.
+    pending: bool,
+    /// Whether each batch was captured already.
+    captured: Vec<bool>,
+    /// Whether the contact warmstart of each batch was captured since the
+    /// contact buffers were last reallocated.
+    warmstart_captured: Vec<bool>,
+}
+
+impl EnvSnapshots {
+    fn new(backend: &GpuBackend, state: &RbdState) -> Self {
+        let nb = state.num_batches;
+        let storage = BufferUsages::STORAGE;
+        // The multibody snapshots are never bound when there are no multibodies.
+        let mb = &state.multibodies;
+        let (ws_len, dofs_len, mb_contacts_len, mb_counts_len) = if mb.is_empty() {
+            (1, 1, 1, 1)
+        } else {
+            (
+                mb.links_per_batch() * WS_QUADS * nb,
+                2 * mb.dofs_per_batch() * nb,
+                mb.contact_constraints_per_batch().max(1) * nb,
+                mb.multibodies_per_batch() * nb,
+            )
+        };
+        // Same for the joint snapshots without joints.
+        let joints_len = (state.joints.gpu_joints().len() as u32).max(1);
+        let contacts_capacity = state.contacts_per_batch_cpu;
+        let (contacts, contact_builders, body_constraint_ids) =
+            Self::contact_buffers(backend, contacts_capacity, nb);
+
+        Self {
+            bodies: Tensor::vector_uninit(backend, state.num_body_slots(), storage).unwrap(),
+            contacts,
+            contact_builders,
+            body_constraint_ids,
+            contacts_capacity,
+            joints_enabled: Tensor::vector_uninit(backend, joints_len, storage).unwrap(),
+            mb_workspace: Tensor::vector_uninit(backend, ws_len, storage).unwrap(),
+            mb_dofs: Tensor::vector_uninit(backend, dofs_len, storage).unwrap(),
+            mb_contacts: Tensor::vector_uninit(backend, mb_contacts_len, storage).unwrap(),
+            mb_contact_counts: Tensor::vector_uninit(backend, mb_counts_len, storage).unwrap(),
+            ops: Tensor::vector(
+                backend,
+                vec![0u32; nb as usize],
+                storage | BufferUsages::COPY_DST,
+            )
+            .unwrap(),
+            ops_cpu: vec![0; nb as usize],
+            pending: false,
+            captured: vec![false; nb as usize],
+            warmstart_captured: vec![false; nb as usize],
+        }
+    }
+
+    /// Allocates the contact warmstart snapshots for `capacity` contacts per batch.
+    fn contact_buffers(
+        backend: &GpuBackend,
+        capacity: u32,
+        num_batches: u32,
+    ) -> (
+        Tensor<TwoBodyConstraint>,
+        Tensor<TwoBodyConstraintBuilder>,
+        Tensor<u32>,
+    ) {
+        let storage = BufferUsages::STORAGE;
+        let len = capacity.max(1) * num_batches;
+        (
+            Tensor::vector_uninit(backend, len, storage).unwrap(),
+            Tensor::vector_uninit(backend, len, storage).unwrap(),
+            Tensor::vector_uninit(backend, 2 * len, storage).unwrap(),
+        )
+    }
+}
+
+/// Per-environment snapshots.
+///
+/// Like the body updates, captures and restorations are queued on the CPU and
+/// applied by a single dispatch at the beginning of the next
+/// [`RbdPipeline::step`](crate::pipeline::RbdPipeline::step), before the queued
+/// body updates.
+impl RbdState {
+    /// Captures the state of batch `batch_id` as its snapshot, replacing the
+    /// previous one.
+    ///
+    /// The snapshot holds the body poses, velocities, persistent forces and
+    /// sleep states, the contact warmstart of the rigid bodies and multibodies,
+    /// whether each impulse joint is enabled, and the multibody generalized
+    /// coordinates and velocities. It is taken at the beginning of the next
+    /// step, before the body updates queued until then are applied.
+    pub fn snapshot_batch(&mut self, backend: &GpuBackend, batch_id: u32) {
+        assert!(batch_id < self.num_batches, "batch index out of bounds");
+        if self.env_snapshots.is_none() {
+            self.env_snapshots = Some(EnvSnapshots::new(backend, self));
+        }
+        let snapshots = self.env_snapshots.as_mut().unwrap();
+        snapshots.ops_cpu[batch_id as usize] |= ENV_SNAPSHOT_CAPTURE;
+        snapshots.captured[batch_id as usize] = true;
+        snapshots.pending = true;
+    }
+
+    /// Whether batch `batch_id` has a snapshot to be reset to.
+    pub fn has_batch_snapshot(&self, batch_id: u32) -> bool {
+        self.env_snapshots
+            .as_ref()
+            .is_some_and(|s| s.captured.get(batch_id as usize).copied().unwrap_or(false))
+    }
+
+    /// Resets every batch `i` with `mask[i] == true` to its snapshot.
+    ///
+    /// Restored batches get back the sleep states, contact warmstart and joint
+    /// enabled flags of their snapshot (repairing the joints broken since the
+    /// capture), so they carry on exactly as after the capture. The impulse
+    /// joints have no warmstart to restore: their impulses restart from zero
+    /// every substep.
+    ///
+    /// The rigid-body contact warmstart is dropped instead if the contact
+    /// buffers were reallocated since the capture, see
+    /// [`RbdPipeline::auto_resize_buffers`](crate::pipeline::RbdPipeline::auto_resize_buffers).
+    ///
+    /// # Panics
+    /// Panics if `mask` isn't one entry per batch, or if a masked batch has no
+    /// snapshot (see [`Self::snapshot_batch`]).
+    pub fn reset_batches(&mut self, mask: &[bool]) {
+        assert_eq!(
+            mask.len(),
+            self.num_batches as usize,
+            "the reset mask must have one entry per batch"
+        );
+        if !mask.contains(&true) {
+            return;
+        }
+        let snapshots = self
+            .env_snapshots
+            .as_mut()
+            .expect("no batch snapshot was captured");
+        for (batch_id, _) in mask.iter().enumerate().filter(|(_, reset)| **reset) {
+            assert!(
+                snapshots.captured[batch_id],
+                "batch {batch_id} has no snapshot"
+            );
+            snapshots.ops_cpu[batch_id] |= ENV_SNAPSHOT_RESTORE;
+        }
+        snapshots.pending = true;
+    }
+
+    /// Uploads the queued captures and restorations to the GPU, and returns
+    /// whether there are any.
+    ///
+    /// They must then be dispatched with [`crate::dynamics::GpuEnvSnapshots`],
+    /// [`crate::dynamics::GpuJointSolver::copy_env_snapshots`] and
+    /// [`crate::dynamics::GpuMultibodySolver::copy_env_snapshots`].
+    pub(super) fn upload_env_snapshot_ops(
+        &mut self,
+        backend: &GpuBackend,
+    ) -> Result<bool, GpuBackendError> {
+        let Some(snapshots) = self.env_snapshots.as_mut() else {
+            return Ok(false);
+        };
+        if !snapshots.pending {
+            return Ok(false);
+        }
+
+        // The contact buffers are reallocated (uninitialized) when they grow,
+        // which invalidates the contact warmstart captured until then.
+        if snapshots.contacts_capacity != self.contacts_per_batch_cpu {
+            snapshots.contacts_capacity = self.contacts_per_batch_cpu;
+            (
+                snapshots.contacts,
+                snapshots.contact_builders,
+                snapshots.body_constraint_ids,
+            ) = EnvSnapshots::contact_buffers(
+                backend,
+                self.contacts_per_batch_cpu,
+                self.num_batches,
+            );
+            snapshots.warmstart_captured.fill(false);
+        }
+        for (op, warmstart_captured) in snapshots
+            .ops_cpu
+            .iter_mut()
+            .zip(snapshots.warmstart_captured.iter_mut())
+        {
+            if *op & ENV_SNAPSHOT_CAPTURE != 0 {
+                *warmstart_captured = true;
+            } else if *op & ENV_SNAPSHOT_RESTORE != 0 && !*warmstart_captured {
+                *op |= ENV_SNAPSHOT_DROP_WARMSTART;
+            }
+        }
+
+        backend.write_buffer(snapshots.ops.buffer_mut(), 0, &snapshots.ops_cpu)?;
+        snapshots.ops_cpu.fill(0);
+        snapshots.pending = false;
+        Ok(true)
+    }
+}
@@ -0,0 +1,378 @@
+//! Dense per-environment observation and action tensors for policies running on
+//! the GPU.
+
+use super::RbdState;
+use crate::shaders::dynamics::{
+    ACCELERATION_BASED, ACT_BODY_FORCE, ACT_JOINT_EFFORT, ACT_LINK_WRENCH, ACT_MOTOR_POSITION,
+    ACT_MOTOR_VELOCITY, EnvTerm, EnvTermsShape, GpuGatherObservations, GpuScatterActions,
+    JointMotor, OBS_BODY_POSE, OBS_BODY_POSE_LEN, OBS_BODY_VELOCITY, OBS_BODY_VELOCITY_LEN,
+    OBS_CONTACT_FLAG, OBS_JOINT_POSITION, OBS_JOINT_VELOCITY, SPATIAL_DIM,
+};
+use khal::backend::{GpuBackend, GpuBackendError, GpuPass};
+use khal::{BufferUsages, Shader};
+use vortx::tensor::Tensor;
+
+/// Damping of the motors driven by [`RbdAction::JointEffort`]. Large enough for
+/// the motor to always saturate at the requested effort.
+const JOINT_EFFORT_MOTOR_DAMPING: f32 = 1.0e6;
+
+/// GPU shaders for the observation and action tensors.
+#[derive(Shader)]
+pub struct GpuEnvTensors {
+    gather_observations: GpuGatherObservations,
+    scatter_actions: GpuScatterActions,
+}
+
+/// A value observed in every environment.
+///
+/// Body ids are local body slots, below the capacity of every batch (see
+/// [`RbdBatchRange`](super::RbdBatchRange)), and link ids are per-batch
+/// multibody link indices (see
+/// [`GpuMultibodySet::link_of_body`](crate::dynamics::GpuMultibodySet::link_of_body)).
+/// Joint axes index the `SPATIAL_DIM` spatial layout (`0..DIM` linear, `DIM..`
+/// angular).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RbdObservation {
+    /// The body-origin world translation, then rotation (`re`, `im` in 2D,
+    /// quaternion `x`, `y`, `z`, `w` in 3D) of a body.
+    BodyPose { body_id: u32 },
+    /// The linear, then angular velocity of a body.
+    BodyVelocity { body_id: u32 },
+    /// The coordinate of a multibody joint along one of its free axes.
+    JointPosition { link_id: u32, axis: usize },
+    /// The velocity of a multibody joint along one of its free axes.
+    JointVelocity { link_id: u32, axis: usize },
+    /// `1.0` if the last narrow phase found contact points on a body, `0.0`
+    /// otherwise.
+    ContactFlag { body_id: u32 },
+}
+
+impl RbdObservation {
+    /// The number of columns this observation takes in a row.
+    pub fn num_columns(&self) -> u32 {
+        match self {
+            Self::BodyPose { .. } => OBS_BODY_POSE_LEN,
+            Self::BodyVelocity { .. } => OBS_BODY_VELOCITY_LEN,
+            Self::JointPosition { .. } | Self::JointVelocity { .. } | Self::ContactFlag { .. } => 1,
+        }
+    }
+}
+
+/// A scalar input written in every environment, from one column of the action
+/// tensor.
+///
+/// Ids and axes follow the conventions of [`RbdObservation`]. Wrench and force
+/// axes are `0..DIM` for the force and `DIM..SPATIAL_DIM` for the torque.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RbdAction {
+    /// The target position of a multibody joint motor.
+    MotorPosition { link_id: u32, axis: usize },
+    /// The target velocity of a multibody joint motor.
+    MotorVelocity { link_id: u32, axis: usize },
+    /// A signed force or torque applied by a multibody joint motor.
+    ///
+    /// The motor is reconfigured by [`EnvTensors::action_spec`] to apply exactly
+    /// that effort, and no longer tracks its target position or velocity.
+    JointEffort { link_id: u32, axis: usize },
+    /// A component of the persistent force and torque of a body, as set by
+    /// [`RbdState::add_force`] and [`RbdState::add_torque`]. Wakes the body up.
+    BodyForce { body_id: u32, axis: usize },
+    /// A component of the world-space external wrench of a multibody link, as
+    /// set by [`GpuMultibodySet::set_link_external_wrench`](crate::dynamics::GpuMultibodySet::set_link_external_wrench).
+    LinkWrench { link_id: u32, axis: usize },
+}
+
+/// The terms of a spec, uploaded to the GPU.
+struct EnvTerms {
+    terms: Tensor<EnvTerm>,
+    shape: Tensor<EnvTermsShape>,
+    num_terms: u32,
+    row_len: u32,
+}
+
+impl EnvTerms {
+    fn new(backend: &GpuBackend, terms: &[EnvTerm], row_len: u32) -> Self {
+        assert!(!terms.is_empty(), "a spec needs at least one term");
+        let shape = EnvTermsShape {
+            num_terms: terms.len() as u32,
+            row_len,
+            padding: [0; 2],
+        };
+        Self {
+            terms: Tensor::vector(backend, terms, BufferUsages::STORAGE).unwrap(),
+            shape: Tensor::scalar(backend, shape, BufferUsages::UNIFORM).unwrap(),
+            num_terms: shape.num_terms,
+            row_len,
+        }
+    }
+}
+
+/// An observation spec built by [`EnvTensors::observation_spec`].
+pub struct ObservationSpec(EnvTerms);
+
+impl ObservationSpec {
+    /// The number of columns of the observation tensor (`obs_dim`).
+    pub fn row_len(&self) -> u32 {
+        self.0.row_len
+    }
+}
+
+/// An action spec built by [`EnvTensors::action_spec`].
+pub struct ActionSpec(EnvTerms);
+
+impl ActionSpec {
+    /// The number of columns of the action tensor (`act_dim`).
+    pub fn row_len(&self) -> u32 {
+        self.0.row_len
+    }
+}
+
+/// Gathers observations of every environment of an [`RbdState`] into a dense
+/// `[num_batches, obs_dim]` tensor, and scatters a `[num_batches, act_dim]`
+/// action tensor into its inputs, one dispatch each.
+///
+/// Both tensors are row-major, one row per batch, and stay on the GPU: a policy
+/// evaluated on the GPU can be chained with the simulation step without any
+/// CPU round-trip. The specs are resolved against batch 0, so every batch must
+/// lay out the observed bodies and links the same way.
+///
+/// Observations are gathered from the state left by the last step. Actions are
+/// persistent: motor targets, efforts and forces keep their value until the next
+/// scatter or host-side write.
+pub struct EnvTensors {
+    shaders: GpuEnvTensors,
+}
+
+impl EnvTensors {
+    /// Creates the observation and action pipeline by loading its shaders on the
+    /// given backend.
+    pub fn new(backend: &GpuBackend) -> Result<Self, GpuBackendError> {
+        Ok(Self {
+            shaders: GpuEnvTensors::from_backend(backend)?,
+        })
+    }
+
+    /// Uploads an observation spec. Its terms fill the row in order.
+    ///
+    /// Panics if a term refers to a body, link or axis that doesn't exist, or to
+    /// a locked joint axis.
+    pub fn observation_spec(
+        &self,
+        backend: &GpuBackend,
+        state: &RbdState,
+        observations: &[RbdObservation],
+    ) -> ObservationSpec {
+        let mut terms = Vec::with_capacity(observations.len());
+        let mut column = 0;
+
+        for obs in observations {
+            let (kind, id, axis) = match *obs {
+                RbdObservation::BodyPose { body_id } => {
+                    (OBS_BODY_POSE, check_body(state, body_id), 0)
+                }
+                RbdObservation::BodyVelocity { body_id } => {
+                    (OBS_BODY_VELOCITY, check_body(state, body_id), 0)
+                }
+                RbdObservation::JointPosition { link_id, axis } => {
+                    check_dof(state, link_id, axis);
+                    (OBS_JOINT_POSITION, link_id, axis as u32)
+                }
+                RbdObservation::JointVelocity { link_id, axis } => {
+                    let dof = check_dof(state, link_id, axis);
+                    (OBS_JOINT_VELOCITY, dof, 0)
+                }
+                RbdObservation::ContactFlag { body_id } => {
+                    (OBS_CONTACT_FLAG, check_body(state, body_id), 0)
+                }
+            };
+            terms.push(EnvTerm {
+                kind,
+                id,
+                axis,
+                column,
+            });
+            column += obs.num_columns();
+        }
+
+        ObservationSpec(EnvTerms::new(backend, &terms, column))
+    }
+
+    /// Uploads an action spec, the `i`-th action reading the `i`-th column.
+    ///
+    /// The motors of [`RbdAction::JointEffort`] actions are reconfigured, in
+    /// every batch, as saturated velocity motors applying no effort until the
+    /// first scatter.
+    ///
+    /// Panics if an action refers to a body, link or axis that doesn't exist, to
+    /// a locked joint axis, or to a motor that wasn't enabled in rapier.
+    pub fn action_spec(
+        &self,
+        backend: &GpuBackend,
+        state: &mut RbdState,
+        actions: &[RbdAction],
+    ) -> Result<ActionSpec, GpuBackendError> {
+        let mut terms = Vec::with_capacity(actions.len());
+        let mut effort_motors = vec![];
+
+        for (column, action) in actions.iter().enumerate() {
+            let (kind, id, axis) = match *action {
+                RbdAction::MotorPosition { link_id, axis } => {
+                    check_motor(state, link_id, axis);
+                    (ACT_MOTOR_POSITION, link_id, axis)
+                }
+                RbdAction::MotorVelocity { link_id, axis } => {
+                    check_motor(state, link_id, axis);
+                    (ACT_MOTOR_VELOCITY, link_id, axis)
+                }
+                RbdAction::JointEffort { link_id, axis } => {
+                    check_motor(state, link_id, axis);
+                    effort_motors.push((link_id, axis));
+                    (ACT_JOINT_EFFORT, link_id, axis)
+                }
+                RbdAction::BodyForce { body_id, axis } => {
+                    assert!(axis < SPATIAL_DIM, "force axis out of bounds");
+                    (ACT_BODY_FORCE, check_body(state, body_id), axis)
+                }
+                RbdAction::LinkWrench { link_id, axis } => {
+                    assert!(axis < SPATIAL_DIM, "wrench axis out of bounds");
+                    check_link(state, link_id);
+                    (ACT_LINK_WRENCH, link_id, axis)
+                }
+            };
+            terms.push(EnvTerm {
+                kind,
+                id,
+                axis: axis as u32,
+                column: column as u32,
+            });
+        }
+
+        if !effort_motors.is_empty() {
+            let multibodies = state.multibodies_mut();
+            for batch in 0..multibodies.num_batches() {
+                let updates: Vec<_> = effort_motors
+                    .iter()
+                    .map(|&(link_id, axis)| {
+                        let motor = JointMotor {
+                            stiffness: 0.0,
+                            damping: JOINT_EFFORT_MOTOR_DAMPING,
+                            max_force: 0.0,
+                            model: ACCELERATION_BASED,
+                            ..multibodies.motor(batch, link_id, axis).unwrap()
+                        };
+                        (link_id, axis, motor)
+                    })
+                    .collect();
+                multibodies.set_motors(backend, batch, &updates)?;
+            }
+        }
+
+        Ok(ActionSpec(EnvTerms::new(
+            backend,
+            &terms,
+            actions.len() as u32,
+        )))
+    }
+
+    /// Gathers the observations of every batch into `observations`, a
+    /// `[num_batches, spec.row_len()]` row-major tensor.
+    pub fn gather_observations(
+        &self,
+        pass: &mut GpuPass,
+        state: &RbdState,
+        spec: &ObservationSpec,
+        observations: &mut Tensor<f32>,
+    ) -> Result<(), GpuBackendError> {
+        let spec = &spec.0;
+        assert_eq!(
+            observations.len(),
+            (spec.row_len * state.num_batches) as usize,
+            "the observation tensor must have one row per batch"
+        );
+        let multibodies = state.multibodies();
+        self.shaders.gather_observations.call(
+            pass,
+            [spec.num_terms, state.num_batches, 1],
+            &spec.terms,
+            observations,
+            &state.body_poses,
+            &state.vels,
+            multibodies.links_workspace(),
+            multibodies.dof_state(),
+            &state.contacts,
+            &state.contacts_len,
+            &spec.shape,
+            &state.batch_indices,
+        )?;
+        Ok(())
+    }
+
+    /// Scatters `actions`, a `[num_batches, spec.row_len()]` row-major tensor,
+    /// into the inputs of every batch.
+    ///
+    /// Must be dispatched before the step the actions drive. The motors written
+    /// here aren't reflected by
+    /// [`GpuMultibodySet::motor`](crate::dynamics::GpuMultibodySet::motor).
+    pub fn scatter_actions(
+        &self,
+        pass: &mut GpuPass,
+        state: &mut RbdState,
+        spec: &ActionSpec,
+        actions: &Tensor<f32>,
+    ) -> Result<(), GpuBackendError> {
+        let spec = &spec.0;
+        assert_eq!(
+            actions.len(),
+            (spec.row_len * state.num_batches) as usize,
+            "the action tensor must have one row per batch"
+        );
+        let num_batches = state.num_batches;
+        let (links_static, links_workspace) = state.multibodies.actuation_buffers_mut();
+        self.shaders.scatter_actions.call(
+            pass,
+            [num_batches, 1, 1],
+            &spec.terms,
+            actions,
+            links_static,
+            links_workspace,
+            &mut state.forces,
+            &mut state.activations,
+            &spec.shape,
+            &state.batch_indices,
+        )?;
+        Ok(())
+    }
+}
+
+fn check_body(state: &RbdState, body_id: u32) -> u32 {
+    assert!(
+        (0..state.num_batches).all(|b| body_id < state.batch_range(b).capacity),
+        "body slot out of bounds"
+    );
+    body_id
+}
+
+fn check_link(state: &RbdState, link_id: u32) {
+    let multibodies = state.multibodies();
+    assert!(
+        !multibodies.is_empty() && link_id < multibodies.links_per_batch(),
+        "multibody link index out of bounds"
+    );
+}
+
+/// Returns the per-batch DOF index of the joint axis.
+fn check_dof(state: &RbdState, link_id: u32, axis: usize) -> u32 {
+    check_link(state, link_id);
+    state
+        .multibodies()
+        .link_dof(0, link_id, axis)
+        .expect("the observed joint axis must be free")
+}
+
+fn check_motor(state: &RbdState, link_id: u32, axis: usize) {
+    check_dof(state, link_id, axis);
+    assert!(
+        state.multibodies().is_motor_enabled(0, link_id, axis),
+        "the actuated joint motor must be enabled in rapier before finalization"
+    );
+}
@@ -0,0 +1,210 @@
+//! Non-blocking GPU→CPU readback of the collision, contact-force and joint-break events.
+
+use super::RbdState;
+use crate::shaders::broad_phase::{CollisionEvent, ContactForceEvent};
+use crate::shaders::dynamics::JointBreakEvent;
+use khal::backend::{GpuBackend, GpuBackendError, GpuReadback};
+use vortx::tensor::Tensor;
+
+/// Non-blocking readback of per-batch event ring buffers of [`RbdState`], polled
+/// by [`RbdState::poll_events`].
+///
+/// The GPU event counters only grow, so each harvest picks up the events emitted
+/// since the previous one. Events overwritten in a ring buffer before being read
+/// are counted as dropped.
+pub(super) struct EventsReadback<T> {
+    events: GpuReadback<T>,
+    events_len: GpuReadback<u32>,
+    events_cpu: Vec<T>,
+    events_len_cpu: Vec<u32>,
+    events_per_batch: u32,
+    /// Incremented whenever the GPU event buffers are reset.
+    epoch: u32,
+    requested_epoch: u32,
+    /// Whether each half of the readback in flight was harvested already.
+    events_taken: bool,
+    events_len_taken: bool,
+    /// Per-batch value of the GPU event counter at the last harvest.
+    consumed: Vec<u32>,
+    /// Per-batch harvested events, until drained.
+    pending: Vec<Vec<T>>,
+    dropped: u64,
+}
+
+impl<T: bytemuck::Pod + Default> EventsReadback<T> {
+    pub(super) fn new(backend: &GpuBackend, num_batches: u32, events_per_batch: u32) -> Self {
+        let num_events = (num_batches * events_per_batch) as usize;
+        Self {
+            events: GpuReadback::new(backend, num_events).unwrap(),
+            events_len: GpuReadback::new(backend, num_batches as usize).unwrap(),
+            events_cpu: vec![T::default(); num_events],
+            events_len_cpu: vec![0; num_batches as usize],
+            events_per_batch,
+            epoch: 0,
+            requested_epoch: 0,
+            events_taken: false,
+            events_len_taken: false,
+            consumed: vec![0; num_batches as usize],
+            pending: vec![Vec::new(); num_batches as usize],
+            dropped: 0,
+        }
+    }
+
+    /// Discards the pending events and any readback in flight, after the GPU
+    /// event buffers were reset: the collider slots they refer to are stale.
+    pub(super) fn invalidate(&mut self) {
+        self.epoch = self.epoch.wrapping_add(1);
+        self.consumed.fill(0);
+        self.pending.iter_mut().for_each(Vec::clear);
+    }
+
+    /// Harvests the readback in flight if it completed, then requests a new one
+    /// of `events`/`events_len` if none is pending.
+    fn poll(
+        &mut self,
+        backend: &GpuBackend,
+        events: &Tensor<T>,
+        events_len: &Tensor<u32>,
+    ) -> Result<(), GpuBackendError> {
+        // Both halves are requested together but may complete at different polls.
+        self.events_taken |= self.events.try_take(backend, &mut self.events_cpu);
+        self.events_len_taken |= self.events_len.try_take(backend, &mut self.events_len_cpu);
+        if self.events_taken && self.events_len_taken {
+            self.events_taken = false;
+            self.events_len_taken = false;
+            if self.requested_epoch == self.epoch {
+                self.harvest();
+            }
+        }
+
+        if self.events.is_idle() && self.events_len.is_idle() {
+            self.requested_epoch = self.epoch;
+            self.events_len
+                .request(backend, &[(events_len.buffer(), 0, events_len.len())])?;
+            self.events
+                .request(backend, &[(events.buffer(), 0, events.len())])?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the events emitted since the previous harvest to `pending`.
+    fn harvest(&mut self) {
+        let cap = self.events_per_batch;
+        for (batch_id, (&total, consumed)) in self
+            .events_len_cpu
+            .iter()
+            .zip(self.consumed.iter_mut())
+            .enumerate()
+        {
+            let mut start = *consumed;
+            let num_new = total.wrapping_sub(start);
+            if num_new > cap {
+                // The oldest events were overwritten before we could read them.
+                self.dropped += (num_new - cap) as u64;
+                start = total.wrapping_sub(cap);
+            }
+
+            let ring = &self.events_cpu[batch_id * cap as usize..(batch_id + 1) * cap as usize];
+            let mut k = start;
+            while k != total {
+                self.pending[batch_id].push(ring[(k % cap) as usize]);
+                k = k.wrapping_add(1);
+            }
+            *consumed = total;
+        }
+    }
+
+    fn drain(&mut self, batch_id: u32) -> Vec<T> {
+        self.pending
+            .get_mut(batch_id as usize)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+impl RbdState {
+    /// Polls the non-blocking readback of the collision, contact-force and
+    /// joint-break events.
+    ///
+    /// Harvests the readbacks in flight if they completed, then requests new ones
+    /// if none is pending. Call this once per frame, after the step was submitted.
+    /// The harvested events are retrieved with [`Self::drain_collision_events`],
+    /// [`Self::drain_contact_force_events`] and [`Self::drain_joint_break_events`];
+    /// like the auto-resize readback, they lag the GPU by a frame or two.
+    pub fn poll_events(&mut self, backend: &GpuBackend) -> Result<(), GpuBackendError> {
+        if self.sensors.is_enabled() || self.collision_events.has_contact_events() {
+            self.collision_events_readback.poll(
+                backend,
+                self.collision_events.collision_events(),
+                self.collision_events.collision_events_len(),
+            )?;
+        }
+        if self.collision_events.has_contact_force_events() {
+            self.contact_force_events_readback.poll(
+                backend,
+                self.collision_events.contact_force_events(),
+                self.collision_events.contact_force_events_len(),
+            )?;
+        }
+        if !self.joints.is_empty() {
+            let (num_batches, joints_per_batch) =
+                (self.num_batches, self.joints.joints_per_batch());
+            self.joint_break_events_readback
+                .get_or_insert_with(|| EventsReadback::new(backend, num_batches, joints_per_batch))
+                .poll(
+                    backend,
+                    self.joints.break_events(),
+                    self.joints.break_events_len(),
+                )?;
+        }
+        Ok(())
+    }
+
+    /// Takes the collision events of the batch `batch_id` harvested by
+    /// [`Self::poll_events`], oldest first.
+    ///
+    /// The event colliders are batch-local collider indices.
+    pub fn drain_collision_events(&mut self, batch_id: u32) -> Vec<CollisionEvent> {
+        self.collision_events_readback.drain(batch_id)
+    }
+
+    /// Takes the contact-force events of the batch `batch_id` harvested by
+    /// [`Self::poll_events`], oldest first.
+    ///
+    /// The event colliders are batch-local collider indices.
+    pub fn drain_contact_force_events(&mut self, batch_id: u32) -> Vec<ContactForceEvent> {
+        self.contact_force_events_readback.drain(batch_id)
+    }
+
+    /// Takes the joint-break events of the batch `batch_id` harvested by
+    /// [`Self::poll_events`], oldest first.
+    ///
+    /// The event joint ids index
+    /// [`GpuImpulseJointSet::handles`](crate::dynamics::GpuImpulseJointSet::handles).
+    pub fn drain_joint_break_events(&mut self, batch_id: u32) -> Vec<JointBreakEvent> {
+        self.joint_break_events_readback
+            .as_mut()
+            .map(|readback| readback.drain(batch_id))
+            .unwrap_or_default()
+    }
+
+    /// Total number of collision events overwritten in their ring buffer before
+    /// being read. Raise [`RbdCapacities::events_capacity`](super::RbdCapacities::events_capacity)
+    /// or poll more often if this grows.
+    pub fn num_dropped_collision_events(&self) -> u64 {
+        self.collision_events_readback.dropped
+    }
+
+    /// Total number of contact-force events overwritten in their ring buffer
+    /// before being read.
+    pub fn num_dropped_contact_force_events(&self) -> u64 {
+        self.contact_force_events_readback.dropped
+    }
+
+    /// Discards the pending events after the GPU event buffers were reset.
+    pub(super) fn invalidate_events(&mut self) {
+        self.collision_events_readback.invalidate();
+        self.contact_force_events_readback.invalidate();
+    }
+}
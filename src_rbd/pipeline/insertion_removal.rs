@@ -1,29 +1,31 @@
 //! Incremental construction of [`RbdState`]: empty allocation, append and removal of bodies.
 
-use crate::broad_phase::LbvhState;
-use crate::dynamics::GpuImpulseJointSet;
-#[cfg(feature = "dim3")]
+use crate::broad_phase::{CollisionEventsState, LbvhState, SensorState};
 use crate::dynamics::GpuMultibodySet;
+use crate::dynamics::{CcdState, GpuImpulseJointSet, IslandState};
 use crate::math::{Pose, Vector};
 use crate::queries::GpuColliderMaterial;
 use crate::shaders::dynamics::{
+    BODY_TYPE_FIXED, BodyActivation, BodyParams as GpuBodyParams, Force as GpuForce,
     LocalMassProperties as GpuLocalMassProperties, RbdSimParams, Velocity as GpuVelocity,
-    WorldMassProperties as GpuWorldMassProperties,
+    WorldMassProperties as GpuWorldMassProperties, is_kinematic,
 };
 use crate::shaders::shapes::Shape;
-use crate::shaders::utils::BatchIndices;
+use crate::shaders::utils::{BatchIndices, MAX_BATCHES};
 use crate::utils::PrefixSumWorkspace;
 use std::ops::Range;
 
+use super::body_readback::BodyStatesReadback;
+use super::events_readback::EventsReadback;
+use super::param_updates::ParamUpdates;
 use super::rbd_state::*;
-#[cfg(feature = "dim3")]
 use crate::rapier::dynamics::{MultibodyJointSet, RigidBodySet};
 use khal::BufferUsages;
 use khal::backend::{Backend, Encoder, GpuBackend, GpuBackendError, GpuReadback};
 use vortx::tensor::Tensor;
 use {
-    crate::math::Point, crate::rapier::dynamics::ImpulseJointSet, crate::shapes::shape_from_parry,
-    std::collections::HashMap,
+    crate::math::Point, crate::rapier::dynamics::ImpulseJointSet,
+    crate::shapes::shape_parts_from_parry, std::collections::HashMap,
 };
 
 impl RbdState {
@@ -40,31 +42,49 @@ impl RbdState {
     /// are not grown/restrided by the incremental path).
     pub fn empty(backend: &GpuBackend, capacities: RbdCapacities) -> Self {
         let num_batches = capacities.batches;
+        assert!(
+            num_batches as usize <= MAX_BATCHES,
+            "batched rbd supports at most {MAX_BATCHES} batches, got {num_batches}"
+        );
         let capacity_per_batch = capacities.body_capacity;
         let collisions_capacity = capacities.collisions_capacity;
         let num_colliders_per_batch = capacity_per_batch;
         let num_bodies_total = (capacity_per_batch * num_batches) as usize;
+        // Every batch gets the same range, initially without any active body.
+        let batch_ranges: Vec<_> = (0..num_batches)
+            .map(|batch_id| RbdBatchRange {
+                offset: batch_id * capacity_per_batch,
+                capacity: capacity_per_batch,
+                colliders_len: 0,
+                bodies_len: 0,
+            })
+            .collect();
 
         let num_solver_iterations = 4u32;
         let mut base_sim_params = RbdSimParams::default();
         base_sim_params.dt /= num_solver_iterations as f32;
         let all_sim_params = vec![base_sim_params; num_batches as usize];
 
-        // Inactive (padding) slots use empty collision groups so the broad-phase
+        // Inactive (padding) slots are outside of the batch's `colliders_len`, and
+        // use a zero-extent shape and empty collision groups so the broad-phase
         // never matches them with anything.
         let none_groups = crate::rapier::geometry::InteractionGroups::new(
             crate::rapier::geometry::Group::NONE,
             crate::rapier::geometry::Group::NONE,
             crate::rapier::geometry::InteractionTestMode::And,
         );
-        let dummy_shape = Shape::cuboid(Vector::splat(0.5));
+        let dummy_shape = Shape::ball(0.0);
         let all_poses = vec![Pose::default(); num_bodies_total];
         let all_collider_local_poses = vec![Pose::IDENTITY; num_bodies_total];
         let all_local_mprops = vec![GpuLocalMassProperties::default(); num_bodies_total];
+        let all_body_params = vec![GpuBodyParams::default(); num_bodies_total];
+        let all_activations = vec![BodyActivation::default(); num_bodies_total];
+        let all_forces = vec![GpuForce::default(); num_bodies_total];
         let all_mprops = vec![GpuWorldMassProperties::default(); num_bodies_total];
         let all_shapes = vec![dummy_shape; num_bodies_total];
         let all_collision_groups = vec![none_groups; num_bodies_total];
         let all_vels = vec![GpuVelocity::default(); num_bodies_total];
+        let all_body_types = vec![BODY_TYPE_FIXED; num_bodies_total];
 
         // body_group: per-batch local indices (free bodies map to themselves).
         let mut all_body_group = Vec::with_capacity(num_bodies_total);
@@ -94,14 +114,17 @@ impl RbdState {
             .collect();
         let joints = GpuImpulseJointSet::from_rapier_filtered(backend, &joint_env_refs, &[], &[]);
 
-        #[cfg(feature = "dim3")]
         let multibodies = {
             let empty_mb = MultibodyJointSet::new();
             let empty_bodies = RigidBodySet::new();
             let mb_refs: Vec<_> = (0..num_batches as usize)
                 .map(|_| (&empty_mb, &empty_body_ids, &empty_bodies))
                 .collect();
-            let mut mb = GpuMultibodySet::from_rapier(backend, &mb_refs, capacity_per_batch);
+            let body_slots: Vec<_> = batch_ranges
+                .iter()
+                .map(|r| r.offset..r.offset + r.capacity)
+                .collect();
+            let mut mb = GpuMultibodySet::from_rapier(backend, &mb_refs, &body_slots);
             mb.set_constraint_softness(backend, &all_sim_params[0]);
             mb
         };
@@ -169,6 +192,24 @@ impl RbdState {
             BufferUsages::STORAGE | BufferUsages::COPY_SRC,
         )
         .unwrap();
+        // Enabled by `append_bodies` once a sensor or a collider with events is added.
+        let sensors =
+            SensorState::new(backend, num_batches, capacities.event_pairs_capacity, false).unwrap();
+        let collision_events = CollisionEventsState::new(
+            backend,
+            num_batches,
+            capacities.event_pairs_capacity,
+            capacities.events_capacity,
+            0,
+        )
+        .unwrap();
+        let collision_events_readback =
+            EventsReadback::new(backend, num_batches, capacities.events_capacity);
+        let contact_force_events_readback =
+            EventsReadback::new(backend, num_batches, capacities.events_capacity);
+        // Enabled by `append_bodies` once a body with CCD is added.
+        let ccd = CcdState::new(backend, false).unwrap();
+        let islands = IslandState::new(backend).unwrap();
         let old_constraints =
             Tensor::vector_uninit(backend, collisions_capacity * num_batches, storage).unwrap();
         let old_constraint_builders =
@@ -215,21 +256,18 @@ impl RbdState {
 
         let contacts_per_batch_cpu = collisions_capacity;
         let collision_pairs_per_batch_cpu = collisions_capacity;
-        #[allow(unused_mut)] // Only mutated with the dim3 (multibody) feature.
         let mut bi = BatchIndices {
             num_batches,
             colliders_batch_capacity: num_colliders_per_batch,
-            // No body is active initially; bodies are added later via `append_bodies`.
-            colliders_len: 0,
-            bodies_len: 0,
             collision_pairs_batch_capacity: collision_pairs_per_batch_cpu,
             contacts_batch_capacity: contacts_per_batch_cpu,
-            impulse_joints_batch_capacity: joints.joints_per_batch(),
-            impulse_joints_len: joints.num_active_joints(),
+            event_pairs_batch_capacity: capacities.event_pairs_capacity,
+            events_batch_capacity: capacities.events_capacity,
             solver_color_buckets_stride: color_buckets_stride,
             ..Default::default()
         };
-        #[cfg(feature = "dim3")]
+        fill_batch_ranges(&mut bi, &batch_ranges);
+        joints.fill_batch_indices(&mut bi);
         multibodies.fill_batch_indices(&mut bi);
         let batch_indices = Tensor::scalar(
             backend,
@@ -247,12 +285,24 @@ impl RbdState {
             vels: Tensor::vector(backend, &all_vels, rw).unwrap(),
             solver_vels: Tensor::vector(backend, &all_vels, storage).unwrap(),
             solver_vels_inc: Tensor::vector(backend, &all_vels, storage).unwrap(),
+            body_types: Tensor::vector(backend, &all_body_types, rw).unwrap(),
+            kinematic_targets: Tensor::vector(backend, &all_poses, rw).unwrap(),
+            forces: Tensor::vector(backend, &all_forces, rw).unwrap(),
+            pending_body_updates: HashMap::new(),
+            body_updates: Self::body_updates_tensor(backend, 1),
+            num_body_updates: Self::num_body_updates_tensor(backend),
+            body_states_readback: BodyStatesReadback::new(backend, num_bodies_total),
             joints,
-            #[cfg(feature = "dim3")]
             multibodies,
-            gravity: Self::gravity_tensor(backend, [0.0, -9.81, 0.0]),
+            joint_wrenches_readback: None,
+            joint_break_events_readback: None,
+            env_snapshots: None,
+            param_updates: ParamUpdates::new(backend),
+            gravity: Self::gravity_tensor(backend, num_batches, [0.0, -9.81, 0.0]),
             body_group,
             local_mprops: Tensor::vector(backend, &all_local_mprops, rw).unwrap(),
+            body_params: Tensor::vector(backend, &all_body_params, rw).unwrap(),
+            activations: Tensor::vector(backend, &all_activations, rw).unwrap(),
             mprops: Tensor::vector(backend, &all_mprops, rw).unwrap(),
             body_poses: Tensor::vector(backend, &all_poses, rw).unwrap(),
             solver_body_poses: Tensor::vector(backend, &all_poses, rw).unwrap(),
@@ -279,6 +329,12 @@ impl RbdState {
             contacts_len,
             contacts_indirect,
             mb_sweep_indirect,
+            sensors,
+            collision_events,
+            collision_events_readback,
+            ccd,
+            islands,
+            contact_force_events_readback,
             pfm_pairs,
             pfm_pairs_len,
             pfm_pairs_indirect,
@@ -324,8 +380,8 @@ impl RbdState {
             lbvh: LbvhState::with_usages(backend, lbvh_usages),
             max_colors: capacities.solver_colors,
             rb_contacts_inert: false,
-            num_active_colliders: 0,
-            num_active_bodies: 0,
+            has_kinematic_bodies: false,
+            batch_ranges,
         }
     }
 
@@ -333,13 +389,16 @@ impl RbdState {
     /// *every* simulation batch, returning the per-batch local slot range the
     /// new bodies occupy (identical across batches). The same topology is added
     /// to all environments, preserving the equal-topology invariant; per-batch
-    /// dynamic divergence can be applied afterwards via the usual buffers.
+    /// dynamic divergence can be applied afterwards via the usual buffers. Every
+    /// batch must have the same number of active colliders.
     ///
     /// Only primitive (vertex-less) colliders are currently supported; mesh
-    /// colliders would require growing the shared vertex/index buffers.
+    /// colliders would require growing the shared vertex/index buffers, and
+    /// compound colliders more than one collider slot per body.
     ///
     /// # Panics
-    /// Panics if any batch would exceed `num_colliders_per_batch`.
+    /// Panics if the batches have different numbers of active colliders, or if
+    /// any batch would exceed its capacity (see [`Self::batch_range`]).
     pub fn append_bodies(
         &mut self,
         backend: &GpuBackend,
@@ -348,13 +407,14 @@ impl RbdState {
             crate::rapier::geometry::Collider,
         )],
     ) -> Result<Range<u32>, GpuBackendError> {
-        let cap = self.num_colliders_per_batch as usize;
-        let active = self.num_active_colliders as usize;
-        assert!(
-            active + bodies.len() <= cap,
-            "rbd batch capacity ({}) exceeded",
-            cap
-        );
+        let active = self.shared_colliders_len() as usize;
+        for range in &self.batch_ranges {
+            assert!(
+                active + bodies.len() <= range.capacity as usize,
+                "rbd batch capacity ({}) exceeded",
+                range.capacity
+            );
+        }
 
         let mut poses = Vec::with_capacity(bodies.len());
         let mut collider_local_poses = Vec::with_capacity(bodies.len());
@@ -364,6 +424,10 @@ impl RbdState {
         let mut collision_groups = Vec::with_capacity(bodies.len());
         let mut materials = Vec::with_capacity(bodies.len());
         let mut vels = Vec::with_capacity(bodies.len());
+        let mut body_types = Vec::with_capacity(bodies.len());
+        let mut body_params = Vec::with_capacity(bodies.len());
+        let mut activations = Vec::with_capacity(bodies.len());
+        let mut forces = Vec::with_capacity(bodies.len());
 
         for (rb, co) in bodies {
             let body_pose = *rb.position();
@@ -375,6 +439,12 @@ impl RbdState {
                 // `false` only costs performance.
                 self.rb_contacts_inert = false;
             }
+            let body_type = body_type_from_rapier(rb);
+            if is_kinematic(body_type) {
+                // Kinematic bodies need the solver to integrate them.
+                self.rb_contacts_inert = false;
+                self.has_kinematic_bodies = true;
+            }
             let (local, world) = if is_dynamic {
                 // A standalone rigid-body carries no collider mass: rapier only
                 // folds a collider's mass into the body once the collider is
@@ -410,20 +480,38 @@ impl RbdState {
             };
 
             let mut shape_buffers = crate::shapes::ShapeBuffers::default();
-            let shape =
-                shape_from_parry(co.shape(), &mut shape_buffers).expect("Unsupported shape");
+            let parts =
+                shape_parts_from_parry(co.shape(), &mut shape_buffers).expect("Unsupported shape");
             assert!(
-                shape_buffers.vertices.is_empty(),
+                shape_buffers.vertices.is_empty() && parts.len() == 1,
                 "RbdState::append_bodies currently supports primitive (vertex-less) colliders only."
             );
+            let (part_pose, shape) = parts[0];
 
             poses.push(body_pose);
-            collider_local_poses.push(collider_local_pose);
+            body_types.push(body_type);
+            let params = body_params_from_rapier(rb);
+            if params.is_ccd_enabled() {
+                self.ccd.enable();
+            }
+            body_params.push(params);
+            activations.push(body_activation_from_rapier(rb));
+            forces.push(if is_dynamic {
+                GpuForce::new(rb.user_force(), rb.user_torque())
+            } else {
+                GpuForce::default()
+            });
+            collider_local_poses.push(collider_local_pose * part_pose);
             local_mprops.push(local);
             mprops.push(world);
             shapes.push(shape);
             collision_groups.push(co.collision_groups());
-            materials.push(collider_material_from_rapier(co));
+            let material = collider_material_from_rapier(co);
+            if material.is_sensor() {
+                self.sensors.enable();
+            }
+            self.collision_events.enable(material.flags);
+            materials.push(material);
             vels.push(GpuVelocity::new(
                 rb.linvel(),
                 #[cfg(feature = "dim2")]
@@ -442,8 +530,8 @@ impl RbdState {
 
         // Write the same body data into every batch's slot range so all
         // environments share the same topology.
-        for batch_id in 0..self.num_batches as usize {
-            let base = (batch_id * cap + active) as u64;
+        for batch_id in 0..self.num_batches {
+            let base = self.body_slot(batch_id, active as u32) as u64;
             backend.write_buffer(self.body_poses.buffer_mut(), base, &poses)?;
             backend.write_buffer(self.solver_body_poses.buffer_mut(), base, &poses)?;
             backend.write_buffer(self.collider_world_poses.buffer_mut(), base, &poses)?;
@@ -455,17 +543,24 @@ impl RbdState {
             backend.write_buffer(self.collider_parent.buffer_mut(), base, &parents)?;
             backend.write_buffer(self.pair_filter.buffer_mut(), base, &pair_filters)?;
             backend.write_buffer(self.local_mprops.buffer_mut(), base, &local_mprops)?;
+            backend.write_buffer(self.body_params.buffer_mut(), base, &body_params)?;
+            backend.write_buffer(self.activations.buffer_mut(), base, &activations)?;
+            backend.write_buffer(self.forces.buffer_mut(), base, &forces)?;
             backend.write_buffer(self.mprops.buffer_mut(), base, &mprops)?;
             backend.write_buffer(self.shapes.buffer_mut(), base, &shapes)?;
             backend.write_buffer(self.collision_groups.buffer_mut(), base, &collision_groups)?;
             backend.write_buffer(self.collider_materials.buffer_mut(), base, &materials)?;
             backend.write_buffer(self.vels.buffer_mut(), base, &vels)?;
+            backend.write_buffer(self.body_types.buffer_mut(), base, &body_types)?;
+            backend.write_buffer(self.kinematic_targets.buffer_mut(), base, &poses)?;
         }
 
         let new_active = (active + bodies.len()) as u32;
-        self.num_active_colliders = new_active;
-        // One collider per body on this path → body count tracks collider count.
-        self.num_active_bodies = new_active;
+        for range in &mut self.batch_ranges {
+            range.colliders_len = new_active;
+            // One collider per body on this path → body count tracks collider count.
+            range.bodies_len = new_active;
+        }
         self.rebuild_batch_indices(backend);
 
         Ok(active as u32..new_active)
@@ -476,12 +571,15 @@ impl RbdState {
     /// removal is applied identically to every batch, preserving the
     /// equal-topology invariant. Returns the list of `(from, to)` local-slot
     /// relocations performed so callers can patch their slot bookkeeping.
+    ///
+    /// # Panics
+    /// Panics if the batches have different numbers of active colliders.
     pub fn remove_bodies(
         &mut self,
         backend: &GpuBackend,
         local_indices: &[u32],
     ) -> Result<Vec<(u32, u32)>, GpuBackendError> {
-        let cap = self.num_colliders_per_batch as usize;
+        let mut active = self.shared_colliders_len() as usize;
         let mut remaps = Vec::new();
 
         // Process local slots in descending order so removing one doesn't
@@ -489,12 +587,9 @@ impl RbdState {
         let mut locals: Vec<usize> = local_indices.iter().map(|&l| l as usize).collect();
         locals.sort_unstable_by(|a, b| b.cmp(a));
         locals.dedup();
-
-        let none_groups = crate::rapier::geometry::InteractionGroups::new(
-            crate::rapier::geometry::Group::NONE,
-            crate::rapier::geometry::Group::NONE,
-            crate::rapier::geometry::InteractionTestMode::And,
-        );
+        if !locals.is_empty() {
+            self.invalidate_body_slots(backend)?;
+        }
 
         let staging_usages =
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
@@ -503,9 +598,13 @@ impl RbdState {
         let mut staging_pose = backend.uninit_buffer::<Pose>(1, staging_usages)?;
         let mut staging_local_mprops =
             backend.uninit_buffer::<GpuLocalMassProperties>(1, staging_usages)?;
+        let mut staging_body_params = backend.uninit_buffer::<GpuBodyParams>(1, staging_usages)?;
+        let mut staging_activations = backend.uninit_buffer::<BodyActivation>(1, staging_usages)?;
         let mut staging_mprops =
             backend.uninit_buffer::<GpuWorldMassProperties>(1, staging_usages)?;
         let mut staging_vels = backend.uninit_buffer::<GpuVelocity>(1, staging_usages)?;
+        let mut staging_forces = backend.uninit_buffer::<GpuForce>(1, staging_usages)?;
+        let mut staging_u32 = backend.uninit_buffer::<u32>(1, staging_usages)?;
         let mut staging_shapes = backend.uninit_buffer::<Shape>(1, staging_usages)?;
         let mut staging_groups = backend
             .uninit_buffer::<crate::rapier::geometry::InteractionGroups>(1, staging_usages)?;
@@ -515,15 +614,14 @@ impl RbdState {
         let mut neutralize: Vec<usize> = Vec::new();
 
         for local in locals {
-            let active = self.num_active_colliders as usize;
             if active == 0 || local >= active {
                 continue;
             }
             let last = active - 1;
 
-            for batch in 0..self.num_batches as usize {
-                let hole_global = batch * cap + local;
-                let last_global = batch * cap + last;
+            for batch in 0..self.num_batches {
+                let hole_global = self.body_slot(batch, local as u32) as usize;
+                let last_global = self.body_slot(batch, last as u32) as usize;
 
                 if local != last {
                     // Relocate the last active body into the freed slot. A staging
@@ -552,20 +650,33 @@ impl RbdState {
                     relocate!(self.collider_world_poses, staging_pose);
                     relocate!(self.collider_local_poses, staging_pose);
                     relocate!(self.local_mprops, staging_local_mprops);
+                    relocate!(self.body_params, staging_body_params);
+                    relocate!(self.activations, staging_activations);
                     relocate!(self.mprops, staging_mprops);
                     relocate!(self.vels, staging_vels);
+                    relocate!(self.forces, staging_forces);
+                    relocate!(self.body_types, staging_u32);
+                    relocate!(self.kinematic_targets, staging_pose);
                     relocate!(self.shapes, staging_shapes);
                     relocate!(self.collision_groups, staging_groups);
                     relocate!(self.collider_materials, staging_materials);
                 }
 
+                // Updates queued for the removed body are dropped, the ones of the
+                // relocated body follow it.
+                self.pending_body_updates.remove(&(hole_global as u32));
+                if let Some(mut update) = self.pending_body_updates.remove(&(last_global as u32)) {
+                    update.body_id = hole_global as u32;
+                    self.pending_body_updates.insert(hole_global as u32, update);
+                }
+
                 neutralize.push(last_global);
             }
 
             if local != last {
                 remaps.push((last as u32, local as u32));
             }
-            self.num_active_colliders = (active - 1) as u32;
+            active -= 1;
         }
 
         if any_copy {
@@ -577,29 +688,106 @@ impl RbdState {
         // the per-batch capacity. Done after the relocation submit so the
         // copies read the pre-neutralisation data.
         for last_global in neutralize {
-            backend.write_buffer(
-                self.collision_groups.buffer_mut(),
-                last_global as u64,
-                &[none_groups],
-            )?;
-            backend.write_buffer(
-                self.local_mprops.buffer_mut(),
-                last_global as u64,
-                &[GpuLocalMassProperties::default()],
-            )?;
-            backend.write_buffer(
-                self.mprops.buffer_mut(),
-                last_global as u64,
-                &[GpuWorldMassProperties::default()],
-            )?;
+            self.neutralize_body_slot(backend, last_global)?;
         }
 
         // `collider_parent` is the identity mapping on the incremental (one
         // collider per body) path and stays identity under swap-remove, so it
         // needs no relocation; only the active body count tracks the colliders.
         // The same holds for `pair_filter` (`[identity, 0]` on this path).
-        self.num_active_bodies = self.num_active_colliders;
+        for range in &mut self.batch_ranges {
+            range.colliders_len = active as u32;
+            range.bodies_len = active as u32;
+        }
         self.rebuild_batch_indices(backend);
         Ok(remaps)
     }
+
+    /// Removes the bodies at the given local slot indices from the batch
+    /// `batch` only, leaving the other batches untouched.
+    ///
+    /// Unlike [`Self::remove_bodies`], no body is relocated: the freed slots are
+    /// neutralized in place (no collision, no mass, fixed and at rest) and stay
+    /// inert inside the active range, so every other body keeps its slot in
+    /// every batch and no remapping is needed. The inert slots are only
+    /// reclaimed when the state is rebuilt.
+    pub fn remove_batch_bodies(
+        &mut self,
+        backend: &GpuBackend,
+        batch: u32,
+        local_indices: &[u32],
+    ) -> Result<(), GpuBackendError> {
+        assert!(batch < self.num_batches, "batch {batch} out of range");
+        let range = self.batch_range(batch);
+        let globals: Vec<usize> = local_indices
+            .iter()
+            .filter(|&&local| local < range.colliders_len)
+            .map(|&local| (range.offset + local) as usize)
+            .collect();
+        if globals.is_empty() {
+            return Ok(());
+        }
+
+        self.invalidate_body_slots(backend)?;
+        for global in globals {
+            self.pending_body_updates.remove(&(global as u32));
+            self.neutralize_body_slot(backend, global)?;
+        }
+        Ok(())
+    }
+
+    /// Drops every cached or queued data referring to body or collider slots,
+    /// before these slots are reassigned or neutralized.
+    fn invalidate_body_slots(&mut self, backend: &GpuBackend) -> Result<(), GpuBackendError> {
+        self.body_states_readback.invalidate();
+        // The snapshots refer to the old body slots.
+        self.env_snapshots = None;
+        // So do the queued writes to collider materials and body masses.
+        self.param_updates.forget_slots();
+        // The tracked sensor and touching pairs refer to the old collider slots.
+        self.sensors.reset(backend)?;
+        self.collision_events.reset(backend)?;
+        self.invalidate_events();
+        Ok(())
+    }
+
+    /// Turns the body (and its collider) at the global slot `global` into
+    /// inactive padding: it never collides, has no mass and stays at rest.
+    fn neutralize_body_slot(
+        &mut self,
+        backend: &GpuBackend,
+        global: usize,
+    ) -> Result<(), GpuBackendError> {
+        let none_groups = crate::rapier::geometry::InteractionGroups::new(
+            crate::rapier::geometry::Group::NONE,
+            crate::rapier::geometry::Group::NONE,
+            crate::rapier::geometry::InteractionTestMode::And,
+        );
+        let global = global as u64;
+        backend.write_buffer(self.collision_groups.buffer_mut(), global, &[none_groups])?;
+        backend.write_buffer(
+            self.local_mprops.buffer_mut(),
+            global,
+            &[GpuLocalMassProperties::default()],
+        )?;
+        backend.write_buffer(
+            self.body_params.buffer_mut(),
+            global,
+            &[GpuBodyParams::default()],
+        )?;
+        backend.write_buffer(
+            self.activations.buffer_mut(),
+            global,
+            &[BodyActivation::default()],
+        )?;
+        backend.write_buffer(self.forces.buffer_mut(), global, &[GpuForce::default()])?;
+        backend.write_buffer(
+            self.mprops.buffer_mut(),
+            global,
+            &[GpuWorldMassProperties::default()],
+        )?;
+        backend.write_buffer(self.vels.buffer_mut(), global, &[GpuVelocity::default()])?;
+        backend.write_buffer(self.body_types.buffer_mut(), global, &[BODY_TYPE_FIXED])?;
+        Ok(())
+    }
 }
@@ -0,0 +1,195 @@
+//! Non-blocking GPU→CPU readback of the joint reaction wrenches.
+
+use super::RbdState;
+use crate::shaders::dynamics::Force;
+use khal::backend::{GpuBackend, GpuBackendError, GpuReadback};
+use vortx::tensor::Tensor;
+
+/// One wrench buffer being read back. `None` halves of [`JointWrenchesReadback`]
+/// stand for an empty joint set, with nothing to read.
+struct WrenchesHalf {
+    readback: GpuReadback<Force>,
+    cpu: Vec<Force>,
+    /// Whether the readback in flight was harvested already.
+    taken: bool,
+}
+
+impl WrenchesHalf {
+    fn new(backend: &GpuBackend, len: usize) -> Option<Self> {
+        (len != 0).then(|| Self {
+            readback: GpuReadback::new(backend, len).unwrap(),
+            cpu: vec![Force::default(); len],
+            taken: false,
+        })
+    }
+}
+
+/// Non-blocking readback of the impulse-joint, multibody-link and
+/// multibody-touching impulse-joint wrenches of [`RbdState`], polled by
+/// [`RbdState::poll_joint_wrenches`].
+///
+/// The joint sets are never relocated after construction, so unlike the body
+/// states readback, snapshots don't need to be tagged with a layout epoch.
+pub(super) struct JointWrenchesReadback {
+    impulse: Option<WrenchesHalf>,
+    multibody: Option<WrenchesHalf>,
+    multibody_impulse: Option<WrenchesHalf>,
+    /// Whether the CPU buffers hold a complete snapshot.
+    ready: bool,
+}
+
+impl JointWrenchesReadback {
+    pub(super) fn new(
+        backend: &GpuBackend,
+        num_impulse_joints: usize,
+        num_links: usize,
+        num_mb_impulse_joints: usize,
+    ) -> Self {
+        Self {
+            impulse: WrenchesHalf::new(backend, num_impulse_joints),
+            multibody: WrenchesHalf::new(backend, num_links),
+            multibody_impulse: WrenchesHalf::new(backend, num_mb_impulse_joints),
+            ready: false,
+        }
+    }
+
+    /// Harvests the readback in flight if it completed, then requests a new one
+    /// of `impulse`/`multibody`/`multibody_impulse` if none is pending.
+    fn poll(
+        &mut self,
+        backend: &GpuBackend,
+        impulse: &Tensor<Force>,
+        multibody: &Tensor<Force>,
+        multibody_impulse: &Tensor<Force>,
+    ) -> Result<(), GpuBackendError> {
+        let mut halves: Vec<_> = [
+            (self.impulse.as_mut(), impulse),
+            (self.multibody.as_mut(), multibody),
+            (self.multibody_impulse.as_mut(), multibody_impulse),
+        ]
+        .into_iter()
+        .filter_map(|(half, tensor)| Some((half?, tensor)))
+        .collect();
+        if halves.is_empty() {
+            return Ok(());
+        }
+
+        // All halves are requested together but may complete at different polls.
+        for (half, _) in &mut halves {
+            half.taken |= half.readback.try_take(backend, &mut half.cpu);
+        }
+        if halves.iter().all(|(half, _)| half.taken) {
+            halves.iter_mut().for_each(|(half, _)| half.taken = false);
+            self.ready = true;
+        }
+
+        if halves.iter().all(|(half, _)| half.readback.is_idle()) {
+            for (half, tensor) in &mut halves {
+                half.readback
+                    .request(backend, &[(tensor.buffer(), 0, tensor.len())])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl RbdState {
+    /// Polls the non-blocking readback of the joint reaction wrenches.
+    ///
+    /// Harvests the readback in flight if it completed, then requests a new one
+    /// if none is pending. Call this once per frame, after the step was submitted.
+    /// Returns `true` if a snapshot is available through
+    /// [`Self::impulse_joint_wrench`], [`Self::multibody_link_wrench`] and
+    /// [`Self::multibody_impulse_joint_wrench`]; like the body states readback,
+    /// it lags the GPU by a frame or two.
+    pub fn poll_joint_wrenches(&mut self, backend: &GpuBackend) -> Result<bool, GpuBackendError> {
+        let num_impulse_joints = if self.joints.is_empty() {
+            0
+        } else {
+            self.joints.wrenches().len() as usize
+        };
+        let num_links = if self.multibodies.is_empty() {
+            0
+        } else {
+            (self.num_batches * self.multibodies.links_per_batch()) as usize
+        };
+        let num_mb_impulse_joints = if self.multibodies.is_empty() {
+            0
+        } else {
+            (self.num_batches * self.multibodies.mb_impulse_joints_per_batch()) as usize
+        };
+        let readback = self.joint_wrenches_readback.get_or_insert_with(|| {
+            JointWrenchesReadback::new(
+                backend,
+                num_impulse_joints,
+                num_links,
+                num_mb_impulse_joints,
+            )
+        });
+        readback.poll(
+            backend,
+            self.joints.wrenches(),
+            self.multibodies.link_wrenches(),
+            self.multibodies.impulse_joint_wrenches(),
+        )?;
+        Ok(readback.ready)
+    }
+
+    /// The reaction wrench of the impulse joint `joint_id` of batch `batch_id`,
+    /// from the latest snapshot harvested by [`Self::poll_joint_wrenches`].
+    ///
+    /// `joint_id` indexes [`GpuImpulseJointSet::handles`](crate::dynamics::GpuImpulseJointSet::handles),
+    /// and the wrench is described by [`GpuImpulseJointSet::wrenches`](crate::dynamics::GpuImpulseJointSet::wrenches).
+    /// Returns `None` if no snapshot is available yet.
+    pub fn impulse_joint_wrench(&self, batch_id: u32, joint_id: u32) -> Option<Force> {
+        let readback = self.joint_wrenches_readback.as_ref()?;
+        if !readback.ready
+            || batch_id >= self.num_batches
+            || joint_id >= self.joints.num_joints(batch_id)
+        {
+            return None;
+        }
+        let idx = self.joints.joint_index(batch_id, joint_id);
+        readback.impulse.as_ref()?.cpu.get(idx as usize).copied()
+    }
+
+    /// The joint wrench of the multibody link `link_id` of batch `batch_id`, from
+    /// the latest snapshot harvested by [`Self::poll_joint_wrenches`].
+    ///
+    /// `link_id` is the per-batch link index, see
+    /// [`GpuMultibodySet::link_of_body`](crate::dynamics::GpuMultibodySet::link_of_body),
+    /// and the wrench is described by [`GpuMultibodySet::link_wrenches`](crate::dynamics::GpuMultibodySet::link_wrenches).
+    /// Returns `None` if no snapshot is available yet.
+    pub fn multibody_link_wrench(&self, batch_id: u32, link_id: u32) -> Option<Force> {
+        let readback = self.joint_wrenches_readback.as_ref()?;
+        if !readback.ready || batch_id >= self.num_batches {
+            return None;
+        }
+        // Per-link buffers are interleaved across batches.
+        let idx = link_id * self.num_batches + batch_id;
+        readback.multibody.as_ref()?.cpu.get(idx as usize).copied()
+    }
+
+    /// The reaction wrench of the multibody-touching impulse joint `joint_id` of
+    /// batch `batch_id`, from the latest snapshot harvested by
+    /// [`Self::poll_joint_wrenches`].
+    ///
+    /// `joint_id` indexes [`GpuMultibodySet::impulse_joint_handles`](crate::dynamics::GpuMultibodySet::impulse_joint_handles),
+    /// and the wrench is described by [`GpuMultibodySet::impulse_joint_wrenches`](crate::dynamics::GpuMultibodySet::impulse_joint_wrenches).
+    /// Returns `None` if no snapshot is available yet.
+    pub fn multibody_impulse_joint_wrench(&self, batch_id: u32, joint_id: u32) -> Option<Force> {
+        let readback = self.joint_wrenches_readback.as_ref()?;
+        let per_batch = self.multibodies.mb_impulse_joints_per_batch();
+        if !readback.ready || joint_id >= per_batch {
+            return None;
+        }
+        let idx = batch_id * per_batch + joint_id;
+        readback
+            .multibody_impulse
+            .as_ref()?
+            .cpu
+            .get(idx as usize)
+            .copied()
+    }
+}
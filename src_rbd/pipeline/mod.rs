@@ -4,11 +4,23 @@
 //! simulation step on the GPU. The pipeline manages collision detection, contact generation,
 //! constraint solving, and integration.
 
+mod body_readback;
+mod env_snapshots;
+mod env_tensors;
+mod events_readback;
 mod insertion_removal;
+mod joint_wrench_readback;
 mod lbvh_validation;
+mod param_updates;
 mod rbd_state;
 mod rbd_state_from_rapier;
 mod rbd_step;
+mod scene_queries;
 
-pub use rbd_state::{RbdCapacities, RbdResizePolicy, RbdState, RunStats};
+pub use env_tensors::{
+    ActionSpec, EnvTensors, GpuEnvTensors, ObservationSpec, RbdAction, RbdObservation,
+};
+pub use param_updates::{ParamSample, RbdParam};
+pub use rbd_state::{RbdBatchRange, RbdCapacities, RbdResizePolicy, RbdState, RunStats};
 pub use rbd_step::RbdPipeline;
+pub use scene_queries::{GpuSceneQueries, SceneQueries};
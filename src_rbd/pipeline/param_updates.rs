@@ -0,0 +1,226 @@
+//! Per-environment randomization of the physical parameters, written in place.
+
+use super::RbdState;
+use crate::shaders::dynamics::{
+    PARAM_DOF_ARMATURE, PARAM_DOF_DAMPING, PARAM_FRICTION, PARAM_GRAVITY, PARAM_MASS,
+    PARAM_RESTITUTION, PARAM_SAMPLE_NORMAL, PARAM_SAMPLE_UNIFORM, PARAM_SAMPLE_VALUE, ParamUpdate,
+    ParamUpdatesHeader,
+};
+use khal::BufferUsages;
+use khal::backend::{GpuBackend, GpuBackendError};
+use std::collections::HashMap;
+use vortx::tensor::Tensor;
+
+/// A physical parameter of one environment.
+///
+/// Collider and body ids are local slots of the batch (below its
+/// [`RbdBatchRange::capacity`](super::RbdBatchRange::capacity)), link
+/// ids are per-batch multibody link indices (see
+/// [`GpuMultibodySet::link_of_body`](crate::dynamics::GpuMultibodySet::link_of_body))
+/// and joint axes index the `SPATIAL_DIM` spatial layout (`0..DIM` linear,
+/// `DIM..` angular).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RbdParam {
+    /// The friction coefficient of a collider.
+    Friction { collider_id: u32 },
+    /// The restitution coefficient of a collider.
+    Restitution { collider_id: u32 },
+    /// The mass of a dynamic body. Its angular inertia is scaled by the same
+    /// ratio, so the mass distribution is kept.
+    Mass { body_id: u32 },
+    /// The damping of a multibody joint along one of its free axes.
+    DofDamping { link_id: u32, axis: usize },
+    /// The armature of a multibody joint along one of its free axes.
+    DofArmature { link_id: u32, axis: usize },
+    /// One component of the gravity (ignored for `axis == 2` in 2D).
+    Gravity { axis: usize },
+}
+
+/// The value written to an [`RbdParam`], or the distribution it is drawn from
+/// on the GPU.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParamSample {
+    /// Exactly this value.
+    Value(f32),
+    /// A value drawn uniformly in `[min, max[`.
+    Uniform { min: f32, max: f32 },
+    /// A value drawn from a normal distribution.
+    Normal { mean: f32, std_dev: f32 },
+}
+
+/// The parameter writes queued since the last step.
+pub(super) struct ParamUpdates {
+    /// The last write queued for each `(kind, batch, id)`.
+    pending: HashMap<(u32, u32, u32), ParamUpdate>,
+    pub(super) updates: Tensor<ParamUpdate>,
+    pub(super) header: Tensor<ParamUpdatesHeader>,
+    seed: u32,
+    /// The number of dispatches since the seed was set.
+    draw: u32,
+}
+
+impl ParamUpdates {
+    pub(super) fn new(backend: &GpuBackend) -> Self {
+        Self {
+            pending: HashMap::new(),
+            updates: Self::updates_tensor(backend, 1),
+            header: Tensor::scalar(
+                backend,
+                ParamUpdatesHeader::default(),
+                BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            )
+            .unwrap(),
+            seed: 0,
+            draw: 0,
+        }
+    }
+
+    fn updates_tensor(backend: &GpuBackend, capacity: u32) -> Tensor<ParamUpdate> {
+        Tensor::vector(
+            backend,
+            &vec![ParamUpdate::default(); capacity as usize],
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        )
+        .unwrap()
+    }
+
+    /// Drops the queued writes to collider and body slots, after they were
+    /// reassigned.
+    pub(super) fn forget_slots(&mut self) {
+        self.pending.retain(|(kind, _, _), _| {
+            ![PARAM_FRICTION, PARAM_RESTITUTION, PARAM_MASS].contains(kind)
+        });
+    }
+}
+
+/// Per-environment parameter randomization.
+///
+/// Like the body updates, the writes are queued on the CPU and applied by a
+/// single dispatch at the beginning of the next
+/// [`RbdPipeline::step`](crate::pipeline::RbdPipeline::step), after the
+/// environment snapshots are restored. The parameters aren't part of the
+/// snapshots: a randomized environment keeps its parameters when it is reset.
+impl RbdState {
+    /// Writes a physical parameter of batch `batch_id`, replacing the write
+    /// queued for the same parameter since the last step, if any.
+    ///
+    /// Sampled values are drawn independently for every parameter and batch.
+    /// Values other than gravity components are clamped to be nonnegative, and
+    /// a zero mass is ignored.
+    ///
+    /// The multibody motor setters of [`crate::dynamics::GpuMultibodySet`]
+    /// upload whole links from a CPU copy holding their build-time masses: set
+    /// the motors of a link before randomizing its mass.
+    ///
+    /// # Panics
+    /// Panics if the batch, collider, body, link or axis doesn't exist, or if
+    /// the joint axis is locked.
+    pub fn set_batch_param(&mut self, batch_id: u32, param: RbdParam, sample: ParamSample) {
+        assert!(batch_id < self.num_batches, "batch index out of bounds");
+        let slots = self.batch_range(batch_id).capacity;
+        let mut link_id = u32::MAX;
+        let (kind, id) = match param {
+            RbdParam::Friction { collider_id } => {
+                assert!(collider_id < slots, "collider slot out of bounds");
+                (PARAM_FRICTION, collider_id)
+            }
+            RbdParam::Restitution { collider_id } => {
+                assert!(collider_id < slots, "collider slot out of bounds");
+                (PARAM_RESTITUTION, collider_id)
+            }
+            RbdParam::Mass { body_id } => {
+                assert!(body_id < slots, "body slot out of bounds");
+                if !self.multibodies.is_empty() {
+                    link_id = self
+                        .multibodies
+                        .link_of_body(batch_id, body_id)
+                        .unwrap_or(u32::MAX);
+                }
+                (PARAM_MASS, body_id)
+            }
+            RbdParam::DofDamping { link_id, axis } => {
+                (PARAM_DOF_DAMPING, self.batch_dof(batch_id, link_id, axis))
+            }
+            RbdParam::DofArmature { link_id, axis } => {
+                (PARAM_DOF_ARMATURE, self.batch_dof(batch_id, link_id, axis))
+            }
+            RbdParam::Gravity { axis } => {
+                assert!(axis < 3, "gravity axis out of bounds");
+                (PARAM_GRAVITY, axis as u32)
+            }
+        };
+        let (sampler, a, b) = match sample {
+            ParamSample::Value(value) => (PARAM_SAMPLE_VALUE, value, 0.0),
+            ParamSample::Uniform { min, max } => (PARAM_SAMPLE_UNIFORM, min, max),
+            ParamSample::Normal { mean, std_dev } => (PARAM_SAMPLE_NORMAL, mean, std_dev),
+        };
+
+        self.param_updates.pending.insert(
+            (kind, batch_id, id),
+            ParamUpdate {
+                kind,
+                batch_id,
+                id,
+                link_id,
+                sampler,
+                a,
+                b,
+                padding: 0,
+            },
+        );
+    }
+
+    /// Seeds the GPU sampler of [`Self::set_batch_param`].
+    ///
+    /// The draw of a parameter only depends on the seed, on the number of steps
+    /// with parameter writes since it was set, and on the parameter itself (its
+    /// batch, kind and index), so a seeded sequence of randomizations is
+    /// reproducible whatever else is queued along with it.
+    pub fn set_param_seed(&mut self, seed: u32) {
+        self.param_updates.seed = seed;
+        self.param_updates.draw = 0;
+    }
+
+    /// The per-batch DOF index of a multibody joint axis.
+    fn batch_dof(&self, batch_id: u32, link_id: u32, axis: usize) -> u32 {
+        assert!(
+            !self.multibodies.is_empty() && link_id < self.multibodies.links_per_batch(),
+            "multibody link index out of bounds"
+        );
+        self.multibodies
+            .link_dof(batch_id, link_id, axis)
+            .expect("the randomized joint axis must be free")
+    }
+
+    /// Uploads the queued parameter writes to the GPU and returns their count.
+    ///
+    /// The writes must then be dispatched with [`crate::dynamics::GpuParamUpdates`].
+    pub(super) fn upload_param_updates(
+        &mut self,
+        backend: &GpuBackend,
+    ) -> Result<u32, GpuBackendError> {
+        let params = &mut self.param_updates;
+        if params.pending.is_empty() {
+            return Ok(0);
+        }
+
+        let mut updates: Vec<_> = params.pending.drain().map(|(_, u)| u).collect();
+        // The draw of an update depends on its index: keep it independent of the
+        // hash map iteration order.
+        updates.sort_unstable_by_key(|u| (u.batch_id, u.kind, u.id));
+        let num_updates = updates.len() as u32;
+        if (params.updates.len() as u32) < num_updates {
+            params.updates = ParamUpdates::updates_tensor(backend, num_updates.next_power_of_two());
+        }
+        let header = ParamUpdatesHeader {
+            num_updates,
+            seed: params.seed,
+            draw: params.draw,
+            padding: 0,
+        };
+        params.draw = params.draw.wrapping_add(1);
+        backend.write_buffer(params.updates.buffer_mut(), 0, &updates)?;
+        backend.write_buffer(params.header.buffer_mut(), 0, &[header])?;
+        Ok(num_updates)
+    }
+}
@@ -1,24 +1,37 @@
 //! GPU-resident rigid-body state ([`RbdState`]): buffer definitions, accessors,
 //! run statistics and capacity/resize policies.
-use crate::broad_phase::LbvhState;
-use crate::dynamics::GpuImpulseJointSet;
-#[cfg(feature = "dim3")]
+use super::body_readback::BodyStatesReadback;
+use super::env_snapshots::EnvSnapshots;
+use super::events_readback::EventsReadback;
+use super::joint_wrench_readback::JointWrenchesReadback;
+use super::param_updates::ParamUpdates;
+use crate::broad_phase::{CollisionEventsState, LbvhState, SensorState};
 use crate::dynamics::GpuMultibodySet;
-use crate::math::Pose;
+use crate::dynamics::{CcdState, GpuImpulseJointSet, IslandState};
+use crate::math::{AngVector, Pose, Vector};
 use crate::queries::{GpuColliderMaterial, GpuIndexedContact};
 use crate::shaders::PaddedVector;
-use crate::shaders::broad_phase::{CollisionPair, NarrowPhasePfmPair};
+use crate::shaders::broad_phase::{
+    CollisionEvent, CollisionPair, ContactForceEvent, NarrowPhasePfmPair,
+};
 use crate::shaders::dynamics::{
+    BODY_FLAG_CCD_ENABLED, BODY_UPDATE_ADD_FORCE, BODY_UPDATE_IMPULSE, BODY_UPDATE_POSE,
+    BODY_UPDATE_RESET_FORCE, BODY_UPDATE_VELOCITY, BodyActivation, BodyParams as GpuBodyParams,
+    BodyUpdate as GpuBodyUpdate, Force as GpuForce, Impulse as GpuImpulse, JointBreakEvent,
     LocalMassProperties as GpuLocalMassProperties, RbdSimParams, TwoBodyConstraint,
     TwoBodyConstraintBuilder, Velocity as GpuVelocity,
     WorldMassProperties as GpuWorldMassProperties,
 };
+use crate::shaders::queries::{
+    COLLIDER_FLAG_COLLISION_EVENTS, COLLIDER_FLAG_CONTACT_FORCE_EVENTS, COLLIDER_FLAG_SENSOR,
+};
 use crate::shaders::shapes::Shape;
 use crate::shaders::utils::BatchIndices;
 use crate::utils::PrefixSumWorkspace;
 
 use khal::BufferUsages;
-use khal::backend::{Backend, GpuBackend, GpuReadback};
+use khal::backend::{Backend, GpuBackend, GpuBackendError, GpuReadback};
+use std::collections::HashMap;
 use std::time::Duration;
 use vortx::shaders::linalg::Shape as TensorShape;
 use vortx::tensor::Tensor;
@@ -81,6 +94,17 @@ pub struct RbdCapacities {
     /// GPU->CPU buffer readback, resulting in a larger performance gain than just setting
     /// only one of them to `Fixed`.
     pub solver_colors_resize_policy: RbdResizePolicy,
+    /// Maximum number of collider pairs tracked per batch for the collision events:
+    /// the pairs involving a sensor, and the touching pairs involving a collider
+    /// with [`ActiveEvents::COLLISION_EVENTS`](crate::rapier::geometry::ActiveEvents::COLLISION_EVENTS).
+    ///
+    /// Never resized: the pairs in excess are ignored.
+    pub event_pairs_capacity: u32,
+    /// Maximum number of collision events, and of contact-force events, buffered
+    /// per batch until they are read.
+    ///
+    /// Never resized: the oldest unread events are overwritten.
+    pub events_capacity: u32,
 }
 
 impl Default for RbdCapacities {
@@ -92,10 +116,31 @@ impl Default for RbdCapacities {
             collisions_resize_policy: RbdResizePolicy::Grow,
             solver_colors: 8,
             solver_colors_resize_policy: RbdResizePolicy::Grow,
+            event_pairs_capacity: 256,
+            events_capacity: 1024,
         }
     }
 }
 
+/// The collider and body slots of one batch in the per-collider and per-body
+/// buffers of an [`RbdState`].
+///
+/// Batches are packed one after the other: the slots of batch `b` are
+/// `offset..offset + capacity`, where its bodies and colliders occupy the first
+/// `bodies_len` and `colliders_len` slots. The remaining slots are inactive
+/// padding, never read by the collision detection.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RbdBatchRange {
+    /// Index of the batch's first slot.
+    pub offset: u32,
+    /// Number of slots reserved for the batch.
+    pub capacity: u32,
+    /// Number of *active* colliders of the batch.
+    pub colliders_len: u32,
+    /// Number of *active* rigid bodies of the batch.
+    pub bodies_len: u32,
+}
+
 /// Governs the way the rigid-body dynamics pipeline automatically resizes internal buffers storing
 /// data with unpredictable size (like collisions).
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
@@ -129,10 +174,32 @@ pub struct RbdState {
     /// the solver (and then written back to `body_poses` after un-centering).
     pub(super) solver_body_poses: Tensor<Pose>,
     pub(super) local_mprops: Tensor<GpuLocalMassProperties>,
+    /// Per-body gravity scale, damping, locked axes and dominance group.
+    pub(super) body_params: Tensor<GpuBodyParams>,
+    /// Per-body sleep timer and sleeping flag.
+    pub(super) activations: Tensor<BodyActivation>,
     pub(super) mprops: Tensor<GpuWorldMassProperties>,
     pub(super) vels: Tensor<GpuVelocity>,
     pub(super) solver_vels: Tensor<GpuVelocity>,
     pub(super) solver_vels_inc: Tensor<GpuVelocity>,
+    /// Per-body type, mirroring rapier's `RigidBodyType` (see
+    /// [`crate::shaders::dynamics::BODY_TYPE_DYNAMIC`] and siblings).
+    pub(super) body_types: Tensor<u32>,
+    /// Per-body target pose of position-based kinematic bodies, reached at the end
+    /// of the next step. Ignored for every other body type.
+    pub(super) kinematic_targets: Tensor<Pose>,
+    /// Per-body persistent user force and torque, applied at every step until reset.
+    pub(super) forces: Tensor<GpuForce>,
+    /// Per-body updates queued since the last step, keyed by global body slot.
+    /// Merged so that each body has at most one entry.
+    pub(super) pending_body_updates: HashMap<u32, GpuBodyUpdate>,
+    /// GPU staging area for `pending_body_updates`, grown on demand.
+    pub(super) body_updates: Tensor<GpuBodyUpdate>,
+    /// Number of valid entries in `body_updates`, as a uniform.
+    pub(super) num_body_updates: Tensor<u32>,
+    /// Non-blocking readback of `body_poses` and `vels`, polled by
+    /// [`Self::poll_body_states`].
+    pub(super) body_states_readback: BodyStatesReadback,
     pub(super) vertex_buffers: Tensor<PaddedVector>,
     pub(super) index_buffers: Tensor<u32>,
     pub(super) shapes: Tensor<Shape>,
@@ -190,6 +257,17 @@ pub struct RbdState {
     /// Workgroup grid for the per-multibody contact-constraint dispatches:
     /// `[multibodies_batch_capacity, num_batches, 1]`.
     pub(super) mb_sweep_indirect: Tensor<[u32; 3]>,
+    /// Intersecting sensor pairs.
+    pub(super) sensors: SensorState,
+    /// Touching pairs, and the collision and contact-force event ring buffers.
+    pub(super) collision_events: CollisionEventsState,
+    /// Non-blocking readbacks of the events, polled by [`Self::poll_events`].
+    pub(super) collision_events_readback: EventsReadback<CollisionEvent>,
+    pub(super) contact_force_events_readback: EventsReadback<ContactForceEvent>,
+    /// Continuous collision detection of the bodies with CCD enabled.
+    pub(super) ccd: CcdState,
+    /// Scratch buffers of the island detection putting bodies to sleep.
+    pub(super) islands: IslandState,
     pub(super) new_constraints: Tensor<TwoBodyConstraint>,
     pub(super) new_constraint_builders: Tensor<TwoBodyConstraintBuilder>,
     pub(super) new_constraints_counts: Tensor<u32>,
@@ -220,9 +298,18 @@ pub struct RbdState {
     pub(super) uncolored_staging: Tensor<u32>,
     pub(super) lbvh: LbvhState,
     pub(super) joints: GpuImpulseJointSet,
-    #[cfg(feature = "dim3")]
     pub(super) multibodies: GpuMultibodySet,
-    /// The one gravity uniform every rigid-body and multibody kernel reads.
+    /// Non-blocking readback of the joint wrenches, created by the first
+    /// [`Self::poll_joint_wrenches`].
+    pub(super) joint_wrenches_readback: Option<JointWrenchesReadback>,
+    /// Non-blocking readback of the joint-break events, created by the first
+    /// [`Self::poll_events`] if there are impulse joints.
+    pub(super) joint_break_events_readback: Option<EventsReadback<JointBreakEvent>>,
+    /// Per-batch state snapshots, created by the first [`Self::snapshot_batch`].
+    pub(super) env_snapshots: Option<EnvSnapshots>,
+    /// Per-batch parameter writes queued by [`Self::set_batch_param`].
+    pub(super) param_updates: ParamUpdates,
+    /// The per-batch gravity every rigid-body and multibody kernel reads.
     pub(super) gravity: Tensor<glamx::Vec4>,
     /// Per-body "graph group" id, used by graph coloring to treat all bodies of
     /// the same multibody as a single node. For free bodies, `body_group[i] = i`;
@@ -233,18 +320,17 @@ pub struct RbdState {
     pub(super) prefix_sum_workspace: PrefixSumWorkspace,
     /// Maximum number of constraint colors the solver will iterate.
     pub(super) max_colors: u32,
-    /// `true` when every body is either non-dynamic or multibody-controlled
-    /// (its rb-side `inv_mass` is zero),i.e., we can skip the contact pipelines.
+    /// `true` when every body is either fixed or multibody-controlled
+    /// (its rb-side `inv_mass` is zero and it isn't kinematic),i.e., we can skip
+    /// the contact pipelines.
     pub(super) rb_contacts_inert: bool,
-    /// CPU-side mirror of the number of *active* colliders per batch. Identical
-    /// across all batches by the equal-topology invariant; slots in
-    /// `[num_active_colliders .. num_colliders_per_batch)` are reserved padding.
-    /// Mirrors `BatchIndices::colliders_len` and is kept in sync by
-    /// the incremental [`Self::append_bodies`] / [`Self::remove_bodies`] APIs.
-    pub(super) num_active_colliders: u32,
-    /// CPU-side mirror of the number of *active* rigid bodies per batch.
-    /// Mirrors `BatchIndices::bodies_len`. Always `<= num_active_colliders`.
-    pub(super) num_active_bodies: u32,
+    /// `true` when at least one body is kinematic, i.e., the kinematic kernels
+    /// must run. Never set back on removal; a stale `true` only costs performance.
+    pub(super) has_kinematic_bodies: bool,
+    /// CPU-side mirror of the collider/body range of each batch, uploaded in
+    /// the `BatchIndices` per-batch offsets and lengths. Kept in sync by the
+    /// incremental [`Self::append_bodies`] / [`Self::remove_bodies`] APIs.
+    pub(super) batch_ranges: Vec<RbdBatchRange>,
 }
 
 impl RbdState {
@@ -255,26 +341,37 @@ impl RbdState {
     /// `GpuMultibodySet::set_impulse_joints`). Call whenever a cap edit
     /// happens that any kernel reads via its `batch_ids` uniform.
     pub(super) fn rebuild_batch_indices(&mut self, backend: &GpuBackend) {
-        #[allow(unused_mut)] // Only mutated with the dim3 (multibody) feature.
         let mut bi = BatchIndices {
             num_batches: self.num_batches,
             colliders_batch_capacity: self.num_colliders_per_batch,
-            colliders_len: self.num_active_colliders,
-            bodies_len: self.num_active_bodies,
             collision_pairs_batch_capacity: self.collision_pairs_per_batch_cpu,
             contacts_batch_capacity: self.contacts_per_batch_cpu,
-            impulse_joints_batch_capacity: self.joints.joints_per_batch(),
-            impulse_joints_len: self.joints.num_active_joints(),
+            event_pairs_batch_capacity: self.collision_events.pairs_per_batch(),
+            events_batch_capacity: self.collision_events.events_per_batch(),
             solver_color_buckets_stride: self.max_colors + 3,
             ..Default::default()
         };
-        #[cfg(feature = "dim3")]
+        fill_batch_ranges(&mut bi, &self.batch_ranges);
+        self.joints.fill_batch_indices(&mut bi);
         self.multibodies.fill_batch_indices(&mut bi);
         backend
             .write_buffer(self.batch_indices.buffer_mut(), 0, &[bi])
             .unwrap();
     }
 
+    /// The number of active colliders shared by every batch, for the incremental
+    /// APIs applying the same change to every batch.
+    ///
+    /// Panics if the batches have different numbers of active colliders.
+    pub(super) fn shared_colliders_len(&self) -> u32 {
+        let len = self.batch_ranges.first().map_or(0, |r| r.colliders_len);
+        assert!(
+            self.batch_ranges.iter().all(|r| r.colliders_len == len),
+            "this operation requires the same number of colliders in every batch"
+        );
+        len
+    }
+
     /// Shared per-batch index uniform.
     pub fn batch_indices(&self) -> &Tensor<BatchIndices> {
         &self.batch_indices
@@ -322,6 +419,17 @@ impl RbdState {
         &self.body_poses
     }
 
+    /// Per-body linear and angular velocity.
+    pub fn body_velocities(&self) -> &Tensor<GpuVelocity> {
+        &self.vels
+    }
+
+    /// Per-body sleep state. Sleeping bodies are skipped by the solver until
+    /// woken up.
+    pub fn body_activations(&self) -> &Tensor<BodyActivation> {
+        &self.activations
+    }
+
     /// Mutable access to the per-body world-origin poses, for bodies this
     /// pipeline doesn't own and that another solver integrates itself.
     ///
@@ -343,24 +451,30 @@ impl RbdState {
         self.collision_pairs.capacity() as u32
     }
 
-    /// Uploads a new gravity vector, e.g. `[0.0, 0.0, -9.81]` for a Z-up scene.
-    /// Every solver path reads this one uniform, so it applies to free
+    /// Uploads a new gravity vector for every batch, e.g. `[0.0, 0.0, -9.81]`
+    /// for a Z-up scene. Every solver path reads it, so it applies to free
     /// rigid-bodies and multibody links alike. In 2D the third component is
     /// ignored.
     pub fn set_gravity(&mut self, backend: &GpuBackend, gravity: [f32; 3]) {
-        self.gravity = Self::gravity_tensor(backend, gravity);
+        self.gravity = Self::gravity_tensor(backend, self.num_batches, gravity);
     }
 
-    /// The gravity uniform shared by every solver kernel.
+    /// The per-batch gravity vectors read by every solver kernel, one `Vec4`
+    /// (with an unused `w`) per batch.
     pub fn gravity(&self) -> &Tensor<glamx::Vec4> {
         &self.gravity
     }
 
-    pub(super) fn gravity_tensor(backend: &GpuBackend, gravity: [f32; 3]) -> Tensor<glamx::Vec4> {
-        Tensor::scalar(
+    pub(super) fn gravity_tensor(
+        backend: &GpuBackend,
+        num_batches: u32,
+        gravity: [f32; 3],
+    ) -> Tensor<glamx::Vec4> {
+        let gravity = glamx::Vec4::new(gravity[0], gravity[1], gravity[2], 0.0);
+        Tensor::vector(
             backend,
-            glamx::Vec4::new(gravity[0], gravity[1], gravity[2], 0.0),
-            BufferUsages::STORAGE | BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            vec![gravity; num_batches as usize],
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
         )
         .unwrap()
     }
@@ -375,21 +489,23 @@ impl RbdState {
         &self.joints
     }
 
+    /// Mutable access to the joint set, e.g. to set breaking thresholds.
+    pub fn joints_mut(&mut self) -> &mut GpuImpulseJointSet {
+        &mut self.joints
+    }
+
     /// Mutable access to the multibody set, useful for runtime mutations like
     /// per-step motor changes.
-    #[cfg(feature = "dim3")]
     pub fn multibodies_mut(&mut self) -> &mut crate::dynamics::GpuMultibodySet {
         &mut self.multibodies
     }
 
     /// Immutable access to the multibody set (e.g. to read back `dof_state`).
-    #[cfg(feature = "dim3")]
     pub fn multibodies(&self) -> &crate::dynamics::GpuMultibodySet {
         &self.multibodies
     }
 
     /// Enables or disables the implicit treatment of multibody coriolis forces.
-    #[cfg(feature = "dim3")]
     pub fn set_implicit_coriolis(&mut self, backend: &GpuBackend, enabled: bool) {
         self.multibodies.set_implicit_coriolis(enabled);
         self.rebuild_batch_indices(backend);
@@ -459,16 +575,47 @@ impl RbdState {
         out
     }
 
-    /// The number of colliders per batch.
+    /// The number of collider/body slots of the largest batch.
     pub fn num_colliders_per_batch(&self) -> u32 {
         self.num_colliders_per_batch
     }
 
-    /// The number of *active* colliders per batch — i.e. how many of the
-    /// `num_colliders_per_batch` capacity slots are currently in use. Bodies
-    /// added via [`Self::append_bodies`] increase this up to the capacity.
-    pub fn num_active_colliders(&self) -> u32 {
-        self.num_active_colliders
+    /// The collider/body slots of batch `batch_id`.
+    pub fn batch_range(&self, batch_id: u32) -> RbdBatchRange {
+        self.batch_ranges[batch_id as usize]
+    }
+
+    /// The total number of collider/body slots, across all batches.
+    pub(super) fn num_body_slots(&self) -> u32 {
+        self.batch_ranges
+            .last()
+            .map_or(0, |r| r.offset + r.capacity)
+    }
+
+    /// The global slot of the body (or collider) `local_slot` of batch `batch_id`.
+    pub fn body_slot(&self, batch_id: u32, local_slot: u32) -> u32 {
+        self.batch_ranges[batch_id as usize].offset + local_slot
+    }
+
+    /// The number of *active* colliders of batch `batch_id` — i.e. how many of
+    /// its capacity slots are currently in use. Bodies added via
+    /// [`Self::append_bodies`] increase this up to the capacity.
+    pub fn num_active_colliders(&self, batch_id: u32) -> u32 {
+        self.batch_ranges[batch_id as usize].colliders_len
+    }
+
+    /// The number of active colliders of every batch.
+    pub(super) fn active_colliders_per_batch(&self) -> Vec<u32> {
+        self.batch_ranges.iter().map(|r| r.colliders_len).collect()
+    }
+
+    /// The number of active colliders of the largest batch.
+    pub(super) fn max_active_colliders(&self) -> u32 {
+        self.batch_ranges
+            .iter()
+            .map(|r| r.colliders_len)
+            .max()
+            .unwrap_or(0)
     }
 
     /// The number of batches.
@@ -480,18 +627,255 @@ impl RbdState {
     pub fn num_solver_iterations(&self) -> u32 {
         self.num_solver_iterations
     }
+
+    /// Per-body type, mirroring rapier's `RigidBodyType` (see
+    /// [`crate::shaders::dynamics::BODY_TYPE_DYNAMIC`] and siblings).
+    pub fn body_types(&self) -> &Tensor<u32> {
+        &self.body_types
+    }
+
+    /// Sets the pose a position-based kinematic body reaches at the end of the next step.
+    ///
+    /// `body_id` is the global body slot (see [`Self::body_slot`]).
+    /// The body is given the velocity needed to reach that pose, so it pushes the
+    /// dynamic bodies it touches along the way. The target persists: a body whose
+    /// target isn't updated stays still once reached. Has no effect on other body types.
+    pub fn set_kinematic_next_pose(
+        &mut self,
+        backend: &GpuBackend,
+        body_id: u32,
+        pose: Pose,
+    ) -> Result<(), GpuBackendError> {
+        backend.write_buffer(self.kinematic_targets.buffer_mut(), body_id as u64, &[pose])
+    }
+
+    /// Sets the velocity of a velocity-based kinematic body.
+    ///
+    /// `body_id` is the global body slot (see [`Self::body_slot`]).
+    /// The velocity persists across steps until changed. Position-based kinematic
+    /// bodies recompute their velocity from their target pose every step, so this
+    /// should not be used for them.
+    pub fn set_kinematic_velocity(
+        &mut self,
+        backend: &GpuBackend,
+        body_id: u32,
+        linvel: Vector,
+        angvel: AngVector,
+    ) -> Result<(), GpuBackendError> {
+        let velocity = GpuVelocity::new(linvel, angvel);
+        backend.write_buffer(self.vels.buffer_mut(), body_id as u64, &[velocity])
+    }
+}
+
+/// Runtime writes to individual bodies.
+///
+/// These are queued on the CPU and scattered into the GPU buffers by a single
+/// dispatch at the beginning of the next [`RbdPipeline::step`](crate::pipeline::RbdPipeline::step).
+/// `body_id` is always the global body slot (see [`RbdState::body_slot`]).
+impl RbdState {
+    fn body_update(&mut self, body_id: u32) -> &mut GpuBodyUpdate {
+        self.pending_body_updates
+            .entry(body_id)
+            .or_insert_with(|| GpuBodyUpdate {
+                body_id,
+                ..Default::default()
+            })
+    }
+
+    /// Teleports a body to the given body-origin world pose.
+    ///
+    /// The target pose of a position-based kinematic body is reset to `pose` too.
+    pub fn set_body_pose(&mut self, body_id: u32, pose: Pose) {
+        let update = self.body_update(body_id);
+        update.pose = pose;
+        update.flags |= BODY_UPDATE_POSE;
+    }
+
+    /// Sets the linear and angular velocity of a body.
+    ///
+    /// Discards the impulses queued for this body before this call. Has no effect
+    /// on fixed and position-based kinematic bodies.
+    pub fn set_body_velocity(&mut self, body_id: u32, linvel: Vector, angvel: AngVector) {
+        let update = self.body_update(body_id);
+        update.velocity = GpuVelocity::new(linvel, angvel);
+        update.impulse = GpuImpulse::default();
+        update.flags = (update.flags | BODY_UPDATE_VELOCITY) & !BODY_UPDATE_IMPULSE;
+    }
+
+    /// Applies an impulse at the center-of-mass of a body, changing its velocity
+    /// instantly. Has no effect on non-dynamic bodies.
+    pub fn apply_impulse(&mut self, body_id: u32, impulse: GpuImpulse) {
+        let update = self.body_update(body_id);
+        update.impulse.linear += impulse.linear;
+        update.impulse.angular += impulse.angular;
+        update.flags |= BODY_UPDATE_IMPULSE;
+    }
+
+    /// Adds a force at the center-of-mass of a body.
+    ///
+    /// The force persists: it is applied at every step until [`Self::reset_forces`]
+    /// is called. Has no effect on non-dynamic bodies.
+    pub fn add_force(&mut self, body_id: u32, force: Vector) {
+        let update = self.body_update(body_id);
+        update.force.linear += force;
+        update.flags |= BODY_UPDATE_ADD_FORCE;
+    }
+
+    /// Adds a torque to a body.
+    ///
+    /// The torque persists: it is applied at every step until [`Self::reset_forces`]
+    /// is called. Has no effect on non-dynamic bodies.
+    pub fn add_torque(&mut self, body_id: u32, torque: AngVector) {
+        let update = self.body_update(body_id);
+        update.force.angular += torque;
+        update.flags |= BODY_UPDATE_ADD_FORCE;
+    }
+
+    /// Clears the persistent force and torque of a body, including the ones added
+    /// (but not uploaded yet) since the last step.
+    pub fn reset_forces(&mut self, body_id: u32) {
+        let update = self.body_update(body_id);
+        update.force = GpuForce::default();
+        update.flags = (update.flags | BODY_UPDATE_RESET_FORCE) & !BODY_UPDATE_ADD_FORCE;
+    }
+
+    /// Uploads the queued body updates to the GPU and returns their count.
+    ///
+    /// The updates must then be dispatched with [`crate::dynamics::GpuBodyUpdates`].
+    pub(super) fn upload_body_updates(
+        &mut self,
+        backend: &GpuBackend,
+    ) -> Result<u32, GpuBackendError> {
+        if self.pending_body_updates.is_empty() {
+            return Ok(0);
+        }
+
+        let updates: Vec<_> = self.pending_body_updates.drain().map(|(_, u)| u).collect();
+        let num_updates = updates.len() as u32;
+        if (self.body_updates.len() as u32) < num_updates {
+            self.body_updates = Self::body_updates_tensor(backend, num_updates.next_power_of_two());
+        }
+        backend.write_buffer(self.body_updates.buffer_mut(), 0, &updates)?;
+        backend.write_buffer(self.num_body_updates.buffer_mut(), 0, &[num_updates])?;
+        Ok(num_updates)
+    }
+
+    pub(super) fn body_updates_tensor(
+        backend: &GpuBackend,
+        capacity: u32,
+    ) -> Tensor<GpuBodyUpdate> {
+        Tensor::vector(
+            backend,
+            &vec![GpuBodyUpdate::default(); capacity as usize],
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        )
+        .unwrap()
+    }
+
+    pub(super) fn num_body_updates_tensor(backend: &GpuBackend) -> Tensor<u32> {
+        Tensor::scalar(backend, 0, BufferUsages::UNIFORM | BufferUsages::COPY_DST).unwrap()
+    }
+}
+
+/// Writes the collider/body range of every batch into `bi`.
+pub(super) fn fill_batch_ranges(bi: &mut BatchIndices, ranges: &[RbdBatchRange]) {
+    for (batch_id, range) in ranges.iter().enumerate() {
+        bi.set_colliders_range(
+            batch_id as u32,
+            range.offset,
+            range.colliders_len,
+            range.bodies_len,
+        );
+    }
+}
+
+/// The GPU body type (see [`crate::shaders::dynamics::BODY_TYPE_DYNAMIC`] and
+/// siblings) of a rapier rigid-body.
+pub(super) fn body_type_from_rapier(rb: &crate::rapier::dynamics::RigidBody) -> u32 {
+    use crate::rapier::dynamics::RigidBodyType;
+    use crate::shaders::dynamics::{
+        BODY_TYPE_DYNAMIC, BODY_TYPE_FIXED, BODY_TYPE_KINEMATIC_POSITION,
+        BODY_TYPE_KINEMATIC_VELOCITY,
+    };
+    match rb.body_type() {
+        RigidBodyType::Dynamic => BODY_TYPE_DYNAMIC,
+        RigidBodyType::Fixed => BODY_TYPE_FIXED,
+        RigidBodyType::KinematicPositionBased => BODY_TYPE_KINEMATIC_POSITION,
+        RigidBodyType::KinematicVelocityBased => BODY_TYPE_KINEMATIC_VELOCITY,
+    }
+}
+
+/// Extracts the [`GpuBodyParams`] of a rapier rigid-body.
+///
+/// Only dynamic bodies carry their rapier settings: gravity, damping, locks,
+/// dominance and CCD have no effect on the other body types.
+pub(super) fn body_params_from_rapier(rb: &crate::rapier::dynamics::RigidBody) -> GpuBodyParams {
+    if !rb.is_dynamic() {
+        return GpuBodyParams::default();
+    }
+
+    GpuBodyParams {
+        gravity_scale: rb.gravity_scale(),
+        linear_damping: rb.linear_damping(),
+        angular_damping: rb.angular_damping(),
+        locked_axes: rb.locked_axes().bits() as u32,
+        dominance_group: rb.dominance_group() as i32,
+        flags: if rb.is_ccd_enabled() {
+            BODY_FLAG_CCD_ENABLED
+        } else {
+            0
+        },
+        sleep_linear_threshold: rb.activation().normalized_linear_threshold,
+        sleep_angular_threshold: rb.activation().angular_threshold,
+        time_until_sleep: rb.activation().time_until_sleep,
+        padding: [0; 3],
+    }
+}
+
+/// Extracts the sleep state of a rapier rigid-body. Only dynamic bodies sleep.
+pub(super) fn body_activation_from_rapier(
+    rb: &crate::rapier::dynamics::RigidBody,
+) -> BodyActivation {
+    if !rb.is_dynamic() {
+        return BodyActivation::default();
+    }
+
+    BodyActivation {
+        time_since_can_sleep: rb.activation().time_since_can_sleep,
+        sleeping: rb.is_sleeping() as u32,
+    }
 }
 
 /// Extracts a [`GpuColliderMaterial`] from a rapier collider: friction,
-/// restitution and their `CoefficientCombineRule`s (stored as `rule as u32`).
+/// restitution and their `CoefficientCombineRule`s (stored as `rule as u32`),
+/// whether it is a sensor, and its active events.
 pub(super) fn collider_material_from_rapier(
     co: &crate::rapier::geometry::Collider,
 ) -> GpuColliderMaterial {
+    use crate::rapier::geometry::ActiveEvents;
+
+    let mut flags = 0;
+    if co.is_sensor() {
+        flags |= COLLIDER_FLAG_SENSOR;
+    }
+    if co.active_events().contains(ActiveEvents::COLLISION_EVENTS) {
+        flags |= COLLIDER_FLAG_COLLISION_EVENTS;
+    }
+    if co
+        .active_events()
+        .contains(ActiveEvents::CONTACT_FORCE_EVENTS)
+    {
+        flags |= COLLIDER_FLAG_CONTACT_FORCE_EVENTS;
+    }
+
     GpuColliderMaterial {
         friction: co.friction(),
         restitution: co.restitution(),
         friction_combine_rule: co.friction_combine_rule() as u32,
         restitution_combine_rule: co.restitution_combine_rule() as u32,
+        flags,
+        contact_force_event_threshold: co.contact_force_event_threshold(),
+        part_id: 0,
     }
 }
 
@@ -533,7 +917,7 @@ pub(super) fn world_mprops_from_local(
         GpuWorldMassProperties {
             inv_inertia: local.inv_inertia,
             inv_mass: local.inv_mass,
-            padding1: 0,
+            dominance_group: 0,
             com: *pose * local.com,
         }
     }
@@ -557,7 +941,7 @@ pub(super) fn world_mprops_from_local(
             inv_mass: local.inv_mass,
             padding0: 0,
             com: *pose * local.com,
-            padding1: 0,
+            dominance_group: 0,
         }
     }
 }
@@ -1,35 +1,47 @@
 //! Initialization of [`RbdState`] from CPU-side Rapier data structures.
 
-use crate::broad_phase::LbvhState;
-use crate::dynamics::GpuImpulseJointSet;
-#[cfg(feature = "dim3")]
+use crate::broad_phase::{CollisionEventsState, LbvhState, SensorState};
 use crate::dynamics::GpuMultibodySet;
+use crate::dynamics::{CcdState, GpuImpulseJointSet, IslandState};
 use crate::math::{Pose, Vector};
 use crate::queries::GpuColliderMaterial;
 use crate::shaders::dynamics::{
+    BODY_TYPE_FIXED, BodyActivation, BodyParams as GpuBodyParams, Force as GpuForce,
     LocalMassProperties as GpuLocalMassProperties, RbdSimParams, Velocity as GpuVelocity,
-    WorldMassProperties as GpuWorldMassProperties,
+    WorldMassProperties as GpuWorldMassProperties, is_kinematic,
 };
-use crate::shaders::utils::BatchIndices;
+use crate::shaders::utils::{BatchIndices, MAX_BATCHES};
 use crate::utils::PrefixSumWorkspace;
 
+use super::body_readback::BodyStatesReadback;
+use super::events_readback::EventsReadback;
+use super::param_updates::ParamUpdates;
 use super::rbd_state::*;
 use khal::BufferUsages;
-use khal::backend::{GpuBackend, GpuReadback};
+use khal::backend::{Backend, Encoder, GpuBackend, GpuReadback};
 use vortx::tensor::Tensor;
 use {
     crate::math::Point,
     crate::rapier::dynamics::{ImpulseJointSet, MultibodyJointSet, RigidBodySet},
     crate::rapier::geometry::ColliderSet,
+    crate::shaders::shapes::Shape,
     crate::shapes::ShapeBuffers,
-    crate::shapes::shape_from_parry,
+    crate::shapes::{num_shape_parts, shape_parts_from_parry},
     std::collections::HashMap,
+    std::sync::Arc,
 };
 
 impl RbdState {
     /// Creates a new GPU physics state from per-environment Rapier data structures.
     ///
-    /// Environments with fewer colliders/joints are padded with dummy fixed bodies.
+    /// Environments may have different numbers of colliders, bodies and impulse
+    /// joints: each batch gets its own range of the packed buffers (see
+    /// [`RbdState::batch_range`]), sized to its environment, and the kernels only
+    /// iterate over each batch's own colliders, bodies and joints. The number of
+    /// multibodies and of solver iterations must match across environments, and
+    /// there can be at most [`MAX_BATCHES`] environments.
+    /// Compound colliders are expanded into one GPU collider per part, all
+    /// attached to the collider's body.
     /// Panics if any rigid body has more than one collider attached.
     pub fn from_rapier(
         backend: &GpuBackend,
@@ -41,45 +53,59 @@ impl RbdState {
             &RbdSimParams,
         )],
         capacities: RbdCapacities,
+    ) -> Self {
+        Self::from_rapier_cloned(backend, environments, &[], capacities)
+    }
+
+    /// Like [`Self::from_rapier`], with some environments being copies of others.
+    ///
+    /// `templates[i]` is the environment that environment `i` is a copy of, if
+    /// any: its rapier sets were cloned from the template's and only its body
+    /// states may differ. Its colliders aren't converted, their GPU data is copied
+    /// from the template's batch instead. Templates must not be copies
+    /// themselves. Missing entries are environments without a template.
+    pub fn from_rapier_cloned(
+        backend: &GpuBackend,
+        environments: &[(
+            &RigidBodySet,
+            &ColliderSet,
+            &ImpulseJointSet,
+            &MultibodyJointSet,
+            &RbdSimParams,
+        )],
+        templates: &[Option<usize>],
+        capacities: RbdCapacities,
     ) -> Self {
         let num_batches = environments.len() as u32;
+        assert!(
+            environments.len() <= MAX_BATCHES,
+            "batched rbd supports at most {MAX_BATCHES} environments, got {}",
+            environments.len()
+        );
+        let template_of = |env: usize| templates.get(env).copied().flatten();
+        // GPU colliders of an environment: compound colliders count once per part.
+        let num_gpu_colliders = |colliders: &ColliderSet| -> usize {
+            colliders
+                .iter()
+                .map(|(_, co)| num_shape_parts(co.shape()))
+                .sum()
+        };
 
-        // Equal-topology invariant: every environment must share the same
-        // collider count, joint count, multibody count and solver-iteration
-        // count. Only collider shapes and dynamic state may differ. This lets
-        // the per-batch topology counts collapse to scalar uniforms instead of
-        // padded per-batch storage arrays.
-        if let Some(((b0, c0, ij0, mj0, sp0), rest)) = environments.split_first() {
-            for (i, (b, c, ij, mj, sp)) in rest.iter().enumerate() {
+        // Environments may have different numbers of colliders, bodies and
+        // impulse joints: each batch has its own offset and lengths in
+        // `BatchIndices`. Multibodies aren't packed (`BatchIndices::multibodies_len`
+        // is shared by every batch), so the multibody count and the
+        // solver-iteration count must still match across environments.
+        if let Some(((_, _, _, mj0, sp0), rest)) = environments.split_first() {
+            for (i, (_, _, _, mj, sp)) in rest.iter().enumerate() {
                 let env = i + 1;
-                assert_eq!(
-                    c.len(),
-                    c0.len(),
-                    "batched rbd requires the same collider count in every environment \
-                     (env 0 has {}, env {env} has {})",
-                    c0.len(),
-                    c.len()
-                );
-                assert_eq!(
-                    b.len(),
-                    b0.len(),
-                    "batched rbd requires the same rigid-body count in every environment \
-                     (env 0 has {}, env {env} has {})",
-                    b0.len(),
-                    b.len()
-                );
-                assert_eq!(
-                    ij.len(),
-                    ij0.len(),
-                    "batched rbd requires the same impulse-joint count in every environment \
-                     (env 0 has {}, env {env} has {})",
-                    ij0.len(),
-                    ij.len()
-                );
                 assert_eq!(
                     mj.multibodies().count(),
                     mj0.multibodies().count(),
-                    "batched rbd requires the same multibody count in every environment"
+                    "batched rbd requires the same multibody count in every environment \
+                     (env 0 has {}, env {env} has {})",
+                    mj0.multibodies().count(),
+                    mj.multibodies().count()
                 );
                 assert_eq!(
                     sp.num_solver_iterations, sp0.num_solver_iterations,
@@ -90,24 +116,27 @@ impl RbdState {
             }
         }
 
-        // Equal across all environments by the invariant above, so the
-        // first environment's collider count is the per-batch count and there
-        // is no padding.
-        let num_colliders = environments
-            .first()
-            .map(|(_, c, _, _, _)| c.len())
-            .unwrap_or(0);
+        for (env, (_, colliders, _, _, _)) in environments.iter().enumerate() {
+            if let Some(template) = template_of(env) {
+                assert!(
+                    template < environments.len() && template_of(template).is_none(),
+                    "environment {env} is a copy of {template}, which isn't a template"
+                );
+                assert_eq!(
+                    num_gpu_colliders(colliders),
+                    num_gpu_colliders(environments[template].1),
+                    "environment {env} doesn't have the colliders of its template {template}"
+                );
+            }
+        }
+
         // Body slots are independent of colliders: every collider-parented body,
         // every parentless collider's synthetic body, AND every multibody link
         // (even collider-less ones — visual-only links, spacers) gets a slot, so
-        // rapier-style collider-less bodies need no placeholder collider. Bodies
-        // and colliders still share one per-batch stride; size it to fit whichever
-        // is larger. Equal topology across batches → env 0's counts are the
-        // per-batch counts.
-        let num_bodies_capacity = environments
-            .first()
+        // rapier-style collider-less bodies need no placeholder collider.
+        let env_body_counts: Vec<usize> = environments
+            .iter()
             .map(|(_bodies, colliders, _imp, mbj, _sp)| {
-                let _ = &mbj;
                 let mut parents = std::collections::HashSet::new();
                 let mut parentless = 0usize;
                 for (_, co) in colliders.iter() {
@@ -118,7 +147,6 @@ impl RbdState {
                         None => parentless += 1,
                     }
                 }
-                #[cfg(feature = "dim3")]
                 for mb in mbj.multibodies() {
                     for link in mb.links() {
                         parents.insert(link.rigid_body_handle());
@@ -126,27 +154,51 @@ impl RbdState {
                 }
                 parents.len() + parentless
             })
-            .unwrap_or(0);
-        let max_colliders = num_colliders.max(num_bodies_capacity);
+            .collect();
+
+        // Bodies and colliders share each batch's range; size it to fit whichever
+        // is larger. Padding colliders (the slots past the colliders of an
+        // environment with more bodies than colliders) are attached to a padding
+        // body, so one more slot is reserved for it in that case.
+        let mut batch_ranges = Vec::with_capacity(environments.len());
+        let mut num_slots = 0u32;
+        for ((_, colliders, _, _, _), &num_bodies) in environments.iter().zip(&env_body_counts) {
+            let num_colliders = num_gpu_colliders(colliders);
+            let capacity = num_colliders.max(num_bodies + (num_colliders < num_bodies) as usize);
+            batch_ranges.push(RbdBatchRange {
+                offset: num_slots,
+                capacity: capacity as u32,
+                colliders_len: num_colliders as u32,
+                bodies_len: num_bodies as u32,
+            });
+            num_slots += capacity as u32;
+        }
+        let max_colliders = batch_ranges.iter().map(|r| r.capacity).max().unwrap_or(0) as usize;
 
         let mut all_poses = Vec::new();
         let mut all_vels = Vec::new();
         let mut all_local_mprops = Vec::new();
         let mut all_mprops = Vec::new();
+        let mut all_body_types: Vec<u32> = Vec::new();
+        let mut all_body_params = Vec::new();
+        let mut all_activations = Vec::new();
+        let mut all_forces = Vec::new();
         let mut all_shapes = Vec::new();
         let mut all_collision_groups: Vec<crate::rapier::geometry::InteractionGroups> = Vec::new();
         let mut all_collider_materials: Vec<GpuColliderMaterial> = Vec::new();
         let mut all_collider_local_poses: Vec<Pose> = Vec::new();
         // Per-collider map to the owning rigid body's slot (env-local index).
-        // Bodies and colliders share the `max_colliders` per-batch stride but
-        // form distinct index spaces — a body may own several colliders.
+        // Bodies and colliders share the batch ranges but form distinct index
+        // spaces — a body may own several colliders.
         let mut all_collider_parent: Vec<u32> = Vec::new();
         let mut all_pair_filter: Vec<[u32; 2]> = Vec::new();
-        // Per-environment count of *active* rigid bodies (distinct collider
-        // parents + one synthetic body per parentless collider). Used to assert
-        // the equal-topology invariant and to set `BatchIndices::bodies_len`.
-        let mut all_env_body_counts: Vec<usize> = Vec::new();
         let mut shape_buffers = ShapeBuffers::default();
+        // GPU parts of every shape converted so far, keyed by the address of its
+        // `SharedShape` allocation. Colliders sharing a shape (e.g. the
+        // environments cloned with `NexusState::clone_environment`) then share
+        // its vertex/index data instead of converting it, and rebuilding its
+        // BVH, once per environment.
+        let mut converted_shapes: HashMap<*const (), Vec<(Pose, Shape)>> = HashMap::new();
         let mut joint_envs: Vec<(
             &ImpulseJointSet,
             HashMap<crate::rapier::dynamics::RigidBodyHandle, u32>,
@@ -167,27 +219,33 @@ impl RbdState {
             })
             .collect();
         // Pick representative dt (outer dt, not the per-substep one) from any batch.
-        #[cfg(feature = "dim3")]
         let multibody_dt = environments
             .first()
             .map(|(_, _, _, _, sp)| sp.dt)
             .unwrap_or(1.0 / 60.0);
 
-        // Dummy data for padding shorter environments.
+        // Dummy data for the padding slots of the batch ranges.
         let dummy_pose = Pose::default();
         let dummy_local_mprops = GpuLocalMassProperties::default();
         let dummy_mprops = GpuWorldMassProperties::default();
 
-        #[cfg(feature = "dim3")]
         let mut multibody_envs: Vec<(
             &MultibodyJointSet,
             HashMap<crate::rapier::dynamics::RigidBodyHandle, u32>,
             &RigidBodySet,
         )> = Vec::new();
 
-        #[cfg_attr(not(feature = "dim3"), allow(unused_variables))]
-        for (bodies, colliders, impulse_joints, multibody_joints, _sim_params) in environments {
-            let env_collider_count = colliders.len();
+        for (env, (bodies, colliders, impulse_joints, multibody_joints, _sim_params)) in
+            environments.iter().enumerate()
+        {
+            // The collider data of copies is filled in from their template's
+            // batch on the GPU, only their bodies are converted here.
+            let is_copy = template_of(env).is_some();
+            let env_collider_count = if is_copy {
+                0
+            } else {
+                num_gpu_colliders(colliders)
+            };
             // `body_ids` maps a rigid-body handle to its env-local body slot.
             // A body slot is allocated the first time one of the body's
             // colliders is visited (so multi-collider bodies get one slot).
@@ -231,11 +289,7 @@ impl RbdState {
                 };
 
             // Handle bodies whose multibody disables self-contacts.
-            #[cfg(feature = "dim3")]
-            let no_self_collide: HashMap<
-                crate::rapier::dynamics::RigidBodyHandle,
-                u32,
-            > = {
+            let no_self_collide: HashMap<crate::rapier::dynamics::RigidBodyHandle, u32> = {
                 let mut map = HashMap::new();
                 for (mb_ord, mb) in multibody_joints.multibodies().enumerate() {
                     if !mb.self_contacts_enabled() {
@@ -274,6 +328,10 @@ impl RbdState {
                             all_vels.push(GpuVelocity::new(b.linvel(), b.angvel()));
                             all_local_mprops.push(local_mprops);
                             all_mprops.push(mprops);
+                            all_body_types.push(body_type_from_rapier(b));
+                            all_body_params.push(body_params_from_rapier(b));
+                            all_activations.push(body_activation_from_rapier(b));
+                            all_forces.push(GpuForce::new(b.user_force(), b.user_torque()));
                             idx
                         });
                         (body_local, collider_local_pose)
@@ -287,28 +345,47 @@ impl RbdState {
                         all_vels.push(GpuVelocity::default());
                         all_local_mprops.push(local_mprops);
                         all_mprops.push(mprops);
+                        all_body_types.push(BODY_TYPE_FIXED);
+                        all_body_params.push(GpuBodyParams::default());
+                        all_activations.push(BodyActivation::default());
+                        all_forces.push(GpuForce::default());
                         (idx, *co.position())
                     }
                 };
 
-                all_shapes.push(
-                    shape_from_parry(co.shape(), &mut shape_buffers).expect("Unsupported shape"),
-                );
-                all_collider_local_poses.push(collider_local_pose);
-                all_collision_groups.push(co.collision_groups());
-                all_collider_materials.push(collider_material_from_rapier(co));
-                // Env-local body slot; the kernels apply the per-batch stride.
-                all_collider_parent.push(body_local);
+                if is_copy {
+                    continue;
+                }
 
                 // Broad-phase pair-filter key.
-                #[cfg(feature = "dim3")]
                 let mb_key = co
                     .parent()
                     .and_then(|h| no_self_collide.get(&h).copied())
                     .unwrap_or(0);
-                #[cfg(feature = "dim2")]
-                let mb_key = 0u32;
-                all_pair_filter.push([body_local, mb_key]);
+
+                // Compound colliders get one consecutive GPU collider slot per
+                // part. The parts share the body slot, so the same-body pair
+                // filter keeps them from colliding with each other. Their
+                // `part_id` lets the event kernels report them under the first
+                // part's slot.
+                let shape_key = Arc::as_ptr(&co.shared_shape().0) as *const ();
+                let parts = converted_shapes.entry(shape_key).or_insert_with(|| {
+                    shape_parts_from_parry(co.shape(), &mut shape_buffers)
+                        .expect("Unsupported shape")
+                });
+                let material = collider_material_from_rapier(co);
+                for (part_id, &(part_pose, shape)) in parts.iter().enumerate() {
+                    all_shapes.push(shape);
+                    all_collider_local_poses.push(collider_local_pose * part_pose);
+                    all_collision_groups.push(co.collision_groups());
+                    all_collider_materials.push(GpuColliderMaterial {
+                        part_id: part_id as u32,
+                        ..material
+                    });
+                    // Env-local body slot; the kernels apply the per-batch stride.
+                    all_collider_parent.push(body_local);
+                    all_pair_filter.push([body_local, mb_key]);
+                }
             }
 
             // Give every multibody link a body slot too, even collider-less ones
@@ -316,7 +393,6 @@ impl RbdState {
             // collider-less link's FK pose would fall back to slot 0. Slots are
             // appended after the collider-parented bodies, in multibody-link
             // order; `state.rs`'s `rbd2gpu` rebuild mirrors this exact order.
-            #[cfg(feature = "dim3")]
             for mb in multibody_joints.multibodies() {
                 for link in mb.links() {
                     let h = link.rigid_body_handle();
@@ -331,55 +407,53 @@ impl RbdState {
                         all_vels.push(GpuVelocity::new(b.linvel(), b.angvel()));
                         all_local_mprops.push(local_mprops);
                         all_mprops.push(mprops);
+                        all_body_types.push(body_type_from_rapier(b));
+                        all_body_params.push(body_params_from_rapier(b));
+                        all_activations.push(body_activation_from_rapier(b));
+                        all_forces.push(GpuForce::new(b.user_force(), b.user_torque()));
                         idx
                     });
                 }
             }
 
+            let range = batch_ranges[env];
             let env_body_count = env_body_idx as usize;
-            all_env_body_counts.push(env_body_count);
+            debug_assert_eq!(env_body_count, range.bodies_len as usize);
 
-            // Pad colliders to `max_colliders` with dummy colliders that never
-            // collide (zero membership AND zero filter).
-            let dummy_shape = all_shapes.last().copied().unwrap_or_default();
-            for _ in env_collider_count..max_colliders {
+            // Fill the rest of the batch range with padding colliders: outside of
+            // `colliders_len`, they are never part of the broad-phase, and they
+            // have a zero-extent shape, no collision groups and a padding parent
+            // body. Copies are entirely padding until their template's colliders
+            // are copied over.
+            for _ in env_collider_count..range.capacity as usize {
                 all_collider_local_poses.push(Pose::IDENTITY);
-                all_shapes.push(dummy_shape);
+                all_shapes.push(Shape::ball(0.0));
                 all_collision_groups.push(crate::rapier::geometry::InteractionGroups::new(
                     crate::rapier::geometry::Group::NONE,
                     crate::rapier::geometry::Group::NONE,
                     crate::rapier::geometry::InteractionTestMode::And,
                 ));
                 all_collider_materials.push(GpuColliderMaterial::default());
-                all_collider_parent.push(0);
+                all_collider_parent.push(range.bodies_len);
                 all_pair_filter.push([u32::MAX, 0]);
             }
 
-            // Pad bodies to the shared per-batch stride (`max_colliders` =
-            // `max(num_colliders, num_bodies)`) with dummy fixed bodies.
-            for _ in env_body_count..max_colliders {
+            // Fill the rest of the batch range with dummy fixed bodies.
+            for _ in env_body_count..range.capacity as usize {
                 all_poses.push(dummy_pose);
                 all_vels.push(GpuVelocity::default());
                 all_local_mprops.push(dummy_local_mprops);
                 all_mprops.push(dummy_mprops);
+                all_body_types.push(BODY_TYPE_FIXED);
+                all_body_params.push(GpuBodyParams::default());
+                all_activations.push(BodyActivation::default());
+                all_forces.push(GpuForce::default());
             }
 
-            #[cfg(feature = "dim3")]
             multibody_envs.push((multibody_joints, body_ids.clone(), bodies));
             joint_envs.push((impulse_joints, body_ids));
         }
 
-        // Equal-topology invariant also covers the body count: every batch must
-        // expose the same number of rigid bodies in the same slot order.
-        let num_bodies = all_env_body_counts.first().copied().unwrap_or(0);
-        for (env, count) in all_env_body_counts.iter().enumerate() {
-            assert_eq!(
-                *count, num_bodies,
-                "batched rbd requires the same rigid-body count in every environment \
-                 (env {env} has {count}, env 0 has {num_bodies})"
-            );
-        }
-
         // NOTE: GPU doesn't like empty storage buffer bindings so add dummy data
         //       instead of leaving them empty (which is fine considering they are
         //       not referenced by any collider).
@@ -413,7 +487,6 @@ impl RbdState {
         // joint touching such a body; those joints are routed to
         // `GpuMultibodySet::set_impulse_joints` instead so they go through
         // the generic `M⁻¹·Jᵀ` solver path.
-        #[cfg(feature = "dim3")]
         let (multibody_groups, is_mb_body): (Vec<Vec<u32>>, Vec<Vec<bool>>) = {
             let mut all_groups: Vec<Vec<u32>> = Vec::with_capacity(num_batches as usize);
             let mut all_is_mb: Vec<Vec<bool>> = Vec::with_capacity(num_batches as usize);
@@ -435,9 +508,6 @@ impl RbdState {
             }
             (all_groups, all_is_mb)
         };
-        #[cfg(not(feature = "dim3"))]
-        let (multibody_groups, is_mb_body): (Vec<Vec<u32>>, Vec<Vec<bool>>) =
-            (Vec::new(), Vec::new());
 
         let joints = GpuImpulseJointSet::from_rapier_filtered(
             backend,
@@ -446,8 +516,7 @@ impl RbdState {
             &is_mb_body,
         );
 
-        // Convert multibodies (3D only).
-        #[cfg(feature = "dim3")]
+        // Convert multibodies.
         let multibodies = {
             let mb_refs: Vec<(
                 &MultibodyJointSet,
@@ -457,7 +526,11 @@ impl RbdState {
                 .iter()
                 .map(|(mb, ids, bodies)| (*mb, ids, *bodies))
                 .collect();
-            let mut mb = GpuMultibodySet::from_rapier(backend, &mb_refs, max_colliders as u32);
+            let body_slots: Vec<_> = batch_ranges
+                .iter()
+                .map(|r| r.offset..r.offset + r.capacity)
+                .collect();
+            let mut mb = GpuMultibodySet::from_rapier(backend, &mb_refs, &body_slots);
             // `set_visible_dt` divides by the substep count, so that has to be
             // in place first or the multibody integrates at the wrong rate.
             mb.set_num_solver_iterations(num_solver_iterations);
@@ -487,16 +560,24 @@ impl RbdState {
         // Mark multibody-controlled bodies as kinematic (inv_mass = 0) in the shared
         // body buffers so the rigid-body pipeline leaves them alone. The multibody
         // solver owns their masses internally.
-        #[cfg(feature = "dim3")]
         {
             for (batch_idx, (mb_set, body_ids, _)) in multibody_envs.iter().enumerate() {
-                let batch_offset = batch_idx * max_colliders;
+                let batch_offset = batch_ranges[batch_idx].offset as usize;
                 for mb in mb_set.multibodies() {
                     for link in mb.links() {
                         if let Some(&rb_local_id) = body_ids.get(&link.rigid_body_handle()) {
                             let global = batch_offset + rb_local_id as usize;
                             all_local_mprops[global].inv_mass = Vector::ZERO;
-                            all_local_mprops[global].inv_principal_inertia = glamx::Vec3::ZERO;
+                            #[cfg(feature = "dim3")]
+                            {
+                                all_local_mprops[global].inv_principal_inertia = glamx::Vec3::ZERO;
+                                all_mprops[global].inv_inertia = glamx::Mat4::ZERO;
+                            }
+                            #[cfg(feature = "dim2")]
+                            {
+                                all_local_mprops[global].inv_inertia = 0.0;
+                                all_mprops[global].inv_inertia = 0.0;
+                            }
                             // Multibody links must have a zero local COM in the
                             // shared body buffer: the multibody substep writes
                             // body-origin poses to `solver_body_poses`, and we
@@ -506,22 +587,34 @@ impl RbdState {
                             // as body-origin poses.
                             all_local_mprops[global].com = Vector::ZERO;
                             all_mprops[global].inv_mass = Vector::ZERO;
-                            all_mprops[global].inv_inertia = glamx::Mat4::ZERO;
+                            // The multibody solver moves the link, even if its
+                            // rapier body is kinematic: the rigid-body pipeline
+                            // sees it as fixed.
+                            all_body_types[global] = BODY_TYPE_FIXED;
+                            all_body_params[global] = GpuBodyParams::default();
+                            all_activations[global] = BodyActivation::default();
                         }
                     }
                 }
             }
         }
 
-        let rb_contacts_inert = all_env_body_counts
+        let has_kinematic_bodies = all_body_types.iter().any(|t| is_kinematic(*t));
+        let has_sensors = all_collider_materials.iter().any(|m| m.is_sensor());
+        let has_ccd_bodies = all_body_params.iter().any(|p| p.is_ccd_enabled());
+        let all_collider_flags = all_collider_materials
             .iter()
-            .enumerate()
-            .all(|(batch, &count)| {
-                let start = batch * max_colliders;
-                all_local_mprops[start..start + count]
+            .fold(0, |flags, m| flags | m.flags);
+        let rb_contacts_inert = batch_ranges
+            .iter()
+            .all(|range| {
+                let start = range.offset as usize;
+                all_local_mprops[start..start + range.bodies_len as usize]
                     .iter()
                     .all(|m| m.inv_mass == Vector::ZERO)
-            });
+            })
+            // Kinematic bodies still need to be integrated by the solver.
+            && !has_kinematic_bodies;
 
         // Build the per-body "graph group" lookup. Free bodies map to themselves
         // (one body = one graph node). Bodies belonging to a multibody all map
@@ -533,15 +626,12 @@ impl RbdState {
         // `body_group` stores PER-BATCH local indices (so a kernel can use the
         // same `Slice(buf, colliders_start)` pattern as for `body_constraint_*`
         // and just index by `group_local`).
-        let mut all_body_group: Vec<u32> = Vec::with_capacity(max_colliders * num_batches as usize);
-        for _batch_idx in 0..num_batches as usize {
-            for b in 0..max_colliders {
-                all_body_group.push(b as u32);
-            }
+        let mut all_body_group: Vec<u32> = Vec::with_capacity(num_slots as usize);
+        for range in &batch_ranges {
+            all_body_group.extend(0..range.capacity);
         }
-        #[cfg(feature = "dim3")]
         for (batch_idx, (mb_set, body_ids, _)) in multibody_envs.iter().enumerate() {
-            let base = batch_idx * max_colliders;
+            let base = batch_ranges[batch_idx].offset as usize;
             for mb in mb_set.multibodies() {
                 let group_local = mb
                     .links()
@@ -560,7 +650,7 @@ impl RbdState {
         let body_group = Tensor::vector(backend, &all_body_group, BufferUsages::STORAGE).unwrap();
 
         let num_colliders_per_batch = max_colliders;
-        let num_bodies_total = num_colliders_per_batch * num_batches as usize;
+        let num_bodies_total = num_slots as usize;
 
         // Initial body velocities were accumulated in body-slot order alongside
         // `all_poses`; zero-filling here would silently drop each body's initial
@@ -568,13 +658,69 @@ impl RbdState {
         // initial spin was lost entirely).
         debug_assert_eq!(all_vels.len(), num_bodies_total);
         let storage: BufferUsages = BufferUsages::STORAGE | BufferUsages::COPY_SRC;
-        let shapes = Tensor::vector(backend, &all_shapes, storage).unwrap();
-        let collider_local_poses =
-            Tensor::vector(backend, &all_collider_local_poses, storage).unwrap();
-        let collider_parent = Tensor::vector(backend, &all_collider_parent, storage).unwrap();
-        let collision_groups = Tensor::vector(backend, &all_collision_groups, storage).unwrap();
-        let pair_filter = Tensor::vector(backend, &all_pair_filter, storage).unwrap();
-        let collider_materials = Tensor::vector(backend, &all_collider_materials, storage).unwrap();
+        // COPY_DST so the collider data of the copies can be filled in below.
+        let collider_usages = storage | BufferUsages::COPY_DST;
+        let mut shapes = Tensor::vector(backend, &all_shapes, collider_usages).unwrap();
+        let mut collider_local_poses =
+            Tensor::vector(backend, &all_collider_local_poses, collider_usages).unwrap();
+        let mut collider_parent =
+            Tensor::vector(backend, &all_collider_parent, collider_usages).unwrap();
+        let mut collision_groups =
+            Tensor::vector(backend, &all_collision_groups, collider_usages).unwrap();
+        let mut pair_filter = Tensor::vector(backend, &all_pair_filter, collider_usages).unwrap();
+        let mut collider_materials =
+            Tensor::vector(backend, &all_collider_materials, collider_usages).unwrap();
+
+        // Copy the collider data of each template's batch range into the ranges
+        // of its copies, which have the same size. A buffer can't be copied into
+        // itself, so the template's range goes through a staging buffer.
+        let mut copies_of: HashMap<usize, Vec<usize>> = HashMap::new();
+        for env in 0..environments.len() {
+            if let Some(template) = template_of(env) {
+                copies_of.entry(template).or_default().push(env);
+            }
+        }
+        if !copies_of.is_empty() && max_colliders > 0 {
+            let staging_usages =
+                BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+            macro_rules! replicate {
+                ($t:expr, $ty:ty) => {{
+                    let mut staging = backend
+                        .uninit_buffer::<$ty>(max_colliders, staging_usages)
+                        .unwrap();
+                    let mut enc = backend.begin_encoding();
+                    for (template, copies) in &copies_of {
+                        let range = batch_ranges[*template];
+                        let len = range.capacity as usize;
+                        enc.copy_buffer_to_buffer(
+                            $t.buffer(),
+                            range.offset as usize,
+                            &mut staging,
+                            0,
+                            len,
+                        )
+                        .unwrap();
+                        for copy in copies {
+                            enc.copy_buffer_to_buffer(
+                                &staging,
+                                0,
+                                $t.buffer_mut(),
+                                batch_ranges[*copy].offset as usize,
+                                len,
+                            )
+                            .unwrap();
+                        }
+                    }
+                    backend.submit(enc).unwrap();
+                }};
+            }
+            replicate!(shapes, Shape);
+            replicate!(collider_local_poses, Pose);
+            replicate!(collider_parent, u32);
+            replicate!(collision_groups, crate::rapier::geometry::InteractionGroups);
+            replicate!(pair_filter, [u32; 2]);
+            replicate!(collider_materials, GpuColliderMaterial);
+        }
 
         let collision_pairs = Tensor::vector_uninit(
             backend,
@@ -632,6 +778,27 @@ impl RbdState {
             BufferUsages::STORAGE | BufferUsages::COPY_SRC,
         )
         .unwrap();
+        let sensors = SensorState::new(
+            backend,
+            num_batches,
+            capacities.event_pairs_capacity,
+            has_sensors,
+        )
+        .unwrap();
+        let collision_events = CollisionEventsState::new(
+            backend,
+            num_batches,
+            capacities.event_pairs_capacity,
+            capacities.events_capacity,
+            all_collider_flags,
+        )
+        .unwrap();
+        let collision_events_readback =
+            EventsReadback::new(backend, num_batches, capacities.events_capacity);
+        let contact_force_events_readback =
+            EventsReadback::new(backend, num_batches, capacities.events_capacity);
+        let ccd = CcdState::new(backend, has_ccd_bodies).unwrap();
+        let islands = IslandState::new(backend).unwrap();
         let old_constraints = Tensor::vector_uninit(
             backend,
             capacities.collisions_capacity * num_batches,
@@ -693,18 +860,8 @@ impl RbdState {
             storage,
         )
         .unwrap();
-        let old_constraints_counts = Tensor::vector_uninit(
-            backend,
-            num_colliders_per_batch as u32 * num_batches,
-            storage,
-        )
-        .unwrap();
-        let new_constraints_counts = Tensor::vector_uninit(
-            backend,
-            num_colliders_per_batch as u32 * num_batches,
-            storage,
-        )
-        .unwrap();
+        let old_constraints_counts = Tensor::vector_uninit(backend, num_slots, storage).unwrap();
+        let new_constraints_counts = Tensor::vector_uninit(backend, num_slots, storage).unwrap();
         let old_body_constraint_ids = Tensor::vector_uninit(
             backend,
             capacities.collisions_capacity * 2 * num_batches,
@@ -726,20 +883,18 @@ impl RbdState {
 
         let contacts_per_batch_cpu = capacities.collisions_capacity;
         let collision_pairs_per_batch_cpu = capacities.collisions_capacity;
-        #[allow(unused_mut)] // Only mutated with the dim3 (multibody) feature.
         let mut bi = BatchIndices {
             num_batches,
             colliders_batch_capacity: num_colliders_per_batch as u32,
-            colliders_len: num_colliders as u32,
-            bodies_len: num_bodies as u32,
             collision_pairs_batch_capacity: collision_pairs_per_batch_cpu,
             contacts_batch_capacity: contacts_per_batch_cpu,
-            impulse_joints_batch_capacity: joints.joints_per_batch(),
-            impulse_joints_len: joints.num_active_joints(),
+            event_pairs_batch_capacity: capacities.event_pairs_capacity,
+            events_batch_capacity: capacities.events_capacity,
             solver_color_buckets_stride: color_buckets_stride,
             ..Default::default()
         };
-        #[cfg(feature = "dim3")]
+        fill_batch_ranges(&mut bi, &batch_ranges);
+        joints.fill_batch_indices(&mut bi);
         multibodies.fill_batch_indices(&mut bi);
         let batch_indices = Tensor::scalar(
             backend,
@@ -754,15 +909,46 @@ impl RbdState {
             num_colliders_per_batch: num_colliders_per_batch as u32,
             num_solver_iterations,
             sim_params: Tensor::vector(backend, &all_sim_params, BufferUsages::STORAGE).unwrap(),
-            vels: Tensor::vector(backend, &all_vels, storage).unwrap(),
+            // COPY_DST so `set_kinematic_velocity` can write velocities in place.
+            vels: Tensor::vector(
+                backend,
+                &all_vels,
+                BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            )
+            .unwrap(),
             solver_vels: Tensor::vector(backend, &all_vels, storage).unwrap(),
             solver_vels_inc: Tensor::vector(backend, &all_vels, storage).unwrap(),
+            body_types: Tensor::vector(backend, &all_body_types, storage).unwrap(),
+            // Position-based kinematic bodies target their current pose until
+            // `set_kinematic_next_pose` is called.
+            kinematic_targets: Tensor::vector(
+                backend,
+                &all_poses,
+                BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            )
+            .unwrap(),
+            forces: Tensor::vector(backend, &all_forces, storage).unwrap(),
+            pending_body_updates: HashMap::new(),
+            body_updates: RbdState::body_updates_tensor(backend, 1),
+            num_body_updates: RbdState::num_body_updates_tensor(backend),
+            body_states_readback: BodyStatesReadback::new(backend, all_poses.len()),
             joints,
-            #[cfg(feature = "dim3")]
             multibodies,
-            gravity: RbdState::gravity_tensor(backend, [0.0, -9.81, 0.0]),
+            joint_wrenches_readback: None,
+            joint_break_events_readback: None,
+            env_snapshots: None,
+            param_updates: ParamUpdates::new(backend),
+            gravity: RbdState::gravity_tensor(backend, num_batches, [0.0, -9.81, 0.0]),
             body_group,
             local_mprops: Tensor::vector(backend, &all_local_mprops, storage).unwrap(),
+            body_params: Tensor::vector(backend, &all_body_params, storage).unwrap(),
+            // COPY_SRC so `NexusState::sync_to_rapier` can read the sleep states back.
+            activations: Tensor::vector(
+                backend,
+                &all_activations,
+                BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+            )
+            .unwrap(),
             mprops: Tensor::vector(backend, &all_mprops, storage).unwrap(),
             body_poses: Tensor::vector(
                 backend,
@@ -810,6 +996,12 @@ impl RbdState {
             contacts_len,
             contacts_indirect,
             mb_sweep_indirect,
+            sensors,
+            collision_events,
+            collision_events_readback,
+            ccd,
+            islands,
+            contact_force_events_readback,
             pfm_pairs,
             pfm_pairs_len,
             pfm_pairs_indirect,
@@ -855,8 +1047,8 @@ impl RbdState {
             lbvh: LbvhState::with_usages(backend, lbvh_usages),
             max_colors: capacities.solver_colors,
             rb_contacts_inert,
-            num_active_colliders: num_colliders as u32,
-            num_active_bodies: num_bodies as u32,
+            has_kinematic_bodies,
+            batch_ranges,
         }
     }
 }
@@ -1,11 +1,13 @@
 //! The [`RbdPipeline`] running one full simulation step on the GPU.
 
-use crate::broad_phase::{BRUTE_FORCE_MAX_COLLIDERS, GpuNarrowPhase, Lbvh};
-#[cfg(feature = "dim3")]
+use crate::broad_phase::{
+    BRUTE_FORCE_MAX_COLLIDERS, GpuCollisionEvents, GpuNarrowPhase, GpuSensors, Lbvh,
+};
 use crate::dynamics::GpuMultibodySolver;
 use crate::dynamics::{
-    ColoringArgs, GpuColoring, GpuJointSolver, GpuMpropsUpdate, GpuSolver, GpuWarmstart,
-    JointSolverArgs, SolverArgs, warmstart::WarmstartArgs,
+    ColoringArgs, GpuBodyUpdates, GpuCcd, GpuColoring, GpuEnvSnapshots, GpuIslands, GpuJointSolver,
+    GpuKinematics, GpuMpropsUpdate, GpuParamUpdates, GpuSolver, GpuWarmstart, JointSolverArgs,
+    SolverArgs, warmstart::WarmstartArgs,
 };
 use crate::shaders::broad_phase::LbvhNode;
 use crate::utils::GpuPrefixSum;
@@ -22,10 +24,17 @@ use vortx::tensor::Tensor;
 pub struct RbdPipeline {
     mprops_update: GpuMpropsUpdate,
     sync_collider_poses: crate::dynamics::GpuSyncColliderPosesShader,
+    kinematics: GpuKinematics,
+    body_updates: GpuBodyUpdates,
+    env_snapshots: GpuEnvSnapshots,
+    param_updates: GpuParamUpdates,
     narrow_phase: GpuNarrowPhase,
+    sensors: GpuSensors,
+    collision_events: GpuCollisionEvents,
+    ccd: GpuCcd,
+    islands: GpuIslands,
     solver: GpuSolver,
     joint_solver: GpuJointSolver,
-    #[cfg(feature = "dim3")]
     multibody_solver: GpuMultibodySolver,
     prefix_sum: GpuPrefixSum,
     lbvh: Lbvh,
@@ -44,10 +53,17 @@ impl RbdPipeline {
             sync_collider_poses: crate::dynamics::GpuSyncColliderPosesShader::from_backend(
                 backend,
             )?,
+            kinematics: GpuKinematics::from_backend(backend)?,
+            body_updates: GpuBodyUpdates::from_backend(backend)?,
+            env_snapshots: GpuEnvSnapshots::from_backend(backend)?,
+            param_updates: GpuParamUpdates::from_backend(backend)?,
             narrow_phase: GpuNarrowPhase::from_backend(backend)?,
+            sensors: GpuSensors::from_backend(backend)?,
+            collision_events: GpuCollisionEvents::from_backend(backend)?,
+            ccd: GpuCcd::from_backend(backend)?,
+            islands: GpuIslands::from_backend(backend)?,
             solver: GpuSolver::from_backend(backend)?,
             joint_solver: GpuJointSolver::from_backend(backend)?,
-            #[cfg(feature = "dim3")]
             multibody_solver: GpuMultibodySolver::from_backend(backend)?,
             prefix_sum: GpuPrefixSum::from_backend(backend)?,
             lbvh: Lbvh::from_backend(backend),
@@ -73,44 +89,145 @@ impl RbdPipeline {
         {
             let mut needed = state.max_colors + 2;
             needed = needed.max(state.joints.num_colors() + 1);
-            #[cfg(feature = "dim3")]
-            {
-                needed = needed.max(state.multibodies.mb_imp_joint_num_colors() + 1);
-            }
+            needed = needed.max(state.multibodies.mb_imp_joint_num_colors() + 1);
             state.ensure_color_uniforms(backend, needed);
         }
 
+        let num_body_updates = state.upload_body_updates(backend)?;
+        let has_env_snapshot_ops = state.upload_env_snapshot_ops(backend)?;
+        let num_param_updates = state.upload_param_updates(backend)?;
+
         let mut encoder = backend.begin_encoding();
 
-        // Phase 0: Multibody once-per-visible-step setup (3D only for now).
-        #[cfg(feature = "dim3")]
-        {
-            if !state.multibodies.is_empty() {
-                let mut args = crate::dynamics::MultibodySolverArgs {
-                    poses: &mut state.body_poses,
-                    collider_world_poses: &state.collider_world_poses,
-                    mprops: &state.mprops,
-                    contacts: &state.contacts,
-                    contacts_len: &state.contacts_len,
-                    solver_vels: &mut state.solver_vels,
-                    batch_indices: &state.batch_indices,
-                    color_uniforms: &state.color_uniforms,
-                    mb_sweep_indirect: &state.mb_sweep_indirect,
-                    gravity: &state.gravity,
-                };
-                self.multibody_solver.init_step(
-                    &mut encoder,
-                    timestamps.as_deref_mut(),
-                    &mut state.multibodies,
-                    &mut args,
-                )?;
-            }
+        // Capture and restore the per-environment snapshots before anything
+        // reads the body or multibody state.
+        if has_env_snapshot_ops && let Some(snapshots) = state.env_snapshots.as_mut() {
+            let mut pass = encoder.begin_pass("[RBD] env-snapshots", timestamps.as_deref_mut());
+            self.env_snapshots.dispatch(
+                &mut pass,
+                &snapshots.ops,
+                &mut snapshots.bodies,
+                &mut state.body_poses,
+                &mut state.vels,
+                &mut state.kinematic_targets,
+                &mut state.forces,
+                &mut state.activations,
+                &mut state.old_constraints_counts,
+                &state.batch_indices,
+                state.num_colliders_per_batch,
+                state.num_batches,
+            )?;
+            self.env_snapshots.dispatch_contacts(
+                &mut pass,
+                &snapshots.ops,
+                &mut snapshots.contacts,
+                &mut snapshots.contact_builders,
+                &mut snapshots.body_constraint_ids,
+                &mut state.old_constraints,
+                &mut state.old_constraint_builders,
+                &mut state.old_body_constraint_ids,
+                &state.batch_indices,
+                state.contacts_per_batch_cpu,
+                state.num_batches,
+            )?;
+            self.joint_solver.copy_env_snapshots(
+                &mut pass,
+                &mut state.joints,
+                &snapshots.ops,
+                &mut snapshots.joints_enabled,
+                &state.batch_indices,
+                state.num_batches,
+            )?;
+            self.multibody_solver.copy_env_snapshots(
+                &mut pass,
+                &mut state.multibodies,
+                &snapshots.ops,
+                &mut snapshots.mb_workspace,
+                &mut snapshots.mb_dofs,
+                &mut snapshots.mb_contacts,
+                &mut snapshots.mb_contact_counts,
+                &state.batch_indices,
+            )?;
+        }
+
+        // Write the randomized parameters before the mass properties and the
+        // multibody setup read them.
+        if num_param_updates > 0 {
+            let mut pass = encoder.begin_pass("[RBD] param-updates", timestamps.as_deref_mut());
+            let (links_static, dof_state) = state.multibodies.parameter_buffers_mut();
+            self.param_updates.dispatch(
+                &mut pass,
+                &state.param_updates.updates,
+                &mut state.collider_materials,
+                &mut state.local_mprops,
+                links_static,
+                dof_state,
+                &mut state.gravity,
+                &state.param_updates.header,
+                &state.batch_indices,
+                num_param_updates,
+            )?;
+        }
+
+        // Phase 0: Multibody once-per-visible-step setup.
+        if !state.multibodies.is_empty() {
+            let mut args = crate::dynamics::MultibodySolverArgs {
+                poses: &mut state.body_poses,
+                collider_world_poses: &state.collider_world_poses,
+                mprops: &state.mprops,
+                contacts: &state.contacts,
+                contacts_len: &state.contacts_len,
+                solver_vels: &mut state.solver_vels,
+                batch_indices: &state.batch_indices,
+                color_uniforms: &state.color_uniforms,
+                mb_sweep_indirect: &state.mb_sweep_indirect,
+                gravity: &state.gravity,
+            };
+            self.multibody_solver.init_step(
+                &mut encoder,
+                timestamps.as_deref_mut(),
+                &mut state.multibodies,
+                &mut args,
+            )?;
         }
 
         // Phase 1: Update mass properties, build LBVH, and find collision pairs.
         {
             let mut pass = encoder.begin_pass("[RBD] update-mprops", timestamps.as_deref_mut());
 
+            // Scatter the user's pose/velocity/impulse/force writes first so
+            // every kernel below sees them.
+            self.body_updates.dispatch(
+                &mut pass,
+                &state.body_updates,
+                &mut state.body_poses,
+                &mut state.vels,
+                &mut state.kinematic_targets,
+                &mut state.forces,
+                &state.local_mprops,
+                &state.body_params,
+                &mut state.activations,
+                &state.body_types,
+                &state.num_body_updates,
+                num_body_updates,
+            )?;
+
+            // Derive the velocities of position-based kinematic bodies from
+            // their target poses, before the solver picks them up.
+            if state.has_kinematic_bodies {
+                self.kinematics.dispatch_velocities(
+                    &mut pass,
+                    &state.body_types,
+                    &state.kinematic_targets,
+                    &state.body_poses,
+                    &mut state.vels,
+                    &state.sim_params,
+                    &state.batch_indices,
+                    state.num_colliders_per_batch,
+                    state.num_batches,
+                )?;
+            }
+
             // Update mass properties — uses body world poses to compute the
             // world COM and inertia tensor.
             self.mprops_update.dispatch(
@@ -118,6 +235,8 @@ impl RbdPipeline {
                 &mut state.mprops,
                 &state.local_mprops,
                 &state.body_poses,
+                &state.body_params,
+                &state.activations,
                 &state.batch_indices,
                 state.num_colliders_per_batch,
                 state.num_batches,
@@ -135,9 +254,26 @@ impl RbdPipeline {
                 state.num_batches,
             )?;
 
+            // Predict the motion of the CCD bodies so the broad phase finds the
+            // pairs they may hit during the step.
+            self.ccd.dispatch_sweeps(
+                backend,
+                &mut pass,
+                &mut state.ccd,
+                &state.collider_parent,
+                &state.vels,
+                &state.mprops,
+                &state.body_params,
+                &state.sim_params,
+                &state.batch_indices,
+                state.num_colliders_per_batch,
+                state.num_batches,
+            )?;
+
             drop(pass);
 
-            let use_bf = state.num_active_colliders <= BRUTE_FORCE_MAX_COLLIDERS
+            let max_active_colliders = state.max_active_colliders();
+            let use_bf = max_active_colliders <= BRUTE_FORCE_MAX_COLLIDERS
                 && std::env::var("NEXUS_DISABLE_BF").is_err();
             if use_bf {
                 let mut pass = encoder.begin_pass("[RBD] bf-find-pairs", timestamps.as_deref_mut());
@@ -145,12 +281,12 @@ impl RbdPipeline {
                     backend,
                     &mut pass,
                     &mut state.lbvh,
-                    state.collider_local_poses.len() as u32,
-                    state.num_active_colliders,
+                    state.num_colliders_per_batch,
                     state.num_batches,
                     &state.collider_world_poses,
                     &state.vertex_buffers,
                     &state.shapes,
+                    state.ccd.sweeps(),
                     &state.batch_indices,
                     &mut state.collision_pairs,
                     &mut state.collision_pairs_len,
@@ -166,12 +302,12 @@ impl RbdPipeline {
                     backend,
                     &mut encoder,
                     &mut state.lbvh,
-                    state.collider_local_poses.len() as u32,
-                    state.num_active_colliders,
-                    state.num_batches,
+                    state.num_colliders_per_batch,
+                    &state.active_colliders_per_batch(),
                     &state.collider_world_poses,
                     &state.vertex_buffers,
                     &state.shapes,
+                    state.ccd.sweeps(),
                     &state.batch_indices,
                     timestamps.as_deref_mut(),
                 )?;
@@ -180,14 +316,22 @@ impl RbdPipeline {
                 if crate::VALIDATE_LBVH_TOPOLOGY {
                     backend.submit(encoder)?;
 
-                    let num_colliders = state.collider_world_poses.len() as u32;
                     let tree: Vec<LbvhNode> = futures::executor::block_on(
                         backend.slow_read_vec(state.lbvh.tree().buffer()),
                     )?;
                     let sorted_colliders: Vec<u32> = futures::executor::block_on(
                         backend.slow_read_vec(state.lbvh.sorted_colliders().buffer()),
                     )?;
-                    validate_lbvh_topology(&tree, &sorted_colliders, num_colliders);
+                    // Every batch has its own tree, `num_colliders_per_batch` leaves apart.
+                    let cap = state.num_colliders_per_batch as usize;
+                    for batch_id in 0..state.num_batches {
+                        let start = batch_id as usize * cap;
+                        validate_lbvh_topology(
+                            &tree[2 * start..2 * (start + cap)],
+                            &sorted_colliders[start..start + cap],
+                            state.num_active_colliders(batch_id),
+                        );
+                    }
 
                     encoder = backend.begin_encoding();
                     let _pass = encoder
@@ -199,7 +343,7 @@ impl RbdPipeline {
                 self.lbvh.find_pairs(
                     &mut pass,
                     &mut state.lbvh,
-                    state.num_active_colliders,
+                    max_active_colliders,
                     state.num_batches,
                     &state.batch_indices,
                     &mut state.collision_pairs,
@@ -260,6 +404,52 @@ impl RbdPipeline {
                 &state.collider_materials,
             )?;
 
+            // Sensor pairs were skipped by the contact generation above: detect
+            // their intersections instead.
+            self.sensors.dispatch(
+                &mut pass,
+                &mut state.sensors,
+                &mut state.collision_events,
+                &state.collider_world_poses,
+                &state.shapes,
+                &state.vertex_buffers,
+                &state.index_buffers,
+                &state.collision_pairs,
+                &state.collision_pairs_len,
+                &state.collision_pairs_indirect,
+                &state.batch_indices,
+                &state.collider_parent,
+                &state.collider_materials,
+            )?;
+
+            self.collision_events.dispatch_contact_events(
+                &mut pass,
+                &mut state.collision_events,
+                &state.contacts,
+                &state.contacts_len,
+                &state.contacts_indirect,
+                &state.collider_materials,
+                &state.batch_indices,
+            )?;
+
+            // Record the start of the motion of the CCD bodies before the
+            // solver moves them.
+            self.ccd.dispatch_pairs(
+                backend,
+                &mut pass,
+                &mut state.ccd,
+                &state.body_poses,
+                &state.collision_pairs,
+                &state.collision_pairs_len,
+                &state.collision_pairs_indirect,
+                &state.collider_parent,
+                &state.collider_materials,
+                &state.body_params,
+                &state.batch_indices,
+                state.num_colliders_per_batch,
+                state.num_batches,
+            )?;
+
             drop(pass);
             if !merge_submits {
                 backend.submit(encoder)?;
@@ -290,6 +480,9 @@ impl RbdPipeline {
                 solver_vels_inc: &mut state.solver_vels_inc,
                 mprops: &state.mprops,
                 local_mprops: &state.local_mprops,
+                body_types: &state.body_types,
+                body_params: &state.body_params,
+                forces: &state.forces,
                 body_constraint_counts: &mut state.new_constraints_counts,
                 body_constraint_ids: &mut state.new_body_constraint_ids,
                 color_bucket_starts: &state.color_bucket_starts,
@@ -442,6 +635,9 @@ impl RbdPipeline {
             solver_vels_inc: &mut state.solver_vels_inc,
             mprops: &state.mprops,
             local_mprops: &state.local_mprops,
+            body_types: &state.body_types,
+            body_params: &state.body_params,
+            forces: &state.forces,
             body_constraint_counts: &mut state.new_constraints_counts,
             body_constraint_ids: &mut state.new_body_constraint_ids,
             color_bucket_starts: &state.color_bucket_starts,
@@ -457,10 +653,7 @@ impl RbdPipeline {
             mb_sweep_indirect: &state.mb_sweep_indirect,
             // The gather warmstart is only valid without multibody grouping;
             // see `SolverArgs::colorless_warmstart`.
-            #[cfg(feature = "dim3")]
             colorless_warmstart: state.multibodies.is_empty(),
-            #[cfg(not(feature = "dim3"))]
-            colorless_warmstart: true,
             fused_color_sweeps,
             rb_contacts_inert: state.rb_contacts_inert,
             gravity: &state.gravity,
@@ -478,7 +671,6 @@ impl RbdPipeline {
         };
 
         {
-            #[cfg(feature = "dim3")]
             let mb = if state.multibodies.is_empty() {
                 None
             } else {
@@ -490,10 +682,88 @@ impl RbdPipeline {
                 &self.joint_solver,
                 solver_args,
                 joint_solver_args,
-                #[cfg(feature = "dim3")]
                 mb,
             )?;
 
+            // Remove the integration drift of position-based kinematic bodies.
+            if state.has_kinematic_bodies {
+                let mut pass =
+                    encoder.begin_pass("[RBD] kinematic-snap", timestamps.as_deref_mut());
+                self.kinematics.dispatch_snap_poses(
+                    &mut pass,
+                    &state.body_types,
+                    &state.kinematic_targets,
+                    &mut state.body_poses,
+                    &state.batch_indices,
+                    state.num_colliders_per_batch,
+                    state.num_batches,
+                )?;
+                drop(pass);
+            }
+
+            // Move the CCD bodies back to their earliest time of impact.
+            if state.ccd.is_enabled() {
+                let mut pass = encoder.begin_pass("[RBD] ccd", timestamps.as_deref_mut());
+                self.ccd.dispatch_clamp_motion(
+                    &mut pass,
+                    &mut state.ccd,
+                    &mut state.body_poses,
+                    &state.collider_local_poses,
+                    &state.collider_world_poses,
+                    &state.collider_parent,
+                    &state.shapes,
+                    &state.vertex_buffers,
+                    &state.index_buffers,
+                    &state.collision_pairs_indirect,
+                    &state.batch_indices,
+                    state.num_colliders_per_batch,
+                    state.num_batches,
+                )?;
+                drop(pass);
+            }
+
+            // Put the islands at rest to sleep, and wake up the ones touched by
+            // an awake body.
+            if !state.rb_contacts_inert {
+                let mut pass = encoder.begin_pass("[RBD] islands", timestamps.as_deref_mut());
+                self.islands.dispatch(
+                    backend,
+                    &mut pass,
+                    &mut state.islands,
+                    &mut state.activations,
+                    &mut state.vels,
+                    &state.body_types,
+                    &state.body_params,
+                    &state.contacts,
+                    &state.contacts_len,
+                    &state.contacts_indirect,
+                    &state.joints,
+                    &state.sim_params,
+                    &state.batch_indices,
+                    state.num_colliders_per_batch,
+                    state.num_batches,
+                )?;
+                drop(pass);
+            }
+
+            // Read the solved impulses before the constraint buffers are swapped.
+            if state.collision_events.has_contact_force_events() && !state.rb_contacts_inert {
+                let mut pass =
+                    encoder.begin_pass("[RBD] contact-force-events", timestamps.as_deref_mut());
+                self.collision_events.dispatch_contact_force_events(
+                    &mut pass,
+                    &mut state.collision_events,
+                    &state.contacts,
+                    &state.contacts_len,
+                    &state.contacts_indirect,
+                    &state.new_constraints,
+                    &state.collider_materials,
+                    &state.sim_params,
+                    &state.batch_indices,
+                )?;
+                drop(pass);
+            }
+
             // Resolve all accumulated timestamps before the final submit.
             if let Some(ts) = &timestamps {
                 ts.resolve(&mut encoder);
@@ -0,0 +1,221 @@
+//! Batched scene queries (ray casts, shape casts and point projections) against an [`RbdState`].
+
+use super::rbd_state::RbdState;
+use crate::broad_phase::Lbvh;
+use crate::dynamics::GpuSyncColliderPosesShader;
+use crate::queries::{
+    GpuPointQuery, GpuRayQuery, GpuSceneQueryCollider, GpuSceneQueryHit, GpuShapeCastQuery,
+};
+use crate::shaders::queries::{
+    GpuSceneCastRays, GpuSceneCastShapes, GpuSceneProjectPoints, GpuSceneQueryColliders,
+};
+use khal::backend::{Encoder, GpuBackend, GpuBackendError, GpuEncoder, GpuPass, GpuTimestamps};
+use khal::{BufferUsages, Shader};
+use vortx::tensor::Tensor;
+
+/// GPU shaders for the scene queries.
+#[derive(Shader)]
+pub struct GpuSceneQueries {
+    query_colliders: GpuSceneQueryColliders,
+    cast_rays: GpuSceneCastRays,
+    cast_shapes: GpuSceneCastShapes,
+    project_points: GpuSceneProjectPoints,
+}
+
+/// Runs batched ray casts, shape casts and point projections on the GPU.
+///
+/// The query tensors are split evenly between the batches of the state, so
+/// their length must be a multiple of the number of batches: with
+/// `num_batches` batches and `n` queries, the queries `[b * n / num_batches, (b + 1) * n / num_batches[`
+/// are only tested against the colliders of batch `b`. The result of the `i`-th
+/// query is written to the `i`-th element of the hits tensor, with collider
+/// indices local to the query's batch.
+///
+/// Each query only hits the colliders passing its filter: by default, every
+/// collider except sensors.
+///
+/// [`SceneQueries::update`] must be called whenever the bodies moved (or the
+/// collision groups or sensor flags changed) before running any query.
+pub struct SceneQueries {
+    shaders: GpuSceneQueries,
+    sync_collider_poses: GpuSyncColliderPosesShader,
+    lbvh: Lbvh,
+    /// Per-collider data tested against the query filters, strided like the
+    /// other per-collider buffers.
+    colliders: Tensor<GpuSceneQueryCollider>,
+}
+
+impl SceneQueries {
+    /// Creates the scene-queries pipeline by loading its shaders on the given backend.
+    pub fn new(backend: &GpuBackend) -> Result<Self, GpuBackendError> {
+        Ok(Self {
+            shaders: GpuSceneQueries::from_backend(backend)?,
+            sync_collider_poses: GpuSyncColliderPosesShader::from_backend(backend)?,
+            lbvh: Lbvh::from_backend(backend),
+            colliders: Tensor::vector_uninit(backend, 0, BufferUsages::STORAGE)?,
+        })
+    }
+
+    /// Refreshes the collider world poses and filtering data, and rebuilds the
+    /// LBVH of every batch.
+    ///
+    /// The simulation step only builds the LBVH for large batches (small ones use
+    /// a brute-force broad-phase), so the tree is always rebuilt here.
+    pub fn update(
+        &mut self,
+        backend: &GpuBackend,
+        encoder: &mut GpuEncoder,
+        state: &mut RbdState,
+        mut timestamps: Option<&mut GpuTimestamps>,
+    ) -> Result<(), GpuBackendError> {
+        if state.max_active_colliders() == 0 {
+            return Ok(());
+        }
+
+        let mut pass =
+            encoder.begin_pass("[RBD] scene-queries-sync-poses", timestamps.as_deref_mut());
+        self.sync_collider_poses.dispatch(
+            &mut pass,
+            &state.body_poses,
+            &state.collider_local_poses,
+            &mut state.collider_world_poses,
+            &state.collider_parent,
+            &state.batch_indices,
+            state.num_colliders_per_batch,
+            state.num_batches,
+        )?;
+
+        let num_colliders = state.collider_local_poses.len() as u32;
+        if (self.colliders.len() as u32) < num_colliders {
+            self.colliders = Tensor::vector_uninit(backend, num_colliders, BufferUsages::STORAGE)?;
+        }
+        self.shaders.query_colliders.call(
+            &mut pass,
+            [state.num_colliders_per_batch, state.num_batches, 1],
+            &state.collision_groups,
+            &state.collider_materials,
+            &mut self.colliders,
+            &state.batch_indices,
+        )?;
+        drop(pass);
+
+        self.lbvh.update_tree(
+            backend,
+            encoder,
+            &mut state.lbvh,
+            state.num_colliders_per_batch,
+            &state.active_colliders_per_batch(),
+            &state.collider_world_poses,
+            &state.vertex_buffers,
+            &state.shapes,
+            // Queries need the tight AABBs: no CCD sweeps.
+            None,
+            &state.batch_indices,
+            timestamps,
+        )
+    }
+
+    /// Casts rays on the colliders of their batch, keeping the closest hit.
+    pub fn cast_rays(
+        &self,
+        pass: &mut GpuPass,
+        state: &RbdState,
+        rays: &Tensor<GpuRayQuery>,
+        hits: &mut Tensor<GpuSceneQueryHit>,
+    ) -> Result<(), GpuBackendError> {
+        assert_eq!(rays.len(), hits.len(), "one hit must be allocated per ray");
+        assert_eq!(
+            rays.len() % state.num_batches as usize,
+            0,
+            "the number of rays must be a multiple of the number of batches"
+        );
+        let rays_per_batch = rays.len() as u32 / state.num_batches;
+        self.shaders.cast_rays.call(
+            pass,
+            [rays_per_batch, state.num_batches, 1],
+            state.lbvh.tree(),
+            &state.collider_world_poses,
+            &state.shapes,
+            rays,
+            hits,
+            &self.colliders,
+            &state.batch_indices,
+            &state.vertex_buffers,
+            &state.index_buffers,
+        )?;
+        Ok(())
+    }
+
+    /// Casts shapes on the colliders of their batch, keeping the earliest hit.
+    ///
+    /// Only convex shapes (balls, cuboids, capsules, cones, cylinders, convex
+    /// polyhedra and triangles) can be cast; other queries never hit anything.
+    pub fn cast_shapes(
+        &self,
+        pass: &mut GpuPass,
+        state: &RbdState,
+        casts: &Tensor<GpuShapeCastQuery>,
+        hits: &mut Tensor<GpuSceneQueryHit>,
+    ) -> Result<(), GpuBackendError> {
+        assert_eq!(
+            casts.len(),
+            hits.len(),
+            "one hit must be allocated per cast"
+        );
+        assert_eq!(
+            casts.len() % state.num_batches as usize,
+            0,
+            "the number of casts must be a multiple of the number of batches"
+        );
+        let casts_per_batch = casts.len() as u32 / state.num_batches;
+        self.shaders.cast_shapes.call(
+            pass,
+            [casts_per_batch, state.num_batches, 1],
+            state.lbvh.tree(),
+            &state.collider_world_poses,
+            &state.shapes,
+            casts,
+            hits,
+            &self.colliders,
+            &state.batch_indices,
+            &state.vertex_buffers,
+            &state.index_buffers,
+        )?;
+        Ok(())
+    }
+
+    /// Projects points on the closest collider of their batch.
+    pub fn project_points(
+        &self,
+        pass: &mut GpuPass,
+        state: &RbdState,
+        points: &Tensor<GpuPointQuery>,
+        hits: &mut Tensor<GpuSceneQueryHit>,
+    ) -> Result<(), GpuBackendError> {
+        assert_eq!(
+            points.len(),
+            hits.len(),
+            "one hit must be allocated per point"
+        );
+        assert_eq!(
+            points.len() % state.num_batches as usize,
+            0,
+            "the number of points must be a multiple of the number of batches"
+        );
+        let points_per_batch = points.len() as u32 / state.num_batches;
+        self.shaders.project_points.call(
+            pass,
+            [points_per_batch, state.num_batches, 1],
+            state.lbvh.tree(),
+            &state.collider_world_poses,
+            &state.shapes,
+            points,
+            hits,
+            &self.colliders,
+            &state.batch_indices,
+            &state.vertex_buffers,
+            &state.index_buffers,
+        )?;
+        Ok(())
+    }
+}
@@ -8,6 +8,8 @@
 
 mod contact;
 mod projection;
+mod scene_queries;
 
 pub use contact::*;
 pub use projection::*;
+pub use scene_queries::*;
@@ -0,0 +1,11 @@
+//! Query and hit types of the batched scene queries.
+//!
+//! See [`SceneQueries`](crate::pipeline::SceneQueries) for running them.
+
+// Re-export scene query types from the shader crate with Gpu prefix.
+pub use crate::shaders::queries::{
+    PointQuery as GpuPointQuery, RayQuery as GpuRayQuery, SCENE_QUERY_INCLUDE_SENSORS,
+    SCENE_QUERY_NONE, SceneQueryCollider as GpuSceneQueryCollider,
+    SceneQueryFilter as GpuSceneQueryFilter, SceneQueryHit as GpuSceneQueryHit,
+    ShapeCastQuery as GpuShapeCastQuery,
+};
@@ -2,13 +2,14 @@
 //!
 //! Converts shapes from CPU-side representations (like parry shapes) to the
 //! GPU-compatible [`Shape`] type (defined in the shader crate, re-exported from
-//! the crate root). Complex shapes (polylines, triangle meshes) reference
-//! external vertex data held in [`ShapeBuffers`] rather than storing it inline.
+//! the crate root). Complex shapes (polylines, triangle meshes, heightfields)
+//! reference external vertex data held in [`ShapeBuffers`] rather than storing it
+//! inline.
 
-use crate::math::Point;
+use crate::math::{Point, Pose};
 use crate::parry::shape::{Shape as ParryShape, TypedShape};
 use crate::shaders::PaddedVector;
-use crate::shaders::shapes::Shape;
+use crate::shaders::shapes::{Shape, Triangle};
 
 /// Auxiliary buffers for complex shape types like polylines and triangle meshes.
 ///
@@ -16,17 +17,20 @@ use crate::shaders::shapes::Shape;
 /// than storing inline.
 #[derive(Default, Clone, Debug)]
 pub struct ShapeBuffers {
-    /// Vertex buffer for polylines and triangle meshes. Polyline and TriMesh
-    /// shapes reference ranges within this buffer.
+    /// Vertex buffer for polylines, triangle meshes, and heightfields. Polyline,
+    /// TriMesh and HeightField shapes reference ranges within this buffer.
     pub vertices: Vec<PaddedVector>,
     /// Index buffers for polylines, triangle meshes, and convex polyhedrons.
+    /// Heightfields store their per-cell flags here.
     pub indices: Vec<u32>,
 }
 
 /// Converts a parry shape to a GPU [`Shape`]. For complex shapes like polylines
 /// and triangle meshes, vertex data is appended to `buffers`.
 ///
-/// Returns `None` if the shape type is not yet supported on GPU.
+/// Returns `None` if the shape type is not yet supported on GPU. Compound shapes
+/// aren't a single GPU shape, and tetrahedra need a pose offset to their first
+/// vertex: see [`shape_parts_from_parry`] for both.
 pub fn shape_from_parry(
     shape: &(impl ParryShape + ?Sized),
     buffers: &mut ShapeBuffers,
@@ -39,6 +43,10 @@ pub fn shape_from_parry(
             shape.segment.b,
             shape.radius,
         )),
+        TypedShape::Segment(shape) => Some(Shape::segment(shape.a, shape.b)),
+        TypedShape::Triangle(shape) => Some(Shape::from_triangle(&Triangle::new(
+            shape.a, shape.b, shape.c,
+        ))),
         TypedShape::Polyline(shape) => {
             let bvh_vtx_root_id = buffers.vertices.len();
             let bvh_idx_root_id = buffers.indices.len();
@@ -241,21 +249,175 @@ pub fn shape_from_parry(
             ))
         }
         #[cfg(feature = "dim2")]
-        TypedShape::HeightField(_shape) => {
-            todo!()
+        TypedShape::HeightField(shape) => {
+            use crate::shaders::shapes::HEIGHTFIELD_SEGMENT_REMOVED;
+
+            let first_vtx_id = buffers.vertices.len();
+            let first_cell_id = buffers.indices.len();
+            let heights = shape.heights();
+            let scale = shape.scale();
+            let ncols = shape.num_cells();
+
+            // Grid vertices are stored already scaled so the shaders don't need
+            // to know about the heightfield's scale.
+            buffers.vertices.extend((0..=ncols).map(|j| {
+                let x = (-0.5 + j as f32 / ncols as f32) * scale.x;
+                PaddedVector::new(Point::new(x, heights[j] * scale.y))
+            }));
+            buffers.indices.extend((0..ncols).map(|j| {
+                if shape.is_segment_removed(j) {
+                    HEIGHTFIELD_SEGMENT_REMOVED
+                } else {
+                    0
+                }
+            }));
+
+            let aabb = shape.local_aabb();
+            Some(Shape::heightfield(
+                first_vtx_id as u32,
+                first_cell_id as u32,
+                1,
+                ncols as u32,
+                aabb.mins,
+                aabb.maxs,
+            ))
         }
         #[cfg(feature = "dim3")]
-        TypedShape::HeightField(_shape) => {
-            todo!()
+        TypedShape::HeightField(shape) => {
+            use crate::parry::shape::HeightFieldCellStatus;
+            use crate::shaders::shapes::{
+                HEIGHTFIELD_LEFT_TRIANGLE_REMOVED, HEIGHTFIELD_RIGHT_TRIANGLE_REMOVED,
+                HEIGHTFIELD_ZIGZAG_SUBDIVISION,
+            };
+
+            let first_vtx_id = buffers.vertices.len();
+            let first_cell_id = buffers.indices.len();
+            let heights = shape.heights();
+            let scale = shape.scale();
+            let nrows = shape.nrows();
+            let ncols = shape.ncols();
+
+            // Grid vertices are stored already scaled (row-major, one row per
+            // `z` coordinate) so the shaders don't need to know about the
+            // heightfield's scale.
+            for i in 0..=nrows {
+                for j in 0..=ncols {
+                    let pt = Point::new(shape.x_at(j), heights[(i, j)] * scale.y, shape.z_at(i));
+                    buffers.vertices.push(PaddedVector::new(pt));
+                }
+            }
+
+            for i in 0..nrows {
+                for j in 0..ncols {
+                    let status = shape.cell_status(i, j);
+                    let mut flags = 0;
+                    if status.contains(HeightFieldCellStatus::ZIGZAG_SUBDIVISION) {
+                        flags |= HEIGHTFIELD_ZIGZAG_SUBDIVISION;
+                    }
+                    if status.contains(HeightFieldCellStatus::LEFT_TRIANGLE_REMOVED) {
+                        flags |= HEIGHTFIELD_LEFT_TRIANGLE_REMOVED;
+                    }
+                    if status.contains(HeightFieldCellStatus::RIGHT_TRIANGLE_REMOVED) {
+                        flags |= HEIGHTFIELD_RIGHT_TRIANGLE_REMOVED;
+                    }
+                    buffers.indices.push(flags);
+                }
+            }
+
+            let aabb = shape.local_aabb();
+            Some(Shape::heightfield(
+                first_vtx_id as u32,
+                first_cell_id as u32,
+                nrows as u32,
+                ncols as u32,
+                aabb.mins,
+                aabb.maxs,
+            ))
         }
         #[cfg(feature = "dim3")]
         TypedShape::Cone(shape) => Some(Shape::cone(shape.half_height, shape.radius)),
         #[cfg(feature = "dim3")]
         TypedShape::Cylinder(shape) => Some(Shape::cylinder(shape.half_height, shape.radius)),
+        // Round shapes: the inner shape, dilated by the border radius.
+        TypedShape::RoundCuboid(shape) => Some(
+            shape_from_parry(&shape.inner_shape, buffers)?.with_border_radius(shape.border_radius),
+        ),
+        TypedShape::RoundTriangle(shape) => {
+            let tri = &shape.inner_shape;
+            let tri = Triangle::new(tri.a, tri.b, tri.c);
+            Some(Shape::from_triangle(&tri).with_border_radius(shape.border_radius))
+        }
+        #[cfg(feature = "dim2")]
+        TypedShape::RoundConvexPolygon(shape) => Some(
+            shape_from_parry(&shape.inner_shape, buffers)?.with_border_radius(shape.border_radius),
+        ),
+        #[cfg(feature = "dim3")]
+        TypedShape::RoundConvexPolyhedron(shape) => Some(
+            shape_from_parry(&shape.inner_shape, buffers)?.with_border_radius(shape.border_radius),
+        ),
+        #[cfg(feature = "dim3")]
+        TypedShape::RoundCone(shape) => Some(
+            shape_from_parry(&shape.inner_shape, buffers)?.with_border_radius(shape.border_radius),
+        ),
+        #[cfg(feature = "dim3")]
+        TypedShape::RoundCylinder(shape) => Some(
+            shape_from_parry(&shape.inner_shape, buffers)?.with_border_radius(shape.border_radius),
+        ),
         _ => None,
     }
 }
 
+/// Converts a parry shape to the GPU [`Shape`]s it is made of, each with its pose
+/// relative to the shape's frame.
+///
+/// A compound shape (e.g. from `SharedShape::convex_decomposition`) yields one
+/// part per sub-shape, which becomes a separate GPU collider attached to the same
+/// body. A tetrahedron yields a single part translated to its first vertex (see
+/// [`Shape::tetrahedron`]). Any other shape yields a single part at the identity.
+///
+/// Returns `None` if the shape, or any of its parts, is not yet supported on GPU.
+pub fn shape_parts_from_parry(
+    shape: &(impl ParryShape + ?Sized),
+    buffers: &mut ShapeBuffers,
+) -> Option<Vec<(Pose, Shape)>> {
+    match shape.as_typed_shape() {
+        TypedShape::Compound(compound) => compound
+            .shapes()
+            .iter()
+            .map(|(pose, part)| {
+                let (part_pose, part) = shape_part_from_parry(&**part, buffers)?;
+                Some((*pose * part_pose, part))
+            })
+            .collect(),
+        _ => Some(vec![shape_part_from_parry(shape, buffers)?]),
+    }
+}
+
+/// Converts a non-compound parry shape to a GPU [`Shape`] and its pose relative
+/// to the shape's frame.
+fn shape_part_from_parry(
+    shape: &(impl ParryShape + ?Sized),
+    buffers: &mut ShapeBuffers,
+) -> Option<(Pose, Shape)> {
+    match shape.as_typed_shape() {
+        #[cfg(feature = "dim3")]
+        TypedShape::Tetrahedron(tetra) => Some((
+            Pose::from_translation(tetra.a),
+            Shape::tetrahedron(tetra.b - tetra.a, tetra.c - tetra.a, tetra.d - tetra.a),
+        )),
+        _ => Some((Pose::IDENTITY, shape_from_parry(shape, buffers)?)),
+    }
+}
+
+/// The number of GPU colliders a parry shape is expanded into by
+/// [`shape_parts_from_parry`].
+pub fn num_shape_parts(shape: &(impl ParryShape + ?Sized)) -> usize {
+    match shape.as_typed_shape() {
+        TypedShape::Compound(compound) => compound.shapes().len(),
+        _ => 1,
+    }
+}
+
 /// Convert parry point to bvh Point3 (glam::Vec3)
 fn parry_to_bvh_point(p: Point) -> bvh::Point3 {
     #[cfg(feature = "dim2")]
@@ -0,0 +1,82 @@
+//! Tests for the non-blocking readback of the body poses and velocities.
+
+use crate::math::Vector;
+use crate::pipeline::{RbdCapacities, RbdState};
+use crate::rapier::dynamics::RigidBodyBuilder;
+use crate::rapier::geometry::ColliderBuilder;
+use khal::backend::GpuBackend;
+
+const BODY_CAPACITY: u32 = 4;
+
+/// Two batches of three balls: the `i`-th one at `(i, 0)` moving at `(0, i)`.
+fn balls_state(backend: &GpuBackend) -> RbdState {
+    let mut state = RbdState::empty(
+        backend,
+        RbdCapacities {
+            batches: 2,
+            body_capacity: BODY_CAPACITY,
+            ..Default::default()
+        },
+    );
+    let bodies: Vec<_> = (0..3)
+        .map(|i| {
+            let body = RigidBodyBuilder::dynamic()
+                .translation(Vector::X * i as f32)
+                .linvel(Vector::Y * i as f32)
+                .build();
+            (body, ColliderBuilder::ball(0.25).build())
+        })
+        .collect();
+    state.append_bodies(backend, &bodies).unwrap();
+    state
+}
+
+/// Polls the readback until a snapshot is available.
+fn poll_until_ready(state: &mut RbdState, backend: &GpuBackend) {
+    for _ in 0..16 {
+        if state.poll_body_states(backend).unwrap() {
+            return;
+        }
+    }
+    panic!("the body states readback never completed");
+}
+
+/// Checks that the body at the local slot `local` of every batch is the
+/// `expected`-th ball of [`balls_state`].
+fn assert_body_state(state: &RbdState, local: u32, expected: u32) {
+    for batch in 0..2 {
+        let (pose, vel) = state.body_state(batch * BODY_CAPACITY + local).unwrap();
+        assert_eq!(pose.translation, Vector::X * expected as f32);
+        assert_eq!(vel.linear, Vector::Y * expected as f32);
+    }
+}
+
+#[test]
+fn test_body_states_readback() {
+    let backend = GpuBackend::Cpu;
+    let mut state = balls_state(&backend);
+
+    // Nothing was read back yet.
+    assert!(state.body_state(0).is_none());
+
+    poll_until_ready(&mut state, &backend);
+    for i in 0..3 {
+        assert_body_state(&state, i, i);
+    }
+    assert!(state.body_state(2 * BODY_CAPACITY).is_none());
+}
+
+#[test]
+fn test_body_states_readback_after_removal() {
+    let backend = GpuBackend::Cpu;
+    let mut state = balls_state(&backend);
+    poll_until_ready(&mut state, &backend);
+
+    // The last body is moved to the freed slot: the snapshot is stale.
+    state.remove_bodies(&backend, &[0]).unwrap();
+    assert!(state.body_state(0).is_none());
+
+    poll_until_ready(&mut state, &backend);
+    assert_body_state(&state, 0, 2);
+    assert_body_state(&state, 1, 1);
+}
@@ -0,0 +1,292 @@
+//! Tests for the heightfield packing, cell lookup, point projection and contacts.
+
+use crate::Aabb;
+use crate::math::{Pose, Vector};
+use crate::parry::query::PointQuery;
+use crate::parry::shape::HeightField as ParryHeightField;
+use crate::shaders::broad_phase::{
+    CollisionPair, NarrowPhasePfmPair, gpu_narrow_phase_pfm_pfm,
+    gpu_narrow_phase_shape_shape_deferred,
+};
+use crate::shaders::queries::{ColliderMaterial, IndexedManifold};
+use crate::shaders::shapes::Shape;
+use crate::shaders::utils::BatchIndices;
+use crate::shapes::{ShapeBuffers, shape_from_parry};
+use glamx::{UVec2, UVec3};
+
+/// Packs a parry heightfield with `shape_from_parry`.
+fn gpu_heightfield(hf: &ParryHeightField) -> (Shape, ShapeBuffers) {
+    let mut buffers = ShapeBuffers::default();
+    let shape = shape_from_parry(hf, &mut buffers).expect("heightfields are supported");
+    (shape, buffers)
+}
+
+/// A small heightfield with uneven heights.
+#[cfg(feature = "dim2")]
+fn test_heightfield() -> ParryHeightField {
+    let heights = vec![0.0, 0.5, -0.25, 1.0, 0.75];
+    ParryHeightField::new(heights, Vector::new(8.0, 2.0))
+}
+
+/// A small heightfield with uneven heights, and a few cells split along the
+/// other diagonal or with a removed triangle.
+#[cfg(feature = "dim3")]
+fn test_heightfield() -> ParryHeightField {
+    use crate::parry::shape::HeightFieldCellStatus;
+    use crate::parry::utils::Array2;
+
+    let (nrows, ncols) = (3, 4);
+    let heights = (0..(nrows + 1) * (ncols + 1))
+        .map(|k| ((k * 7) % 5) as f32 * 0.25 - 0.5)
+        .collect();
+    let mut hf = ParryHeightField::new(
+        Array2::new(nrows + 1, ncols + 1, heights),
+        Vector::new(8.0, 2.0, 6.0),
+    );
+    hf.set_cell_status(1, 2, HeightFieldCellStatus::ZIGZAG_SUBDIVISION);
+    hf.set_cell_status(0, 1, HeightFieldCellStatus::LEFT_TRIANGLE_REMOVED);
+    hf.set_cell_status(
+        2,
+        3,
+        HeightFieldCellStatus::ZIGZAG_SUBDIVISION | HeightFieldCellStatus::RIGHT_TRIANGLE_REMOVED,
+    );
+    hf
+}
+
+fn assert_close(a: Vector, b: Vector) {
+    assert!((a - b).length() < 1.0e-4, "expected {b:?}, got {a:?}");
+}
+
+#[cfg(feature = "dim2")]
+#[test]
+fn test_heightfield_segments_match_parry() {
+    let hf = test_heightfield();
+    let (shape, buffers) = gpu_heightfield(&hf);
+    let shape = shape.to_heightfield();
+
+    for j in 0..hf.num_cells() {
+        let (seg, valid) = shape.segment(&buffers.vertices, &buffers.indices, j as u32);
+        let expected = hf.segment_at(j).expect("no segment is removed");
+        assert!(valid);
+        assert_close(seg.a, expected.a);
+        assert_close(seg.b, expected.b);
+    }
+}
+
+#[cfg(feature = "dim3")]
+#[test]
+fn test_heightfield_triangles_match_parry() {
+    let hf = test_heightfield();
+    let (shape, buffers) = gpu_heightfield(&hf);
+    let shape = shape.to_heightfield();
+
+    for i in 0..hf.nrows() {
+        for j in 0..hf.ncols() {
+            let expected = hf.triangles_at(i, j);
+            for (right, expected) in [(false, expected.0), (true, expected.1)] {
+                let (tri, valid) = shape.triangle(
+                    &buffers.vertices,
+                    &buffers.indices,
+                    i as u32,
+                    j as u32,
+                    right,
+                );
+                assert_eq!(valid, expected.is_some(), "cell ({i}, {j}), right: {right}");
+                if let Some(expected) = expected {
+                    assert_close(tri.a, expected.a);
+                    assert_close(tri.b, expected.b);
+                    assert_close(tri.c, expected.c);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_heightfield_cell_range() {
+    let hf = test_heightfield();
+    let (shape, _) = gpu_heightfield(&hf);
+    let shape = shape.to_heightfield();
+
+    // Cells are 2 wide along `x` (and `z` in 3D), starting at -4 (and -3).
+    let aabb = Aabb::new(Vector::splat(-1.5), Vector::splat(0.5));
+    let (mins, maxs) = shape.cell_range(&aabb);
+    assert_eq!((mins.x, maxs.x), (1, 3));
+    #[cfg(feature = "dim2")]
+    assert_eq!((mins.y, maxs.y), (0, 1));
+    #[cfg(feature = "dim3")]
+    assert_eq!((mins.y, maxs.y), (0, 2));
+
+    // Ranges overflowing the grid are clamped to it.
+    let aabb = Aabb::new(Vector::splat(-100.0), Vector::splat(100.0));
+    let (mins, maxs) = shape.cell_range(&aabb);
+    assert_eq!((mins.x, maxs.x), (0, 4));
+    #[cfg(feature = "dim3")]
+    assert_eq!((mins.y, maxs.y), (0, 3));
+}
+
+#[test]
+fn test_heightfield_project_local_point_matches_parry() {
+    let hf = test_heightfield();
+    let (shape, buffers) = gpu_heightfield(&hf);
+    let shape = shape.to_heightfield();
+    let (vertices, indices) = (&buffers.vertices, &buffers.indices);
+
+    #[cfg(feature = "dim2")]
+    let points = [
+        Vector::new(-3.5, 4.0),
+        Vector::new(0.3, -2.0),
+        Vector::new(2.9, 0.1),
+        Vector::new(6.0, 1.0),
+    ];
+    #[cfg(feature = "dim3")]
+    let points = [
+        Vector::new(-3.5, 4.0, -2.5),
+        Vector::new(0.3, -2.0, 0.7),
+        Vector::new(2.9, 0.1, 2.2),
+        Vector::new(6.0, 1.0, -5.0),
+        // Above the removed triangle of the cell (0, 1).
+        Vector::new(-1.8, 3.0, -2.8),
+    ];
+
+    for pt in points {
+        let (proj, found) = shape.project_local_point(vertices, indices, pt);
+        assert!(found);
+        let expected = hf.project_local_point(pt, false);
+        assert_close(proj.point, expected.point);
+    }
+
+    // Points are inside if they lie below the closest feature.
+    let (above, _) = shape.project_local_point(vertices, indices, points[0]);
+    assert!(!above.is_inside);
+    let (below, _) = shape.project_local_point(vertices, indices, points[1]);
+    assert!(below.is_inside);
+}
+
+/// Runs the deferred narrow phase, then the PFM-PFM pass, between a heightfield
+/// at the origin and a ball of radius 0.5 centered at `center`.
+fn heightfield_ball_contacts(hf: &ParryHeightField, center: Vector) -> Vec<IndexedManifold> {
+    let capacity = 16;
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: 2,
+        collision_pairs_batch_capacity: capacity,
+        contacts_batch_capacity: capacity,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(2, 2, 0);
+    let (hf_shape, buffers) = gpu_heightfield(hf);
+    let shapes = [hf_shape, Shape::ball(0.5)];
+    let poses = [Pose::IDENTITY, Pose::from_translation(center)];
+    let pairs = [CollisionPair {
+        colliders: UVec2::new(0, 1),
+    }];
+
+    let mut pfm_pairs = vec![NarrowPhasePfmPair::default(); capacity as usize];
+    let mut pfm_pairs_len = [0];
+    gpu_narrow_phase_shape_shape_deferred(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &pairs,
+        &[1],
+        &poses,
+        &shapes,
+        &mut pfm_pairs,
+        &mut pfm_pairs_len,
+        &buffers.vertices,
+        &buffers.indices,
+        &batch_ids,
+    );
+
+    let mut contacts = vec![IndexedManifold::default(); capacity as usize];
+    let mut contacts_len = [0];
+    gpu_narrow_phase_pfm_pfm(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &mut contacts,
+        &mut contacts_len,
+        &pfm_pairs,
+        &pfm_pairs_len,
+        &batch_ids,
+        &buffers.vertices,
+        &buffers.indices,
+        &[0, 1],
+        &[ColliderMaterial::default(); 2],
+    );
+    contacts.truncate(contacts_len[0] as usize);
+    contacts
+}
+
+/// Asserts that every contact touches the heightfield at `pt`, along `normal`,
+/// 0.01 away from the ball.
+fn assert_contacts(contacts: &[IndexedManifold], pt: Vector, normal: Vector) {
+    assert!(!contacts.is_empty());
+    for manifold in contacts {
+        assert_eq!(manifold.colliders, UVec2::new(0, 1));
+        let contact = &manifold.contact;
+        assert!(contact.len > 0);
+        assert_close(contact.normal_a, normal);
+        for point in &contact.points_a[..contact.len as usize] {
+            assert!(
+                (point.dist - 0.01).abs() < 1.0e-3,
+                "unexpected distance {}",
+                point.dist
+            );
+            assert!(
+                (point.pt - pt).length() < 1.0e-3,
+                "unexpected point {:?}",
+                point.pt
+            );
+        }
+    }
+}
+
+#[cfg(feature = "dim2")]
+#[test]
+fn test_ball_on_heightfield_contacts() {
+    // Flat on `[-3, -1]`, a 45° slope up to `(1, 2)`, then flat again.
+    let hf = ParryHeightField::new(vec![0.0, 0.0, 2.0, 2.0], Vector::new(6.0, 1.0));
+
+    // Resting 0.01 above the first flat cell.
+    let contacts = heightfield_ball_contacts(&hf, Vector::new(-2.0, 0.51));
+    assert_eq!(contacts.len(), 1);
+    assert_contacts(&contacts, Vector::new(-2.0, 0.0), Vector::Y);
+
+    // Resting 0.01 above the middle of the slope.
+    let normal = Vector::new(-1.0, 1.0).normalize();
+    let pt = Vector::new(0.0, 1.0);
+    let contacts = heightfield_ball_contacts(&hf, pt + normal * 0.51);
+    assert_eq!(contacts.len(), 1);
+    assert_contacts(&contacts, pt, normal);
+
+    // Far above the terrain.
+    assert!(heightfield_ball_contacts(&hf, Vector::new(0.0, 5.0)).is_empty());
+}
+
+#[cfg(feature = "dim3")]
+#[test]
+fn test_ball_on_heightfield_contacts() {
+    use crate::parry::utils::Array2;
+
+    // Along `x`: flat on `[-2, 0]`, then a 45° slope up to `x = 2`.
+    let mut heights = Array2::new(3, 3, vec![0.0; 9]);
+    for i in 0..3 {
+        heights[(i, 2)] = 2.0;
+    }
+    let hf = ParryHeightField::new(heights, Vector::new(4.0, 1.0, 4.0));
+
+    // Resting 0.01 above a flat triangle, far enough from the cell diagonals for
+    // the other triangle to stay out of reach.
+    let contacts = heightfield_ball_contacts(&hf, Vector::new(-1.5, 0.51, -0.8));
+    assert_eq!(contacts.len(), 1);
+    assert_contacts(&contacts, Vector::new(-1.5, 0.0, -0.8), Vector::Y);
+
+    // Resting 0.01 above the slope. Both triangles of a cell lie on it.
+    let normal = Vector::new(-1.0, 1.0, 0.0).normalize();
+    let pt = Vector::new(1.0, 1.0, 1.0);
+    let contacts = heightfield_ball_contacts(&hf, pt + normal * 0.51);
+    assert_contacts(&contacts, pt, normal);
+
+    // Far above the terrain.
+    assert!(heightfield_ball_contacts(&hf, Vector::new(0.0, 5.0, 0.0)).is_empty());
+}
@@ -0,0 +1,11 @@
+//! Tests for the host-side shape conversions and pipeline bookkeeping.
+//!
+//! These tests run on the CPU. Shader functions are called directly, and the
+//! pipeline runs on the `cpu` backend.
+
+#[cfg(feature = "cpu")]
+mod body_readback;
+mod heightfield;
+#[cfg(all(feature = "dim2", feature = "cpu"))]
+mod multibody_pendulum;
+mod scene_queries;
@@ -0,0 +1,81 @@
+//! Simulates a 2D multibody pendulum with the CPU backend.
+
+use crate::dynamics::RbdSimParams;
+use crate::math::{Pose, Vector};
+use crate::pipeline::{RbdCapacities, RbdPipeline, RbdState};
+use crate::rapier::dynamics::{
+    ImpulseJointSet, MultibodyJointSet, RevoluteJointBuilder, RigidBodyBuilder, RigidBodySet,
+};
+use crate::rapier::geometry::{ColliderBuilder, ColliderSet};
+use khal::backend::{Backend, GpuBackend};
+
+const LINK_LENGTH: f32 = 1.0;
+
+#[futures_test::test]
+async fn test_multibody_pendulum_2d() {
+    let backend = GpuBackend::Cpu;
+    let mut bodies = RigidBodySet::new();
+    let mut colliders = ColliderSet::new();
+    let mut multibody_joints = MultibodyJointSet::new();
+
+    // A fixed root at the origin, and a link starting horizontally.
+    let root = bodies.insert(RigidBodyBuilder::fixed().build());
+    colliders.insert_with_parent(ColliderBuilder::ball(0.1).build(), root, &mut bodies);
+    let link = bodies.insert(
+        RigidBodyBuilder::dynamic()
+            .translation(Vector::X * LINK_LENGTH)
+            .build(),
+    );
+    colliders.insert_with_parent(ColliderBuilder::ball(0.1).build(), link, &mut bodies);
+    let joint = RevoluteJointBuilder::new().local_anchor2(-Vector::X * LINK_LENGTH);
+    multibody_joints
+        .insert(root, link, joint, true)
+        .expect("the pendulum is a valid multibody");
+
+    let params = RbdSimParams::default();
+    let mut state = RbdState::from_rapier(
+        &backend,
+        &[(
+            &bodies,
+            &colliders,
+            &ImpulseJointSet::new(),
+            &multibody_joints,
+            &params,
+        )],
+        RbdCapacities::default(),
+    );
+    let pipeline = RbdPipeline::new(&backend).unwrap();
+
+    let mut prev_y = 0.0;
+    for _ in 0..2 {
+        for _ in 0..10 {
+            pipeline.step(&backend, &mut state, None).unwrap();
+        }
+
+        // Bodies get their slots in collider order: the link is the second one.
+        let poses: Vec<Pose> = backend
+            .slow_read_vec(state.body_poses().buffer())
+            .await
+            .unwrap();
+        let link_pos = poses[1].translation;
+
+        // The root doesn't move, and the link swings down around it without
+        // stretching the joint.
+        assert_eq!(poses[0].translation, Vector::ZERO);
+        assert!(
+            (link_pos.length() - LINK_LENGTH).abs() < 1.0e-2,
+            "link at {link_pos:?}"
+        );
+        assert!(link_pos.y < prev_y, "link at {link_pos:?}");
+        prev_y = link_pos.y;
+    }
+
+    // After 20 steps (a third of a second), the link is still swinging down
+    // towards the vertical.
+    let vels: Vec<crate::Velocity> = backend
+        .slow_read_vec(state.body_velocities().buffer())
+        .await
+        .unwrap();
+    assert!(vels[1].linear.x < 0.0);
+    assert!(vels[1].linear.y < 0.0);
+}
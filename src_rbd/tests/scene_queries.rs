@@ -0,0 +1,378 @@
+//! Tests for the scene-query kernels, traversing hand-built LBVHs.
+
+use crate::math::{Pose, Vector};
+use crate::parry::shape::{Ball, Cuboid, HeightField as ParryHeightField, Shape as ParryShape};
+use crate::rapier::geometry::{Group, InteractionGroups, InteractionTestMode};
+use crate::shaders::broad_phase::LbvhNode;
+use crate::shaders::queries::{
+    COLLIDER_FLAG_SENSOR, ColliderMaterial, PointQuery, RayQuery, SCENE_QUERY_INCLUDE_SENSORS,
+    SceneQueryCollider, SceneQueryFilter, SceneQueryHit, ShapeCastQuery, gpu_scene_cast_rays,
+    gpu_scene_cast_shapes, gpu_scene_project_points, gpu_scene_query_colliders,
+};
+use crate::shaders::shapes::Shape;
+use crate::shaders::utils::BatchIndices;
+use crate::shapes::{ShapeBuffers, shape_from_parry};
+use glamx::UVec3;
+
+/// The colliders of a single batch.
+#[derive(Default)]
+struct Scene {
+    shapes: Vec<Shape>,
+    poses: Vec<Pose>,
+    groups: Vec<InteractionGroups>,
+    materials: Vec<ColliderMaterial>,
+    buffers: ShapeBuffers,
+}
+
+impl Scene {
+    fn push(&mut self, shape: &dyn ParryShape, translation: Vector) -> usize {
+        let shape = shape_from_parry(shape, &mut self.buffers).expect("unsupported shape");
+        self.shapes.push(shape);
+        self.poses.push(Pose::from_translation(translation));
+        self.groups.push(InteractionGroups::all());
+        self.materials.push(ColliderMaterial::default());
+        self.shapes.len() - 1
+    }
+
+    fn batch_ids(&self) -> BatchIndices {
+        let n = self.shapes.len() as u32;
+        let mut batch_ids = BatchIndices {
+            num_batches: 1,
+            colliders_batch_capacity: n,
+            ..Default::default()
+        };
+        batch_ids.set_uniform_ranges(n, n, 0);
+        batch_ids
+    }
+
+    /// Builds the LBVH of the scene as a chain: the internal node `i` has the
+    /// leaf of the collider `i` on its left, and the internal node `i + 1` on its
+    /// right. This is the deepest possible tree.
+    fn chain_tree(&self) -> Vec<LbvhNode> {
+        let n = self.shapes.len();
+        let first_leaf = n - 1;
+        let mut tree = vec![LbvhNode::default(); 2 * n];
+
+        for k in 0..n {
+            let leaf = &mut tree[first_leaf + k];
+            leaf.aabb = self.shapes[k].compute_aabb(self.poses[k], &self.buffers.vertices);
+            leaf.left = k as u32;
+        }
+
+        for i in (0..first_leaf).rev() {
+            let left = first_leaf + i;
+            let right = if i + 1 < first_leaf { i + 1 } else { 2 * n - 2 };
+            tree[i].left = left as u32;
+            tree[i].right = right as u32;
+            tree[i].aabb = tree[left].aabb.merged(&tree[right].aabb);
+            tree[left].parent = i as u32;
+            tree[right].parent = i as u32;
+        }
+
+        tree
+    }
+
+    /// Gathers the per-collider filtering data with `gpu_scene_query_colliders`.
+    fn query_colliders(&self) -> Vec<SceneQueryCollider> {
+        let mut colliders = vec![SceneQueryCollider::default(); self.shapes.len()];
+        gpu_scene_query_colliders(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &self.groups,
+            &self.materials,
+            &mut colliders,
+            &self.batch_ids(),
+        );
+        colliders
+    }
+
+    fn cast_rays(&self, rays: &[RayQuery]) -> Vec<SceneQueryHit> {
+        let mut hits = vec![SceneQueryHit::default(); rays.len()];
+        gpu_scene_cast_rays(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &self.chain_tree(),
+            &self.poses,
+            &self.shapes,
+            rays,
+            &mut hits,
+            &self.query_colliders(),
+            &self.batch_ids(),
+            &self.buffers.vertices,
+            &self.buffers.indices,
+        );
+        hits
+    }
+
+    fn cast_shapes(&self, casts: &[ShapeCastQuery]) -> Vec<SceneQueryHit> {
+        let mut hits = vec![SceneQueryHit::default(); casts.len()];
+        gpu_scene_cast_shapes(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &self.chain_tree(),
+            &self.poses,
+            &self.shapes,
+            casts,
+            &mut hits,
+            &self.query_colliders(),
+            &self.batch_ids(),
+            &self.buffers.vertices,
+            &self.buffers.indices,
+        );
+        hits
+    }
+
+    fn project_points(&self, points: &[PointQuery]) -> Vec<SceneQueryHit> {
+        let mut hits = vec![SceneQueryHit::default(); points.len()];
+        gpu_scene_project_points(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &self.chain_tree(),
+            &self.poses,
+            &self.shapes,
+            points,
+            &mut hits,
+            &self.query_colliders(),
+            &self.batch_ids(),
+            &self.buffers.vertices,
+            &self.buffers.indices,
+        );
+        hits
+    }
+}
+
+fn ray(origin: Vector, dir: Vector, max_toi: f32) -> RayQuery {
+    RayQuery {
+        origin,
+        dir,
+        max_toi,
+        ..Default::default()
+    }
+}
+
+fn point(point: Vector, max_dist: f32) -> PointQuery {
+    PointQuery {
+        point,
+        max_dist,
+        ..Default::default()
+    }
+}
+
+fn ball_cast(radius: f32, center: Vector, dir: Vector, max_toi: f32) -> ShapeCastQuery {
+    ShapeCastQuery {
+        shape: Shape::ball(radius),
+        pose: Pose::from_translation(center),
+        dir,
+        max_toi,
+        ..Default::default()
+    }
+}
+
+fn assert_close(a: Vector, b: Vector) {
+    assert!((a - b).length() < 1.0e-3, "expected {b:?}, got {a:?}");
+}
+
+/// Asserts that `hit` touched `collider` at `toi`.
+fn assert_hit(hit: &SceneQueryHit, collider: usize, toi: f32) {
+    assert_eq!(hit.collider, collider as u32);
+    assert!(
+        (hit.toi - toi).abs() < 1.0e-3,
+        "expected toi {toi}, got {}",
+        hit.toi
+    );
+}
+
+/// A unit ball at the origin, and a cuboid with half-extents 1 centered at `5 * X`.
+fn ball_and_cuboid() -> Scene {
+    let mut scene = Scene::default();
+    scene.push(&Ball::new(1.0), Vector::ZERO);
+    scene.push(&Cuboid::new(Vector::ONE), Vector::X * 5.0);
+    scene
+}
+
+#[test]
+fn test_scene_cast_rays() {
+    let scene = ball_and_cuboid();
+    let hits = scene.cast_rays(&[
+        // Hits the ball.
+        ray(-Vector::X * 3.0, Vector::X, 100.0),
+        // Starts between the ball and the cuboid, hits the cuboid.
+        ray(Vector::X * 2.5, Vector::X, 100.0),
+        // Passes above both colliders.
+        ray(Vector::Y * 3.0, Vector::X, 100.0),
+        // Stops short of the ball.
+        ray(-Vector::X * 3.0, Vector::X, 1.0),
+        // Starts inside of the ball, and inside of the cuboid.
+        ray(Vector::X * 0.5, Vector::Y, 100.0),
+        ray(Vector::X * 5.0 + Vector::Y * 0.5, Vector::X, 100.0),
+    ]);
+
+    assert_hit(&hits[0], 0, 2.0);
+    assert_close(hits[0].point, -Vector::X);
+    assert_close(hits[0].normal, -Vector::X);
+    assert_hit(&hits[1], 1, 1.5);
+    assert_close(hits[1].point, Vector::X * 4.0);
+    assert_close(hits[1].normal, -Vector::X);
+    assert!(!hits[2].is_hit());
+    assert!(!hits[3].is_hit());
+    assert_hit(&hits[4], 0, 0.0);
+    assert_hit(&hits[5], 1, 0.0);
+}
+
+#[test]
+fn test_scene_cast_shapes() {
+    let scene = ball_and_cuboid();
+    let hits = scene.cast_shapes(&[
+        ball_cast(0.5, -Vector::X * 3.0, Vector::X, 100.0),
+        // Moving above both colliders.
+        ball_cast(0.5, -Vector::X * 3.0 + Vector::Y * 2.0, Vector::X, 100.0),
+        // Too slow to reach the ball.
+        ball_cast(0.5, -Vector::X * 3.0, Vector::X * 0.1, 10.0),
+    ]);
+
+    assert_hit(&hits[0], 0, 1.5);
+    assert_close(hits[0].point, -Vector::X);
+    assert_close(hits[0].normal, -Vector::X);
+    assert!(!hits[1].is_hit());
+    assert!(!hits[2].is_hit());
+}
+
+#[test]
+fn test_scene_project_points() {
+    let scene = ball_and_cuboid();
+    let hits = scene.project_points(&[
+        // Closer to the ball than to the cuboid.
+        point(Vector::X * 2.0, 100.0),
+        // Inside of the cuboid, closer to its top face.
+        point(Vector::X * 5.0 + Vector::Y * 0.75, 100.0),
+        // Too far from both colliders.
+        point(Vector::Y * 5.0, 1.0),
+    ]);
+
+    assert_hit(&hits[0], 0, 1.0);
+    assert_close(hits[0].point, Vector::X);
+    assert_close(hits[0].normal, Vector::X);
+    assert_hit(&hits[1], 1, -0.25);
+    assert_close(hits[1].point, Vector::X * 5.0 + Vector::Y);
+    assert!(!hits[2].is_hit());
+}
+
+#[test]
+fn test_scene_query_filters() {
+    // A sensor ball at the origin, and a ball in the second group at `5 * X`.
+    let mut scene = Scene::default();
+    scene.push(&Ball::new(1.0), Vector::ZERO);
+    scene.push(&Ball::new(1.0), Vector::X * 5.0);
+    scene.materials[0].flags = COLLIDER_FLAG_SENSOR;
+    scene.groups[1] = InteractionGroups::new(Group::GROUP_2, Group::ALL, InteractionTestMode::And);
+
+    let with_sensors = SceneQueryFilter {
+        flags: SCENE_QUERY_INCLUDE_SENSORS,
+        ..Default::default()
+    };
+    let group_1 = SceneQueryFilter {
+        groups: InteractionGroups::new(Group::ALL, Group::GROUP_1, InteractionTestMode::And),
+        flags: SCENE_QUERY_INCLUDE_SENSORS,
+    };
+    let filtered_ray = |filter| RayQuery {
+        filter,
+        ..ray(-Vector::X * 3.0, Vector::X, 100.0)
+    };
+    let hits = scene.cast_rays(&[
+        ray(-Vector::X * 3.0, Vector::X, 100.0),
+        filtered_ray(with_sensors),
+        filtered_ray(group_1),
+        filtered_ray(SceneQueryFilter {
+            groups: InteractionGroups::none(),
+            flags: SCENE_QUERY_INCLUDE_SENSORS,
+        }),
+    ]);
+
+    // The sensor is skipped by default.
+    assert_hit(&hits[0], 1, 7.0);
+    assert_hit(&hits[1], 0, 2.0);
+    assert_hit(&hits[2], 0, 2.0);
+    assert!(!hits[3].is_hit());
+
+    // The second ball isn't in the first group.
+    let hits = scene.project_points(&[
+        point(Vector::X * 4.0, 100.0),
+        PointQuery {
+            filter: group_1,
+            ..point(Vector::X * 4.0, 100.0)
+        },
+    ]);
+    assert_hit(&hits[0], 1, 0.0);
+    assert_hit(&hits[1], 0, 3.0);
+}
+
+#[test]
+fn test_scene_queries_on_deep_tree() {
+    // 100 balls along the `x` axis: the last one is 99 levels deep in the tree,
+    // much deeper than a fixed-size traversal stack would allow.
+    let mut scene = Scene::default();
+    for k in 0..100 {
+        scene.push(&Ball::new(1.0), Vector::X * (3 * k) as f32);
+    }
+
+    let hits = scene.cast_rays(&[
+        ray(-Vector::X * 3.0, Vector::X, 1000.0),
+        ray(Vector::X * 400.0, -Vector::X, 1000.0),
+        ray(Vector::X * 297.0 + Vector::Y * 5.0, -Vector::Y, 1000.0),
+        ray(Vector::X * 241.5 + Vector::Y * 5.0, -Vector::Y, 1000.0),
+    ]);
+    assert_hit(&hits[0], 0, 2.0);
+    assert_hit(&hits[1], 99, 102.0);
+    assert_hit(&hits[2], 99, 4.0);
+    // Passes between two balls.
+    assert!(!hits[3].is_hit());
+
+    let hits = scene.project_points(&[point(Vector::X * 240.0 + Vector::Y * 2.0, 100.0)]);
+    assert_hit(&hits[0], 80, 1.0);
+}
+
+/// A flat heightfield at `y = 0`, spanning `[-20, 20]` along `x`.
+#[cfg(feature = "dim2")]
+fn flat_heightfield() -> ParryHeightField {
+    ParryHeightField::new(vec![0.0; 5], Vector::new(40.0, 1.0))
+}
+
+/// A flat heightfield at `y = 0`, spanning `[-20, 20]` along `x` and `z`.
+#[cfg(feature = "dim3")]
+fn flat_heightfield() -> ParryHeightField {
+    use crate::parry::utils::Array2;
+    ParryHeightField::new(
+        Array2::new(4, 4, vec![0.0; 16]),
+        Vector::new(40.0, 1.0, 40.0),
+    )
+}
+
+#[test]
+fn test_scene_queries_on_heightfield() {
+    let mut scene = Scene::default();
+    scene.push(&flat_heightfield(), Vector::ZERO);
+
+    // The ball’s center never reaches the heightfield’s AABB, but the ball does.
+    let vel = Vector::X * 10.0 - Vector::Y;
+    let hits = scene.cast_shapes(&[
+        ball_cast(1.0, Vector::Y * 1.5, vel, 1.0),
+        ball_cast(1.0, Vector::Y * 1.5, vel, 0.4),
+    ]);
+    assert_hit(&hits[0], 0, 0.5);
+    assert_close(hits[0].point, Vector::X * 5.0);
+    assert_close(hits[0].normal, Vector::Y);
+    assert!(!hits[1].is_hit());
+
+    let hits = scene.cast_rays(&[
+        ray(Vector::X * 3.0 + Vector::Y * 5.0, -Vector::Y, 100.0),
+        // Outside of the heightfield’s domain.
+        ray(Vector::X * 30.0 + Vector::Y * 5.0, -Vector::Y, 100.0),
+    ]);
+    assert_hit(&hits[0], 0, 5.0);
+    assert_close(hits[0].point, Vector::X * 3.0);
+    assert!(!hits[1].is_hit());
+
+    let hits = scene.project_points(&[point(Vector::X * 3.0 + Vector::Y * 2.0, 100.0)]);
+    assert_hit(&hits[0], 0, 2.0);
+    assert_close(hits[0].point, Vector::X * 3.0);
+}
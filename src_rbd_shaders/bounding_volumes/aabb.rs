@@ -229,4 +229,30 @@ impl Aabb {
             padding1: 0,
         }
     }
+
+    /// Computes the distance between this AABB and a point (zero if the point is inside).
+    #[inline]
+    pub fn distance_to_point(&self, pt: Vector) -> f32 {
+        (self.mins - pt)
+            .max(pt - self.maxs)
+            .max(Vector::ZERO)
+            .length()
+    }
+
+    /// Casts a ray on this AABB with the slab method.
+    ///
+    /// Returns the time of impact in `[0, max_toi]`, or `MAX_FLT` if the ray
+    /// misses. A ray starting inside of the AABB has a time of impact of zero.
+    #[inline]
+    pub fn cast_ray(&self, origin: Vector, dir: Vector, max_toi: f32) -> f32 {
+        // Avoid divisions by zero for axis-aligned rays.
+        let safe_dir = dir.signum() * dir.abs().max(Vector::splat(crate::F32_EPSILON));
+        let inv_dir = Vector::ONE / safe_dir;
+        let t1 = (self.mins - origin) * inv_dir;
+        let t2 = (self.maxs - origin) * inv_dir;
+        let tmin = t1.min(t2).max_element().max(0.0);
+        let tmax = t1.max(t2).min_element().min(max_toi);
+
+        if tmin <= tmax { tmin } else { MAX_FLT }
+    }
 }
@@ -10,6 +10,7 @@ use khal_std::sync::atomic_add_u32;
 
 use crate::bounding_volumes::Aabb;
 use crate::broad_phase::CollisionPair;
+use crate::dynamics::CcdSweep;
 use crate::shapes::Shape;
 use crate::utils::BatchIndices;
 use crate::{PaddedVector, Pose, Vector};
@@ -29,12 +30,15 @@ pub fn gpu_bf_compute_aabbs(
     #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
     #[spirv(storage_buffer, descriptor_set = 1, binding = 0)] vertices: &[PaddedVector],
 ) {
-    let n = batch_ids.colliders_len;
-    if invocation_id.x >= n * batch_ids.num_batches {
+    let cap = batch_ids.colliders_batch_capacity;
+    if invocation_id.x >= cap * batch_ids.num_batches {
+        return;
+    }
+    let batch_id = invocation_id.x / cap;
+    let i = invocation_id.x % cap;
+    if i >= batch_ids.colliders_len(batch_id) {
         return;
     }
-    let batch_id = invocation_id.x / n;
-    let i = invocation_id.x % n;
 
     let poses = batch_ids.coll_batch(batch_id, poses);
     let shapes = batch_ids.coll_batch(batch_id, shapes);
@@ -45,6 +49,34 @@ pub fn gpu_bf_compute_aabbs(
     );
 }
 
+/// Enlarges the AABBs of the colliders of CCD bodies by their motion over the
+/// step (see [`CcdSweep::sweep_aabb`]).
+///
+/// Run after [`gpu_bf_compute_aabbs`], only if any body has CCD enabled.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_bf_sweep_aabbs(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] sweeps: &[CcdSweep],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] aabbs: &mut [Aabb],
+    #[spirv(uniform, descriptor_set = 0, binding = 2)] batch_ids: &BatchIndices,
+) {
+    let cap = batch_ids.colliders_batch_capacity;
+    if invocation_id.x >= cap * batch_ids.num_batches {
+        return;
+    }
+    let batch_id = invocation_id.x / cap;
+    let i = invocation_id.x % cap;
+    if i >= batch_ids.colliders_len(batch_id) {
+        return;
+    }
+
+    let sweeps = batch_ids.coll_batch(batch_id, sweeps);
+    let out = batch_ids.coll_start(batch_id) + i as usize;
+    let aabb = aabbs.read(out);
+    aabbs.write(out, sweeps[i as usize].sweep_aabb(&aabb));
+}
+
 /// Tests every collider pair of every batch and appends the intersecting,
 /// unfiltered ones to `collision_pairs`.
 #[spirv_bindgen]
@@ -60,16 +92,16 @@ pub fn gpu_bf_find_pairs(
     #[spirv(uniform, descriptor_set = 0, binding = 4)] batch_ids: &BatchIndices,
     #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] pair_filter: &[[u32; 2]],
 ) {
-    let n = batch_ids.colliders_len;
-    let nn = n * n;
-    if invocation_id.x >= nn * batch_ids.num_batches {
+    let cap = batch_ids.colliders_batch_capacity;
+    let cap2 = cap * cap;
+    if invocation_id.x >= cap2 * batch_ids.num_batches {
         return;
     }
-    let batch_id = invocation_id.x / nn;
-    let r = invocation_id.x % nn;
-    let i = r / n;
-    let j = r % n;
-    if i >= j {
+    let batch_id = invocation_id.x / cap2;
+    let r = invocation_id.x % cap2;
+    let i = r / cap;
+    let j = r % cap;
+    if i >= j || j >= batch_ids.colliders_len(batch_id) {
         return;
     }
 
@@ -0,0 +1,364 @@
+//! Collision-event and contact-force-event kernels.
+//!
+//! The collider pairs tracked for collision events (the intersecting sensor pairs,
+//! and the touching pairs involving a collider with
+//! [`COLLIDER_FLAG_COLLISION_EVENTS`](crate::queries::COLLIDER_FLAG_COLLISION_EVENTS))
+//! are compared with the previous step's to record collision-started/stopped
+//! events. The contact-force events are derived from the solved contact
+//! constraints.
+//!
+//! The parts of a compound collider occupy consecutive collider slots. Their
+//! pairs and forces are reported under the first part's slot (see
+//! [`ColliderMaterial::part_id`]), so a compound touching another collider emits
+//! a single event whatever the number of touching parts.
+//!
+//! The events of each batch go to ring buffers of `events_batch_capacity` entries
+//! indexed by ever-growing counters, so the host can drain them without ever
+//! resetting the counters.
+
+use crate::dynamics::{RbdSimParams, TwoBodyConstraint};
+use crate::queries::{ColliderMaterial, IndexedManifold};
+use crate::utils::{BatchIndices, Slice};
+use crate::{PaddedVector, Vector};
+use glamx::UVec2;
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::macros::{spirv, spirv_bindgen};
+use khal_std::{iter::StepRng, sync::atomic_add_u32};
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// [`CollisionEvent::kind`] of a pair that stopped colliding.
+pub const COLLISION_EVENT_STOPPED: u32 = 0;
+/// [`CollisionEvent::kind`] of a pair that started colliding.
+pub const COLLISION_EVENT_STARTED: u32 = 1;
+
+/// [`CollisionEvent::flags`] bit set if at least one of the colliders is a sensor.
+pub const COLLISION_EVENT_FLAG_SENSOR: u32 = 1;
+
+/// A change of contact or intersection state between two colliders.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct CollisionEvent {
+    /// Batch-local indices of the two colliders.
+    pub colliders: UVec2,
+    /// Either [`COLLISION_EVENT_STARTED`] or [`COLLISION_EVENT_STOPPED`].
+    pub kind: u32,
+    /// Bitwise combination of the `COLLISION_EVENT_FLAG_*` constants.
+    pub flags: u32,
+}
+
+/// The contact forces between two colliders during a step, mirroring rapier's
+/// `ContactForceEvent`.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct ContactForceEvent {
+    /// Sum of the contact forces applied by the first collider on the second one.
+    pub total_force: PaddedVector,
+    /// Normal direction of the strongest contact-point force.
+    pub max_force_direction: PaddedVector,
+    /// Batch-local indices of the two colliders.
+    pub colliders: UVec2,
+    /// Sum of the magnitudes of the contact-point forces.
+    pub total_force_magnitude: f32,
+    /// Magnitude of the strongest contact-point force.
+    pub max_force_magnitude: f32,
+}
+
+/// Resets the touching-pair counters. One thread per batch.
+///
+/// The event counters are never reset: they index ring buffers and only ever grow.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_reset_touching_pairs(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] touching_pairs_len: &mut [u32],
+) {
+    let batch_id = invocation_id.x as usize;
+    if batch_id < touching_pairs_len.len() {
+        touching_pairs_len.write(batch_id, 0);
+    }
+}
+
+/// Collects the collider pairs of the contact manifolds involving a collider with
+/// collision events.
+///
+/// A pair may generate several manifolds (e.g. one per triangle of a mesh), so it
+/// may appear several times in `touching_pairs`. So may a compound touching another
+/// collider with several of its parts: the parts are only merged by
+/// [`gpu_emit_collision_events`].
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_touching_pairs(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] contacts: &[IndexedManifold],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] contacts_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)]
+    collider_materials: &[ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] touching_pairs: &mut [UVec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] touching_pairs_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let event_pairs_batch_capacity = batch_ids.event_pairs_batch_capacity as usize;
+
+    let contacts = batch_ids.contact_batch(batch_id, contacts);
+    let collider_materials = batch_ids.coll_batch(batch_id, collider_materials);
+    let mut touching_pairs = batch_ids.event_pairs_batch_mut(batch_id, touching_pairs);
+    let touching_pairs_len = touching_pairs_len.at_mut(batch_id as usize);
+
+    let len = contacts_len
+        .read(batch_id as usize)
+        .min(batch_ids.contacts_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let colliders = contacts[i as usize].colliders;
+        let mat1 = collider_materials[colliders.x as usize];
+        let mat2 = collider_materials[colliders.y as usize];
+        if mat1.has_collision_events() || mat2.has_collision_events() {
+            let pair_index = atomic_add_u32(touching_pairs_len, 1) as usize;
+            // NOTE: if we exceed capacity, just skip the pair.
+            if pair_index < event_pairs_batch_capacity {
+                touching_pairs.write(pair_index, colliders);
+            }
+        }
+    }
+}
+
+/// Compares the colliding `pairs` with the previous step's `old_pairs` to append
+/// the collision-started/stopped events.
+///
+/// The pairs of a compound's parts are compared under the compound's first part
+/// (see [`ColliderMaterial::part_id`]). Duplicate pairs emit a single event, so a
+/// compound starts colliding with its first touching part and stops once none of
+/// its parts touch.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_emit_collision_events(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] pairs: &[UVec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] pairs_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] old_pairs: &[UVec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] old_pairs_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)]
+    collider_materials: &[ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] events: &mut [CollisionEvent],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] events_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let event_pairs_batch_capacity = batch_ids.event_pairs_batch_capacity;
+    let events_batch_capacity = batch_ids.events_batch_capacity;
+
+    let pairs = batch_ids.event_pairs_batch(batch_id, pairs);
+    let old_pairs = batch_ids.event_pairs_batch(batch_id, old_pairs);
+    let collider_materials = batch_ids.coll_batch(batch_id, collider_materials);
+    let mut events = batch_ids.events_batch_mut(batch_id, events);
+    let events_len = events_len.at_mut(batch_id as usize);
+
+    let len = pairs_len
+        .read(batch_id as usize)
+        .min(event_pairs_batch_capacity);
+    let old_len = old_pairs_len
+        .read(batch_id as usize)
+        .min(event_pairs_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let colliders = event_pair(&collider_materials, pairs[i as usize]);
+        if !contains_pair(&collider_materials, &pairs, i, colliders)
+            && !contains_pair(&collider_materials, &old_pairs, old_len, colliders)
+        {
+            let event_index = atomic_add_u32(events_len, 1) % events_batch_capacity;
+            events.write(
+                event_index as usize,
+                CollisionEvent {
+                    colliders,
+                    kind: COLLISION_EVENT_STARTED,
+                    flags: event_flags(&collider_materials, colliders),
+                },
+            );
+        }
+    }
+
+    for i in StepRng::new(invocation_id.x..old_len, num_threads) {
+        let colliders = event_pair(&collider_materials, old_pairs[i as usize]);
+        if !contains_pair(&collider_materials, &old_pairs, i, colliders)
+            && !contains_pair(&collider_materials, &pairs, len, colliders)
+        {
+            let event_index = atomic_add_u32(events_len, 1) % events_batch_capacity;
+            events.write(
+                event_index as usize,
+                CollisionEvent {
+                    colliders,
+                    kind: COLLISION_EVENT_STOPPED,
+                    flags: event_flags(&collider_materials, colliders),
+                },
+            );
+        }
+    }
+}
+
+/// Appends a contact-force event for each collider pair whose total contact force
+/// exceeds the smaller contact-force-event threshold of its two colliders.
+///
+/// The forces of all the touching parts of a compound are summed into a single
+/// event.
+///
+/// The forces are the normal impulses of the last substep divided by the substep
+/// length. Must run after the solver, before the constraint buffers are swapped.
+/// Contacts with multibody links are solved by the multibody solver and may
+/// report no force.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_emit_contact_force_events(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] contacts: &[IndexedManifold],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] contacts_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] constraints: &[TwoBodyConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)]
+    collider_materials: &[ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] all_params: &[RbdSimParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] events: &mut [ContactForceEvent],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] events_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let events_batch_capacity = batch_ids.events_batch_capacity;
+    let inv_dt = 1.0 / all_params.at(batch_id as usize).dt;
+
+    let contacts = batch_ids.contact_batch(batch_id, contacts);
+    let constraints = batch_ids.contact_batch(batch_id, constraints);
+    let collider_materials = batch_ids.coll_batch(batch_id, collider_materials);
+    let mut events = batch_ids.events_batch_mut(batch_id, events);
+    let events_len = events_len.at_mut(batch_id as usize);
+
+    let len = contacts_len
+        .read(batch_id as usize)
+        .min(batch_ids.contacts_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let part_colliders = contacts[i as usize].colliders;
+        let mat1 = collider_materials[part_colliders.x as usize];
+        let mat2 = collider_materials[part_colliders.y as usize];
+        let threshold = mat1
+            .effective_contact_force_event_threshold()
+            .min(mat2.effective_contact_force_event_threshold());
+        if threshold == f32::INFINITY {
+            continue;
+        }
+
+        // The first manifold of the pair accumulates the forces of all of them,
+        // including the ones of the other parts of a compound.
+        let colliders = event_pair(&collider_materials, part_colliders);
+        let mut is_first = true;
+        for j in 0..i {
+            let other = event_pair(&collider_materials, contacts[j as usize].colliders);
+            if same_pair(other, colliders) {
+                is_first = false;
+                break;
+            }
+        }
+        if !is_first {
+            continue;
+        }
+
+        let mut total_force = Vector::ZERO;
+        let mut total_force_magnitude = 0.0;
+        let mut max_force_direction = Vector::ZERO;
+        let mut max_force_magnitude = 0.0;
+        for j in i..len {
+            let other = event_pair(&collider_materials, contacts[j as usize].colliders);
+            if !same_pair(other, colliders) {
+                continue;
+            }
+
+            let constraint = &constraints[j as usize];
+            // Orient the normal from `colliders.x` to `colliders.y`.
+            let dir = if other == colliders {
+                constraint.dir_a
+            } else {
+                -constraint.dir_a
+            };
+            for k in 0..constraint.len {
+                let force = constraint.elements[k as usize].normal_part.impulse * inv_dt;
+                total_force += dir * force;
+                total_force_magnitude += force;
+                if force > max_force_magnitude {
+                    max_force_magnitude = force;
+                    max_force_direction = dir;
+                }
+            }
+        }
+
+        if total_force_magnitude > threshold {
+            let event_index = atomic_add_u32(events_len, 1) % events_batch_capacity;
+            events.write(
+                event_index as usize,
+                ContactForceEvent {
+                    total_force: total_force.into(),
+                    max_force_direction: max_force_direction.into(),
+                    colliders,
+                    total_force_magnitude,
+                    max_force_magnitude,
+                },
+            );
+        }
+    }
+}
+
+/// The slot the events of the collider at `slot` are reported under: the first
+/// part of its compound, or `slot` itself for a non-compound collider.
+fn event_collider(collider_materials: &Slice<ColliderMaterial>, slot: u32) -> u32 {
+    slot - collider_materials[slot as usize].part_id
+}
+
+/// The pair events between the `colliders` are reported under.
+fn event_pair(collider_materials: &Slice<ColliderMaterial>, colliders: UVec2) -> UVec2 {
+    UVec2::new(
+        event_collider(collider_materials, colliders.x),
+        event_collider(collider_materials, colliders.y),
+    )
+}
+
+/// The [`CollisionEvent::flags`] of an event between the `colliders`.
+fn event_flags(collider_materials: &Slice<ColliderMaterial>, colliders: UVec2) -> u32 {
+    if collider_materials[colliders.x as usize].is_sensor()
+        || collider_materials[colliders.y as usize].is_sensor()
+    {
+        COLLISION_EVENT_FLAG_SENSOR
+    } else {
+        0
+    }
+}
+
+/// Are `a` and `b` the same unordered collider pair?
+///
+/// The broad phase doesn't guarantee the order of a pair's colliders to be
+/// stable across steps, hence the unordered comparison.
+fn same_pair(a: UVec2, b: UVec2) -> bool {
+    a == b || (a.x == b.y && a.y == b.x)
+}
+
+/// Linear search of the unordered collider pair `colliders` among the
+/// [`event_pair`]s of the first `len` elements of `pairs`.
+fn contains_pair(
+    collider_materials: &Slice<ColliderMaterial>,
+    pairs: &Slice<UVec2>,
+    len: u32,
+    colliders: UVec2,
+) -> bool {
+    for i in 0..len {
+        if same_pair(event_pair(collider_materials, pairs[i as usize]), colliders) {
+            return true;
+        }
+    }
+    false
+}
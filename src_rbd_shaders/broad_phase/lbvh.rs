@@ -7,6 +7,7 @@
 
 use crate::bounding_volumes::Aabb;
 use crate::broad_phase::CollisionPair;
+use crate::dynamics::CcdSweep;
 use crate::shapes::Shape;
 use crate::utils::{BatchIndices, Slice, SliceMut, div_ceil};
 use crate::{MAX_FLT, PaddedVector, Pose, Vector};
@@ -136,7 +137,7 @@ pub fn gpu_lbvh_compute_domain(
     *workspace_mins.at_mut(thread_id as usize) = Vector::splat(MAX_FLT);
     *workspace_maxs.at_mut(thread_id as usize) = Vector::splat(-MAX_FLT);
     let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let colliders_end = colliders_start + batch_ids.colliders_len;
+    let colliders_end = colliders_start + batch_ids.colliders_len(batch_id);
 
     for i in StepRng::new(
         colliders_start + thread_id..colliders_end,
@@ -194,13 +195,11 @@ pub fn gpu_lbvh_compute_morton(
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
     let domain_aabb = domain_aabb.read(batch_id as usize);
-    let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let colliders_end = colliders_start + batch_ids.colliders_len;
+    let num_colliders = batch_ids.colliders_len(batch_id);
+    let poses = batch_ids.coll_batch(batch_id, poses);
+    let mut morton_keys = batch_ids.lbvh_batch_mut(batch_id, morton_keys);
 
-    for i in StepRng::new(
-        colliders_start + invocation_id.x..colliders_end,
-        num_threads,
-    ) {
+    for i in StepRng::new(invocation_id.x..num_colliders, num_threads) {
         let center = poses.at(i as usize).translation;
         let normalized = (center - domain_aabb.mins) / (domain_aabb.maxs - domain_aabb.mins);
         let morton_key = morton(normalized);
@@ -223,13 +222,18 @@ pub fn gpu_lbvh_build(
 ) {
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
-    let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let num_bodies = batch_ids.colliders_len;
+    let num_bodies = batch_ids.colliders_len(batch_id);
+    if num_bodies == 0 {
+        return;
+    }
     let num_internal_nodes = num_bodies - 1;
     let first_leaf_id = num_internal_nodes;
 
-    let mut tree = SliceMut(tree, root_id(colliders_start) as usize);
-    let morton_keys = Slice(morton_keys, colliders_start as usize);
+    let mut tree = SliceMut(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
+    let morton_keys = batch_ids.lbvh_batch(batch_id, morton_keys);
 
     for i in StepRng::new(invocation_id.x..num_internal_nodes, num_threads) {
         // Determine the direction of the range (+1 or -1).
@@ -332,14 +336,19 @@ pub fn gpu_lbvh_refit_leaves(
     // Bottom-up refit. Leaf index starts at `num_colliders`.
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
-    let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let num_colliders = batch_ids.colliders_len;
+    let num_colliders = batch_ids.colliders_len(batch_id);
+    if num_colliders == 0 {
+        return;
+    }
     let first_leaf_id = num_colliders - 1;
 
     let poses = batch_ids.coll_batch(batch_id, poses);
     let shapes = batch_ids.coll_batch(batch_id, shapes);
-    let sorted_colliders = batch_ids.coll_batch(batch_id, sorted_colliders);
-    let mut tree = SliceMut(tree, root_id(colliders_start) as usize);
+    let sorted_colliders = batch_ids.lbvh_batch(batch_id, sorted_colliders);
+    let mut tree = SliceMut(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
 
     for i in StepRng::new(invocation_id.x..num_colliders, num_threads) {
         let curr_leaf_id = first_leaf_id + i;
@@ -356,6 +365,42 @@ pub fn gpu_lbvh_refit_leaves(
     }
 }
 
+/// Enlarges the leaf AABBs of the colliders of CCD bodies by their motion over
+/// the step (see [`CcdSweep::sweep_aabb`]).
+///
+/// Run between [`gpu_lbvh_refit_leaves`] and [`gpu_lbvh_refit_internal`], only
+/// if any body has CCD enabled.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_lbvh_sweep_leaves(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] sweeps: &[CcdSweep],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] tree: &mut [LbvhNode],
+    #[spirv(uniform, descriptor_set = 0, binding = 2)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let num_colliders = batch_ids.colliders_len(batch_id);
+    if num_colliders == 0 {
+        return;
+    }
+    let first_leaf_id = num_colliders - 1;
+
+    let sweeps = batch_ids.coll_batch(batch_id, sweeps);
+    let mut tree = SliceMut(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
+
+    for i in StepRng::new(invocation_id.x..num_colliders, num_threads) {
+        let leaf_id = (first_leaf_id + i) as usize;
+        let leaf_collider = tree.at(leaf_id).left;
+        let aabb = tree.at(leaf_id).aabb;
+        tree.at_mut(leaf_id).aabb = sweeps[leaf_collider as usize].sweep_aabb(&aabb);
+    }
+}
+
 /// Bottom-up AABB propagation using atomic synchronization.
 /// This version uses uniform control flow for web compatibility.
 #[spirv_bindgen]
@@ -371,11 +416,17 @@ pub fn gpu_lbvh_refit_internal(
     // Bottom-up refit. Leaf index starts at `num_colliders`.
     let num_threads = 256u32;
     let batch_id = workgroup_id.y;
-    let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let num_bodies = batch_ids.colliders_len;
+    let num_bodies = batch_ids.colliders_len(batch_id);
+    if num_bodies == 0 {
+        // Uniform across the workgroup: all its threads belong to this batch.
+        return;
+    }
     let first_leaf_id = num_bodies - 1;
 
-    let mut tree = SliceMut(tree, root_id(colliders_start) as usize);
+    let mut tree = SliceMut(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
     let num_iterations = num_bodies.div_ceil(num_threads);
 
     // NOTE: using unchecked indexing (via MaybeIndexUnchecked) because otherwise the bounds
@@ -468,14 +519,19 @@ pub fn gpu_lbvh_refit(
     // Bottom-up refit. Leaf index starts at `num_colliders`.
     let batch_id = invocation_id.y;
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
-    let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let num_bodies = batch_ids.colliders_len;
+    let num_bodies = batch_ids.colliders_len(batch_id);
+    if num_bodies == 0 {
+        return;
+    }
     let first_leaf_id = num_bodies - 1;
 
     let poses = batch_ids.coll_batch(batch_id, poses);
     let shapes = batch_ids.coll_batch(batch_id, shapes);
-    let sorted_colliders = batch_ids.coll_batch(batch_id, sorted_colliders);
-    let mut tree = SliceMut(tree, root_id(colliders_start) as usize);
+    let sorted_colliders = batch_ids.lbvh_batch(batch_id, sorted_colliders);
+    let mut tree = SliceMut(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
 
     for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
         let curr_leaf_id = first_leaf_id + i;
@@ -544,12 +600,17 @@ pub fn gpu_lbvh_find_collision_pairs(
 ) {
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
-    let colliders_start = batch_ids.coll_start(batch_id) as u32;
-    let num_bodies = batch_ids.colliders_len;
+    let num_bodies = batch_ids.colliders_len(batch_id);
+    if num_bodies == 0 {
+        return;
+    }
     let first_leaf_id = num_bodies - 1;
 
     let mut collision_pairs = batch_ids.collision_pairs_batch_mut(batch_id, collision_pairs);
-    let tree = Slice(tree, root_id(colliders_start) as usize);
+    let tree = Slice(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
     let collision_groups = batch_ids.coll_batch(batch_id, collision_groups);
     let pair_filter = batch_ids.coll_batch(batch_id, pair_filter);
 
@@ -718,19 +779,90 @@ pub fn prefix_len(
     }
 }
 
-fn root_id(collider_start_id: u32) -> u32 {
+pub(crate) fn root_id(lbvh_start_id: u32) -> u32 {
     // Every LBVH tree contains `n - 1` internal nodes and `n` leaves, where
     // `n` is its number of colliders. This is a total of `2n - 1`, but to
     // simplify calculations we allocate `2n` nodes per tree.
     //
-    // Before the batch dimension with collider id starting at `collider_start_id`,
-    // there are `collider_start_id` colliders for other batch dimensions, so they
-    // require a total of `2 * colliders_start_id` nodes for their LBVH; so the root
-    // of the current LBVH is `2 * collider_start_id`.
+    // Before the batch dimension with LBVH section starting at `lbvh_start_id`
+    // (see `BatchIndices::lbvh_start`), there are `lbvh_start_id` leaf slots for
+    // other batch dimensions, so they require a total of `2 * lbvh_start_id` nodes
+    // for their LBVH; so the root of the current LBVH is `2 * lbvh_start_id`.
     //
     // NOTE: if we allocated `2n - 1` node per LBVH instead of `2n`, then the root
     //       id for the current LBVH would be `2n - b` where `b` is the current batch
     //       id. We don’t do this for the simplicity of not having to deal with the
     //       `- b`.
-    collider_start_id * 2
+    lbvh_start_id * 2
+}
+
+/// Stackless depth-first traversal of a batch's LBVH, following the parent
+/// pointers of the tree instead of maintaining a stack.
+///
+/// Each step either enters a node (coming from its parent) or comes back to an
+/// internal node from one of its children. Entered nodes must be handled with
+/// [`LbvhTraversal::descend`] (to visit the subtree of an internal node) or
+/// [`LbvhTraversal::ascend`] (after visiting a leaf, or to prune a subtree).
+/// Nodes that aren't entered must be handled with [`LbvhTraversal::advance`].
+#[derive(Copy, Clone)]
+pub(crate) struct LbvhTraversal {
+    /// The current node.
+    pub curr: u32,
+    /// The node visited before `curr`.
+    prev: u32,
+    /// Did we reach `curr` from its parent?
+    pub entering: bool,
+    /// Did we come back up from the root?
+    pub done: bool,
+}
+
+impl LbvhTraversal {
+    /// Starts a traversal at the root of the tree.
+    pub fn new() -> Self {
+        Self {
+            curr: 0,
+            prev: 0,
+            entering: true,
+            done: false,
+        }
+    }
+
+    /// Upper bound on the number of steps of a full traversal of a tree with
+    /// `num_colliders` leaves: every internal node is reached once from its
+    /// parent and once from each child, every leaf once.
+    pub fn max_steps(num_colliders: u32) -> u32 {
+        4 * num_colliders
+    }
+
+    /// Moves from the entered internal node to its left child `left`.
+    pub fn descend(&mut self, left: u32) {
+        self.prev = self.curr;
+        self.curr = left;
+        self.entering = true;
+    }
+
+    /// Leaves the current node, going back to its parent.
+    pub fn ascend(&mut self, tree: &Slice<LbvhNode>) {
+        if self.curr == 0 {
+            // The root’s parent isn’t set.
+            self.done = true;
+        } else {
+            self.prev = self.curr;
+            self.curr = tree.at(self.curr as usize).parent;
+            self.entering = false;
+        }
+    }
+
+    /// Continues the traversal from an internal node we came back to: enters its
+    /// right child after its left child, and goes up after its right child.
+    pub fn advance(&mut self, tree: &Slice<LbvhNode>) {
+        let node = tree.at(self.curr as usize);
+        if self.prev == node.left {
+            self.prev = self.curr;
+            self.curr = node.right;
+            self.entering = true;
+        } else {
+            self.ascend(tree);
+        }
+    }
 }
@@ -9,17 +9,21 @@ mod brute_force;
 mod lbvh;
 
 // GPU compute shader kernels
+mod collision_events;
 mod narrow_phase;
+mod sensors;
 
 use glamx::UVec2;
 // Re-export non-spirv items explicitly to avoid ambiguous glob re-exports.
 // The div_ceil functions have different signatures (u32 vs i32) so we pick one.
 // Spirv-only items (functions and generated structs) are re-exported via glob.
 pub use brute_force::*;
+pub use collision_events::*;
 pub use lbvh::*;
 #[cfg(feature = "dim2")]
 pub use lbvh::{expand_bits_2d, morton_2d};
 pub use narrow_phase::*;
+pub use sensors::*;
 
 #[derive(Copy, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
@@ -2,13 +2,15 @@
 //!
 //! Computes contact manifolds from collision pairs detected by the broad phase.
 
+use crate::bounding_volumes::Aabb;
 use crate::queries::{
     ColliderMaterial, ContactManifold, IndexedManifold, ball_ball, ball_convex, convex_ball,
     cuboid_cuboid, pfm_pfm,
 };
 use crate::shapes::{
-    Capsule, Polyline, SHAPE_TYPE_BALL, SHAPE_TYPE_CAPSULE, SHAPE_TYPE_CONE, SHAPE_TYPE_CUBOID,
-    SHAPE_TYPE_CYLINDER, SHAPE_TYPE_POLYLINE, SHAPE_TYPE_TRIMESH, Shape, TriMesh,
+    Capsule, HeightField, Polyline, SHAPE_TYPE_BALL, SHAPE_TYPE_CAPSULE, SHAPE_TYPE_CONE,
+    SHAPE_TYPE_CUBOID, SHAPE_TYPE_CYLINDER, SHAPE_TYPE_HEIGHTFIELD, SHAPE_TYPE_POLYLINE,
+    SHAPE_TYPE_TRIMESH, Shape, TriMesh,
 };
 use crate::{PaddedVector, Pose, Vector};
 use khal_std::glamx::UVec3;
@@ -68,12 +70,14 @@ pub fn gpu_narrow_phase_init_contacts_dispatch(
 }
 
 pub(crate) const PREDICTION: f32 = 2.0e-2; // TODO: make the prediction configurable.
+/// Radius of the capsules the polyline segments are seen as by the narrow phase.
+const POLYLINE_THICKNESS: f32 = 0.4; // TODO: make thickness configurable or part of the polyline struct
 
 /// Narrow phase, pass 1 of 2: analytic shape-shape contacts for ball / cuboid
 /// pairs, written straight into the `contacts` buffer.
 ///
-/// The complex cases (generic convex via PFM, trimesh, polyline) are deferred
-/// to `gpu_narrow_phase_shape_shape_deferred`.
+/// The complex cases (generic convex via PFM, trimesh, polyline, heightfield)
+/// are deferred to `gpu_narrow_phase_shape_shape_deferred`.
 #[spirv_bindgen]
 #[spirv(compute(threads(64)))]
 pub fn gpu_narrow_phase_shape_shape(
@@ -119,6 +123,13 @@ pub fn gpu_narrow_phase_shape_shape(
         if body1 == body2 {
             continue;
         }
+        // Sensors never generate contacts: their intersections are detected by
+        // the `gpu_sensor_*` kernels instead.
+        let mat1 = collider_materials[pair.colliders.x as usize];
+        let mat2 = collider_materials[pair.colliders.y as usize];
+        if mat1.is_sensor() || mat2.is_sensor() {
+            continue;
+        }
         let pose1 = poses[pair.colliders.x as usize];
         let pose2 = poses[pair.colliders.y as usize];
         let shape1 = &shapes[pair.colliders.x as usize];
@@ -156,22 +167,25 @@ pub fn gpu_narrow_phase_shape_shape(
         }
 
         // Cuboid - Cuboid
-        if shape_ty1 == SHAPE_TYPE_CUBOID && shape_ty2 == SHAPE_TYPE_CUBOID {
+        // Round cuboids are left to the PFM path, which accounts for their border radius.
+        if shape_ty1 == SHAPE_TYPE_CUBOID
+            && shape_ty2 == SHAPE_TYPE_CUBOID
+            && shape1.border_radius() == 0.0
+            && shape2.border_radius() == 0.0
+        {
             let cuboid1 = shape1.to_cuboid();
             let cuboid2 = shape2.to_cuboid();
             manifold = cuboid_cuboid(pose12, &cuboid1, &cuboid2, PREDICTION);
         }
 
-        // Everything else (PFM / trimesh / polyline) is handled by the deferred
-        // pass; `manifold.len` stays 0 here so nothing is written.
+        // Everything else (PFM / trimesh / polyline / heightfield) is handled by
+        // the deferred pass; `manifold.len` stays 0 here so nothing is written.
         if manifold.len > 0 && manifold.points_a.at(0).dist < PREDICTION {
             let target_contact_index = atomic_add_u32(contacts_len, 1) as usize;
 
             // NOTE: if we exceed the contacts allocation size, just skip
             //       the contact.
             if target_contact_index < contacts_batch_capacity {
-                let mat1 = collider_materials[pair.colliders.x as usize];
-                let mat2 = collider_materials[pair.colliders.y as usize];
                 contacts[target_contact_index] = IndexedManifold {
                     contact: manifold,
                     colliders: pair.colliders,
@@ -186,7 +200,7 @@ pub fn gpu_narrow_phase_shape_shape(
 }
 
 /// Narrow phase, pass 2 of 2: defer the complex shape-shape pairs (generic
-/// convex via PFM, trimesh, polyline) into the `pfm_pairs` work-list consumed by
+/// convex via PFM, trimesh, polyline, heightfield, and mesh-mesh pairs) into the `pfm_pairs` work-list consumed by
 /// `gpu_narrow_phase_pfm_pfm`. Ball / cuboid pairs were already resolved by
 /// `gpu_narrow_phase_shape_shape`; this pass skips them via the same shape-type
 /// predicate. See that kernel for why the work is split.
@@ -237,7 +251,8 @@ pub fn gpu_narrow_phase_shape_shape_deferred(
 
         // Mirror pass 1's analytic-pair predicate (ball/cuboid) so those pairs
         // are skipped here — they were already turned into contacts. Only the
-        // complex cases fall through to the PFM / trimesh / polyline handling.
+        // complex cases fall through to the PFM / trimesh / polyline /
+        // heightfield handling.
         let mut checked = false;
         if shape_ty1 == SHAPE_TYPE_BALL
             && (shape_ty2 == SHAPE_TYPE_BALL
@@ -257,7 +272,12 @@ pub fn gpu_narrow_phase_shape_shape_deferred(
         {
             checked = true;
         }
-        if !checked && shape_ty1 == SHAPE_TYPE_CUBOID && shape_ty2 == SHAPE_TYPE_CUBOID {
+        if !checked
+            && shape_ty1 == SHAPE_TYPE_CUBOID
+            && shape_ty2 == SHAPE_TYPE_CUBOID
+            && shape1.border_radius() == 0.0
+            && shape2.border_radius() == 0.0
+        {
             checked = true;
         }
         if checked {
@@ -293,6 +313,24 @@ pub fn gpu_narrow_phase_shape_shape_deferred(
             }
         }
 
+        // TriMesh/Polyline - TriMesh/Polyline
+        let composite1 = shape_ty1 == SHAPE_TYPE_TRIMESH || shape_ty1 == SHAPE_TYPE_POLYLINE;
+        let composite2 = shape_ty2 == SHAPE_TYPE_TRIMESH || shape_ty2 == SHAPE_TYPE_POLYLINE;
+        if !checked && composite1 && composite2 {
+            composite_composite(
+                pose12,
+                shape1,
+                shape2,
+                pair.colliders,
+                &mut pfm_pairs,
+                pfm_pairs_len,
+                contacts_batch_capacity,
+                vertices,
+                indices,
+            );
+            continue;
+        }
+
         // TriMesh - Convex
         // Note: trimesh collision writes contacts directly to the buffer and early-exits.
         if !checked && shape_ty1 == SHAPE_TYPE_TRIMESH {
@@ -366,6 +404,43 @@ pub fn gpu_narrow_phase_shape_shape_deferred(
             );
             continue;
         }
+
+        // HeightField - Convex
+        // Note: heightfield collision writes contacts directly to the buffer and early-exits.
+        if !checked && shape_ty1 == SHAPE_TYPE_HEIGHTFIELD {
+            let hf = shape1.to_heightfield();
+            let convex = shape2;
+            heightfield_convex(
+                pose12,
+                &hf,
+                convex,
+                pair.colliders,
+                &mut pfm_pairs,
+                pfm_pairs_len,
+                contacts_batch_capacity,
+                vertices,
+                indices,
+            );
+            continue;
+        }
+
+        if !checked && shape_ty2 == SHAPE_TYPE_HEIGHTFIELD {
+            let convex = shape1;
+            let hf = shape2.to_heightfield();
+            // NOTE: pair indices are flipped.
+            heightfield_convex(
+                pose12.inverse(),
+                &hf,
+                convex,
+                UVec2::new(pair.colliders.y, pair.colliders.x),
+                &mut pfm_pairs,
+                pfm_pairs_len,
+                contacts_batch_capacity,
+                vertices,
+                indices,
+            );
+            continue;
+        }
     }
 }
 
@@ -458,7 +533,7 @@ fn polyline_convex(
     }
 
     // Get the convex shape's AABB in the polyline's local space, and enlarge with the PREDICTION.
-    let thickness = 0.4; // TODO: make thickness configurable or part of the polyline struct
+    let thickness = POLYLINE_THICKNESS;
     let mut test_aabb = convex.compute_aabb(pose12, vertices);
     test_aabb.mins -= Vector::splat(PREDICTION + thickness);
     test_aabb.maxs += Vector::splat(PREDICTION + thickness);
@@ -512,6 +587,265 @@ fn polyline_convex(
     }
 }
 
+/// BVH node of a trimesh or polyline, as `(entry_index, exit_index, shape_index)`.
+fn composite_bvh_node_idx(shape: &Shape, node_id: u32, indices: &[u32]) -> UVec3 {
+    if shape.shape_type() == SHAPE_TYPE_TRIMESH {
+        let idx = shape.to_trimesh().bvh_node_idx(indices, node_id);
+        UVec3::new(idx.entry_index, idx.exit_index, idx.shape_index)
+    } else {
+        let idx = shape.to_polyline().bvh_node_idx(node_id, indices);
+        UVec3::new(idx.entry_index, idx.exit_index, idx.shape_index)
+    }
+}
+
+/// AABB of a BVH node of a trimesh or polyline, enlarged by the polyline thickness.
+fn composite_bvh_node_aabb(
+    shape: &Shape,
+    node_id: u32,
+    vertices: &[PaddedVector],
+    polyline_thickness: f32,
+) -> Aabb {
+    if shape.shape_type() == SHAPE_TYPE_TRIMESH {
+        shape.to_trimesh().bvh_node_aabb(vertices, node_id)
+    } else {
+        shape
+            .to_polyline()
+            .bvh_node_aabb(node_id, vertices)
+            .loosened(polyline_thickness)
+    }
+}
+
+/// Number of BVH nodes of a trimesh or polyline.
+fn composite_bvh_node_len(shape: &Shape) -> u32 {
+    if shape.shape_type() == SHAPE_TYPE_TRIMESH {
+        shape.to_trimesh().bvh_node_len
+    } else {
+        shape.to_polyline().bvh_node_len
+    }
+}
+
+/// Root AABB of a trimesh or polyline, enlarged by the polyline thickness.
+fn composite_root_aabb(shape: &Shape, polyline_thickness: f32) -> Aabb {
+    if shape.shape_type() == SHAPE_TYPE_TRIMESH {
+        shape.to_trimesh().root_aabb
+    } else {
+        shape.to_polyline().root_aabb.loosened(polyline_thickness)
+    }
+}
+
+/// Leaf of a trimesh or polyline: a triangle, or a segment seen as a capsule.
+fn composite_leaf(
+    shape: &Shape,
+    leaf_id: u32,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+    polyline_thickness: f32,
+) -> Shape {
+    if shape.shape_type() == SHAPE_TYPE_TRIMESH {
+        let tri = shape.to_trimesh().triangle(indices, vertices, leaf_id);
+        Shape::from_triangle(&tri)
+    } else {
+        let seg = shape.to_polyline().segment(leaf_id, vertices, indices);
+        Shape::from_capsule(&Capsule::new(seg, polyline_thickness))
+    }
+}
+
+/// Collision detection between two composite shapes (trimeshes or polylines).
+///
+/// Traverses the BVH of the first shape with the AABB of the second one, then the
+/// BVH of the second shape with the AABB of each leaf found. Each pair of
+/// overlapping leaves is emitted as a PFM pair.
+///
+/// The segments of a polyline are seen as capsules. When both shapes are
+/// polylines, each capsule only gets half the polyline thickness so the pair
+/// isn't twice as thick as a polyline-convex pair.
+fn composite_composite(
+    pose12: Pose,
+    shape1: &Shape,
+    shape2: &Shape,
+    colliders: UVec2,
+    pfm_pairs: &mut SliceMut<NarrowPhasePfmPair>,
+    pfm_pairs_len: &mut u32,
+    pfm_pairs_capacity: usize,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) {
+    let polyline_thickness = if shape1.shape_type() == SHAPE_TYPE_POLYLINE
+        && shape2.shape_type() == SHAPE_TYPE_POLYLINE
+    {
+        POLYLINE_THICKNESS / 2.0
+    } else {
+        POLYLINE_THICKNESS
+    };
+    let root_aabb1 = composite_root_aabb(shape1, polyline_thickness);
+    let root_aabb2 = composite_root_aabb(shape2, polyline_thickness);
+
+    // Get the second shape's AABB in the first shape's local space, and enlarge with the PREDICTION.
+    let test_aabb1 = root_aabb2.transform_by(pose12).loosened(PREDICTION);
+    if !test_aabb1.intersects(&root_aabb1) {
+        // No collision possible.
+        return;
+    }
+
+    let pose21 = pose12.inverse();
+    let bvh_node_len1 = composite_bvh_node_len(shape1);
+    let bvh_node_len2 = composite_bvh_node_len(shape2);
+    let mut curr1 = 0u32;
+
+    // NOTE: we use fixed-size for loops to avoid miscompilation issues of while loops on MacOs.
+    for _ in 0..bvh_node_len1 {
+        if curr1 >= bvh_node_len1 {
+            break;
+        }
+
+        let idx1 = composite_bvh_node_idx(shape1, curr1, indices);
+        if idx1.x == 0xffffffff {
+            // This is a leaf: find the leaves of the second shape it overlaps.
+            let leaf1 = composite_leaf(shape1, idx1.z, vertices, indices, polyline_thickness);
+            let sub1 = leaf1.pfm_subshape();
+            let test_aabb2 = leaf1.compute_aabb(pose21, vertices).loosened(PREDICTION);
+
+            if test_aabb2.intersects(&root_aabb2) {
+                let mut curr2 = 0u32;
+
+                for _ in 0..bvh_node_len2 {
+                    if curr2 >= bvh_node_len2 {
+                        break;
+                    }
+
+                    let idx2 = composite_bvh_node_idx(shape2, curr2, indices);
+                    if idx2.x == 0xffffffff {
+                        let leaf2 =
+                            composite_leaf(shape2, idx2.z, vertices, indices, polyline_thickness);
+                        // The parent nodes only bound the leaf loosely (and the root
+                        // itself can be a leaf): test the leaf's own AABB.
+                        let leaf_aabb2 = leaf2.compute_aabb(Pose::IDENTITY, vertices);
+                        if test_aabb2.intersects(&leaf_aabb2) {
+                            let sub2 = leaf2.pfm_subshape();
+                            // TODO PERF: add special-cases for pairs that can be handled more efficiently than with GJK/EPA.
+                            let pfm_pair = NarrowPhasePfmPair {
+                                shape1: sub1.shape,
+                                shape2: sub2.shape,
+                                pose12,
+                                thickness1: sub1.thickness,
+                                thickness2: sub2.thickness,
+                                colliders,
+                            };
+                            let pfm_index = atomic_add_u32(pfm_pairs_len, 1);
+                            // Skip (don’t write) on overflow; the caller resizes and re-runs.
+                            if (pfm_index as usize) < pfm_pairs_capacity {
+                                pfm_pairs.write(pfm_index as usize, pfm_pair);
+                            }
+                        }
+
+                        // Continue traversal.
+                        curr2 = idx2.y;
+                    } else {
+                        let node_aabb =
+                            composite_bvh_node_aabb(shape2, curr2, vertices, polyline_thickness);
+                        if test_aabb2.intersects(&node_aabb) {
+                            curr2 = idx2.x;
+                        } else {
+                            curr2 = idx2.y;
+                        }
+                    }
+                }
+            }
+
+            // Continue traversal.
+            curr1 = idx1.y;
+        } else {
+            let node_aabb = composite_bvh_node_aabb(shape1, curr1, vertices, polyline_thickness);
+            if test_aabb1.intersects(&node_aabb) {
+                curr1 = idx1.x;
+            } else {
+                curr1 = idx1.y;
+            }
+        }
+    }
+}
+
+/// Collision detection between a heightfield and a convex shape.
+///
+/// Unlike trimeshes and polylines, no BVH traversal is needed: the cells
+/// overlapping the convex shape's AABB are read straight from the grid.
+fn heightfield_convex(
+    pose12: Pose,
+    hf: &HeightField,
+    convex: &Shape,
+    colliders: UVec2,
+    pfm_pairs: &mut SliceMut<NarrowPhasePfmPair>,
+    pfm_pairs_len: &mut u32,
+    pfm_pairs_capacity: usize,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) {
+    let sub2 = convex.pfm_subshape();
+    if !sub2.valid {
+        // Collisions with non-PFM shapes is not supported.
+        return;
+    }
+
+    // Get the convex shape's AABB in the heightfield's local space, and enlarge with the PREDICTION.
+    let mut test_aabb = convex.compute_aabb(pose12, vertices);
+    test_aabb.mins -= Vector::splat(PREDICTION);
+    test_aabb.maxs += Vector::splat(PREDICTION);
+
+    if !test_aabb.intersects(&hf.root_aabb) {
+        // No collision possible.
+        return;
+    }
+
+    let (mins, maxs) = hf.cell_range(&test_aabb);
+
+    for i in mins.y..maxs.y {
+        for j in mins.x..maxs.x {
+            #[cfg(feature = "dim2")]
+            let num_subcells = 1;
+            #[cfg(feature = "dim3")]
+            let num_subcells = 2;
+
+            for k in 0..num_subcells {
+                #[cfg(feature = "dim2")]
+                let (cell_shape, cell_aabb, valid) = {
+                    let _ = (i, k);
+                    let (seg, valid) = hf.segment(vertices, indices, j);
+                    // The segment is seen as a capsule with a zero radius.
+                    let capsule = Capsule::new(seg, 0.0);
+                    let aabb =
+                        crate::bounding_volumes::Aabb::new(seg.a.min(seg.b), seg.a.max(seg.b));
+                    (Shape::from_capsule(&capsule), aabb, valid)
+                };
+                #[cfg(feature = "dim3")]
+                let (cell_shape, cell_aabb, valid) = {
+                    let (tri, valid) = hf.triangle(vertices, indices, i, j, k == 1);
+                    (Shape::from_triangle(&tri), tri.aabb(), valid)
+                };
+
+                if !valid || !test_aabb.intersects(&cell_aabb) {
+                    continue;
+                }
+
+                let sub1 = cell_shape.pfm_subshape();
+                // TODO PERF: add special-cases for pairs that can be handled more efficiently than with GJK/EPA.
+                let pfm_pair = NarrowPhasePfmPair {
+                    shape1: sub1.shape,
+                    shape2: sub2.shape,
+                    pose12,
+                    thickness1: sub1.thickness,
+                    thickness2: sub2.thickness,
+                    colliders,
+                };
+                let pfm_index = atomic_add_u32(pfm_pairs_len, 1);
+                // Skip (don’t write) on overflow; the caller resizes and re-runs.
+                if (pfm_index as usize) < pfm_pairs_capacity {
+                    pfm_pairs.write(pfm_index as usize, pfm_pair);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, Default)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
 #[repr(C)]
@@ -577,7 +911,7 @@ pub fn gpu_narrow_phase_pfm_pfm(
     for i in StepRng::new(invocation_id.x..pfm_pairs_len, num_threads) {
         let pair = pfm_pairs[i as usize];
         // Resolve the parent rigid-bodies and skip same-body collider pairs. This
-        // is where the deferred (PFM / trimesh / polyline) pairs get the same-body
+        // is where the deferred (PFM / trimesh / polyline / heightfield) pairs get the same-body
         // filtering that the analytic pass does inline — the broad phase no longer
         // does it, and the deferred pass has no spare storage binding for it.
         let body1 = collider_parent.read(pair.colliders.x as usize);
@@ -585,6 +919,13 @@ pub fn gpu_narrow_phase_pfm_pfm(
         if body1 == body2 {
             continue;
         }
+        // Sensor pairs (including the sub-shape pairs of the deferred pass) never
+        // generate contacts.
+        let mat1 = collider_materials[pair.colliders.x as usize];
+        let mat2 = collider_materials[pair.colliders.y as usize];
+        if mat1.is_sensor() || mat2.is_sensor() {
+            continue;
+        }
         let manifold = pfm_pfm(
             pair.pose12,
             &pair.shape1,
@@ -602,8 +943,6 @@ pub fn gpu_narrow_phase_pfm_pfm(
 
             // NOTE: if we exceed capacity, just skip the pair.
             if target_contact_index < contacts_batch_capacity {
-                let mat1 = collider_materials[pair.colliders.x as usize];
-                let mat2 = collider_materials[pair.colliders.y as usize];
                 contacts[target_contact_index] = IndexedManifold {
                     contact: manifold,
                     colliders: pair.colliders,
@@ -0,0 +1,163 @@
+//! Sensor intersection kernels.
+//!
+//! Sensor colliders go through the broad phase like any other collider, but the
+//! contact-generating narrow-phase kernels skip them. Instead, the pairs involving
+//! a sensor are tested for intersection here. The intersecting pairs are then
+//! compared with the previous step's by [`gpu_emit_collision_events`](super::gpu_emit_collision_events).
+
+use crate::queries::{ColliderMaterial, ContactManifold, MAX_MANIFOLD_POINTS, pfm_pfm};
+use crate::shapes::Shape;
+use crate::utils::BatchIndices;
+use crate::{PaddedVector, Pose};
+use glamx::UVec2;
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::macros::{spirv, spirv_bindgen};
+use khal_std::{iter::StepRng, sync::atomic_add_u32};
+
+use super::CollisionPair;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Resets the sensor-pair and intersection counters. One thread per batch.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_reset_sensors(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] sensor_pairs_len: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] intersections_len: &mut [u32],
+) {
+    let batch_id = invocation_id.x as usize;
+    if batch_id < sensor_pairs_len.len() {
+        sensor_pairs_len.write(batch_id, 0);
+        intersections_len.write(batch_id, 0);
+    }
+}
+
+/// Sensor pass 1 of 2: collects the broad-phase pairs involving a sensor.
+///
+/// Split from the intersection test to fit 8 storage buffers.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_sensor_pairs(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] collision_pairs: &[CollisionPair],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] collision_pairs_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] collider_parent: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)]
+    collider_materials: &[ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] sensor_pairs: &mut [UVec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] sensor_pairs_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let contacts_batch_capacity = batch_ids.contacts_batch_capacity;
+    let event_pairs_batch_capacity = batch_ids.event_pairs_batch_capacity as usize;
+
+    let collision_pairs = batch_ids.contact_batch(batch_id, collision_pairs);
+    let collider_parent = batch_ids.coll_batch(batch_id, collider_parent);
+    let collider_materials = batch_ids.coll_batch(batch_id, collider_materials);
+    let mut sensor_pairs = batch_ids.event_pairs_batch_mut(batch_id, sensor_pairs);
+    let sensor_pairs_len = sensor_pairs_len.at_mut(batch_id as usize);
+
+    let len = collision_pairs_len
+        .read(batch_id as usize)
+        .min(contacts_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let pair = collision_pairs[i as usize];
+        let body1 = collider_parent[pair.colliders.x as usize];
+        let body2 = collider_parent[pair.colliders.y as usize];
+        if body1 == body2 {
+            continue;
+        }
+
+        let mat1 = collider_materials[pair.colliders.x as usize];
+        let mat2 = collider_materials[pair.colliders.y as usize];
+        if mat1.is_sensor() || mat2.is_sensor() {
+            let sensor_index = atomic_add_u32(sensor_pairs_len, 1) as usize;
+            // NOTE: if we exceed capacity, just skip the pair.
+            if sensor_index < event_pairs_batch_capacity {
+                sensor_pairs.write(sensor_index, pair.colliders);
+            }
+        }
+    }
+}
+
+/// Sensor pass 2 of 2: exact intersection test of the sensor pairs, collecting
+/// the intersecting ones into `intersections`.
+///
+/// Only pairs of convex shapes (the ones with a valid [`Shape::pfm_subshape`])
+/// are tested: sensors never intersect triangle meshes, polylines or heightfields.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_sensor_intersections(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] sensor_pairs: &[UVec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] sensor_pairs_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] shapes: &[Shape],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] vertices: &[PaddedVector],
+    #[allow(unused_variables)]
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)]
+    indices: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] intersections: &mut [UVec2],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 7)] intersections_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 8)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let event_pairs_batch_capacity = batch_ids.event_pairs_batch_capacity;
+
+    let sensor_pairs = batch_ids.event_pairs_batch(batch_id, sensor_pairs);
+    let poses = batch_ids.coll_batch(batch_id, poses);
+    let shapes = batch_ids.coll_batch(batch_id, shapes);
+    let mut intersections = batch_ids.event_pairs_batch_mut(batch_id, intersections);
+    let intersections_len = intersections_len.at_mut(batch_id as usize);
+
+    let len = sensor_pairs_len
+        .read(batch_id as usize)
+        .min(event_pairs_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let colliders = sensor_pairs[i as usize];
+        let sub1 = shapes[colliders.x as usize].pfm_subshape();
+        let sub2 = shapes[colliders.y as usize].pfm_subshape();
+        if !sub1.valid || !sub2.valid {
+            // Intersections with non-PFM shapes are not supported.
+            continue;
+        }
+
+        let pose12 = poses[colliders.x as usize].inverse() * poses[colliders.y as usize];
+        let manifold = pfm_pfm(
+            pose12,
+            &sub1.shape,
+            sub1.thickness,
+            &sub2.shape,
+            sub2.thickness,
+            0.0,
+            vertices,
+            #[cfg(feature = "dim3")]
+            indices,
+        );
+
+        if is_penetrating(&manifold) {
+            // Can't overflow: there are at most as many intersections as sensor pairs.
+            let target_index = atomic_add_u32(intersections_len, 1) as usize;
+            intersections.write(target_index, colliders);
+        }
+    }
+}
+
+/// Does the manifold contain at least one penetrating point?
+fn is_penetrating(manifold: &ContactManifold) -> bool {
+    for k in 0..MAX_MANIFOLD_POINTS {
+        if (k as u32) < manifold.len && manifold.points_a.at(k).dist <= 0.0 {
+            return true;
+        }
+    }
+    false
+}
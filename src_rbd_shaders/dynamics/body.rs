@@ -5,10 +5,146 @@ use crate::{AngVector, Pose, Rotation, Vector};
 use crate::{rotation_from_scaled_axis, rotation_renormalize_fast, rotation_to_matrix};
 
 #[cfg(feature = "dim3")]
-use glamx::{Mat3, Mat4, Quat, Vec3};
+use glamx::{Mat3, Mat4, Quat, Vec3, Vec4};
 #[cfg(feature = "dim2")]
 use glamx::{Rot2, Vec2};
 
+/// Body type of a dynamic rigid-body, affected by forces and contacts.
+///
+/// The body type values mirror the discriminants of rapier's `RigidBodyType`.
+pub const BODY_TYPE_DYNAMIC: u32 = 0;
+/// Body type of a fixed rigid-body, which never moves.
+pub const BODY_TYPE_FIXED: u32 = 1;
+/// Body type of a kinematic rigid-body moved toward a target pose set by the user.
+pub const BODY_TYPE_KINEMATIC_POSITION: u32 = 2;
+/// Body type of a kinematic rigid-body moved at a velocity set by the user.
+pub const BODY_TYPE_KINEMATIC_VELOCITY: u32 = 3;
+
+/// Is the given body type one of the kinematic body types?
+#[inline]
+pub fn is_kinematic(body_type: u32) -> bool {
+    body_type == BODY_TYPE_KINEMATIC_POSITION || body_type == BODY_TYPE_KINEMATIC_VELOCITY
+}
+
+/// Flag locking the translation along the `x` axis.
+///
+/// The `LOCKED_*` flags mirror the bits of rapier's `LockedAxes`.
+pub const LOCKED_TRANSLATION_X: u32 = 1 << 0;
+/// Flag locking the translation along the `y` axis.
+pub const LOCKED_TRANSLATION_Y: u32 = 1 << 1;
+/// Flag locking the translation along the `z` axis (3D only).
+#[cfg(feature = "dim3")]
+pub const LOCKED_TRANSLATION_Z: u32 = 1 << 2;
+/// Flag locking the rotation around the `x` axis (3D only).
+#[cfg(feature = "dim3")]
+pub const LOCKED_ROTATION_X: u32 = 1 << 3;
+/// Flag locking the rotation around the `y` axis (3D only).
+#[cfg(feature = "dim3")]
+pub const LOCKED_ROTATION_Y: u32 = 1 << 4;
+/// Flag locking the rotation around the `z` axis (the only rotation axis in 2D).
+pub const LOCKED_ROTATION_Z: u32 = 1 << 5;
+
+/// Flag enabling continuous collision detection for a dynamic body.
+pub const BODY_FLAG_CCD_ENABLED: u32 = 1 << 0;
+
+/// Per-body dynamics parameters of a free rigid-body, mirroring rapier's
+/// `RigidBody` settings.
+///
+/// Only dynamic bodies carry non-default values: other bodies ignore gravity,
+/// damping and locks anyway.
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    not(target_arch_is_gpu),
+    derive(bytemuck::Pod, bytemuck::Zeroable, Debug)
+)]
+#[repr(C)]
+pub struct BodyParams {
+    /// Multiplier applied to the gravity affecting this body.
+    pub gravity_scale: f32,
+    /// Damping coefficient of the linear velocity.
+    pub linear_damping: f32,
+    /// Damping coefficient of the angular velocity.
+    pub angular_damping: f32,
+    /// Degrees of freedom locked for this body, a combination of the `LOCKED_*` flags.
+    pub locked_axes: u32,
+    /// Dominance group, in `[-127, 127]`. When two dynamic bodies touch, the one with
+    /// the highest dominance group behaves as if it had an infinite mass.
+    pub dominance_group: i32,
+    /// A combination of the `BODY_FLAG_*` flags.
+    pub flags: u32,
+    /// Linear velocity (in length units per second) below which the body can
+    /// fall asleep. Negative if the body never sleeps.
+    pub sleep_linear_threshold: f32,
+    /// Angular velocity below which the body can fall asleep. Negative if the
+    /// body never sleeps.
+    pub sleep_angular_threshold: f32,
+    /// Time the body must stay below both sleep thresholds before falling asleep.
+    pub time_until_sleep: f32,
+    pub padding: [u32; 3],
+}
+
+impl Default for BodyParams {
+    fn default() -> Self {
+        Self {
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            locked_axes: 0,
+            dominance_group: 0,
+            flags: 0,
+            // Same defaults as rapier's `RigidBodyActivation`.
+            sleep_linear_threshold: 0.4,
+            sleep_angular_threshold: 0.5,
+            time_until_sleep: 2.0,
+            padding: [0; 3],
+        }
+    }
+}
+
+impl BodyParams {
+    /// Is continuous collision detection enabled for this body?
+    pub fn is_ccd_enabled(&self) -> bool {
+        self.flags & BODY_FLAG_CCD_ENABLED != 0
+    }
+
+    /// Applies the linear and angular damping to `vels` over a timestep `dt`.
+    ///
+    /// Same (implicit) damping model as rapier.
+    pub fn apply_damping(&self, vels: &mut Velocity, dt: f32) {
+        vels.linear *= 1.0 / (1.0 + dt * self.linear_damping);
+        vels.angular *= 1.0 / (1.0 + dt * self.angular_damping);
+    }
+
+    /// Zeroes the velocity components along the axes locked for this body.
+    pub fn lock_velocity(&self, vels: &mut Velocity) {
+        if self.locked_axes & LOCKED_TRANSLATION_X != 0 {
+            vels.linear.x = 0.0;
+        }
+        if self.locked_axes & LOCKED_TRANSLATION_Y != 0 {
+            vels.linear.y = 0.0;
+        }
+        #[cfg(feature = "dim2")]
+        if self.locked_axes & LOCKED_ROTATION_Z != 0 {
+            vels.angular = 0.0;
+        }
+        #[cfg(feature = "dim3")]
+        {
+            if self.locked_axes & LOCKED_TRANSLATION_Z != 0 {
+                vels.linear.z = 0.0;
+            }
+            if self.locked_axes & LOCKED_ROTATION_X != 0 {
+                vels.angular.x = 0.0;
+            }
+            if self.locked_axes & LOCKED_ROTATION_Y != 0 {
+                vels.angular.y = 0.0;
+            }
+            if self.locked_axes & LOCKED_ROTATION_Z != 0 {
+                vels.angular.z = 0.0;
+            }
+        }
+    }
+}
+
 /// The mass-properties of a rigid-body in local (body-space) coordinates.
 #[derive(Clone, Copy)]
 #[cfg_attr(
@@ -74,7 +210,7 @@ impl LocalMassProperties {
             inv_inertia: self.inv_inertia,
             inv_mass: self.inv_mass,
             com: world_com,
-            padding1: 0,
+            dominance_group: 0,
         }
     }
 
@@ -95,7 +231,7 @@ impl LocalMassProperties {
             inv_mass: self.inv_mass,
             com: world_com,
             padding0: 0,
-            padding1: 0,
+            dominance_group: 0,
         }
     }
 }
@@ -121,7 +257,8 @@ pub struct WorldMassProperties {
     /// The rigid-body's center of mass in world-space coordinates.
     pub com: Vector,
 
-    pub padding1: u32,
+    /// The rigid-body's dominance group (see [`BodyParams::dominance_group`]).
+    pub dominance_group: i32,
 
     #[cfg(feature = "dim2")]
     /// The inverse inertia tensor in world space (scalar in 2D).
@@ -129,6 +266,80 @@ pub struct WorldMassProperties {
 }
 
 impl WorldMassProperties {
+    /// Zeroes the inverse mass and inverse inertia along the axes locked by
+    /// `locked_axes` (a combination of the `LOCKED_*` flags).
+    ///
+    /// Mirrors rapier's effective (world-space) mass properties: rotation locks
+    /// zero the corresponding rows and columns of the world inverse inertia.
+    pub fn lock_axes(&mut self, locked_axes: u32) {
+        if locked_axes & LOCKED_TRANSLATION_X != 0 {
+            self.inv_mass.x = 0.0;
+        }
+        if locked_axes & LOCKED_TRANSLATION_Y != 0 {
+            self.inv_mass.y = 0.0;
+        }
+        #[cfg(feature = "dim2")]
+        if locked_axes & LOCKED_ROTATION_Z != 0 {
+            self.inv_inertia = 0.0;
+        }
+        #[cfg(feature = "dim3")]
+        {
+            if locked_axes & LOCKED_TRANSLATION_Z != 0 {
+                self.inv_mass.z = 0.0;
+            }
+            // Zero the rows and columns of the locked rotation axes.
+            let free = |flag: u32| if locked_axes & flag != 0 { 0.0 } else { 1.0 };
+            let mask = Vec4::new(
+                free(LOCKED_ROTATION_X),
+                free(LOCKED_ROTATION_Y),
+                free(LOCKED_ROTATION_Z),
+                1.0,
+            );
+            self.inv_inertia.x_axis *= mask * mask.x;
+            self.inv_inertia.y_axis *= mask * mask.y;
+            self.inv_inertia.z_axis *= mask * mask.z;
+        }
+    }
+
+    /// Zeroes the inverse mass and inverse inertia, making the body behave as if
+    /// it had an infinite mass.
+    pub fn make_infinite(&mut self) {
+        self.inv_mass = Vector::ZERO;
+        #[cfg(feature = "dim2")]
+        {
+            self.inv_inertia = 0.0;
+        }
+        #[cfg(feature = "dim3")]
+        {
+            self.inv_inertia = Mat4::ZERO;
+        }
+    }
+
+    /// Does this body react to impulses along at least one degree of freedom?
+    ///
+    /// Unlike checking `inv_mass` alone, this stays `true` for dynamic bodies with
+    /// all their translations locked.
+    #[inline]
+    pub fn is_dynamic(&self) -> bool {
+        #[cfg(feature = "dim2")]
+        return self.inv_mass != Vector::ZERO || self.inv_inertia != 0.0;
+        #[cfg(feature = "dim3")]
+        return self.inv_mass != Vector::ZERO || self.inv_inertia != Mat4::ZERO;
+    }
+
+    /// The dominance group used to resolve contacts.
+    ///
+    /// Mirrors rapier's `effective_dominance_group`: bodies that don't react to
+    /// impulses (fixed, kinematic or sleeping) dominate every dynamic body.
+    #[inline]
+    pub fn effective_dominance_group(&self) -> i32 {
+        if self.is_dynamic() {
+            self.dominance_group
+        } else {
+            i8::MAX as i32 + 1
+        }
+    }
+
     #[inline]
     pub fn inv_inertia_mul(&self, v: AngVector) -> AngVector {
         #[cfg(feature = "dim2")]
@@ -150,7 +361,7 @@ impl Default for WorldMassProperties {
             #[cfg(feature = "dim3")]
             padding0: 0,
             com: Vector::ZERO,
-            padding1: 0,
+            dominance_group: 0,
             #[cfg(feature = "dim2")]
             inv_inertia: 1.0,
         }
@@ -164,14 +375,23 @@ impl Default for WorldMassProperties {
 pub struct Impulse {
     /// Linear impulse component (change in linear momentum).
     pub linear: Vector,
+    #[cfg(feature = "dim3")]
+    pub padding0: u32,
 
     /// Angular impulse component (change in angular momentum / torque impulse).
     pub angular: AngVector,
+    pub padding1: u32,
 }
 
 impl Impulse {
     pub fn new(linear: Vector, angular: AngVector) -> Self {
-        Self { linear, angular }
+        Self {
+            linear,
+            angular,
+            #[cfg(feature = "dim3")]
+            padding0: 0,
+            padding1: 0,
+        }
     }
 }
 
@@ -182,14 +402,23 @@ impl Impulse {
 pub struct Force {
     /// Linear force component.
     pub linear: Vector,
+    #[cfg(feature = "dim3")]
+    pub padding0: u32,
 
     /// Angular force component (torque).
     pub angular: AngVector,
+    pub padding1: u32,
 }
 
 impl Force {
     pub fn new(linear: Vector, angular: AngVector) -> Self {
-        Self { linear, angular }
+        Self {
+            linear,
+            angular,
+            #[cfg(feature = "dim3")]
+            padding0: 0,
+            padding1: 0,
+        }
     }
 
     /// Integrates forces over a timestep to compute velocity changes (explicit Euler).
@@ -0,0 +1,122 @@
+//! Sparse runtime writes to the rigid-body state.
+//!
+//! The host queues per-body updates (teleportation, velocity changes, impulses
+//! and persistent forces) and scatters them into the body buffers with a single
+//! dispatch before the next step.
+
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::macros::{spirv, spirv_bindgen};
+
+use crate::Pose;
+
+use super::body::{
+    BODY_TYPE_DYNAMIC, BODY_TYPE_KINEMATIC_VELOCITY, BodyParams, Force, Impulse,
+    LocalMassProperties, Velocity,
+};
+use super::islands::BodyActivation;
+
+/// The update replaces the body pose (and kinematic target) with [`BodyUpdate::pose`].
+pub const BODY_UPDATE_POSE: u32 = 1 << 0;
+/// The update replaces the body velocity with [`BodyUpdate::velocity`]. Only
+/// applies to dynamic and velocity-based kinematic bodies.
+pub const BODY_UPDATE_VELOCITY: u32 = 1 << 1;
+/// The update applies [`BodyUpdate::impulse`] at the body center of mass. Only
+/// applies to dynamic bodies.
+pub const BODY_UPDATE_IMPULSE: u32 = 1 << 2;
+/// The update clears the persistent force of the body before adding [`BodyUpdate::force`].
+pub const BODY_UPDATE_RESET_FORCE: u32 = 1 << 3;
+/// The update adds [`BodyUpdate::force`] to the persistent force of the body.
+/// Only applies to dynamic bodies.
+pub const BODY_UPDATE_ADD_FORCE: u32 = 1 << 4;
+
+/// A sparse write to the state of a single rigid-body.
+///
+/// At most one update per body is dispatched at once: the host merges all the
+/// writes queued for the same body between two steps. They are applied in the
+/// order pose, velocity, impulse, then force.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct BodyUpdate {
+    /// The new body-origin world pose, if [`BODY_UPDATE_POSE`] is set.
+    pub pose: Pose,
+    /// The new velocity, if [`BODY_UPDATE_VELOCITY`] is set.
+    pub velocity: Velocity,
+    /// The impulse to apply, if [`BODY_UPDATE_IMPULSE`] is set.
+    pub impulse: Impulse,
+    /// The force to accumulate, if [`BODY_UPDATE_ADD_FORCE`] is set.
+    pub force: Force,
+    /// Global body slot (the collider offset of the batch, plus the local slot).
+    pub body_id: u32,
+    /// Combination of the `BODY_UPDATE_*` flags.
+    pub flags: u32,
+    pub padding: [u32; 2],
+}
+
+/// Scatters the queued [`BodyUpdate`]s into the body buffers.
+///
+/// Dispatched with one thread per update, before the world mass properties and
+/// collider poses are refreshed so they see the new poses. Any update wakes the
+/// body up. As in rapier, velocity writes are ignored by fixed and position-based
+/// kinematic bodies, and impulses and forces by every non-dynamic body.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_apply_body_updates(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] updates: &[BodyUpdate],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] body_poses: &mut [Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] vels: &mut [Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] kinematic_targets: &mut [Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] forces: &mut [Force],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)]
+    local_mprops: &[LocalMassProperties],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 7)] activations: &mut [BodyActivation],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 8)] body_types: &[u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 9)] num_updates: &u32,
+) {
+    let i = invocation_id.x;
+    if i >= *num_updates {
+        return;
+    }
+
+    let update = *updates.at(i as usize);
+    let id = update.body_id as usize;
+    *activations.at_mut(id) = BodyActivation::default();
+    let body_type = body_types.read(id);
+    let is_dynamic = body_type == BODY_TYPE_DYNAMIC;
+
+    if update.flags & BODY_UPDATE_POSE != 0 {
+        *body_poses.at_mut(id) = update.pose;
+        // Keep position-based kinematic bodies from moving back to their old target.
+        *kinematic_targets.at_mut(id) = update.pose;
+    }
+
+    if update.flags & BODY_UPDATE_VELOCITY != 0
+        && (is_dynamic || body_type == BODY_TYPE_KINEMATIC_VELOCITY)
+    {
+        *vels.at_mut(id) = update.velocity;
+    }
+
+    if update.flags & BODY_UPDATE_IMPULSE != 0 && is_dynamic {
+        // The world mass properties are recomputed from the (possibly just
+        // updated) pose, since they are only refreshed later in the step.
+        let mut mprops = local_mprops.at(id).to_world(body_poses.at(id));
+        mprops.lock_axes(body_params.at(id).locked_axes);
+        let vel = *vels.at(id);
+        *vels.at_mut(id) = vel.apply_impulse(&mprops, &update.impulse);
+    }
+
+    if update.flags & BODY_UPDATE_RESET_FORCE != 0 {
+        *forces.at_mut(id) = Force::default();
+    }
+
+    if update.flags & BODY_UPDATE_ADD_FORCE != 0 && is_dynamic {
+        let force = *forces.at(id);
+        *forces.at_mut(id) = Force::new(
+            force.linear + update.force.linear,
+            force.angular + update.force.angular,
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! Continuous collision detection (CCD) compute shader kernels.
+//!
+//! Bodies with [`BODY_FLAG_CCD_ENABLED`] are protected against tunnelling in
+//! four stages:
+//! 1. [`gpu_ccd_sweeps`] computes, before the broad phase, the motion the
+//!    colliders of CCD bodies are expected to cover during the step. The broad
+//!    phase enlarges their AABBs accordingly (see [`CcdSweep::sweep_aabb`]), so
+//!    pairs that will only meet mid-step are still found.
+//! 2. [`gpu_ccd_init`] and [`gpu_ccd_pairs`] record, before the solver runs, the
+//!    body poses at the start of the step and the broad-phase pairs involving a
+//!    CCD body.
+//! 3. After the solver, [`gpu_ccd_toi`] computes the time of impact of each of
+//!    these pairs along their motion over the step, using conservative
+//!    advancement.
+//! 4. [`gpu_ccd_clamp_motion`] moves every CCD body back to its pose at its
+//!    earliest time of impact. Its velocity is left untouched: the contact is
+//!    picked up by the next step's narrow phase.
+//!
+//! The time of impact only accounts for the linear motion of the colliders, and
+//! pairs of composite shapes (triangle meshes, polylines, heightfields) are
+//! ignored.
+
+use khal_std::glamx::{UVec3, UVec4};
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::iter::StepRng;
+use khal_std::macros::{spirv, spirv_bindgen};
+use khal_std::sync::{atomic_add_u32, atomic_max_u32};
+
+use crate::bounding_volumes::Aabb;
+use crate::broad_phase::{CollisionPair, PREDICTION};
+use crate::queries::{ColliderMaterial, cast_on_shape};
+use crate::shapes::Shape;
+use crate::utils::BatchIndices;
+use crate::{PaddedVector, Pose, Vector};
+
+use super::body::{BodyParams, Velocity, WorldMassProperties};
+use super::kinematic::angular_displacement;
+use super::sim_params::RbdSimParams;
+
+const WORKGROUP_SIZE: u32 = 64;
+/// Value of the body indices of a [`gpu_ccd_pairs`] output pair when the body
+/// doesn't have CCD enabled (and must not be clamped).
+const CCD_NO_BODY: u32 = u32::MAX;
+
+/// The motion of a collider over one step, used to enlarge its AABB.
+///
+/// All zeros for the colliders of bodies without CCD.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(
+    not(target_arch_is_gpu),
+    derive(bytemuck::Pod, bytemuck::Zeroable, Debug)
+)]
+#[repr(C)]
+pub struct CcdSweep {
+    /// The linear displacement of the body over the step.
+    pub translation: Vector,
+    /// The angle the body rotates by over the step.
+    pub angle: f32,
+    #[cfg(feature = "dim2")]
+    pub padding0: u32,
+    /// The world-space center of mass of the body, center of its rotation.
+    pub center: Vector,
+    #[cfg(feature = "dim3")]
+    pub padding0: u32,
+}
+
+impl CcdSweep {
+    /// The AABB containing `aabb` at every instant of the motion.
+    ///
+    /// The rotation is accounted for by loosening the AABB by the distance its
+    /// furthest point can travel around [`Self::center`].
+    pub fn sweep_aabb(&self, aabb: &Aabb) -> Aabb {
+        let end = Aabb::new(aabb.mins + self.translation, aabb.maxs + self.translation);
+        let radius = (aabb.center() - self.center).length() + aabb.half_extents().length();
+        aabb.merged(&end).loosened(self.angle * radius)
+    }
+}
+
+/// Converts a time of impact in `[0, 1]` into a key whose maximum is the
+/// smallest time of impact.
+///
+/// The bit patterns of positive floats are ordered like the floats themselves,
+/// so an atomic max over the keys computes the earliest impact.
+#[inline]
+fn toi_key(toi: f32) -> u32 {
+    u32::MAX - toi.to_bits()
+}
+
+/// Inverse of [`toi_key`].
+#[inline]
+fn toi_from_key(key: u32) -> f32 {
+    f32::from_bits(u32::MAX - key)
+}
+
+/// Computes the sweep of every collider attached to a CCD body, from its
+/// body's current velocity.
+///
+/// Run after the mass properties update, before the broad phase.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_ccd_sweeps(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] collider_parent: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] vels: &[Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] mprops: &[WorldMassProperties],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] all_params: &[RbdSimParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] sweeps: &mut [CcdSweep],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let params = all_params.at(batch_id as usize);
+    // `params.dt` is the substep length.
+    let step_dt = params.dt * params.num_solver_iterations as f32;
+
+    let num_colliders = batch_ids.colliders_len(batch_id);
+    let collider_parent = batch_ids.coll_batch(batch_id, collider_parent);
+    let vels = batch_ids.coll_batch(batch_id, vels);
+    let mprops = batch_ids.coll_batch(batch_id, mprops);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
+    let mut sweeps = batch_ids.coll_batch_mut(batch_id, sweeps);
+
+    for i in StepRng::new(invocation_id.x..num_colliders, num_threads) {
+        let body = collider_parent[i as usize] as usize;
+        let mut sweep = CcdSweep::default();
+        if body_params[body].is_ccd_enabled() {
+            let vel = vels[body];
+            sweep.translation = vel.linear * step_dt;
+            #[cfg(feature = "dim2")]
+            {
+                sweep.angle = crate::abs(vel.angular) * step_dt;
+            }
+            #[cfg(feature = "dim3")]
+            {
+                sweep.angle = vel.angular.length() * step_dt;
+            }
+            sweep.center = mprops[body].com;
+        }
+        sweeps[i as usize] = sweep;
+    }
+}
+
+/// Resets the CCD pair counters. One thread per batch.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_ccd_reset_pairs(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ccd_pairs_len: &mut [u32],
+) {
+    let batch_id = invocation_id.x as usize;
+    if batch_id < ccd_pairs_len.len() {
+        ccd_pairs_len.write(batch_id, 0);
+    }
+}
+
+/// Saves the body poses at the start of the step and resets their time of
+/// impact to the end of the step.
+///
+/// Run before the solver.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_ccd_init(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] body_poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] start_poses: &mut [Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] toi_keys: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let body_poses = batch_ids.coll_batch(batch_id, body_poses);
+    let mut start_poses = batch_ids.coll_batch_mut(batch_id, start_poses);
+    let mut toi_keys = batch_ids.coll_batch_mut(batch_id, toi_keys);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        start_poses[i as usize] = body_poses[i as usize];
+        toi_keys[i as usize] = toi_key(1.0);
+    }
+}
+
+/// Collects the broad-phase pairs involving a CCD body, as
+/// `(collider1, collider2, body1, body2)` where the body index is
+/// [`CCD_NO_BODY`] if that body doesn't have CCD enabled.
+///
+/// Pairs involving a sensor are skipped: sensors never stop anything.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_ccd_pairs(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] collision_pairs: &[CollisionPair],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] collision_pairs_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] collider_parent: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)]
+    collider_materials: &[ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] ccd_pairs: &mut [UVec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] ccd_pairs_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let collision_pairs_batch_capacity = batch_ids.collision_pairs_batch_capacity;
+
+    let collision_pairs = batch_ids.collision_pairs_batch(batch_id, collision_pairs);
+    let collider_parent = batch_ids.coll_batch(batch_id, collider_parent);
+    let collider_materials = batch_ids.coll_batch(batch_id, collider_materials);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
+    let mut ccd_pairs = batch_ids.collision_pairs_batch_mut(batch_id, ccd_pairs);
+    let ccd_pairs_len = ccd_pairs_len.at_mut(batch_id as usize);
+
+    let len = collision_pairs_len
+        .read(batch_id as usize)
+        .min(collision_pairs_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let colliders = collision_pairs[i as usize].colliders;
+        let body1 = collider_parent[colliders.x as usize];
+        let body2 = collider_parent[colliders.y as usize];
+        let ccd1 = body_params[body1 as usize].is_ccd_enabled();
+        let ccd2 = body_params[body2 as usize].is_ccd_enabled();
+        if body1 == body2 || (!ccd1 && !ccd2) {
+            continue;
+        }
+
+        if collider_materials[colliders.x as usize].is_sensor()
+            || collider_materials[colliders.y as usize].is_sensor()
+        {
+            continue;
+        }
+
+        // Can't overflow: there are at most as many CCD pairs as collision pairs.
+        let target_index = atomic_add_u32(ccd_pairs_len, 1) as usize;
+        ccd_pairs.write(
+            target_index,
+            UVec4::new(
+                colliders.x,
+                colliders.y,
+                if ccd1 { body1 } else { CCD_NO_BODY },
+                if ccd2 { body2 } else { CCD_NO_BODY },
+            ),
+        );
+    }
+}
+
+/// Computes the time of impact of every CCD pair along the linear motion of
+/// its colliders from `collider_start_poses` to `collider_end_poses`, and keeps the earliest one
+/// of each CCD body into `toi_keys`.
+///
+/// Pairs moving by less than the contact prediction distance relative to each
+/// other are skipped: their contacts were already found by the narrow phase.
+/// So are pairs already touching at the start of the step.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_ccd_toi(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ccd_pairs: &[UVec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] ccd_pairs_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] collider_start_poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] collider_end_poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] shapes: &[Shape],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] toi_keys: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 0)] vertices: &[PaddedVector],
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 1)] indices: &[u32],
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let collision_pairs_batch_capacity = batch_ids.collision_pairs_batch_capacity;
+
+    let ccd_pairs = batch_ids.collision_pairs_batch(batch_id, ccd_pairs);
+    let start_poses = batch_ids.coll_batch(batch_id, collider_start_poses);
+    let end_poses = batch_ids.coll_batch(batch_id, collider_end_poses);
+    let shapes = batch_ids.coll_batch(batch_id, shapes);
+    let mut toi_keys = batch_ids.coll_batch_mut(batch_id, toi_keys);
+
+    let len = ccd_pairs_len
+        .read(batch_id as usize)
+        .min(collision_pairs_batch_capacity);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let pair = ccd_pairs[i as usize];
+        let (c1, c2) = (pair.x as usize, pair.y as usize);
+        let start1 = start_poses[c1];
+        let start2 = start_poses[c2];
+        let disp1 = end_poses[c1].translation - start1.translation;
+        let disp2 = end_poses[c2].translation - start2.translation;
+        if (disp2 - disp1).length() <= PREDICTION {
+            continue;
+        }
+
+        // Cast the convex shape against the other one, which may be composite.
+        let sub1 = shapes[c1].pfm_subshape();
+        let sub2 = shapes[c2].pfm_subshape();
+        let toi = if sub2.valid {
+            cast_on_shape(
+                start1.inverse() * start2,
+                start1.rotation.inverse() * (disp2 - disp1),
+                &shapes[c1],
+                &sub2,
+                1.0,
+                vertices,
+                indices,
+            )
+            .toi
+        } else if sub1.valid {
+            cast_on_shape(
+                start2.inverse() * start1,
+                start2.rotation.inverse() * (disp1 - disp2),
+                &shapes[c2],
+                &sub1,
+                1.0,
+                vertices,
+                indices,
+            )
+            .toi
+        } else {
+            // Composite-composite pairs are not supported.
+            continue;
+        };
+
+        // A zero time of impact means the shapes were already touching: that
+        // contact is handled by the solver.
+        if toi > 0.0 && toi < 1.0 {
+            let key = toi_key(toi);
+            if pair.z != CCD_NO_BODY {
+                atomic_max_u32(toi_keys.at_mut(pair.z as usize), key);
+            }
+            if pair.w != CCD_NO_BODY {
+                atomic_max_u32(toi_keys.at_mut(pair.w as usize), key);
+            }
+        }
+    }
+}
+
+/// Moves every CCD body with an impact back to its pose at its time of impact,
+/// interpolated between its pose at the start of the step and its solved pose.
+///
+/// Run after the solver (and the kinematic snap).
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_ccd_clamp_motion(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] start_poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] toi_keys: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_poses: &mut [Pose],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let start_poses = batch_ids.coll_batch(batch_id, start_poses);
+    let toi_keys = batch_ids.coll_batch(batch_id, toi_keys);
+    let mut body_poses = batch_ids.coll_batch_mut(batch_id, body_poses);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let idx = i as usize;
+        let toi = toi_from_key(toi_keys[idx]);
+        if toi < 1.0 {
+            let start = start_poses[idx];
+            let end = body_poses[idx];
+            let rotation = angular_displacement(&start, &end) * toi;
+            #[cfg(feature = "dim2")]
+            let rotation = crate::rotation_from_angle(rotation);
+            #[cfg(feature = "dim3")]
+            let rotation = crate::rotation_from_scaled_axis(rotation);
+            body_poses[idx] = Pose::from_parts(
+                start.translation + (end.translation - start.translation) * toi,
+                rotation * start.rotation,
+            );
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Per-environment snapshots of the rigid-body state.
+//!
+//! The host flags, for every batch, whether its state must be captured into
+//! its snapshot and/or restored from it. A single dispatch then copies the
+//! flagged batches in place, so thousands of environments can be reset at
+//! different times without rebuilding the simulation.
+//!
+//! Besides the body states, the snapshots hold the sleep state, the contact
+//! warmstart (the constraints solved by the last step, which the next step
+//! matches its contacts against) and whether each impulse joint is enabled.
+
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::iter::StepRng;
+use khal_std::macros::{spirv, spirv_bindgen};
+
+use crate::Pose;
+use crate::utils::{BatchIndices, SliceMut};
+
+use super::body::{Force, Velocity};
+use super::constraint::{TwoBodyConstraint, TwoBodyConstraintBuilder};
+use super::islands::BodyActivation;
+use super::joint::ImpulseJoint;
+
+/// The batch state is copied into its snapshot.
+pub const ENV_SNAPSHOT_CAPTURE: u32 = 1 << 0;
+/// The batch state is replaced by its snapshot. Applied after
+/// [`ENV_SNAPSHOT_CAPTURE`] if both are set.
+pub const ENV_SNAPSHOT_RESTORE: u32 = 1 << 1;
+/// The contact warmstart of the batch isn't restored along with the rest of
+/// its snapshot, e.g. because the contact buffers were reallocated since it
+/// was captured. The restored bodies then start without any old constraint.
+pub const ENV_SNAPSHOT_DROP_WARMSTART: u32 = 1 << 2;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The snapshotted state of a single rigid-body.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct BodySnapshot {
+    /// The body-origin world pose.
+    pub pose: Pose,
+    /// The body velocity.
+    pub velocity: Velocity,
+    /// The persistent user force.
+    pub force: Force,
+    /// The sleep timer and sleeping flag.
+    pub activation: BodyActivation,
+    /// The entry of the body in the prefix sum of the old constraint counts.
+    pub old_constraint_count: u32,
+    pub padding: u32,
+}
+
+/// Captures and restores the body snapshots of the batches flagged in `ops`.
+///
+/// Dispatched with one thread row per batch. Restored bodies get their snapshot
+/// sleep state back, and their old constraint counts unless the batch is flagged
+/// with [`ENV_SNAPSHOT_DROP_WARMSTART`]. The old constraints themselves are
+/// copied by [`gpu_copy_contact_snapshots`].
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_copy_body_snapshots(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ops: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] snapshots: &mut [BodySnapshot],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_poses: &mut [Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] vels: &mut [Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] kinematic_targets: &mut [Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] forces: &mut [Force],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] activations: &mut [BodyActivation],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 7)]
+    old_body_constraint_counts: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 8)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let op = *ops.at(batch_id as usize);
+    if op == 0 {
+        return;
+    }
+
+    let mut snapshots = batch_ids.coll_batch_mut(batch_id, snapshots);
+    let mut body_poses = batch_ids.coll_batch_mut(batch_id, body_poses);
+    let mut vels = batch_ids.coll_batch_mut(batch_id, vels);
+    let mut kinematic_targets = batch_ids.coll_batch_mut(batch_id, kinematic_targets);
+    let mut forces = batch_ids.coll_batch_mut(batch_id, forces);
+    let mut activations = batch_ids.coll_batch_mut(batch_id, activations);
+    let mut old_body_constraint_counts =
+        batch_ids.coll_batch_mut(batch_id, old_body_constraint_counts);
+
+    for i in StepRng::new(invocation_id.x..batch_ids.bodies_len(batch_id), num_threads) {
+        let idx = i as usize;
+
+        if op & ENV_SNAPSHOT_CAPTURE != 0 {
+            snapshots[idx] = BodySnapshot {
+                pose: body_poses[idx],
+                velocity: vels[idx],
+                force: forces[idx],
+                activation: activations[idx],
+                old_constraint_count: old_body_constraint_counts[idx],
+                padding: 0,
+            };
+        }
+
+        if op & ENV_SNAPSHOT_RESTORE != 0 {
+            let snapshot = snapshots[idx];
+            body_poses[idx] = snapshot.pose;
+            // Keep position-based kinematic bodies from moving back to their old target.
+            kinematic_targets[idx] = snapshot.pose;
+            vels[idx] = snapshot.velocity;
+            forces[idx] = snapshot.force;
+            activations[idx] = snapshot.activation;
+            old_body_constraint_counts[idx] = if op & ENV_SNAPSHOT_DROP_WARMSTART == 0 {
+                snapshot.old_constraint_count
+            } else {
+                // An all-zero prefix sum leaves every body without old constraints.
+                0
+            };
+        }
+    }
+}
+
+/// Captures and restores the contact warmstart of the batches flagged in `ops`.
+///
+/// Dispatched with one thread row per batch, over the contact capacity. The
+/// snapshot buffers are laid out like the old constraints, builders and
+/// per-body constraint ids. Batches flagged with [`ENV_SNAPSHOT_DROP_WARMSTART`]
+/// aren't restored.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_copy_contact_snapshots(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ops: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)]
+    constraint_snapshots: &mut [TwoBodyConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)]
+    builder_snapshots: &mut [TwoBodyConstraintBuilder],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)]
+    body_constraint_id_snapshots: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)]
+    old_constraints: &mut [TwoBodyConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)]
+    old_constraint_builders: &mut [TwoBodyConstraintBuilder],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] old_body_constraint_ids: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let op = *ops.at(batch_id as usize);
+    let capture = op & ENV_SNAPSHOT_CAPTURE != 0;
+    let restore = op & ENV_SNAPSHOT_RESTORE != 0 && op & ENV_SNAPSHOT_DROP_WARMSTART == 0;
+    if !capture && !restore {
+        return;
+    }
+
+    let mut constraint_snapshots = batch_ids.contact_batch_mut(batch_id, constraint_snapshots);
+    let mut builder_snapshots = batch_ids.contact_batch_mut(batch_id, builder_snapshots);
+    let mut old_constraints = batch_ids.contact_batch_mut(batch_id, old_constraints);
+    let mut old_constraint_builders =
+        batch_ids.contact_batch_mut(batch_id, old_constraint_builders);
+    // Each contact owns two entries of the per-body constraint ids.
+    let bci_start = batch_id as usize * 2 * batch_ids.contacts_batch_capacity as usize;
+    let mut body_constraint_id_snapshots = SliceMut(body_constraint_id_snapshots, bci_start);
+    let mut old_body_constraint_ids = SliceMut(old_body_constraint_ids, bci_start);
+
+    let len = batch_ids.contacts_batch_capacity;
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let idx = i as usize;
+
+        if capture {
+            constraint_snapshots[idx] = old_constraints[idx];
+            builder_snapshots[idx] = old_constraint_builders[idx];
+            body_constraint_id_snapshots[2 * idx] = old_body_constraint_ids[2 * idx];
+            body_constraint_id_snapshots[2 * idx + 1] = old_body_constraint_ids[2 * idx + 1];
+        }
+
+        if restore {
+            old_constraints[idx] = constraint_snapshots[idx];
+            old_constraint_builders[idx] = builder_snapshots[idx];
+            old_body_constraint_ids[2 * idx] = body_constraint_id_snapshots[2 * idx];
+            old_body_constraint_ids[2 * idx + 1] = body_constraint_id_snapshots[2 * idx + 1];
+        }
+    }
+}
+
+/// Captures and restores whether the impulse joints of the batches flagged in
+/// `ops` are enabled, so that the joints broken since the capture are repaired
+/// by the restoration.
+///
+/// Dispatched with one thread row per batch. `enabled_snapshots` is laid out
+/// like the joints.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_copy_joint_snapshots(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ops: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] enabled_snapshots: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] joints: &mut [ImpulseJoint],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let op = *ops.at(batch_id as usize);
+    if op == 0 {
+        return;
+    }
+
+    let mut enabled_snapshots = batch_ids.impulse_joints_batch_mut(batch_id, enabled_snapshots);
+    let mut joints = batch_ids.impulse_joints_batch_mut(batch_id, joints);
+
+    let len = batch_ids.impulse_joints_len(batch_id);
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let idx = i as usize;
+
+        if op & ENV_SNAPSHOT_CAPTURE != 0 {
+            enabled_snapshots[idx] = joints[idx].enabled;
+        }
+
+        if op & ENV_SNAPSHOT_RESTORE != 0 {
+            let mut joint = joints[idx];
+            joint.enabled = enabled_snapshots[idx];
+            joints[idx] = joint;
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Dense per-environment observation and action tensors.
+//!
+//! An observation spec is a list of [`EnvTerm`]s, each gathering a few floats
+//! of the simulation state (body poses or velocities, multibody joint
+//! coordinates or velocities, contact flags) into its columns of a row-major
+//! `[num_batches, row_len]` tensor. An action spec is a list of [`EnvTerm`]s,
+//! each scattering one column of such a tensor into the simulation inputs
+//! (multibody motor targets and efforts, body forces, link wrenches).
+
+use glamx::Vec4;
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::macros::{spirv, spirv_bindgen};
+
+#[cfg(feature = "dim3")]
+use crate::DIM;
+use crate::Pose;
+use crate::queries::IndexedManifold;
+use crate::utils::BatchIndices;
+
+use super::body::{Force, Velocity};
+use super::islands::BodyActivation;
+#[cfg(feature = "dim3")]
+use super::multibody::WS_EXT_TORQUE;
+use super::multibody::{MultibodyLinkStatic, WS_EXT_FORCE, WsAddr, ws_coord, ws_set_component};
+
+/// Observes the body-origin world pose of the body `id`: its translation, then
+/// its rotation (`re`, `im` in 2D, quaternion `x`, `y`, `z`, `w` in 3D).
+pub const OBS_BODY_POSE: u32 = 0;
+/// Observes the linear then angular velocity of the body `id`.
+pub const OBS_BODY_VELOCITY: u32 = 1;
+/// Observes the coordinate `axis` of the joint of the multibody link `id`.
+pub const OBS_JOINT_POSITION: u32 = 2;
+/// Observes the generalized velocity of the multibody DOF `id`.
+pub const OBS_JOINT_VELOCITY: u32 = 3;
+/// Observes `1.0` if the body `id` has contact points, `0.0` otherwise.
+pub const OBS_CONTACT_FLAG: u32 = 4;
+
+/// Number of columns of an [`OBS_BODY_POSE`] term.
+#[cfg(feature = "dim2")]
+pub const OBS_BODY_POSE_LEN: u32 = 4;
+/// Number of columns of an [`OBS_BODY_POSE`] term.
+#[cfg(feature = "dim3")]
+pub const OBS_BODY_POSE_LEN: u32 = 7;
+/// Number of columns of an [`OBS_BODY_VELOCITY`] term.
+#[cfg(feature = "dim2")]
+pub const OBS_BODY_VELOCITY_LEN: u32 = 3;
+/// Number of columns of an [`OBS_BODY_VELOCITY`] term.
+#[cfg(feature = "dim3")]
+pub const OBS_BODY_VELOCITY_LEN: u32 = 6;
+
+/// Sets the target position of the motor `axis` of the multibody link `id`.
+pub const ACT_MOTOR_POSITION: u32 = 0;
+/// Sets the target velocity of the motor `axis` of the multibody link `id`.
+pub const ACT_MOTOR_VELOCITY: u32 = 1;
+/// Applies a signed effort (force or torque) with the motor `axis` of the
+/// multibody link `id`, configured as a saturated velocity motor.
+pub const ACT_JOINT_EFFORT: u32 = 2;
+/// Sets the component `axis` (linear axes first, then angular ones) of the
+/// persistent force of the body `id`, waking it up.
+pub const ACT_BODY_FORCE: u32 = 3;
+/// Sets the component `axis` (linear axes first, then angular ones) of the
+/// world-space external wrench of the multibody link `id`.
+pub const ACT_LINK_WRENCH: u32 = 4;
+
+/// Target velocity of the motors driven by [`ACT_JOINT_EFFORT`]: never reached,
+/// so that the motor always applies its maximum force.
+pub const JOINT_EFFORT_MOTOR_VELOCITY: f32 = 1.0e6;
+
+/// One term of an observation or action spec.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct EnvTerm {
+    /// One of the `OBS_*` or `ACT_*` constants.
+    pub kind: u32,
+    /// The per-batch body slot, multibody link or DOF the term reads or writes.
+    pub id: u32,
+    /// The joint or wrench axis, for the kinds that need one.
+    pub axis: u32,
+    /// The first column of the term in a tensor row.
+    pub column: u32,
+}
+
+/// The shape of a spec and of its tensor.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct EnvTermsShape {
+    /// The number of terms of the spec.
+    pub num_terms: u32,
+    /// The number of columns of the tensor rows.
+    pub row_len: u32,
+    pub padding: [u32; 2],
+}
+
+/// Gathers the observation terms into the `[num_batches, row_len]` tensor
+/// `observations`.
+///
+/// Dispatched with one thread per term and batch.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_gather_observations(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] terms: &[EnvTerm],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] observations: &mut [f32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] vels: &[Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] links_workspace: &[Vec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] dof_state: &[f32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] contacts: &[IndexedManifold],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 7)] contacts_len: &[u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 8)] shape: &EnvTermsShape,
+    #[spirv(uniform, descriptor_set = 0, binding = 9)] batch_ids: &BatchIndices,
+) {
+    let term_id = invocation_id.x;
+    let batch_id = invocation_id.y;
+    if term_id >= shape.num_terms {
+        return;
+    }
+
+    let term = *terms.at(term_id as usize);
+    let row = (batch_id * shape.row_len + term.column) as usize;
+    let body_id = batch_ids.coll_start(batch_id) + term.id as usize;
+
+    if term.kind == OBS_BODY_POSE {
+        let pose = *body_poses.at(body_id);
+        write_vector(observations, row, pose.translation);
+        #[cfg(feature = "dim2")]
+        {
+            *observations.at_mut(row + 2) = pose.rotation.re;
+            *observations.at_mut(row + 3) = pose.rotation.im;
+        }
+        #[cfg(feature = "dim3")]
+        {
+            *observations.at_mut(row + 3) = pose.rotation.x;
+            *observations.at_mut(row + 4) = pose.rotation.y;
+            *observations.at_mut(row + 5) = pose.rotation.z;
+            *observations.at_mut(row + 6) = pose.rotation.w;
+        }
+    } else if term.kind == OBS_BODY_VELOCITY {
+        let vel = *vels.at(body_id);
+        write_vector(observations, row, vel.linear);
+        #[cfg(feature = "dim2")]
+        {
+            *observations.at_mut(row + 2) = vel.angular;
+        }
+        #[cfg(feature = "dim3")]
+        write_vector(observations, row + 3, vel.angular);
+    } else if term.kind == OBS_JOINT_POSITION {
+        let a = WsAddr::new(0, batch_ids.num_batches, batch_id);
+        *observations.at_mut(row) = ws_coord(links_workspace, a, term.id, term.axis);
+    } else if term.kind == OBS_JOINT_VELOCITY {
+        // The velocities are the first section of `dof_state`.
+        *observations.at_mut(row) = *dof_state.at(batch_ids.mbi(batch_id, term.id as usize));
+    } else if term.kind == OBS_CONTACT_FLAG {
+        let contacts = batch_ids.contact_batch(batch_id, contacts);
+        let len = *contacts_len.at(batch_id as usize);
+        let mut flag = 0.0;
+        for i in 0..len {
+            let im = &contacts[i as usize];
+            if im.contact.len != 0 && (im.bodies.x == term.id || im.bodies.y == term.id) {
+                flag = 1.0;
+                break;
+            }
+        }
+        *observations.at_mut(row) = flag;
+    }
+}
+
+/// Scatters the `[num_batches, row_len]` tensor `actions` into the simulation
+/// inputs targeted by the action terms.
+///
+/// Dispatched with one thread per batch, running through the terms in order:
+/// terms writing to the same link or body never race, and the last one wins if
+/// several target the same input.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_scatter_actions(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] terms: &[EnvTerm],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] actions: &[f32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)]
+    links_static: &mut [MultibodyLinkStatic],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] links_workspace: &mut [Vec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] forces: &mut [Force],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] activations: &mut [BodyActivation],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] shape: &EnvTermsShape,
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let batch_id = invocation_id.x;
+    if batch_id >= batch_ids.num_batches {
+        return;
+    }
+
+    let a = WsAddr::new(0, batch_ids.num_batches, batch_id);
+
+    for t in 0..shape.num_terms {
+        let term = *terms.at(t as usize);
+        let value = *actions.at((batch_id * shape.row_len + term.column) as usize);
+
+        if term.kind == ACT_MOTOR_POSITION
+            || term.kind == ACT_MOTOR_VELOCITY
+            || term.kind == ACT_JOINT_EFFORT
+        {
+            let idx = batch_ids.mbi(batch_id, term.id as usize);
+            let mut stat = *links_static.at(idx);
+            let axis = term.axis as usize;
+            if term.kind == ACT_MOTOR_POSITION {
+                stat.data.motors[axis].target_pos = value;
+            } else if term.kind == ACT_MOTOR_VELOCITY {
+                stat.data.motors[axis].target_vel = value;
+            } else {
+                let sign = if value < 0.0 { -1.0 } else { 1.0 };
+                stat.data.motors[axis].target_vel = sign * JOINT_EFFORT_MOTOR_VELOCITY;
+                stat.data.motors[axis].max_force = value * sign;
+            }
+            *links_static.at_mut(idx) = stat;
+        } else if term.kind == ACT_BODY_FORCE {
+            let body_id = batch_ids.coll_start(batch_id) + term.id as usize;
+            let force = *forces.at(body_id);
+            *forces.at_mut(body_id) = with_force_component(force, term.axis, value);
+            *activations.at_mut(body_id) = BodyActivation::default();
+        } else if term.kind == ACT_LINK_WRENCH {
+            #[cfg(feature = "dim2")]
+            let (quad, i) = (WS_EXT_FORCE, term.axis);
+            #[cfg(feature = "dim3")]
+            let (quad, i) = if term.axis < DIM {
+                (WS_EXT_FORCE, term.axis)
+            } else {
+                (WS_EXT_TORQUE, term.axis - DIM)
+            };
+            ws_set_component(links_workspace, a, term.id, quad, i, value);
+        }
+    }
+}
+
+#[cfg(feature = "dim2")]
+#[inline]
+fn write_vector(out: &mut [f32], row: usize, v: glamx::Vec2) {
+    *out.at_mut(row) = v.x;
+    *out.at_mut(row + 1) = v.y;
+}
+
+#[cfg(feature = "dim3")]
+#[inline]
+fn write_vector(out: &mut [f32], row: usize, v: glamx::Vec3) {
+    *out.at_mut(row) = v.x;
+    *out.at_mut(row + 1) = v.y;
+    *out.at_mut(row + 2) = v.z;
+}
+
+/// `force` with its component `axis` (linear axes first, then angular ones)
+/// replaced by `value`.
+#[inline]
+fn with_force_component(force: Force, axis: u32, value: f32) -> Force {
+    let mut out = force;
+    if axis == 0 {
+        out.linear.x = value;
+    } else if axis == 1 {
+        out.linear.y = value;
+    } else {
+        #[cfg(feature = "dim2")]
+        {
+            out.angular = value;
+        }
+        #[cfg(feature = "dim3")]
+        {
+            if axis == 2 {
+                out.linear.z = value;
+            } else if axis == 3 {
+                out.angular.x = value;
+            } else if axis == 4 {
+                out.angular.y = value;
+            } else {
+                out.angular.z = value;
+            }
+        }
+    }
+    out
+}
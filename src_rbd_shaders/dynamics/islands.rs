@@ -0,0 +1,354 @@
+//! Island detection and sleeping compute shader kernels.
+//!
+//! Dynamic bodies at rest are put to sleep island by island, like rapier's
+//! `IslandManager` does. An island is a set of dynamic bodies linked by contacts
+//! or impulse joints: fixed and kinematic bodies don't propagate islands.
+//!
+//! Once the solver is done:
+//! 1. [`gpu_islands_init`] updates the sleep timer of every awake dynamic body
+//!    from its velocity, and gives every body its own island label.
+//! 2. [`gpu_islands_link_contacts`] and [`gpu_islands_link_joints`] merge the
+//!    labels of the bodies linked by a contact manifold (the same contact graph
+//!    the graph coloring works on) or by an impulse joint, and
+//!    [`gpu_islands_compress`] shortcuts the label chains. These are repeated
+//!    [`num_island_link_rounds`] times, after which all the bodies of an island
+//!    share the label of its largest body index.
+//! 3. [`gpu_islands_awake`] flags the islands containing a body not ready to
+//!    sleep.
+//! 4. [`gpu_islands_apply`] puts the bodies of the other islands to sleep, and
+//!    wakes up the sleeping bodies of the flagged islands.
+//!
+//! Sleeping bodies keep their contacts, but [`gpu_update_mprops`](super::gpu_update_mprops)
+//! gives them an infinite mass: the solver handles them like fixed bodies, so they
+//! don't get a graph color and their velocity stays zero. They are not excluded
+//! from the dispatches though: the collision detection, the constraints
+//! (including the ones between two sleeping bodies) and the integration still
+//! run over them, so sleeping keeps piles at rest steady but doesn't save GPU
+//! work yet. A sleeping body is woken up by a contact with an awake dynamic body
+//! or a moving kinematic body (which joins or flags its island), or by any write
+//! through [`BodyUpdate`](super::BodyUpdate).
+
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::iter::StepRng;
+use khal_std::macros::{spirv, spirv_bindgen};
+use khal_std::sync::atomic_max_u32;
+
+use crate::queries::IndexedManifold;
+use crate::utils::{BatchIndices, SliceMut};
+use crate::{AngVector, Vector};
+
+use super::body::{BODY_TYPE_DYNAMIC, BodyParams, Velocity, is_kinematic};
+use super::joint::ImpulseJoint;
+use super::sim_params::RbdSimParams;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The sleep state of a rigid-body, mirroring rapier's `RigidBodyActivation`.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(
+    not(target_arch_is_gpu),
+    derive(bytemuck::Pod, bytemuck::Zeroable, Debug)
+)]
+#[repr(C)]
+pub struct BodyActivation {
+    /// Time elapsed since the velocity of the body went below its sleep thresholds.
+    pub time_since_can_sleep: f32,
+    /// Non-zero if the body is sleeping.
+    pub sleeping: u32,
+}
+
+impl BodyActivation {
+    /// Is the body sleeping?
+    #[inline]
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping != 0
+    }
+
+    /// Has the body been slow enough, for long enough, to fall asleep?
+    #[inline]
+    pub fn can_sleep(&self, params: &BodyParams) -> bool {
+        self.time_since_can_sleep >= params.time_until_sleep
+    }
+
+    /// Advances the sleep timer by `dt` if `vels` is below the sleep thresholds of
+    /// `params`, and resets it otherwise.
+    ///
+    /// Same criterion as rapier: negative thresholds are never met, so the
+    /// body never sleeps.
+    pub fn update_energy(
+        &mut self,
+        params: &BodyParams,
+        vels: &Velocity,
+        length_unit: f32,
+        dt: f32,
+    ) {
+        let linear_threshold = params.sleep_linear_threshold * length_unit;
+        let angular_threshold = params.sleep_angular_threshold;
+        let sq_linvel = vels.linear.length_squared();
+        #[cfg(feature = "dim2")]
+        let sq_angvel = vels.angular * vels.angular;
+        #[cfg(feature = "dim3")]
+        let sq_angvel = vels.angular.length_squared();
+
+        if sq_linvel < linear_threshold * crate::abs(linear_threshold)
+            && sq_angvel < angular_threshold * crate::abs(angular_threshold)
+        {
+            self.time_since_can_sleep += dt;
+        } else {
+            self.time_since_can_sleep = 0.0;
+        }
+    }
+}
+
+/// Number of link/compress rounds merging the islands of `num_bodies` bodies.
+///
+/// Each round about halves the length of the label chains, so the rounds grow
+/// with the logarithm of the number of bodies: `ceil(log2(num_bodies))`, plus one
+/// round of margin for the order in which the atomic updates land.
+#[inline]
+pub fn num_island_link_rounds(num_bodies: u32) -> u32 {
+    u32::BITS - num_bodies.saturating_sub(1).leading_zeros() + 1
+}
+
+/// Is `body_type` a kinematic body type with a non-zero velocity?
+#[inline]
+fn is_moving_kinematic(body_type: u32, vels: &Velocity) -> bool {
+    is_kinematic(body_type) && (vels.linear != Vector::ZERO || vels.angular != AngVector::default())
+}
+
+/// Merges the islands of `body1` and `body2`.
+///
+/// The labels of both bodies, and the labels their labels point to, are raised
+/// to the largest of the two labels.
+#[inline]
+fn link(labels: &mut SliceMut<u32>, body1: usize, body2: usize) {
+    let label1 = labels[body1];
+    let label2 = labels[body2];
+    let label = label1.max(label2);
+    atomic_max_u32(&mut labels[body1], label);
+    atomic_max_u32(&mut labels[body2], label);
+    atomic_max_u32(&mut labels[label1 as usize], label);
+    atomic_max_u32(&mut labels[label2 as usize], label);
+}
+
+/// Updates the sleep timers of the awake dynamic bodies and resets the island
+/// labels and flags.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_islands_init(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] vels: &[Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] activations: &mut [BodyActivation],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] labels: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] island_awake: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] all_params: &[RbdSimParams],
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let params = all_params.at(batch_id as usize);
+    // `params.dt` is the substep length.
+    let step_dt = params.dt * params.num_solver_iterations as f32;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let vels = batch_ids.coll_batch(batch_id, vels);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
+    let mut activations = batch_ids.coll_batch_mut(batch_id, activations);
+    let mut labels = batch_ids.coll_batch_mut(batch_id, labels);
+    let mut island_awake = batch_ids.coll_batch_mut(batch_id, island_awake);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let idx = i as usize;
+        labels[idx] = i;
+        island_awake[idx] = 0;
+
+        let mut activation = activations[idx];
+        if body_types[idx] == BODY_TYPE_DYNAMIC && !activation.is_sleeping() {
+            activation.update_energy(&body_params[idx], &vels[idx], params.length_unit, step_dt);
+            activations[idx] = activation;
+        }
+    }
+}
+
+/// Merges the islands of the dynamic bodies in contact.
+///
+/// A dynamic body touching a moving kinematic body has its sleep timer reset,
+/// which keeps its island awake.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_islands_link_contacts(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] contacts: &[IndexedManifold],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] contacts_len: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] vels: &[Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] activations: &mut [BodyActivation],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] labels: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let contacts = batch_ids.contact_batch(batch_id, contacts);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let vels = batch_ids.coll_batch(batch_id, vels);
+    let mut activations = batch_ids.coll_batch_mut(batch_id, activations);
+    let mut labels = batch_ids.coll_batch_mut(batch_id, labels);
+    let len = contacts_len.read(batch_id as usize);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let im = &contacts[i as usize];
+        if im.contact.len == 0 {
+            continue;
+        }
+
+        let body1 = im.bodies.x as usize;
+        let body2 = im.bodies.y as usize;
+        let dynamic1 = body_types[body1] == BODY_TYPE_DYNAMIC;
+        let dynamic2 = body_types[body2] == BODY_TYPE_DYNAMIC;
+
+        if dynamic1 && dynamic2 {
+            link(&mut labels, body1, body2);
+        } else if dynamic1 && is_moving_kinematic(body_types[body2], &vels[body2]) {
+            activations[body1].time_since_can_sleep = 0.0;
+        } else if dynamic2 && is_moving_kinematic(body_types[body1], &vels[body1]) {
+            activations[body2].time_since_can_sleep = 0.0;
+        }
+    }
+}
+
+/// Merges the islands of the dynamic bodies attached by an enabled impulse joint.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_islands_link_joints(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] joints: &[ImpulseJoint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] labels: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let joints = batch_ids.impulse_joints_batch(batch_id, joints);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let mut labels = batch_ids.coll_batch_mut(batch_id, labels);
+    let len = batch_ids.impulse_joints_len(batch_id);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let joint = &joints[i as usize];
+        if joint.enabled == 0 {
+            continue;
+        }
+        let body1 = joint.body_a as usize;
+        let body2 = joint.body_b as usize;
+        if body_types[body1] == BODY_TYPE_DYNAMIC && body_types[body2] == BODY_TYPE_DYNAMIC {
+            link(&mut labels, body1, body2);
+        }
+    }
+}
+
+/// Replaces every island label by the label it points to.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_islands_compress(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] labels: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 1)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let mut labels = batch_ids.coll_batch_mut(batch_id, labels);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let label = labels[i as usize];
+        let root = labels[label as usize];
+        atomic_max_u32(&mut labels[i as usize], root);
+    }
+}
+
+/// Flags the islands containing at least one dynamic body not ready to sleep.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_islands_awake(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] activations: &[BodyActivation],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] labels: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] island_awake: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
+    let activations = batch_ids.coll_batch(batch_id, activations);
+    let labels = batch_ids.coll_batch(batch_id, labels);
+    let mut island_awake = batch_ids.coll_batch_mut(batch_id, island_awake);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let idx = i as usize;
+        if body_types[idx] == BODY_TYPE_DYNAMIC && !activations[idx].can_sleep(&body_params[idx]) {
+            island_awake[labels[idx] as usize] = 1;
+        }
+    }
+}
+
+/// Puts the dynamic bodies of the unflagged islands to sleep, and wakes up the
+/// sleeping bodies of the flagged ones.
+///
+/// Bodies falling asleep lose their velocity. Bodies waking up have their sleep
+/// timer reset.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_islands_apply(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] labels: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] island_awake: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] activations: &mut [BodyActivation],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] vels: &mut [Velocity],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let labels = batch_ids.coll_batch(batch_id, labels);
+    let island_awake = batch_ids.coll_batch(batch_id, island_awake);
+    let mut activations = batch_ids.coll_batch_mut(batch_id, activations);
+    let mut vels = batch_ids.coll_batch_mut(batch_id, vels);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let idx = i as usize;
+        if body_types[idx] != BODY_TYPE_DYNAMIC {
+            continue;
+        }
+
+        let activation = activations[idx];
+        if island_awake[labels[idx] as usize] != 0 {
+            if activation.is_sleeping() {
+                activations[idx] = BodyActivation::default();
+            }
+        } else if !activation.is_sleeping() {
+            activations[idx].sleeping = 1;
+            vels[idx] = Velocity::default();
+        }
+    }
+}
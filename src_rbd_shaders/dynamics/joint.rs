@@ -2,7 +2,7 @@
 //!
 //! This module defines the data structures for articulated joints between rigid bodies.
 
-use crate::Pose;
+use crate::{MAX_FLT, Pose};
 
 #[cfg(feature = "dim2")]
 /// Spatial dimension (3 DOFs in 2D: 2 linear + 1 angular).
@@ -28,6 +28,15 @@ pub const LIN_AXES_MASK: u32 = 1 + (1 << 1) + (1 << 2);
 /// Bitmask for angular axes (Rx, Ry, Rz in 3D).
 pub const ANG_AXES_MASK: u32 = (1 << 3) + (1 << 4) + (1 << 5);
 
+/// A joint constraining the degrees of freedom described by its [`GenericJoint`] axis masks.
+pub const JOINT_KIND_GENERIC: u32 = 0;
+/// A rope: the distance between both anchors can't exceed `GenericJoint::limits[0].max`, but the
+/// anchors can get arbitrarily close. Motorizing the first linear axis drives the distance.
+pub const JOINT_KIND_ROPE: u32 = 1;
+/// A spring pulling both anchors toward the rest length `GenericJoint::motors[0].target_pos`, with
+/// the motor's stiffness and damping.
+pub const JOINT_KIND_SPRING: u32 = 2;
+
 /// An impulse-based joint connecting two rigid bodies.
 #[derive(Clone, Copy)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
@@ -37,13 +46,55 @@ pub struct ImpulseJoint {
     pub body_a: u32,
     /// Index of the second body.
     pub body_b: u32,
-    /// Padding for alignment (GenericJoint starts with Pose which has 16-byte alignment in 3D).
-    #[cfg(feature = "dim3")]
-    pub padding: [u32; 2],
+    /// The kind of joint, one of the `JOINT_KIND_*` constants.
+    ///
+    /// Ropes and springs constrain the distance between the anchors along the line joining them
+    /// instead of the axes of the first frame.
+    pub kind: u32,
+    /// `1` if the joint is enabled, `0` if it was disabled (e.g. broken by
+    /// `gpu_break_joints`). Disabled joints don't generate any constraint.
+    ///
+    /// This also pads the struct before `data` (GenericJoint starts with Pose which has 8-byte
+    /// alignment in 2D and 16-byte alignment in 3D).
+    pub enabled: u32,
     /// Joint configuration data.
     pub data: GenericJoint,
 }
 
+/// The reaction force and torque norms above which an impulse joint breaks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct JointBreakThreshold {
+    /// Maximum norm of the reaction force.
+    pub max_force: f32,
+    /// Maximum norm of the reaction torque.
+    pub max_torque: f32,
+}
+
+impl Default for JointBreakThreshold {
+    /// An unbreakable joint.
+    fn default() -> Self {
+        Self {
+            max_force: MAX_FLT,
+            max_torque: MAX_FLT,
+        }
+    }
+}
+
+/// Emitted when the reaction wrench of an impulse joint exceeds its [`JointBreakThreshold`].
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct JointBreakEvent {
+    /// Index of the broken joint within its batch, in GPU buffer order.
+    pub joint_id: u32,
+    /// Norm of the reaction force when the joint broke.
+    pub force: f32,
+    /// Norm of the reaction torque when the joint broke.
+    pub torque: f32,
+}
+
 /// A generic (6 DOFs in 3D or 3 DOFs in 2D) joint.
 #[derive(Clone, Copy)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
@@ -2,7 +2,7 @@
 //!
 //! This module defines the constraint structures used by the joint solver.
 
-use crate::{AngVector, Vector};
+use crate::{AngVector, Vector, gcross};
 
 use super::joint::SPATIAL_DIM;
 
@@ -14,13 +14,14 @@ use glamx::{Mat4, Vec2};
 use khal_std::glamx::UVec3;
 use khal_std::iter::StepRng;
 use khal_std::macros::{spirv, spirv_bindgen};
+use khal_std::sync::atomic_add_u32;
 
 use crate::Pose;
-use crate::utils::{BatchIndices, Slice};
+use crate::utils::BatchIndices;
 use khal_std::index::MaybeIndexUnchecked;
 
-use super::body::{LocalMassProperties, Velocity, WorldMassProperties};
-use super::joint::ImpulseJoint;
+use super::body::{Force, LocalMassProperties, Velocity, WorldMassProperties};
+use super::joint::{ImpulseJoint, JointBreakEvent, JointBreakThreshold};
 use super::joint_constraint_builder::JointConstraintBuilder;
 use super::sim_params::RbdSimParams;
 
@@ -182,7 +183,7 @@ pub fn gpu_init_joint_constraints(
     let mut constraints = batch_ids.impulse_joints_batch_mut(batch_id, constraints);
     let local_mprops = batch_ids.coll_batch(batch_id, local_mprops);
 
-    let len = batch_ids.impulse_joints_len;
+    let len = batch_ids.impulse_joints_len(batch_id);
 
     for i in StepRng::new(invocation_id.x..len, num_threads) {
         let idx = i as usize;
@@ -201,8 +202,12 @@ pub fn gpu_init_joint_constraints(
             body1: joint.body_a,
             body2: joint.body_b,
             joint_id: i,
-            joint: joint_data,
             constraint_id: i,
+            kind: joint.kind,
+            enabled: joint.enabled,
+            #[cfg(feature = "dim3")]
+            padding: [0; _],
+            joint: joint_data,
         };
 
         constraints[idx].solver_vel_a = joint.body_a;
@@ -235,7 +240,7 @@ pub fn gpu_update_joint_constraints(
     let poses = batch_ids.coll_batch(batch_id, poses);
     let mprops = batch_ids.coll_batch(batch_id, mprops);
 
-    let len = batch_ids.impulse_joints_len;
+    let len = batch_ids.impulse_joints_len(batch_id);
 
     for i in StepRng::new(invocation_id.x..len, num_threads) {
         let idx = i as usize;
@@ -263,10 +268,11 @@ pub fn gpu_solve_joint_constraints(
     let mut solver_vels = batch_ids.coll_batch_mut(batch_id, solver_vels);
     let use_bias = *use_bias != 0;
 
+    // `color_groups` is a per-batch prefix-sum over the color-sorted joints:
+    // color `c` owns the range `[color_groups[c-1], color_groups[c])`. The
+    // dispatch is sized for the largest group across batches.
     let color = *curr_color as usize;
-    // Coloring is identical across batches (enforced on the host), so the
-    // color-group prefix sums live in a single-batch buffer read at offset 0.
-    let color_groups = Slice(all_color_groups, 0);
+    let color_groups = batch_ids.impulse_joint_color_groups_batch(batch_id, all_color_groups);
 
     let start = if color > 0 {
         color_groups[color - 1]
@@ -279,3 +285,135 @@ pub fn gpu_solve_joint_constraints(
         constraints[i as usize].solve_joint_constraint(&mut solver_vels, use_bias);
     }
 }
+
+impl JointConstraint {
+    /// The wrench applied by this constraint on its second body, from the
+    /// impulses accumulated during the last solved substep, divided by `dt`.
+    ///
+    /// The torque is expressed about `anchor2`. Both are in world space.
+    pub fn reaction_wrench(&self, world_com2: Vector, anchor2: Vector, dt: f32) -> Force {
+        let inv_dt = if dt != 0.0 { 1.0 / dt } else { 0.0 };
+        let mut linear = Vector::ZERO;
+        let mut angular = AngVector::default();
+
+        // The solver applies `-lin_jac * impulse` and `-ang_jac_b * impulse` to
+        // the second body (the latter about its center of mass).
+        for i in 0..(self.len as usize) {
+            let element = self.elements.at(i);
+            linear -= element.lin_jac * element.impulse;
+            angular -= element.ang_jac_b * element.impulse;
+        }
+
+        // Move the torque from the center of mass to the anchor.
+        angular -= gcross(anchor2 - world_com2, linear);
+        Force::new(linear * inv_dt, angular * inv_dt)
+    }
+}
+
+/// Computes the reaction wrench of every joint, after the last substep.
+///
+/// The wrench is the one applied by the joint on its second body, at its anchor,
+/// expressed in the joint frame attached to the first body.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_compute_joint_wrenches(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] builders: &[JointConstraintBuilder],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] constraints: &[JointConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] all_params: &[RbdSimParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] wrenches: &mut [Force],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let params = all_params.at(batch_id as usize);
+
+    let builders = batch_ids.impulse_joints_batch(batch_id, builders);
+    let constraints = batch_ids.impulse_joints_batch(batch_id, constraints);
+    let mut wrenches = batch_ids.impulse_joints_batch_mut(batch_id, wrenches);
+    let poses = batch_ids.coll_batch(batch_id, poses);
+
+    let len = batch_ids.impulse_joints_len(batch_id);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let idx = i as usize;
+        let builder = &builders[idx];
+
+        // Same COM-centered frames as `JointConstraintBuilder::update_constraint`.
+        let pose1 = poses[builder.body1 as usize];
+        let pose2 = poses[builder.body2 as usize];
+        let frame1 = pose1 * builder.joint.local_frame_a;
+        let frame2 = pose2 * builder.joint.local_frame_b;
+
+        let world =
+            constraints[idx].reaction_wrench(pose2.translation, frame2.translation, params.dt);
+        let inv_rot1 = frame1.rotation.inverse();
+        #[cfg(feature = "dim2")]
+        let angular = world.angular;
+        #[cfg(feature = "dim3")]
+        let angular = inv_rot1 * world.angular;
+        wrenches[idx] = Force::new(inv_rot1 * world.linear, angular);
+    }
+}
+
+/// Disables the enabled impulse joints whose reaction wrench (as computed by
+/// [`gpu_compute_joint_wrenches`]) exceeds their [`JointBreakThreshold`].
+///
+/// Every broken joint appends a [`JointBreakEvent`] to the ring buffer of its batch,
+/// of `impulse_joints_batch_capacity` entries, indexed by the ever-growing counter
+/// `events_len[batch_id]`. A joint breaks at most once, so a batch can't overrun its
+/// ring between two host drains unless its joints are re-enabled.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_break_joints(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] joints: &mut [ImpulseJoint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] wrenches: &[Force],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] thresholds: &[JointBreakThreshold],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] events: &mut [JointBreakEvent],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] events_len: &mut [u32],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let capacity = batch_ids.impulse_joints_batch_capacity;
+
+    let mut joints = batch_ids.impulse_joints_batch_mut(batch_id, joints);
+    let wrenches = batch_ids.impulse_joints_batch(batch_id, wrenches);
+    let thresholds = batch_ids.impulse_joints_batch(batch_id, thresholds);
+    let mut events = batch_ids.impulse_joint_events_batch_mut(batch_id, events);
+    let events_len = events_len.at_mut(batch_id as usize);
+
+    let len = batch_ids.impulse_joints_len(batch_id);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let idx = i as usize;
+        if joints[idx].enabled == 0 {
+            continue;
+        }
+
+        let wrench = wrenches[idx];
+        let threshold = thresholds[idx];
+        let force = wrench.linear.length();
+        #[cfg(feature = "dim2")]
+        let torque = wrench.angular.abs();
+        #[cfg(feature = "dim3")]
+        let torque = wrench.angular.length();
+
+        if force > threshold.max_force || torque > threshold.max_torque {
+            joints[idx].enabled = 0;
+            let event_index = atomic_add_u32(events_len, 1) % capacity;
+            events.write(
+                event_index as usize,
+                JointBreakEvent {
+                    joint_id: i,
+                    force,
+                    torque,
+                },
+            );
+        }
+    }
+}
@@ -4,7 +4,9 @@
 //! joint definitions and body states.
 
 use super::body::{Velocity, WorldMassProperties};
-use super::joint::{ANG_AXES_MASK, GenericJoint, LIN_AXES_MASK, MotorParameters, SPATIAL_DIM};
+use super::joint::{
+    ANG_AXES_MASK, GenericJoint, JOINT_KIND_GENERIC, LIN_AXES_MASK, MotorParameters, SPATIAL_DIM,
+};
 use super::joint_constraint::{JointConstraint, JointConstraintElement, JointSolverBody};
 use super::sim_params::{RbdSimParams, TWO_PI};
 #[cfg(feature = "dim2")]
@@ -34,6 +36,12 @@ pub struct JointConstraintBuilder {
     pub body2: u32,
     pub joint_id: u32,
     pub constraint_id: u32,
+    /// The joint kind, one of the `JOINT_KIND_*` constants.
+    pub kind: u32,
+    /// `0` if the joint is disabled, in which case it generates no constraint.
+    pub enabled: u32,
+    #[cfg(feature = "dim3")]
+    pub padding: [u32; 2],
     pub joint: GenericJoint,
 }
 
@@ -705,6 +713,129 @@ impl JointConstraintHelper {
     }
 }
 
+/// Helper for building the constraints of ropes and springs.
+///
+/// Unlike [`JointConstraintHelper`], these act along the line joining both anchors instead of
+/// along the axes of the first joint frame, and apply their impulses at each body's own anchor.
+#[derive(Clone, Copy)]
+pub struct DistanceConstraintHelper {
+    /// Unit vector from the first anchor to the second one (zero if both anchors coincide).
+    pub dir: Vector,
+    /// Distance between both anchors.
+    pub dist: f32,
+    /// Angular Jacobian of the first body.
+    pub ang_jac1: AngVector,
+    /// Angular Jacobian of the second body.
+    pub ang_jac2: AngVector,
+}
+
+impl DistanceConstraintHelper {
+    /// Creates a distance helper from the world-space joint frames and centers of mass.
+    pub fn new(frame1: Pose, frame2: Pose, world_com1: Vector, world_com2: Vector) -> Self {
+        let delta = frame2.translation - frame1.translation;
+        let dist = delta.length();
+        let dir = delta * pseudo_inv(dist);
+        let r1 = frame1.translation - world_com1;
+        let r2 = frame2.translation - world_com2;
+
+        #[cfg(feature = "dim2")]
+        let (ang_jac1, ang_jac2) = (gcross_matrix(r1).dot(dir), gcross_matrix(r2).dot(dir));
+        #[cfg(feature = "dim3")]
+        let (ang_jac1, ang_jac2) = (gcross_matrix(r1) * dir, gcross_matrix(r2) * dir);
+
+        Self {
+            dir,
+            dist,
+            ang_jac1,
+            ang_jac2,
+        }
+    }
+
+    fn element(
+        &self,
+        joint_id: u32,
+        body1: &JointSolverBody,
+        body2: &JointSolverBody,
+    ) -> JointConstraintElement {
+        JointConstraintElement {
+            joint_id,
+            impulse: 0.0,
+            impulse_bounds: Vec2::new(-MAX_FLT, MAX_FLT),
+            lin_jac: self.dir,
+            ang_jac_a: self.ang_jac1,
+            ang_jac_b: self.ang_jac2,
+            ii_ang_jac_a: body1.ii_mul(self.ang_jac1),
+            ii_ang_jac_b: body2.ii_mul(self.ang_jac2),
+            inv_lhs: 0.0, // Will be set during orthogonalization.
+            rhs: 0.0,
+            rhs_wo_bias: 0.0,
+            cfm_gain: 0.0,
+            cfm_coeff: 0.0,
+            #[cfg(feature = "dim2")]
+            padding: 0,
+        }
+    }
+
+    /// Creates the one-sided constraint element keeping both anchors at most `max_dist` apart.
+    pub fn limit_max_distance(
+        &self,
+        joint_id: u32,
+        body1: &JointSolverBody,
+        body2: &JointSolverBody,
+        max_dist: f32,
+        params: &RbdSimParams,
+    ) -> JointConstraintElement {
+        let mut constraint = self.element(joint_id, body1, body2);
+        // Let a slack rope close the gap within one step before pulling.
+        let rhs_wo_bias = (self.dist - max_dist).min(0.0) * params.inv_dt();
+        let rhs_bias = (self.dist - max_dist).max(0.0) * params.joint_erp_inv_dt();
+        constraint.rhs = rhs_wo_bias + rhs_bias;
+        constraint.rhs_wo_bias = rhs_wo_bias;
+        constraint.cfm_coeff = params.joint_cfm_coeff();
+        constraint.impulse_bounds = Vec2::new(0.0, MAX_FLT);
+        constraint
+    }
+
+    /// Creates the constraint element driving the distance between both anchors with a motor.
+    ///
+    /// This is the spring itself for springs (whose motor targets the rest length), and a winch
+    /// for motorized ropes.
+    pub fn motor_distance(
+        &self,
+        joint_id: u32,
+        body1: &JointSolverBody,
+        body2: &JointSolverBody,
+        motor_params: &MotorParameters,
+        limits: Vec2,
+        params: &RbdSimParams,
+    ) -> JointConstraintElement {
+        let dt_inv = params.inv_dt();
+        let mut constraint = self.element(joint_id, body1, body2);
+
+        let mut rhs_wo_bias = 0.0;
+        if motor_params.erp_inv_dt != 0.0 {
+            rhs_wo_bias += (self.dist - motor_params.target_pos) * motor_params.erp_inv_dt;
+        }
+
+        let mut target_vel = motor_params.target_vel;
+        if limits != Vec2::new(-MAX_FLT, MAX_FLT) {
+            target_vel = target_vel.clamp(
+                (limits.x - self.dist) * dt_inv,
+                (limits.y - self.dist) * dt_inv,
+            );
+        }
+
+        rhs_wo_bias += -target_vel;
+
+        constraint.cfm_coeff = motor_params.cfm_coeff;
+        constraint.cfm_gain = motor_params.cfm_gain;
+        constraint.impulse_bounds = Vec2::new(-motor_params.max_impulse, motor_params.max_impulse);
+        constraint.rhs = rhs_wo_bias;
+        constraint.rhs_wo_bias = rhs_wo_bias;
+        constraint
+    }
+}
+
 impl JointConstraintBuilder {
     /// Updates a joint constraint for a new substep.
     pub fn update_constraint(
@@ -747,6 +878,23 @@ impl JointConstraintBuilder {
             solver_vel: body2,
         };
 
+        if self.enabled == 0 {
+            constraint.len = 0;
+            return;
+        }
+
+        if self.kind != JOINT_KIND_GENERIC {
+            self.update_distance_constraint(
+                constraint,
+                frame1,
+                frame2,
+                &joint_body1,
+                &joint_body2,
+                params,
+            );
+            return;
+        }
+
         let mut len = 0usize;
         let locked_axes = joint.locked_axes;
         let motor_axes = joint.motor_axes & !locked_axes;
@@ -930,6 +1078,56 @@ impl JointConstraintBuilder {
         constraint.orthogonalize_constraints(start, len);
         constraint.len = len as u32;
     }
+
+    /// Updates the constraint of a rope or a spring.
+    ///
+    /// Both only constrain the distance between the anchors: first with the motor of the first
+    /// linear axis (always set for springs), then with the max-distance limit of ropes. The element
+    /// order matches the one of coupled linear axes in [`Self::update_constraint`].
+    fn update_distance_constraint(
+        &self,
+        constraint: &mut JointConstraint,
+        frame1: Pose,
+        frame2: Pose,
+        body1: &JointSolverBody,
+        body2: &JointSolverBody,
+        params: &RbdSimParams,
+    ) {
+        let joint = &self.joint;
+        let helper =
+            DistanceConstraintHelper::new(frame1, frame2, body1.world_com, body2.world_com);
+        let limited = (joint.limit_axes & 1) != 0;
+        let limits = if limited {
+            Vec2::new(joint.limits.at(0).min, joint.limits.at(0).max)
+        } else {
+            Vec2::new(-MAX_FLT, MAX_FLT)
+        };
+
+        let mut len = 0usize;
+
+        if (joint.motor_axes & LIN_AXES_MASK) != 0 {
+            let mp = joint.motors.at(0).motor_params(params.dt);
+            constraint.elements.write(
+                len,
+                helper.motor_distance(self.constraint_id, body1, body2, &mp, limits, params),
+            );
+            len += 1;
+        }
+
+        constraint.orthogonalize_constraints(0, len);
+        let start = len;
+
+        if limited {
+            constraint.elements.write(
+                len,
+                helper.limit_max_distance(self.constraint_id, body1, body2, limits.y, params),
+            );
+            len += 1;
+        }
+
+        constraint.orthogonalize_constraints(start, len);
+        constraint.len = len as u32;
+    }
 }
 
 impl JointConstraint {
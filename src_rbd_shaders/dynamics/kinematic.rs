@@ -0,0 +1,115 @@
+//! Kinematic rigid-bodies compute shader kernels.
+//!
+//! Position-based kinematic bodies are given the velocity reaching their target
+//! pose in one step, so the solver sees (and pushes dynamic bodies with) their
+//! actual motion. Velocity-based kinematic bodies keep their user-defined velocity.
+
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::iter::StepRng;
+use khal_std::macros::{spirv, spirv_bindgen};
+
+use crate::{AngVector, Pose};
+
+use super::body::{BODY_TYPE_KINEMATIC_POSITION, Velocity};
+use super::sim_params::RbdSimParams;
+use crate::utils::BatchIndices;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The angular displacement (scaled axis in 3D, angle in 2D) bringing `from` to `to`
+/// along the shortest path.
+#[cfg(feature = "dim2")]
+pub(super) fn angular_displacement(from: &Pose, to: &Pose) -> AngVector {
+    crate::rotation_angle(to.rotation * from.rotation.inverse())
+}
+
+/// The angular displacement (scaled axis in 3D, angle in 2D) bringing `from` to `to`
+/// along the shortest path.
+#[cfg(feature = "dim3")]
+pub(super) fn angular_displacement(from: &Pose, to: &Pose) -> AngVector {
+    let mut dq = to.rotation * from.rotation.inverse();
+    if dq.w < 0.0 {
+        dq = -dq;
+    }
+    let imag = crate::rotation_imag(dq);
+    let sin_half = imag.length();
+    if sin_half <= 1.0e-7 {
+        // Small-angle approximation of `2 * asin(sin_half) / sin_half`.
+        imag * 2.0
+    } else {
+        imag * (2.0 * crate::atan2(sin_half, dq.w) / sin_half)
+    }
+}
+
+/// Computes the velocity of every position-based kinematic body so that it reaches
+/// its target pose at the end of the step.
+///
+/// Kinematic bodies have a zero local center-of-mass, so the linear velocity of
+/// their origin is also their solver (center-of-mass) velocity.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_kinematic_velocities(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] kinematic_targets: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] vels: &mut [Velocity],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] all_params: &[RbdSimParams],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let params = all_params.at(batch_id as usize);
+    // `params.dt` is the substep length.
+    let step_dt = params.dt * params.num_solver_iterations as f32;
+    let inv_step_dt = if step_dt == 0.0 { 0.0 } else { 1.0 / step_dt };
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let kinematic_targets = batch_ids.coll_batch(batch_id, kinematic_targets);
+    let body_poses = batch_ids.coll_batch(batch_id, body_poses);
+    let mut vels = batch_ids.coll_batch_mut(batch_id, vels);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let idx = i as usize;
+        if body_types[idx] == BODY_TYPE_KINEMATIC_POSITION {
+            let pose = body_poses[idx];
+            let target = kinematic_targets[idx];
+            let linear = (target.translation - pose.translation) * inv_step_dt;
+            let angular = angular_displacement(&pose, &target) * inv_step_dt;
+            vels[idx] = Velocity::new(linear, angular);
+        }
+    }
+}
+
+/// Snaps every position-based kinematic body exactly on its target pose.
+///
+/// Run after the solver finalization to remove the drift of the linearized
+/// substep integration.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_kinematic_snap_poses(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] body_types: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] kinematic_targets: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_poses: &mut [Pose],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+
+    let num_bodies = batch_ids.bodies_len(batch_id);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
+    let kinematic_targets = batch_ids.coll_batch(batch_id, kinematic_targets);
+    let mut body_poses = batch_ids.coll_batch_mut(batch_id, body_poses);
+
+    for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
+        let idx = i as usize;
+        if body_types[idx] == BODY_TYPE_KINEMATIC_POSITION {
+            body_poses[idx] = kinematic_targets[idx];
+        }
+    }
+}
@@ -2,6 +2,13 @@
 //!
 //! This module provides:
 //! - Body state and mass properties
+//! - Kinematic bodies driven by target poses or velocities
+//! - Continuous collision detection of fast bodies
+//! - Island detection and sleeping of bodies at rest
+//! - Sparse runtime writes to body poses, velocities and forces
+//! - Per-environment state snapshots
+//! - Per-environment randomization of the physical parameters
+//! - Batched observation and action tensors
 //! - Contact constraints
 //! - Joint constraints
 //! - Constraint solver (PGS/Sequential Impulse)
@@ -19,27 +26,44 @@ mod solver_utils;
 mod warmstart;
 
 // GPU compute shader kernels
+mod body_updates;
+mod ccd;
 mod color_buckets;
 mod coloring;
+mod env_snapshots;
+mod env_tensors;
+mod islands;
+mod kinematic;
 mod mprops_update;
+mod param_updates;
 mod prep_render;
 mod solver;
 
 pub use body::*;
 pub use constraint::*;
 pub use joint::{
-    ACCELERATION_BASED, ANG_AXES_MASK, FORCE_BASED, GenericJoint, ImpulseJoint, JointLimits,
+    ACCELERATION_BASED, ANG_AXES_MASK, FORCE_BASED, GenericJoint, ImpulseJoint, JOINT_KIND_GENERIC,
+    JOINT_KIND_ROPE, JOINT_KIND_SPRING, JointBreakEvent, JointBreakThreshold, JointLimits,
     JointMotor, LIN_AXES_MASK, MotorParameters, SPATIAL_DIM,
 };
 pub use joint_constraint::*;
 pub(crate) use joint_constraint_builder::smallest_abs_diff_between_sin_angles;
-pub use joint_constraint_builder::{JointConstraintBuilder, JointConstraintHelper, new_helper};
+pub use joint_constraint_builder::{
+    DistanceConstraintHelper, JointConstraintBuilder, JointConstraintHelper, new_helper,
+};
 pub use multibody::*;
 pub use sim_params::*;
 // Re-export solver items; update_constraint comes from joint_constraint_builder for joints
+pub use body_updates::*;
+pub use ccd::*;
 pub use color_buckets::*;
 pub use coloring::*;
+pub use env_snapshots::*;
+pub use env_tensors::*;
+pub use islands::*;
+pub use kinematic::*;
 pub use mprops_update::*;
+pub use param_updates::*;
 pub use prep_render::*;
 pub use solver::*;
 pub use solver_utils::warmstart_body;
@@ -8,12 +8,17 @@ use khal_std::macros::{spirv, spirv_bindgen};
 
 use crate::Pose;
 
-use super::body::{LocalMassProperties, WorldMassProperties};
+use super::body::{BodyParams, LocalMassProperties, WorldMassProperties};
+use super::islands::BodyActivation;
 use crate::utils::BatchIndices;
 
 const WORKGROUP_SIZE: u32 = 64;
 
 /// Updates world-space mass properties for all rigid bodies.
+///
+/// The locked axes and dominance group from `body_params` are folded into the
+/// world-space mass properties so the solver doesn't need to read them again.
+/// Sleeping bodies are given an infinite mass, so the solver leaves them still.
 #[spirv_bindgen]
 #[spirv(compute(threads(64)))]
 pub fn gpu_update_mprops(
@@ -23,19 +28,29 @@ pub fn gpu_update_mprops(
     #[spirv(storage_buffer, descriptor_set = 0, binding = 1)]
     local_mprops: &[LocalMassProperties],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] poses: &[Pose],
-    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] activations: &[BodyActivation],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
 ) {
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
 
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
     let mut mprops = batch_ids.coll_batch_mut(batch_id, mprops);
     let local_mprops = batch_ids.coll_batch(batch_id, local_mprops);
     let poses = batch_ids.coll_batch(batch_id, poses);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
+    let activations = batch_ids.coll_batch(batch_id, activations);
 
     for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
         let idx = i as usize;
-        let new_mprops = local_mprops[idx].to_world(&poses[idx]);
+        let params = body_params[idx];
+        let mut new_mprops = local_mprops[idx].to_world(&poses[idx]);
+        new_mprops.lock_axes(params.locked_axes);
+        new_mprops.dominance_group = params.dominance_group;
+        if activations[idx].is_sleeping() {
+            new_mprops.make_infinite();
+        }
         mprops[idx] = new_mprops;
     }
 }
@@ -60,7 +75,7 @@ pub fn gpu_sync_collider_poses(
 ) {
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
-    let num_colliders = batch_ids.colliders_len;
+    let num_colliders = batch_ids.colliders_len(batch_id);
 
     let body_poses = batch_ids.coll_batch(batch_id, body_poses);
     let collider_local_poses = batch_ids.coll_batch(batch_id, collider_local_poses);
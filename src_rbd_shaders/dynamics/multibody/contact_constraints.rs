@@ -174,7 +174,7 @@ pub fn gpu_mb_init_contact_constraints(
 
     let cons_start = batch_ids.mb_contact_constraints_start(batch_id);
     let colliders_start = batch_ids.coll_start(batch_id);
-    // `body_to_link` is laid out with stride = colliders_batch_capacity.
+    // `body_to_link` is laid out like the body buffers.
     let b2l_start = colliders_start;
 
     // Per-multibody early-out: padding multibody slots have `ndofs == 0`,
@@ -0,0 +1,141 @@
+//! Per-environment snapshots of the multibody state.
+//!
+//! Counterpart of [`crate::dynamics::gpu_copy_body_snapshots`] for the reduced
+//! coordinates. The link workspace holds the generalized coordinates the
+//! integrator advances (and everything derived from them), so it is captured
+//! whole, along with the generalized coordinates and velocities. The contact
+//! constraints the next step warmstarts from are captured separately.
+
+use glamx::Vec4;
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::iter::StepRng;
+use khal_std::macros::{spirv, spirv_bindgen};
+
+use crate::dynamics::{ENV_SNAPSHOT_CAPTURE, ENV_SNAPSHOT_RESTORE};
+use crate::utils::BatchIndices;
+
+use super::types::{MultibodyContactConstraint, MultibodyInfo};
+use super::ws_soa::WS_QUADS;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Captures and restores the multibody snapshots of the batches flagged in `ops`.
+///
+/// Dispatched with one thread row per batch. `dof_snapshots` interleaves the
+/// generalized coordinates (first `dof_batch_capacity` entries of each batch)
+/// and velocities (next `dof_batch_capacity` entries) like the DOF buffers.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_mb_copy_env_snapshots(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ops: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] ws_snapshots: &mut [Vec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] dof_snapshots: &mut [f32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] links_workspace: &mut [Vec4],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] dof_values: &mut [f32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] dof_state: &mut [f32],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let op = *ops.at(batch_id as usize);
+    if op == 0 {
+        return;
+    }
+
+    let num_quads = batch_ids.links_batch_capacity * WS_QUADS;
+    let num_dofs = batch_ids.dof_batch_capacity;
+    let len = num_quads.max(num_dofs);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let idx = i as usize;
+        let val = batch_ids.mbi(batch_id, idx);
+        // The velocities are the first section of `dof_state`.
+        let vel = batch_ids.mbi(batch_id, num_dofs as usize + idx);
+
+        if op & ENV_SNAPSHOT_CAPTURE != 0 {
+            if i < num_quads {
+                ws_snapshots.write(val, links_workspace.read(val));
+            }
+            if i < num_dofs {
+                dof_snapshots.write(val, dof_values.read(val));
+                dof_snapshots.write(vel, dof_state.read(val));
+            }
+        }
+
+        if op & ENV_SNAPSHOT_RESTORE != 0 {
+            if i < num_quads {
+                links_workspace.write(val, ws_snapshots.read(val));
+            }
+            if i < num_dofs {
+                dof_values.write(val, dof_snapshots.read(val));
+                dof_state.write(val, dof_snapshots.read(vel));
+            }
+        }
+    }
+}
+
+/// Captures and restores the multibody contact constraints of the batches
+/// flagged in `ops`, which the next step warmstarts its contacts from.
+///
+/// Dispatched with one thread row per batch. `contact_snapshots` is laid out
+/// like the contact constraint slab, and `count_snapshots` like the multibody
+/// infos: the constraint count of each multibody is restored along with its
+/// slab, so that the next build still deactivates every leftover slot.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_mb_copy_contact_snapshots(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] ops: &[u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)]
+    contact_snapshots: &mut [MultibodyContactConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] count_snapshots: &mut [u32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)]
+    contact_constraints: &mut [MultibodyContactConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)]
+    multibody_info: &mut [MultibodyInfo],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let op = *ops.at(batch_id as usize);
+    if op == 0 {
+        return;
+    }
+
+    let num_multibodies = batch_ids.multibodies_len;
+    let num_contacts = batch_ids.mb_contact_constraints_batch_capacity;
+    let len = num_multibodies.max(num_contacts);
+    let mut contact_snapshots =
+        batch_ids.mb_contact_constraints_batch_mut(batch_id, contact_snapshots);
+    let mut contact_constraints =
+        batch_ids.mb_contact_constraints_batch_mut(batch_id, contact_constraints);
+
+    for i in StepRng::new(invocation_id.x..len, num_threads) {
+        let idx = i as usize;
+        let mbi = batch_ids.mbi(batch_id, idx);
+
+        if op & ENV_SNAPSHOT_CAPTURE != 0 {
+            if i < num_multibodies {
+                count_snapshots.write(mbi, multibody_info.read(mbi).contact_constraint_count);
+            }
+            if i < num_contacts {
+                contact_snapshots[idx] = contact_constraints[idx];
+            }
+        }
+
+        if op & ENV_SNAPSHOT_RESTORE != 0 {
+            if i < num_multibodies {
+                let mut mb = multibody_info.read(mbi);
+                mb.contact_constraint_count = count_snapshots.read(mbi);
+                multibody_info.write(mbi, mb);
+            }
+            if i < num_contacts {
+                contact_constraints[idx] = contact_snapshots[idx];
+            }
+        }
+    }
+}
@@ -94,7 +94,7 @@ pub fn gpu_mb_gravity_and_lu(
     #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] mass_matrices: &mut [f32],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] lu_pivots: &mut [u32],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 7)] dof_state: &[f32],
-    #[spirv(uniform, descriptor_set = 0, binding = 8)] gravity: &Vec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 8)] gravity: &[Vec4],
     #[spirv(uniform, descriptor_set = 0, binding = 9)] batch_ids: &BatchIndices,
     #[spirv(uniform, descriptor_set = 0, binding = 10)] dt_uniform: &f32,
     // Mass-matrix tile in shared memory.
@@ -148,6 +148,7 @@ pub fn gpu_mb_gravity_and_lu(
     fill_par(gen_forces, accelerations, 0.0, lane, LANES);
     workgroup_memory_barrier_with_group_sync();
 
+    let gravity = gravity.read(batch_id as usize);
     #[cfg(feature = "dim3")]
     let g = Vec3::new(gravity.x, gravity.y, gravity.z);
     #[cfg(feature = "dim2")]
@@ -405,7 +406,7 @@ fn gravity_and_lu_packed_impl<const T: u32, const MATN: usize, const SLOTS: usiz
     mass_matrices: &mut [f32],
     lu_pivots: &mut [u32],
     dof_state: &[f32],
-    gravity: &Vec4,
+    gravity: &[Vec4],
     batch_ids: &BatchIndices,
     dt_uniform: &f32,
     mat: &mut [f32; MATN],
@@ -465,6 +466,7 @@ fn gravity_and_lu_packed_impl<const T: u32, const MATN: usize, const SLOTS: usiz
     fill_par(gen_forces, accelerations, 0.0, lane, T);
     workgroup_memory_barrier_with_group_sync();
 
+    let gravity = gravity.read(batch_id as usize);
     #[cfg(feature = "dim3")]
     let g = Vec3::new(gravity.x, gravity.y, gravity.z);
     #[cfg(feature = "dim2")]
@@ -740,7 +742,7 @@ pub fn gpu_mb_gravity_and_lu_t1(
     #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] mass_matrices: &mut [f32],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] lu_pivots: &mut [u32],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 7)] dof_state: &[f32],
-    #[spirv(uniform, descriptor_set = 0, binding = 8)] gravity: &Vec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 8)] gravity: &[Vec4],
     #[spirv(uniform, descriptor_set = 0, binding = 9)] batch_ids: &BatchIndices,
     #[spirv(uniform, descriptor_set = 0, binding = 10)] dt_uniform: &f32,
 ) {
@@ -785,6 +787,7 @@ pub fn gpu_mb_gravity_and_lu_t1(
         gen_forces.write(batch_ids.mbi(batch_id, gen_base + d as usize), 0.0);
     }
 
+    let gravity = gravity.read(batch_id as usize);
     #[cfg(feature = "dim3")]
     let g = Vec3::new(gravity.x, gravity.y, gravity.z);
     #[cfg(feature = "dim2")]
@@ -969,7 +972,7 @@ macro_rules! gravity_and_lu_packed_entry {
             #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] mass_matrices: &mut [f32],
             #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] lu_pivots: &mut [u32],
             #[spirv(storage_buffer, descriptor_set = 0, binding = 7)] dof_state: &[f32],
-            #[spirv(uniform, descriptor_set = 0, binding = 8)] gravity: &Vec4,
+            #[spirv(storage_buffer, descriptor_set = 0, binding = 8)] gravity: &[Vec4],
             #[spirv(uniform, descriptor_set = 0, binding = 9)] batch_ids: &BatchIndices,
             #[spirv(uniform, descriptor_set = 0, binding = 10)] dt_uniform: &f32,
             #[spirv(workgroup)] mat: &mut [f32; $matn],
@@ -227,48 +227,44 @@ pub(super) fn lock_jacobians_generic(
     // "B" side and mark side A inactive (`ndofs_a = 0`). The solver's
     // `vel2 - vel1`, the `dot_j_wj` effective mass, and the impulse apply then
     // read the relative quantities directly, with no solver-side changes.
-    // Mirrors rapier's `lock_jacobians_generic` same-multibody branch. 3D only;
-    // 2D keeps the separate-block path (no 2D loop-closure models in practice).
-    #[cfg(feature = "dim3")]
-    {
-        if a.side_kind == SIDE_KIND_MB && b.side_kind == SIDE_KIND_MB && a.side_id == b.side_id {
-            out.side_a_kind = a.side_kind;
-            out.side_a_id = a.side_id;
-            out.side_a_link = a.side_link;
-            out.ndofs_a = 0;
-            out.j_id_a = j_id_a;
-
-            out.side_b_kind = b.side_kind;
-            out.side_b_id = b.side_id;
-            out.side_b_link = b.side_link;
-            out.ndofs_b = b.ndofs;
-            out.j_id_b = j_id_b;
-
-            fill_relative_mb_jacobians(
-                jacobians,
-                j_id_b,
-                &b.mb,
-                a.side_link,
-                lin_jac,
-                ang_jac1,
-                b.side_link,
-                lin_jac,
-                ang_jac2,
-                body_jacobians,
-                il,
-            );
-
-            out.kind = 1;
-            out.impulse = 0.0;
-            out.impulse_lo = -MAX_FLT;
-            out.impulse_hi = MAX_FLT;
-            out.inv_lhs = 0.0;
-            out.rhs = 0.0;
-            out.rhs_wo_bias = 0.0;
-            out.cfm_coeff = 0.0;
-            out.cfm_gain = 0.0;
-            return;
-        }
+    // Mirrors rapier's `lock_jacobians_generic` same-multibody branch.
+    if a.side_kind == SIDE_KIND_MB && b.side_kind == SIDE_KIND_MB && a.side_id == b.side_id {
+        out.side_a_kind = a.side_kind;
+        out.side_a_id = a.side_id;
+        out.side_a_link = a.side_link;
+        out.ndofs_a = 0;
+        out.j_id_a = j_id_a;
+
+        out.side_b_kind = b.side_kind;
+        out.side_b_id = b.side_id;
+        out.side_b_link = b.side_link;
+        out.ndofs_b = b.ndofs;
+        out.j_id_b = j_id_b;
+
+        fill_relative_mb_jacobians(
+            jacobians,
+            j_id_b,
+            &b.mb,
+            a.side_link,
+            lin_jac,
+            ang_jac1,
+            b.side_link,
+            lin_jac,
+            ang_jac2,
+            body_jacobians,
+            il,
+        );
+
+        out.kind = 1;
+        out.impulse = 0.0;
+        out.impulse_lo = -MAX_FLT;
+        out.impulse_hi = MAX_FLT;
+        out.inv_lhs = 0.0;
+        out.rhs = 0.0;
+        out.rhs_wo_bias = 0.0;
+        out.cfm_coeff = 0.0;
+        out.cfm_gain = 0.0;
+        return;
     }
 
     out.side_a_kind = a.side_kind;
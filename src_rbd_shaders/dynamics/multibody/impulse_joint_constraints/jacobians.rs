@@ -301,9 +301,7 @@ pub(super) fn fill_mb_jacobians(
 /// Writes `J_rel` at `j_id` (the `M⁻¹·J_rel` back-solve is deferred to the
 /// finalize kernel). The caller stores this block on the constraint's "B" side
 /// and sets `ndofs_a = 0`. Mirrors `Multibody::fill_relative_jacobians` (rapier
-/// 9265a19b), including the cancellation guard. 3D only (2D falls back to the
-/// separate-block path).
-#[cfg(feature = "dim3")]
+/// 9265a19b), including the cancellation guard.
 #[allow(clippy::too_many_arguments)]
 pub(super) fn fill_relative_mb_jacobians(
     jacobians: &mut [f32],
@@ -332,35 +330,62 @@ pub(super) fn fill_relative_mb_jacobians(
     let mut jba_nsq = 0.0f32;
     let mut jbb_nsq = 0.0f32;
     for k in 0..ndofs {
-        let av0 = body_jacobians.read(la_v.idx(0, k));
-        let av1 = body_jacobians.read(la_v.idx(1, k));
-        let av2 = body_jacobians.read(la_v.idx(2, k));
-        let aw0 = body_jacobians.read(la_w.idx(0, k));
-        let aw1 = body_jacobians.read(la_w.idx(1, k));
-        let aw2 = body_jacobians.read(la_w.idx(2, k));
-        let bv0 = body_jacobians.read(lb_v.idx(0, k));
-        let bv1 = body_jacobians.read(lb_v.idx(1, k));
-        let bv2 = body_jacobians.read(lb_v.idx(2, k));
-        let bw0 = body_jacobians.read(lb_w.idx(0, k));
-        let bw1 = body_jacobians.read(lb_w.idx(1, k));
-        let bw2 = body_jacobians.read(lb_w.idx(2, k));
+        #[cfg(feature = "dim3")]
+        let (ja, jb, a_nsq, b_nsq) = {
+            let av0 = body_jacobians.read(la_v.idx(0, k));
+            let av1 = body_jacobians.read(la_v.idx(1, k));
+            let av2 = body_jacobians.read(la_v.idx(2, k));
+            let aw0 = body_jacobians.read(la_w.idx(0, k));
+            let aw1 = body_jacobians.read(la_w.idx(1, k));
+            let aw2 = body_jacobians.read(la_w.idx(2, k));
+            let bv0 = body_jacobians.read(lb_v.idx(0, k));
+            let bv1 = body_jacobians.read(lb_v.idx(1, k));
+            let bv2 = body_jacobians.read(lb_v.idx(2, k));
+            let bw0 = body_jacobians.read(lb_w.idx(0, k));
+            let bw1 = body_jacobians.read(lb_w.idx(1, k));
+            let bw2 = body_jacobians.read(lb_w.idx(2, k));
+
+            let ja = lin_a.x * av0
+                + lin_a.y * av1
+                + lin_a.z * av2
+                + ang_a.x * aw0
+                + ang_a.y * aw1
+                + ang_a.z * aw2;
+            let jb = lin_b.x * bv0
+                + lin_b.y * bv1
+                + lin_b.z * bv2
+                + ang_b.x * bw0
+                + ang_b.y * bw1
+                + ang_b.z * bw2;
+            (
+                ja,
+                jb,
+                av0 * av0 + av1 * av1 + av2 * av2 + aw0 * aw0 + aw1 * aw1 + aw2 * aw2,
+                bv0 * bv0 + bv1 * bv1 + bv2 * bv2 + bw0 * bw0 + bw1 * bw1 + bw2 * bw2,
+            )
+        };
+        #[cfg(feature = "dim2")]
+        let (ja, jb, a_nsq, b_nsq) = {
+            let av0 = body_jacobians.read(la_v.idx(0, k));
+            let av1 = body_jacobians.read(la_v.idx(1, k));
+            let aw = body_jacobians.read(la_w.idx(0, k));
+            let bv0 = body_jacobians.read(lb_v.idx(0, k));
+            let bv1 = body_jacobians.read(lb_v.idx(1, k));
+            let bw = body_jacobians.read(lb_w.idx(0, k));
 
-        let ja = lin_a.x * av0
-            + lin_a.y * av1
-            + lin_a.z * av2
-            + ang_a.x * aw0
-            + ang_a.y * aw1
-            + ang_a.z * aw2;
-        let jb = lin_b.x * bv0
-            + lin_b.y * bv1
-            + lin_b.z * bv2
-            + ang_b.x * bw0
-            + ang_b.y * bw1
-            + ang_b.z * bw2;
+            let ja = lin_a.x * av0 + lin_a.y * av1 + ang_a * aw;
+            let jb = lin_b.x * bv0 + lin_b.y * bv1 + ang_b * bw;
+            (
+                ja,
+                jb,
+                av0 * av0 + av1 * av1 + aw * aw,
+                bv0 * bv0 + bv1 * bv1 + bw * bw,
+            )
+        };
         jacobians.write(j_id as usize + k as usize, jb - ja);
 
-        jba_nsq += av0 * av0 + av1 * av1 + av2 * av2 + aw0 * aw0 + aw1 * aw1 + aw2 * aw2;
-        jbb_nsq += bv0 * bv0 + bv1 * bv1 + bv2 * bv2 + bw0 * bw0 + bw1 * bw1 + bw2 * bw2;
+        jba_nsq += a_nsq;
+        jbb_nsq += b_nsq;
     }
 
     // 2) Cancellation guard (rapier). The reference scale is the magnitude of
@@ -369,8 +394,16 @@ pub(super) fn fill_relative_mb_jacobians(
     //    constrained direction isn't expressible by the multibody's DOFs). A
     //    `J_rel` this small is noise, not a real constraint direction → zero it
     //    so it contributes no (huge, mass-near-zero) impulse.
-    let fa_sq = lin_a.dot(lin_a) + ang_a.dot(ang_a);
-    let fb_sq = lin_b.dot(lin_b) + ang_b.dot(ang_b);
+    #[cfg(feature = "dim3")]
+    let (fa_sq, fb_sq) = (
+        lin_a.dot(lin_a) + ang_a.dot(ang_a),
+        lin_b.dot(lin_b) + ang_b.dot(ang_b),
+    );
+    #[cfg(feature = "dim2")]
+    let (fa_sq, fb_sq) = (
+        lin_a.dot(lin_a) + ang_a * ang_a,
+        lin_b.dot(lin_b) + ang_b * ang_b,
+    );
     let scale_sq = jba_nsq * fa_sq + jbb_nsq * fb_sq;
     let mut jrel_nsq = 0.0f32;
     for k in 0..ndofs {
@@ -1,5 +1,5 @@
-//! The four compute entry points of the multibody impulse-joint pipeline
-//! (update / finalize / solve / remove-bias).
+//! The compute entry points of the multibody impulse-joint pipeline
+//! (update / finalize / solve / remove-bias / wrenches).
 
 use glamx::Vec4;
 use khal_std::glamx::UVec3;
@@ -9,10 +9,11 @@ use khal_std::sync::workgroup_memory_barrier_with_group_sync;
 
 use crate::Pose;
 use crate::dynamics::ConstraintSoftness;
-use crate::dynamics::body::{Velocity, WorldMassProperties};
+use crate::dynamics::body::{Force, Velocity, WorldMassProperties};
 use crate::utils::BatchIndices;
 use crate::utils::linalg::VSlice;
 
+use super::super::joint_constraints::add_axis_effort;
 use super::super::lu::LANES;
 use super::super::types::{MultibodyInfo, MultibodyLinkStatic};
 
@@ -372,3 +373,56 @@ pub fn gpu_mb_remove_impulse_joint_constraint_bias(
         i += num_threads;
     }
 }
+
+/// Computes the reaction wrench of every multibody-touching impulse joint, after
+/// the last substep — the counterpart of `gpu_compute_joint_wrenches` for the
+/// joints solved here.
+///
+/// Side B receives `-impulse · J_b`, so this is the wrench applied by the joint
+/// on its second side, with the torque taken about the second anchor, expressed
+/// in the joint frame attached to the first side. It is written at the joint's
+/// insertion index (`MbImpulseJointBuilder::joint_id`), not at its color-sorted
+/// slot.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_mb_compute_impulse_joint_wrenches(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] builders: &[MbImpulseJointBuilder],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)]
+    constraints: &[MbImpulseJointConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] wrenches: &mut [Force],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] dt_uniform: &f32,
+    #[spirv(uniform, descriptor_set = 0, binding = 4)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * 64;
+    let batch_id = invocation_id.y;
+    let cap = batch_ids.mb_imp_joints_batch_capacity;
+    if invocation_id.x >= cap {
+        return;
+    }
+    let inv_dt = 1.0 / *dt_uniform;
+
+    let joints_start = batch_ids.mb_imp_joints_start(batch_id);
+    let cons_start = batch_ids.mb_imp_joint_constraints_start(batch_id);
+
+    let mut i = invocation_id.x;
+    while i < cap {
+        let builder = builders.read(joints_start + i as usize);
+        let is_dummy =
+            builder.side_a_kind == SIDE_KIND_FIXED && builder.side_b_kind == SIDE_KIND_FIXED;
+        // Padding builders share `joint_id == 0`, they must not overwrite it.
+        if !is_dummy {
+            let cons_base = cons_start + builder.constraint_id as usize;
+            let mut wrench = Force::default();
+            for s in 0..MAX_AXIS_CONSTRAINTS {
+                let c = constraints.read(cons_base + s as usize);
+                if c.kind != 0 {
+                    add_axis_effort(&mut wrench, c.writeback_axis, -c.impulse * inv_dt);
+                }
+            }
+            wrenches.write(joints_start + builder.joint_id as usize, wrench);
+        }
+        i += num_threads;
+    }
+}
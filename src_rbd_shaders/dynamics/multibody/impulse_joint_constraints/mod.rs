@@ -12,6 +12,8 @@
 //!      sides' velocities.
 //!   4. `gpu_mb_remove_impulse_joint_constraint_bias` — strips the positional
 //!      bias from `rhs` before the stabilization sweep.
+//!   5. `gpu_mb_compute_impulse_joint_wrenches` — once per step, after the last
+//!      substep, turns the impulses into reaction wrenches.
 
 mod helper;
 mod jacobians;
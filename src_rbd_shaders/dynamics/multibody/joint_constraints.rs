@@ -12,10 +12,11 @@ use khal_std::macros::{spirv, spirv_bindgen};
 use khal_std::sync::control_barrier;
 
 use crate::dynamics::ConstraintSoftness;
+use crate::dynamics::body::Force;
 use crate::dynamics::joint::SPATIAL_DIM;
-use crate::utils::BatchIndices;
 use crate::utils::linalg::{MatSlice, VSlice, lu_solve_in_place};
-use crate::{DIM, MAX_FLT};
+use crate::utils::{BatchIndices, ISliceMut};
+use crate::{DIM, MAX_FLT, RotMatrix};
 
 use super::types::{
     MB_JOINT_KIND_COUPLING, MB_JOINT_KIND_LIMIT, MB_JOINT_KIND_LIMIT_INACTIVE, MB_JOINT_KIND_MOTOR,
@@ -550,3 +551,87 @@ pub fn gpu_mb_init_joint_constraints(
         }
     }
 }
+
+/// Adds the generalized effort `effort` of the joint axis packed in `link_axis`
+/// (`link_id | axis << 16`) to that link's joint wrench.
+#[inline]
+fn add_joint_effort(wrenches: &mut ISliceMut<Force>, link_axis: u32, effort: f32) {
+    let link_id = (link_axis & 0xffff) as usize;
+    add_axis_effort(&mut wrenches[link_id], link_axis >> 16, effort);
+}
+
+/// Adds the effort `effort` along the joint axis `axis` (`0..DIM` for linear
+/// axes, `DIM..SPATIAL_DIM` for angular ones) to the joint-frame `wrench`.
+#[inline]
+pub(crate) fn add_axis_effort(wrench: &mut Force, axis: u32, effort: f32) {
+    if axis < DIM {
+        wrench.linear += RotMatrix::IDENTITY.col(axis as usize) * effort;
+    } else {
+        #[cfg(feature = "dim2")]
+        {
+            wrench.angular += effort;
+        }
+        #[cfg(feature = "dim3")]
+        {
+            wrench.angular += RotMatrix::IDENTITY.col((axis - DIM) as usize) * effort;
+        }
+    }
+}
+
+/// Computes the joint wrench of every link, after the last substep.
+///
+/// This is the wrench applied by each link's joint on the link, expressed in the
+/// joint frame. Reduced coordinates leave no constraint force along the locked
+/// axes, so only the efforts of the limits, motors and DoF couplings along the
+/// free axes are reported; the locked components are zero.
+///
+/// One thread per (multibody, batch).
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_mb_compute_joint_wrenches(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] multibody_info: &[MultibodyInfo],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)]
+    joint_constraints: &[MultibodyJointConstraint],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] link_wrenches: &mut [Force],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] dt_uniform: &f32,
+    #[spirv(uniform, descriptor_set = 0, binding = 4)] batch_ids: &BatchIndices,
+) {
+    let num_mb = batch_ids.multibodies_len;
+    if invocation_id.x >= num_mb * batch_ids.num_batches {
+        return;
+    }
+    let batch_id = invocation_id.x / num_mb;
+    let mb_idx = invocation_id.x % num_mb;
+    let dt = *dt_uniform;
+    let inv_dt = if dt != 0.0 { 1.0 / dt } else { 0.0 };
+
+    let mb = batch_ids.ib(batch_id, multibody_info).read(mb_idx as usize);
+    let mut wrenches = batch_ids
+        .ib_mut(batch_id, link_wrenches)
+        .offset(mb.first_link as usize);
+    for k in 0..mb.num_links {
+        wrenches[k as usize] = Force::default();
+    }
+
+    // The solve applies `-impulse · J` to the generalized velocities, with
+    // `J = e_{dof_id} − coupling_coeff·e_{dof2_id}`.
+    let cons_base = batch_ids.mb_joint_constraints_start(batch_id) + mb.first_constraint as usize;
+    for s in 0..mb.max_constraints {
+        let cons = joint_constraints.read(cons_base + s as usize);
+        if cons.kind != MB_JOINT_KIND_LIMIT
+            && cons.kind != MB_JOINT_KIND_MOTOR
+            && cons.kind != MB_JOINT_KIND_COUPLING
+        {
+            continue;
+        }
+        add_joint_effort(&mut wrenches, cons._kind_extra, -cons.impulse * inv_dt);
+        if cons.kind == MB_JOINT_KIND_COUPLING {
+            add_joint_effort(
+                &mut wrenches,
+                cons._kind_extra2,
+                cons.coupling_coeff * cons.impulse * inv_dt,
+            );
+        }
+    }
+}
@@ -1,4 +1,4 @@
-//! Reduced-coordinates articulated multibody (3D).
+//! Reduced-coordinates articulated multibody.
 //!
 //! GPU port of rapier's `Multibody`, restricted to:
 //! - Forward kinematics (link world transforms from generalized coordinates).
@@ -15,6 +15,7 @@
 
 mod compute_dynamics_pre;
 mod contact_constraints;
+mod env_snapshots;
 mod gravity_and_lu;
 mod impulse_joint_constraints;
 mod integrate;
@@ -28,6 +29,7 @@ mod ws_soa;
 
 pub use compute_dynamics_pre::*;
 pub use contact_constraints::*;
+pub use env_snapshots::*;
 pub use gravity_and_lu::*;
 pub use impulse_joint_constraints::*;
 pub use integrate::*;
@@ -356,6 +356,14 @@ pub fn ws_set_coord(buf: &mut [Vec4], a: WsAddr, k: u32, i: u32, v: f32) {
     buf.write(idx, vec4_set(q, i % 4, v));
 }
 
+/// Read-modify-write of component `i` of the quad `f` of link `k`.
+#[inline]
+pub fn ws_set_component(buf: &mut [Vec4], a: WsAddr, k: u32, f: u32, i: u32, v: f32) {
+    let idx = a.at(k, f);
+    let q = buf.read(idx);
+    buf.write(idx, vec4_set(q, i, v));
+}
+
 /// Load the whole coords array (for `body_to_parent`).
 #[cfg(feature = "dim3")]
 #[inline]
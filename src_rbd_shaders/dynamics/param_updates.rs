@@ -0,0 +1,220 @@
+//! Per-environment writes to the physical parameters.
+//!
+//! The host queues, for individual batches, new collider frictions and
+//! restitutions, body masses, multibody DOF dampings and armatures, and gravity
+//! components. Each write carries either its value or the distribution it is
+//! drawn from on the GPU, and all of them are scattered with a single dispatch
+//! before the next step. This is the domain randomization applied between
+//! episodes, without rebuilding the simulation.
+
+use glamx::Vec4;
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::macros::{spirv, spirv_bindgen};
+
+use crate::Vector;
+use crate::queries::ColliderMaterial;
+use crate::utils::BatchIndices;
+
+use super::body::LocalMassProperties;
+use super::multibody::MultibodyLinkStatic;
+
+/// Sets the friction coefficient of the collider `id`.
+pub const PARAM_FRICTION: u32 = 0;
+/// Sets the restitution coefficient of the collider `id`.
+pub const PARAM_RESTITUTION: u32 = 1;
+/// Sets the mass of the body `id`, scaling its angular inertia by the same
+/// ratio. Bodies with an infinite mass are left untouched.
+pub const PARAM_MASS: u32 = 2;
+/// Sets the damping of the multibody DOF `id`.
+pub const PARAM_DOF_DAMPING: u32 = 3;
+/// Sets the armature of the multibody DOF `id`.
+pub const PARAM_DOF_ARMATURE: u32 = 4;
+/// Sets the component `id` of the gravity.
+pub const PARAM_GRAVITY: u32 = 5;
+
+/// The value is [`ParamUpdate::a`].
+pub const PARAM_SAMPLE_VALUE: u32 = 0;
+/// The value is drawn uniformly in `[a, b[`.
+pub const PARAM_SAMPLE_UNIFORM: u32 = 1;
+/// The value is drawn from the normal distribution of mean `a` and standard
+/// deviation `b`.
+pub const PARAM_SAMPLE_NORMAL: u32 = 2;
+
+/// A write to one physical parameter of one batch.
+///
+/// At most one update per parameter is dispatched at once: the host keeps the
+/// last write queued for each.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct ParamUpdate {
+    /// One of the `PARAM_*` parameter constants.
+    pub kind: u32,
+    /// The batch the parameter belongs to.
+    pub batch_id: u32,
+    /// The local collider or body slot, the per-batch DOF index, or the gravity
+    /// axis the update writes to.
+    pub id: u32,
+    /// For [`PARAM_MASS`], the per-batch index of the multibody link backed by
+    /// the body, or `u32::MAX` if it isn't a link.
+    pub link_id: u32,
+    /// One of the `PARAM_SAMPLE_*` constants.
+    pub sampler: u32,
+    /// The value, lower bound or mean, depending on the sampler.
+    pub a: f32,
+    /// The upper bound or standard deviation, depending on the sampler.
+    pub b: f32,
+    pub padding: u32,
+}
+
+/// The number of queued [`ParamUpdate`]s and the seed of their samples.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct ParamUpdatesHeader {
+    pub num_updates: u32,
+    /// The user-provided seed.
+    pub seed: u32,
+    /// Incremented at every dispatch so that successive draws differ.
+    pub draw: u32,
+    pub padding: u32,
+}
+
+/// Scatters the queued [`ParamUpdate`]s into the parameter buffers.
+///
+/// Dispatched with one thread per update. Sampled values other than gravity
+/// components are clamped to be nonnegative, and a zero mass is ignored.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_apply_param_updates(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] updates: &[ParamUpdate],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)]
+    collider_materials: &mut [ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)]
+    local_mprops: &mut [LocalMassProperties],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)]
+    links_static: &mut [MultibodyLinkStatic],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] dof_state: &mut [f32],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] gravity: &mut [Vec4],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] header: &ParamUpdatesHeader,
+    #[spirv(uniform, descriptor_set = 0, binding = 7)] batch_ids: &BatchIndices,
+) {
+    let i = invocation_id.x;
+    if i >= header.num_updates {
+        return;
+    }
+
+    let update = *updates.at(i as usize);
+    // Keyed by the parameter rather than by `i`, so that a draw doesn't depend
+    // on the other updates dispatched along with it.
+    let param = pcg_hash(update.batch_id ^ pcg_hash(update.kind ^ pcg_hash(update.id)));
+    let key = pcg_hash(header.seed ^ pcg_hash(header.draw ^ param));
+    let value = sample(&update, key);
+    let batch_id = update.batch_id;
+
+    if update.kind == PARAM_GRAVITY {
+        let g = gravity.at_mut(batch_id as usize);
+        if update.id == 0 {
+            g.x = value;
+        } else if update.id == 1 {
+            g.y = value;
+        } else {
+            g.z = value;
+        }
+        return;
+    }
+
+    let value = value.max(0.0);
+
+    if update.kind == PARAM_FRICTION || update.kind == PARAM_RESTITUTION {
+        let id = batch_ids.coll_start(batch_id) + update.id as usize;
+        let material = collider_materials.at_mut(id);
+        if update.kind == PARAM_FRICTION {
+            material.friction = value;
+        } else {
+            material.restitution = value;
+        }
+    } else if update.kind == PARAM_MASS {
+        if value == 0.0 {
+            return;
+        }
+        let id = batch_ids.coll_start(batch_id) + update.id as usize;
+        let mprops = *local_mprops.at(id);
+        *local_mprops.at_mut(id) = with_mass(mprops, value);
+        if update.link_id != u32::MAX {
+            let idx = batch_ids.mbi(batch_id, update.link_id as usize);
+            let mut stat = *links_static.at(idx);
+            stat.local_mprops = with_mass(stat.local_mprops, value);
+            *links_static.at_mut(idx) = stat;
+        }
+    } else if update.kind == PARAM_DOF_DAMPING || update.kind == PARAM_DOF_ARMATURE {
+        // Damping and armature are the second and third sections of `dof_state`.
+        let section = if update.kind == PARAM_DOF_DAMPING {
+            1
+        } else {
+            2
+        };
+        let intra = section * batch_ids.dof_batch_capacity as usize + update.id as usize;
+        *dof_state.at_mut(batch_ids.mbi(batch_id, intra)) = value;
+    }
+}
+
+/// `mprops` with a mass of `mass`, and its angular inertia scaled by the same
+/// ratio. Left unchanged if its mass is infinite.
+#[inline]
+fn with_mass(mprops: LocalMassProperties, mass: f32) -> LocalMassProperties {
+    let old_inv_mass = mprops.inv_mass.max_element();
+    if old_inv_mass == 0.0 {
+        return mprops;
+    }
+
+    let inv_mass = 1.0 / mass;
+    let ratio = inv_mass / old_inv_mass;
+    let mut out = mprops;
+    out.inv_mass = Vector::select(
+        mprops.inv_mass.cmpne(Vector::ZERO),
+        Vector::splat(inv_mass),
+        Vector::ZERO,
+    );
+    #[cfg(feature = "dim2")]
+    {
+        out.inv_inertia *= ratio;
+    }
+    #[cfg(feature = "dim3")]
+    {
+        out.inv_principal_inertia *= ratio;
+    }
+    out
+}
+
+/// Draws the value of `update` from the random bits `key`.
+#[inline]
+fn sample(update: &ParamUpdate, key: u32) -> f32 {
+    if update.sampler == PARAM_SAMPLE_UNIFORM {
+        update.a + (update.b - update.a) * unit_f32(key)
+    } else if update.sampler == PARAM_SAMPLE_NORMAL {
+        // Box-Muller transform. `u1` is in `]0, 1]` so its log is finite.
+        let u1 = 1.0 - unit_f32(key);
+        let u2 = unit_f32(pcg_hash(key));
+        let z = crate::sqrt(-2.0 * crate::ln(u1)) * crate::cos(core::f32::consts::TAU * u2);
+        update.a + update.b * z
+    } else {
+        update.a
+    }
+}
+
+/// PCG hash of a 32-bit value.
+#[inline]
+fn pcg_hash(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+/// Maps random bits to a float uniformly distributed in `[0, 1[`.
+#[inline]
+fn unit_f32(bits: u32) -> f32 {
+    (bits >> 8) as f32 * (1.0 / 16_777_216.0)
+}
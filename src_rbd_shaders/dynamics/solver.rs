@@ -12,7 +12,9 @@ use khal_std::{
     sync::{atomic_add_u32, control_barrier},
 };
 
-use super::body::{LocalMassProperties, Velocity, WorldMassProperties};
+use super::body::{
+    BodyParams, Force, LocalMassProperties, Velocity, WorldMassProperties, is_kinematic,
+};
 use super::constraint::{TwoBodyConstraint, TwoBodyConstraintBuilder};
 use super::sim_params::RbdSimParams;
 use super::solver_utils::warmstart_body;
@@ -110,11 +112,11 @@ pub fn gpu_solver_count_constraints(
         // own dynamics but its bodies still need correct coloring so contacts
         // touching different links of the same multibody never share a color).
         let is_mb1 = group1 != body1;
-        if mprops[body1 as usize].inv_mass != Vector::ZERO || is_mb1 {
+        if mprops[body1 as usize].is_dynamic() || is_mb1 {
             atomic_add_u32(&mut body_constraint_counts[group1 as usize], 1);
         }
         let is_mb2 = group2 != body2;
-        if mprops[body2 as usize].inv_mass != Vector::ZERO || is_mb2 {
+        if mprops[body2 as usize].is_dynamic() || is_mb2 {
             atomic_add_u32(&mut body_constraint_counts[group2 as usize], 1);
         }
     }
@@ -218,13 +220,13 @@ pub fn gpu_solver_sort_constraints(
         let group2 = body_group[body2] as usize;
 
         let is_mb1 = group1 != body1;
-        if mprops[body1].inv_mass != Vector::ZERO || is_mb1 {
+        if mprops[body1].is_dynamic() || is_mb1 {
             let id1 = atomic_add_u32(&mut body_constraint_counts[group1], 1);
             body_constraint_ids[id1 as usize] = i;
         }
 
         let is_mb2 = group2 != body2;
-        if mprops[body2].inv_mass != Vector::ZERO || is_mb2 {
+        if mprops[body2].is_dynamic() || is_mb2 {
             let id2 = atomic_add_u32(&mut body_constraint_counts[group2], 1);
             body_constraint_ids[id2 as usize] = i;
         }
@@ -241,23 +243,26 @@ pub fn gpu_solver_cleanup(
     #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] solver_vels: &mut [Velocity],
     #[spirv(storage_buffer, descriptor_set = 1, binding = 0)] vels: &[Velocity],
     #[spirv(storage_buffer, descriptor_set = 1, binding = 1)] mprops: &[WorldMassProperties],
-    #[spirv(uniform, descriptor_set = 1, binding = 2)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 2)] body_types: &[u32],
+    #[spirv(uniform, descriptor_set = 1, binding = 3)] batch_ids: &BatchIndices,
 ) {
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
-    let num_bodies = batch_ids.colliders_len;
+    let num_bodies = batch_ids.colliders_len(batch_id);
 
     let mut body_constraint_counts = batch_ids.coll_batch_mut(batch_id, body_constraint_counts);
     let mut solver_vels = batch_ids.coll_batch_mut(batch_id, solver_vels);
     let vels = batch_ids.coll_batch(batch_id, vels);
     let mprops = batch_ids.coll_batch(batch_id, mprops);
+    let body_types = batch_ids.coll_batch(batch_id, body_types);
 
     for i in StepRng::new(invocation_id.x..num_bodies, num_threads) {
         let idx = i as usize;
         body_constraint_counts[idx] = 0;
 
-        // HACK: to handle static bodies.
-        if mprops[idx].inv_mass != Vector::ZERO {
+        // HACK: to handle static bodies. Kinematic bodies have a zero mass too
+        //       but keep moving at their (user-defined or target-derived) velocity.
+        if mprops[idx].is_dynamic() || is_kinematic(body_types[idx]) {
             solver_vels[idx].linear = vels[idx].linear;
             solver_vels[idx].angular = vels[idx].angular;
         } else {
@@ -274,17 +279,21 @@ pub fn gpu_init_solver_vels_inc(
     #[spirv(global_invocation_id)] invocation_id: UVec3,
     #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] solver_vels_inc: &mut [Velocity],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] mprops: &[WorldMassProperties],
-    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] all_params: &[RbdSimParams],
-    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
-    #[spirv(uniform, descriptor_set = 0, binding = 4)] gravity: &glamx::Vec4,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] forces: &[Force],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] all_params: &[RbdSimParams],
+    #[spirv(uniform, descriptor_set = 0, binding = 5)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 6)] gravity: &[glamx::Vec4],
 ) {
     let batch_id = invocation_id.y;
     let params = all_params.at(batch_id as usize);
     let i = invocation_id.x;
 
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
     let mut solver_vels_inc = batch_ids.coll_batch_mut(batch_id, solver_vels_inc);
     let mprops = batch_ids.coll_batch(batch_id, mprops);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
+    let forces = batch_ids.coll_batch(batch_id, forces);
 
     if i < num_bodies {
         let idx = i as usize;
@@ -292,14 +301,22 @@ pub fn gpu_init_solver_vels_inc(
         solver_vels_inc[idx].angular = AngVector::default();
 
         // TODO: this isn't a very pretty way of detecting static bodies.
-        if mprops[idx].inv_mass != Vector::ZERO {
-            // TODO: this currently only handles gravity (no user forces yet).
+        let inv_mass = mprops[idx].inv_mass;
+        if inv_mass != Vector::ZERO {
+            let gravity = gravity.at(batch_id as usize);
             #[cfg(feature = "dim3")]
             let g = Vector::new(gravity.x, gravity.y, gravity.z);
             #[cfg(feature = "dim2")]
             let g = Vector::new(gravity.x, gravity.y);
-            solver_vels_inc[idx].linear = g * params.dt;
+            // Locked translation axes have a zero inverse mass: don't accelerate them.
+            let g = Vector::select(inv_mass.cmpne(Vector::ZERO), g, Vector::ZERO);
+            solver_vels_inc[idx].linear = g * (body_params[idx].gravity_scale * params.dt);
         }
+
+        // User forces. Non-dynamic bodies have a zero inverse mass and inertia so
+        // this is a no-op for them.
+        solver_vels_inc[idx] =
+            forces[idx].integrate(&mprops[idx], &solver_vels_inc[idx], params.dt);
     }
 }
 
@@ -315,7 +332,7 @@ pub fn gpu_apply_solver_vels_inc(
     let batch_id = invocation_id.y;
     let i = invocation_id.x;
 
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
     let mut solver_vels = batch_ids.coll_batch_mut(batch_id, solver_vels);
     let solver_vels_inc = batch_ids.coll_batch(batch_id, solver_vels_inc);
 
@@ -341,7 +358,7 @@ pub fn gpu_warmstart_without_colors(
     let num_threads = num_workgroups.x * WORKGROUP_SIZE;
     let batch_id = invocation_id.y;
     let bci_start = batch_id as usize * 2 * batch_ids.contacts_batch_capacity as usize;
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
 
     let body_constraint_counts = batch_ids.coll_batch(batch_id, body_constraint_counts);
     let body_constraint_ids = Slice(body_constraint_ids, bci_start);
@@ -603,21 +620,27 @@ pub fn gpu_integrate_linearized(
     #[spirv(global_invocation_id)] invocation_id: UVec3,
     #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] poses: &mut [Pose],
     #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] solver_vels: &mut [Velocity],
-    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] all_params: &[RbdSimParams],
-    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] body_params: &[BodyParams],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] all_params: &[RbdSimParams],
+    #[spirv(uniform, descriptor_set = 0, binding = 4)] batch_ids: &BatchIndices,
 ) {
     let batch_id = invocation_id.y;
     let params = all_params.at(batch_id as usize);
     let i = invocation_id.x;
 
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
     let mut poses = batch_ids.coll_batch_mut(batch_id, poses);
     let mut solver_vels = batch_ids.coll_batch_mut(batch_id, solver_vels);
+    let body_params = batch_ids.coll_batch(batch_id, body_params);
 
     if i < num_bodies {
         let idx = i as usize;
         let mut vels = solver_vels[idx];
 
+        let body_params = body_params[idx];
+        body_params.apply_damping(&mut vels, params.dt);
+        body_params.lock_velocity(&mut vels);
+
         let max_lin = params.max_linear_velocity();
         let lin_norm = vels.linear.length();
         if lin_norm > max_lin {
@@ -668,7 +691,7 @@ pub fn gpu_init_solver_bodies(
     let batch_id = invocation_id.y;
     let i = invocation_id.x;
 
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
     let body_poses = batch_ids.coll_batch(batch_id, body_poses);
     let local_mprops = batch_ids.coll_batch(batch_id, local_mprops);
     let mut solver_body_poses = batch_ids.coll_batch_mut(batch_id, solver_body_poses);
@@ -699,7 +722,7 @@ pub fn gpu_solver_finalize(
     let batch_id = invocation_id.y;
     let i = invocation_id.x;
 
-    let num_bodies = batch_ids.bodies_len;
+    let num_bodies = batch_ids.bodies_len(batch_id);
     let mut vels = batch_ids.coll_batch_mut(batch_id, vels);
     let solver_vels = batch_ids.coll_batch(batch_id, solver_vels);
     let mut body_poses = batch_ids.coll_batch_mut(batch_id, body_poses);
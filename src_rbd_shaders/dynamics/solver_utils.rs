@@ -115,8 +115,19 @@ impl IndexedManifold {
         let bid1 = self.bodies.x;
         let bid2 = self.bodies.y;
 
-        let mprops1 = &mprops[bid1 as usize];
-        let mprops2 = &mprops[bid2 as usize];
+        let mut mprops1 = mprops[bid1 as usize];
+        let mut mprops2 = mprops[bid2 as usize];
+        // Dominance: the body with the highest dominance group behaves as if it had
+        // an infinite mass with respect to this contact (as in rapier). Non-dynamic
+        // bodies already have an infinite mass and dominate every dynamic body, so
+        // they never make the other body infinite too.
+        let dominance1 = mprops1.effective_dominance_group();
+        let dominance2 = mprops2.effective_dominance_group();
+        if dominance1 > dominance2 {
+            mprops1.make_infinite();
+        } else if dominance2 > dominance1 {
+            mprops2.make_infinite();
+        }
         // Contact features (`points_a`, `normal_a`) are stored in collider A's
         // local space, so only `cpose1` is needed to recover their world-space
         // forms; collider B's pose isn't read here.
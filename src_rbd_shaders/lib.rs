@@ -371,6 +371,12 @@ pub fn cos(x: f32) -> f32 {
     Float::cos(x)
 }
 
+/// Computes the natural logarithm of a value.
+#[inline]
+pub fn ln(x: f32) -> f32 {
+    Float::ln(x)
+}
+
 /// Computes the arcsine of a value.
 #[inline]
 pub fn asin(x: f32) -> f32 {
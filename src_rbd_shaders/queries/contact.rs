@@ -26,6 +26,9 @@ use crate::Vector;
 /// [`ColliderMaterial::combined_restitution`] (the rapier
 /// `CoefficientCombineRule`). The `*_combine_rule` fields hold the rule as
 /// `CoefficientCombineRule as u32` (Average = 0 .. ClampedSum = 4).
+///
+/// The `flags` carry the per-collider behavior bits (see [`COLLIDER_FLAG_SENSOR`],
+/// [`COLLIDER_FLAG_COLLISION_EVENTS`] and [`COLLIDER_FLAG_CONTACT_FORCE_EVENTS`]).
 #[derive(Clone, Copy)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
 #[repr(C)]
@@ -38,8 +41,26 @@ pub struct ColliderMaterial {
     pub friction_combine_rule: u32,
     /// `CoefficientCombineRule as u32` used to merge restitution with the other collider's.
     pub restitution_combine_rule: u32,
+    /// Bitwise combination of the `COLLIDER_FLAG_*` constants.
+    pub flags: u32,
+    /// Total contact force magnitude above which a contact-force event is emitted,
+    /// if [`COLLIDER_FLAG_CONTACT_FORCE_EVENTS`] is set.
+    pub contact_force_event_threshold: f32,
+    /// Index of this collider among the consecutive collider slots of its
+    /// compound's parts (0 for a non-compound collider). The compound's first
+    /// part is at `slot - part_id`, which is where its events are reported.
+    pub part_id: u32,
 }
 
+/// Flag of sensor colliders: they detect intersections but never generate contacts.
+pub const COLLIDER_FLAG_SENSOR: u32 = 1;
+/// Flag of colliders emitting collision-started/stopped events when they start or
+/// stop touching another collider (rapier's `ActiveEvents::COLLISION_EVENTS`).
+pub const COLLIDER_FLAG_COLLISION_EVENTS: u32 = 2;
+/// Flag of colliders emitting contact-force events (rapier's
+/// `ActiveEvents::CONTACT_FORCE_EVENTS`).
+pub const COLLIDER_FLAG_CONTACT_FORCE_EVENTS: u32 = 4;
+
 impl Default for ColliderMaterial {
     #[inline]
     fn default() -> Self {
@@ -50,6 +71,9 @@ impl Default for ColliderMaterial {
             restitution: 0.0,
             friction_combine_rule: 0,
             restitution_combine_rule: 0,
+            flags: 0,
+            contact_force_event_threshold: 0.0,
+            part_id: 0,
         }
     }
 }
@@ -78,6 +102,29 @@ fn combine_coefficient(coeff1: f32, coeff2: f32, rule1: u32, rule2: u32) -> f32
 }
 
 impl ColliderMaterial {
+    /// Is this the material of a sensor collider?
+    #[inline(always)]
+    pub fn is_sensor(&self) -> bool {
+        self.flags & COLLIDER_FLAG_SENSOR != 0
+    }
+
+    /// Does this collider emit collision-started/stopped events?
+    #[inline(always)]
+    pub fn has_collision_events(&self) -> bool {
+        self.flags & COLLIDER_FLAG_COLLISION_EVENTS != 0
+    }
+
+    /// The contact force magnitude above which this collider emits contact-force
+    /// events: infinite if it doesn't emit any.
+    #[inline(always)]
+    pub fn effective_contact_force_event_threshold(&self) -> f32 {
+        if self.flags & COLLIDER_FLAG_CONTACT_FORCE_EVENTS != 0 {
+            self.contact_force_event_threshold
+        } else {
+            f32::INFINITY
+        }
+    }
+
     /// Effective friction for a contact between `self` and `other`.
     #[inline(always)]
     pub fn combined_friction(&self, other: &ColliderMaterial) -> f32 {
@@ -3,6 +3,7 @@
 //! This module provides various query algorithms for collision detection:
 //! - Point projection onto shapes
 //! - Ray casting
+//! - Batched scene queries (ray casts, shape casts, point projections)
 //! - Contact manifold generation
 //! - GJK/EPA algorithms for convex shapes
 //! - SAT (Separating Axis Theorem) for specific shape pairs
@@ -15,6 +16,7 @@ mod polygonal_feature;
 mod projection;
 mod ray;
 mod sat;
+mod scene_queries;
 
 pub use contact::*;
 pub use contact_manifold::*;
@@ -26,6 +28,7 @@ pub use projection::{
     ProjectionWithLocation,
 };
 pub use ray::*;
+pub use scene_queries::*;
 // Re-export sat items explicitly; EPSILON comes from projection
 use crate::Vector;
 use crate::queries::projection::EPSILON;
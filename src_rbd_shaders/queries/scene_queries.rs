@@ -0,0 +1,901 @@
+//! Batched scene queries: ray casts, shape casts and point projections.
+//!
+//! Every query is tested against the colliders of its own batch by traversing
+//! that batch's LBVH, skipping the colliders rejected by the query's
+//! [`SceneQueryFilter`]. The tree is traversed without a stack, following its
+//! parent pointers, so deep trees are never truncated. Convex shapes are cast
+//! with conservative advancement on top of GJK, composite shapes (trimeshes,
+//! polylines, heightfields) are cast against each of their sub-shapes
+//! overlapping the query's path.
+
+use crate::bounding_volumes::Aabb;
+use crate::broad_phase::{LbvhNode, LbvhTraversal, root_id};
+use crate::queries::gjk::CLOSEST_POINTS;
+use crate::queries::{
+    COLLIDER_FLAG_SENSOR, ColliderMaterial, ProjectionResult, contact_support_map_support_map,
+};
+#[cfg(feature = "dim3")]
+use crate::shapes::SHAPE_TYPE_CONE;
+#[cfg(feature = "dim3")]
+use crate::shapes::SHAPE_TYPE_CYLINDER;
+#[cfg(feature = "dim3")]
+use crate::shapes::SHAPE_TYPE_TETRAHEDRON;
+use crate::shapes::{
+    Capsule, PfmSubShape, SHAPE_TYPE_BALL, SHAPE_TYPE_CAPSULE, SHAPE_TYPE_CUBOID,
+    SHAPE_TYPE_HEIGHTFIELD, SHAPE_TYPE_POLYLINE, SHAPE_TYPE_SEGMENT, SHAPE_TYPE_TRIANGLE,
+    SHAPE_TYPE_TRIMESH, Shape,
+};
+use crate::utils::{BatchIndices, Slice};
+use crate::{F32_EPSILON, MAX_FLT, PaddedVector, Pose, Rotation, Vector};
+use khal_std::glamx::UVec3;
+use khal_std::index::MaybeIndexUnchecked;
+use khal_std::iter::StepRng;
+use khal_std::macros::{spirv, spirv_bindgen};
+use rapier::geometry::InteractionGroups;
+
+const WORKGROUP_SIZE: u32 = 64;
+/// Maximum number of conservative advancement steps for a single cast.
+const MAX_CAST_ITERATIONS: u32 = 32;
+/// Distance under which a cast shape is considered touching its target.
+const CAST_TOLERANCE: f32 = 1.0e-4;
+/// Value of [`SceneQueryHit::collider`] and [`SceneQueryHit::feature_id`] when unset.
+pub const SCENE_QUERY_NONE: u32 = u32::MAX;
+/// Flag of [`SceneQueryFilter::flags`]: sensor colliders can be hit by the query.
+pub const SCENE_QUERY_INCLUDE_SENSORS: u32 = 1;
+
+/// Selects the colliders a scene query can hit.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct SceneQueryFilter {
+    /// Only colliders with collision groups compatible with these groups can be hit.
+    pub groups: InteractionGroups,
+    /// Bitwise combination of the `SCENE_QUERY_*` flags.
+    pub flags: u32,
+}
+
+impl Default for SceneQueryFilter {
+    /// Hits every non-sensor collider.
+    fn default() -> Self {
+        Self {
+            groups: InteractionGroups::all(),
+            flags: 0,
+        }
+    }
+}
+
+/// The per-collider data tested against [`SceneQueryFilter`]s.
+///
+/// Gathered by [`gpu_scene_query_colliders`] so the query kernels only bind
+/// one extra buffer for filtering.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct SceneQueryCollider {
+    /// The collider's collision groups.
+    pub groups: InteractionGroups,
+    /// The collider's `COLLIDER_FLAG_*` flags.
+    pub flags: u32,
+}
+
+impl SceneQueryFilter {
+    /// Can a query with this filter hit `collider`?
+    #[inline]
+    pub fn test(&self, collider: &SceneQueryCollider) -> bool {
+        let sensor_ok = self.flags & SCENE_QUERY_INCLUDE_SENSORS != 0
+            || collider.flags & COLLIDER_FLAG_SENSOR == 0;
+        sensor_ok && self.groups.test(collider.groups)
+    }
+}
+
+/// A ray to cast against the scene.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct RayQuery {
+    /// The ray's starting point, in world-space.
+    pub origin: Vector,
+    /// The maximum time of impact, in multiples of `dir`.
+    pub max_toi: f32,
+    #[cfg(feature = "dim2")]
+    pub padding0: u32,
+    /// The ray's direction (not necessarily normalized).
+    pub dir: Vector,
+    #[cfg(feature = "dim3")]
+    pub padding1: u32,
+    /// The colliders the ray can hit.
+    pub filter: SceneQueryFilter,
+}
+
+/// A point to project on the scene.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct PointQuery {
+    /// The point to project, in world-space.
+    pub point: Vector,
+    /// Colliders further than this distance are ignored.
+    pub max_dist: f32,
+    #[cfg(feature = "dim2")]
+    pub padding0: u32,
+    /// The colliders the point can be projected on.
+    pub filter: SceneQueryFilter,
+}
+
+/// A shape moving along a straight line to cast against the scene.
+///
+/// Only convex shapes (that have a polygonal feature representation) can be cast.
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct ShapeCastQuery {
+    /// The shape to cast.
+    pub shape: Shape,
+    /// The shape's initial world-space pose.
+    pub pose: Pose,
+    /// The shape's linear velocity.
+    pub dir: Vector,
+    /// The maximum time of impact, in multiples of `dir`.
+    pub max_toi: f32,
+    #[cfg(feature = "dim2")]
+    pub padding0: u32,
+    /// The colliders the shape can hit.
+    pub filter: SceneQueryFilter,
+}
+
+/// The result of a scene query.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
+#[repr(C)]
+pub struct SceneQueryHit {
+    /// The world-space hit point (or projected point) on the collider.
+    pub point: Vector,
+    /// Batch-local index of the collider hit, or [`SCENE_QUERY_NONE`] if nothing was hit.
+    pub collider: u32,
+    #[cfg(feature = "dim2")]
+    pub padding0: u32,
+    /// The world-space collider normal at `point`.
+    pub normal: Vector,
+    /// The time of impact for casts. For point projections, the signed distance
+    /// between the point and the collider (negative if the point is inside).
+    pub toi: f32,
+    /// The sub-shape hit: triangle or segment index for trimeshes and polylines,
+    /// `2 * cell + k` (3D) or the cell index (2D) for heightfields, and
+    /// [`SCENE_QUERY_NONE`] for convex shapes and point projections.
+    pub feature_id: u32,
+    #[cfg(feature = "dim3")]
+    pub padding1: [u32; 3],
+}
+
+impl Default for SceneQueryHit {
+    fn default() -> Self {
+        Self {
+            point: Vector::ZERO,
+            collider: SCENE_QUERY_NONE,
+            #[cfg(feature = "dim2")]
+            padding0: 0,
+            normal: Vector::ZERO,
+            toi: MAX_FLT,
+            feature_id: SCENE_QUERY_NONE,
+            #[cfg(feature = "dim3")]
+            padding1: [0; 3],
+        }
+    }
+}
+
+impl SceneQueryHit {
+    /// Did the query hit anything?
+    #[inline]
+    pub fn is_hit(&self) -> bool {
+        self.collider != SCENE_QUERY_NONE
+    }
+}
+
+/// A cast result expressed in the local-space of the shape being hit.
+#[derive(Clone, Copy)]
+pub(crate) struct LocalCastHit {
+    pub(crate) toi: f32,
+    point: Vector,
+    normal: Vector,
+    feature_id: u32,
+}
+
+impl LocalCastHit {
+    #[inline]
+    fn none() -> Self {
+        Self {
+            toi: MAX_FLT,
+            point: Vector::ZERO,
+            normal: Vector::ZERO,
+            feature_id: SCENE_QUERY_NONE,
+        }
+    }
+}
+
+/// Checks if a box of half-extents `half_extents` centered at `center`, moving
+/// with the velocity `vel`, hits `aabb` before `max_toi`.
+#[inline]
+fn swept_box_hits_aabb(
+    aabb: &Aabb,
+    center: Vector,
+    half_extents: Vector,
+    vel: Vector,
+    max_toi: f32,
+) -> bool {
+    let enlarged = Aabb::new(aabb.mins - half_extents, aabb.maxs + half_extents);
+    enlarged.cast_ray(center, vel, max_toi) < MAX_FLT
+}
+
+/// Time of impact between a fixed PFM shape and a PFM shape moving with a
+/// constant linear velocity, using conservative advancement.
+///
+/// `pose12` and `vel12` are the moving shape's initial pose and velocity in the
+/// local-space of the fixed shape. The result is expressed in the same space.
+fn cast_pfm_pfm(
+    pose12: Pose,
+    vel12: Vector,
+    pfm1: &Shape,
+    thickness1: f32,
+    pfm2: &Shape,
+    thickness2: f32,
+    max_toi: f32,
+    feature_id: u32,
+    vertices: &[PaddedVector],
+) -> LocalCastHit {
+    let mut toi = 0.0;
+    let mut pose = pose12;
+
+    for _ in 0..MAX_CAST_ITERATIONS {
+        let cpts = contact_support_map_support_map(pose, pfm1, pfm2, MAX_FLT, vertices);
+        if cpts.status != CLOSEST_POINTS {
+            break;
+        }
+
+        let normal = cpts.dir;
+        let dist = (cpts.b - cpts.a).dot(normal) - thickness1 - thickness2;
+        if dist <= CAST_TOLERANCE {
+            return LocalCastHit {
+                toi,
+                point: cpts.a + normal * thickness1,
+                normal,
+                feature_id,
+            };
+        }
+
+        // Speed at which the moving shape approaches the fixed one along the normal.
+        let approach_speed = -vel12.dot(normal);
+        if approach_speed <= F32_EPSILON {
+            break;
+        }
+
+        toi += dist / approach_speed;
+        if toi > max_toi {
+            break;
+        }
+        pose.translation = pose12.translation + vel12 * toi;
+    }
+
+    LocalCastHit::none()
+}
+
+/// Casts a PFM shape against any shape, in the local-space of the shape being hit.
+///
+/// Also used by the continuous collision detection, see [`gpu_ccd_toi`](crate::dynamics::gpu_ccd_toi).
+pub(crate) fn cast_on_shape(
+    pose12: Pose,
+    vel12: Vector,
+    target: &Shape,
+    sub2: &PfmSubShape,
+    max_toi: f32,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) -> LocalCastHit {
+    let sub1 = target.pfm_subshape();
+    if sub1.valid {
+        return cast_pfm_pfm(
+            pose12,
+            vel12,
+            &sub1.shape,
+            sub1.thickness,
+            &sub2.shape,
+            sub2.thickness,
+            max_toi,
+            SCENE_QUERY_NONE,
+            vertices,
+        );
+    }
+
+    // Composite shape: only sub-shapes overlapping the path of the cast shape are tested.
+    let query_aabb = sub2
+        .shape
+        .compute_aabb(pose12, vertices)
+        .loosened(sub2.thickness);
+    let center = query_aabb.center();
+    let half_extents = query_aabb.half_extents();
+    let mut best = LocalCastHit::none();
+    best.toi = max_toi;
+    let ty = target.shape_type();
+
+    if ty == SHAPE_TYPE_TRIMESH {
+        let mesh = target.to_trimesh();
+        let mut curr = 0u32;
+
+        // NOTE: we use fixed-size for loops to avoid miscompilation issues of while loops on MacOs.
+        for _ in 0..mesh.bvh_node_len {
+            if curr >= mesh.bvh_node_len {
+                break;
+            }
+
+            let idx = mesh.bvh_node_idx(indices, curr);
+            if idx.entry_index == 0xffffffff {
+                // This is a leaf.
+                let tri = mesh.triangle(indices, vertices, idx.shape_index);
+                let sub1 = Shape::from_triangle(&tri).pfm_subshape();
+                let hit = cast_pfm_pfm(
+                    pose12,
+                    vel12,
+                    &sub1.shape,
+                    sub1.thickness,
+                    &sub2.shape,
+                    sub2.thickness,
+                    best.toi,
+                    idx.shape_index,
+                    vertices,
+                );
+                if hit.toi < best.toi {
+                    best = hit;
+                }
+
+                // Continue traversal.
+                curr = idx.exit_index;
+            } else {
+                let node_aabb = mesh.bvh_node_aabb(vertices, curr);
+                if swept_box_hits_aabb(&node_aabb, center, half_extents, vel12, best.toi) {
+                    curr = idx.entry_index;
+                } else {
+                    curr = idx.exit_index;
+                }
+            }
+        }
+    } else if ty == SHAPE_TYPE_POLYLINE {
+        let polyline = target.to_polyline();
+        let mut curr = 0u32;
+
+        // NOTE: we use fixed-size for loops to avoid miscompilation issues of while loops on MacOs.
+        for _ in 0..polyline.bvh_node_len {
+            if curr >= polyline.bvh_node_len {
+                break;
+            }
+
+            let idx = polyline.bvh_node_idx(curr, indices);
+            if idx.entry_index == 0xffffffff {
+                // This is a leaf.
+                let seg = polyline.segment(idx.shape_index, vertices, indices);
+                let sub1 = Shape::from_capsule(&Capsule::new(seg, 0.0)).pfm_subshape();
+                let hit = cast_pfm_pfm(
+                    pose12,
+                    vel12,
+                    &sub1.shape,
+                    sub1.thickness,
+                    &sub2.shape,
+                    sub2.thickness,
+                    best.toi,
+                    idx.shape_index,
+                    vertices,
+                );
+                if hit.toi < best.toi {
+                    best = hit;
+                }
+
+                // Continue traversal.
+                curr = idx.exit_index;
+            } else {
+                let node_aabb = polyline.bvh_node_aabb(curr, vertices);
+                if swept_box_hits_aabb(&node_aabb, center, half_extents, vel12, best.toi) {
+                    curr = idx.entry_index;
+                } else {
+                    curr = idx.exit_index;
+                }
+            }
+        }
+    } else if ty == SHAPE_TYPE_HEIGHTFIELD {
+        let hf = target.to_heightfield();
+        let root_aabb = hf.aabb();
+        // Time at which the swept query AABB starts overlapping the heightfield’s AABB.
+        let enlarged = Aabb::new(root_aabb.mins - half_extents, root_aabb.maxs + half_extents);
+        let entry = enlarged.cast_ray(center, vel12, max_toi);
+        if entry == MAX_FLT {
+            return LocalCastHit::none();
+        }
+
+        // Only the part of the path inside the heightfield's AABB can hit a cell.
+        let exit_aabb = Aabb::new(
+            query_aabb.mins + vel12 * max_toi,
+            query_aabb.maxs + vel12 * max_toi,
+        );
+        let entry_aabb = Aabb::new(
+            query_aabb.mins + vel12 * entry,
+            query_aabb.maxs + vel12 * entry,
+        );
+        let swept = entry_aabb.merged(&exit_aabb);
+        let (mins, maxs) = hf.cell_range(&swept);
+
+        for i in mins.y..maxs.y {
+            for j in mins.x..maxs.x {
+                #[cfg(feature = "dim2")]
+                {
+                    let _ = i;
+                    let (seg, valid) = hf.segment(vertices, indices, j);
+                    if valid {
+                        let sub1 = Shape::from_capsule(&Capsule::new(seg, 0.0)).pfm_subshape();
+                        let hit = cast_pfm_pfm(
+                            pose12,
+                            vel12,
+                            &sub1.shape,
+                            sub1.thickness,
+                            &sub2.shape,
+                            sub2.thickness,
+                            best.toi,
+                            j,
+                            vertices,
+                        );
+                        if hit.toi < best.toi {
+                            best = hit;
+                        }
+                    }
+                }
+
+                #[cfg(feature = "dim3")]
+                for k in 0..2 {
+                    let (tri, valid) = hf.triangle(vertices, indices, i, j, k == 1);
+                    if valid {
+                        let sub1 = Shape::from_triangle(&tri).pfm_subshape();
+                        let hit = cast_pfm_pfm(
+                            pose12,
+                            vel12,
+                            &sub1.shape,
+                            sub1.thickness,
+                            &sub2.shape,
+                            sub2.thickness,
+                            best.toi,
+                            (i * hf.ncols + j) * 2 + k,
+                            vertices,
+                        );
+                        if hit.toi < best.toi {
+                            best = hit;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if best.feature_id == SCENE_QUERY_NONE {
+        // Nothing was hit (`best.toi` still holds the initial `max_toi`).
+        LocalCastHit::none()
+    } else {
+        best
+    }
+}
+
+/// Casts a PFM shape against every collider of the current batch passing `filter`.
+fn cast_on_scene(
+    tree: &Slice<LbvhNode>,
+    poses: &Slice<Pose>,
+    shapes: &Slice<Shape>,
+    colliders: &Slice<SceneQueryCollider>,
+    num_colliders: u32,
+    filter: &SceneQueryFilter,
+    pose2: Pose,
+    vel: Vector,
+    sub2: &PfmSubShape,
+    max_toi: f32,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) -> SceneQueryHit {
+    let mut result = SceneQueryHit::default();
+    if num_colliders == 0 || !sub2.valid {
+        return result;
+    }
+
+    let first_leaf_id = num_colliders - 1;
+    let query_aabb = sub2
+        .shape
+        .compute_aabb(pose2, vertices)
+        .loosened(sub2.thickness);
+    let center = query_aabb.center();
+    let half_extents = query_aabb.half_extents();
+    let mut best_toi = max_toi;
+    let mut traversal = LbvhTraversal::new();
+
+    // NOTE: we use a fixed-size for loop to avoid miscompilation issues of
+    //       while loops on MacOs.
+    for _ in 0..LbvhTraversal::max_steps(num_colliders) {
+        if traversal.done {
+            break;
+        }
+        if !traversal.entering {
+            traversal.advance(tree);
+            continue;
+        }
+
+        let curr_id = traversal.curr;
+        let node = tree.at(curr_id as usize);
+
+        if !swept_box_hits_aabb(&node.aabb, center, half_extents, vel, best_toi) {
+            traversal.ascend(tree);
+        } else if curr_id >= first_leaf_id {
+            // We reached a leaf, cast on the collider's shape.
+            let j = node.left;
+            if filter.test(&colliders[j as usize]) {
+                let pose1 = poses[j as usize];
+                let pose12 = pose1.inverse() * pose2;
+                let vel12 = pose1.rotation.inverse() * vel;
+                let hit = cast_on_shape(
+                    pose12,
+                    vel12,
+                    &shapes[j as usize],
+                    sub2,
+                    best_toi,
+                    vertices,
+                    indices,
+                );
+
+                if hit.toi < MAX_FLT && hit.toi <= best_toi {
+                    best_toi = hit.toi;
+                    result.point = pose1 * hit.point;
+                    result.normal = pose1.rotation * hit.normal;
+                    result.toi = hit.toi;
+                    result.collider = j;
+                    result.feature_id = hit.feature_id;
+                }
+            }
+            traversal.ascend(tree);
+        } else {
+            traversal.descend(node.left);
+        }
+    }
+
+    result
+}
+
+/// Projects a local point on any shape.
+///
+/// Returns `false` if the shape has no feature closer than `max_dist` (only
+/// checked for composite shapes) or if the projection isn't supported.
+fn project_on_shape(
+    shape: &Shape,
+    pt: Vector,
+    max_dist: f32,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) -> (ProjectionResult, bool) {
+    let ty = shape.shape_type();
+    #[cfg(feature = "dim2")]
+    let analytic = ty == SHAPE_TYPE_BALL
+        || ty == SHAPE_TYPE_CUBOID
+        || ty == SHAPE_TYPE_CAPSULE
+        || ty == SHAPE_TYPE_SEGMENT
+        || ty == SHAPE_TYPE_TRIANGLE;
+    #[cfg(feature = "dim3")]
+    let analytic = ty == SHAPE_TYPE_BALL
+        || ty == SHAPE_TYPE_CUBOID
+        || ty == SHAPE_TYPE_CAPSULE
+        || ty == SHAPE_TYPE_SEGMENT
+        || ty == SHAPE_TYPE_TRIANGLE
+        || ty == SHAPE_TYPE_CONE
+        || ty == SHAPE_TYPE_CYLINDER
+        || ty == SHAPE_TYPE_TETRAHEDRON;
+
+    if analytic {
+        return (shape.project_local_point_on_boundary(pt), true);
+    }
+    if ty == SHAPE_TYPE_TRIMESH {
+        return shape
+            .to_trimesh()
+            .project_local_point(indices, vertices, pt, max_dist);
+    }
+    if ty == SHAPE_TYPE_POLYLINE {
+        return shape
+            .to_polyline()
+            .project_local_point(vertices, indices, pt, max_dist);
+    }
+    if ty == SHAPE_TYPE_HEIGHTFIELD {
+        return shape
+            .to_heightfield()
+            .project_local_point(vertices, indices, pt);
+    }
+
+    // Other convex shapes: compute the distance to the point with GJK (or EPA
+    // if the point is inside).
+    let sub1 = shape.pfm_subshape();
+    if sub1.valid {
+        let point_shape = Shape::ball(0.0).pfm_subshape();
+        let pose12 = Pose::from_parts(pt, Rotation::IDENTITY);
+        let cpts = contact_support_map_support_map(
+            pose12,
+            &sub1.shape,
+            &point_shape.shape,
+            MAX_FLT,
+            vertices,
+        );
+        if cpts.status == CLOSEST_POINTS {
+            let is_inside = (cpts.b - cpts.a).dot(cpts.dir) < sub1.thickness;
+            let proj = cpts.a + cpts.dir * sub1.thickness;
+            return (ProjectionResult::new(proj, is_inside), true);
+        }
+    }
+
+    (ProjectionResult::new(pt, false), false)
+}
+
+/// Projects a point on every collider of the current batch passing `filter`,
+/// keeping the closest.
+fn project_on_scene(
+    tree: &Slice<LbvhNode>,
+    poses: &Slice<Pose>,
+    shapes: &Slice<Shape>,
+    colliders: &Slice<SceneQueryCollider>,
+    num_colliders: u32,
+    filter: &SceneQueryFilter,
+    pt: Vector,
+    max_dist: f32,
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) -> SceneQueryHit {
+    let mut result = SceneQueryHit::default();
+    if num_colliders == 0 {
+        return result;
+    }
+
+    let first_leaf_id = num_colliders - 1;
+    let mut best_dist = max_dist;
+    let mut traversal = LbvhTraversal::new();
+
+    // NOTE: we use a fixed-size for loop to avoid miscompilation issues of
+    //       while loops on MacOs.
+    for _ in 0..LbvhTraversal::max_steps(num_colliders) {
+        if traversal.done {
+            break;
+        }
+        if !traversal.entering {
+            traversal.advance(tree);
+            continue;
+        }
+
+        let curr_id = traversal.curr;
+        let node = tree.at(curr_id as usize);
+
+        if node.aabb.distance_to_point(pt) > best_dist {
+            traversal.ascend(tree);
+        } else if curr_id >= first_leaf_id {
+            // We reached a leaf, project on the collider's shape.
+            let j = node.left;
+            traversal.ascend(tree);
+            if !filter.test(&colliders[j as usize]) {
+                continue;
+            }
+
+            let pose1 = poses[j as usize];
+            let local_pt = pose1.inverse() * pt;
+            let (proj, valid) =
+                project_on_shape(&shapes[j as usize], local_pt, best_dist, vertices, indices);
+            if !valid {
+                continue;
+            }
+
+            let delta = local_pt - proj.point;
+            let dist = delta.length();
+            // Points inside of a collider are at distance zero from it, like
+            // solid point projections in parry.
+            let sort_dist = if proj.is_inside { 0.0 } else { dist };
+
+            if sort_dist <= best_dist {
+                best_dist = sort_dist;
+                let normal = if dist > F32_EPSILON {
+                    if proj.is_inside {
+                        -delta / dist
+                    } else {
+                        delta / dist
+                    }
+                } else {
+                    Vector::ZERO
+                };
+
+                result.point = pose1 * proj.point;
+                result.normal = pose1.rotation * normal;
+                result.toi = if proj.is_inside { -dist } else { dist };
+                result.collider = j;
+                result.feature_id = SCENE_QUERY_NONE;
+            }
+        } else {
+            traversal.descend(node.left);
+        }
+    }
+
+    result
+}
+
+/// Gathers the collision groups and flags of every collider, tested against the
+/// [`SceneQueryFilter`] of each query.
+///
+/// Dispatch with `[colliders_batch_capacity, num_batches, 1]` threads.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_scene_query_colliders(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)]
+    collision_groups: &[InteractionGroups],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] materials: &[ColliderMaterial],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)]
+    colliders: &mut [SceneQueryCollider],
+    #[spirv(uniform, descriptor_set = 0, binding = 3)] batch_ids: &BatchIndices,
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let collision_groups = batch_ids.coll_batch(batch_id, collision_groups);
+    let materials = batch_ids.coll_batch(batch_id, materials);
+    let mut colliders = batch_ids.coll_batch_mut(batch_id, colliders);
+
+    for i in StepRng::new(
+        invocation_id.x..batch_ids.colliders_len(batch_id),
+        num_threads,
+    ) {
+        let idx = i as usize;
+        colliders[idx] = SceneQueryCollider {
+            groups: collision_groups[idx],
+            flags: materials[idx].flags,
+        };
+    }
+}
+
+/// Casts rays on the scene. Queries are split evenly between the batches.
+///
+/// Dispatch with `[rays_per_batch, num_batches, 1]` threads.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_scene_cast_rays(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] tree: &[LbvhNode],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] shapes: &[Shape],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] rays: &[RayQuery],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] hits: &mut [SceneQueryHit],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] colliders: &[SceneQueryCollider],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 0)] vertices: &[PaddedVector],
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 1)] indices: &[u32],
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let rays_per_batch = rays.len() as u32 / batch_ids.num_batches;
+    let tree = Slice(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
+    let poses = batch_ids.coll_batch(batch_id, poses);
+    let shapes = batch_ids.coll_batch(batch_id, shapes);
+    let colliders = batch_ids.coll_batch(batch_id, colliders);
+    // A ray is cast as a point (zero-radius ball).
+    let point = Shape::ball(0.0).pfm_subshape();
+
+    for i in StepRng::new(invocation_id.x..rays_per_batch, num_threads) {
+        let query_id = (batch_id * rays_per_batch + i) as usize;
+        let ray = rays.read(query_id);
+        let hit = cast_on_scene(
+            &tree,
+            &poses,
+            &shapes,
+            &colliders,
+            batch_ids.colliders_len(batch_id),
+            &ray.filter,
+            Pose::from_parts(ray.origin, Rotation::IDENTITY),
+            ray.dir,
+            &point,
+            ray.max_toi,
+            vertices,
+            indices,
+        );
+        hits.write(query_id, hit);
+    }
+}
+
+/// Casts shapes on the scene. Queries are split evenly between the batches.
+///
+/// Dispatch with `[casts_per_batch, num_batches, 1]` threads.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_scene_cast_shapes(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] tree: &[LbvhNode],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] shapes: &[Shape],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] casts: &[ShapeCastQuery],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] hits: &mut [SceneQueryHit],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] colliders: &[SceneQueryCollider],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 0)] vertices: &[PaddedVector],
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 1)] indices: &[u32],
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let casts_per_batch = casts.len() as u32 / batch_ids.num_batches;
+    let tree = Slice(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
+    let poses = batch_ids.coll_batch(batch_id, poses);
+    let shapes = batch_ids.coll_batch(batch_id, shapes);
+    let colliders = batch_ids.coll_batch(batch_id, colliders);
+
+    for i in StepRng::new(invocation_id.x..casts_per_batch, num_threads) {
+        let query_id = (batch_id * casts_per_batch + i) as usize;
+        let cast = casts.read(query_id);
+        let hit = cast_on_scene(
+            &tree,
+            &poses,
+            &shapes,
+            &colliders,
+            batch_ids.colliders_len(batch_id),
+            &cast.filter,
+            cast.pose,
+            cast.dir,
+            &cast.shape.pfm_subshape(),
+            cast.max_toi,
+            vertices,
+            indices,
+        );
+        hits.write(query_id, hit);
+    }
+}
+
+/// Projects points on the scene. Queries are split evenly between the batches.
+///
+/// Dispatch with `[points_per_batch, num_batches, 1]` threads.
+#[spirv_bindgen]
+#[spirv(compute(threads(64)))]
+pub fn gpu_scene_project_points(
+    #[spirv(global_invocation_id)] invocation_id: UVec3,
+    #[spirv(num_workgroups)] num_workgroups: UVec3,
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 0)] tree: &[LbvhNode],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 1)] poses: &[Pose],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 2)] shapes: &[Shape],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 3)] points: &[PointQuery],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 4)] hits: &mut [SceneQueryHit],
+    #[spirv(storage_buffer, descriptor_set = 0, binding = 5)] colliders: &[SceneQueryCollider],
+    #[spirv(uniform, descriptor_set = 0, binding = 6)] batch_ids: &BatchIndices,
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 0)] vertices: &[PaddedVector],
+    #[spirv(storage_buffer, descriptor_set = 1, binding = 1)] indices: &[u32],
+) {
+    let num_threads = num_workgroups.x * WORKGROUP_SIZE;
+    let batch_id = invocation_id.y;
+    let points_per_batch = points.len() as u32 / batch_ids.num_batches;
+    let tree = Slice(
+        tree,
+        root_id(batch_ids.lbvh_start(batch_id) as u32) as usize,
+    );
+    let poses = batch_ids.coll_batch(batch_id, poses);
+    let shapes = batch_ids.coll_batch(batch_id, shapes);
+    let colliders = batch_ids.coll_batch(batch_id, colliders);
+
+    for i in StepRng::new(invocation_id.x..points_per_batch, num_threads) {
+        let query_id = (batch_id * points_per_batch + i) as usize;
+        let query = points.read(query_id);
+        let hit = project_on_scene(
+            &tree,
+            &poses,
+            &shapes,
+            &colliders,
+            batch_ids.colliders_len(batch_id),
+            &query.filter,
+            query.point,
+            query.max_dist,
+            vertices,
+            indices,
+        );
+        hits.write(query_id, hit);
+    }
+}
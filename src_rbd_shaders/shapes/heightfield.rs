@@ -0,0 +1,278 @@
+//! Heightfield Shape Module
+//!
+//! Geometric operations for heightfields. A heightfield is a regular grid of
+//! heights: its cells are looked up directly from an AABB instead of going
+//! through a BVH like trimeshes and polylines.
+
+use crate::bounding_volumes::Aabb;
+use crate::queries::ProjectionResult;
+#[cfg(feature = "dim2")]
+use crate::shapes::segment::Segment;
+#[cfg(feature = "dim3")]
+use crate::shapes::triangle::Triangle;
+use crate::{MAX_FLT, PaddedVector, Vector};
+use glamx::UVec2;
+use khal_std::index::MaybeIndexUnchecked;
+
+/// Cell flag: the cell is split along its `p00 → p11` diagonal instead of `p10 → p01`.
+#[cfg(feature = "dim3")]
+pub const HEIGHTFIELD_ZIGZAG_SUBDIVISION: u32 = 1 << 0;
+/// Cell flag: the first triangle of the cell is removed.
+#[cfg(feature = "dim3")]
+pub const HEIGHTFIELD_LEFT_TRIANGLE_REMOVED: u32 = 1 << 1;
+/// Cell flag: the second triangle of the cell is removed.
+#[cfg(feature = "dim3")]
+pub const HEIGHTFIELD_RIGHT_TRIANGLE_REMOVED: u32 = 1 << 2;
+/// Cell flag: the segment of the cell is removed.
+#[cfg(feature = "dim2")]
+pub const HEIGHTFIELD_SEGMENT_REMOVED: u32 = 1 << 1;
+
+/// A heightfield whose (already scaled) grid vertices live in the vertex buffer
+/// and whose per-cell flags live in the index buffer.
+///
+/// In 3D, the grid vertex `(i, j)` (row `i` along the local `z` axis, column `j`
+/// along the local `x` axis) is stored at `first_vtx_id + i * (ncols + 1) + j`.
+/// In 2D, there is a single row of `ncols + 1` vertices along the local `x` axis.
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct HeightField {
+    /// Index of the first grid vertex in the vertex buffer.
+    pub first_vtx_id: u32,
+    /// Index of the first cell flag in the index buffer.
+    pub first_cell_id: u32,
+    /// The number of cell rows (along the local `z` axis). Always 1 in 2D.
+    pub nrows: u32,
+    /// The number of cell columns (along the local `x` axis).
+    pub ncols: u32,
+    /// Root AABB of the heightfield.
+    pub root_aabb: Aabb,
+}
+
+impl HeightField {
+    /// Creates a new heightfield.
+    #[inline]
+    pub fn new(
+        first_vtx_id: u32,
+        first_cell_id: u32,
+        nrows: u32,
+        ncols: u32,
+        root_aabb: Aabb,
+    ) -> Self {
+        Self {
+            first_vtx_id,
+            first_cell_id,
+            nrows,
+            ncols,
+            root_aabb,
+        }
+    }
+
+    /// Computes the AABB of a heightfield.
+    pub fn aabb(&self) -> Aabb {
+        self.root_aabb
+    }
+
+    /// Gets the flags of the cell at row `i` and column `j`.
+    #[inline]
+    pub fn cell_flags(&self, indices: &[u32], i: u32, j: u32) -> u32 {
+        indices.read((self.first_cell_id + i * self.ncols + j) as usize)
+    }
+
+    /// Gets the grid vertex at row `i` and column `j`.
+    #[inline]
+    pub fn grid_vertex(&self, vertices: &[PaddedVector], i: u32, j: u32) -> Vector {
+        *vertices.read((self.first_vtx_id + i * (self.ncols + 1) + j) as usize)
+    }
+
+    /// Index of the column containing the local coordinate `x`, clamped to the grid.
+    #[inline]
+    fn clamped_col(&self, x: f32) -> u32 {
+        let width = self.root_aabb.maxs.x - self.root_aabb.mins.x;
+        let cell_width = width / self.ncols as f32;
+        let j = ((x - self.root_aabb.mins.x) / cell_width).max(0.0);
+        (j as u32).min(self.ncols - 1)
+    }
+
+    /// Index of the row containing the local coordinate `z`, clamped to the grid.
+    #[cfg(feature = "dim3")]
+    #[inline]
+    fn clamped_row(&self, z: f32) -> u32 {
+        let height = self.root_aabb.maxs.z - self.root_aabb.mins.z;
+        let cell_height = height / self.nrows as f32;
+        let i = ((z - self.root_aabb.mins.z) / cell_height).max(0.0);
+        (i as u32).min(self.nrows - 1)
+    }
+
+    /// Range of cells overlapping `aabb` (given in the heightfield's local space).
+    ///
+    /// Returns `(mins, maxs)` where `x` is the column and `y` the row. The upper
+    /// bound is exclusive. The range is clamped to the grid so the caller must
+    /// first check that `aabb` intersects the heightfield's root AABB.
+    #[inline]
+    pub fn cell_range(&self, aabb: &Aabb) -> (UVec2, UVec2) {
+        let j0 = self.clamped_col(aabb.mins.x);
+        let j1 = self.clamped_col(aabb.maxs.x) + 1;
+        #[cfg(feature = "dim2")]
+        return (UVec2::new(j0, 0), UVec2::new(j1, 1));
+        #[cfg(feature = "dim3")]
+        {
+            let i0 = self.clamped_row(aabb.mins.z);
+            let i1 = self.clamped_row(aabb.maxs.z) + 1;
+            (UVec2::new(j0, i0), UVec2::new(j1, i1))
+        }
+    }
+
+    /// Gets the segment of the `j`-th cell, and whether it exists (2D only).
+    #[cfg(feature = "dim2")]
+    #[inline]
+    pub fn segment(&self, vertices: &[PaddedVector], indices: &[u32], j: u32) -> (Segment, bool) {
+        let a = self.grid_vertex(vertices, 0, j);
+        let b = self.grid_vertex(vertices, 0, j + 1);
+        let flags = self.cell_flags(indices, 0, j);
+        (Segment::new(a, b), flags & HEIGHTFIELD_SEGMENT_REMOVED == 0)
+    }
+
+    /// Gets one of the two triangles of the cell at row `i` and column `j`, and
+    /// whether it exists (3D only).
+    ///
+    /// The triangle ordering and the diagonal choice match parry's `HeightField`.
+    #[cfg(feature = "dim3")]
+    #[inline]
+    pub fn triangle(
+        &self,
+        vertices: &[PaddedVector],
+        indices: &[u32],
+        i: u32,
+        j: u32,
+        right: bool,
+    ) -> (Triangle, bool) {
+        let p00 = self.grid_vertex(vertices, i, j);
+        let p10 = self.grid_vertex(vertices, i + 1, j);
+        let p01 = self.grid_vertex(vertices, i, j + 1);
+        let p11 = self.grid_vertex(vertices, i + 1, j + 1);
+        let flags = self.cell_flags(indices, i, j);
+        let zigzag = flags & HEIGHTFIELD_ZIGZAG_SUBDIVISION != 0;
+
+        if right {
+            let tri = if zigzag {
+                Triangle::new(p00, p11, p01)
+            } else {
+                Triangle::new(p10, p11, p01)
+            };
+            (tri, flags & HEIGHTFIELD_RIGHT_TRIANGLE_REMOVED == 0)
+        } else {
+            let tri = if zigzag {
+                Triangle::new(p00, p10, p11)
+            } else {
+                Triangle::new(p00, p10, p01)
+            };
+            (tri, flags & HEIGHTFIELD_LEFT_TRIANGLE_REMOVED == 0)
+        }
+    }
+
+    /// Projects a point on the features of the cell at row `i` and column `j`,
+    /// keeping it if it is closer than `best`.
+    fn project_on_cell(
+        &self,
+        vertices: &[PaddedVector],
+        indices: &[u32],
+        i: u32,
+        j: u32,
+        pt: Vector,
+        best: &mut f32,
+        best_proj: &mut Vector,
+        best_normal: &mut Vector,
+    ) {
+        #[cfg(feature = "dim2")]
+        {
+            let _ = i;
+            let (seg, valid) = self.segment(vertices, indices, j);
+            if valid {
+                let proj = seg.project_local_point(pt);
+                let dist = (proj - pt).length();
+                if dist < *best {
+                    let ab = seg.b - seg.a;
+                    *best = dist;
+                    *best_proj = proj;
+                    // Points to the "above" side of the heightfield since a.x < b.x.
+                    *best_normal = Vector::new(-ab.y, ab.x);
+                }
+            }
+        }
+
+        #[cfg(feature = "dim3")]
+        for k in 0..2 {
+            let (tri, valid) = self.triangle(vertices, indices, i, j, k == 1);
+            if valid {
+                let proj = tri.project_local_point_and_get_location(pt, false).point;
+                let dist = (proj - pt).length();
+                if dist < *best {
+                    let n = (tri.b - tri.a).cross(tri.c - tri.a);
+                    *best = dist;
+                    *best_proj = proj;
+                    // Orient the normal towards the "above" side of the heightfield.
+                    *best_normal = if n.y < 0.0 { -n } else { n };
+                }
+            }
+        }
+    }
+
+    /// Projects a local point onto the heightfield and returns the projection
+    /// result with inside/outside information.
+    ///
+    /// A point is considered inside if it lies below the closest heightfield
+    /// feature. The returned flag is `false` if every cell was removed.
+    pub fn project_local_point(
+        &self,
+        vertices: &[PaddedVector],
+        indices: &[u32],
+        pt: Vector,
+    ) -> (ProjectionResult, bool) {
+        let mut best = MAX_FLT;
+        let mut best_proj = pt;
+        let mut best_normal = Vector::ZERO;
+
+        // First, project on the cell right below (or above) the point to get a
+        // tight upper bound on the distance.
+        let j = self.clamped_col(pt.x);
+        #[cfg(feature = "dim2")]
+        let i = 0;
+        #[cfg(feature = "dim3")]
+        let i = self.clamped_row(pt.z);
+        self.project_on_cell(
+            vertices,
+            indices,
+            i,
+            j,
+            pt,
+            &mut best,
+            &mut best_proj,
+            &mut best_normal,
+        );
+
+        // Then, check every other cell that may contain a closer point.
+        let search = Aabb::new(pt - Vector::splat(best), pt + Vector::splat(best));
+        let (mins, maxs) = self.cell_range(&search);
+        for i in mins.y..maxs.y {
+            for j in mins.x..maxs.x {
+                self.project_on_cell(
+                    vertices,
+                    indices,
+                    i,
+                    j,
+                    pt,
+                    &mut best,
+                    &mut best_proj,
+                    &mut best_normal,
+                );
+            }
+        }
+
+        if best < MAX_FLT {
+            let is_inside = best_normal.dot(pt - best_proj) < 0.0;
+            (ProjectionResult::new(best_proj, is_inside), true)
+        } else {
+            (ProjectionResult::new(Vector::ZERO, false), false)
+        }
+    }
+}
@@ -12,6 +12,7 @@
 //! - ConvexPolyhedron (convex polygon/polyhedron)
 //! - TriMesh (triangle mesh)
 //! - Polyline
+//! - HeightField
 
 mod capsule;
 #[cfg(feature = "dim3")]
@@ -19,6 +20,7 @@ mod cone;
 mod convex_polyhedron;
 #[cfg(feature = "dim3")]
 mod cylinder;
+mod heightfield;
 mod polyline;
 mod segment;
 mod shape;
@@ -36,6 +38,14 @@ pub use cone::Cone;
 pub use convex_polyhedron::ConvexPolyhedron;
 #[cfg(feature = "dim3")]
 pub use cylinder::Cylinder;
+#[cfg(feature = "dim2")]
+pub use heightfield::HEIGHTFIELD_SEGMENT_REMOVED;
+pub use heightfield::HeightField;
+#[cfg(feature = "dim3")]
+pub use heightfield::{
+    HEIGHTFIELD_LEFT_TRIANGLE_REMOVED, HEIGHTFIELD_RIGHT_TRIANGLE_REMOVED,
+    HEIGHTFIELD_ZIGZAG_SUBDIVISION,
+};
 pub use polyline::Polyline;
 pub use segment::Segment;
 pub use shape::*;
@@ -3,9 +3,10 @@
 //! Geometric operations for polylines (line strips). A polyline uses a BVH for
 //! efficient queries.
 
-use crate::PaddedVector;
 use crate::bounding_volumes::Aabb;
+use crate::queries::ProjectionResult;
 use crate::shapes::segment::Segment;
+use crate::{PaddedVector, Vector};
 use khal_std::index::MaybeIndexUnchecked;
 
 /// A polyline (connected line segments) with BVH acceleration structure.
@@ -84,4 +85,49 @@ impl Polyline {
         let b = *vertices.read(base_vid + indices.read(base_id + 1) as usize);
         Segment::new(a, b)
     }
+
+    /// Projects a local point onto the polyline.
+    ///
+    /// A polyline has no interior so the point is never considered inside. The
+    /// returned flag is `false` if no segment is closer than `max_dist`.
+    pub fn project_local_point(
+        &self,
+        vertices: &[PaddedVector],
+        indices: &[u32],
+        pt: Vector,
+        max_dist: f32,
+    ) -> (ProjectionResult, bool) {
+        let mut curr = 0u32;
+        let mut best = max_dist;
+        let mut best_proj = pt;
+        let mut found = false;
+
+        // NOTE: we use fixed-size for loops to avoid miscompilation issues of while loops on MacOs.
+        for _ in 0..self.bvh_node_len {
+            if curr >= self.bvh_node_len {
+                break;
+            }
+            let node_idx = self.bvh_node_idx(curr, indices);
+            if node_idx.entry_index == 0xFFFFFFFF {
+                // This is a leaf.
+                let seg = self.segment(node_idx.shape_index, vertices, indices);
+                let proj = seg.project_local_point(pt);
+                let dist = (proj - pt).length();
+                if dist <= best {
+                    best = dist;
+                    best_proj = proj;
+                    found = true;
+                }
+
+                // Continue traversal.
+                curr = node_idx.exit_index;
+            } else if self.bvh_node_aabb(curr, vertices).distance_to_point(pt) <= best {
+                curr = node_idx.entry_index;
+            } else {
+                curr = node_idx.exit_index;
+            }
+        }
+
+        (ProjectionResult::new(best_proj, false), found)
+    }
 }
@@ -20,8 +20,11 @@ use parry::{query::PointQuery, shape::SupportMap};
 use crate::shapes::cone::Cone;
 #[cfg(feature = "dim3")]
 use crate::shapes::cylinder::Cylinder;
+#[cfg(feature = "dim3")]
+use crate::shapes::tetrahedron::Tetrahedron;
 
 use crate::shapes::convex_polyhedron::ConvexPolyhedron;
+use crate::shapes::heightfield::HeightField;
 use crate::shapes::polyline::Polyline;
 use crate::shapes::trimesh::TriMesh;
 
@@ -34,14 +37,20 @@ pub const SHAPE_TYPE_CYLINDER: u32 = 4;
 pub const SHAPE_TYPE_POLYLINE: u32 = 5;
 pub const SHAPE_TYPE_TRIMESH: u32 = 6;
 pub const SHAPE_TYPE_CONVEX_POLY: u32 = 7;
-// TODO: since this shape type is only for trimesh, it doesn't implement all the
-//       operations it could if it were a standalone self.
 pub const SHAPE_TYPE_TRIANGLE: u32 = 8;
+pub const SHAPE_TYPE_HEIGHTFIELD: u32 = 9;
+pub const SHAPE_TYPE_SEGMENT: u32 = 10;
+/// Tetrahedra are 3D only.
+pub const SHAPE_TYPE_TETRAHEDRON: u32 = 11;
 
 /// A generic shape that can represent any concrete shape type.
 ///
 /// This is a tagged union encoded in two vec4 values. The shape type
 /// is stored in the 'a.w' component as a bitcast u32.
+///
+/// Cuboids, cones, cylinders, convex polygons/polyhedra and triangles can be
+/// rounded: their border radius is stored in the 'c.w' component (see
+/// [`Shape::border_radius`]).
 #[derive(Clone, Copy, Default)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
 #[repr(C)]
@@ -50,7 +59,7 @@ pub struct Shape {
     pub a: Vec4,
     /// Second vec4 for additional shape data (primarily for capsule segment endpoint).
     pub b: Vec4,
-    /// Third vec4, only used for triangles.
+    /// Third vec4, used by triangles, tetrahedra and round shapes.
     pub c: Vec4,
 }
 
@@ -74,6 +83,44 @@ impl Shape {
         f32::to_bits(self.a.w)
     }
 
+    /// The radius this shape is dilated by, zero if it isn't a round shape.
+    ///
+    /// Balls and capsules aren't seen as round shapes: their radius is part of
+    /// their own layout.
+    #[inline]
+    pub fn border_radius(&self) -> f32 {
+        let ty = self.shape_type();
+        if ty == SHAPE_TYPE_CUBOID
+            || ty == SHAPE_TYPE_CONE
+            || ty == SHAPE_TYPE_CYLINDER
+            || ty == SHAPE_TYPE_CONVEX_POLY
+            || ty == SHAPE_TYPE_TRIANGLE
+        {
+            self.c.w
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns a copy of this shape dilated by `border_radius`.
+    ///
+    /// Only meaningful for the shapes that can be rounded (see
+    /// [`Self::border_radius`]).
+    #[inline]
+    pub fn with_border_radius(&self, border_radius: f32) -> Self {
+        let mut result = *self;
+        result.c.w = border_radius;
+        result
+    }
+
+    /// Segments, triangles and tetrahedra have no solid point projection of
+    /// their own: it is derived from their boundary projection instead.
+    #[inline]
+    fn projects_through_boundary(&self) -> bool {
+        let ty = self.shape_type();
+        ty == SHAPE_TYPE_SEGMENT || ty == SHAPE_TYPE_TRIANGLE || ty == SHAPE_TYPE_TETRAHEDRON
+    }
+
     /*
      *
      * Shape conversions.
@@ -191,6 +238,31 @@ impl Shape {
         }
     }
 
+    /// Converts a Shape to a Segment.
+    pub fn to_segment(&self) -> Segment {
+        // Segment layout (a capsule with a zero radius):
+        //     vec4(ax, ay, az, shape_type)
+        //     vec4(bx, by, bz, 0.0)
+        self.to_capsule().segment
+    }
+
+    #[cfg(feature = "dim3")]
+    /// Converts a Shape to a Tetrahedron (3D only).
+    ///
+    /// The first vertex of the tetrahedron is always at the origin.
+    pub fn to_tetrahedron(&self) -> Tetrahedron {
+        // Tetrahedron layout:
+        //     vec4(b.x, b.y, b.z, shape_type)
+        //     vec4(c.x, c.y, c.z, _)
+        //     vec4(d.x, d.y, d.z, _)
+        Tetrahedron::new(
+            Vector::ZERO,
+            Vector::new(self.a.x, self.a.y, self.a.z),
+            Vector::new(self.b.x, self.b.y, self.b.z),
+            Vector::new(self.c.x, self.c.y, self.c.z),
+        )
+    }
+
     /// Converts a Shape to a Cuboid.
     pub fn to_cuboid(&self) -> Cuboid {
         // Cuboid layout:
@@ -289,6 +361,29 @@ impl Shape {
         Polyline::new(bvh_vtx_root_id, bvh_idx_root_id, bvh_node_len, root_aabb)
     }
 
+    /// Converts a Shape to a HeightField.
+    pub fn to_heightfield(&self) -> HeightField {
+        // Heightfield layout:
+        //     vec4(first_vtx_id, first_cell_id, nrows, shape_type)
+        //     vec4(root_aabb.mins.xyz, ncols)
+        //     vec4(root_aabb.maxs.xyz, _)
+        let first_vtx_id = f32::to_bits(self.a.x);
+        let first_cell_id = f32::to_bits(self.a.y);
+        let nrows = f32::to_bits(self.a.z);
+        let ncols = f32::to_bits(self.b.w);
+        #[cfg(feature = "dim2")]
+        let root_aabb = Aabb::new(
+            Vector::new(self.b.x, self.b.y),
+            Vector::new(self.c.x, self.c.y),
+        );
+        #[cfg(feature = "dim3")]
+        let root_aabb = Aabb::new(
+            Vector::new(self.b.x, self.b.y, self.b.z),
+            Vector::new(self.c.x, self.c.y, self.c.z),
+        );
+        HeightField::new(first_vtx_id, first_cell_id, nrows, ncols, root_aabb)
+    }
+
     /*
      *
      * Geometric operations.
@@ -299,6 +394,11 @@ impl Shape {
     ///
     /// If the point is inside the shape, the point itself is returned.
     pub fn project_local_point(&self, pt: Vector) -> Vector {
+        if self.border_radius() > 0.0 || self.projects_through_boundary() {
+            let proj = self.project_local_point_on_boundary(pt);
+            return if proj.is_inside { pt } else { proj.point };
+        }
+
         let ty = self.shape_type();
         if ty == SHAPE_TYPE_BALL {
             return self.to_ball().project_local_point(pt, true).point;
@@ -325,6 +425,11 @@ impl Shape {
     ///
     /// If the point is inside the shape, the point itself is returned.
     pub fn project_point(&self, pose: Pose, pt: Vector) -> Vector {
+        if self.border_radius() > 0.0 || self.projects_through_boundary() {
+            let proj = self.project_point_on_boundary(pose, pt);
+            return if proj.is_inside { pt } else { proj.point };
+        }
+
         let ty = self.shape_type();
         if ty == SHAPE_TYPE_BALL {
             return self.to_ball().project_point(&pose, pt, true).point;
@@ -354,18 +459,34 @@ impl Shape {
             return self.to_ball().project_local_point(pt, false).into();
         }
         if ty == SHAPE_TYPE_CUBOID {
-            return self.to_cuboid().project_local_point(pt, false).into();
+            let proj = self.to_cuboid().project_local_point(pt, false).into();
+            return dilate_projection(proj, pt, self.border_radius());
         }
         if ty == SHAPE_TYPE_CAPSULE {
             return self.to_capsule().project_local_point_on_boundary(pt);
         }
+        if ty == SHAPE_TYPE_SEGMENT {
+            return ProjectionResult::new(self.to_segment().project_local_point(pt), false);
+        }
+        if ty == SHAPE_TYPE_TRIANGLE {
+            let proj = self
+                .to_triangle()
+                .project_local_point_and_get_location(pt, false);
+            let proj = ProjectionResult::new(proj.point, proj.inside);
+            return dilate_projection(proj, pt, self.border_radius());
+        }
         #[cfg(feature = "dim3")]
         {
             if ty == SHAPE_TYPE_CONE {
-                return self.to_cone().project_local_point_on_boundary(pt);
+                let proj = self.to_cone().project_local_point_on_boundary(pt);
+                return dilate_projection(proj, pt, self.border_radius());
             }
             if ty == SHAPE_TYPE_CYLINDER {
-                return self.to_cylinder().project_local_point_on_boundary(pt);
+                let proj = self.to_cylinder().project_local_point_on_boundary(pt);
+                return dilate_projection(proj, pt, self.border_radius());
+            }
+            if ty == SHAPE_TYPE_TETRAHEDRON {
+                return self.to_tetrahedron().project_local_point_on_boundary(pt);
             }
         }
         ProjectionResult::new(pt, false)
@@ -381,18 +502,27 @@ impl Shape {
             return self.to_ball().project_point(&pose, pt, false).into();
         }
         if ty == SHAPE_TYPE_CUBOID {
-            return self.to_cuboid().project_point(&pose, pt, false).into();
+            let proj = self.to_cuboid().project_point(&pose, pt, false).into();
+            return dilate_projection(proj, pt, self.border_radius());
         }
         if ty == SHAPE_TYPE_CAPSULE {
             return self.to_capsule().project_point_on_boundary(pose, pt);
         }
+        if ty == SHAPE_TYPE_SEGMENT || ty == SHAPE_TYPE_TRIANGLE || ty == SHAPE_TYPE_TETRAHEDRON {
+            let local_pt = pose.inverse_transform_point(pt);
+            let mut proj = self.project_local_point_on_boundary(local_pt);
+            proj.point = pose * proj.point;
+            return proj;
+        }
         #[cfg(feature = "dim3")]
         {
             if ty == SHAPE_TYPE_CONE {
-                return self.to_cone().project_point_on_boundary(pose, pt);
+                let proj = self.to_cone().project_point_on_boundary(pose, pt);
+                return dilate_projection(proj, pt, self.border_radius());
             }
             if ty == SHAPE_TYPE_CYLINDER {
-                return self.to_cylinder().project_point_on_boundary(pose, pt);
+                let proj = self.to_cylinder().project_point_on_boundary(pose, pt);
+                return dilate_projection(proj, pt, self.border_radius());
             }
         }
         ProjectionResult::new(pt, false)
@@ -407,6 +537,17 @@ impl Shape {
 
     /// Computes the local support point of a self.
     pub fn local_support_point(&self, dir: Vector, vertices: &[PaddedVector]) -> Vector {
+        let inner_pt = self.inner_local_support_point(dir, vertices);
+        let border_radius = self.border_radius();
+        if border_radius > 0.0 {
+            inner_pt + dir.normalize_or_zero() * border_radius
+        } else {
+            inner_pt
+        }
+    }
+
+    /// Computes the local support point of a self, ignoring its border radius.
+    fn inner_local_support_point(&self, dir: Vector, vertices: &[PaddedVector]) -> Vector {
         let ty = self.shape_type();
         if ty == SHAPE_TYPE_BALL {
             return self.to_ball().local_support_point(dir);
@@ -417,7 +558,7 @@ impl Shape {
         if ty == SHAPE_TYPE_TRIANGLE {
             return self.to_triangle().local_support_point(dir);
         }
-        if ty == SHAPE_TYPE_CAPSULE {
+        if ty == SHAPE_TYPE_CAPSULE || ty == SHAPE_TYPE_SEGMENT {
             return self.to_capsule().local_support_point(dir);
         }
         #[cfg(feature = "dim3")]
@@ -428,6 +569,9 @@ impl Shape {
             if ty == SHAPE_TYPE_CYLINDER {
                 return self.to_cylinder().local_support_point(dir);
             }
+            if ty == SHAPE_TYPE_TETRAHEDRON {
+                return self.to_tetrahedron().local_support_point(dir);
+            }
         }
 
         if ty == SHAPE_TYPE_CONVEX_POLY {
@@ -447,7 +591,7 @@ impl Shape {
         if ty == SHAPE_TYPE_TRIANGLE {
             return self.to_triangle().support_face(dir);
         }
-        if ty == SHAPE_TYPE_CAPSULE {
+        if ty == SHAPE_TYPE_CAPSULE || ty == SHAPE_TYPE_SEGMENT {
             return self.to_capsule().support_face(dir);
         }
 
@@ -473,7 +617,7 @@ impl Shape {
         if ty == SHAPE_TYPE_TRIANGLE {
             return self.to_triangle().support_face(dir);
         }
-        if ty == SHAPE_TYPE_CAPSULE {
+        if ty == SHAPE_TYPE_CAPSULE || ty == SHAPE_TYPE_SEGMENT {
             return self.to_capsule().support_face(dir);
         }
         if ty == SHAPE_TYPE_CONE {
@@ -482,6 +626,9 @@ impl Shape {
         if ty == SHAPE_TYPE_CYLINDER {
             return self.to_cylinder().support_face(dir);
         }
+        if ty == SHAPE_TYPE_TETRAHEDRON {
+            return self.to_tetrahedron().support_face(dir);
+        }
 
         if ty == SHAPE_TYPE_CONVEX_POLY {
             return self.to_convex_poly().support_face(vertices, indices, dir);
@@ -498,11 +645,14 @@ impl Shape {
             || ty == SHAPE_TYPE_CYLINDER
             || ty == SHAPE_TYPE_CONVEX_POLY
             || ty == SHAPE_TYPE_TRIANGLE
+            || ty == SHAPE_TYPE_SEGMENT
+            || ty == SHAPE_TYPE_TETRAHEDRON
         {
-            // No subshape, return the original shape itself.
+            // No subshape, return the original shape itself. Its border radius
+            // becomes the thickness.
             return PfmSubShape {
-                shape: *self,
-                thickness: 0.0,
+                shape: self.with_border_radius(0.0),
+                thickness: self.border_radius(),
                 valid: true,
             };
         }
@@ -537,6 +687,12 @@ impl Shape {
 
     /// Creates an AABB from a transformed self.
     pub fn compute_aabb(&self, pose: Pose, vertices: &[PaddedVector]) -> Aabb {
+        self.compute_inner_aabb(pose, vertices)
+            .loosened(self.border_radius())
+    }
+
+    /// Creates an AABB from a transformed self, ignoring its border radius.
+    fn compute_inner_aabb(&self, pose: Pose, vertices: &[PaddedVector]) -> Aabb {
         let ty = self.shape_type();
         if ty == SHAPE_TYPE_BALL {
             let ball = self.to_ball();
@@ -571,7 +727,7 @@ impl Shape {
             return local_aabb.transform_by(pose);
         }
 
-        if ty == SHAPE_TYPE_CAPSULE {
+        if ty == SHAPE_TYPE_CAPSULE || ty == SHAPE_TYPE_SEGMENT {
             let capsule = self.to_capsule();
             let aa = pose * capsule.segment.a;
             let bb = pose * capsule.segment.b;
@@ -606,6 +762,11 @@ impl Shape {
                 );
                 return local_aabb.transform_by(pose);
             }
+
+            if ty == SHAPE_TYPE_TETRAHEDRON {
+                let local_aabb = self.to_tetrahedron().aabb();
+                return local_aabb.transform_by(pose);
+            }
         }
 
         if ty == SHAPE_TYPE_CONVEX_POLY {
@@ -626,6 +787,12 @@ impl Shape {
             return local_aabb.transform_by(pose);
         }
 
+        if ty == SHAPE_TYPE_HEIGHTFIELD {
+            let hf = self.to_heightfield();
+            let local_aabb = hf.aabb();
+            return local_aabb.transform_by(pose);
+        }
+
         Aabb::default()
     }
 
@@ -683,6 +850,34 @@ impl Shape {
         };
     }
 
+    /// Creates a segment shape.
+    ///
+    /// # Parameters
+    ///
+    /// - `a`: First endpoint of the segment
+    /// - `b`: Second endpoint of the segment
+    pub fn segment(a: Vector, b: Vector) -> Self {
+        let mut result = Self::capsule(a, b, 0.0);
+        result.a.w = f32::from_bits(SHAPE_TYPE_SEGMENT);
+        result
+    }
+
+    /// Creates a tetrahedron shape (3D only).
+    ///
+    /// The GPU layout has no room for four vertices, so the tetrahedron is
+    /// expressed relative to its first vertex: `b`, `c` and `d` are the
+    /// positions of the other vertices minus the first one, and the shape's
+    /// pose must be translated to that first vertex.
+    #[cfg(feature = "dim3")]
+    pub fn tetrahedron(b: Vector, c: Vector, d: Vector) -> Self {
+        let tag = f32::from_bits(SHAPE_TYPE_TETRAHEDRON);
+        Self {
+            a: Vec4::new(b.x, b.y, b.z, tag),
+            b: Vec4::new(c.x, c.y, c.z, 0.0),
+            c: Vec4::new(d.x, d.y, d.z, 0.0),
+        }
+    }
+
     /// Creates a polyline shape from BVH data.
     ///
     /// A polyline is a connected sequence of line segments defined by vertices.
@@ -751,6 +946,43 @@ impl Shape {
         };
     }
 
+    /// Creates a heightfield shape from its grid data.
+    ///
+    /// # Parameters
+    ///
+    /// - `first_vtx_id`: Index of the first (scaled) grid vertex in the vertex buffer
+    /// - `first_cell_id`: Index of the first cell flag in the index buffer
+    /// - `nrows`: Number of cell rows (always 1 in 2D)
+    /// - `ncols`: Number of cell columns
+    /// - `aabb_mins`: Minimum point of the bounding box
+    /// - `aabb_maxs`: Maximum point of the bounding box
+    pub fn heightfield(
+        first_vtx_id: u32,
+        first_cell_id: u32,
+        nrows: u32,
+        ncols: u32,
+        aabb_mins: Vector,
+        aabb_maxs: Vector,
+    ) -> Self {
+        let tag = f32::from_bits(SHAPE_TYPE_HEIGHTFIELD);
+        let a0 = f32::from_bits(first_vtx_id);
+        let a1 = f32::from_bits(first_cell_id);
+        let a2 = f32::from_bits(nrows);
+        let ncols = f32::from_bits(ncols);
+        #[cfg(feature = "dim2")]
+        return Self {
+            a: Vec4::new(a0, a1, a2, tag),
+            b: Vec4::new(aabb_mins.x, aabb_mins.y, 0.0, ncols),
+            c: Vec4::new(aabb_maxs.x, aabb_maxs.y, 0.0, 0.0),
+        };
+        #[cfg(feature = "dim3")]
+        return Self {
+            a: Vec4::new(a0, a1, a2, tag),
+            b: Vec4::new(aabb_mins.x, aabb_mins.y, aabb_mins.z, ncols),
+            c: Vec4::new(aabb_maxs.x, aabb_maxs.y, aabb_maxs.z, 0.0),
+        };
+    }
+
     /// Creates a convex polyhedron from vertex and face buffer ranges.
     pub fn convex_poly(
         first_vtx_id: u32,
@@ -831,3 +1063,23 @@ impl Shape {
         bvh_vtx_root_id + bvh_node_len * 2
     }
 }
+
+/// Dilates the projection of `pt` on the boundary of a shape by `border_radius`,
+/// turning it into the projection on the boundary of the rounded shape.
+fn dilate_projection(proj: ProjectionResult, pt: Vector, border_radius: f32) -> ProjectionResult {
+    if border_radius == 0.0 {
+        return proj;
+    }
+
+    let dpt = pt - proj.point;
+    let dist = dpt.length();
+    let mut normal = if dist != 0.0 { dpt / dist } else { Vector::Y };
+    if proj.is_inside {
+        normal = -normal;
+    }
+
+    ProjectionResult::new(
+        proj.point + normal * border_radius,
+        proj.is_inside || dist <= border_radius,
+    )
+}
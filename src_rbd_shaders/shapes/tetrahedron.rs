@@ -3,7 +3,9 @@
 //! This module provides the tetrahedron shape definition from its four vertices.
 
 use crate::Vector;
-use crate::queries::ProjectionWithLocation;
+use crate::bounding_volumes::Aabb;
+use crate::queries::{PolygonalFeature, ProjectionResult, ProjectionWithLocation};
+use crate::shapes::triangle::Triangle;
 use glamx::{Vec2, Vec3};
 
 // TODO: group all the epsilon in the same place.
@@ -30,6 +32,83 @@ impl Tetrahedron {
         Self { a, b, c, d }
     }
 
+    /// Computes the AABB of a tetrahedron.
+    pub fn aabb(&self) -> Aabb {
+        let mins = self.a.min(self.b).min(self.c).min(self.d);
+        let maxs = self.a.max(self.b).max(self.c).max(self.d);
+        Aabb::new(mins, maxs)
+    }
+
+    /// Computes the local support point of a tetrahedron.
+    pub fn local_support_point(&self, dir: Vector) -> Vector {
+        let mut best = self.a;
+        let mut best_dot = dir.dot(self.a);
+        if dir.dot(self.b) > best_dot {
+            best = self.b;
+            best_dot = dir.dot(self.b);
+        }
+        if dir.dot(self.c) > best_dot {
+            best = self.c;
+            best_dot = dir.dot(self.c);
+        }
+        if dir.dot(self.d) > best_dot {
+            best = self.d;
+        }
+        best
+    }
+
+    /// Computes the support face of a tetrahedron: the face with the outward
+    /// normal most aligned with `dir`.
+    pub fn support_face(&self, dir: Vector) -> PolygonalFeature {
+        let mut best = Triangle::new(self.a, self.b, self.c);
+        let mut best_dot = outward_normal(self.a, self.b, self.c, self.d).dot(dir);
+
+        let abd = outward_normal(self.a, self.b, self.d, self.c).dot(dir);
+        if abd > best_dot {
+            best = Triangle::new(self.a, self.b, self.d);
+            best_dot = abd;
+        }
+        let acd = outward_normal(self.a, self.c, self.d, self.b).dot(dir);
+        if acd > best_dot {
+            best = Triangle::new(self.a, self.c, self.d);
+            best_dot = acd;
+        }
+        let bcd = outward_normal(self.b, self.c, self.d, self.a).dot(dir);
+        if bcd > best_dot {
+            best = Triangle::new(self.b, self.c, self.d);
+        }
+
+        best.support_face(dir)
+    }
+
+    /// Projects a point onto the boundary of a tetrahedron.
+    ///
+    /// A point inside the tetrahedron is projected on its closest face.
+    pub fn project_local_point_on_boundary(&self, pt: Vector) -> ProjectionResult {
+        let proj = self.project_local_point_and_get_location(pt, true);
+        if !proj.inside {
+            return ProjectionResult::new(proj.point, false);
+        }
+
+        let faces = [
+            Triangle::new(self.a, self.b, self.c),
+            Triangle::new(self.a, self.b, self.d),
+            Triangle::new(self.a, self.c, self.d),
+            Triangle::new(self.b, self.c, self.d),
+        ];
+        let mut best = pt;
+        let mut best_dist = f32::MAX;
+        for face in faces {
+            let face_proj = face.project_local_point_and_get_location(pt, false).point;
+            let dist = face_proj.distance_squared(pt);
+            if dist < best_dist {
+                best = face_proj;
+                best_dist = dist;
+            }
+        }
+        ProjectionResult::new(best, true)
+    }
+
     /// Projects a point onto a tetrahedron and returns location information.
     pub fn project_local_point_and_get_location(
         &self,
@@ -188,6 +267,12 @@ struct EdgeCheck {
     valid: bool,
 }
 
+/// Unit normal of the face `abc`, pointing away from the `opposite` vertex.
+fn outward_normal(a: Vector, b: Vector, c: Vector, opposite: Vector) -> Vector {
+    let n = (b - a).cross(c - a).normalize_or_zero();
+    if n.dot(opposite - a) > 0.0 { -n } else { n }
+}
+
 /// Voronoi regions of edges.
 fn check_edge(
     i: u32,
@@ -0,0 +1,118 @@
+//! Tests for the sparse runtime writes to the rigid-body state.
+
+use crate::dynamics::{
+    BODY_TYPE_DYNAMIC, BODY_TYPE_FIXED, BODY_TYPE_KINEMATIC_VELOCITY, BODY_UPDATE_ADD_FORCE,
+    BODY_UPDATE_IMPULSE, BODY_UPDATE_POSE, BODY_UPDATE_RESET_FORCE, BODY_UPDATE_VELOCITY,
+    BodyActivation, BodyParams, BodyUpdate, Force, Impulse, LocalMassProperties, Velocity,
+    gpu_apply_body_updates,
+};
+use crate::{AngVector, Pose, Vector};
+use glamx::UVec3;
+
+/// Applies the same update (every flag set) to a dynamic, a fixed and a
+/// velocity-based kinematic body, each with an inverse mass of 0.5, a velocity
+/// of `Vector::X` and a force of `Vector::Y`.
+fn apply_to_every_body_type(update: BodyUpdate) -> (Vec<Pose>, Vec<Velocity>, Vec<Force>) {
+    let body_types = [
+        BODY_TYPE_DYNAMIC,
+        BODY_TYPE_FIXED,
+        BODY_TYPE_KINEMATIC_VELOCITY,
+    ];
+    let num_bodies = body_types.len();
+    let updates: Vec<_> = (0..num_bodies as u32)
+        .map(|body_id| BodyUpdate { body_id, ..update })
+        .collect();
+    let mut poses = vec![Pose::IDENTITY; num_bodies];
+    let mut vels = vec![Velocity::new(Vector::X, AngVector::default()); num_bodies];
+    let mut targets = vec![Pose::IDENTITY; num_bodies];
+    let mut forces = vec![Force::new(Vector::Y, AngVector::default()); num_bodies];
+    let local_mprops = vec![
+        LocalMassProperties {
+            inv_mass: Vector::splat(0.5),
+            ..Default::default()
+        };
+        num_bodies
+    ];
+    let params = vec![BodyParams::default(); num_bodies];
+    let mut activations = vec![
+        BodyActivation {
+            time_since_can_sleep: 1.0,
+            sleeping: 1,
+        };
+        num_bodies
+    ];
+
+    for i in 0..num_bodies as u32 {
+        gpu_apply_body_updates(
+            UVec3::new(i, 0, 0),
+            &updates,
+            &mut poses,
+            &mut vels,
+            &mut targets,
+            &mut forces,
+            &local_mprops,
+            &params,
+            &mut activations,
+            &body_types,
+            &(num_bodies as u32),
+        );
+    }
+
+    for (pose, target) in poses.iter().zip(&targets) {
+        assert_eq!(pose.translation, target.translation);
+    }
+    assert!(activations.iter().all(|a| a.sleeping == 0));
+    (poses, vels, forces)
+}
+
+#[test]
+fn test_body_updates_applied_in_order() {
+    let update = BodyUpdate {
+        pose: Pose::from_translation(Vector::splat(3.0)),
+        velocity: Velocity::new(Vector::Y * 2.0, AngVector::default()),
+        impulse: Impulse::new(Vector::X * 4.0, AngVector::default()),
+        force: Force::new(Vector::X * 5.0, AngVector::default()),
+        flags: BODY_UPDATE_POSE
+            | BODY_UPDATE_VELOCITY
+            | BODY_UPDATE_IMPULSE
+            | BODY_UPDATE_RESET_FORCE
+            | BODY_UPDATE_ADD_FORCE,
+        ..Default::default()
+    };
+    let (poses, vels, forces) = apply_to_every_body_type(update);
+
+    // Every body is teleported.
+    assert!(poses.iter().all(|p| p.translation == Vector::splat(3.0)));
+
+    // Dynamic body: the velocity is replaced before the impulse is applied, and
+    // the force is reset before the new one is added.
+    assert_eq!(vels[0].linear, Vector::Y * 2.0 + Vector::X * 2.0);
+    assert_eq!(forces[0].linear, Vector::X * 5.0);
+
+    // Fixed body: only the pose changes.
+    assert_eq!(vels[1].linear, Vector::X);
+    assert_eq!(forces[1].linear, Vector::ZERO);
+
+    // Velocity-based kinematic body: the velocity is replaced, but impulses and
+    // forces are ignored.
+    assert_eq!(vels[2].linear, Vector::Y * 2.0);
+    assert_eq!(forces[2].linear, Vector::ZERO);
+}
+
+#[test]
+fn test_body_updates_accumulate_forces() {
+    let update = BodyUpdate {
+        impulse: Impulse::new(Vector::X * 4.0, AngVector::default()),
+        force: Force::new(Vector::X * 5.0, AngVector::default()),
+        flags: BODY_UPDATE_IMPULSE | BODY_UPDATE_ADD_FORCE,
+        ..Default::default()
+    };
+    let (poses, vels, forces) = apply_to_every_body_type(update);
+
+    assert!(poses.iter().all(|p| p.translation == Vector::ZERO));
+    // Without a velocity write, the impulse adds to the current velocity.
+    assert_eq!(vels[0].linear, Vector::X * 3.0);
+    assert_eq!(forces[0].linear, Vector::Y + Vector::X * 5.0);
+    assert_eq!(vels[1].linear, Vector::X);
+    assert_eq!(forces[1].linear, Vector::Y);
+}
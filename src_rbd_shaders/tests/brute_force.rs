@@ -0,0 +1,62 @@
+//! Tests for the brute-force broad phase on batches of different sizes.
+
+use crate::bounding_volumes::Aabb;
+use crate::broad_phase::{CollisionPair, gpu_bf_compute_aabbs, gpu_bf_find_pairs};
+use crate::shapes::Shape;
+use crate::utils::BatchIndices;
+use crate::{Pose, Vector};
+use glamx::{UVec2, UVec3};
+use rapier::geometry::InteractionGroups;
+
+const CAPACITY: u32 = 4;
+
+#[test]
+fn test_pairs_of_packed_batches() {
+    // Batch 0 holds two colliders, and batch 1 three, packed right after them.
+    let mut batch_ids = BatchIndices {
+        num_batches: 2,
+        colliders_batch_capacity: 3,
+        collision_pairs_batch_capacity: CAPACITY,
+        ..Default::default()
+    };
+    batch_ids.set_colliders_range(0, 0, 2, 2);
+    batch_ids.set_colliders_range(1, 2, 3, 3);
+
+    // Overlapping balls, except the first one of batch 1.
+    let poses = [0.0, 0.8, 5.0, 0.0, 0.8].map(|x| Pose::from_translation(Vector::X * x));
+    let shapes = [Shape::ball(0.5); 5];
+    let groups = [InteractionGroups::all(); 5];
+    let pair_filter: Vec<_> = (0..5).map(|i| [i, 0]).collect();
+
+    let cap = batch_ids.colliders_batch_capacity;
+    let mut aabbs = vec![Aabb::default(); 5];
+    for x in 0..cap * batch_ids.num_batches {
+        gpu_bf_compute_aabbs(
+            UVec3::new(x, 0, 0),
+            &poses,
+            &shapes,
+            &mut aabbs,
+            &batch_ids,
+            &[],
+        );
+    }
+
+    let mut pairs = vec![CollisionPair::default(); (2 * CAPACITY) as usize];
+    let mut pairs_len = [0; 2];
+    for x in 0..cap * cap * batch_ids.num_batches {
+        gpu_bf_find_pairs(
+            UVec3::new(x, 0, 0),
+            &aabbs,
+            &mut pairs,
+            &mut pairs_len,
+            &groups,
+            &batch_ids,
+            &pair_filter,
+        );
+    }
+
+    // Pairs hold the local collider indices of their batch.
+    assert_eq!(pairs_len, [1, 1]);
+    assert_eq!(pairs[0].colliders, UVec2::new(0, 1));
+    assert_eq!(pairs[CAPACITY as usize].colliders, UVec2::new(1, 2));
+}
@@ -0,0 +1,110 @@
+//! Tests for the continuous collision detection of fast bodies.
+
+use crate::broad_phase::CollisionPair;
+use crate::dynamics::{
+    BODY_FLAG_CCD_ENABLED, BodyParams, gpu_ccd_clamp_motion, gpu_ccd_init, gpu_ccd_pairs,
+    gpu_ccd_reset_pairs, gpu_ccd_toi,
+};
+use crate::queries::ColliderMaterial;
+use crate::shapes::Shape;
+use crate::utils::BatchIndices;
+use crate::{Pose, Vector};
+use glamx::{UVec2, UVec3, UVec4};
+
+#[test]
+fn test_ccd_stops_fast_ball_before_thin_wall() {
+    let capacity = 4;
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: 3,
+        collision_pairs_batch_capacity: capacity,
+        contacts_batch_capacity: capacity,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(3, 3, 0);
+
+    // A thin fixed wall at the origin, and two balls crossing it in a single
+    // step. Only the first ball has CCD enabled. Each collider is attached to
+    // the body with the same index, at its origin.
+    let mut wall_half_extents = Vector::splat(5.0);
+    wall_half_extents.x = 0.1;
+    let shapes = [
+        Shape::cuboid(wall_half_extents),
+        Shape::ball(0.2),
+        Shape::ball(0.2),
+    ];
+    let mut body_params = [BodyParams::default(); 3];
+    body_params[1].flags |= BODY_FLAG_CCD_ENABLED;
+    let start_poses = [
+        Pose::IDENTITY,
+        Pose::from_translation(-Vector::X * 5.0),
+        Pose::from_translation(Vector::Y * 3.0 - Vector::X * 5.0),
+    ];
+    let end_poses = [
+        Pose::IDENTITY,
+        Pose::from_translation(Vector::X * 5.0),
+        Pose::from_translation(Vector::Y * 3.0 + Vector::X * 5.0),
+    ];
+
+    let mut saved_poses = [Pose::IDENTITY; 3];
+    let mut toi_keys = [0; 3];
+    gpu_ccd_init(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &start_poses,
+        &mut saved_poses,
+        &mut toi_keys,
+        &batch_ids,
+    );
+
+    let pairs = [UVec2::new(0, 1), UVec2::new(0, 2), UVec2::new(1, 2)]
+        .map(|colliders| CollisionPair { colliders });
+    let mut ccd_pairs = vec![UVec4::ZERO; capacity as usize];
+    let mut ccd_pairs_len = [7];
+    gpu_ccd_reset_pairs(UVec3::ZERO, &mut ccd_pairs_len);
+    gpu_ccd_pairs(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &pairs,
+        &[pairs.len() as u32],
+        &[0, 1, 2],
+        &[ColliderMaterial::default(); 3],
+        &body_params,
+        &mut ccd_pairs,
+        &mut ccd_pairs_len,
+        &batch_ids,
+    );
+    // The pair without a CCD body is skipped.
+    assert_eq!(ccd_pairs_len[0], 2);
+
+    gpu_ccd_toi(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &ccd_pairs,
+        &ccd_pairs_len,
+        &saved_poses,
+        &end_poses,
+        &shapes,
+        &mut toi_keys,
+        &batch_ids,
+        &[],
+        &[],
+    );
+
+    let mut poses = end_poses;
+    gpu_ccd_clamp_motion(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &saved_poses,
+        &toi_keys,
+        &mut poses,
+        &batch_ids,
+    );
+
+    // The CCD ball stops where it first touches the wall, at x = -0.1 - 0.2.
+    let x = poses[1].translation.x;
+    assert!((x + 0.3).abs() < 0.05, "expected x = -0.3, got {x}");
+    // The other bodies keep their solved poses: the second ball tunnels through.
+    assert_eq!(poses[0].translation, end_poses[0].translation);
+    assert_eq!(poses[2].translation, end_poses[2].translation);
+}
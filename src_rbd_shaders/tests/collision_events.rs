@@ -0,0 +1,285 @@
+//! Tests for the collision events and contact-force events of touching colliders.
+
+use crate::Vector;
+use crate::broad_phase::{
+    COLLISION_EVENT_STARTED, COLLISION_EVENT_STOPPED, CollisionEvent, ContactForceEvent,
+    gpu_emit_collision_events, gpu_emit_contact_force_events, gpu_reset_touching_pairs,
+    gpu_touching_pairs,
+};
+use crate::dynamics::{RbdSimParams, TwoBodyConstraint};
+use crate::queries::{
+    COLLIDER_FLAG_COLLISION_EVENTS, COLLIDER_FLAG_CONTACT_FORCE_EVENTS, ColliderMaterial,
+    IndexedManifold,
+};
+use crate::utils::BatchIndices;
+use glamx::{UVec2, UVec3};
+
+const CAPACITY: u32 = 8;
+
+fn batch_ids() -> BatchIndices {
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: 3,
+        contacts_batch_capacity: CAPACITY,
+        event_pairs_batch_capacity: CAPACITY,
+        events_batch_capacity: CAPACITY,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(3, 3, 0);
+    batch_ids
+}
+
+fn manifold(x: u32, y: u32) -> IndexedManifold {
+    IndexedManifold {
+        colliders: UVec2::new(x, y),
+        bodies: UVec2::new(x, y),
+        ..Default::default()
+    }
+}
+
+/// Collects the touching pairs of the `contacts`, padded to the batch capacity.
+fn touching_pairs(
+    contacts: &[IndexedManifold],
+    materials: &[ColliderMaterial; 3],
+) -> (Vec<UVec2>, u32) {
+    let mut padded = contacts.to_vec();
+    padded.resize(CAPACITY as usize, IndexedManifold::default());
+
+    let mut pairs = vec![UVec2::ZERO; CAPACITY as usize];
+    let mut pairs_len = [7];
+    gpu_reset_touching_pairs(UVec3::ZERO, &mut pairs_len);
+    assert_eq!(pairs_len[0], 0);
+    gpu_touching_pairs(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &padded,
+        &[contacts.len() as u32],
+        materials,
+        &mut pairs,
+        &mut pairs_len,
+        &batch_ids(),
+    );
+    (pairs, pairs_len[0])
+}
+
+#[test]
+fn test_collision_events_of_touching_pairs() {
+    // Only the first collider asks for collision events.
+    let mut materials = [ColliderMaterial::default(); 3];
+    materials[0].flags = COLLIDER_FLAG_COLLISION_EVENTS;
+
+    // The pair (0, 1) generates two manifolds (e.g. two mesh triangles).
+    let contacts = [manifold(0, 1), manifold(1, 2), manifold(0, 1)];
+    let (pairs, pairs_len) = touching_pairs(&contacts, &materials);
+    assert_eq!(pairs_len, 2);
+    assert_eq!(pairs[..2], [UVec2::new(0, 1); 2]);
+
+    let (no_pairs, no_pairs_len) = touching_pairs(&[], &materials);
+    assert_eq!(no_pairs_len, 0);
+
+    let emit = |old_pairs: &[UVec2], old_len: u32, pairs: &[UVec2], len: u32| {
+        let mut events = vec![CollisionEvent::default(); CAPACITY as usize];
+        let mut events_len = [0];
+        gpu_emit_collision_events(
+            UVec3::ZERO,
+            UVec3::ONE,
+            pairs,
+            &[len],
+            old_pairs,
+            &[old_len],
+            &materials,
+            &mut events,
+            &mut events_len,
+            &batch_ids(),
+        );
+        events.truncate(events_len[0] as usize);
+        events
+    };
+
+    // The duplicate manifolds emit a single event.
+    let started = emit(&no_pairs, no_pairs_len, &pairs, pairs_len);
+    assert_eq!(
+        started,
+        [CollisionEvent {
+            colliders: UVec2::new(0, 1),
+            kind: COLLISION_EVENT_STARTED,
+            flags: 0,
+        }]
+    );
+
+    assert!(emit(&pairs, pairs_len, &pairs, pairs_len).is_empty());
+
+    let stopped = emit(&pairs, pairs_len, &no_pairs, no_pairs_len);
+    assert_eq!(
+        stopped,
+        [CollisionEvent {
+            colliders: UVec2::new(0, 1),
+            kind: COLLISION_EVENT_STOPPED,
+            flags: 0,
+        }]
+    );
+}
+
+#[test]
+fn test_contact_force_events_above_threshold() {
+    let mut materials = [ColliderMaterial::default(); 3];
+    materials[0].flags = COLLIDER_FLAG_CONTACT_FORCE_EVENTS;
+    materials[0].contact_force_event_threshold = 5.0;
+    materials[2].flags = COLLIDER_FLAG_CONTACT_FORCE_EVENTS;
+    materials[2].contact_force_event_threshold = 10.0;
+
+    // The pair (0, 1) has two manifolds, the second one reported the other way
+    // around. The pair (1, 2) stays below its threshold.
+    let mut contacts = vec![IndexedManifold::default(); CAPACITY as usize];
+    contacts[0] = manifold(0, 1);
+    contacts[1] = manifold(1, 2);
+    contacts[2] = manifold(1, 0);
+
+    let mut constraints = vec![TwoBodyConstraint::default(); CAPACITY as usize];
+    constraints[0].dir_a = Vector::Y;
+    constraints[0].len = 2;
+    constraints[0].elements[0].normal_part.impulse = 0.1;
+    constraints[0].elements[1].normal_part.impulse = 0.2;
+    constraints[1].dir_a = Vector::X;
+    constraints[1].len = 1;
+    constraints[1].elements[0].normal_part.impulse = 0.5;
+    constraints[2].dir_a = -Vector::Y;
+    constraints[2].len = 1;
+    constraints[2].elements[0].normal_part.impulse = 0.3;
+
+    let params = RbdSimParams {
+        dt: 0.1,
+        ..Default::default()
+    };
+    let mut events = vec![ContactForceEvent::default(); CAPACITY as usize];
+    let mut events_len = [0];
+    gpu_emit_contact_force_events(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &contacts,
+        &[3],
+        &constraints,
+        &materials,
+        &[params],
+        &mut events,
+        &mut events_len,
+        &batch_ids(),
+    );
+
+    assert_eq!(events_len[0], 1);
+    let event = events[0];
+    assert_eq!(event.colliders, UVec2::new(0, 1));
+    assert!((*event.total_force - Vector::Y * 6.0).length() < 1.0e-4);
+    assert!((event.total_force_magnitude - 6.0).abs() < 1.0e-4);
+    assert!((event.max_force_magnitude - 3.0).abs() < 1.0e-4);
+    assert!((*event.max_force_direction - Vector::Y).length() < 1.0e-4);
+}
+
+/// Materials of a compound made of the parts `0` and `1`, and of a collider `2`.
+fn compound_materials(flags: u32) -> [ColliderMaterial; 3] {
+    let mut materials = [ColliderMaterial::default(); 3];
+    materials[0].flags = flags;
+    materials[1].flags = flags;
+    materials[1].part_id = 1;
+    materials
+}
+
+#[test]
+fn test_collision_events_of_compound_parts() {
+    let materials = compound_materials(COLLIDER_FLAG_COLLISION_EVENTS);
+    let emit = |old_pairs: &[UVec2], pairs: &[UVec2]| {
+        let mut padded_old = old_pairs.to_vec();
+        padded_old.resize(CAPACITY as usize, UVec2::ZERO);
+        let mut padded = pairs.to_vec();
+        padded.resize(CAPACITY as usize, UVec2::ZERO);
+
+        let mut events = vec![CollisionEvent::default(); CAPACITY as usize];
+        let mut events_len = [0];
+        gpu_emit_collision_events(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &padded,
+            &[pairs.len() as u32],
+            &padded_old,
+            &[old_pairs.len() as u32],
+            &materials,
+            &mut events,
+            &mut events_len,
+            &batch_ids(),
+        );
+        events.truncate(events_len[0] as usize);
+        events
+    };
+
+    // Both parts start touching: a single event, under the first part.
+    let both = [UVec2::new(1, 2), UVec2::new(0, 2)];
+    assert_eq!(
+        emit(&[], &both),
+        [CollisionEvent {
+            colliders: UVec2::new(0, 2),
+            kind: COLLISION_EVENT_STARTED,
+            flags: 0,
+        }]
+    );
+
+    // The compound keeps touching while any of its parts does.
+    let one = [UVec2::new(2, 1)];
+    assert!(emit(&both, &one).is_empty());
+    assert!(emit(&one, &both).is_empty());
+
+    assert_eq!(
+        emit(&one, &[]),
+        [CollisionEvent {
+            colliders: UVec2::new(2, 0),
+            kind: COLLISION_EVENT_STOPPED,
+            flags: 0,
+        }]
+    );
+}
+
+#[test]
+fn test_contact_force_events_of_compound_parts() {
+    let mut materials = compound_materials(COLLIDER_FLAG_CONTACT_FORCE_EVENTS);
+    materials[0].contact_force_event_threshold = 5.0;
+    materials[1].contact_force_event_threshold = 5.0;
+
+    // Each part pushes the collider `2` with a force of 3: only their sum
+    // exceeds the threshold.
+    let mut contacts = vec![IndexedManifold::default(); CAPACITY as usize];
+    contacts[0] = manifold(1, 2);
+    contacts[1] = manifold(2, 0);
+
+    let mut constraints = vec![TwoBodyConstraint::default(); CAPACITY as usize];
+    constraints[0].dir_a = Vector::Y;
+    constraints[0].len = 1;
+    constraints[0].elements[0].normal_part.impulse = 0.3;
+    constraints[1].dir_a = -Vector::Y;
+    constraints[1].len = 1;
+    constraints[1].elements[0].normal_part.impulse = 0.3;
+
+    let params = RbdSimParams {
+        dt: 0.1,
+        ..Default::default()
+    };
+    let mut events = vec![ContactForceEvent::default(); CAPACITY as usize];
+    let mut events_len = [0];
+    gpu_emit_contact_force_events(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &contacts,
+        &[2],
+        &constraints,
+        &materials,
+        &[params],
+        &mut events,
+        &mut events_len,
+        &batch_ids(),
+    );
+
+    assert_eq!(events_len[0], 1);
+    let event = events[0];
+    assert_eq!(event.colliders, UVec2::new(0, 2));
+    assert!((*event.total_force - Vector::Y * 6.0).length() < 1.0e-4);
+    assert!((event.total_force_magnitude - 6.0).abs() < 1.0e-4);
+    assert!((event.max_force_magnitude - 3.0).abs() < 1.0e-4);
+}
@@ -0,0 +1,201 @@
+//! Tests for the narrow phase between two composite shapes (trimeshes or polylines).
+
+use crate::broad_phase::{
+    CollisionPair, NarrowPhasePfmPair, gpu_narrow_phase_pfm_pfm,
+    gpu_narrow_phase_shape_shape_deferred,
+};
+use crate::queries::{ColliderMaterial, IndexedManifold};
+use crate::shapes::Shape;
+use crate::utils::BatchIndices;
+use crate::{PaddedVector, Pose, Vector};
+use glamx::{UVec2, UVec3};
+use khal_std::index::MaybeIndexUnchecked;
+
+/// Appends a composite shape to the vertex and index buffers: a BVH made of a
+/// root and one leaf per primitive (or of a single leaf), then the primitives.
+///
+/// Returns `(bvh_vtx_root_id, bvh_idx_root_id, bvh_node_len, aabb_mins, aabb_maxs)`.
+fn push_composite(
+    vertices: &mut Vec<PaddedVector>,
+    indices: &mut Vec<u32>,
+    points: &[Vector],
+    prims: &[&[u32]],
+) -> (u32, u32, u32, Vector, Vector) {
+    let vtx_root = vertices.len() as u32;
+    let idx_root = indices.len() as u32;
+    let leaf_aabbs: Vec<_> = prims
+        .iter()
+        .map(|prim| {
+            let pts = || prim.iter().map(|i| points[*i as usize]);
+            (
+                pts().reduce(Vector::min).unwrap(),
+                pts().reduce(Vector::max).unwrap(),
+            )
+        })
+        .collect();
+    let mins = leaf_aabbs.iter().map(|a| a.0).reduce(Vector::min).unwrap();
+    let maxs = leaf_aabbs.iter().map(|a| a.1).reduce(Vector::max).unwrap();
+
+    let mut nodes = vec![];
+    if prims.len() > 1 {
+        let node_len = prims.len() as u32 + 1;
+        nodes.push(((mins, maxs), [1, node_len, 0]));
+        for (k, aabb) in leaf_aabbs.iter().enumerate() {
+            nodes.push((*aabb, [u32::MAX, k as u32 + 2, k as u32]));
+        }
+    } else {
+        nodes.push((leaf_aabbs[0], [u32::MAX, 1, 0]));
+    }
+
+    for ((node_mins, node_maxs), idx) in &nodes {
+        vertices.push(PaddedVector::new(*node_mins));
+        vertices.push(PaddedVector::new(*node_maxs));
+        indices.extend_from_slice(idx);
+    }
+    vertices.extend(points.iter().map(|pt| PaddedVector::new(*pt)));
+    for prim in prims {
+        indices.extend_from_slice(prim);
+    }
+
+    (vtx_root, idx_root, nodes.len() as u32, mins, maxs)
+}
+
+/// Runs the deferred narrow phase, then the PFM-PFM pass, on the pair made of
+/// the two given colliders, each attached to its own body.
+///
+/// Returns the number of PFM pairs emitted and the contacts found.
+fn narrow_phase(
+    shapes: [Shape; 2],
+    poses: [Pose; 2],
+    vertices: &[PaddedVector],
+    indices: &[u32],
+) -> (u32, Vec<IndexedManifold>) {
+    let capacity = 16;
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: 2,
+        collision_pairs_batch_capacity: capacity,
+        contacts_batch_capacity: capacity,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(2, 2, 0);
+    let pairs = [CollisionPair {
+        colliders: UVec2::new(0, 1),
+    }];
+
+    let mut pfm_pairs = vec![NarrowPhasePfmPair::default(); capacity as usize];
+    let mut pfm_pairs_len = [0];
+    gpu_narrow_phase_shape_shape_deferred(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &pairs,
+        &[1],
+        &poses,
+        &shapes,
+        &mut pfm_pairs,
+        &mut pfm_pairs_len,
+        vertices,
+        indices,
+        &batch_ids,
+    );
+
+    let mut contacts = vec![IndexedManifold::default(); capacity as usize];
+    let mut contacts_len = [0];
+    gpu_narrow_phase_pfm_pfm(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &mut contacts,
+        &mut contacts_len,
+        &pfm_pairs,
+        &pfm_pairs_len,
+        &batch_ids,
+        vertices,
+        indices,
+        &[0, 1],
+        &[ColliderMaterial::default(); 2],
+    );
+    contacts.truncate(contacts_len[0] as usize);
+
+    (pfm_pairs_len[0], contacts)
+}
+
+#[test]
+fn test_polyline_polyline_thickness() {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    let points = [-Vector::X, Vector::X];
+    let mut shapes = [Shape::default(); 2];
+    for shape in &mut shapes {
+        let (vtx, idx, len, mins, maxs) =
+            push_composite(&mut vertices, &mut indices, &points, &[&[0, 1]]);
+        *shape = Shape::polyline(vtx, idx, len, mins, maxs);
+    }
+
+    // Two parallel segments, 0.41 apart. The polyline thickness (0.4) is shared
+    // between both capsules, leaving a gap of 0.01.
+    let poses = [Pose::IDENTITY, Pose::from_translation(Vector::Y * 0.41)];
+    let (num_pfm_pairs, contacts) = narrow_phase(shapes, poses, &vertices, &indices);
+
+    assert_eq!(num_pfm_pairs, 1);
+    assert_eq!(contacts.len(), 1);
+    let contact = &contacts[0].contact;
+    assert!(
+        (contact.points_a.at(0).dist - 0.01).abs() < 1.0e-3,
+        "expected a distance of 0.01, got {}",
+        contact.points_a.at(0).dist
+    );
+    assert!((contact.normal_a - Vector::Y).length() < 1.0e-3);
+}
+
+#[cfg(feature = "dim3")]
+#[test]
+fn test_trimesh_resting_on_trimesh() {
+    use glamx::Vec3;
+
+    let mut vertices = vec![];
+    let mut indices = vec![];
+
+    // A flat ground made of two triangles facing up.
+    let ground_points = [
+        Vec3::new(-5.0, 0.0, -5.0),
+        Vec3::new(5.0, 0.0, -5.0),
+        Vec3::new(5.0, 0.0, 5.0),
+        Vec3::new(-5.0, 0.0, 5.0),
+    ];
+    let ground_tris: [&[u32]; 2] = [&[0, 2, 1], &[0, 3, 2]];
+    let (vtx, idx, len, mins, maxs) =
+        push_composite(&mut vertices, &mut indices, &ground_points, &ground_tris);
+    let ground = Shape::trimesh(vtx, idx, len, 2, 4, mins, maxs);
+
+    // A falling mesh made of a small triangle about to touch the ground, and a
+    // second one high above it. The root of its BVH overlaps the ground, but the
+    // upper triangle's leaf doesn't.
+    let mesh_points = [
+        Vec3::new(-0.5, 0.0, -0.5),
+        Vec3::new(0.0, 0.0, 0.5),
+        Vec3::new(0.5, 0.0, -0.5),
+        Vec3::new(-0.5, 3.0, -0.5),
+        Vec3::new(0.0, 3.0, 0.5),
+        Vec3::new(0.5, 3.0, -0.5),
+    ];
+    let mesh_tris: [&[u32]; 2] = [&[0, 1, 2], &[3, 4, 5]];
+    let (vtx, idx, len, mins, maxs) =
+        push_composite(&mut vertices, &mut indices, &mesh_points, &mesh_tris);
+    let mesh = Shape::trimesh(vtx, idx, len, 2, 6, mins, maxs);
+
+    let poses = [Pose::IDENTITY, Pose::from_translation(Vector::Y * 0.01)];
+    let (num_pfm_pairs, contacts) = narrow_phase([ground, mesh], poses, &vertices, &indices);
+
+    // Only the lower triangle is paired, with both ground triangles it straddles.
+    assert_eq!(num_pfm_pairs, 2);
+    assert_eq!(contacts.len(), 2);
+    for contact in &contacts {
+        let contact = &contact.contact;
+        assert!(contact.len > 0);
+        for k in 0..contact.len as usize {
+            let dist = contact.points_a.at(k).dist;
+            assert!((dist - 0.01).abs() < 1.0e-3, "unexpected distance {dist}");
+        }
+        assert!((contact.normal_a - Vector::Y).length() < 1.0e-3);
+    }
+}
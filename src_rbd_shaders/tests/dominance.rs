@@ -0,0 +1,87 @@
+//! Tests for the dominance groups of the contact constraints.
+
+use crate::dynamics::{
+    RbdSimParams, TwoBodyConstraint, TwoBodyConstraintBuilder, Velocity, WorldMassProperties,
+};
+use crate::queries::{ContactManifold, IndexedManifold};
+use crate::utils::Slice;
+use crate::{Pose, Vector};
+use glamx::UVec2;
+use khal_std::index::MaybeIndexUnchecked;
+
+/// Builds the constraint of a body `1` resting on top of body `0`, touching it at
+/// the origin. Returns the inverse masses of both bodies seen by the constraint and
+/// the effective mass along the contact normal.
+fn resting_contact(mprops: [WorldMassProperties; 2]) -> (Vector, Vector, f32) {
+    let manifold = IndexedManifold {
+        contact: ContactManifold::single_point(Vector::ZERO, 0.0, Vector::Y),
+        colliders: UVec2::new(0, 1),
+        bodies: UVec2::new(0, 1),
+        friction: 0.5,
+        ..Default::default()
+    };
+    let collider_poses = [Pose::IDENTITY, Pose::from_translation(Vector::Y * 0.5)];
+    let solver_poses = [
+        Pose::from_translation(-Vector::Y * 0.5),
+        Pose::from_translation(Vector::Y * 0.5),
+    ];
+    let vels = [Velocity::default(); 2];
+    let mut constraint = TwoBodyConstraint::default();
+    let mut builder = TwoBodyConstraintBuilder::default();
+
+    manifold.contact_to_constraint(
+        &Slice(&mprops, 0),
+        &Slice(&collider_poses, 0),
+        &Slice(&solver_poses, 0),
+        &Slice(&vels, 0),
+        &RbdSimParams::default(),
+        &mut constraint,
+        &mut builder,
+    );
+
+    assert_eq!(constraint.len, 1);
+    let r = constraint.elements.at(0).normal_part.r;
+    (constraint.im_a, constraint.im_b, r)
+}
+
+fn dynamic_body(dominance_group: i32) -> WorldMassProperties {
+    WorldMassProperties {
+        dominance_group,
+        ..Default::default()
+    }
+}
+
+fn fixed_body() -> WorldMassProperties {
+    let mut mprops = WorldMassProperties::default();
+    mprops.make_infinite();
+    mprops
+}
+
+#[test]
+fn test_dominant_body_resting_on_fixed_ground() {
+    // A fixed ground keeps the default dominance group of zero. A dominant
+    // dynamic body on top of it must still be pushed back by the contact.
+    let (im_ground, im_body, r) = resting_contact([fixed_body(), dynamic_body(5)]);
+    assert_eq!(im_ground, Vector::ZERO);
+    assert_eq!(im_body, Vector::ONE);
+    assert!(r > 0.0, "the contact has no effective mass");
+
+    // Same with the bodies swapped in the manifold, and with negative groups.
+    let (im_body, im_ground, r) = resting_contact([dynamic_body(-5), fixed_body()]);
+    assert_eq!(im_ground, Vector::ZERO);
+    assert_eq!(im_body, Vector::ONE);
+    assert!(r > 0.0, "the contact has no effective mass");
+}
+
+#[test]
+fn test_dominant_body_is_not_pushed_by_dynamic_bodies() {
+    let (im_dominated, im_dominant, r) = resting_contact([dynamic_body(0), dynamic_body(3)]);
+    assert_eq!(im_dominated, Vector::ONE);
+    assert_eq!(im_dominant, Vector::ZERO);
+    assert!(r > 0.0);
+
+    // Equal groups: both bodies react to the contact.
+    let (im1, im2, _) = resting_contact([dynamic_body(3), dynamic_body(3)]);
+    assert_eq!(im1, Vector::ONE);
+    assert_eq!(im2, Vector::ONE);
+}
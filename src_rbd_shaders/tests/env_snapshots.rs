@@ -0,0 +1,377 @@
+//! Tests for the per-environment snapshots of the rigid-body and multibody states.
+
+use crate::dynamics::{
+    BodyActivation, BodySnapshot, ENV_SNAPSHOT_CAPTURE, ENV_SNAPSHOT_DROP_WARMSTART,
+    ENV_SNAPSHOT_RESTORE, Force, ImpulseJoint, MB_CONTACT_KIND_INACTIVE, MB_CONTACT_KIND_NORMAL,
+    MultibodyContactConstraint, MultibodyInfo, TwoBodyConstraint, TwoBodyConstraintBuilder,
+    Velocity, WS_QUADS, gpu_copy_body_snapshots, gpu_copy_contact_snapshots,
+    gpu_copy_joint_snapshots, gpu_mb_copy_contact_snapshots, gpu_mb_copy_env_snapshots,
+};
+use crate::utils::BatchIndices;
+use crate::{AngVector, Pose, Vector};
+use glamx::{UVec3, Vec4};
+
+const NUM_BATCHES: u32 = 2;
+const NUM_BODIES: usize = 2;
+const LEN: usize = NUM_BATCHES as usize * NUM_BODIES;
+
+/// The state of every body of every batch.
+struct State {
+    poses: [Pose; LEN],
+    vels: [Velocity; LEN],
+    kinematic_targets: [Pose; LEN],
+    forces: [Force; LEN],
+    activations: [BodyActivation; LEN],
+    old_body_constraint_counts: [u32; LEN],
+}
+
+impl State {
+    /// A state where every body is at `x` along the X axis, moves at `x` along
+    /// the Y axis, and has `x` old constraints.
+    fn new(x: f32) -> Self {
+        let velocity = Velocity {
+            linear: Vector::Y * x,
+            ..Default::default()
+        };
+        Self {
+            poses: [Pose::from_translation(Vector::X * x); LEN],
+            vels: [velocity; LEN],
+            kinematic_targets: [Pose::IDENTITY; LEN],
+            forces: [Force::new(Vector::X * x, AngVector::default()); LEN],
+            activations: [BodyActivation {
+                time_since_can_sleep: x,
+                sleeping: 1,
+            }; LEN],
+            old_body_constraint_counts: [x as u32; LEN],
+        }
+    }
+
+    fn copy_snapshots(&mut self, ops: [u32; NUM_BATCHES as usize], snapshots: &mut [BodySnapshot]) {
+        let mut batch_ids = BatchIndices {
+            num_batches: NUM_BATCHES,
+            colliders_batch_capacity: NUM_BODIES as u32,
+            ..Default::default()
+        };
+        batch_ids.set_uniform_ranges(NUM_BODIES as u32, NUM_BODIES as u32, 0);
+        for batch_id in 0..NUM_BATCHES {
+            gpu_copy_body_snapshots(
+                UVec3::new(0, batch_id, 0),
+                UVec3::ONE,
+                &ops,
+                snapshots,
+                &mut self.poses,
+                &mut self.vels,
+                &mut self.kinematic_targets,
+                &mut self.forces,
+                &mut self.activations,
+                &mut self.old_body_constraint_counts,
+                &batch_ids,
+            );
+        }
+    }
+
+    /// Asserts that the body `i` is in the state built by `State::new(x)`.
+    fn assert_body_eq(&self, i: usize, x: f32) {
+        assert_eq!(self.poses[i].translation, Vector::X * x, "body {i}");
+        assert_eq!(self.vels[i].linear, Vector::Y * x, "body {i}");
+        assert_eq!(self.forces[i].linear, Vector::X * x, "body {i}");
+        assert!(self.activations[i].is_sleeping(), "body {i}");
+        assert_eq!(self.activations[i].time_since_can_sleep, x, "body {i}");
+        assert_eq!(self.old_body_constraint_counts[i], x as u32, "body {i}");
+    }
+}
+
+#[test]
+fn test_body_snapshot_round_trip() {
+    let mut snapshots = [BodySnapshot::default(); LEN];
+    let mut state = State::new(1.0);
+
+    // Only the first batch is captured.
+    state.copy_snapshots([ENV_SNAPSHOT_CAPTURE, 0], &mut snapshots);
+    assert_eq!(snapshots[0].pose.translation, Vector::X);
+    assert_eq!(snapshots[1].velocity.linear, Vector::Y);
+    assert_eq!(snapshots[2].pose.translation, Vector::ZERO);
+
+    // The simulation moves on, then the first batch is reset.
+    let mut new_state = State::new(2.0);
+    new_state.copy_snapshots([ENV_SNAPSHOT_RESTORE, 0], &mut snapshots);
+    for i in 0..NUM_BODIES {
+        // Restored bodies get their sleep state and old constraints back, and
+        // hold their new pose.
+        new_state.assert_body_eq(i, 1.0);
+        assert_eq!(new_state.kinematic_targets[i].translation, Vector::X);
+    }
+    // The other batch is left untouched.
+    for i in NUM_BODIES..LEN {
+        new_state.assert_body_eq(i, 2.0);
+        assert_eq!(new_state.kinematic_targets[i].translation, Vector::ZERO);
+    }
+
+    // Without its warmstart, the restored batch has no old constraint.
+    let mut new_state = State::new(2.0);
+    new_state.copy_snapshots(
+        [ENV_SNAPSHOT_RESTORE | ENV_SNAPSHOT_DROP_WARMSTART, 0],
+        &mut snapshots,
+    );
+    for i in 0..NUM_BODIES {
+        assert_eq!(new_state.poses[i].translation, Vector::X, "body {i}");
+        assert_eq!(new_state.old_body_constraint_counts[i], 0, "body {i}");
+    }
+
+    // Capturing and restoring in the same dispatch keeps the current state.
+    let mut state = State::new(3.0);
+    state.copy_snapshots(
+        [0, ENV_SNAPSHOT_CAPTURE | ENV_SNAPSHOT_RESTORE],
+        &mut snapshots,
+    );
+    for i in NUM_BODIES..LEN {
+        state.assert_body_eq(i, 3.0);
+        assert_eq!(snapshots[i].pose.translation, Vector::X * 3.0);
+    }
+    // The first batch keeps its earlier snapshot.
+    assert_eq!(snapshots[0].pose.translation, Vector::X);
+}
+
+#[test]
+fn test_multibody_snapshot_round_trip() {
+    let num_dofs = 2;
+    let batch_ids = BatchIndices {
+        num_batches: NUM_BATCHES,
+        links_batch_capacity: 1,
+        dof_batch_capacity: num_dofs,
+        mb_contact_constraints_batch_capacity: 1,
+        ..Default::default()
+    };
+    let num_quads = (WS_QUADS * NUM_BATCHES) as usize;
+    let num_dof_values = (num_dofs * NUM_BATCHES) as usize;
+
+    let mut ws_snapshots = vec![Vec4::ZERO; num_quads];
+    let mut dof_snapshots = vec![0.0; num_dof_values * 2];
+    let mut links_workspace = vec![Vec4::ONE; num_quads];
+    let mut dof_values = vec![1.0; num_dof_values];
+    let mut dof_state = vec![10.0; num_dof_values];
+
+    let mut copy_snapshots = |ops: [u32; NUM_BATCHES as usize],
+                              links_workspace: &mut [Vec4],
+                              dof_values: &mut [f32],
+                              dof_state: &mut [f32]| {
+        for batch_id in 0..NUM_BATCHES {
+            gpu_mb_copy_env_snapshots(
+                UVec3::new(0, batch_id, 0),
+                UVec3::ONE,
+                &ops,
+                &mut ws_snapshots,
+                &mut dof_snapshots,
+                links_workspace,
+                dof_values,
+                dof_state,
+                &batch_ids,
+            );
+        }
+    };
+
+    // Capture both batches, then move on.
+    copy_snapshots(
+        [ENV_SNAPSHOT_CAPTURE; 2],
+        &mut links_workspace,
+        &mut dof_values,
+        &mut dof_state,
+    );
+    links_workspace.fill(Vec4::splat(2.0));
+    dof_values.fill(2.0);
+    dof_state.fill(20.0);
+
+    // Only the second batch is reset. The buffers interleave the batches.
+    copy_snapshots(
+        [0, ENV_SNAPSHOT_RESTORE],
+        &mut links_workspace,
+        &mut dof_values,
+        &mut dof_state,
+    );
+    for k in 0..num_quads {
+        let expected = if k % 2 == 1 { 1.0 } else { 2.0 };
+        assert_eq!(links_workspace[k], Vec4::splat(expected), "quad {k}");
+    }
+    for k in 0..num_dof_values {
+        let (value, vel) = if k % 2 == 1 { (1.0, 10.0) } else { (2.0, 20.0) };
+        assert_eq!((dof_values[k], dof_state[k]), (value, vel), "dof {k}");
+    }
+}
+
+#[test]
+fn test_contact_snapshot_round_trip() {
+    let capacity = 2;
+    let batch_ids = BatchIndices {
+        num_batches: NUM_BATCHES,
+        contacts_batch_capacity: capacity,
+        ..Default::default()
+    };
+    let len = (capacity * NUM_BATCHES) as usize;
+    let constraint = |impulse: f32| {
+        let mut constraint = TwoBodyConstraint::default();
+        constraint.elements[0].normal_part.impulse = impulse;
+        constraint
+    };
+
+    let mut constraint_snapshots = vec![TwoBodyConstraint::default(); len];
+    let mut builder_snapshots = vec![TwoBodyConstraintBuilder::default(); len];
+    let mut id_snapshots = vec![0; 2 * len];
+    let mut old_constraints = vec![constraint(1.0); len];
+    let mut old_builders = vec![TwoBodyConstraintBuilder::default(); len];
+    let mut old_ids = vec![1; 2 * len];
+
+    let mut copy_snapshots = |ops: [u32; NUM_BATCHES as usize],
+                              old_constraints: &mut [TwoBodyConstraint],
+                              old_ids: &mut [u32]| {
+        for batch_id in 0..NUM_BATCHES {
+            gpu_copy_contact_snapshots(
+                UVec3::new(0, batch_id, 0),
+                UVec3::ONE,
+                &ops,
+                &mut constraint_snapshots,
+                &mut builder_snapshots,
+                &mut id_snapshots,
+                old_constraints,
+                &mut old_builders,
+                old_ids,
+                &batch_ids,
+            );
+        }
+    };
+
+    // Capture both batches, then solve other contacts.
+    copy_snapshots(
+        [ENV_SNAPSHOT_CAPTURE; 2],
+        &mut old_constraints,
+        &mut old_ids,
+    );
+    old_constraints.fill(constraint(2.0));
+    old_ids.fill(2);
+
+    // The first batch is restored, the second one is restored without its warmstart.
+    copy_snapshots(
+        [
+            ENV_SNAPSHOT_RESTORE,
+            ENV_SNAPSHOT_RESTORE | ENV_SNAPSHOT_DROP_WARMSTART,
+        ],
+        &mut old_constraints,
+        &mut old_ids,
+    );
+    for (i, constraint) in old_constraints.iter().enumerate() {
+        let expected = if i < capacity as usize { 1.0 } else { 2.0 };
+        assert_eq!(
+            constraint.elements[0].normal_part.impulse, expected,
+            "contact {i}"
+        );
+    }
+    for (i, id) in old_ids.iter().enumerate() {
+        let expected = if i < 2 * capacity as usize { 1 } else { 2 };
+        assert_eq!(*id, expected, "id {i}");
+    }
+}
+
+#[test]
+fn test_joint_snapshot_round_trip() {
+    let mut batch_ids = BatchIndices {
+        num_batches: NUM_BATCHES,
+        ..Default::default()
+    };
+    // The first batch has one joint, and the second two.
+    batch_ids.set_impulse_joints_range(0, 0, 1);
+    batch_ids.set_impulse_joints_range(1, 1, 2);
+
+    let enabled = |enabled: u32| ImpulseJoint {
+        enabled,
+        ..bytemuck::Zeroable::zeroed()
+    };
+    let mut enabled_snapshots = [0; 3];
+    let mut joints = [enabled(1); 3];
+    let mut copy_snapshots = |ops: [u32; NUM_BATCHES as usize], joints: &mut [ImpulseJoint]| {
+        for batch_id in 0..NUM_BATCHES {
+            gpu_copy_joint_snapshots(
+                UVec3::new(0, batch_id, 0),
+                UVec3::ONE,
+                &ops,
+                &mut enabled_snapshots,
+                joints,
+                &batch_ids,
+            );
+        }
+    };
+
+    // Capture both batches, then break every joint.
+    copy_snapshots([ENV_SNAPSHOT_CAPTURE; 2], &mut joints);
+    joints = [enabled(0); 3];
+
+    // Only the joints of the second batch are repaired.
+    copy_snapshots([0, ENV_SNAPSHOT_RESTORE], &mut joints);
+    assert_eq!(joints.map(|joint| joint.enabled), [0, 1, 1]);
+}
+
+#[test]
+fn test_multibody_contact_snapshot_round_trip() {
+    let batch_ids = BatchIndices {
+        num_batches: NUM_BATCHES,
+        multibodies_batch_capacity: 1,
+        multibodies_len: 1,
+        mb_contact_constraints_batch_capacity: 2,
+        ..Default::default()
+    };
+    let with_kind = |kind| MultibodyContactConstraint {
+        kind,
+        ..Default::default()
+    };
+    let with_count = |contact_constraint_count| MultibodyInfo {
+        contact_constraint_count,
+        ..Default::default()
+    };
+
+    let mut contact_snapshots = [MultibodyContactConstraint::default(); 4];
+    let mut count_snapshots = [0; 2];
+    let mut copy_snapshots = |ops: [u32; NUM_BATCHES as usize],
+                              contact_constraints: &mut [MultibodyContactConstraint],
+                              multibody_info: &mut [MultibodyInfo]| {
+        for batch_id in 0..NUM_BATCHES {
+            gpu_mb_copy_contact_snapshots(
+                UVec3::new(0, batch_id, 0),
+                UVec3::ONE,
+                &ops,
+                &mut contact_snapshots,
+                &mut count_snapshots,
+                contact_constraints,
+                multibody_info,
+                &batch_ids,
+            );
+        }
+    };
+
+    // Capture two active constraints in both batches, then lose them.
+    let mut contact_constraints = [with_kind(MB_CONTACT_KIND_NORMAL); 4];
+    let mut multibody_info = [with_count(2); 2];
+    copy_snapshots(
+        [ENV_SNAPSHOT_CAPTURE; 2],
+        &mut contact_constraints,
+        &mut multibody_info,
+    );
+    contact_constraints = [with_kind(MB_CONTACT_KIND_INACTIVE); 4];
+    multibody_info = [with_count(0); 2];
+
+    // Only the second batch gets its constraints, and their count, back.
+    copy_snapshots(
+        [0, ENV_SNAPSHOT_RESTORE],
+        &mut contact_constraints,
+        &mut multibody_info,
+    );
+    assert_eq!(
+        contact_constraints.map(|cons| cons.kind),
+        [
+            MB_CONTACT_KIND_INACTIVE,
+            MB_CONTACT_KIND_INACTIVE,
+            MB_CONTACT_KIND_NORMAL,
+            MB_CONTACT_KIND_NORMAL,
+        ]
+    );
+    // The multibody infos interleave the batches.
+    assert_eq!(multibody_info[0].contact_constraint_count, 0);
+    assert_eq!(multibody_info[1].contact_constraint_count, 2);
+}
@@ -0,0 +1,189 @@
+//! Tests for the gathering of observations and the scattering of actions.
+
+use crate::dynamics::{
+    ACT_BODY_FORCE, ACT_JOINT_EFFORT, ACT_MOTOR_POSITION, BodyActivation, EnvTerm, EnvTermsShape,
+    Force, JOINT_EFFORT_MOTOR_VELOCITY, MultibodyLinkStatic, OBS_BODY_POSE, OBS_BODY_POSE_LEN,
+    OBS_BODY_VELOCITY, OBS_BODY_VELOCITY_LEN, OBS_CONTACT_FLAG, OBS_JOINT_VELOCITY, Velocity,
+    gpu_gather_observations, gpu_scatter_actions,
+};
+use crate::queries::IndexedManifold;
+use crate::utils::BatchIndices;
+use crate::{AngVector, DIM, Pose, Vector};
+use glamx::{UVec2, UVec3};
+
+const NUM_BATCHES: u32 = 2;
+const NUM_BODIES: u32 = 2;
+
+fn batch_ids() -> BatchIndices {
+    let mut batch_ids = BatchIndices {
+        num_batches: NUM_BATCHES,
+        colliders_batch_capacity: NUM_BODIES,
+        contacts_batch_capacity: 1,
+        dof_batch_capacity: 2,
+        links_batch_capacity: 1,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(NUM_BODIES, NUM_BODIES, 0);
+    batch_ids
+}
+
+fn term(kind: u32, id: u32, axis: u32, column: u32) -> EnvTerm {
+    EnvTerm {
+        kind,
+        id,
+        axis,
+        column,
+    }
+}
+
+#[test]
+fn test_gather_observations() {
+    let terms = [
+        term(OBS_BODY_POSE, 1, 0, 0),
+        term(OBS_BODY_VELOCITY, 0, 0, OBS_BODY_POSE_LEN),
+        term(
+            OBS_JOINT_VELOCITY,
+            1,
+            0,
+            OBS_BODY_POSE_LEN + OBS_BODY_VELOCITY_LEN,
+        ),
+        term(
+            OBS_CONTACT_FLAG,
+            0,
+            0,
+            OBS_BODY_POSE_LEN + OBS_BODY_VELOCITY_LEN + 1,
+        ),
+    ];
+    let row_len = OBS_BODY_POSE_LEN + OBS_BODY_VELOCITY_LEN + 2;
+    let shape = EnvTermsShape {
+        num_terms: terms.len() as u32,
+        row_len,
+        padding: [0; 2],
+    };
+
+    // The body `i` of the batch `b` is at `10 * b + i` along X, and moves at
+    // `b + 1` along Y.
+    let poses: Vec<_> = (0..NUM_BATCHES * NUM_BODIES)
+        .map(|k| {
+            let (b, i) = (k / NUM_BODIES, k % NUM_BODIES);
+            Pose::from_translation(Vector::X * (10 * b + i) as f32)
+        })
+        .collect();
+    let vels: Vec<_> = (0..NUM_BATCHES * NUM_BODIES)
+        .map(|k| Velocity {
+            linear: Vector::Y * (k / NUM_BODIES + 1) as f32,
+            ..Default::default()
+        })
+        .collect();
+    // The DOF velocities interleave the batches.
+    let dof_state = [0.0, 0.5, 1.0, 1.5];
+    // Only the first body of the second batch has contact points.
+    let mut contacts = [IndexedManifold::default(); NUM_BATCHES as usize];
+    contacts[0].bodies = UVec2::new(0, 1);
+    contacts[1].bodies = UVec2::new(1, 0);
+    contacts[1].contact.len = 1;
+
+    let mut observations = vec![f32::NAN; (NUM_BATCHES * row_len) as usize];
+    for batch_id in 0..NUM_BATCHES {
+        for term_id in 0..terms.len() as u32 + 1 {
+            gpu_gather_observations(
+                UVec3::new(term_id, batch_id, 0),
+                &terms,
+                &mut observations,
+                &poses,
+                &vels,
+                &[],
+                &dof_state,
+                &contacts,
+                &[1, 1],
+                &shape,
+                &batch_ids(),
+            );
+        }
+    }
+
+    let mut expected = vec![];
+    for b in 0..NUM_BATCHES {
+        let mut pose = vec![0.0; OBS_BODY_POSE_LEN as usize];
+        pose[0] = (10 * b + 1) as f32;
+        // The identity rotation: `re = 1` in 2D, `w = 1` in 3D.
+        *pose.last_mut().unwrap() = if DIM == 2 { 0.0 } else { 1.0 };
+        pose[DIM as usize] = if DIM == 2 { 1.0 } else { 0.0 };
+        expected.extend(pose);
+
+        let mut vel = vec![0.0; OBS_BODY_VELOCITY_LEN as usize];
+        vel[1] = (b + 1) as f32;
+        expected.extend(vel);
+
+        expected.push(dof_state[(2 + b) as usize]);
+        expected.push(b as f32);
+    }
+    assert_eq!(observations, expected);
+}
+
+#[test]
+fn test_scatter_actions() {
+    let angular_axis = if DIM == 2 { 2 } else { 5 };
+    let terms = [
+        term(ACT_BODY_FORCE, 1, 0, 0),
+        term(ACT_BODY_FORCE, 1, angular_axis, 1),
+        term(ACT_JOINT_EFFORT, 0, 0, 2),
+        term(ACT_MOTOR_POSITION, 0, 1, 3),
+        // The last term targeting an input wins.
+        term(ACT_BODY_FORCE, 1, 0, 4),
+    ];
+    let shape = EnvTermsShape {
+        num_terms: terms.len() as u32,
+        row_len: 5,
+        padding: [0; 2],
+    };
+    let actions = [
+        1.0, 2.0, -3.0, 4.0, 5.0, // First batch.
+        -1.0, -2.0, 3.0, -4.0, -5.0, // Second batch.
+    ];
+
+    let sleeping = BodyActivation {
+        time_since_can_sleep: 10.0,
+        sleeping: 1,
+    };
+    let mut links_static: [MultibodyLinkStatic; NUM_BATCHES as usize] =
+        bytemuck::Zeroable::zeroed();
+    let mut forces = [Force::default(); (NUM_BATCHES * NUM_BODIES) as usize];
+    let mut activations = [sleeping; (NUM_BATCHES * NUM_BODIES) as usize];
+    for batch_id in 0..NUM_BATCHES + 1 {
+        gpu_scatter_actions(
+            UVec3::new(batch_id, 0, 0),
+            &terms,
+            &actions,
+            &mut links_static,
+            &mut [],
+            &mut forces,
+            &mut activations,
+            &shape,
+            &batch_ids(),
+        );
+    }
+
+    for b in 0..NUM_BATCHES as usize {
+        let sign = if b == 0 { 1.0 } else { -1.0 };
+
+        // The body force is set and its body woken up. The other body is untouched.
+        #[cfg(feature = "dim2")]
+        let torque = 2.0 * sign;
+        #[cfg(feature = "dim3")]
+        let torque = Vector::Z * 2.0 * sign;
+        let force = forces[b * 2 + 1];
+        assert_eq!(force.linear, Vector::X * 5.0 * sign);
+        assert_eq!(force.angular, torque);
+        assert!(!activations[b * 2 + 1].is_sleeping());
+        assert_eq!(forces[b * 2].linear, Vector::ZERO);
+        assert_eq!(forces[b * 2].angular, AngVector::default());
+        assert!(activations[b * 2].is_sleeping());
+
+        // The links interleave the batches. Efforts saturate a velocity motor.
+        let motors = &links_static[b].data.motors;
+        assert_eq!(motors[0].max_force, 3.0);
+        assert_eq!(motors[0].target_vel, -sign * JOINT_EFFORT_MOTOR_VELOCITY);
+        assert_eq!(motors[1].target_pos, 4.0 * sign);
+    }
+}
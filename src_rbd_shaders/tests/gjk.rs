@@ -49,6 +49,46 @@ fn test_separated_cuboids() {
     );
 }
 
+#[test]
+fn test_separated_round_cuboids() {
+    // Same as `test_separated_cuboids`, but both cuboids have a 0.25 border radius.
+    let half_extents = Vector::ONE;
+
+    let shape1 = Shape::cuboid(half_extents).with_border_radius(0.25);
+    let shape2 = Shape::cuboid(half_extents).with_border_radius(0.25);
+
+    #[cfg(feature = "dim2")]
+    let translation = glamx::Vec2::new(4.0, 0.0);
+    #[cfg(feature = "dim3")]
+    let translation = glamx::Vec3::new(4.0, 0.0, 0.0);
+
+    let pose12 = Pose::from_translation(translation);
+    let vertices = vec![];
+
+    let init_dir = Vector::X;
+    let cso_point = cso_point_from_shapes(pose12, &shape1, &shape2, init_dir, &vertices);
+    let mut simplex = VoronoiSimplex::init(cso_point);
+
+    let result = closest_points(
+        pose12,
+        &shape1,
+        &shape2,
+        10.0,
+        true,
+        &mut simplex,
+        &vertices,
+    );
+
+    assert_eq!(result.status, CLOSEST_POINTS);
+    // The gap of 2.0 is reduced by both border radii.
+    let dist = (result.b - result.a).length();
+    assert!(
+        (dist - 1.5).abs() < 1.0e-3,
+        "Expected distance ~1.5, got {}",
+        dist
+    );
+}
+
 #[test]
 fn test_nearly_touching_cuboids() {
     // Two cuboids with a very small gap
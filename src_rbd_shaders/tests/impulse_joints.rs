@@ -0,0 +1,197 @@
+//! Tests for the impulse joints between rigid bodies.
+
+use crate::dynamics::{
+    Force, GenericJoint, ImpulseJoint, JOINT_KIND_ROPE, JOINT_KIND_SPRING, JointBreakEvent,
+    JointBreakThreshold, JointConstraint, JointConstraintBuilder, RbdSimParams, Velocity,
+    WorldMassProperties, gpu_break_joints,
+};
+use crate::utils::{BatchIndices, Slice, SliceMut};
+use crate::{AngVector, Pose, Vector};
+use glamx::UVec3;
+
+/// A joint of the given kind between a fixed body (0) and a dynamic one (1),
+/// anchored at their centers of mass.
+fn builder(kind: u32, joint: GenericJoint) -> JointConstraintBuilder {
+    JointConstraintBuilder {
+        body1: 0,
+        body2: 1,
+        kind,
+        enabled: 1,
+        joint: GenericJoint {
+            local_frame_a: Pose::IDENTITY,
+            local_frame_b: Pose::IDENTITY,
+            ..joint
+        },
+        ..bytemuck::Zeroable::zeroed()
+    }
+}
+
+/// Builds the joint constraint with the dynamic body at `x` along the X axis,
+/// then solves it once without bias.
+///
+/// Returns the number of constraint elements and the velocity of the dynamic body.
+fn solve(builder: &JointConstraintBuilder, x: f32, linvel: Vector) -> (u32, Vector) {
+    let params = RbdSimParams {
+        dt: 0.1,
+        ..Default::default()
+    };
+    let poses = [Pose::IDENTITY, Pose::from_translation(Vector::X * x)];
+    let mprops = [bytemuck::Zeroable::zeroed(), WorldMassProperties::default()];
+
+    let mut constraint = JointConstraint {
+        solver_vel_a: 0,
+        solver_vel_b: 1,
+        im_a: mprops[0].inv_mass,
+        im_b: mprops[1].inv_mass,
+        ..Default::default()
+    };
+    builder.update_constraint(
+        &mut constraint,
+        &Slice(&poses, 0),
+        &Slice(&mprops, 0),
+        &params,
+    );
+
+    let mut vels = [
+        Velocity::default(),
+        Velocity {
+            linear: linvel,
+            ..Default::default()
+        },
+    ];
+    constraint.solve_joint_constraint(&mut SliceMut(&mut vels, 0), false);
+    (constraint.len, vels[1].linear)
+}
+
+#[test]
+fn test_rope_only_pulls_when_taut() {
+    let mut joint: GenericJoint = bytemuck::Zeroable::zeroed();
+    joint.limit_axes = 1;
+    joint.limits[0].max = 2.0;
+    let rope = builder(JOINT_KIND_ROPE, joint);
+
+    // Stretched beyond its length, the rope stops the body moving away.
+    let (len, vel) = solve(&rope, 3.0, Vector::X);
+    assert_eq!(len, 1);
+    assert!(
+        vel.x < 1.0 - 1.0e-3 && vel.x > -1.0e-5,
+        "unexpected {vel:?}"
+    );
+    // The rope acts along the line joining the anchors only.
+    let (_, vel) = solve(&rope, 3.0, Vector::Y);
+    assert!((vel - Vector::Y).length() < 1.0e-5, "unexpected {vel:?}");
+
+    // A slack rope lets the body move freely, even away from the anchor as long
+    // as it doesn't get taut within the step.
+    let (_, vel) = solve(&rope, 1.0, Vector::X);
+    assert!((vel - Vector::X).length() < 1.0e-5, "unexpected {vel:?}");
+    let (_, vel) = solve(&rope, 1.0, -Vector::X);
+    assert!((vel + Vector::X).length() < 1.0e-5, "unexpected {vel:?}");
+}
+
+#[test]
+fn test_spring_pulls_toward_rest_length() {
+    let mut joint: GenericJoint = bytemuck::Zeroable::zeroed();
+    joint.motor_axes = 1;
+    joint.motors[0].target_pos = 1.0;
+    joint.motors[0].stiffness = 100.0;
+    joint.motors[0].damping = 1.0;
+    joint.motors[0].max_force = 1.0e6;
+    let spring = builder(JOINT_KIND_SPRING, joint);
+
+    // Stretched: pulled back toward the anchor.
+    let (len, vel) = solve(&spring, 2.0, Vector::ZERO);
+    assert_eq!(len, 1);
+    assert!(vel.x < -1.0e-3, "unexpected {vel:?}");
+    // Compressed: pushed away from the anchor.
+    let (_, vel) = solve(&spring, 0.5, Vector::ZERO);
+    assert!(vel.x > 1.0e-3, "unexpected {vel:?}");
+    // At rest length, at rest: nothing happens.
+    let (_, vel) = solve(&spring, 1.0, Vector::ZERO);
+    assert!(vel.length() < 1.0e-5, "unexpected {vel:?}");
+}
+
+#[test]
+fn test_joints_break_above_threshold() {
+    let num_batches = 2;
+    let capacity = 3;
+    let mut batch_ids = BatchIndices {
+        num_batches,
+        impulse_joints_batch_capacity: capacity,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(0, 0, capacity);
+
+    let enabled = ImpulseJoint {
+        enabled: 1,
+        ..bytemuck::Zeroable::zeroed()
+    };
+    let mut joints = [enabled; 6];
+    // Batch 1: the first joint is already broken.
+    joints[3].enabled = 0;
+
+    #[cfg(feature = "dim2")]
+    let torque = |t: f32| t;
+    #[cfg(feature = "dim3")]
+    let torque = |t: f32| Vector::Z * t;
+    let wrenches = [
+        Force::new(Vector::X * 5.0, AngVector::default()),
+        Force::new(Vector::ZERO, torque(3.0)),
+        Force::new(Vector::X * 1.0e6, torque(1.0e6)),
+        Force::new(Vector::X * 5.0, AngVector::default()),
+        Force::new(Vector::Y * 3.0, torque(1.0)),
+        Force::default(),
+    ];
+    let breakable = JointBreakThreshold {
+        max_force: 4.0,
+        max_torque: 2.0,
+    };
+    let mut thresholds = [breakable; 6];
+    thresholds[2] = JointBreakThreshold::default();
+
+    let mut events = [JointBreakEvent::default(); 6];
+    let mut events_len = [0; 2];
+    for _ in 0..2 {
+        for batch_id in 0..num_batches {
+            gpu_break_joints(
+                UVec3::new(0, batch_id, 0),
+                UVec3::ONE,
+                &mut joints,
+                &wrenches,
+                &thresholds,
+                &mut events,
+                &mut events_len,
+                &batch_ids,
+            );
+        }
+    }
+
+    // Joints break once, above either threshold. Unbreakable joints never break.
+    let enabled: Vec<_> = joints.iter().map(|joint| joint.enabled).collect();
+    assert_eq!(enabled, [0, 0, 1, 0, 1, 1]);
+    assert_eq!(events_len, [2, 0]);
+    assert_eq!(
+        events[..2],
+        [
+            JointBreakEvent {
+                joint_id: 0,
+                force: 5.0,
+                torque: 0.0,
+            },
+            JointBreakEvent {
+                joint_id: 1,
+                force: 0.0,
+                torque: 3.0,
+            },
+        ]
+    );
+
+    // Broken joints don't generate any constraint.
+    let builder = JointConstraintBuilder {
+        enabled: 0,
+        ..builder(JOINT_KIND_ROPE, bytemuck::Zeroable::zeroed())
+    };
+    let (len, vel) = solve(&builder, 3.0, Vector::X);
+    assert_eq!(len, 0);
+    assert_eq!(vel, Vector::X);
+}
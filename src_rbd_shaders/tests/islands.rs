@@ -0,0 +1,196 @@
+//! Tests for the island detection and the sleeping of bodies at rest.
+
+use crate::Vector;
+use crate::dynamics::{
+    BODY_TYPE_DYNAMIC, BODY_TYPE_FIXED, BodyActivation, BodyParams, RbdSimParams, Velocity,
+    gpu_islands_apply, gpu_islands_awake, gpu_islands_compress, gpu_islands_init,
+    gpu_islands_link_contacts, gpu_islands_link_joints, num_island_link_rounds,
+};
+use crate::queries::IndexedManifold;
+use crate::utils::BatchIndices;
+use glamx::{UVec2, UVec3};
+
+/// Runs the island passes over one step with the given contacts.
+fn update_islands(
+    contacts: &[UVec2],
+    body_types: &[u32],
+    vels: &mut [Velocity],
+    activations: &mut [BodyActivation],
+) {
+    let num_bodies = body_types.len() as u32;
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: num_bodies,
+        contacts_batch_capacity: contacts.len() as u32,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(num_bodies, num_bodies, 0);
+    let params = RbdSimParams {
+        dt: 0.1,
+        ..Default::default()
+    };
+    let body_params = vec![BodyParams::default(); body_types.len()];
+    let contacts: Vec<_> = contacts
+        .iter()
+        .map(|bodies| {
+            let mut manifold = IndexedManifold {
+                colliders: *bodies,
+                bodies: *bodies,
+                ..Default::default()
+            };
+            manifold.contact.len = 1;
+            manifold
+        })
+        .collect();
+
+    let mut labels = vec![0; body_types.len()];
+    let mut island_awake = vec![0; body_types.len()];
+    gpu_islands_init(
+        UVec3::ZERO,
+        UVec3::ONE,
+        vels,
+        body_types,
+        &body_params,
+        activations,
+        &mut labels,
+        &mut island_awake,
+        &[params],
+        &batch_ids,
+    );
+    for _ in 0..num_island_link_rounds(num_bodies) {
+        gpu_islands_link_contacts(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &contacts,
+            &[contacts.len() as u32],
+            body_types,
+            vels,
+            activations,
+            &mut labels,
+            &batch_ids,
+        );
+        gpu_islands_link_joints(
+            UVec3::ZERO,
+            UVec3::ONE,
+            &[],
+            body_types,
+            &mut labels,
+            &batch_ids,
+        );
+        gpu_islands_compress(UVec3::ZERO, UVec3::ONE, &mut labels, &batch_ids);
+    }
+    gpu_islands_awake(
+        UVec3::ZERO,
+        UVec3::ONE,
+        body_types,
+        &body_params,
+        activations,
+        &labels,
+        &mut island_awake,
+        &batch_ids,
+    );
+    gpu_islands_apply(
+        UVec3::ZERO,
+        UVec3::ONE,
+        body_types,
+        &labels,
+        &island_awake,
+        activations,
+        vels,
+        &batch_ids,
+    );
+}
+
+#[test]
+fn test_islands_sleep_together() {
+    // A fixed ground, a body resting on it, and a slow body touching a fast one.
+    // The resting and the slow bodies have been below the sleep thresholds for
+    // long enough. The ground doesn't link the islands of the bodies touching it.
+    let body_types = [
+        BODY_TYPE_FIXED,
+        BODY_TYPE_DYNAMIC,
+        BODY_TYPE_DYNAMIC,
+        BODY_TYPE_DYNAMIC,
+    ];
+    let slow = Velocity {
+        linear: Vector::X * 0.01,
+        ..Default::default()
+    };
+    let fast = Velocity {
+        linear: Vector::X * 10.0,
+        ..Default::default()
+    };
+    let mut vels = [Velocity::default(), slow, slow, fast];
+    let rested = BodyActivation {
+        time_since_can_sleep: 10.0,
+        sleeping: 0,
+    };
+    let mut activations = [
+        BodyActivation::default(),
+        rested,
+        rested,
+        BodyActivation::default(),
+    ];
+    let contacts = [UVec2::new(0, 1), UVec2::new(0, 2), UVec2::new(2, 3)];
+
+    // The fast body keeps the slow one it touches awake.
+    update_islands(&contacts, &body_types, &mut vels, &mut activations);
+    assert!(activations[1].is_sleeping());
+    assert_eq!(vels[1].linear, Vector::ZERO);
+    assert!(!activations[2].is_sleeping());
+    assert_eq!(vels[2].linear, slow.linear);
+    assert!(!activations[3].is_sleeping());
+    assert_eq!(activations[3].time_since_can_sleep, 0.0);
+    // Fixed bodies never sleep.
+    assert!(!activations[0].is_sleeping());
+
+    // Once the fast body has slowed down for long enough, the whole island sleeps.
+    vels[3] = slow;
+    activations[3].time_since_can_sleep = 10.0;
+    update_islands(&contacts, &body_types, &mut vels, &mut activations);
+    assert!(activations[2].is_sleeping());
+    assert!(activations[3].is_sleeping());
+    assert_eq!(vels[2].linear, Vector::ZERO);
+    assert_eq!(vels[3].linear, Vector::ZERO);
+
+    // A body that never had time to rest wakes its whole island up.
+    vels[2] = fast;
+    activations[2] = BodyActivation::default();
+    update_islands(&contacts, &body_types, &mut vels, &mut activations);
+    assert!(!activations[2].is_sleeping());
+    assert!(!activations[3].is_sleeping());
+    assert_eq!(activations[3].time_since_can_sleep, 0.0);
+    assert!(activations[1].is_sleeping());
+}
+
+#[test]
+fn test_long_chain_islands_merge() {
+    // A chain of bodies at rest, with contacts listed in a scrambled order, and
+    // a fast body at the end with the smallest label.
+    const LEN: u32 = 1000;
+    let body_types = vec![BODY_TYPE_DYNAMIC; LEN as usize];
+    let contacts: Vec<_> = (0..LEN - 1)
+        .map(|i| {
+            let first = (i * 7919) % (LEN - 1);
+            UVec2::new(first, first + 1)
+        })
+        .collect();
+    let rested = BodyActivation {
+        time_since_can_sleep: 10.0,
+        sleeping: 0,
+    };
+    let mut vels = vec![Velocity::default(); LEN as usize];
+    vels[0].linear = Vector::X * 10.0;
+    let mut activations = vec![rested; LEN as usize];
+    activations[0] = BodyActivation::default();
+
+    // The fast body keeps the whole chain awake, up to its other end.
+    update_islands(&contacts, &body_types, &mut vels, &mut activations);
+    assert!(activations.iter().all(|a| !a.is_sleeping()));
+
+    // Once it rested too, the whole chain sleeps.
+    vels[0] = Velocity::default();
+    activations[0] = rested;
+    update_islands(&contacts, &body_types, &mut vels, &mut activations);
+    assert!(activations.iter().all(|a| a.is_sleeping()));
+}
@@ -0,0 +1,109 @@
+//! Tests for the reaction wrenches of the multibody-touching impulse joints.
+
+use crate::dynamics::{
+    Force, MAX_AXIS_CONSTRAINTS, MbImpulseJointBuilder, MbImpulseJointConstraint, SIDE_KIND_FIXED,
+    SIDE_KIND_MB, gpu_mb_compute_impulse_joint_wrenches,
+};
+use crate::utils::BatchIndices;
+use crate::{AngVector, DIM, Vector};
+use glamx::UVec3;
+
+fn builder(joint_id: u32, constraint_id: u32) -> MbImpulseJointBuilder {
+    MbImpulseJointBuilder {
+        side_a_kind: SIDE_KIND_FIXED,
+        side_b_kind: SIDE_KIND_MB,
+        joint_id,
+        constraint_id,
+        ..bytemuck::Zeroable::zeroed()
+    }
+}
+
+fn padding() -> MbImpulseJointBuilder {
+    MbImpulseJointBuilder {
+        side_a_kind: SIDE_KIND_FIXED,
+        side_b_kind: SIDE_KIND_FIXED,
+        ..bytemuck::Zeroable::zeroed()
+    }
+}
+
+fn active(axis: u32, impulse: f32) -> MbImpulseJointConstraint {
+    MbImpulseJointConstraint {
+        kind: 1,
+        writeback_axis: axis,
+        impulse,
+        ..Default::default()
+    }
+}
+
+fn assert_wrench_eq(a: Force, b: Force) {
+    assert!(
+        (a.linear - b.linear).length() < 1.0e-5,
+        "expected {:?}, got {:?}",
+        b.linear,
+        a.linear
+    );
+    #[cfg(feature = "dim2")]
+    assert!((a.angular - b.angular).abs() < 1.0e-5);
+    #[cfg(feature = "dim3")]
+    assert!((a.angular - b.angular).length() < 1.0e-5);
+}
+
+#[test]
+fn test_mb_impulse_joint_wrenches_follow_insertion_order() {
+    let num_batches = 2;
+    let joints_cap = 2;
+    let cons_cap = joints_cap * MAX_AXIS_CONSTRAINTS;
+    let batch_ids = BatchIndices {
+        num_batches,
+        mb_imp_joints_batch_capacity: joints_cap,
+        mb_imp_joint_constraints_batch_capacity: cons_cap,
+        ..Default::default()
+    };
+
+    // Batch 0: the builders are color-sorted, so joint 1 comes first, followed
+    // by joint 0. Batch 1: a single joint, then a padding builder.
+    let builders = [
+        builder(1, MAX_AXIS_CONSTRAINTS),
+        builder(0, 0),
+        builder(0, 0),
+        padding(),
+    ];
+    let mut constraints = vec![MbImpulseJointConstraint::default(); (cons_cap * 2) as usize];
+    let batch1 = cons_cap as usize;
+    let joint1 = MAX_AXIS_CONSTRAINTS as usize;
+    constraints[0] = active(0, 0.1);
+    constraints[joint1] = active(1, 0.2);
+    constraints[joint1 + 3] = active(DIM, -0.3);
+    constraints[batch1] = active(0, 0.4);
+
+    let sentinel = Force::new(Vector::splat(7.0), AngVector::default());
+    let mut wrenches = vec![sentinel; (joints_cap * num_batches) as usize];
+    let dt = 0.1;
+    for batch_id in 0..num_batches {
+        for i in 0..joints_cap {
+            gpu_mb_compute_impulse_joint_wrenches(
+                UVec3::new(i, batch_id, 0),
+                UVec3::ONE,
+                &builders,
+                &constraints,
+                &mut wrenches,
+                &dt,
+                &batch_ids,
+            );
+        }
+    }
+
+    // The second side receives `-impulse`, and impulses become forces over `dt`.
+    #[cfg(feature = "dim2")]
+    let torque = 3.0;
+    #[cfg(feature = "dim3")]
+    let torque = Vector::X * 3.0;
+    assert_wrench_eq(wrenches[0], Force::new(-Vector::X, AngVector::default()));
+    assert_wrench_eq(wrenches[1], Force::new(-Vector::Y * 2.0, torque));
+    assert_wrench_eq(
+        wrenches[2],
+        Force::new(-Vector::X * 4.0, AngVector::default()),
+    );
+    // The padding builder doesn't write anything.
+    assert_wrench_eq(wrenches[3], sentinel);
+}
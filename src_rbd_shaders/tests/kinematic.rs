@@ -0,0 +1,229 @@
+//! Tests for the kinematic rigid-bodies kernels.
+
+use crate::dynamics::{
+    BODY_TYPE_DYNAMIC, BODY_TYPE_KINEMATIC_POSITION, BODY_TYPE_KINEMATIC_VELOCITY, RbdSimParams,
+    TwoBodyConstraint, TwoBodyConstraintBuilder, Velocity, WorldMassProperties,
+    gpu_kinematic_snap_poses, gpu_kinematic_velocities,
+};
+use crate::queries::{ContactManifold, IndexedManifold};
+use crate::utils::{BatchIndices, Slice};
+use crate::{AngVector, Pose, Rotation, Vector};
+use glamx::{UVec2, UVec3};
+
+fn batch_ids(num_bodies: u32) -> BatchIndices {
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: num_bodies,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(num_bodies, num_bodies, 0);
+    batch_ids
+}
+
+/// Simulation parameters with a step of `step_dt`, split into two substeps.
+fn params(step_dt: f32) -> RbdSimParams {
+    RbdSimParams {
+        dt: step_dt / 2.0,
+        num_solver_iterations: 2,
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "dim2")]
+fn rotation(angle: f32) -> Rotation {
+    crate::rotation_from_angle(angle)
+}
+
+#[cfg(feature = "dim3")]
+fn rotation(angle: f32) -> Rotation {
+    crate::rotation_from_scaled_axis(Vector::Z * angle)
+}
+
+#[cfg(feature = "dim2")]
+fn angular(angle: f32) -> AngVector {
+    angle
+}
+
+#[cfg(feature = "dim3")]
+fn angular(angle: f32) -> AngVector {
+    Vector::Z * angle
+}
+
+/// Runs `gpu_kinematic_velocities` on a single batch.
+fn kinematic_velocities(
+    body_types: &[u32],
+    targets: &[Pose],
+    poses: &[Pose],
+    vels: &mut [Velocity],
+    step_dt: f32,
+) {
+    gpu_kinematic_velocities(
+        UVec3::ZERO,
+        UVec3::ONE,
+        body_types,
+        targets,
+        poses,
+        vels,
+        &[params(step_dt)],
+        &batch_ids(body_types.len() as u32),
+    );
+}
+
+#[test]
+fn test_kinematic_velocities_reach_the_target_in_one_step() {
+    let body_types = [
+        BODY_TYPE_KINEMATIC_POSITION,
+        BODY_TYPE_DYNAMIC,
+        BODY_TYPE_KINEMATIC_VELOCITY,
+    ];
+    let poses = [Pose::from_parts(Vector::X, rotation(0.1)); 3];
+    let target = Pose::from_parts(Vector::X * 1.5 - Vector::Y, rotation(0.4));
+    let targets = [target; 3];
+    let user_vel = Velocity::new(Vector::Y, angular(2.0));
+    let mut vels = [user_vel; 3];
+
+    kinematic_velocities(&body_types, &targets, &poses, &mut vels, 0.1);
+
+    // Moves by `(0.5, -1)` and turns by 0.3 radians in 0.1 seconds.
+    let expected = Velocity::new(Vector::X * 5.0 - Vector::Y * 10.0, angular(3.0));
+    assert!((vels[0].linear - expected.linear).length() < 1.0e-4);
+    #[cfg(feature = "dim2")]
+    assert!((vels[0].angular - expected.angular).abs() < 1.0e-4);
+    #[cfg(feature = "dim3")]
+    assert!((vels[0].angular - expected.angular).length() < 1.0e-4);
+
+    // Dynamic and velocity-based kinematic bodies keep their velocity.
+    for vel in &vels[1..] {
+        assert_eq!(vel.linear, user_vel.linear);
+        assert_eq!(vel.angular, user_vel.angular);
+    }
+}
+
+#[test]
+fn test_kinematic_velocities_with_zero_dt() {
+    let poses = [Pose::IDENTITY];
+    let targets = [Pose::from_translation(Vector::X)];
+    let mut vels = [Velocity::new(Vector::Y, angular(1.0))];
+
+    kinematic_velocities(
+        &[BODY_TYPE_KINEMATIC_POSITION],
+        &targets,
+        &poses,
+        &mut vels,
+        0.0,
+    );
+
+    assert_eq!(vels[0].linear, Vector::ZERO);
+    assert_eq!(vels[0].angular, AngVector::default());
+}
+
+fn assert_same_pose(a: &Pose, b: &Pose) {
+    assert_eq!(a.translation, b.translation);
+    assert_eq!(a.rotation * Vector::X, b.rotation * Vector::X);
+    assert_eq!(a.rotation * Vector::Y, b.rotation * Vector::Y);
+}
+
+#[test]
+fn test_kinematic_snap_poses() {
+    let body_types = [
+        BODY_TYPE_KINEMATIC_POSITION,
+        BODY_TYPE_DYNAMIC,
+        BODY_TYPE_KINEMATIC_VELOCITY,
+    ];
+    let target = Pose::from_parts(Vector::X * 2.0, rotation(0.5));
+    let targets = [target; 3];
+    let drifted = Pose::from_parts(Vector::X * 1.99, rotation(0.49));
+    let mut poses = [drifted; 3];
+
+    gpu_kinematic_snap_poses(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &body_types,
+        &targets,
+        &mut poses,
+        &batch_ids(3),
+    );
+
+    assert_same_pose(&poses[0], &target);
+    for pose in &poses[1..] {
+        assert_same_pose(pose, &drifted);
+    }
+}
+
+/// Solves the contact between a kinematic body `0` heading to `target` and a
+/// dynamic body `1` at rest on top of it. Returns the velocities of both bodies.
+fn push_resting_body(target: Pose) -> [Velocity; 2] {
+    let step_dt = 1.0 / 60.0;
+    let body_types = [BODY_TYPE_KINEMATIC_POSITION, BODY_TYPE_DYNAMIC];
+    let solver_poses = [
+        Pose::from_translation(-Vector::Y * 0.5),
+        Pose::from_translation(Vector::Y * 0.5),
+    ];
+    let mut vels = [Velocity::default(); 2];
+    kinematic_velocities(
+        &body_types,
+        &[target, Pose::IDENTITY],
+        &solver_poses,
+        &mut vels,
+        step_dt,
+    );
+
+    let mut kinematic = WorldMassProperties::default();
+    kinematic.make_infinite();
+    let mprops = [kinematic, WorldMassProperties::default()];
+    let manifold = IndexedManifold {
+        contact: ContactManifold::single_point(Vector::ZERO, 0.0, Vector::Y),
+        colliders: UVec2::new(0, 1),
+        bodies: UVec2::new(0, 1),
+        friction: 0.5,
+        ..Default::default()
+    };
+    let collider_poses = [Pose::IDENTITY, Pose::from_translation(Vector::Y * 0.5)];
+    let mut constraint = TwoBodyConstraint::default();
+    let mut builder = TwoBodyConstraintBuilder::default();
+    manifold.contact_to_constraint(
+        &Slice(&mprops, 0),
+        &Slice(&collider_poses, 0),
+        &Slice(&solver_poses, 0),
+        &Slice(&vels, 0),
+        &params(step_dt),
+        &mut constraint,
+        &mut builder,
+    );
+
+    let [mut vel1, mut vel2] = vels;
+    for _ in 0..10 {
+        constraint.solve_constraint_gauss_seidel(&mut vel1, &mut vel2, false);
+    }
+    [vel1, vel2]
+}
+
+#[test]
+fn test_moving_kinematic_body_pushes_dynamic_body() {
+    // The kinematic body moves up and sideways, at `(1, 1)`.
+    let step = 1.0 / 60.0;
+    let target = Pose::from_translation(-Vector::Y * 0.5 + (Vector::X + Vector::Y) * step);
+    let [kinematic, dynamic] = push_resting_body(target);
+
+    // The kinematic body isn't affected by the contact.
+    assert!((kinematic.linear - (Vector::X + Vector::Y)).length() < 1.0e-4);
+    assert_eq!(kinematic.angular, AngVector::default());
+
+    // The dynamic body moves up with the kinematic body: the relative normal
+    // velocity at the contact is zero.
+    let relative_normal_vel = (dynamic.linear - kinematic.linear).dot(Vector::Y);
+    assert!(
+        relative_normal_vel.abs() < 1.0e-4,
+        "relative normal velocity: {relative_normal_vel}"
+    );
+
+    // Friction drags the dynamic body sideways, but the contact slides: the
+    // friction impulse is limited to `0.5` (friction × normal impulse).
+    assert!(dynamic.linear.x > 0.0);
+    assert!(dynamic.linear.x <= 0.5 + 1.0e-4);
+
+    // A kinematic body staying still behaves like a fixed body.
+    let [kinematic, dynamic] = push_resting_body(Pose::from_translation(-Vector::Y * 0.5));
+    assert_eq!(kinematic.linear, Vector::ZERO);
+    assert!(dynamic.linear.length() < 1.0e-4);
+}
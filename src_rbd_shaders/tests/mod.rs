@@ -2,8 +2,24 @@
 //!
 //! These tests run on the CPU and call the shader functions directly.
 
+mod body_updates;
+mod brute_force;
+mod ccd;
+mod collision_events;
+mod composite_composite;
+mod dominance;
+mod env_snapshots;
+mod env_tensors;
 mod epa;
 mod gjk;
+mod impulse_joints;
+mod islands;
+mod joint_wrenches;
+mod kinematic;
 #[cfg(feature = "dim3")]
 mod linalg;
+mod param_updates;
 mod pfm_pfm;
+mod scene_queries;
+mod sensors;
+mod standalone_shapes;
@@ -0,0 +1,249 @@
+//! Tests for the per-environment writes and randomization of physical parameters.
+
+use crate::Vector;
+use crate::dynamics::{
+    LocalMassProperties, MultibodyLinkStatic, PARAM_DOF_ARMATURE, PARAM_DOF_DAMPING,
+    PARAM_FRICTION, PARAM_GRAVITY, PARAM_MASS, PARAM_RESTITUTION, PARAM_SAMPLE_NORMAL,
+    PARAM_SAMPLE_UNIFORM, PARAM_SAMPLE_VALUE, ParamUpdate, ParamUpdatesHeader,
+    gpu_apply_param_updates,
+};
+use crate::queries::ColliderMaterial;
+use crate::utils::BatchIndices;
+use glamx::{UVec3, Vec4};
+
+/// The parameter buffers of every batch.
+struct Params {
+    materials: Vec<ColliderMaterial>,
+    local_mprops: Vec<LocalMassProperties>,
+    links_static: Vec<MultibodyLinkStatic>,
+    dof_state: Vec<f32>,
+    gravity: Vec<Vec4>,
+}
+
+impl Params {
+    fn new(batch_ids: &BatchIndices) -> Self {
+        let num_batches = batch_ids.num_batches as usize;
+        let num_colliders = batch_ids.colliders_batch_capacity as usize * num_batches;
+        let num_links = batch_ids.links_batch_capacity as usize * num_batches;
+        let num_dofs = batch_ids.dof_batch_capacity as usize * num_batches;
+        let link = MultibodyLinkStatic {
+            local_mprops: LocalMassProperties::default(),
+            ..bytemuck::Zeroable::zeroed()
+        };
+        Self {
+            materials: vec![ColliderMaterial::default(); num_colliders],
+            local_mprops: vec![LocalMassProperties::default(); num_colliders],
+            links_static: vec![link; num_links],
+            // Velocities, dampings, armatures.
+            dof_state: vec![1.0; num_dofs * 3],
+            gravity: vec![Vec4::new(0.0, -9.81, 0.0, 0.0); num_batches],
+        }
+    }
+
+    fn apply(
+        &mut self,
+        updates: &[ParamUpdate],
+        header: ParamUpdatesHeader,
+        batch_ids: &BatchIndices,
+    ) {
+        let header = ParamUpdatesHeader {
+            num_updates: updates.len() as u32,
+            ..header
+        };
+        for i in 0..updates.len() as u32 + 1 {
+            gpu_apply_param_updates(
+                UVec3::new(i, 0, 0),
+                updates,
+                &mut self.materials,
+                &mut self.local_mprops,
+                &mut self.links_static,
+                &mut self.dof_state,
+                &mut self.gravity,
+                &header,
+                batch_ids,
+            );
+        }
+    }
+}
+
+fn value(kind: u32, batch_id: u32, id: u32, a: f32) -> ParamUpdate {
+    ParamUpdate {
+        kind,
+        batch_id,
+        id,
+        link_id: u32::MAX,
+        sampler: PARAM_SAMPLE_VALUE,
+        a,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_param_updates_write_their_batch() {
+    let mut batch_ids = BatchIndices {
+        num_batches: 2,
+        colliders_batch_capacity: 3,
+        links_batch_capacity: 1,
+        dof_batch_capacity: 2,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(3, 3, 0);
+    let mut params = Params::new(&batch_ids);
+    // The third body of each batch is fixed.
+    params.local_mprops[2].inv_mass = Vector::ZERO;
+    params.local_mprops[5].inv_mass = Vector::ZERO;
+
+    let updates = [
+        value(PARAM_FRICTION, 1, 1, 0.7),
+        // Negative values are clamped, except for gravity.
+        value(PARAM_RESTITUTION, 0, 0, -0.5),
+        ParamUpdate {
+            link_id: 0,
+            ..value(PARAM_MASS, 0, 1, 4.0)
+        },
+        value(PARAM_MASS, 1, 2, 4.0),
+        // A zero mass is ignored.
+        value(PARAM_MASS, 1, 0, 0.0),
+        value(PARAM_DOF_DAMPING, 1, 0, 0.25),
+        value(PARAM_DOF_ARMATURE, 0, 1, 0.5),
+        value(PARAM_GRAVITY, 1, 0, -3.0),
+    ];
+    params.apply(&updates, ParamUpdatesHeader::default(), &batch_ids);
+
+    let frictions: Vec<_> = params.materials.iter().map(|m| m.friction).collect();
+    let default_friction = ColliderMaterial::default().friction;
+    let mut expected = vec![default_friction; 6];
+    expected[4] = 0.7;
+    assert_eq!(frictions, expected);
+    assert_eq!(params.materials[0].restitution, 0.0);
+
+    // The mass of the link-backed body is set on both sides, and its inertia
+    // scaled by the same ratio. Fixed bodies keep their infinite mass.
+    let inv_masses: Vec<_> = params.local_mprops.iter().map(|m| m.inv_mass).collect();
+    let one = Vector::ONE;
+    let zero = Vector::ZERO;
+    assert_eq!(inv_masses, [one, one * 0.25, zero, one, one, zero]);
+    #[cfg(feature = "dim2")]
+    assert_eq!(params.local_mprops[1].inv_inertia, 0.25);
+    #[cfg(feature = "dim3")]
+    assert_eq!(
+        params.local_mprops[1].inv_principal_inertia,
+        glamx::Vec3::splat(0.25)
+    );
+    assert_eq!(params.links_static[0].local_mprops.inv_mass, one * 0.25);
+    assert_eq!(params.links_static[1].local_mprops.inv_mass, one);
+
+    // The DOF buffers interleave the batches: `[section][dof][batch]`.
+    let mut expected = vec![1.0; 12];
+    expected[4 + 1] = 0.25;
+    expected[8 + 2] = 0.5;
+    assert_eq!(params.dof_state, expected);
+
+    assert_eq!(params.gravity[0], Vec4::new(0.0, -9.81, 0.0, 0.0));
+    assert_eq!(params.gravity[1], Vec4::new(-3.0, -9.81, 0.0, 0.0));
+}
+
+#[test]
+fn test_param_updates_sample_their_distribution() {
+    let num_batches = 1000;
+    let mut batch_ids = BatchIndices {
+        num_batches,
+        colliders_batch_capacity: 1,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(1, 1, 0);
+
+    let mut updates = vec![];
+    for batch_id in 0..num_batches {
+        updates.push(ParamUpdate {
+            sampler: PARAM_SAMPLE_UNIFORM,
+            b: 0.8,
+            ..value(PARAM_FRICTION, batch_id, 0, 0.2)
+        });
+        updates.push(ParamUpdate {
+            sampler: PARAM_SAMPLE_NORMAL,
+            b: 0.5,
+            ..value(PARAM_GRAVITY, batch_id, 1, -10.0)
+        });
+    }
+
+    let header = ParamUpdatesHeader {
+        seed: 42,
+        ..Default::default()
+    };
+    let mut params = Params::new(&batch_ids);
+    params.apply(&updates, header, &batch_ids);
+
+    let frictions: Vec<_> = params.materials.iter().map(|m| m.friction).collect();
+    assert!(frictions.iter().all(|f| (0.2..=0.8).contains(f)));
+    let mean = frictions.iter().sum::<f32>() / num_batches as f32;
+    assert!((mean - 0.5).abs() < 0.05, "uniform mean {mean}");
+
+    let gravity: Vec<_> = params.gravity.iter().map(|g| g.y).collect();
+    let mean = gravity.iter().sum::<f32>() / num_batches as f32;
+    let variance = gravity.iter().map(|g| (g - mean).powi(2)).sum::<f32>() / num_batches as f32;
+    assert!((mean + 10.0).abs() < 0.1, "normal mean {mean}");
+    assert!(
+        (variance.sqrt() - 0.5).abs() < 0.1,
+        "normal deviation {variance}"
+    );
+
+    // The same seed and draw give the same samples, the next draw new ones.
+    let mut same = Params::new(&batch_ids);
+    same.apply(&updates, header, &batch_ids);
+    assert_eq!(same.gravity, params.gravity);
+    let mut next = Params::new(&batch_ids);
+    next.apply(
+        &updates,
+        ParamUpdatesHeader { draw: 1, ..header },
+        &batch_ids,
+    );
+    assert_ne!(next.gravity, params.gravity);
+}
+
+#[test]
+fn test_param_draws_ignore_the_other_updates() {
+    let mut batch_ids = BatchIndices {
+        num_batches: 2,
+        colliders_batch_capacity: 2,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(2, 2, 0);
+    let friction = ParamUpdate {
+        sampler: PARAM_SAMPLE_UNIFORM,
+        b: 1.0,
+        ..value(PARAM_FRICTION, 1, 1, 0.0)
+    };
+    let header = ParamUpdatesHeader {
+        seed: 7,
+        ..Default::default()
+    };
+
+    let mut alone = Params::new(&batch_ids);
+    alone.apply(&[friction], header, &batch_ids);
+
+    // Other parameters queued before it shift its dispatch index, not its draw.
+    let others = [
+        ParamUpdate {
+            sampler: PARAM_SAMPLE_UNIFORM,
+            b: 1.0,
+            ..value(PARAM_FRICTION, 0, 1, 0.0)
+        },
+        ParamUpdate {
+            sampler: PARAM_SAMPLE_UNIFORM,
+            b: 1.0,
+            ..value(PARAM_RESTITUTION, 1, 1, 0.0)
+        },
+        friction,
+    ];
+    let mut queued = Params::new(&batch_ids);
+    queued.apply(&others, header, &batch_ids);
+
+    assert_eq!(queued.materials[3].friction, alone.materials[3].friction);
+    // Yet different parameters get different draws.
+    assert_ne!(queued.materials[1].friction, queued.materials[3].friction);
+    assert_ne!(
+        queued.materials[3].restitution,
+        queued.materials[3].friction
+    );
+}
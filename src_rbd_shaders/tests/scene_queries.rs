@@ -0,0 +1,80 @@
+//! Tests for the geometric helpers used by the scene queries.
+
+use crate::bounding_volumes::Aabb;
+use crate::shapes::Polyline;
+use crate::{MAX_FLT, PaddedVector, Vector};
+
+/// A vector in the `xy` plane.
+fn xy(x: f32, y: f32) -> Vector {
+    Vector::X * x + Vector::Y * y
+}
+
+#[test]
+fn test_aabb_cast_ray() {
+    let aabb = Aabb::new(Vector::splat(-1.0), Vector::splat(1.0));
+
+    // Axis-aligned ray hitting the face `x = -1`.
+    let toi = aabb.cast_ray(xy(-3.0, 0.5), Vector::X, MAX_FLT);
+    assert!((toi - 2.0).abs() < 1.0e-5, "got {toi}");
+
+    // Diagonal ray with a non-unit direction hitting the corner region.
+    let toi = aabb.cast_ray(xy(-3.0, -3.0), xy(2.0, 2.0), MAX_FLT);
+    assert!((toi - 1.0).abs() < 1.0e-5, "got {toi}");
+
+    // Rays starting inside of the AABB hit immediately.
+    assert_eq!(aabb.cast_ray(Vector::ZERO, Vector::Y, MAX_FLT), 0.0);
+
+    // Rays passing beside the AABB, pointing away from it, or stopping short of it miss.
+    assert_eq!(aabb.cast_ray(xy(-3.0, 2.0), Vector::X, MAX_FLT), MAX_FLT);
+    assert_eq!(aabb.cast_ray(xy(-3.0, 0.0), -Vector::X, MAX_FLT), MAX_FLT);
+    assert_eq!(aabb.cast_ray(xy(-3.0, 0.0), Vector::X, 1.5), MAX_FLT);
+}
+
+/// The polyline `(0, 0) -> (1, 0) -> (2, 1)` with a BVH made of one root and
+/// one leaf per segment, packed like the host-side conversion does.
+fn test_polyline() -> (Polyline, Vec<PaddedVector>, Vec<u32>) {
+    let pts = [xy(0.0, 0.0), xy(1.0, 0.0), xy(2.0, 1.0)];
+    let node_aabbs = [(pts[0], pts[2]), (pts[0], pts[1]), (pts[1], pts[2])];
+    let vertices = node_aabbs
+        .iter()
+        .flat_map(|(mins, maxs)| [*mins, *maxs])
+        .chain(pts)
+        .map(PaddedVector::new)
+        .collect();
+    #[rustfmt::skip]
+    let indices = vec![
+        // BVH nodes: entry index, exit index, segment index.
+        1, 3, 0,
+        u32::MAX, 2, 0,
+        u32::MAX, 3, 1,
+        // Segments.
+        0, 1,
+        1, 2,
+    ];
+    let polyline = Polyline::new(0, 0, 3, Aabb::new(pts[0], pts[2]));
+    (polyline, vertices, indices)
+}
+
+#[test]
+fn test_polyline_project_local_point() {
+    let (polyline, vertices, indices) = test_polyline();
+
+    // Closest to the first segment.
+    let (proj, found) = polyline.project_local_point(&vertices, &indices, xy(0.5, 1.0), MAX_FLT);
+    assert!(found && !proj.is_inside);
+    assert!((proj.point - xy(0.5, 0.0)).length() < 1.0e-5);
+
+    // Closest to the second segment.
+    let (proj, found) = polyline.project_local_point(&vertices, &indices, xy(2.0, 0.0), MAX_FLT);
+    assert!(found && !proj.is_inside);
+    assert!((proj.point - xy(1.5, 0.5)).length() < 1.0e-5);
+
+    // Closest to the shared vertex.
+    let (proj, found) = polyline.project_local_point(&vertices, &indices, xy(1.5, -1.0), MAX_FLT);
+    assert!(found);
+    assert!((proj.point - xy(1.0, 0.0)).length() < 1.0e-5);
+
+    // No segment within `max_dist`.
+    let (_, found) = polyline.project_local_point(&vertices, &indices, xy(0.5, 5.0), 1.0);
+    assert!(!found);
+}
@@ -0,0 +1,152 @@
+//! Tests for the sensor intersections and their collision events.
+
+use crate::broad_phase::{
+    COLLISION_EVENT_FLAG_SENSOR, COLLISION_EVENT_STARTED, COLLISION_EVENT_STOPPED, CollisionEvent,
+    CollisionPair, gpu_emit_collision_events, gpu_reset_sensors, gpu_sensor_intersections,
+    gpu_sensor_pairs,
+};
+use crate::queries::{COLLIDER_FLAG_SENSOR, ColliderMaterial};
+use crate::shapes::Shape;
+use crate::utils::BatchIndices;
+use crate::{Pose, Vector};
+use glamx::{UVec2, UVec3};
+
+const CAPACITY: u32 = 8;
+
+fn batch_ids() -> BatchIndices {
+    let mut batch_ids = BatchIndices {
+        num_batches: 1,
+        colliders_batch_capacity: 3,
+        collision_pairs_batch_capacity: CAPACITY,
+        contacts_batch_capacity: CAPACITY,
+        event_pairs_batch_capacity: CAPACITY,
+        events_batch_capacity: CAPACITY,
+        ..Default::default()
+    };
+    batch_ids.set_uniform_ranges(3, 3, 0);
+    batch_ids
+}
+
+/// Runs both sensor passes on all the pairs between the three colliders.
+///
+/// Returns the intersecting pairs.
+fn sensor_intersections(
+    poses: &[Pose; 3],
+    shapes: &[Shape; 3],
+    materials: &[ColliderMaterial; 3],
+) -> Vec<UVec2> {
+    let batch_ids = batch_ids();
+    let pairs = [UVec2::new(0, 1), UVec2::new(0, 2), UVec2::new(1, 2)]
+        .map(|colliders| CollisionPair { colliders });
+
+    let mut sensor_pairs = vec![UVec2::ZERO; CAPACITY as usize];
+    let mut sensor_pairs_len = [7];
+    let mut intersections = vec![UVec2::ZERO; CAPACITY as usize];
+    let mut intersections_len = [7];
+    gpu_reset_sensors(UVec3::ZERO, &mut sensor_pairs_len, &mut intersections_len);
+    assert_eq!((sensor_pairs_len[0], intersections_len[0]), (0, 0));
+
+    gpu_sensor_pairs(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &pairs,
+        &[pairs.len() as u32],
+        &[0, 1, 2],
+        materials,
+        &mut sensor_pairs,
+        &mut sensor_pairs_len,
+        &batch_ids,
+    );
+    // The pair without a sensor is left to the contact narrow phase.
+    assert_eq!(sensor_pairs_len[0], 2);
+
+    gpu_sensor_intersections(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &sensor_pairs,
+        &sensor_pairs_len,
+        poses,
+        shapes,
+        &[],
+        &[],
+        &mut intersections,
+        &mut intersections_len,
+        &batch_ids,
+    );
+    intersections.truncate(intersections_len[0] as usize);
+    intersections
+}
+
+/// Emits the collision events between the previous and the current intersections.
+fn collision_events(
+    old_pairs: &[UVec2],
+    pairs: &[UVec2],
+    materials: &[ColliderMaterial; 3],
+) -> Vec<CollisionEvent> {
+    let batch_ids = batch_ids();
+    let pad = |pairs: &[UVec2]| {
+        let mut padded = pairs.to_vec();
+        padded.resize(CAPACITY as usize, UVec2::ZERO);
+        padded
+    };
+
+    let mut events = vec![CollisionEvent::default(); CAPACITY as usize];
+    let mut events_len = [0];
+    gpu_emit_collision_events(
+        UVec3::ZERO,
+        UVec3::ONE,
+        &pad(pairs),
+        &[pairs.len() as u32],
+        &pad(old_pairs),
+        &[old_pairs.len() as u32],
+        materials,
+        &mut events,
+        &mut events_len,
+        &batch_ids,
+    );
+    events.truncate(events_len[0] as usize);
+    events
+}
+
+#[test]
+fn test_sensor_intersection_started_and_stopped() {
+    // A sensor ball touched by a second ball, and a third ball far from both.
+    let shapes = [Shape::ball(1.0); 3];
+    let mut materials = [ColliderMaterial::default(); 3];
+    materials[0].flags = COLLIDER_FLAG_SENSOR;
+    let mut poses = [
+        Pose::IDENTITY,
+        Pose::from_translation(Vector::X * 1.5),
+        Pose::from_translation(Vector::X * 5.0),
+    ];
+
+    let intersections = sensor_intersections(&poses, &shapes, &materials);
+    assert_eq!(intersections, [UVec2::new(0, 1)]);
+    let events = collision_events(&[], &intersections, &materials);
+    assert_eq!(
+        events,
+        [CollisionEvent {
+            colliders: UVec2::new(0, 1),
+            kind: COLLISION_EVENT_STARTED,
+            flags: COLLISION_EVENT_FLAG_SENSOR,
+        }]
+    );
+
+    // Still intersecting: no new event, even if the pair is reported the other way around.
+    let events = collision_events(&intersections, &[UVec2::new(1, 0)], &materials);
+    assert!(events.is_empty());
+
+    // The second ball leaves the sensor.
+    poses[1] = Pose::from_translation(-Vector::X * 2.5);
+    let new_intersections = sensor_intersections(&poses, &shapes, &materials);
+    assert!(new_intersections.is_empty());
+    let events = collision_events(&intersections, &new_intersections, &materials);
+    assert_eq!(
+        events,
+        [CollisionEvent {
+            colliders: UVec2::new(0, 1),
+            kind: COLLISION_EVENT_STOPPED,
+            flags: COLLISION_EVENT_FLAG_SENSOR,
+        }]
+    );
+}
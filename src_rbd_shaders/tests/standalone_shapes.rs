@@ -0,0 +1,100 @@
+//! Tests for the standalone segment, triangle and tetrahedron colliders.
+
+use crate::queries::contact::pfm_pfm;
+use crate::shapes::{Shape, Triangle};
+use crate::{Pose, Vector};
+use khal_std::index::MaybeIndexUnchecked;
+
+fn assert_close(a: Vector, b: Vector) {
+    assert!((a - b).length() < 1.0e-5, "expected {b:?}, got {a:?}");
+}
+
+/// Asserts that `shape`, resting at `y` above the top face of a unit cuboid,
+/// touches it with `len` contact points.
+fn assert_rests_on_cuboid(shape: &Shape, y: f32, len: u32) {
+    let cuboid = Shape::cuboid(Vector::ONE);
+    let pose12 = Pose::from_translation(Vector::Y * (1.0 + y));
+    let manifold = pfm_pfm(
+        pose12,
+        &cuboid,
+        0.0,
+        shape,
+        0.0,
+        0.1,
+        &[],
+        #[cfg(feature = "dim3")]
+        &[],
+    );
+
+    assert_eq!(manifold.len, len);
+    assert_close(manifold.normal_a, Vector::Y);
+    for i in 0..len as usize {
+        let dist = manifold.points_a.at(i).dist;
+        assert!(
+            (dist - y).abs() < 1.0e-3,
+            "expected distance {y}, got {dist}"
+        );
+    }
+}
+
+#[test]
+fn test_segment_shape() {
+    let segment = Shape::segment(-Vector::X, Vector::X);
+
+    assert_close(
+        segment.project_local_point(Vector::X * 0.5 + Vector::Y),
+        Vector::X * 0.5,
+    );
+    assert_close(segment.project_local_point(Vector::X * 2.0), Vector::X);
+    assert_close(segment.local_support_point(Vector::ONE, &[]), Vector::X);
+
+    let aabb = segment.compute_aabb(Pose::from_translation(Vector::Y), &[]);
+    assert_close(aabb.mins, Vector::Y - Vector::X);
+    assert_close(aabb.maxs, Vector::Y + Vector::X);
+
+    // Lying flat, the segment touches the cuboid at both endpoints.
+    assert_rests_on_cuboid(&segment, 0.05, 2);
+}
+
+#[test]
+fn test_triangle_shape() {
+    let triangle = Shape::from_triangle(&Triangle::new(Vector::ZERO, Vector::X, Vector::Y));
+
+    // Points inside the triangle are their own projection.
+    let inside = (Vector::X + Vector::Y) * 0.25;
+    assert_close(triangle.project_local_point(inside), inside);
+    assert_close(triangle.project_local_point(-Vector::ONE), Vector::ZERO);
+    assert_close(
+        triangle.project_point(Pose::from_translation(Vector::Y), Vector::X * 2.0),
+        Vector::X + Vector::Y,
+    );
+}
+
+#[cfg(feature = "dim3")]
+#[test]
+fn test_tetrahedron_shape() {
+    // The tetrahedron `(0, X, Z, Y)`, with its face `(0, X, Z)` facing down.
+    let tetrahedron = Shape::tetrahedron(Vector::X, Vector::Z, Vector::Y);
+
+    let inside = Vector::new(0.1, 0.2, 0.2);
+    assert_close(tetrahedron.project_local_point(inside), inside);
+    let proj = tetrahedron.project_local_point_on_boundary(inside);
+    assert!(proj.is_inside);
+    assert_close(proj.point, Vector::new(0.0, 0.2, 0.2));
+    assert_close(
+        tetrahedron.project_local_point(Vector::new(-1.0, 0.2, 0.2)),
+        Vector::new(0.0, 0.2, 0.2),
+    );
+    assert_close(
+        tetrahedron.local_support_point(Vector::new(1.0, 3.0, 2.0), &[]),
+        Vector::Y,
+    );
+
+    let aabb = tetrahedron.compute_aabb(Pose::IDENTITY, &[]);
+    assert_close(aabb.mins, Vector::ZERO);
+    assert_close(aabb.maxs, Vector::ONE);
+
+    // Resting on its bottom face, the tetrahedron touches the cuboid at its
+    // three vertices.
+    assert_rests_on_cuboid(&tetrahedron, 0.05, 3);
+}
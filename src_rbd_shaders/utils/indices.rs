@@ -1,12 +1,29 @@
 use crate::utils::linalg::{MatSlice, VSlice};
 use crate::utils::{ISlice, ISliceMut, Slice, SliceMut};
+use glamx::UVec4;
+
+/// Maximum number of batches (environments) of a [`BatchIndices`].
+///
+/// Bounded by the size of the per-batch range arrays, which must fit the 64 KiB
+/// uniform buffers of WebGPU.
+pub const MAX_BATCHES: usize = 2048;
+
+/// Number of `UVec4` entries of each per-batch array of [`BatchIndices`].
+const BATCH_ARRAY_LEN: usize = MAX_BATCHES / 4;
 
 /// Per-batch capacities and packed-buffer section offsets, shared by every
 /// kernel that needs to slice a flat tensor into its batch's slot.
 ///
 /// Combining 30+ scalar uniforms into a single struct keeps the WebGPU
 /// uniform count under control and centralises the per-buffer slicing logic.
-#[derive(Copy, Clone, Default)]
+///
+/// Colliders, bodies and free-body impulse joints have per-batch offsets and
+/// lengths, so environments of different sizes are packed one after the other
+/// and kernels only iterate over each batch's own elements. Bodies and colliders
+/// share the same per-batch offset. The other buffers keep one shared stride
+/// per batch. The per-batch arrays pack four batches per `UVec4` (uniform arrays
+/// have a 16-byte stride), read through [`Self::colliders_len`] and its siblings.
+#[derive(Copy, Clone)]
 #[cfg_attr(not(target_arch_is_gpu), derive(bytemuck::Pod, bytemuck::Zeroable))]
 #[repr(C)]
 pub struct BatchIndices {
@@ -16,17 +33,24 @@ pub struct BatchIndices {
     /*
      * RBD / collision-detection capacities.
      */
+    /// Size of the largest batch's collider/body range: the dispatch width of the
+    /// per-collider and per-body kernels, and the per-batch stride of the LBVH
+    /// buffers (see [`Self::lbvh_start`]).
     pub colliders_batch_capacity: u32,
-    /// Number of *active* colliders per batch.
-    pub colliders_len: u32,
-    /// Number of *active* rigid bodies per batch.
-    pub bodies_len: u32,
     pub collision_pairs_batch_capacity: u32,
     pub contacts_batch_capacity: u32,
-    /// Free-body impulse joints — buffer stride (capacity) per batch.
+    /// Free-body impulse joints of the largest batch: the dispatch width of the
+    /// joint kernels, and the per-batch capacity of the joint-break event ring
+    /// buffers.
     pub impulse_joints_batch_capacity: u32,
-    /// Number of *active* free-body impulse joints per batch (the loop bound).
-    pub impulse_joints_len: u32,
+    /// Free-body impulse-joint color-group slab (per-batch stride = number of
+    /// colors).
+    pub impulse_joint_color_groups_batch_capacity: u32,
+    /// Pairs tracked for collision events (sensor pairs, intersecting sensor
+    /// pairs, touching pairs) — buffer stride per batch.
+    pub event_pairs_batch_capacity: u32,
+    /// Collision-event and contact-force-event ring buffers — buffer stride per batch.
+    pub events_batch_capacity: u32,
 
     /*
      * Multibody core capacities.
@@ -53,9 +77,7 @@ pub struct BatchIndices {
     pub mb_imp_joint_constraints_batch_capacity: u32,
     pub mb_imp_joint_jacobians_batch_capacity: u32,
     /// Multibody-touching impulse-joint color-group slab (per-batch stride
-    /// = number of colors). The free-body impulse-joint color groups, by
-    /// contrast, are stored single-batch (identical coloring across batches)
-    /// and read at offset 0.
+    /// = number of colors).
     pub mb_imp_joint_color_groups_batch_capacity: u32,
     /// Actual max `ndofs` across every multibody in every batch (often smaller
     /// than the fixed `MAX_MB_DOFS` limit).
@@ -90,9 +112,154 @@ pub struct BatchIndices {
     pub mass_matrix_acc_section_offset: u32,
     /// Per-batch stride (capacity) of the multibody DoF-coupling buffer.
     pub mb_dof_couplings_batch_capacity: u32,
+    /// Aligns the per-batch arrays on 16 bytes.
+    pub _padding: [u32; 2],
+
+    /*
+     * Per-batch ranges, four batches per entry.
+     */
+    /// Start of each batch's collider and body range.
+    pub colliders_offsets: [UVec4; BATCH_ARRAY_LEN],
+    /// Number of *active* colliders of each batch.
+    pub colliders_lens: [UVec4; BATCH_ARRAY_LEN],
+    /// Number of *active* rigid bodies of each batch.
+    pub bodies_lens: [UVec4; BATCH_ARRAY_LEN],
+    /// Start of each batch's free-body impulse-joint range.
+    pub impulse_joints_offsets: [UVec4; BATCH_ARRAY_LEN],
+    /// Number of free-body impulse joints of each batch (the loop bound).
+    pub impulse_joints_lens: [UVec4; BATCH_ARRAY_LEN],
+}
+
+impl Default for BatchIndices {
+    fn default() -> Self {
+        BatchIndices {
+            num_batches: 0,
+            colliders_batch_capacity: 0,
+            collision_pairs_batch_capacity: 0,
+            contacts_batch_capacity: 0,
+            impulse_joints_batch_capacity: 0,
+            impulse_joint_color_groups_batch_capacity: 0,
+            event_pairs_batch_capacity: 0,
+            events_batch_capacity: 0,
+            multibodies_batch_capacity: 0,
+            multibodies_len: 0,
+            links_batch_capacity: 0,
+            jacobians_batch_capacity: 0,
+            mass_matrix_batch_capacity: 0,
+            coriolis_batch_capacity: 0,
+            i_coriolis_dt_batch_capacity: 0,
+            dof_batch_capacity: 0,
+            mb_joint_constraints_batch_capacity: 0,
+            mb_joint_constraint_columns_batch_capacity: 0,
+            mb_contact_constraints_batch_capacity: 0,
+            mb_contact_constraint_columns_batch_capacity: 0,
+            mb_imp_joints_batch_capacity: 0,
+            mb_imp_joint_constraints_batch_capacity: 0,
+            mb_imp_joint_jacobians_batch_capacity: 0,
+            mb_imp_joint_color_groups_batch_capacity: 0,
+            mb_max_ndofs: 0,
+            mb_max_links: 0,
+            mb_pack_lanes: 0,
+            mb_max_joint_constraints: 0,
+            solver_color_buckets_stride: 0,
+            coriolis_w_section_offset: 0,
+            i_coriolis_dt_section_offset: 0,
+            dof_damping_section_offset: 0,
+            mass_matrix_acc_section_offset: 0,
+            mb_dof_couplings_batch_capacity: 0,
+            _padding: [0; 2],
+            colliders_offsets: [UVec4::ZERO; BATCH_ARRAY_LEN],
+            colliders_lens: [UVec4::ZERO; BATCH_ARRAY_LEN],
+            bodies_lens: [UVec4::ZERO; BATCH_ARRAY_LEN],
+            impulse_joints_offsets: [UVec4::ZERO; BATCH_ARRAY_LEN],
+            impulse_joints_lens: [UVec4::ZERO; BATCH_ARRAY_LEN],
+        }
+    }
+}
+
+/// The entry of batch `batch_id` in a per-batch array.
+#[inline]
+fn batch_entry(entries: &[UVec4; BATCH_ARRAY_LEN], batch_id: u32) -> u32 {
+    let entry = entries[(batch_id / 4) as usize];
+    match batch_id % 4 {
+        0 => entry.x,
+        1 => entry.y,
+        2 => entry.z,
+        _ => entry.w,
+    }
+}
+
+/// Sets the entry of batch `batch_id` in a per-batch array.
+#[inline]
+fn set_batch_entry(entries: &mut [UVec4; BATCH_ARRAY_LEN], batch_id: u32, value: u32) {
+    let entry = &mut entries[(batch_id / 4) as usize];
+    match batch_id % 4 {
+        0 => entry.x = value,
+        1 => entry.y = value,
+        2 => entry.z = value,
+        _ => entry.w = value,
+    }
 }
 
 impl BatchIndices {
+    /// Number of *active* colliders of batch `batch_id`.
+    #[inline]
+    pub fn colliders_len(&self, batch_id: u32) -> u32 {
+        batch_entry(&self.colliders_lens, batch_id)
+    }
+
+    /// Number of *active* rigid bodies of batch `batch_id`.
+    #[inline]
+    pub fn bodies_len(&self, batch_id: u32) -> u32 {
+        batch_entry(&self.bodies_lens, batch_id)
+    }
+
+    /// Number of free-body impulse joints of batch `batch_id`.
+    #[inline]
+    pub fn impulse_joints_len(&self, batch_id: u32) -> u32 {
+        batch_entry(&self.impulse_joints_lens, batch_id)
+    }
+
+    /// Sets the collider/body range of batch `batch_id`: `colliders_len`
+    /// colliders and `bodies_len` bodies, starting at `offset`.
+    pub fn set_colliders_range(
+        &mut self,
+        batch_id: u32,
+        offset: u32,
+        colliders_len: u32,
+        bodies_len: u32,
+    ) {
+        set_batch_entry(&mut self.colliders_offsets, batch_id, offset);
+        set_batch_entry(&mut self.colliders_lens, batch_id, colliders_len);
+        set_batch_entry(&mut self.bodies_lens, batch_id, bodies_len);
+    }
+
+    /// Sets the free-body impulse-joint range of batch `batch_id`: `len` joints
+    /// starting at `offset`.
+    pub fn set_impulse_joints_range(&mut self, batch_id: u32, offset: u32, len: u32) {
+        set_batch_entry(&mut self.impulse_joints_offsets, batch_id, offset);
+        set_batch_entry(&mut self.impulse_joints_lens, batch_id, len);
+    }
+
+    /// Gives every batch a `colliders_batch_capacity` collider/body range and an
+    /// `impulse_joints_batch_capacity` impulse-joint range, one after the other,
+    /// with the given active counts.
+    pub fn set_uniform_ranges(&mut self, colliders_len: u32, bodies_len: u32, joints_len: u32) {
+        for batch_id in 0..self.num_batches {
+            self.set_colliders_range(
+                batch_id,
+                batch_id * self.colliders_batch_capacity,
+                colliders_len,
+                bodies_len,
+            );
+            self.set_impulse_joints_range(
+                batch_id,
+                batch_id * self.impulse_joints_batch_capacity,
+                joints_len,
+            );
+        }
+    }
+
     /*
      * Raw batch-start offsets (in element units, not bytes) for buffers
      * whose batch stride is one of the `*_batch_capacity` fields. Used to
@@ -101,6 +268,15 @@ impl BatchIndices {
      */
     #[inline]
     pub fn coll_start(&self, batch_id: u32) -> usize {
+        batch_entry(&self.colliders_offsets, batch_id) as usize
+    }
+
+    /// Start of the LBVH section (morton keys, sorted colliders and tree) of
+    /// batch `batch_id`. Unlike the collider buffers, these are laid out with a
+    /// `colliders_batch_capacity` stride since the radix sort needs batches of
+    /// equal size.
+    #[inline]
+    pub fn lbvh_start(&self, batch_id: u32) -> usize {
         batch_id as usize * self.colliders_batch_capacity as usize
     }
 
@@ -155,11 +331,33 @@ impl BatchIndices {
         batch_id as usize * self.contacts_batch_capacity as usize
     }
 
+    #[inline]
+    pub fn event_pairs_start(&self, batch_id: u32) -> usize {
+        batch_id as usize * self.event_pairs_batch_capacity as usize
+    }
+
+    #[inline]
+    pub fn events_start(&self, batch_id: u32) -> usize {
+        batch_id as usize * self.events_batch_capacity as usize
+    }
+
     #[inline]
     pub fn impulse_joints_start(&self, batch_id: u32) -> usize {
+        batch_entry(&self.impulse_joints_offsets, batch_id) as usize
+    }
+
+    /// Start of the joint-break event ring buffer of batch `batch_id`, which
+    /// holds `impulse_joints_batch_capacity` events.
+    #[inline]
+    pub fn impulse_joint_events_start(&self, batch_id: u32) -> usize {
         batch_id as usize * self.impulse_joints_batch_capacity as usize
     }
 
+    #[inline]
+    pub fn impulse_joint_color_groups_start(&self, batch_id: u32) -> usize {
+        batch_id as usize * self.impulse_joint_color_groups_batch_capacity as usize
+    }
+
     #[inline]
     pub fn mb_joint_constraints_start(&self, batch_id: u32) -> usize {
         batch_id as usize * self.mb_joint_constraints_batch_capacity as usize
@@ -219,6 +417,16 @@ impl BatchIndices {
         SliceMut(slice, self.coll_start(batch_id))
     }
 
+    #[inline]
+    pub fn lbvh_batch<'s, T>(&self, batch_id: u32, slice: &'s [T]) -> Slice<'s, T> {
+        Slice(slice, self.lbvh_start(batch_id))
+    }
+
+    #[inline]
+    pub fn lbvh_batch_mut<'s, T>(&self, batch_id: u32, slice: &'s mut [T]) -> SliceMut<'s, T> {
+        SliceMut(slice, self.lbvh_start(batch_id))
+    }
+
     #[inline]
     pub fn collision_pairs_batch<'s, T>(&self, batch_id: u32, slice: &'s [T]) -> Slice<'s, T> {
         Slice(slice, self.collision_pairs_start(batch_id))
@@ -243,6 +451,25 @@ impl BatchIndices {
         SliceMut(slice, self.contacts_start(batch_id))
     }
 
+    #[inline]
+    pub fn event_pairs_batch<'s, T>(&self, batch_id: u32, slice: &'s [T]) -> Slice<'s, T> {
+        Slice(slice, self.event_pairs_start(batch_id))
+    }
+
+    #[inline]
+    pub fn event_pairs_batch_mut<'s, T>(
+        &self,
+        batch_id: u32,
+        slice: &'s mut [T],
+    ) -> SliceMut<'s, T> {
+        SliceMut(slice, self.event_pairs_start(batch_id))
+    }
+
+    #[inline]
+    pub fn events_batch_mut<'s, T>(&self, batch_id: u32, slice: &'s mut [T]) -> SliceMut<'s, T> {
+        SliceMut(slice, self.events_start(batch_id))
+    }
+
     #[inline]
     pub fn impulse_joints_batch<'s, T>(&self, batch_id: u32, slice: &'s [T]) -> Slice<'s, T> {
         Slice(slice, self.impulse_joints_start(batch_id))
@@ -257,6 +484,24 @@ impl BatchIndices {
         SliceMut(slice, self.impulse_joints_start(batch_id))
     }
 
+    #[inline]
+    pub fn impulse_joint_events_batch_mut<'s, T>(
+        &self,
+        batch_id: u32,
+        slice: &'s mut [T],
+    ) -> SliceMut<'s, T> {
+        SliceMut(slice, self.impulse_joint_events_start(batch_id))
+    }
+
+    #[inline]
+    pub fn impulse_joint_color_groups_batch<'s, T>(
+        &self,
+        batch_id: u32,
+        slice: &'s [T],
+    ) -> Slice<'s, T> {
+        Slice(slice, self.impulse_joint_color_groups_start(batch_id))
+    }
+
     #[inline]
     pub fn mb_joint_constraints_batch<'s, T>(&self, batch_id: u32, slice: &'s [T]) -> Slice<'s, T> {
         Slice(slice, self.mb_joint_constraints_start(batch_id))
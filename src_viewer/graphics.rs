@@ -11,7 +11,7 @@ use kiss3d::scene::SceneNode2d;
 use kiss3d::scene::SceneNode3d;
 use rapier::data::Index;
 use rapier::math::{DIM, Vector};
-use rapier::parry::shape::ShapeType;
+use rapier::parry::shape::{Cuboid, ShapeType, TypedShape};
 use rapier::prelude::{RigidBodyHandle, SharedShape};
 use std::collections::HashMap;
 #[cfg(feature = "dim2")]
@@ -92,6 +92,108 @@ pub struct InstancedNodeEntry {
     pub local_pose: Pose,
 }
 
+/// Half-thickness of the capsule a 3D segment collider is rendered as.
+#[cfg(feature = "dim3")]
+const SEGMENT_RENDER_RADIUS: f32 = 0.02;
+
+/// The shape a collider without its own render node is drawn as.
+///
+/// A round shape is rendered as its inner shape grown by the border radius.
+/// Primitives are grown exactly along their axes, polygonal shapes are replaced
+/// by the convex hull of their vertices offset in a few directions. Segments,
+/// triangles and tetrahedra are rendered as the polyline, capsule, convex hull
+/// or two-sided mesh covering the same region, and heightfields as the polyline
+/// or triangle mesh of their cells.
+///
+/// Returns `None` if `shape` has a render node of its own.
+fn render_proxy_shape(shape: &SharedShape) -> Option<SharedShape> {
+    match shape.as_typed_shape() {
+        #[cfg(feature = "dim2")]
+        TypedShape::Segment(s) => Some(SharedShape::polyline(vec![s.a, s.b], None)),
+        #[cfg(feature = "dim3")]
+        TypedShape::Segment(s) => Some(SharedShape::capsule(s.a, s.b, SEGMENT_RENDER_RADIUS)),
+        #[cfg(feature = "dim2")]
+        TypedShape::Triangle(s) => SharedShape::convex_hull(&[s.a, s.b, s.c]),
+        #[cfg(feature = "dim3")]
+        TypedShape::Triangle(s) => {
+            // Both windings, with their own vertices so each side gets its own normal.
+            SharedShape::trimesh(
+                vec![s.a, s.b, s.c, s.a, s.c, s.b],
+                vec![[0, 1, 2], [3, 4, 5]],
+            )
+            .ok()
+        }
+        #[cfg(feature = "dim3")]
+        TypedShape::Tetrahedron(s) => SharedShape::convex_hull(&[s.a, s.b, s.c, s.d]),
+        #[cfg(feature = "dim2")]
+        TypedShape::HeightField(s) => {
+            let (vertices, indices) = s.to_polyline();
+            Some(SharedShape::polyline(vertices, Some(indices)))
+        }
+        #[cfg(feature = "dim3")]
+        TypedShape::HeightField(s) => {
+            let (vertices, indices) = s.to_trimesh();
+            SharedShape::trimesh(vertices, indices).ok()
+        }
+        TypedShape::RoundCuboid(s) => Some(SharedShape::new(Cuboid::new(
+            s.inner_shape.half_extents + Vector::splat(s.border_radius),
+        ))),
+        TypedShape::RoundTriangle(s) => {
+            let tri = &s.inner_shape;
+            dilated_convex_hull(&[tri.a, tri.b, tri.c], s.border_radius)
+        }
+        #[cfg(feature = "dim2")]
+        TypedShape::RoundConvexPolygon(s) => {
+            dilated_convex_hull(s.inner_shape.points(), s.border_radius)
+        }
+        #[cfg(feature = "dim3")]
+        TypedShape::RoundConvexPolyhedron(s) => {
+            dilated_convex_hull(s.inner_shape.points(), s.border_radius)
+        }
+        #[cfg(feature = "dim3")]
+        TypedShape::RoundCone(s) => Some(SharedShape::cone(
+            s.inner_shape.half_height + s.border_radius,
+            s.inner_shape.radius + s.border_radius,
+        )),
+        #[cfg(feature = "dim3")]
+        TypedShape::RoundCylinder(s) => Some(SharedShape::cylinder(
+            s.inner_shape.half_height + s.border_radius,
+            s.inner_shape.radius + s.border_radius,
+        )),
+        _ => None,
+    }
+}
+
+/// Convex hull of `points`, each offset by `radius` along the axis-aligned and
+/// diagonal directions.
+fn dilated_convex_hull(points: &[Vector], radius: f32) -> Option<SharedShape> {
+    let steps = [-1.0f32, 0.0, 1.0];
+    #[cfg(feature = "dim2")]
+    let dirs: Vec<Vector> = steps
+        .iter()
+        .flat_map(|x| steps.iter().map(move |y| Vector::new(*x, *y)))
+        .filter(|d| *d != Vector::ZERO)
+        .map(|d| d.normalize())
+        .collect();
+    #[cfg(feature = "dim3")]
+    let dirs: Vec<Vector> = steps
+        .iter()
+        .flat_map(|x| {
+            steps
+                .iter()
+                .flat_map(move |y| steps.iter().map(move |z| Vector::new(*x, *y, *z)))
+        })
+        .filter(|d| *d != Vector::ZERO)
+        .map(|d| d.normalize())
+        .collect();
+
+    let dilated: Vec<Vector> = points
+        .iter()
+        .flat_map(|pt| dirs.iter().map(move |dir| *pt + *dir * radius))
+        .collect();
+    SharedShape::convex_hull(&dilated)
+}
+
 /// Convert polygon vertices to a `Vec<u32>` key for exact matching in batching.
 #[cfg(feature = "dim2")]
 fn polygon_vertex_key(points: &[Vec2]) -> Vec<u32> {
@@ -346,6 +448,11 @@ impl RenderContext {
         #[cfg(feature = "dim3")]
         let scene = scene_3d;
 
+        if let Some(proxy) = render_proxy_shape(shape) {
+            self.insert_shape(scene, env, handle, &proxy, local_pose, color);
+            return;
+        }
+
         // Variety shading only applies to default (opaque) colors, so count
         // entries in the opaque node for this shape type.
         let color_id = self
@@ -636,6 +743,21 @@ impl RenderContext {
                 };
                 self.push_entry(instance_id, env, handle, color, local_pose, [1.0; DIM]);
             }
+            ShapeType::Compound => {
+                // One entry per part, composed with the part's pose, mirroring the
+                // per-part GPU colliders.
+                let compound = shape.as_compound().unwrap();
+                for (part_pose, part) in compound.shapes() {
+                    self.insert_shape(
+                        scene,
+                        env,
+                        handle,
+                        part,
+                        local_pose * *part_pose,
+                        Some(color),
+                    );
+                }
+            }
             _ => todo!("unsupported render shape: {:?}", shape.shape_type()),
         }
     }